@@ -10,14 +10,16 @@
 extern crate tracing;
 
 mod cli;
+mod qr;
 mod subcommands;
 
 use crate::{
     cli::Opt,
     subcommands::{
-        files::files_cmds,
+        doctor::doctor,
+        files::{files_cmds, files_cmds_without_client, FilesCmds},
         gossipsub::gossipsub_cmds,
-        register::register_cmds,
+        register::{register_cmds, register_cmds_without_client, RegisterCmds},
         wallet::{wallet_cmds, wallet_cmds_without_client, WalletCmds},
         SubCmd,
     },
@@ -25,10 +27,13 @@ use crate::{
 use bls::SecretKey;
 use clap::Parser;
 use color_eyre::Result;
-use sn_client::Client;
+use libp2p::Multiaddr;
+use sn_client::{Client, ClientBuilder};
 #[cfg(feature = "metrics")]
 use sn_logging::{metrics::init_metrics, LogBuilder, LogFormat};
-use sn_peers_acquisition::get_peers_from_args;
+#[cfg(feature = "network-contacts")]
+use sn_networking::DohResolver;
+use sn_peers_acquisition::{get_peers_with_sources, PeerCache, PeerSource};
 use sn_transfers::bls_secret_from_hex;
 use std::{io, path::PathBuf};
 use tracing::Level;
@@ -67,66 +72,167 @@ async fn main() -> Result<()> {
     info!("\"{}\"", std::env::args().collect::<Vec<_>>().join(" "));
 
     debug!("Built with git version: {}", sn_build_info::git_info());
+    let client_data_dir_path = get_client_data_dir_path()?;
+    // The doctor prints its own report, which may have to be parsed as JSON
+    if let SubCmd::Doctor(args) = &opt.cmd {
+        doctor(
+            args,
+            opt.peers,
+            &client_data_dir_path,
+            opt.connection_timeout,
+        )
+        .await?;
+        return Ok(());
+    }
+
     println!("Built with git version: {}", sn_build_info::git_info());
 
-    let client_data_dir_path = get_client_data_dir_path()?;
     // Perform actions that do not require us connecting to the network and return early
     if let SubCmd::Wallet(cmds) = &opt.cmd {
-        if let WalletCmds::Address
+        if let WalletCmds::Address { .. }
+        | WalletCmds::Addresses { .. }
+        | WalletCmds::RotateKey
         | WalletCmds::Balance { .. }
         | WalletCmds::Deposit { .. }
-        | WalletCmds::Create { .. } = cmds
+        | WalletCmds::Create { .. }
+        | WalletCmds::Config { .. }
+        | WalletCmds::History { .. }
+        | WalletCmds::Send { dry_run: true, .. } = cmds
         {
             wallet_cmds_without_client(cmds, &client_data_dir_path).await?;
             return Ok(());
         }
     }
+    if let SubCmd::Files(cmds @ (FilesCmds::VerifyLocal { .. } | FilesCmds::History { .. })) =
+        &opt.cmd
+    {
+        files_cmds_without_client(cmds, &client_data_dir_path)?;
+        return Ok(());
+    }
+    if let SubCmd::Register(cmds @ RegisterCmds::Address { .. }) = &opt.cmd {
+        let secret_key = get_client_secret_key(&client_data_dir_path)?;
+        register_cmds_without_client(cmds, secret_key.public_key())?;
+        return Ok(());
+    }
 
     println!("Instantiating a SAFE client...");
     let secret_key = get_client_secret_key(&client_data_dir_path)?;
 
-    let bootstrap_peers = get_peers_from_args(opt.peers).await?;
+    let peer_cache = opt.peers.peer_cache();
+    // the doctor is run early on, without a client, but is dispatched below all the same
+    let peers_args = opt.peers.clone();
+    #[cfg(feature = "network-contacts")]
+    let doh_server = opt.peers.doh_server.clone();
+    let bootstrap_peers = get_peers_with_sources(opt.peers).await?;
 
     println!(
         "Connecting to the network with {} peers",
         bootstrap_peers.len(),
     );
 
-    let bootstrap_peers = if bootstrap_peers.is_empty() {
-        // empty vec is returned if `local-discovery` flag is provided
-        None
-    } else {
-        Some(bootstrap_peers)
-    };
+    // empty vec is returned if `local-discovery` flag is provided
+    let mut contacted_peers: Vec<_> = bootstrap_peers
+        .iter()
+        .map(|(peer, _source)| peer.clone())
+        .collect();
+    let from_peer_cache = bootstrap_peers
+        .iter()
+        .any(|(_peer, source)| *source == PeerSource::PeerCache);
 
     // use gossipsub only for the wallet cmd that requires it.
     let joins_gossipsub = matches!(opt.cmd, SubCmd::Wallet(WalletCmds::ReceiveOnline { .. }));
 
-    let client = Client::new(
-        secret_key,
-        bootstrap_peers,
-        joins_gossipsub,
-        opt.connection_timeout,
-    )
-    .await?;
+    let mut client_builder = ClientBuilder::default()
+        .signer(secret_key)
+        .peers_with_sources(bootstrap_peers)
+        .enable_gossip(joins_gossipsub);
+    if let Some(connection_timeout) = opt.connection_timeout {
+        client_builder = client_builder.connection_timeout(connection_timeout);
+    }
+    #[cfg(feature = "network-contacts")]
+    if let Some(server) = doh_server {
+        client_builder = client_builder.doh_resolver(DohResolver::new(server)?);
+    }
+
+    let client = match client_builder.clone().build().await {
+        Ok(client) => client,
+        Err(err) => {
+            // the cached peers may be gone, don't use them again
+            if let Some(peer_cache) = &peer_cache {
+                if let Err(err) = peer_cache.clear() {
+                    warn!("Failed to clear the peer cache: {err}");
+                }
+            }
+            if !from_peer_cache {
+                return Err(err.into());
+            }
+
+            // rather than giving up, fetch the network contacts right away and try again
+            warn!("Failed to connect to the cached peers, fetching the network contacts: {err}");
+            println!("Could not connect to the cached peers, fetching the network contacts...");
+            let bootstrap_peers =
+                get_peers_with_sources(peers_args.clone().ignoring_peer_cache()).await?;
+            contacted_peers = bootstrap_peers
+                .iter()
+                .map(|(peer, _source)| peer.clone())
+                .collect();
+            client_builder
+                .peers_with_sources(bootstrap_peers)
+                .build()
+                .await?
+        }
+    };
+
+    if let Some(peer_cache) = peer_cache {
+        cache_reachable_peers(&client, &peer_cache, &contacted_peers).await;
+    }
 
     // default to verifying storage
     let should_verify_store = !opt.no_verify;
 
-    match opt.cmd {
+    let result = match opt.cmd {
         SubCmd::Wallet(cmds) => {
-            wallet_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await?
+            wallet_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
         }
         SubCmd::Files(cmds) => {
-            files_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await?
+            files_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
         }
         SubCmd::Register(cmds) => {
-            register_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await?
+            register_cmds(cmds, &client, &client_data_dir_path, should_verify_store).await
+        }
+        SubCmd::Gossipsub(cmds) => gossipsub_cmds(cmds, &client).await,
+        SubCmd::Doctor(args) => {
+            doctor(
+                &args,
+                peers_args,
+                &client_data_dir_path,
+                opt.connection_timeout,
+            )
+            .await
         }
-        SubCmd::Gossipsub(cmds) => gossipsub_cmds(cmds, &client).await?,
     };
 
-    Ok(())
+    // shut the client down before exiting, whatever the outcome, for its metrics to be flushed
+    if let Err(err) = client.shutdown().await {
+        warn!("Failed to shut the client down: {err}");
+    }
+
+    result
+}
+
+/// Save the bootstrap peers we could reach to the peer cache, so the next run can skip
+/// fetching the network contacts. Failing to do so is not fatal.
+async fn cache_reachable_peers(client: &Client, peer_cache: &PeerCache, peers: &[Multiaddr]) {
+    let reachable_peers = match client.reachable_peers(peers).await {
+        Ok(reachable_peers) => reachable_peers,
+        Err(err) => {
+            warn!("Failed to get the reachable bootstrap peers: {err}");
+            return;
+        }
+    };
+    if let Err(err) = peer_cache.save(&reachable_peers) {
+        warn!("Failed to save the peer cache: {err}");
+    }
 }
 
 fn get_client_secret_key(root_dir: &PathBuf) -> Result<SecretKey> {
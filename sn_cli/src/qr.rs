@@ -0,0 +1,113 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use clap::Args;
+use color_eyre::Result;
+use image::Luma;
+use qrcode::{render::unicode, QrCode};
+use std::path::{Path, PathBuf};
+
+/// Options to share an address as a QR code.
+#[derive(Args, Debug, Clone, Default)]
+pub(crate) struct QrArgs {
+    /// Render the address as a QR code in the terminal.
+    #[clap(long)]
+    pub qr: bool,
+    /// Write the address as a QR code to the given PNG file.
+    #[clap(long, value_name = "FILE")]
+    pub qr_out: Option<PathBuf>,
+}
+
+impl QrArgs {
+    /// Render and/or write the QR code of the given address, as requested.
+    pub(crate) fn output(&self, address: &str) -> Result<()> {
+        self.output_to(address, self.qr_out.as_deref())
+    }
+
+    /// Same as `output`, but writing the PNG to `qr_out` suffixed with the given index.
+    /// Used when a single command outputs several addresses.
+    pub(crate) fn output_nth(&self, address: &str, index: usize) -> Result<()> {
+        let qr_out = self.qr_out.as_ref().map(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let extension = path.extension().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!("{stem}-{index}.{extension}"))
+        });
+        self.output_to(address, qr_out.as_deref())
+    }
+
+    fn output_to(&self, address: &str, qr_out: Option<&Path>) -> Result<()> {
+        if self.qr {
+            println!("{}", render_qr(address)?);
+        }
+        if let Some(path) = qr_out {
+            write_qr_png(address, path)?;
+            println!("QR code of {address} written to {path:?}");
+        }
+        Ok(())
+    }
+}
+
+/// Render the payload as a QR code made of unicode blocks, to be printed to a terminal.
+pub(crate) fn render_qr(payload: &str) -> Result<String> {
+    let code = QrCode::new(payload.as_bytes())?;
+    Ok(code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Write the payload as a QR code to a PNG file.
+pub(crate) fn write_qr_png(payload: &str, path: &Path) -> Result<()> {
+    let code = QrCode::new(payload.as_bytes())?;
+    let image = code.render::<Luma<u8>>().min_dimensions(200, 200).build();
+    image.save(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_protocol::storage::ChunkAddress;
+    use sn_transfers::MainSecretKey;
+    use xor_name::XorName;
+
+    fn decode_png(path: &Path) -> eyre::Result<String> {
+        let image = image::open(path)?.to_luma8();
+        let mut prepared = rqrr::PreparedImage::prepare(image);
+        let grids = prepared.detect_grids();
+        let grid = grids
+            .first()
+            .ok_or_else(|| eyre::eyre!("no QR code found in {path:?}"))?;
+        let (_meta, content) = grid.decode()?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_chunk_address_qr_round_trips() -> eyre::Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join("head.png");
+        let address = ChunkAddress::new(XorName::random(&mut rand::thread_rng())).to_hex();
+
+        write_qr_png(&address, &path)?;
+        assert_eq!(decode_png(&path)?, address);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wallet_address_qr_round_trips() -> eyre::Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join("wallet.png");
+        let address = MainSecretKey::random().main_pubkey().to_hex();
+
+        write_qr_png(&address, &path)?;
+        assert_eq!(decode_png(&path)?, address);
+        Ok(())
+    }
+}
@@ -0,0 +1,679 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use bls::SecretKey;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use color_eyre::{eyre::eyre, Result};
+use libp2p::Multiaddr;
+use serde::Serialize;
+use sn_client::{ClientBuilder, Error as ClientError, FsSpaceProbe, ReadCfg, SpaceProbe};
+use sn_networking::Error as NetworkError;
+use sn_peers_acquisition::{
+    error::Error as PeersError, get_peers_with_sources, is_connection_checked, responsive_peers,
+    PeerSource, PeersArgs, PEER_VERIFICATION_TIMEOUT,
+};
+use sn_protocol::storage::ChunkAddress;
+use sn_transfers::{LocalWallet, WalletDirCheck, WalletLockState};
+use std::{
+    fmt, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+use xor_name::XorName;
+
+/// Up to this much clock skew with the contacts server is fine.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+/// Beyond this much clock skew, payment quotes are likely to be deemed expired or from the future.
+const MAX_CLOCK_SKEW_WARN: Duration = Duration::from_secs(5 * 60);
+/// How long the contacts server is given to answer the clock skew check.
+#[cfg(feature = "network-contacts")]
+const CLOCK_SKEW_TIMEOUT: Duration = Duration::from_secs(10);
+/// A wallet lock held longer than this is likely held by a stuck process.
+const STALE_WALLET_LOCK: Duration = Duration::from_secs(10 * 60);
+/// Below this much free disk space, downloads and uploads are likely to fail.
+const MIN_DISK_SPACE: u64 = 100 * 1024 * 1024;
+/// Below this much free disk space, large files can't be uploaded or downloaded anymore.
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+/// How long the self-test is given to connect to the network, unless set with `--timeout`.
+const SELF_TEST_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long the self-test is given to query a chunk.
+const SELF_TEST_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options of the `doctor` command.
+#[derive(Args, Debug)]
+pub struct DoctorArgs {
+    /// Print the report as JSON.
+    #[clap(long)]
+    json: bool,
+    /// Also connect a throwaway client to the network and query a random chunk through it.
+    #[clap(long)]
+    self_test: bool,
+}
+
+/// The outcome of a check.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pass => write!(f, "PASS"),
+            Self::Warn => write!(f, "WARN"),
+            Self::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// The outcome of a check, with what was found.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CheckResult {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// All the checks that were run, the worst of their outcomes being the overall one.
+#[derive(Serialize, Debug)]
+pub(crate) struct DoctorReport {
+    version: &'static str,
+    status: CheckStatus,
+    checks: Vec<CheckResult>,
+}
+
+impl DoctorReport {
+    fn new(version: &'static str, checks: Vec<CheckResult>) -> Self {
+        let status = checks
+            .iter()
+            .map(|check| check.status)
+            .max()
+            .unwrap_or(CheckStatus::Pass);
+        Self {
+            version,
+            status,
+            checks,
+        }
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Built with git version: {}", self.version)?;
+        for check in &self.checks {
+            writeln!(f, "[{}] {}: {}", check.status, check.name, check.detail)?;
+        }
+        writeln!(f, "Overall: {}", self.status)
+    }
+}
+
+/// The outcome of the self-test, see `self_test`.
+#[derive(Debug)]
+enum SelfTest {
+    ConnectionFailed(String),
+    QueryFailed {
+        connected_in: Duration,
+        error: String,
+    },
+    Passed {
+        connected_in: Duration,
+        answered_in: Duration,
+    },
+}
+
+/// Run all the checks and print their report, failing if any of them failed.
+///
+/// Nothing is written to the wallet: it is only read, and its lock only probed.
+pub(crate) async fn doctor(
+    args: &DoctorArgs,
+    peers_args: PeersArgs,
+    root_dir: &Path,
+    connection_timeout: Option<Duration>,
+) -> Result<()> {
+    let mut checks = vec![];
+
+    let acquired = get_peers_with_sources(peers_args.clone()).await;
+    checks.push(check_peer_acquisition(&acquired));
+    let peers: Vec<Multiaddr> = acquired
+        .map(|peers| peers.into_iter().map(|(peer, _source)| peer).collect())
+        .unwrap_or_default();
+
+    let (checked, unchecked): (Vec<_>, Vec<_>) =
+        peers.iter().cloned().partition(is_connection_checked);
+    let responsive = responsive_peers(&checked, PEER_VERIFICATION_TIMEOUT).await;
+    checks.push(check_reachability(
+        checked.len(),
+        responsive.len(),
+        unchecked.len(),
+    ));
+
+    let server_date = contacts_server_date(&peers_args).await;
+    checks.push(check_clock_skew(server_date, Utc::now()));
+
+    checks.push(check_wallet(&LocalWallet::check_dir(root_dir)));
+
+    checks.push(check_disk_space(
+        root_dir,
+        FsSpaceProbe.available_space(root_dir),
+    ));
+
+    if args.self_test {
+        let timeout = connection_timeout.unwrap_or(SELF_TEST_CONNECTION_TIMEOUT);
+        checks.push(check_self_test(&self_test(peers, timeout).await));
+    }
+
+    let report = DoctorReport::new(sn_build_info::git_info(), checks);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{report}");
+    }
+
+    if report.status == CheckStatus::Fail {
+        return Err(eyre!("Some of the checks failed"));
+    }
+    Ok(())
+}
+
+/// Whether peers could be obtained, and where from.
+fn check_peer_acquisition(
+    acquired: &Result<Vec<(Multiaddr, PeerSource)>, PeersError>,
+) -> CheckResult {
+    const NAME: &str = "peer_acquisition";
+    let peers = match acquired {
+        Ok(peers) => peers,
+        Err(err) => return CheckResult::new(NAME, CheckStatus::Fail, err.to_string()),
+    };
+    if peers.is_empty() {
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Warn,
+            "No bootstrap peers, they have to be discovered through mDNS",
+        );
+    }
+
+    let mut sources: Vec<(PeerSource, usize)> = vec![];
+    for (_peer, source) in peers {
+        match sources.iter_mut().find(|(seen, _)| seen == source) {
+            Some((_, count)) => *count += 1,
+            None => sources.push((*source, 1)),
+        }
+    }
+    let sources: Vec<_> = sources
+        .iter()
+        .map(|(source, count)| format!("{count} from {source}"))
+        .collect();
+    CheckResult::new(
+        NAME,
+        CheckStatus::Pass,
+        format!("{} peers: {}", peers.len(), sources.join(", ")),
+    )
+}
+
+/// Whether enough of the peers whose connection can be checked accept one. The `unchecked` peers,
+/// over QUIC or at `/dnsaddr` addresses, are only reported.
+fn check_reachability(peers: usize, responsive: usize, unchecked: usize) -> CheckResult {
+    const NAME: &str = "peer_reachability";
+    let mut detail = format!(
+        "{responsive} of the {peers} peers accepted a connection within {PEER_VERIFICATION_TIMEOUT:?}"
+    );
+    if unchecked > 0 {
+        detail.push_str(&format!(", {unchecked} more peers left unchecked"));
+    }
+    if peers == 0 && unchecked == 0 {
+        CheckResult::new(NAME, CheckStatus::Warn, "No peers to probe")
+    } else if peers == 0 {
+        CheckResult::new(
+            NAME,
+            CheckStatus::Warn,
+            format!(
+                "None of the {unchecked} peers can be checked, over QUIC or at /dnsaddr addresses"
+            ),
+        )
+    } else if responsive == 0 {
+        CheckResult::new(NAME, CheckStatus::Fail, detail)
+    } else if responsive * 2 < peers {
+        CheckResult::new(NAME, CheckStatus::Warn, detail)
+    } else {
+        CheckResult::new(NAME, CheckStatus::Pass, detail)
+    }
+}
+
+/// Whether the local clock agrees with the `Date` header returned by the contacts server.
+fn check_clock_skew(server_date: Result<String>, now: DateTime<Utc>) -> CheckResult {
+    const NAME: &str = "clock_skew";
+    let server_date = match server_date.and_then(|date| {
+        DateTime::parse_from_rfc2822(&date)
+            .map_err(|err| eyre!("Invalid date {date:?} from the contacts server: {err}"))
+    }) {
+        Ok(server_date) => server_date.with_timezone(&Utc),
+        Err(err) => {
+            return CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!("Could not tell the clock skew: {err}"),
+            )
+        }
+    };
+
+    let skew = now.signed_duration_since(server_date);
+    let abs_skew = skew.abs().to_std().unwrap_or(Duration::MAX);
+    let detail = if skew < chrono::Duration::zero() {
+        format!("Local clock is {abs_skew:?} behind the contacts server")
+    } else {
+        format!("Local clock is {abs_skew:?} ahead of the contacts server")
+    };
+    let status = if abs_skew <= MAX_CLOCK_SKEW {
+        CheckStatus::Pass
+    } else if abs_skew <= MAX_CLOCK_SKEW_WARN {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Fail
+    };
+    CheckResult::new(NAME, status, detail)
+}
+
+/// Whether all the files of the wallet can be read, and its lock isn't held by a stuck process.
+fn check_wallet(check: &WalletDirCheck) -> CheckResult {
+    const NAME: &str = "wallet";
+    let dir = check.wallet_dir.display();
+    if !check.exists {
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Pass,
+            format!("No wallet at {dir} yet, one is created on first use"),
+        );
+    }
+    if !check.unreadable_files.is_empty() {
+        let files: Vec<_> = check
+            .unreadable_files
+            .iter()
+            .map(|(path, reason)| format!("{} ({reason})", path.display()))
+            .collect();
+        return CheckResult::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("Unreadable files in {dir}: {}", files.join(", ")),
+        );
+    }
+
+    let summary = format!("{} cash notes in {dir}", check.cash_notes);
+    match check.lock {
+        WalletLockState::Held { since: Some(since) } if since > STALE_WALLET_LOCK => {
+            CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!(
+                    "{summary}, locked for {}s by another process, which may be stuck",
+                    since.as_secs()
+                ),
+            )
+        }
+        WalletLockState::Held { .. } => CheckResult::new(
+            NAME,
+            CheckStatus::Pass,
+            format!("{summary}, in use by another process"),
+        ),
+        WalletLockState::NoLockFile | WalletLockState::Free => {
+            CheckResult::new(NAME, CheckStatus::Pass, summary)
+        }
+    }
+}
+
+/// Whether there is enough disk space left where the client stores its data.
+fn check_disk_space(dir: &Path, available: io::Result<u64>) -> CheckResult {
+    const NAME: &str = "disk_space";
+    let available = match available {
+        Ok(available) => available,
+        Err(err) => {
+            return CheckResult::new(
+                NAME,
+                CheckStatus::Warn,
+                format!(
+                    "Could not tell the disk space available at {}: {err}",
+                    dir.display()
+                ),
+            )
+        }
+    };
+    let detail = format!(
+        "{} MiB available at {}",
+        available / (1024 * 1024),
+        dir.display()
+    );
+    let status = if available < MIN_DISK_SPACE {
+        CheckStatus::Fail
+    } else if available < LOW_DISK_SPACE {
+        CheckStatus::Warn
+    } else {
+        CheckStatus::Pass
+    };
+    CheckResult::new(NAME, status, detail)
+}
+
+/// Whether a throwaway client could connect and get an answer for a chunk.
+fn check_self_test(self_test: &SelfTest) -> CheckResult {
+    const NAME: &str = "self_test";
+    match self_test {
+        SelfTest::ConnectionFailed(error) => CheckResult::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("Could not connect to the network: {error}"),
+        ),
+        SelfTest::QueryFailed {
+            connected_in,
+            error,
+        } => CheckResult::new(
+            NAME,
+            CheckStatus::Fail,
+            format!("Connected in {connected_in:?}, but querying a chunk failed: {error}"),
+        ),
+        SelfTest::Passed {
+            connected_in,
+            answered_in,
+        } => CheckResult::new(
+            NAME,
+            CheckStatus::Pass,
+            format!("Connected in {connected_in:?}, a chunk query was answered in {answered_in:?}"),
+        ),
+    }
+}
+
+/// The `Date` header of the first network contacts URL.
+#[cfg(feature = "network-contacts")]
+async fn contacts_server_date(peers_args: &PeersArgs) -> Result<String> {
+    let url = peers_args
+        .network_contacts_urls()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("No network contacts URL"))?;
+    let response = reqwest::Client::builder()
+        .timeout(CLOCK_SKEW_TIMEOUT)
+        .build()?
+        .head(url.clone())
+        .send()
+        .await?;
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .ok_or_else(|| eyre!("No Date header in the response of {url}"))?;
+    Ok(date.to_str()?.to_string())
+}
+
+#[cfg(not(feature = "network-contacts"))]
+#[allow(clippy::unused_async)]
+async fn contacts_server_date(_peers_args: &PeersArgs) -> Result<String> {
+    Err(eyre!(
+        "Built without the network-contacts feature, there is no contacts server to compare with"
+    ))
+}
+
+/// Connect a client with a random key, and query a random chunk through it, which the network
+/// is expected to answer it doesn't hold.
+async fn self_test(peers: Vec<Multiaddr>, connection_timeout: Duration) -> SelfTest {
+    let start = Instant::now();
+    let client = match ClientBuilder::default()
+        .signer(SecretKey::random())
+        .peers(peers)
+        .connection_timeout(connection_timeout)
+        .headless(true)
+        .build()
+        .await
+    {
+        Ok(client) => client,
+        Err(err) => return SelfTest::ConnectionFailed(err.to_string()),
+    };
+    let connected_in = start.elapsed();
+
+    let start = Instant::now();
+    let address = ChunkAddress::new(XorName::random(&mut rand::thread_rng()));
+    let read_cfg = ReadCfg {
+        re_attempt: false,
+        ..ReadCfg::new(libp2p::kad::Quorum::One)
+    }
+    .with_timeout(SELF_TEST_QUERY_TIMEOUT);
+    let result = client.get_chunk_with_cfg(address, false, read_cfg).await;
+    let answered_in = start.elapsed();
+    if let Err(err) = client.shutdown().await {
+        warn!("Failed to shut the self-test client down: {err}");
+    }
+
+    match result {
+        Ok(_) | Err(ClientError::Network(NetworkError::RecordNotFound)) => SelfTest::Passed {
+            connected_in,
+            answered_in,
+        },
+        Err(err) => SelfTest::QueryFailed {
+            connected_in,
+            error: err.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn peer(port: u16) -> Multiaddr {
+        format!("/ip4/10.0.0.1/tcp/{port}")
+            .parse()
+            .expect("Valid multiaddr")
+    }
+
+    #[test]
+    fn peer_acquisition_reports_the_sources() {
+        let acquired = Ok(vec![
+            (peer(1), PeerSource::NetworkContacts),
+            (peer(2), PeerSource::Env),
+            (peer(3), PeerSource::NetworkContacts),
+        ]);
+        let check = check_peer_acquisition(&acquired);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(
+            check.detail,
+            "3 peers: 2 from network contacts, 1 from SAFE_PEERS"
+        );
+
+        assert_eq!(
+            check_peer_acquisition(&Ok(vec![])).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_peer_acquisition(&Err(PeersError::PeersNotObtained)).status,
+            CheckStatus::Fail
+        );
+    }
+
+    #[test]
+    fn reachability_depends_on_the_share_of_responsive_peers() {
+        assert_eq!(check_reachability(0, 0, 0).status, CheckStatus::Warn);
+        assert_eq!(check_reachability(10, 0, 0).status, CheckStatus::Fail);
+        assert_eq!(check_reachability(10, 4, 0).status, CheckStatus::Warn);
+        assert_eq!(check_reachability(10, 5, 0).status, CheckStatus::Pass);
+        assert_eq!(check_reachability(10, 10, 0).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn unchecked_peers_are_not_counted_as_reachable() {
+        let check = check_reachability(0, 0, 10);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.starts_with("None of the 10 peers"));
+
+        let check = check_reachability(10, 4, 10);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("4 of the 10 peers"));
+        assert!(check.detail.contains("10 more peers left unchecked"));
+    }
+
+    #[test]
+    fn clock_skew_is_measured_against_the_server_date() {
+        let now = Utc
+            .with_ymd_and_hms(2023, 11, 6, 12, 0, 0)
+            .single()
+            .expect("Valid date");
+        let date = |date: &str| Ok(date.to_string());
+
+        let check = check_clock_skew(date("Mon, 06 Nov 2023 12:00:10 GMT"), now);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert_eq!(
+            check.detail,
+            "Local clock is 10s behind the contacts server"
+        );
+        let check = check_clock_skew(date("Mon, 06 Nov 2023 11:58:00 GMT"), now);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert_eq!(
+            check.detail,
+            "Local clock is 120s ahead of the contacts server"
+        );
+        assert_eq!(
+            check_clock_skew(date("Mon, 06 Nov 2023 13:00:00 GMT"), now).status,
+            CheckStatus::Fail
+        );
+
+        assert_eq!(
+            check_clock_skew(date("yesterday"), now).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_clock_skew(Err(eyre!("unreachable")), now).status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn wallet_check_reports_unreadable_files_and_stale_locks() {
+        let sound = WalletDirCheck {
+            wallet_dir: PathBuf::from("wallet"),
+            exists: true,
+            lock: WalletLockState::Free,
+            unreadable_files: vec![],
+            cash_notes: 3,
+        };
+        assert_eq!(check_wallet(&sound).status, CheckStatus::Pass);
+
+        let missing = WalletDirCheck {
+            exists: false,
+            ..sound.clone()
+        };
+        assert_eq!(check_wallet(&missing).status, CheckStatus::Pass);
+
+        let in_use = WalletDirCheck {
+            lock: WalletLockState::Held {
+                since: Some(Duration::from_secs(1)),
+            },
+            ..sound.clone()
+        };
+        assert_eq!(check_wallet(&in_use).status, CheckStatus::Pass);
+
+        let stale = WalletDirCheck {
+            lock: WalletLockState::Held {
+                since: Some(STALE_WALLET_LOCK * 2),
+            },
+            ..sound.clone()
+        };
+        assert_eq!(check_wallet(&stale).status, CheckStatus::Warn);
+
+        let corrupt = WalletDirCheck {
+            unreadable_files: vec![(PathBuf::from("wallet/main_pubkey"), "not hex".to_string())],
+            ..sound
+        };
+        let check = check_wallet(&corrupt);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.detail.contains("main_pubkey"));
+    }
+
+    #[test]
+    fn disk_space_thresholds() {
+        let dir = Path::new("data");
+        assert_eq!(
+            check_disk_space(dir, Ok(MIN_DISK_SPACE - 1)).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            check_disk_space(dir, Ok(LOW_DISK_SPACE - 1)).status,
+            CheckStatus::Warn
+        );
+        assert_eq!(
+            check_disk_space(dir, Ok(LOW_DISK_SPACE)).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            check_disk_space(
+                dir,
+                Err(io::Error::new(io::ErrorKind::Other, "unsupported"))
+            )
+            .status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn self_test_fails_unless_the_query_is_answered() {
+        let connected_in = Duration::from_secs(3);
+        assert_eq!(
+            check_self_test(&SelfTest::ConnectionFailed("timed out".to_string())).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            check_self_test(&SelfTest::QueryFailed {
+                connected_in,
+                error: "timed out".to_string(),
+            })
+            .status,
+            CheckStatus::Fail
+        );
+        assert_eq!(
+            check_self_test(&SelfTest::Passed {
+                connected_in,
+                answered_in: Duration::from_secs(1),
+            })
+            .status,
+            CheckStatus::Pass
+        );
+    }
+
+    #[test]
+    fn report_has_the_worst_status_of_its_checks() -> Result<()> {
+        let report = DoctorReport::new(
+            "version",
+            vec![
+                CheckResult::new("a", CheckStatus::Pass, "fine"),
+                CheckResult::new("b", CheckStatus::Warn, "meh"),
+            ],
+        );
+        assert_eq!(report.status, CheckStatus::Warn);
+        assert!(report.to_string().contains("[WARN] b: meh"));
+
+        let json: serde_json::Value = serde_json::to_value(&report)?;
+        assert_eq!(json["status"], "WARN");
+        assert_eq!(json["checks"][0]["status"], "PASS");
+        assert_eq!(json["checks"][1]["detail"], "meh");
+
+        assert_eq!(
+            DoctorReport::new("version", vec![]).status,
+            CheckStatus::Pass
+        );
+        Ok(())
+    }
+}
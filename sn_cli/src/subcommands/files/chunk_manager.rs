@@ -10,7 +10,7 @@ use crate::subcommands::files::{get_progress_bar, UploadedFile};
 use bytes::Bytes;
 use color_eyre::{eyre::bail, Result};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use sn_client::FilesApi;
+use sn_client::{DiskSpaceCheck, FilesApi};
 use sn_protocol::storage::ChunkAddress;
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -67,6 +67,8 @@ pub(crate) struct ChunkManager {
     verified_files: Vec<(OsString, ChunkAddress)>,
     resumed_chunk_count: usize,
     resumed_files_count: usize,
+    skip_disk_space_check: bool,
+    target_chunk_size: Option<usize>,
 }
 
 impl ChunkManager {
@@ -81,9 +83,35 @@ impl ChunkManager {
             verified_files: Default::default(),
             resumed_files_count: 0,
             resumed_chunk_count: 0,
+            skip_disk_space_check: false,
+            target_chunk_size: None,
         }
     }
 
+    /// Chunk the files whatever the disk space left, see `DiskSpaceCheck`.
+    pub(crate) fn set_skip_disk_space_check(&mut self, skip: bool) {
+        self.skip_disk_space_check = skip;
+    }
+
+    /// Chunk the files into chunks of the given size, as validated by
+    /// `FilesApi::set_target_chunk_size`, rather than into chunks of up to the maximum size.
+    pub(crate) fn set_target_chunk_size(&mut self, target_chunk_size: Option<usize>) {
+        self.target_chunk_size = target_chunk_size;
+    }
+
+    /// All the chunks left in the artifacts dir, e.g. by interrupted uploads.
+    pub(crate) fn artifacts_chunks(&self) -> Vec<(XorName, PathBuf)> {
+        WalkDir::new(&self.artifacts_dir)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file() && entry.file_name() != METADATA_FILE)
+            .filter_map(|entry| {
+                let chunk_xorname = Self::hex_decode_xorname(entry.file_name().to_str()?)?;
+                Some((chunk_xorname, entry.into_path()))
+            })
+            .collect()
+    }
+
     /// Chunk all the files in the provided `files_path`
     /// These are stored to the CHUNK_ARTIFACTS_DIR
     /// if read_cache is true, will take cache from previous runs into account
@@ -181,6 +209,17 @@ impl ChunkManager {
         progress_bar.println(format!("Chunking {total_files} files..."));
 
         let artifacts_dir = &self.artifacts_dir.clone();
+        let target_chunk_size = self.target_chunk_size;
+        // the files are chunked in parallel, so their space is checked at once rather than each
+        // file fitting on its own while they don't all
+        if !self.skip_disk_space_check {
+            let file_sizes = self
+                .files_to_chunk
+                .iter()
+                .filter_map(|(_, _, path)| fs::metadata(path).ok())
+                .map(|metadata| metadata.len());
+            DiskSpaceCheck::default().ensure_space_for_files(artifacts_dir, file_sizes)?;
+        }
         let chunked_files = self.files_to_chunk
             .par_iter()
             .filter_map(|(original_file_name, path_xor, path)| {
@@ -200,7 +239,7 @@ impl ChunkManager {
                     }
                 };
 
-                match FilesApi::chunk_file(path, &file_chunks_dir, include_data_maps) {
+                match FilesApi::chunk_file_with_target_size_and_disk_space_check(path, &file_chunks_dir, include_data_maps, target_chunk_size, &DiskSpaceCheck::skipped()) {
                     Ok((head_chunk_address, data_map, size, chunks)) => {
                         progress_bar.clone().inc(1);
                         debug!("Chunked {original_file_name:?} with {path_xor:?} into file's XorName: {head_chunk_address:?} of size {size}, and chunks len: {}", chunks.len());
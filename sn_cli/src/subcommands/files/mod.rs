@@ -7,21 +7,27 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 mod chunk_manager;
+mod uploads_ledger;
 
 pub(crate) use chunk_manager::{ChunkManager, UPLOADED_FILES};
 
+use crate::{qr::QrArgs, subcommands::wallet::voucher_wallet_dir};
+
 use bytes::Bytes;
 use clap::Parser;
 use color_eyre::{
     eyre::{bail, eyre},
     Help, Result,
 };
+use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use rand::{seq::SliceRandom, thread_rng};
 use serde::Deserialize;
 use sn_client::{
-    Client, Error as ClientError, FileUploadEvent, FilesApi, FilesDownload, FilesDownloadEvent,
-    FilesUpload, BATCH_SIZE, MAX_UPLOAD_RETRIES,
+    verify_local_file, Client, Deadline, DiskSpaceCheck, DownloadManifest, Error as ClientError,
+    FileHealth, FileUploadEvent, FilesApi, FilesDownload, FilesDownloadEvent, FilesUpload,
+    QuotePolicy, ReconcilePlan, UploadManifest, VerificationStatus, BATCH_SIZE,
+    DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE, MAX_UPLOAD_RETRIES,
 };
 use sn_protocol::storage::{Chunk, ChunkAddress};
 use sn_transfers::{Error as TransfersError, WalletError};
@@ -38,6 +44,12 @@ use std::{
 use walkdir::WalkDir;
 use xor_name::XorName;
 
+/// The folder of the client dir the manifests of the uploads are written to.
+const UPLOAD_MANIFESTS_DIR: &str = "upload_manifests";
+
+/// The most manifests kept in the `UPLOAD_MANIFESTS_DIR`, the oldest being removed past it.
+const MAX_UPLOAD_MANIFESTS: usize = 100;
+
 /// The default folder to download files to.
 const DOWNLOAD_FOLDER: &str = "safe_files";
 
@@ -60,6 +72,33 @@ pub enum FilesCmds {
         /// during payment and upload processing.
         #[clap(long, default_value_t = MAX_UPLOAD_RETRIES, short = 'r')]
         max_retries: usize,
+        /// Refuse to pay the nodes quoting over this many times the median quote of the close
+        /// group of a chunk, paying another node of the group instead.
+        #[clap(long, value_name = "MULTIPLE", default_value_t = DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE)]
+        max_quote_multiple: u64,
+        /// Pay the cheapest of the closest nodes to a chunk, whatever its quote.
+        #[clap(long, conflicts_with = "max_quote_multiple")]
+        accept_any_price: bool,
+        /// Chunk the file(s) without checking there is enough disk space for the chunks first.
+        ///
+        /// Use it on filesystems where the space required is overestimated, e.g. compressed ones.
+        #[clap(long)]
+        skip_disk_space_check: bool,
+        /// Split the file(s) into chunks of this many bytes, rather than into chunks as large as
+        /// the nodes accept.
+        #[clap(long, value_name = "BYTES")]
+        chunk_size: Option<usize>,
+        /// Show which of the close nodes hold each chunk, when verifying the chunks of a
+        /// previous upload attempt.
+        #[clap(long, name = "show_holders", default_value = "false")]
+        show_holders: bool,
+        /// Pay for the upload with a storage voucher redeemed with 'wallet redeem-voucher',
+        /// rather than with the local wallet.
+        #[clap(long, value_name = "ID")]
+        voucher: Option<String>,
+        /// Share the head address of the uploaded file(s) as QR code(s).
+        #[clap(flatten)]
+        qr: QrArgs,
     },
     Download {
         /// The name to apply to the downloaded file.
@@ -83,6 +122,48 @@ pub enum FilesCmds {
         /// The batch_size for parallel downloading
         #[clap(long, default_value_t = BATCH_SIZE , short='b')]
         batch_size: usize,
+        /// Write a manifest of each downloaded file next to it, as `<file>.safe-manifest.json`.
+        ///
+        /// It records the chunks of the file, the peers which served them and the file hash,
+        /// so the file can later be checked with `files verify-local`.
+        #[clap(long, name = "manifest", default_value = "false")]
+        manifest: bool,
+        /// Download the file(s) without checking there is enough disk space for them first.
+        ///
+        /// Use it on filesystems where the space required is overestimated, e.g. compressed ones.
+        #[clap(long)]
+        skip_disk_space_check: bool,
+    },
+    /// Check whether a file can be fully retrieved from the network, without downloading it.
+    Health {
+        /// The hex address of the file.
+        #[clap(name = "address")]
+        file_addr: String,
+        /// Stop checking the chunks after this many seconds, reporting those left unchecked.
+        #[clap(long, value_name = "SECS")]
+        deadline: Option<u64>,
+    },
+    /// Check a downloaded file against its manifest, without connecting to the network.
+    VerifyLocal {
+        /// The downloaded file. Its manifest is expected next to it.
+        #[clap(name = "path", value_name = "PATH")]
+        path: PathBuf,
+    },
+    /// Cross-check an interrupted upload against the wallet payments and the network, and plan
+    /// what is left to do for it to complete.
+    Reconcile {
+        /// The manifest of the upload, as written by `files upload`.
+        #[clap(name = "manifest", value_name = "MANIFEST")]
+        manifest: PathBuf,
+        /// Carry out the plan: upload the chunks already paid for, pay for and upload the others.
+        #[clap(long)]
+        apply: bool,
+    },
+    /// List the uploads made from this client, with what they cost.
+    History {
+        /// Also list what was paid to each node for every upload.
+        #[clap(long)]
+        breakdown: bool,
     },
 }
 
@@ -131,6 +212,22 @@ impl UploadedFile {
     }
 }
 
+pub(crate) fn files_cmds_without_client(cmds: &FilesCmds, root_dir: &Path) -> Result<()> {
+    match cmds {
+        FilesCmds::VerifyLocal { path } => verify_local(path),
+        FilesCmds::History { breakdown } => {
+            let uploads = uploads_ledger::read_uploads(root_dir)?;
+            if uploads.is_empty() {
+                println!("No uploads recorded yet.");
+            } else {
+                print!("{}", uploads_ledger::render_uploads(&uploads, *breakdown));
+            }
+            Ok(())
+        }
+        cmd => Err(eyre!("{cmd:?} requires us to be connected to the Network")),
+    }
+}
+
 pub(crate) async fn files_cmds(
     cmds: FilesCmds,
     client: &Client,
@@ -143,7 +240,18 @@ pub(crate) async fn files_cmds(
             batch_size,
             max_retries,
             make_public,
+            max_quote_multiple,
+            accept_any_price,
+            skip_disk_space_check,
+            chunk_size,
+            show_holders,
+            voucher,
+            qr,
         } => {
+            let quote_policy = QuotePolicy {
+                max_median_multiple: max_quote_multiple,
+                accept_any_price,
+            };
             upload_files(
                 path,
                 make_public,
@@ -152,6 +260,12 @@ pub(crate) async fn files_cmds(
                 verify_store,
                 batch_size,
                 max_retries,
+                quote_policy,
+                skip_disk_space_check,
+                chunk_size,
+                show_holders,
+                voucher.as_deref(),
+                &qr,
             )
             .await?
         }
@@ -160,6 +274,8 @@ pub(crate) async fn files_cmds(
             file_addr,
             show_holders,
             batch_size,
+            manifest,
+            skip_disk_space_check,
         } => {
             if (file_name.is_some() && file_addr.is_none())
                 || (file_addr.is_some() && file_name.is_none())
@@ -173,7 +289,10 @@ pub(crate) async fn files_cmds(
             }
 
             let download_dir = dirs_next::download_dir().unwrap_or(root_dir.to_path_buf());
-            let files_api: FilesApi = FilesApi::new(client.clone(), download_dir.clone());
+            let mut files_api: FilesApi = FilesApi::new(client.clone(), download_dir.clone());
+            if skip_disk_space_check {
+                files_api.set_disk_space_check(DiskSpaceCheck::skipped());
+            }
 
             match (file_name, file_addr) {
                 (Some(file_name), Some(address_provided)) => {
@@ -208,21 +327,33 @@ pub(crate) async fn files_cmds(
                         &download_dir,
                         show_holders,
                         batch_size,
+                        manifest,
                     )
                     .await
                 }
                 _ => {
                     println!("Attempting to download all files uploaded by the current user...");
-                    download_files(&files_api, root_dir, show_holders, batch_size).await?
+                    download_files(&files_api, root_dir, show_holders, batch_size, manifest).await?
                 }
             }
         }
+        FilesCmds::Health {
+            file_addr,
+            deadline,
+        } => check_file_health(client, &file_addr, deadline).await?,
+        FilesCmds::Reconcile { manifest, apply } => {
+            reconcile_upload(client, root_dir, &manifest, apply).await?
+        }
+        cmd @ (FilesCmds::VerifyLocal { .. } | FilesCmds::History { .. }) => {
+            files_cmds_without_client(&cmd, root_dir)?
+        }
     };
     Ok(())
 }
 
 /// Given a file or directory, upload either the file or all the files in the directory. Optionally
 /// verify if the data was stored successfully.
+#[allow(clippy::too_many_arguments)]
 async fn upload_files(
     files_path: PathBuf,
     make_data_public: bool,
@@ -231,6 +362,12 @@ async fn upload_files(
     verify_store: bool,
     batch_size: usize,
     max_retries: usize,
+    quote_policy: QuotePolicy,
+    skip_disk_space_check: bool,
+    chunk_size: Option<usize>,
+    show_holders: bool,
+    voucher: Option<&str>,
+    qr: &QrArgs,
 ) -> Result<()> {
     debug!("Uploading file(s) from {files_path:?}, batch size {batch_size:?} will verify?: {verify_store}");
     if make_data_public {
@@ -238,11 +375,29 @@ async fn upload_files(
         println!("{files_path:?} will be made public and linkable");
     }
 
-    let files_api: FilesApi = FilesApi::new(client.clone(), root_dir.to_path_buf());
+    // the chunks are kept with the local wallet's, only the payments being made by the voucher's
+    let wallet_dir = match voucher {
+        Some(voucher_id) => {
+            let wallet_dir = voucher_wallet_dir(&root_dir, voucher_id);
+            if !wallet_dir.exists() {
+                bail!("No voucher {voucher_id} was redeemed. Please redeem it with 'wallet redeem-voucher' first");
+            }
+            println!("Paying for the upload with voucher {voucher_id}");
+            wallet_dir
+        }
+        None => root_dir.to_path_buf(),
+    };
+    let mut files_api: FilesApi = FilesApi::new(client.clone(), wallet_dir);
+    files_api.set_quote_policy(quote_policy);
+    if let Some(chunk_size) = chunk_size {
+        files_api.set_target_chunk_size(chunk_size)?;
+    }
     if files_api.wallet()?.balance().is_zero() {
         bail!("The wallet is empty. Cannot upload any files! Please transfer some funds into the wallet");
     }
     let mut chunk_manager = ChunkManager::new(&root_dir);
+    chunk_manager.set_skip_disk_space_check(skip_disk_space_check);
+    chunk_manager.set_target_chunk_size(files_api.target_chunk_size());
     chunk_manager.chunk_path(&files_path, true, make_data_public)?;
 
     // Return early if we already uploaded them
@@ -253,16 +408,28 @@ async fn upload_files(
             "Files upload attempted previously, verifying {} chunks",
             chunks.len()
         );
-        let failed_chunks = client.verify_uploaded_chunks(&chunks, batch_size).await?;
+        let (failed_chunks, missing_files) =
+            verify_uploaded_chunks(client, &chunks, batch_size, show_holders).await;
 
         // mark the non-failed ones as completed
         chunk_manager.mark_completed(
             chunks
                 .into_iter()
-                .filter(|c| !failed_chunks.contains(c))
+                .filter(|c| !failed_chunks.contains(c) && !missing_files.contains(c))
                 .map(|(xor, _)| xor),
         );
 
+        // the chunks whose file went missing can't be uploaded again as they are
+        if !missing_files.is_empty() {
+            println!(
+                "{} chunk files went missing before they could be verified, run the upload again to chunk them anew",
+                missing_files.len()
+            );
+            if failed_chunks.is_empty() {
+                bail!("{} chunks could not be verified", missing_files.len());
+            }
+        }
+
         // if none are failed, we can return early
         if failed_chunks.is_empty() {
             println!("All files were already uploaded and verified");
@@ -290,6 +457,7 @@ async fn upload_files(
                     info!("Uploaded {file_name:?} to {hex_addr}");
                 }
             }
+            output_uploaded_files_qr(chunk_manager.verified_files(), qr)?;
             return Ok(());
         }
         println!("{:?} chunks were uploaded in the past but failed to verify. Will attempt to upload them again...", failed_chunks.len());
@@ -374,13 +542,17 @@ async fn upload_files(
                     info!("Uploaded {file_name:?} to {hex_addr}");
                 }
             }
+            Ok::<_, ClientError>(chunk_manager.verified_files().clone())
         } else {
             error!("Got FileUploadEvent::Error inside upload event loop");
+            Ok(vec![])
         }
-
-        Ok::<_, ClientError>(())
     });
 
+    // to reconcile the upload with the wallet and the network, should it be interrupted
+    let manifest_path = write_upload_manifest(&root_dir, &chunks_to_upload)?;
+    println!("Wrote the manifest of the upload to {manifest_path:?}");
+
     // upload the files
     println!("Uploading {chunks_to_upload_len} chunks",);
     let now = Instant::now();
@@ -397,9 +569,10 @@ async fn upload_files(
         }
     };
 
-    // bail on errors
+    // bail on errors, keeping the manifest to reconcile the upload with
     upload_result?;
-    progress_handler
+    remove_upload_manifest(&manifest_path);
+    let verified_files = progress_handler
         .await?
         .map_err(|err| eyre!("Failed to write uploaded files with err: {err:?}"))?;
 
@@ -422,6 +595,211 @@ async fn upload_files(
     info!("Made payment of {total_storage_cost} for {uploaded_chunks} chunks");
     info!("New wallet balance: {final_balance}");
 
+    let payees = files_upload.get_upload_payee_breakdown();
+    if !payees.is_empty() {
+        println!("Paid to {} nodes:", payees.len());
+        for payee in &payees {
+            println!(
+                "  {}: {} chunks, {} for storage, {} in royalties",
+                payee.peer_id, payee.chunks, payee.storage_cost, payee.royalties
+            );
+        }
+    }
+    let upload_entry = uploads_ledger::UploadEntry {
+        time: chrono::Utc::now(),
+        path: files_path,
+        uploaded_chunks,
+        storage_cost: total_storage_cost,
+        royalty_fees: total_royalty_fees,
+        final_balance,
+        payees: payees
+            .iter()
+            .map(uploads_ledger::PayeeEntry::from)
+            .collect(),
+    };
+    if let Err(err) = uploads_ledger::record_upload(&root_dir, &upload_entry) {
+        warn!("Could not record the upload in the uploads ledger: {err:?}");
+        println!("Could not record the upload in the uploads ledger: {err}");
+    }
+
+    output_uploaded_files_qr(&verified_files, qr)?;
+
+    Ok(())
+}
+
+/// Write the manifest of an upload of the given chunks to the client dir, returning its path.
+///
+/// The oldest manifests are removed past `MAX_UPLOAD_MANIFESTS`, those of the uploads which
+/// completed being removed as they do.
+fn write_upload_manifest(root_dir: &Path, chunks: &[(XorName, PathBuf)]) -> Result<PathBuf> {
+    let manifests_dir = root_dir.join(UPLOAD_MANIFESTS_DIR);
+    std::fs::create_dir_all(&manifests_dir)?;
+    prune_upload_manifests(&manifests_dir, MAX_UPLOAD_MANIFESTS - 1);
+    let path = manifests_dir.join(format!(
+        "upload-{}.json",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S%.3f")
+    ));
+    UploadManifest::new(chunks).write(&path)?;
+    Ok(path)
+}
+
+/// Remove the oldest manifests of the dir, keeping at most `keep` of them.
+fn prune_upload_manifests(manifests_dir: &Path, keep: usize) {
+    let mut manifests: Vec<_> = WalkDir::new(manifests_dir)
+        .max_depth(1)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+    // the manifests are named after the time of the upload, the oldest first
+    manifests.sort();
+    let excess = manifests.len().saturating_sub(keep);
+    for path in manifests.into_iter().take(excess) {
+        debug!("Removing the old upload manifest {path:?}");
+        remove_upload_manifest(&path);
+    }
+}
+
+/// Remove the manifest of an upload which no longer needs reconciling.
+fn remove_upload_manifest(manifest_path: &Path) {
+    if let Err(err) = std::fs::remove_file(manifest_path) {
+        warn!("Could not remove the upload manifest {manifest_path:?}: {err:?}");
+    }
+}
+
+async fn reconcile_upload(
+    client: &Client,
+    root_dir: &Path,
+    manifest_path: &Path,
+    apply: bool,
+) -> Result<()> {
+    let manifest = UploadManifest::read(manifest_path)?;
+    let files_api = FilesApi::new(client.clone(), root_dir.to_path_buf());
+    let mut plan = files_api.reconcile_upload(&manifest).await?;
+    print_reconcile_plan(&plan);
+
+    if apply && !plan.is_converged() {
+        println!("Applying the plan...");
+        plan = files_api.apply_reconcile_plan(&manifest, &plan).await?;
+        print_reconcile_plan(&plan);
+    }
+    if !plan.lost.is_empty() {
+        println!("Upload the files again to recover the lost chunks.");
+    } else if plan.is_converged() {
+        println!("The upload is complete.");
+        // only the manifests written by `files upload` are ours to remove
+        if manifest_path.starts_with(root_dir.join(UPLOAD_MANIFESTS_DIR)) {
+            remove_upload_manifest(manifest_path);
+        }
+    } else if !apply {
+        println!("Run the command again with `--apply` to carry out the plan.");
+    }
+    Ok(())
+}
+
+fn print_reconcile_plan(plan: &ReconcilePlan) {
+    println!("{} chunks are held by the network", plan.complete.len());
+    println!(
+        "{} chunks are paid for, and to be uploaded again",
+        plan.to_push.len()
+    );
+    for (name, path, payee) in &plan.to_push {
+        println!("    {name:?} at {path:?}, to {payee}");
+    }
+    println!(
+        "{} chunks are to be paid for and uploaded again",
+        plan.to_repay.len()
+    );
+    for (name, path) in &plan.to_repay {
+        println!("    {name:?} at {path:?}");
+    }
+    if !plan.lost.is_empty() {
+        println!(
+            "{} chunks are neither held by the network nor on disk",
+            plan.lost.len()
+        );
+        for (name, path) in &plan.lost {
+            println!("    {name:?} at {path:?}");
+        }
+    }
+    if !plan.unmatched_payments.is_empty() {
+        println!(
+            "{} payments held by the wallet are for chunks outside of the manifest, possibly duplicates",
+            plan.unmatched_payments.len()
+        );
+        for name in &plan.unmatched_payments {
+            println!("    {name:?}");
+        }
+    }
+}
+
+/// Report which chunks of the file at the given hex address are held by the network.
+async fn check_file_health(client: &Client, file_addr: &str, deadline: Option<u64>) -> Result<()> {
+    let bytes = hex::decode(file_addr).map_err(|err| eyre!("Invalid hex address: {err}"))?;
+    let xor_name = XorName(
+        bytes
+            .try_into()
+            .map_err(|_| eyre!("The address is not 32 bytes long"))?,
+    );
+    let head_addr = ChunkAddress::new(xor_name);
+    let report = match deadline {
+        Some(secs) => {
+            let deadline = Deadline::after(Duration::from_secs(secs));
+            client
+                .check_file_health_with_deadline(head_addr, deadline)
+                .await?
+        }
+        None => client.check_file_health(head_addr).await?,
+    };
+    let total = report.chunks.len() + report.unchecked.len() + report.failed.len();
+
+    for (index, chunk) in report.chunks.iter().enumerate() {
+        if chunk.is_present() {
+            println!(
+                "Chunk #{index} {:?} is held by {} nodes",
+                chunk.address,
+                chunk.holders.len()
+            );
+        } else {
+            println!("Chunk #{index} {:?} is MISSING", chunk.address);
+        }
+    }
+    for address in &report.unchecked {
+        println!("Chunk {address:?} was NOT CHECKED before the deadline");
+    }
+    for address in &report.failed {
+        println!("Chunk {address:?} could NOT BE CHECKED");
+    }
+    match report.verdict {
+        FileHealth::Complete => {
+            println!("The file is complete, all its {total} chunks are held")
+        }
+        FileHealth::Degraded { missing } => {
+            println!("The file is degraded, {missing} of its {total} chunks are missing")
+        }
+        FileHealth::Unchecked { unchecked } => println!(
+            "{unchecked} of the {total} chunks of the file could not be checked, none of the others missing"
+        ),
+        FileHealth::Missing if report.chunks.is_empty() => {
+            println!("The file is missing, its data map could not be fetched")
+        }
+        FileHealth::Missing => println!("The file is missing, none of its chunks are held"),
+    }
+    Ok(())
+}
+
+/// Output the head addresses of the uploaded files as QR codes, if requested.
+fn output_uploaded_files_qr(
+    verified_files: &[(OsString, ChunkAddress)],
+    qr: &QrArgs,
+) -> Result<()> {
+    if let [(_, addr)] = verified_files {
+        return qr.output(&addr.to_hex());
+    }
+    for (index, (_, addr)) in verified_files.iter().enumerate() {
+        qr.output_nth(&addr.to_hex(), index)?;
+    }
     Ok(())
 }
 
@@ -430,6 +808,7 @@ async fn download_files(
     root_dir: &Path,
     show_holders: bool,
     batch_size: usize,
+    manifest: bool,
 ) -> Result<()> {
     info!("Downloading with batch size of {}", batch_size);
     let uploaded_files_path = root_dir.join(UPLOADED_FILES);
@@ -466,6 +845,26 @@ async fn download_files(
         }
     }
 
+    // the space of all the files is checked at once, rather than each file fitting on its own
+    // while they don't all
+    let mut files_api = files_api.clone();
+    if !files_api.disk_space_check().is_skipped() {
+        let mut file_sizes = vec![];
+        for (xorname, (file_name, datamap)) in &uploaded_files {
+            match FilesDownload::new(files_api.clone())
+                .file_size(ChunkAddress::new(*xorname), datamap.clone())
+                .await
+            {
+                Ok(file_size) => file_sizes.push(file_size as u64),
+                Err(err) => warn!("Could not tell the size of {file_name:?}: {err}"),
+            }
+        }
+        files_api
+            .disk_space_check()
+            .ensure_space_for_files(&download_path, file_sizes)?;
+        files_api.set_disk_space_check(DiskSpaceCheck::skipped());
+    }
+
     for (xorname, file_data) in uploaded_files.into_iter() {
         download_file(
             files_api.clone(),
@@ -474,6 +873,7 @@ async fn download_files(
             &download_path,
             show_holders,
             batch_size,
+            manifest,
         )
         .await;
     }
@@ -500,10 +900,12 @@ async fn download_file(
     download_path: &Path,
     show_holders: bool,
     batch_size: usize,
+    manifest: bool,
 ) {
     let mut files_download = FilesDownload::new(files_api.clone())
         .set_batch_size(batch_size)
-        .set_show_holders(show_holders);
+        .set_show_holders(show_holders)
+        .set_write_manifest(manifest);
 
     println!("Downloading {file_name:?} from {xor_name:64x} with batch-size {batch_size}");
     debug!("Downloading {file_name:?} from {:64x}", xor_name);
@@ -573,6 +975,12 @@ async fn download_file(
                 "Saved {file_name:?} at {}",
                 downloaded_file_path.to_string_lossy()
             );
+            if manifest {
+                println!(
+                    "Saved its manifest at {}",
+                    DownloadManifest::path_for(&downloaded_file_path).to_string_lossy()
+                );
+            }
         }
         Err(error) => {
             error!("Error downloading {file_name:?}: {error}");
@@ -581,6 +989,92 @@ async fn download_file(
     }
 }
 
+/// Check a downloaded file against its manifest, reporting the chunks which don't match.
+fn verify_local(path: &Path) -> Result<()> {
+    let verification = verify_local_file(path).map_err(|err| {
+        eyre!("Could not verify {path:?} against its manifest: {err}").suggestion(
+            "Please download the file again with the --manifest flag to write its manifest",
+        )
+    })?;
+
+    if verification.is_verified() {
+        println!("{path:?} matches its manifest");
+        return Ok(());
+    }
+
+    if verification.actual_size != verification.expected_size {
+        println!(
+            "{path:?} is {} bytes long, expected {} bytes",
+            verification.actual_size, verification.expected_size
+        );
+    }
+    for chunk in &verification.corrupted_chunks {
+        println!(
+            "Chunk {} ({}) at bytes {:?} does not match, it was served by {:?}",
+            chunk.index,
+            chunk.xorname,
+            chunk.range(),
+            chunk.holders
+        );
+    }
+    error!(
+        "{path:?} does not match its manifest: {} corrupted chunks",
+        verification.corrupted_chunks.len()
+    );
+    bail!("{path:?} does not match its manifest")
+}
+
+/// Verify the chunks were uploaded, showing the progress as they are verified, and which of the
+/// close nodes hold each chunk if `show_holders` is set.
+///
+/// Returns the chunks which failed to verify, to be uploaded again, and apart those whose file
+/// is missing, which can't be.
+async fn verify_uploaded_chunks(
+    client: &Client,
+    chunks: &[(XorName, PathBuf)],
+    batch_size: usize,
+    show_holders: bool,
+) -> (Vec<(XorName, PathBuf)>, Vec<(XorName, PathBuf)>) {
+    let progress_bar = get_progress_bar(chunks.len() as u64).ok();
+    let mut failed_chunks = vec![];
+    let mut missing_files = vec![];
+    let mut results = client.verify_uploaded_chunks_stream(chunks.to_vec(), batch_size);
+    while let Some((name, path, status)) = results.next().await {
+        if matches!(status, VerificationStatus::FileMissing) {
+            warn!("Chunk {name:?} can't be verified, {path:?} is missing");
+            missing_files.push((name, path));
+        } else if !status.is_verified() {
+            debug!("Chunk {name:?} at {path:?} failed to verify: {status:?}");
+            failed_chunks.push((name, path));
+        }
+        if show_holders {
+            if let Some(report) = status.replication_report() {
+                let line = format!(
+                    "Chunk {name:?} is held by {:?}, missing from {:?}{}",
+                    report.holders_ok,
+                    report.holders_missing,
+                    if report.quorum_met {
+                        ""
+                    } else {
+                        " (quorum not met)"
+                    }
+                );
+                match &progress_bar {
+                    Some(progress_bar) => progress_bar.println(line),
+                    None => println!("{line}"),
+                }
+            }
+        }
+        if let Some(progress_bar) = &progress_bar {
+            progress_bar.inc(1);
+        }
+    }
+    if let Some(progress_bar) = progress_bar {
+        progress_bar.finish_and_clear();
+    }
+    (failed_chunks, missing_files)
+}
+
 fn get_progress_bar(length: u64) -> Result<ProgressBar> {
     let progress_bar = ProgressBar::new(length);
     progress_bar.set_style(
@@ -0,0 +1,181 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use chrono::{DateTime, Utc};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use sn_client::PayeeBreakdown;
+use sn_transfers::NanoTokens;
+use std::{
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// The file in the client dir the uploads are recorded in, one JSON entry per line.
+pub(crate) const UPLOADS_LEDGER: &str = "uploads_ledger.jsonl";
+
+/// An upload, as recorded in the ledger.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct UploadEntry {
+    pub(crate) time: DateTime<Utc>,
+    /// The file or directory uploaded.
+    pub(crate) path: PathBuf,
+    pub(crate) uploaded_chunks: usize,
+    pub(crate) storage_cost: NanoTokens,
+    pub(crate) royalty_fees: NanoTokens,
+    pub(crate) final_balance: NanoTokens,
+    /// What was paid to each node.
+    #[serde(default)]
+    pub(crate) payees: Vec<PayeeEntry>,
+}
+
+/// What was paid to a node during an upload.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PayeeEntry {
+    pub(crate) peer_id: String,
+    pub(crate) chunks: usize,
+    pub(crate) storage_cost: NanoTokens,
+    pub(crate) royalties: NanoTokens,
+}
+
+impl From<&PayeeBreakdown> for PayeeEntry {
+    fn from(payee: &PayeeBreakdown) -> Self {
+        Self {
+            peer_id: payee.peer_id.to_string(),
+            chunks: payee.chunks,
+            storage_cost: payee.storage_cost,
+            royalties: payee.royalties,
+        }
+    }
+}
+
+/// Append the upload to the ledger in `root_dir`.
+pub(crate) fn record_upload(root_dir: &Path, entry: &UploadEntry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(root_dir.join(UPLOADS_LEDGER))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// The uploads recorded in the ledger in `root_dir`, oldest first.
+pub(crate) fn read_uploads(root_dir: &Path) -> Result<Vec<UploadEntry>> {
+    let path = root_dir.join(UPLOADS_LEDGER);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    fs::read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| eyre!("Invalid entry {} in {path:?}: {err}", index + 1))
+        })
+        .collect()
+}
+
+/// Render the uploads, each followed by what was paid to each node if `breakdown` is set.
+pub(crate) fn render_uploads(uploads: &[UploadEntry], breakdown: bool) -> String {
+    let mut text = String::new();
+    for upload in uploads {
+        let _ = writeln!(
+            text,
+            "{} {:?}: {} chunks, paid {} for storage and {} in royalties, balance {}",
+            upload.time.format("%Y-%m-%d %H:%M:%S"),
+            upload.path,
+            upload.uploaded_chunks,
+            upload.storage_cost,
+            upload.royalty_fees,
+            upload.final_balance
+        );
+        if !breakdown {
+            continue;
+        }
+        if upload.payees.is_empty() {
+            let _ = writeln!(text, "    no payments recorded");
+        }
+        for payee in &upload.payees {
+            let _ = writeln!(
+                text,
+                "    {}: {} chunks, {} for storage, {} in royalties",
+                payee.peer_id, payee.chunks, payee.storage_cost, payee.royalties
+            );
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use libp2p::PeerId;
+    use tempfile::TempDir;
+
+    fn upload(path: &str, payees: &[(PeerId, usize, u64, u64)]) -> UploadEntry {
+        let payees: Vec<_> = payees
+            .iter()
+            .map(|(peer_id, chunks, cost, royalties)| {
+                PayeeEntry::from(&PayeeBreakdown {
+                    peer_id: *peer_id,
+                    chunks: *chunks,
+                    storage_cost: NanoTokens::from(*cost),
+                    royalties: NanoTokens::from(*royalties),
+                })
+            })
+            .collect();
+        UploadEntry {
+            time: Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            path: PathBuf::from(path),
+            uploaded_chunks: payees.iter().map(|payee| payee.chunks).sum(),
+            storage_cost: NanoTokens::from(
+                payees.iter().map(|p| p.storage_cost.as_nano()).sum::<u64>(),
+            ),
+            royalty_fees: NanoTokens::from(
+                payees.iter().map(|p| p.royalties.as_nano()).sum::<u64>(),
+            ),
+            final_balance: NanoTokens::from(1_000),
+            payees,
+        }
+    }
+
+    #[test]
+    fn uploads_are_read_back_in_order() -> Result<()> {
+        let dir = TempDir::new()?;
+        assert!(read_uploads(dir.path())?.is_empty());
+
+        let first = upload("first", &[(PeerId::random(), 2, 20, 3)]);
+        let second = upload("second", &[]);
+        record_upload(dir.path(), &first)?;
+        record_upload(dir.path(), &second)?;
+
+        assert_eq!(read_uploads(dir.path())?, vec![first, second]);
+        Ok(())
+    }
+
+    #[test]
+    fn the_breakdown_lists_each_payee() {
+        let (peer_a, peer_b) = (PeerId::random(), PeerId::random());
+        let uploads = vec![upload("file", &[(peer_a, 2, 20, 3), (peer_b, 1, 7, 1)])];
+
+        let summary = render_uploads(&uploads, false);
+        assert_eq!(summary.lines().count(), 1);
+        assert!(summary.contains("3 chunks"));
+
+        let breakdown = render_uploads(&uploads, true);
+        assert_eq!(breakdown.lines().count(), 3);
+        assert!(breakdown.contains(&format!("{peer_a}: 2 chunks")));
+        assert!(breakdown.contains(&format!("{peer_b}: 1 chunks")));
+    }
+}
@@ -5,6 +5,7 @@
 // under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
+pub(crate) mod doctor;
 pub(crate) mod files;
 pub(crate) mod gossipsub;
 pub(crate) mod register;
@@ -26,4 +27,7 @@ pub(super) enum SubCmd {
     #[clap(name = "gossipsub", subcommand)]
     /// Commands for gossipsub management
     Gossipsub(gossipsub::GossipsubCmds),
+    #[clap(name = "doctor")]
+    /// Check the network, clock, wallet and disk are fit for the client to run
+    Doctor(doctor::DoctorArgs),
 }
@@ -6,13 +6,20 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::qr::QrArgs;
 use bls::PublicKey;
 use clap::Subcommand;
-use color_eyre::{eyre::WrapErr, Result, Section};
-use sn_client::{Client, Error as ClientError, WalletClient};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use sn_client::{
+    Client, ClientRegister, Deadline, Error as ClientError, RegisterReadOptions, WalletClient,
+    BATCH_SIZE,
+};
 use sn_protocol::storage::RegisterAddress;
 use sn_transfers::LocalWallet;
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path, time::Duration};
 use xor_name::XorName;
 
 #[derive(Subcommand, Debug)]
@@ -46,7 +53,44 @@ pub enum RegisterCmds {
         /// Use this flag if you are providing the register names instead of the addresses
         #[clap(name = "name", short = 'n')]
         use_name: bool,
+        /// Read the copies held by every reachable node of the close group and merge them,
+        /// rather than reading from a quorum. Slower, but more consistent under heavy writes.
+        #[clap(long)]
+        merge_all: bool,
+        /// Retrieve the registers at once, reporting those not retrieved after this many seconds.
+        #[clap(long, value_name = "SECS")]
+        deadline: Option<u64>,
     },
+    /// Print the address of a register, optionally as a QR code.
+    Address {
+        /// The address of the register, or its name if used with `-n`.
+        #[clap(name = "address")]
+        address: String,
+        /// If you are the owner, the name of the register can be used as a shorthand to the address,
+        /// as we can derive the address from the public key + name
+        /// Use this flag if you are providing the register name instead of the address
+        #[clap(name = "name", short = 'n')]
+        use_name: bool,
+        #[clap(flatten)]
+        qr: QrArgs,
+    },
+}
+
+/// Perform the register commands that do not require us connecting to the network.
+pub(crate) fn register_cmds_without_client(cmds: &RegisterCmds, pk: PublicKey) -> Result<()> {
+    match cmds {
+        RegisterCmds::Address {
+            address,
+            use_name,
+            qr,
+        } => {
+            let (address, _) = parse_addr(address, *use_name, pk)?;
+            let hex_addr = address.to_hex();
+            println!("{hex_addr}");
+            qr.output(&hex_addr)
+        }
+        cmd => Err(eyre!("{cmd:?} requires us to be connected to the Network")),
+    }
 }
 
 pub(crate) async fn register_cmds(
@@ -67,7 +111,14 @@ pub(crate) async fn register_cmds(
         RegisterCmds::Get {
             addresses,
             use_name,
-        } => get_registers(addresses, use_name, client).await?,
+            merge_all,
+            deadline,
+        } => get_registers(addresses, use_name, merge_all, deadline, client).await?,
+        cmd => {
+            return Err(eyre!(
+                "{cmd:?} has to be processed before connecting to the network"
+            ))
+        }
     }
     Ok(())
 }
@@ -117,7 +168,10 @@ async fn edit_register(
 
     println!("Trying to retrieve Register from {address}");
 
-    match client.get_register(address).await {
+    match client
+        .get_register(address, RegisterReadOptions::default())
+        .await
+    {
         Ok(mut register) => {
             println!("Successfully retrieved Register {printing_name}",);
             println!("Editing Register {printing_name} with: {entry}");
@@ -146,24 +200,31 @@ async fn edit_register(
     Ok(())
 }
 
-async fn get_registers(addresses: Vec<String>, use_name: bool, client: &Client) -> Result<()> {
+async fn get_registers(
+    addresses: Vec<String>,
+    use_name: bool,
+    merge_all: bool,
+    deadline: Option<u64>,
+    client: &Client,
+) -> Result<()> {
+    let options = if merge_all {
+        RegisterReadOptions::merge_all()
+    } else {
+        RegisterReadOptions::default()
+    };
+    if let Some(secs) = deadline {
+        let deadline = Deadline::after(Duration::from_secs(secs));
+        return get_registers_with_deadline(addresses, use_name, options, deadline, client).await;
+    }
     for addr in addresses {
         let (address, printing_name) = parse_addr(&addr, use_name, client.signer_pk())?;
 
         println!("Trying to retrieve Register {printing_name}");
 
-        match client.get_register(address).await {
+        match client.get_register(address, options).await {
             Ok(register) => {
                 println!("Successfully retrieved Register {printing_name}");
-                let entries = register.read();
-                println!("Register entries:");
-                for (hash, bytes) in entries {
-                    let data_str = match String::from_utf8(bytes.clone()) {
-                        Ok(data_str) => data_str,
-                        Err(_) => format!("{bytes:?}"),
-                    };
-                    println!("{hash:?}: {data_str}");
-                }
+                print_register_entries(&register);
             }
             Err(error) => {
                 println!(
@@ -177,6 +238,78 @@ async fn get_registers(addresses: Vec<String>, use_name: bool, client: &Client)
     Ok(())
 }
 
+/// Retrieve the registers at once, printing those retrieved before the deadline, and flagging
+/// the others.
+async fn get_registers_with_deadline(
+    addresses: Vec<String>,
+    use_name: bool,
+    options: RegisterReadOptions,
+    deadline: Deadline,
+    client: &Client,
+) -> Result<()> {
+    let mut printing_names = BTreeMap::new();
+    for addr in &addresses {
+        let (address, printing_name) = parse_addr(addr, use_name, client.signer_pk())?;
+        let _ = printing_names.insert(address, printing_name);
+    }
+    let addresses: Vec<_> = printing_names.keys().copied().collect();
+    println!(
+        "Trying to retrieve {} Registers within {:?}",
+        addresses.len(),
+        deadline.remaining()
+    );
+
+    let outcome = client
+        .get_registers_with_deadline(&addresses, options, BATCH_SIZE, deadline)
+        .await;
+    for (address, register) in &outcome.completed {
+        println!(
+            "Successfully retrieved Register {}",
+            printing_names[address]
+        );
+        print_register_entries(register);
+    }
+    for (address, error) in &outcome.failed {
+        println!(
+            "FAILED to retrieve Register {}: {error}",
+            printing_names[address]
+        );
+    }
+    for address in &outcome.cancelled {
+        println!(
+            "Register {} was still being retrieved at the deadline",
+            printing_names[address]
+        );
+    }
+    for address in &outcome.not_attempted {
+        println!(
+            "Register {} was NOT ATTEMPTED before the deadline",
+            printing_names[address]
+        );
+    }
+
+    let incomplete = outcome.incomplete().count();
+    if incomplete > 0 {
+        return Err(eyre!(
+            "{incomplete} of the {} Registers were not retrieved",
+            addresses.len()
+        ));
+    }
+    Ok(())
+}
+
+fn print_register_entries(register: &ClientRegister) {
+    let entries = register.read();
+    println!("Register entries:");
+    for (hash, bytes) in entries {
+        let data_str = match String::from_utf8(bytes.clone()) {
+            Ok(data_str) => data_str,
+            Err(_) => format!("{bytes:?}"),
+        };
+        println!("{hash:?}: {data_str}");
+    }
+}
+
 /// Parse str and return the address and the register info for printing
 fn parse_addr(
     address_str: &str,
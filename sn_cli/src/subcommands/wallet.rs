@@ -6,23 +6,37 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::get_stdin_response;
+use crate::{get_stdin_response, qr::QrArgs, subcommands::files::ChunkManager};
 use bls::{PublicKey, SecretKey, PK_SIZE};
 use clap::Parser;
-use color_eyre::{eyre::eyre, Result};
-use sn_client::{Client, ClientEvent, Error as ClientError};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use sn_client::{
+    print_audit_progress, write_anomaly_report, write_royalty_report, AuditFrontier, BucketSize,
+    Client, ClientEvent, Error as ClientError, FilesApi, PaymentAuditStatus, RoyaltyTracker,
+    SpendDag, SpendVerificationReport, WalletClient, SPEND_DAG_FILE_NAME,
+};
 use sn_transfers::{
-    CashNoteRedemption, Error as TransferError, LocalWallet, MainPubkey, MainSecretKey, NanoTokens,
-    SpendAddress, Transfer, UniquePubkey, WalletError, WatchOnlyWallet, GENESIS_CASHNOTE,
+    AutoSplitPolicy, CashNoteRedemption, Error as TransferError, HistoryFormat, LocalWallet,
+    MainPubkey, MainSecretKey, NanoTokens, SpendAddress, StorageVoucherGrant, Transfer,
+    UniquePubkey, WalletError, WatchOnlyWallet, DEFAULT_AUTO_SPLIT_MAX_NOTES, GENESIS_CASHNOTE,
 };
 use std::{
+    collections::BTreeSet,
     io::Read,
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, SystemTime},
 };
 use url::Url;
 
 const DEFAULT_RECEIVE_ONLINE_WALLET_DIR: &str = "receive_online";
+/// The dir holding the wallets of the storage vouchers redeemed, one per voucher id.
+const STORAGE_VOUCHERS_DIR: &str = "storage_vouchers";
+/// How long a storage voucher is valid for, unless set otherwise: a week.
+const DEFAULT_VOUCHER_VALIDITY_SECS: u64 = 7 * 24 * 60 * 60;
 const ROYALTY_TRANSFER_NOTIF_TOPIC: &str = "ROYALTY_TRANSFER_NOTIFICATION";
 
 // Please do not remove the blank lines in these doc comments.
@@ -30,7 +44,21 @@ const ROYALTY_TRANSFER_NOTIF_TOPIC: &str = "ROYALTY_TRANSFER_NOTIFICATION";
 #[derive(Parser, Debug)]
 pub enum WalletCmds {
     /// Print the wallet address.
-    Address,
+    Address {
+        #[clap(flatten)]
+        qr: QrArgs,
+    },
+    /// Print the wallet addresses.
+    Addresses {
+        /// Also print the addresses retired by 'rotate-key', which still receive tokens.
+        #[clap(long, default_value = "false")]
+        all: bool,
+    },
+    /// Replace the wallet key with a new one, e.g. if the address got tied to your identity.
+    ///
+    /// The current key is kept to receive and spend the tokens sent to its address still,
+    /// but only the new address is printed by 'address' and receives the change of payments.
+    RotateKey,
     /// Print the wallet balance.
     Balance {
         /// Instead of checking CLI local wallet balance, the PeerId of a node can be used
@@ -85,6 +113,40 @@ pub enum WalletCmds {
         /// Hex-encoded public address of the recipient.
         #[clap(name = "to")]
         to: String,
+        /// Preview the transfer, including how the change would be split, without sending it.
+        #[clap(long, default_value = "false")]
+        dry_run: bool,
+    },
+    /// Configure the local wallet.
+    ///
+    /// Without any option, print the current configuration.
+    Config {
+        /// Split the change of payments above this number of SafeNetworkTokens into several
+        /// cash notes, so that further payments can be made concurrently.
+        #[clap(long, value_name = "threshold", conflicts_with = "disable_auto_split")]
+        auto_split: Option<String>,
+        /// The amounts the change is split into, comma-separated.
+        /// Defaults to the auto split threshold.
+        #[clap(long, value_delimiter = ',', requires = "auto_split")]
+        denominations: Vec<String>,
+        /// The change is not split beyond this number of cash notes in the wallet.
+        #[clap(long, default_value_t = DEFAULT_AUTO_SPLIT_MAX_NOTES, requires = "auto_split")]
+        max_notes: usize,
+        /// Stop splitting the change of payments.
+        #[clap(long, default_value = "false")]
+        disable_auto_split: bool,
+    },
+    /// Export the history of the tokens received and sent by the local wallet.
+    ///
+    /// The CSV columns are: timestamp, kind, amount, balance, counterparty, reason
+    /// and spend_addresses. Amounts are in SafeNetworkTokens, with nine decimals.
+    History {
+        /// The export format, 'csv' or 'jsonl' (one JSON object per line).
+        #[clap(long, default_value = "csv")]
+        export: HistoryFormat,
+        /// Write the history to this file rather than to stdout.
+        #[clap(long, value_name = "PATH")]
+        out: Option<PathBuf>,
     },
     /// Receive a transfer created by the 'send' command.
     Receive {
@@ -120,24 +182,104 @@ pub enum WalletCmds {
         /// Analogous to verifying an UTXO through the entire blockchain in Bitcoin
         #[clap(long, default_value = "false")]
         genesis: bool,
+        /// Stop verifying the ancestors after this many generations of them.
+        #[clap(long, value_name = "GENERATIONS", requires = "genesis")]
+        max_depth: Option<usize>,
+        /// Stop verifying the ancestors once this many of their spends were fetched.
+        #[clap(long, value_name = "SPENDS", requires = "genesis")]
+        max_spends: Option<usize>,
+        /// Carry on with the verification saved to this file, if any, saving where it stops to
+        /// the file.
+        ///
+        /// A verification stopped at its limits is resumed by running the command again.
+        #[clap(long, value_name = "FILE", requires = "genesis")]
+        resume: Option<PathBuf>,
     },
     /// Audit the Currency
     /// Note that this might take a very long time
     /// Analogous to verifying the entire blockchain in Bitcoin
     Audit {
         /// EXPERIMENTAL Dump Audit DAG in dot format on stdout
+        ///
+        /// The DAG is stored under the client's data dir, and only updated with the spends made
+        /// since on the next run.
         #[clap(long, default_value = "false")]
         dot: bool,
         /// EXPERIMENTAL Find and redeem all Network Royalties
         /// only works if the wallet has the Network Royalties private key
         #[clap(long, default_value = "false")]
         royalties: bool,
+        /// Write a report of the royalties observed so far, summarised by time bucket, to this
+        /// file: as JSON if its extension is `json`, else as CSV.
+        ///
+        /// Spends have no timestamps, so royalties are bucketed by when they were first observed,
+        /// as checkpointed under the client's data dir. Buckets holding royalties observed by
+        /// the first audit, which backfills the history, are flagged as such.
+        #[clap(long, value_name = "PATH")]
+        royalty_report: Option<PathBuf>,
+        /// The size of the time buckets of the royalty report, 'day' or 'week'.
+        #[clap(long, default_value = "day", requires = "royalty_report")]
+        bucket: BucketSize,
+        /// Write the structural anomalies found in the spends audited to this file, as JSON.
+        #[clap(long, value_name = "PATH")]
+        anomaly_report: Option<PathBuf>,
+        /// Carry on from where the previous incremental audit stopped, as stored under the
+        /// client's data dir, only following the spends made since. From genesis the first time.
+        ///
+        /// The anomalies found are those of the spends followed by this audit only.
+        #[clap(long, default_value = "false", conflicts_with = "dot")]
+        incremental: bool,
+    },
+    /// Issue a storage voucher, for someone else to pay for storage on your behalf.
+    ///
+    /// The amount is reserved from the local wallet, and the voucher printed as hex, to be
+    /// handed over to the uploader, who redeems it with 'wallet redeem-voucher'. The keys of the
+    /// local wallet are not part of it.
+    IssueVoucher {
+        /// The number of SafeNetworkTokens reserved for the voucher.
+        #[clap(name = "amount")]
+        amount: String,
+        /// The number of seconds the voucher is valid for.
+        #[clap(long, value_name = "SECS", default_value_t = DEFAULT_VOUCHER_VALIDITY_SECS)]
+        expires_in: u64,
+        /// Hex-encoded prefix the addresses of the data paid for must start with.
+        #[clap(long, value_name = "HEX")]
+        address_prefix: Option<String>,
+    },
+    /// Redeem a storage voucher issued with 'wallet issue-voucher'.
+    ///
+    /// The voucher gets a wallet of its own, which 'files upload --voucher <id>' pays with.
+    RedeemVoucher {
+        /// Read the voucher from a file.
+        #[clap(long, default_value = "false")]
+        file: bool,
+        /// Hex-encoded voucher.
+        #[clap(name = "voucher")]
+        voucher: String,
+    },
+    /// Show how much of the storage vouchers issued by the local wallet was used.
+    VoucherUsage {
+        /// The id of the voucher, as printed when issued. All of them if not given.
+        #[clap(name = "id")]
+        id: Option<String>,
+    },
+    /// Check whether the content paid for with the local wallet made it to the Network.
+    ///
+    /// Interrupted uploads leave payments for content that was never stored. Those whose quote
+    /// is still valid can be uploaded again without paying again, the others are lost.
+    AuditPayments {
+        /// Upload again the chunks whose payment is still valid, from the chunks left over by
+        /// interrupted uploads.
+        #[clap(long, default_value = "false")]
+        repair: bool,
     },
 }
 
 pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Path) -> Result<()> {
     match cmds {
-        WalletCmds::Address => address(root_dir),
+        WalletCmds::Address { qr } => address(root_dir, qr),
+        WalletCmds::Addresses { all } => addresses(root_dir, *all),
+        WalletCmds::RotateKey => rotate_key(root_dir),
         WalletCmds::Balance { peer_id } => {
             if peer_id.is_empty() {
                 let balance = balance(root_dir)?;
@@ -188,6 +330,24 @@ pub(crate) async fn wallet_cmds_without_client(cmds: &WalletCmds, root_dir: &Pat
 
             Ok(())
         }
+        WalletCmds::Config {
+            auto_split,
+            denominations,
+            max_notes,
+            disable_auto_split,
+        } => config(
+            root_dir,
+            auto_split.as_deref(),
+            denominations,
+            *max_notes,
+            *disable_auto_split,
+        ),
+        WalletCmds::History { export, out } => history(root_dir, *export, out.as_deref()),
+        WalletCmds::Send {
+            amount,
+            to,
+            dry_run: true,
+        } => preview_send(amount, to, root_dir),
         cmd => Err(eyre!("{cmd:?} requires us to be connected to the Network")),
     }
 }
@@ -199,18 +359,76 @@ pub(crate) async fn wallet_cmds(
     verify_store: bool,
 ) -> Result<()> {
     match cmds {
-        WalletCmds::Send { amount, to } => send(amount, to, client, root_dir, verify_store).await,
+        WalletCmds::Send {
+            amount,
+            to,
+            dry_run: false,
+        } => send(amount, to, client, root_dir, verify_store).await,
         WalletCmds::Receive { file, transfer } => receive(transfer, file, client, root_dir).await,
         WalletCmds::GetFaucet { url } => get_faucet(root_dir, client, url.clone()).await,
         WalletCmds::ReceiveOnline { pk, path } => {
             let wallet_dir = path.unwrap_or(root_dir.join(DEFAULT_RECEIVE_ONLINE_WALLET_DIR));
             listen_notifs_and_deposit(&wallet_dir, client, pk).await
         }
-        WalletCmds::Audit { dot, royalties } => audit(client, dot, royalties, root_dir).await,
+        WalletCmds::Audit {
+            dot,
+            royalties,
+            royalty_report,
+            bucket,
+            anomaly_report,
+            incremental,
+        } => {
+            audit(
+                client,
+                dot,
+                royalties,
+                royalty_report,
+                bucket,
+                anomaly_report,
+                incremental,
+                root_dir,
+            )
+            .await
+        }
         WalletCmds::Verify {
             spend_address,
             genesis,
-        } => verify(spend_address, genesis, client).await,
+            max_depth,
+            max_spends,
+            resume,
+        } => {
+            verify(
+                spend_address,
+                genesis,
+                max_depth,
+                max_spends,
+                resume.as_deref(),
+                client,
+            )
+            .await
+        }
+        WalletCmds::AuditPayments { repair } => {
+            audit_payments(client, root_dir, repair, verify_store).await
+        }
+        WalletCmds::IssueVoucher {
+            amount,
+            expires_in,
+            address_prefix,
+        } => {
+            issue_voucher(
+                client,
+                root_dir,
+                &amount,
+                expires_in,
+                address_prefix.as_deref(),
+                verify_store,
+            )
+            .await
+        }
+        WalletCmds::RedeemVoucher { file, voucher } => {
+            redeem_voucher(client, root_dir, voucher, file).await
+        }
+        WalletCmds::VoucherUsage { id } => voucher_usage(client, root_dir, id.as_deref()).await,
         cmd => Err(eyre!(
             "{cmd:?} has to be processed before connecting to the network"
         )),
@@ -230,7 +448,14 @@ fn parse_pubkey_address(str_addr: &str) -> Result<SpendAddress> {
 
 /// Verify a spend on the Network.
 /// if genesis is true, verify all the way to Genesis, note that this might take A VERY LONG TIME
-async fn verify(spend_address: String, genesis: bool, client: &Client) -> Result<()> {
+async fn verify(
+    spend_address: String,
+    genesis: bool,
+    max_depth: Option<usize>,
+    max_spends: Option<usize>,
+    resume: Option<&Path>,
+    client: &Client,
+) -> Result<()> {
     if genesis {
         println!("Verifying spend all the way to Genesis, note that this might take a while...");
     } else {
@@ -238,38 +463,422 @@ async fn verify(spend_address: String, genesis: bool, client: &Client) -> Result
     }
 
     let addr = parse_pubkey_address(&spend_address)?;
-    match client.verify_spend(addr, genesis).await {
-        Ok(()) => println!("Spend verified to be stored and unique at {addr:?}"),
+    let saved = match resume {
+        Some(path) if path.exists() => {
+            let report = SpendVerificationReport::load_from(path)?;
+            if report.address != addr {
+                bail!(
+                    "The verification saved to {path:?} is of the spend at {:?}, not {addr:?}",
+                    report.address
+                );
+            }
+            println!(
+                "Carrying on from {path:?}, with {} transactions left to verify",
+                report.frontier.len()
+            );
+            Some(report)
+        }
+        _ => None,
+    };
+    let result = match saved {
+        Some(report) => {
+            client
+                .resume_spend_verification(
+                    report,
+                    max_depth,
+                    max_spends,
+                    Some(&mut print_audit_progress),
+                )
+                .await
+        }
+        None => {
+            client
+                .verify_spend_with_progress(
+                    addr,
+                    genesis,
+                    max_depth,
+                    max_spends,
+                    Some(&mut print_audit_progress),
+                )
+                .await
+        }
+    };
+    if let (Ok(report), Some(path)) = (&result, resume) {
+        report.save_to(path)?;
+    }
+
+    match result {
+        Ok(report) if report.is_partial() => {
+            println!(
+                "Stopped at the limits set, through {} generations, verifying {} transactions in {:?}",
+                report.depth,
+                report.txs_verified(),
+                report.elapsed
+            );
+            println!(
+                "Spend verified to be stored and unique at {addr:?}, {} transactions of its ancestors are left to verify:",
+                report.frontier.len()
+            );
+            for tx_hash in report.frontier_hashes() {
+                println!("  - {tx_hash:?}");
+            }
+            match resume {
+                Some(path) => println!(
+                    "Saved to {path:?}, run the command again to carry on with the verification."
+                ),
+                None => println!(
+                    "Run the command with '--resume <FILE>' to save where it stopped and carry on later."
+                ),
+            }
+        }
+        Ok(report) => {
+            if genesis {
+                println!(
+                    "Verified all the way to genesis! Through {} generations, verifying {} transactions in {:?}",
+                    report.depth,
+                    report.txs_verified(),
+                    report.elapsed
+                );
+            }
+            println!("Spend verified to be stored and unique at {addr:?}");
+        }
+        Err(WalletError::DoubleSpendDetected { address, spends }) => {
+            println!("Double spend detected at {address:?}, conflicting spends:");
+            for (i, spend) in spends.iter().enumerate() {
+                println!(
+                    "Spend {i}: {:?} spent in Tx {:?}\n{:#?}",
+                    spend.unique_pubkey(),
+                    spend.spent_tx_hash(),
+                    spend.spend.spent_tx
+                );
+            }
+        }
         Err(e) => println!("Failed to verify spend at {addr:?}: {e}"),
     }
 
     Ok(())
 }
 
-async fn audit(client: &Client, to_dot: bool, find_royalties: bool, root_dir: &Path) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+async fn audit(
+    client: &Client,
+    to_dot: bool,
+    find_royalties: bool,
+    royalty_report: Option<PathBuf>,
+    bucket_size: BucketSize,
+    anomaly_report: Option<PathBuf>,
+    incremental: bool,
+    root_dir: &Path,
+) -> Result<()> {
     let genesis_addr = SpendAddress::from_unique_pubkey(&GENESIS_CASHNOTE.unique_pubkey());
 
     if to_dot {
-        let dag = client.build_spend_dag_from(genesis_addr).await?;
+        let dag_path = root_dir.join(SPEND_DAG_FILE_NAME);
+        let stored = if dag_path.exists() {
+            match SpendDag::load(&dag_path) {
+                Ok(dag) => Some(dag),
+                Err(err) => {
+                    eprintln!("{err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let dag = match stored {
+            Some(mut dag) => {
+                let added = dag.update_from_network(client).await?;
+                eprintln!("Added {added} spends to the stored SpendDag");
+                dag
+            }
+            None => {
+                eprintln!("Building the SpendDag from genesis...");
+                client.build_spend_dag_from(genesis_addr).await?
+            }
+        };
+        dag.save(&dag_path)?;
         println!("{}", dag.dump_dot_format());
     } else {
         println!("Auditing the Currency, note that this might take a very long time...");
-        client
-            .follow_spend(genesis_addr, find_royalties, root_dir)
-            .await?;
+        let mut royalty_tracker = match royalty_report {
+            Some(_) => Some(RoyaltyTracker::load_from(root_dir)?),
+            None => None,
+        };
+        let mut spend_dag = SpendDag::new();
+        if incremental {
+            let frontier = match AuditFrontier::load_from(root_dir)? {
+                Some(frontier) => {
+                    println!(
+                        "Carrying on from the {} UTXOs found by the previous audit...",
+                        frontier.utxos.len()
+                    );
+                    frontier
+                }
+                None => AuditFrontier::new(BTreeSet::from([genesis_addr])),
+            };
+            let (frontier, audit) = client
+                .follow_spend_from(
+                    frontier,
+                    find_royalties,
+                    royalty_tracker.as_mut(),
+                    Some(&mut spend_dag),
+                    root_dir,
+                    Some(&mut print_audit_progress),
+                )
+                .await?;
+            frontier.save_to(root_dir)?;
+            println!(
+                "Saved the frontier of {} UTXOs for the next incremental audit to carry on from.",
+                frontier.utxos.len()
+            );
+            if !audit.double_spends.is_empty() {
+                println!(
+                    "Found {} double spends since the previous audit: {:?}",
+                    audit.double_spends.len(),
+                    audit.double_spent_addresses()
+                );
+            }
+        } else {
+            let audit = client
+                .follow_spend(
+                    genesis_addr,
+                    find_royalties,
+                    royalty_tracker.as_mut(),
+                    Some(&mut spend_dag),
+                    root_dir,
+                    Some(&mut print_audit_progress),
+                )
+                .await?;
+            match audit.supply_check(GENESIS_CASHNOTE.value()?) {
+                Ok(()) => println!(
+                    "The supply adds up: {} in UTXOs, {} of them royalties, and {} in the branches not followed.",
+                    audit.total_utxo_value, audit.total_royalties, audit.total_unfollowed_value
+                ),
+                Err(err) => println!("{err}"),
+            }
+        }
+
+        let anomalies = spend_dag.detect_anomalies();
+        if anomalies.is_empty() {
+            println!("No anomalies found in the spends audited.");
+        } else {
+            println!("Found {} anomalies in the spends audited:", anomalies.len());
+            for anomaly in &anomalies {
+                println!("  - [{:?}] {anomaly}", anomaly.severity());
+            }
+        }
+        if let Some(path) = anomaly_report {
+            write_anomaly_report(&path, &anomalies)?;
+            println!("Anomaly report written to {path:?}");
+        }
+
+        if let (Some(mut tracker), Some(path)) = (royalty_tracker, royalty_report) {
+            tracker.save_to(root_dir)?;
+            let buckets = tracker.report(bucket_size);
+            write_royalty_report(&path, &buckets)?;
+            println!(
+                "Royalty report of {} {bucket_size} buckets written to {path:?}",
+                buckets.len()
+            );
+            if buckets.iter().any(|bucket| bucket.backfilled) {
+                println!("Note that backfilled buckets hold royalties paid before they were first observed, possibly long before.");
+            }
+        }
     }
 
     Ok(())
 }
 
-fn address(root_dir: &Path) -> Result<()> {
-    let wallet = LocalWallet::load_from(root_dir)?;
+/// The root dir of the wallet a storage voucher was redeemed into.
+pub(crate) fn voucher_wallet_dir(root_dir: &Path, voucher_id: &str) -> PathBuf {
+    root_dir.join(STORAGE_VOUCHERS_DIR).join(voucher_id)
+}
+
+async fn issue_voucher(
+    client: &Client,
+    root_dir: &Path,
+    amount: &str,
+    expires_in: u64,
+    address_prefix: Option<&str>,
+    verify_store: bool,
+) -> Result<()> {
+    let max_amount = NanoTokens::from_str(amount)?;
+    if max_amount.is_zero() {
+        bail!("The amount reserved for a voucher can't be zero");
+    }
+    let address_prefix = match address_prefix {
+        Some(prefix) => Some(hex::decode(prefix)?),
+        None => None,
+    };
+    let expiry = SystemTime::now() + Duration::from_secs(expires_in);
+
+    let mut wallet_client = WalletClient::new(client.clone(), LocalWallet::load_from(root_dir)?);
+    let grant = match wallet_client
+        .issue_storage_voucher(max_amount, expiry, address_prefix, verify_store)
+        .await
+    {
+        Ok(grant) => grant,
+        Err(err) => {
+            println!("Failed to issue the voucher: {err}");
+            wallet_client
+                .into_wallet()
+                .store_unconfirmed_spend_requests()?;
+            return Err(err.into());
+        }
+    };
+
+    println!(
+        "Issued voucher {} for {max_amount}, valid for {expires_in}s.",
+        grant.voucher.hash().to_hex()
+    );
+    println!("New wallet balance is {}.", wallet_client.balance());
+    println!("Hand the voucher over to the uploader, to redeem with 'wallet redeem-voucher':");
+    println!("{}", grant.to_hex()?);
+    Ok(())
+}
+
+async fn redeem_voucher(
+    client: &Client,
+    root_dir: &Path,
+    voucher: String,
+    is_file: bool,
+) -> Result<()> {
+    let voucher = if is_file {
+        std::fs::read_to_string(voucher)?
+    } else {
+        voucher
+    };
+    let grant = StorageVoucherGrant::from_hex(&voucher)?;
+    let voucher_id = grant.voucher.hash().to_hex();
+
+    println!("Verifying the voucher with the Network...");
+    let wallet = client
+        .redeem_storage_voucher(&grant, &voucher_wallet_dir(root_dir, &voucher_id))
+        .await?;
+    println!(
+        "Redeemed voucher {voucher_id}, with {} left to pay for storage until {}.",
+        wallet.balance(),
+        chrono::DateTime::<chrono::Utc>::from(grant.voucher.expiry)
+    );
+    println!("Upload with it using 'files upload --voucher {voucher_id}'.");
+    Ok(())
+}
+
+async fn voucher_usage(client: &Client, root_dir: &Path, id: Option<&str>) -> Result<()> {
+    let vouchers: Vec<_> = LocalWallet::load_from(root_dir)?
+        .issued_storage_vouchers()?
+        .into_iter()
+        .filter(|voucher| id.map_or(true, |id| voucher.hash().to_hex() == id))
+        .collect();
+    if vouchers.is_empty() {
+        bail!("No voucher issued by the local wallet was found");
+    }
+
+    for voucher in vouchers {
+        let usage = client.storage_voucher_usage(&voucher).await?;
+        println!("Voucher {}:", voucher.hash().to_hex());
+        println!("  reserved: {}", voucher.max_amount);
+        println!(
+            "  paid for {} chunks: {} ({} for storage, {} of royalties)",
+            usage.chunks_paid,
+            usage.total_paid(),
+            usage.storage_paid,
+            usage.royalties_paid
+        );
+        println!("  unspent: {}", usage.unspent);
+        if !usage.spent_otherwise.is_zero() {
+            println!("  spent without the voucher: {}", usage.spent_otherwise);
+        }
+    }
+    Ok(())
+}
+
+async fn audit_payments(
+    client: &Client,
+    root_dir: &Path,
+    repair: bool,
+    verify_store: bool,
+) -> Result<()> {
+    let wallet_client = WalletClient::new(client.clone(), LocalWallet::load_from(root_dir)?);
+    println!("Checking the content paid for is stored on the Network...");
+    let report = wallet_client.audit_payments().await?;
+
+    for (status, description) in [
+        (PaymentAuditStatus::Stored, "stored"),
+        (
+            PaymentAuditStatus::MissingButQuoteValid,
+            "missing, can be uploaded again without paying again",
+        ),
+        (
+            PaymentAuditStatus::MissingAndExpired,
+            "missing with an expired quote, the payment is lost",
+        ),
+        (
+            PaymentAuditStatus::Unknown,
+            "could not be checked, run the command again to check them",
+        ),
+    ] {
+        let (count, total) = report.totals(status);
+        println!("{count} payments for {total} tokens: {description}");
+    }
+
+    let (repairable, _) = report.totals(PaymentAuditStatus::MissingButQuoteValid);
+    if repairable == 0 {
+        return Ok(());
+    }
+    if !repair {
+        println!(
+            "Run the command again with '--repair' to upload those {repairable} chunks again."
+        );
+        return Ok(());
+    }
+
+    let to_repair: BTreeSet<_> = report.repairable().collect();
+    let chunks: Vec<_> = ChunkManager::new(root_dir)
+        .artifacts_chunks()
+        .into_iter()
+        .filter(|(name, _)| to_repair.contains(name))
+        .collect();
+    let repaired = FilesApi::new(client.clone(), root_dir.to_path_buf())
+        .repair_chunks(&chunks, verify_store)
+        .await?;
+    println!(
+        "Uploaded again {} of the {} chunks still paid for.",
+        repaired.len(),
+        chunks.len()
+    );
+    Ok(())
+}
+
+fn address(root_dir: &Path, qr: &QrArgs) -> Result<()> {
+    let wallet = LocalWallet::load_shared_from(root_dir)?;
     println!("{:?}", wallet.address());
+    qr.output(&wallet.address().to_hex())?;
+    Ok(())
+}
+
+fn addresses(root_dir: &Path, all: bool) -> Result<()> {
+    let wallet = LocalWallet::load_shared_from(root_dir)?;
+    println!("{:?} (current)", wallet.address());
+    if all {
+        for address in wallet.retired_addresses().iter().rev() {
+            println!("{address:?} (retired)");
+        }
+    }
+    Ok(())
+}
+
+fn rotate_key(root_dir: &Path) -> Result<()> {
+    let mut wallet = LocalWallet::load_from(root_dir)?;
+    let retired = wallet.address();
+    let address = wallet.rotate_key()?;
+    println!("Wallet key rotated, the new address is: {address:?}");
+    println!("Tokens sent to the retired address {retired:?} are still received.");
     Ok(())
 }
 
 fn balance(root_dir: &Path) -> Result<NanoTokens> {
-    let wallet = LocalWallet::try_load_from(root_dir)?;
+    let wallet = LocalWallet::try_load_shared_from(root_dir)?;
     let balance = wallet.balance();
     Ok(balance)
 }
@@ -297,6 +906,20 @@ async fn get_faucet(root_dir: &Path, client: &Client, url: String) -> Result<()>
     Ok(())
 }
 
+fn history(root_dir: &Path, format: HistoryFormat, out: Option<&Path>) -> Result<()> {
+    let wallet = LocalWallet::try_load_shared_from(root_dir)?;
+    let _shared_access = wallet.lock_shared()?;
+    match out {
+        Some(path) => {
+            let file = std::fs::File::create(path)?;
+            wallet.export_history(format, std::io::BufWriter::new(file))?;
+            println!("Wallet history written to {path:?}");
+        }
+        None => wallet.export_history(format, std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
 fn deposit(root_dir: &Path, read_from_stdin: bool, cash_note: Option<&str>) -> Result<()> {
     if read_from_stdin {
         return read_cash_note_from_stdin(root_dir);
@@ -401,6 +1024,87 @@ async fn send(
     Ok(())
 }
 
+fn config(
+    root_dir: &Path,
+    auto_split: Option<&str>,
+    denominations: &[String],
+    max_notes: usize,
+    disable_auto_split: bool,
+) -> Result<()> {
+    let wallet = LocalWallet::load_from(root_dir)?;
+
+    if disable_auto_split {
+        wallet.set_auto_split_policy(None)?;
+        println!("Auto split disabled.");
+    } else if let Some(threshold) = auto_split {
+        let mut policy = AutoSplitPolicy::new(NanoTokens::from_str(threshold)?);
+        policy.denominations = denominations
+            .iter()
+            .map(|denomination| NanoTokens::from_str(denomination))
+            .collect::<std::result::Result<_, _>>()?;
+        policy.max_notes = max_notes;
+        wallet.set_auto_split_policy(Some(&policy))?;
+    }
+
+    match wallet.auto_split_policy()? {
+        Some(policy) => {
+            let denominations = if policy.denominations.is_empty() {
+                vec![policy.threshold]
+            } else {
+                policy.denominations
+            };
+            let denominations = format_amounts(&denominations);
+            println!(
+                "Auto split: change above {} is split into cash notes of {denominations}, \
+                up to {} cash notes in the wallet.",
+                policy.threshold, policy.max_notes
+            );
+        }
+        None => println!("Auto split: disabled."),
+    }
+    Ok(())
+}
+
+/// Print the transfer that would be made by `send`, without sending it.
+fn preview_send(amount: &str, to: &str, root_dir: &Path) -> Result<()> {
+    let mut wallet = LocalWallet::load_from(root_dir)?;
+    let amount = NanoTokens::from_str(amount)?;
+    let to = MainPubkey::from_hex(to)?;
+
+    let transfer = wallet.preview_send(vec![(amount, to)])?;
+    println!(
+        "Sending {amount} to {to:?} would spend {} cash note(s).",
+        transfer.tx.inputs.len()
+    );
+    let change: Vec<_> = transfer
+        .change_cash_note
+        .iter()
+        .chain(transfer.split_change_cash_notes.iter())
+        .map(|cash_note| cash_note.value())
+        .collect::<std::result::Result<_, _>>()?;
+    if change.is_empty() {
+        println!("There would be no change.");
+    } else if change.len() == 1 {
+        println!("The change would be a single cash note of {}.", change[0]);
+    } else {
+        println!(
+            "The change would be auto split into {} cash notes: {}",
+            change.len(),
+            format_amounts(&change)
+        );
+    }
+    println!("Nothing was sent.");
+    Ok(())
+}
+
+fn format_amounts(amounts: &[NanoTokens]) -> String {
+    amounts
+        .iter()
+        .map(|amount| amount.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 async fn receive(transfer: String, is_file: bool, client: &Client, root_dir: &Path) -> Result<()> {
     let transfer = if is_file {
         std::fs::read_to_string(transfer)?.trim().to_string()
@@ -448,8 +1152,11 @@ async fn listen_notifs_and_deposit(root_dir: &Path, client: &Client, pk_hex: Str
         Err(err) => return Err(eyre!("Failed to parse hex-encoded public key: {err:?}")),
     };
 
-    let main_pk = wallet.address();
-    let pk = main_pk.public_key();
+    // notifications to the addresses retired by key rotations are ours as well
+    let main_pks: Vec<MainPubkey> = std::iter::once(wallet.address())
+        .chain(wallet.retired_addresses().iter().copied())
+        .collect();
+    let pk = wallet.address().public_key();
 
     client.subscribe_to_topic(ROYALTY_TRANSFER_NOTIF_TOPIC.to_string())?;
     let mut events_receiver = client.events_channel();
@@ -467,11 +1174,15 @@ async fn listen_notifs_and_deposit(root_dir: &Path, client: &Client, pk_hex: Str
                         println!("GossipsubMsg received on topic '{topic}' couldn't be decoded as transfer notif: {err:?}");
                         continue;
                     }
-                    Ok((key, _)) if key != pk => continue,
                     Ok((key, cashnote_redemptions)) => {
+                        let Some(main_pk) =
+                            main_pks.iter().find(|main_pk| main_pk.public_key() == key)
+                        else {
+                            continue;
+                        };
                         println!("New transfer notification received for {key:?}, containing {} CashNoteRedemption/s.", cashnote_redemptions.len());
                         match client
-                            .verify_cash_notes_redemptions(main_pk, &cashnote_redemptions)
+                            .verify_cash_notes_redemptions(*main_pk, &cashnote_redemptions)
                             .await
                         {
                             Err(err) => {
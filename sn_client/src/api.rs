@@ -7,44 +7,69 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
+    audit::{is_valid_spend_at, resolve_split_spend_record, DOUBLE_SPENDS_DIR_NAME},
     chunks::Error as ChunksError,
+    deadline::{run_batch, BatchOutcome, Deadline},
     error::{Error, Result},
-    Client, ClientEvent, ClientEventsChannel, ClientEventsReceiver, ClientRegister, WalletClient,
+    register::{retry_register_creation, NetworkRegisterCreation},
+    Client, ClientBuilder, ClientEvent, ClientEventsChannel, ClientEventsReceiver, ClientRegister,
+    ConnectionStatus, ReconnectPolicy, RegisterReadOptions, SessionCosts, WalletClient,
+    DEFAULT_REGISTER_CREATION_ATTEMPTS,
 };
+#[cfg(feature = "open-metrics")]
+use crate::metrics::Operation;
 use bls::{PublicKey, SecretKey, Signature};
 use bytes::Bytes;
-use futures::future::join_all;
+use futures::{future::join_all, stream::BoxStream, StreamExt};
 use indicatif::ProgressBar;
 use libp2p::{
     identity::Keypair,
-    kad::{Quorum, Record},
+    kad::{KBucketDistance, Quorum, Record, RecordKey},
+    multiaddr::Protocol,
     Multiaddr, PeerId,
 };
 #[cfg(feature = "open-metrics")]
 use prometheus_client::registry::Registry;
 use rand::{thread_rng, Rng};
+use serde::de::DeserializeOwned;
+#[cfg(feature = "network-contacts")]
+use sn_networking::DohResolver;
 use sn_networking::{
-    multiaddr_is_global, Error as NetworkError, GetRecordCfg, GetRecordError, NetworkBuilder,
-    NetworkEvent, PutRecordCfg, VerificationKind, CLOSE_GROUP_SIZE,
+    ChunkReplicationReport, Error as NetworkError, GetRecordCfg, GetRecordError, NetworkBuilder,
+    NetworkEvent, PutRecordCfg, RetryPolicy, VerificationKind, CLOSE_GROUP_SIZE,
 };
 use sn_protocol::{
     error::Error as ProtocolError,
-    messages::ChunkProof,
+    messages::{Query, QueryResponse, Request, Response, SignedRegisterClaim},
     storage::{
         try_deserialize_record, try_serialize_record, Chunk, ChunkAddress, RecordHeader,
         RecordKind, RegisterAddress, SpendAddress,
     },
     NetworkAddress, PrettyPrintRecordKey,
 };
-use sn_registers::SignedRegister;
-use sn_transfers::{CashNote, CashNoteRedemption, MainPubkey, NanoTokens, Payment, SignedSpend};
+use sn_registers::{EntryHash, Permissions, SignedRegister};
+use sn_transfers::{
+    is_genesis_parent_tx, CashNote, CashNoteRedemption, MainPubkey, NanoTokens, Payment,
+    SignedSpend, WalletError, WalletResult, GENESIS_CASHNOTE, MAX_SPEND_SIZE,
+};
+use std::sync::Mutex;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
+    future::Future,
     num::NonZeroUsize,
-    path::PathBuf,
-    time::Duration,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    task::{spawn, AbortHandle, JoinHandle},
+    time::MissedTickBehavior,
 };
-use tokio::task::spawn;
+use tokio_util::sync::CancellationToken;
 use tracing::trace;
 use xor_name::XorName;
 
@@ -54,23 +79,179 @@ const CONNECTION_TIMEOUT: Duration = Duration::from_secs(180);
 /// The timeout duration for the client to receive any response from the network.
 const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// The maximum time to wait for the background tasks to terminate on shutdown.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of Registers published or verified in parallel by `create_registers_batch`.
+const REGISTERS_BATCH_CONCURRENCY: usize = 8;
+
+/// How many chunk and register operations a client runs at once, by default, across all its
+/// clones. See `Client::set_max_concurrent_ops`.
+pub const DEFAULT_MAX_CONCURRENT_OPS: usize = 16;
+
+/// How many transactions of a generation an audit goes through at once, by default. See
+/// `Client::set_audit_concurrency`.
+pub const DEFAULT_AUDIT_CONCURRENCY: usize = 8;
+
+/// How a record is read from the network.
+///
+/// The `quorum` is the number of nodes of the record's close group that must return the same copy
+/// for the read to succeed, `Quorum::All` being the whole close group. Reads that can't reach it
+/// fail with `GetRecordError::NotEnoughCopies`. If `re_attempt` is set, they are retried as set by
+/// the client's `RetryPolicy`, failing with `NetworkError::RetriesExhausted` once out of attempts.
+///
+/// The expected holders of a chunk (see `get_chunk` with `show_holders`) are unrelated to the
+/// quorum: holders missing from the answers are only logged, and don't make the read fail.
+///
+/// With a `timeout` set, a read taking longer than it, retries included, fails with
+/// `Error::OperationTimedOut`. By default, reads are only bounded by their retries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadCfg {
+    pub quorum: Quorum,
+    pub re_attempt: bool,
+    pub timeout: Option<Duration>,
+}
+
+impl ReadCfg {
+    /// Read with the given quorum, retrying on failures, without timeout.
+    pub fn new(quorum: Quorum) -> Self {
+        Self {
+            quorum,
+            re_attempt: true,
+            timeout: None,
+        }
+    }
+
+    /// Fail the read with `Error::OperationTimedOut` if it takes longer than the given timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn get_record_cfg(
+        &self,
+        expected_holders: HashSet<PeerId>,
+        retry_policy: RetryPolicy,
+    ) -> GetRecordCfg {
+        GetRecordCfg {
+            get_quorum: self.quorum,
+            retry_policy: if self.re_attempt {
+                retry_policy
+            } else {
+                RetryPolicy::none()
+            },
+            target_record: None,
+            expected_holders,
+        }
+    }
+}
+
+/// The background tasks spawned by a `Client`.
+/// Shared by all the clones of the client, they get cancelled once the last one is dropped.
+#[derive(Default)]
+pub(crate) struct ClientTasks {
+    swarm_driver: Mutex<Option<JoinHandle<()>>>,
+    helpers: Mutex<Vec<JoinHandle<()>>>,
+    cancellation: CancellationToken,
+}
+
+impl ClientTasks {
+    fn set_swarm_driver(&self, handle: JoinHandle<()>) {
+        if let Ok(mut swarm_driver) = self.swarm_driver.lock() {
+            *swarm_driver = Some(handle);
+        }
+    }
+
+    /// Spawn a background task, which stops once the tasks are cancelled.
+    pub(crate) fn spawn_helper(
+        &self,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) -> AbortHandle {
+        let cancellation = self.cancellation.clone();
+        let handle = spawn(async move {
+            tokio::select! {
+                _ = cancellation.cancelled() => {}
+                _ = task => {}
+            }
+        });
+        let abort_handle = handle.abort_handle();
+        if let Ok(mut helpers) = self.helpers.lock() {
+            helpers.push(handle);
+        }
+        abort_handle
+    }
+
+    fn take(&self) -> (Option<JoinHandle<()>>, Vec<JoinHandle<()>>) {
+        let swarm_driver = self
+            .swarm_driver
+            .lock()
+            .map(|mut swarm_driver| swarm_driver.take())
+            .unwrap_or_default();
+        let helpers = self
+            .helpers
+            .lock()
+            .map(|mut helpers| std::mem::take(&mut *helpers))
+            .unwrap_or_default();
+        (swarm_driver, helpers)
+    }
+}
+
+impl Drop for ClientTasks {
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+        let (swarm_driver, helpers) = self.take();
+        let running: Vec<_> = swarm_driver
+            .into_iter()
+            .chain(helpers)
+            .filter(|handle| !handle.is_finished())
+            .collect();
+        if !running.is_empty() {
+            warn!(
+                "Client dropped without being shut down, aborting its {} background tasks",
+                running.len()
+            );
+            for handle in running {
+                handle.abort();
+            }
+        }
+    }
+}
+
 impl Client {
     /// Instantiate a new client.
     ///
     /// Optionally specify the maximum time the client will wait for a connection to the network before timing out.
     /// Defaults to 180s
+    ///
+    /// Prefer the [`ClientBuilder`], which this is a shorthand for.
     pub async fn new(
         signer: SecretKey,
         peers: Option<Vec<Multiaddr>>,
         enable_gossip: bool,
         connection_timeout: Option<Duration>,
     ) -> Result<Self> {
-        // If any of our contact peers has a global address, we'll assume we're in a global network.
-        let local = match peers {
-            Some(ref peers) => !peers.iter().any(multiaddr_is_global),
-            None => true,
-        };
+        let mut builder = ClientBuilder::default()
+            .signer(signer)
+            .peers(peers.unwrap_or_default())
+            .enable_gossip(enable_gossip);
+        if let Some(connection_timeout) = connection_timeout {
+            builder = builder.connection_timeout(connection_timeout);
+        }
+        builder.build().await
+    }
 
+    /// Connect a new client to the network, as set up by the `ClientBuilder`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn connect(
+        signer: SecretKey,
+        peers: Option<Vec<Multiaddr>>,
+        local: bool,
+        enable_gossip: bool,
+        connection_timeout: Option<Duration>,
+        headless: bool,
+        reconnect_policy: ReconnectPolicy,
+        #[cfg(feature = "network-contacts")] doh_resolver: Option<DohResolver>,
+    ) -> Result<Self> {
         info!("Startup a client with peers {peers:?} and local {local:?} flag");
         info!("Starting Kad swarm in client mode...");
 
@@ -80,6 +261,10 @@ impl Client {
         if enable_gossip {
             network_builder.enable_gossip();
         }
+        #[cfg(feature = "network-contacts")]
+        if let Some(doh_resolver) = doh_resolver {
+            network_builder.doh_resolver(doh_resolver);
+        }
 
         #[cfg(feature = "open-metrics")]
         network_builder.metrics_registry(Registry::default());
@@ -87,13 +272,29 @@ impl Client {
         let (network, mut network_event_receiver, swarm_driver) = network_builder.build_client()?;
         info!("Client constructed network and swarm_driver");
         let events_channel = ClientEventsChannel::default();
+        let tasks = Arc::new(ClientTasks::default());
 
         let client = Self {
             network: network.clone(),
             events_channel,
             signer,
             peers_added: 0,
-            progress: Some(Self::setup_connection_progress()),
+            busy_responses: Arc::new(AtomicUsize::new(0)),
+            connectivity: Default::default(),
+            genesis_verified: Arc::new(AtomicBool::new(false)),
+            progress: (!headless).then(Self::setup_connection_progress),
+            cancellation: tasks.cancellation.clone(),
+            tasks,
+            register_claim_nonce: thread_rng().gen(),
+            retry_policy: RetryPolicy::default(),
+            register_creation_attempts: DEFAULT_REGISTER_CREATION_ATTEMPTS,
+            standby: Default::default(),
+            ops_limiter: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_OPS)),
+            audit_concurrency: DEFAULT_AUDIT_CONCURRENCY,
+            session_costs: Default::default(),
+            topic_subscriptions: Default::default(),
+            #[cfg(feature = "open-metrics")]
+            metrics: Default::default(),
         };
 
         // subscribe to our events channel first, so we don't have intermittent
@@ -101,14 +302,15 @@ impl Client {
         // (eg, if PeerAdded happens faster than our events channel is created)
         let mut client_events_rx = client.events_channel();
 
-        let _swarm_driver = spawn({
+        client.tasks.set_swarm_driver(spawn({
             trace!("Starting up client swarm_driver");
             swarm_driver.run()
-        });
+        }));
 
         // spawn task to dial to the given peers
         let network_clone = network.clone();
-        let _handle = spawn(async move {
+        let initial_peers = peers.clone().unwrap_or_default();
+        let _ = client.tasks.spawn_helper(async move {
             if let Some(peers) = peers {
                 for addr in peers {
                     trace!(%addr, "dialing initial peer");
@@ -121,11 +323,30 @@ impl Client {
         });
 
         // spawn task to wait for NetworkEvent and check for inactivity
-        let mut client_clone = client.clone();
-        let _event_handler = spawn(async move {
+        // it doesn't share the tasks, otherwise they'd never be aborted on drop
+        let mut client_clone = Client {
+            tasks: Default::default(),
+            ..client.clone()
+        };
+        let _ = client.tasks.spawn_helper(async move {
+            let mut redial_interval = tokio::time::interval(reconnect_policy.interval);
+            redial_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
             loop {
-                match tokio::time::timeout(INACTIVITY_TIMEOUT, network_event_receiver.recv()).await
-                {
+                let reconnecting = client_clone
+                    .connectivity
+                    .lock()
+                    .map(|connectivity| connectivity.is_reconnecting())
+                    .unwrap_or(false);
+                let next_event =
+                    tokio::time::timeout(INACTIVITY_TIMEOUT, network_event_receiver.recv());
+                let event = tokio::select! {
+                    event = next_event => event,
+                    _ = redial_interval.tick(), if reconnecting => {
+                        client_clone.redial(&initial_peers, &reconnect_policy).await;
+                        continue;
+                    }
+                };
+                match event {
                     Ok(event) => {
                         let the_event = match event {
                             Some(the_event) => the_event,
@@ -148,12 +369,22 @@ impl Client {
                     }
                     Err(_elapse_err) => {
                         debug!("Client inactivity... waiting for a network event");
-                        if let Err(error) = client_clone
-                            .events_channel
-                            .broadcast(ClientEvent::InactiveClient(INACTIVITY_TIMEOUT))
-                        {
+                        let (event, reconnecting) = match client_clone.connectivity.lock() {
+                            Ok(mut connectivity) => (
+                                connectivity.inactivity_event(INACTIVITY_TIMEOUT),
+                                connectivity.start_reconnecting(&reconnect_policy),
+                            ),
+                            Err(_) => (ClientEvent::Idle(INACTIVITY_TIMEOUT), None),
+                        };
+                        if let Err(error) = client_clone.events_channel.broadcast(event) {
                             error!("Error broadcasting inactive client event: {error}");
                         }
+                        if let Some(event) = reconnecting {
+                            warn!("The client has been disconnected for too long, reconnecting");
+                            // redial straight away
+                            redial_interval.reset_immediately();
+                            client_clone.events_channel.notify(event);
+                        }
                     }
                 }
             }
@@ -181,7 +412,11 @@ impl Client {
                         info!("Client connected to the Network {is_connected:?}.");
                         break;
                     }
-                    Ok(ClientEvent::InactiveClient(timeout)) => {
+                    Ok(ClientEvent::InitialPeersFound { found, needed }) => {
+                        info!("{found}/{needed} initial peers found.");
+                        continue;
+                    }
+                    Ok(ClientEvent::Idle(timeout)) => {
                         if is_connected {
                             info!("The client was inactive for {timeout:?}.");
                         } else {
@@ -189,7 +424,17 @@ impl Client {
                         }
                         continue;
                     }
-                    Ok(ClientEvent::GossipsubMsg { .. }) => {}
+                    Ok(
+                        ClientEvent::Disconnected { .. }
+                        | ClientEvent::Reconnecting { .. }
+                        | ClientEvent::Reconnected { .. }
+                        | ClientEvent::NodeBusy { .. }
+                        | ClientEvent::GossipsubMsg { .. }
+                        | ClientEvent::ChunkStored { .. }
+                        | ClientEvent::ChunkStoreFailed { .. }
+                        | ClientEvent::PaymentMade { .. }
+                        | ClientEvent::UploadBatchCompleted { .. },
+                    ) => {}
                     Err(err) => {
                         error!("Unexpected error during client startup {err:?}");
                         println!("Unexpected error during client startup {err:?}");
@@ -202,7 +447,7 @@ impl Client {
         // The above loop breaks if `ConnectedToNetwork` is received, but we might need the
         // receiver to still be active for us to not get any error if any other event is sent
         let mut client_events_rx = client.events_channel();
-        spawn(async move {
+        let _ = client.tasks.spawn_helper(async move {
             loop {
                 let _ = client_events_rx.recv().await;
             }
@@ -210,6 +455,110 @@ impl Client {
         Ok(client)
     }
 
+    /// Shut the client down, stopping its swarm driver and its background tasks.
+    ///
+    /// The operations in flight, on this client or any of its clones, are cancelled with
+    /// `Error::ClientShutDown`. Waits up to 10 seconds for the swarm driver to stop, then for the
+    /// background tasks, aborting them past that. Any other clone of this client won't be able to
+    /// reach the network afterwards.
+    ///
+    /// With the `open-metrics` feature, the final metrics of the client are then written to the
+    /// logs, as they can't be scraped anymore once the process exits.
+    pub async fn shutdown(self) -> Result<()> {
+        info!("Shutting down the client");
+        self.cancellation.cancel();
+        let (swarm_driver, helpers) = self.tasks.take();
+
+        if let Some(mut swarm_driver) = swarm_driver {
+            let stopped = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+                match self.network.shutdown().await {
+                    Ok(()) => {
+                        let _ = (&mut swarm_driver).await;
+                        true
+                    }
+                    Err(err) => {
+                        warn!("Failed to signal the swarm driver to stop: {err}");
+                        false
+                    }
+                }
+            })
+            .await
+            .unwrap_or_else(|_| {
+                warn!("The swarm driver did not stop within {SHUTDOWN_TIMEOUT:?}");
+                false
+            });
+
+            if !stopped {
+                // wait for the driver to be dropped, for it not to outlive the client
+                swarm_driver.abort();
+                let _ = swarm_driver.await;
+            }
+        }
+
+        let abort_handles: Vec<_> = helpers.iter().map(JoinHandle::abort_handle).collect();
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, join_all(helpers))
+            .await
+            .is_err()
+        {
+            warn!("The client background tasks did not stop within {SHUTDOWN_TIMEOUT:?}, aborting them");
+            for handle in abort_handles {
+                handle.abort();
+            }
+        }
+
+        // flush the metrics, including the cancelled operations, while we still can
+        #[cfg(feature = "open-metrics")]
+        match self.metrics.render() {
+            Ok(text) => info!("Client metrics at shutdown:\n{text}"),
+            Err(err) => warn!("Failed to render the client metrics at shutdown: {err}"),
+        }
+
+        info!("Client shut down");
+        Ok(())
+    }
+
+    /// Returns those of the given peers that made it into our routing table, i.e. that we could
+    /// actually reach. Peers without a `/p2p/<peer_id>` component are left out.
+    pub async fn reachable_peers(&self, peers: &[Multiaddr]) -> Result<Vec<Multiaddr>> {
+        let known_peers: HashSet<PeerId> = self
+            .network
+            .get_all_local_peers()
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(peers
+            .iter()
+            .filter(|addr| {
+                addr.iter().any(|protocol| {
+                    matches!(protocol, Protocol::P2p(peer_id) if known_peers.contains(&peer_id))
+                })
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// The peers closest to the address, the client excluded, sorted by their distance to it.
+    /// At most `CLOSE_GROUP_SIZE` of them are returned.
+    ///
+    /// This is best-effort: the peers are those the network knows of at the time of the lookup,
+    /// which churn may change right after.
+    pub async fn get_closest_peers(&self, addr: &NetworkAddress) -> Result<Vec<PeerId>> {
+        Ok(self.network.client_get_closest_peers(addr).await?)
+    }
+
+    /// Like `get_closest_peers`, with each peer paired with its XOR distance to the address.
+    pub async fn get_closest_peers_with_distance(
+        &self,
+        addr: &NetworkAddress,
+    ) -> Result<Vec<(PeerId, KBucketDistance)>> {
+        let peers = self.get_closest_peers(addr).await?;
+        Ok(peers
+            .into_iter()
+            .map(|peer| (peer, addr.distance(&NetworkAddress::from_peer(peer))))
+            .collect())
+    }
+
     /// Set up our initial progress bar for network connectivity
     fn setup_connection_progress() -> ProgressBar {
         // Network connection progress bar
@@ -229,12 +578,16 @@ impl Client {
             NetworkEvent::PeerAdded(peer_id, _connected_peer) => {
                 self.peers_added += 1;
                 debug!("PeerAdded: {peer_id}");
+                self.standby.peer_added(&peer_id);
 
                 // In case client running in non-local-discovery mode,
                 // it may take some time to fill up the RT.
                 // To avoid such delay may fail the query with RecordNotFound,
                 // wait till certain amount of peers populated into RT
                 if self.peers_added >= CLOSE_GROUP_SIZE {
+                    if let Ok(mut connectivity) = self.connectivity.lock() {
+                        connectivity.set_connected();
+                    }
                     if let Some(progress) = &self.progress {
                         progress.finish_with_message("Connected to the Network");
                         // Remove the progress bar
@@ -248,6 +601,10 @@ impl Client {
                         "{}/{CLOSE_GROUP_SIZE} initial peers found.",
                         self.peers_added
                     );
+                    self.events_channel.notify(ClientEvent::InitialPeersFound {
+                        found: self.peers_added,
+                        needed: CLOSE_GROUP_SIZE,
+                    });
 
                     if let Some(progress) = &self.progress {
                         progress.set_message(format!(
@@ -257,8 +614,48 @@ impl Client {
                     }
                 }
             }
+            NetworkEvent::PeerRemoved(peer_id, _connected_peers) => {
+                debug!("PeerRemoved: {peer_id}");
+                self.standby.peer_departed(&peer_id);
+            }
+            NetworkEvent::PeerConnected(peer_id, live_peers) => {
+                trace!("Connected to {peer_id:?}, {live_peers} live peers");
+                let reconnected = match self.connectivity.lock() {
+                    Ok(mut connectivity) => connectivity.peer_connected(live_peers),
+                    Err(_) => None,
+                };
+                if let Some(event) = reconnected {
+                    info!("The client reconnected to the network: {event:?}");
+                    // nobody listening is no reason to fail handling the network event
+                    self.events_channel.notify(event);
+                }
+            }
+            NetworkEvent::PeerDisconnected(peer_id, live_peers) => {
+                // a closed connection may be re-established, only `PeerRemoved` says the peer
+                // left the network
+                trace!("Disconnected from {peer_id:?}, {live_peers} live peers left");
+                let disconnected = match self.connectivity.lock() {
+                    Ok(mut connectivity) => {
+                        connectivity.peer_disconnected(live_peers, SystemTime::now())
+                    }
+                    Err(_) => None,
+                };
+                if let Some(event) = disconnected {
+                    warn!("The client lost the connections to all its peers");
+                    self.events_channel.notify(event);
+                }
+            }
+            NetworkEvent::PeerBusy { peer, retry_after } => {
+                let total_busy_responses = self.busy_responses.fetch_add(1, Ordering::SeqCst) + 1;
+                debug!("Peer {peer:?} is busy, asking to retry after {retry_after:?}");
+                self.events_channel.broadcast(ClientEvent::NodeBusy {
+                    retry_after,
+                    total_busy_responses,
+                })?;
+            }
             NetworkEvent::GossipsubMsgReceived { topic, msg }
             | NetworkEvent::GossipsubMsgPublished { topic, msg } => {
+                self.topic_subscriptions.dispatch(&topic, &msg);
                 self.events_channel
                     .broadcast(ClientEvent::GossipsubMsg { topic, msg })?;
             }
@@ -268,6 +665,168 @@ impl Client {
         Ok(())
     }
 
+    /// Redial the initial peers, or if there were none, the peers of our routing table, while
+    /// reconnecting to the network.
+    async fn redial(&self, initial_peers: &[Multiaddr], policy: &ReconnectPolicy) {
+        let attempt = match self.connectivity.lock() {
+            Ok(mut connectivity) => connectivity.next_reconnect_attempt(policy),
+            Err(_) => None,
+        };
+        let Some(attempt) = attempt else {
+            warn!(
+                "Failed to reconnect after {} attempts, giving up for now",
+                policy.attempts
+            );
+            return;
+        };
+
+        let peers = if initial_peers.is_empty() {
+            match self.network.get_routing_table_addrs().await {
+                Ok(peers) => peers
+                    .into_iter()
+                    .flat_map(|(peer_id, addrs)| {
+                        addrs
+                            .into_iter()
+                            .map(move |addr| addr.with(Protocol::P2p(peer_id)))
+                    })
+                    .collect(),
+                Err(err) => {
+                    error!("Failed to get the peers to redial: {err}");
+                    vec![]
+                }
+            }
+        } else {
+            initial_peers.to_vec()
+        };
+
+        info!(
+            "Reconnecting, attempt {attempt}/{}: redialling {} peers",
+            policy.attempts,
+            peers.len()
+        );
+        for addr in peers {
+            if let Err(err) = self.network.dial(addr.clone()).await {
+                debug!("Failed to redial {addr}: {err:?}");
+            }
+        }
+    }
+
+    /// The number of responses received from nodes asking us to back off as they are busy.
+    pub fn busy_responses(&self) -> usize {
+        self.busy_responses.load(Ordering::SeqCst)
+    }
+
+    /// The state of the connection to the network, telling an idle client
+    /// from one that lost all its peers.
+    pub fn connection_status(&self) -> ConnectionStatus {
+        self.connectivity
+            .lock()
+            .map(|connectivity| connectivity.status())
+            .unwrap_or(ConnectionStatus::Connecting)
+    }
+
+    /// The policy the network operations of the client are retried with.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Set the policy the network operations of the client are retried with, such as storing
+    /// chunks, spends or registers, and reading them. Registers and wallets created from the
+    /// client afterwards inherit it.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// How many times a Register is published, and paid for again if its payment expired,
+    /// before giving up on verifying it is stored.
+    pub fn register_creation_attempts(&self) -> usize {
+        self.register_creation_attempts
+    }
+
+    /// Set how many times a Register is published before giving up on verifying it is stored,
+    /// at least once.
+    pub fn set_register_creation_attempts(&mut self, attempts: usize) {
+        self.register_creation_attempts = attempts.max(1);
+    }
+
+    /// Set how many chunk and register operations, fetching or storing them, the client runs at
+    /// once, at least one. The others wait for a slot before hitting the network.
+    ///
+    /// The limit is shared by the clones of the client made afterwards, so it bounds the
+    /// operations of all the `FilesApi`, `FilesUpload` and `FilesDownload` built from them,
+    /// whatever their batch sizes: a batch larger than the limit only queues its operations.
+    /// Clones made before keep the previous limit.
+    pub fn set_max_concurrent_ops(&mut self, max_concurrent_ops: usize) {
+        self.ops_limiter = Arc::new(Semaphore::new(max_concurrent_ops.max(1)));
+    }
+
+    /// How many transactions of a generation `Client::follow_spend` and `Client::verify_spend`
+    /// go through at once, fetching their spends concurrently.
+    pub fn audit_concurrency(&self) -> usize {
+        self.audit_concurrency
+    }
+
+    /// Set how many transactions of a generation an audit goes through at once, at least one.
+    pub fn set_audit_concurrency(&mut self, audit_concurrency: usize) {
+        self.audit_concurrency = audit_concurrency.max(1);
+    }
+
+    /// The fees paid to the network so far, by this client and its clones, since it connected
+    /// or the costs were last reset. Only the payments made through a `WalletClient` count,
+    /// not the tokens sent to other wallets.
+    pub fn session_costs(&self) -> SessionCosts {
+        self.session_costs
+            .lock()
+            .map(|costs| *costs)
+            .unwrap_or_default()
+    }
+
+    /// Start accumulating the fees paid from zero again, returning the costs up to now.
+    pub fn reset_session_costs(&self) -> SessionCosts {
+        self.session_costs
+            .lock()
+            .map(|mut costs| std::mem::take(&mut *costs))
+            .unwrap_or_default()
+    }
+
+    /// Add a payment to the session costs, returning the running totals.
+    pub(crate) fn record_session_payment(
+        &self,
+        storage_cost: NanoTokens,
+        royalties: NanoTokens,
+    ) -> SessionCosts {
+        self.session_costs
+            .lock()
+            .map(|mut costs| {
+                costs.record_payment(storage_cost, royalties);
+                *costs
+            })
+            .unwrap_or_default()
+    }
+
+    /// Wait for a slot among the chunk and register operations the client runs at once, which
+    /// is freed once the permit is dropped.
+    ///
+    /// Only taken around the requests to the network themselves, never while holding another
+    /// one, so operations built on top of others can't deadlock waiting on their own slots.
+    pub(crate) async fn acquire_op_permit(&self) -> Option<SemaphorePermit<'_>> {
+        // the limiter is never closed
+        self.ops_limiter.acquire().await.ok()
+    }
+
+    /// Run the given operation, failing with `Error::ClientShutDown` if the client is shut down
+    /// before it completes.
+    pub(crate) async fn cancellable<T>(
+        &self,
+        operation: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        tokio::select! {
+            biased;
+            _ = self.cancellation.cancelled() => Err(Error::ClientShutDown),
+            result = operation => result,
+        }
+    }
+
     /// Get the client events channel.
     pub fn events_channel(&self) -> ClientEventsReceiver {
         self.events_channel.subscribe()
@@ -288,35 +847,74 @@ impl Client {
         self.signer.public_key()
     }
 
+    /// Return the nonce this client claims Registers under, telling it apart from the other
+    /// clients of the same owner.
+    pub fn register_claim_nonce(&self) -> u64 {
+        self.register_claim_nonce
+    }
+
     /// Get a register from network
     pub async fn get_signed_register_from_network(
         &self,
         address: RegisterAddress,
         is_verifying: bool,
     ) -> Result<SignedRegister> {
-        let key = NetworkAddress::from_register_address(address).to_record_key();
         let quorum = if is_verifying {
             Quorum::N(NonZeroUsize::new(2).ok_or(Error::NonZeroUsizeWasInitialisedAsZero)?)
         } else {
             Quorum::One
         };
-        let get_cfg = GetRecordCfg {
-            get_quorum: quorum,
-            re_attempt: true,
-            target_record: None,
-            expected_holders: Default::default(),
-        };
+        self.get_signed_register_from_network_with_cfg(address, ReadCfg::new(quorum))
+            .await
+    }
+
+    /// Get a register from network, reading it as set by the `read_cfg`.
+    ///
+    /// See `get_signed_register_from_network`.
+    pub async fn get_signed_register_from_network_with_cfg(
+        &self,
+        address: RegisterAddress,
+        read_cfg: ReadCfg,
+    ) -> Result<SignedRegister> {
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let result = self
+            .fetch_signed_register_from_network(address, read_cfg)
+            .await;
+        #[cfg(feature = "open-metrics")]
+        self.metrics
+            .record_operation(Operation::RegisterGet, result.is_ok(), start.elapsed());
+        result
+    }
 
-        let maybe_record = self.network.get_record_from_network(key, &get_cfg).await;
+    async fn fetch_signed_register_from_network(
+        &self,
+        address: RegisterAddress,
+        read_cfg: ReadCfg,
+    ) -> Result<SignedRegister> {
+        let key = NetworkAddress::from_register_address(address).to_record_key();
+        let get_cfg = read_cfg.get_record_cfg(Default::default(), self.retry_policy);
+
+        self.standby.record_lookup();
+        let _permit = self.acquire_op_permit().await;
+        let maybe_record = self
+            .cancellable(with_timeout(
+                NetworkAddress::from_register_address(address),
+                read_cfg.timeout,
+                self.network.get_record_from_network(key, &get_cfg),
+            ))
+            .await?;
         let record = match &maybe_record {
             Ok(r) => r,
-            Err(NetworkError::GetRecordError(GetRecordError::SplitRecord { result_map })) => {
-                return merge_split_register_records(address, result_map)
-            }
-            Err(e) => {
-                warn!("Failed to get record at {address:?} from the network: {e:?}");
-                return Err(ProtocolError::RegisterNotFound(Box::new(address)).into());
-            }
+            Err(e) => match e.last_cause() {
+                NetworkError::GetRecordError(GetRecordError::SplitRecord { result_map }) => {
+                    return merge_split_register_records(address, result_map)
+                }
+                _ => {
+                    warn!("Failed to get record at {address:?} from the network: {e:?}");
+                    return Err(ProtocolError::RegisterNotFound(Box::new(address)).into());
+                }
+            },
         };
 
         debug!(
@@ -329,14 +927,105 @@ impl Client {
         Ok(register)
     }
 
-    /// Retrieve a Register from the network.
-    pub async fn get_register(&self, address: RegisterAddress) -> Result<ClientRegister> {
-        info!("Retrieving a Register replica at {address}");
-        ClientRegister::retrieve(self.clone(), address).await
+    /// Get a register from every reachable node of its close group, rather than from a quorum,
+    /// merging all the valid copies.
+    ///
+    /// Returns the merged register along with its divergence score: the number of distinct
+    /// copies held by the nodes, which is 1 if they all agree.
+    pub async fn get_signed_register_merging_all(
+        &self,
+        address: RegisterAddress,
+    ) -> Result<(SignedRegister, usize)> {
+        let net_addr = NetworkAddress::from_register_address(address);
+        let key = net_addr.to_record_key();
+        let close_nodes = self.close_group(&net_addr).await?;
+
+        let request = Request::Query(Query::GetReplicatedRecord {
+            requester: NetworkAddress::from_peer(self.network.peer_id),
+            key: net_addr,
+        });
+        let responses = self
+            .network
+            .send_and_get_responses(&close_nodes, &request, true)
+            .await;
+
+        // group the copies by content, to count the distinct views
+        let mut copies: HashMap<XorName, (Record, HashSet<PeerId>)> = HashMap::new();
+        for (peer, response) in responses {
+            match response {
+                Ok(Response::Query(QueryResponse::GetReplicatedRecord(Ok((_, value))))) => {
+                    let content_hash = XorName::from_content(&value);
+                    let (_, holders) = copies.entry(content_hash).or_insert_with(|| {
+                        let record = Record {
+                            key: key.clone(),
+                            value: value.to_vec(),
+                            publisher: None,
+                            expires: None,
+                        };
+                        (record, HashSet::new())
+                    });
+                    let _ = holders.insert(peer);
+                }
+                other => trace!("No copy of Register {address:?} from {peer:?}: {other:?}"),
+            }
+        }
+
+        if copies.is_empty() {
+            warn!("No node of the close group holds the Register {address:?}");
+            return Err(ProtocolError::RegisterNotFound(Box::new(address)).into());
+        }
+        let divergence = copies.len();
+        if divergence > 1 {
+            info!("Merging {divergence} diverging copies of the Register {address:?}");
+        }
+
+        let register = merge_split_register_records(address, &copies)?;
+        Ok((register, divergence))
+    }
+
+    /// Retrieve a Register from the network, as set by the read options.
+    pub async fn get_register(
+        &self,
+        address: RegisterAddress,
+        options: RegisterReadOptions,
+    ) -> Result<ClientRegister> {
+        info!("Retrieving a Register replica at {address} with {options:?}");
+        with_timeout(
+            NetworkAddress::from_register_address(address),
+            options.timeout,
+            ClientRegister::retrieve(self.clone(), address, options.consistency),
+        )
+        .await?
+    }
+
+    /// Retrieve several Registers from the network, as set by the read options, fetching up to
+    /// `max_concurrency` of them at once until the deadline.
+    ///
+    /// See `get_chunks_with_deadline`.
+    pub async fn get_registers_with_deadline(
+        &self,
+        addrs: &[RegisterAddress],
+        options: RegisterReadOptions,
+        max_concurrency: usize,
+        deadline: Deadline,
+    ) -> BatchOutcome<RegisterAddress, ClientRegister> {
+        info!(
+            "Retrieving {} Registers with {options:?}, {max_concurrency} at a time, for {:?} at most",
+            addrs.len(),
+            deadline.remaining()
+        );
+        run_batch(addrs, max_concurrency, Some(deadline), |address| {
+            self.get_register(address, options)
+        })
+        .await
     }
 
     /// Create a new Register on the Network.
-    /// Tops up payments and retries if necessary and verification failed
+    ///
+    /// With `verify_store`, the Register is published again until it verifies as stored, at most
+    /// `Client::register_creation_attempts` times in total, failing with
+    /// `Error::RegisterCreationVerificationFailed` after that. It is only paid for again once
+    /// the payment made for it can't be used anymore.
     pub async fn create_and_pay_for_register(
         &self,
         address: XorName,
@@ -344,37 +1033,152 @@ impl Client {
         verify_store: bool,
     ) -> Result<(ClientRegister, NanoTokens, NanoTokens)> {
         info!("Instantiating a new Register replica with address {address:?}");
-        let (reg, mut total_cost, mut total_royalties) =
+        let (reg, storage_cost, royalties_fees) =
             ClientRegister::create_online(self.clone(), address, wallet_client, false).await?;
+        if !verify_store {
+            return Ok((reg, storage_cost, royalties_fees));
+        }
 
-        debug!("{address:?} Created in theorryyyyy");
-        let reg_address = reg.address();
-        if verify_store {
-            debug!("WE SHOULD VERRRRIFYING");
-            let mut stored = self.verify_register_stored(*reg_address).await.is_ok();
+        let mut creation = NetworkRegisterCreation::new(&reg, wallet_client);
+        let (storage_cost, royalties_fees) = retry_register_creation(
+            &mut creation,
+            self.register_creation_attempts,
+            (storage_cost, royalties_fees),
+        )
+        .await?;
+        Ok((reg, storage_cost, royalties_fees))
+    }
+
+    /// Retrieve the Register with the given meta and our key from the Network, creating it with
+    /// the given permissions if it doesn't exist yet.
+    ///
+    /// Safe to race against other clients sharing our key: only one of them pays for the
+    /// Register, the others retrieve it once created. Returns the Register, whether it was
+    /// created by this call, and the storage cost plus royalties paid for it.
+    pub async fn get_or_create_register(
+        &self,
+        meta: XorName,
+        wallet_client: &mut WalletClient,
+        perms: Permissions,
+        verify_store: bool,
+    ) -> Result<(ClientRegister, bool, NanoTokens)> {
+        info!("Getting the Register with meta {meta:?}, creating it if absent");
+        ClientRegister::get_or_create_online(self.clone(), meta, wallet_client, perms, verify_store)
+            .await
+    }
+
+    /// Create several new Registers on the Network, paying for all of them in a single payment.
+    ///
+    /// The Registers are published concurrently and, if `verify_store` is set, verified
+    /// once they have all been published. Returns the result for each Register, in the same
+    /// order as `metas`, along with the total storage cost and royalties paid.
+    pub async fn create_registers_batch(
+        &self,
+        metas: &[XorName],
+        wallet_client: &mut WalletClient,
+        verify_store: bool,
+    ) -> Result<(Vec<Result<ClientRegister>>, NanoTokens, NanoTokens)> {
+        info!("Instantiating a batch of {} new Registers", metas.len());
+        let registers = metas
+            .iter()
+            .map(|meta| ClientRegister::create(self.clone(), *meta))
+            .collect::<Result<Vec<_>>>()?;
+
+        // claim the Registers first, so we don't pay for those being uploaded by someone else
+        let claims: Vec<_> = futures::stream::iter(registers.iter())
+            .map(|reg| reg.claim_creation())
+            .buffered(REGISTERS_BATCH_CONCURRENCY)
+            .collect()
+            .await;
+        let net_addrs: Vec<_> = registers
+            .iter()
+            .map(|reg| NetworkAddress::from_register_address(*reg.address()))
+            .collect();
+        let claimed_addrs: Vec<_> = net_addrs
+            .iter()
+            .zip(&claims)
+            .filter(|(_, claim)| claim.is_ok())
+            .map(|(net_addr, _)| net_addr.clone())
+            .collect();
+
+        let ((storage_cost, royalties_fees), (_payee_map, skipped)) = if claimed_addrs.is_empty() {
+            ((NanoTokens::zero(), NanoTokens::zero()), (vec![], vec![]))
+        } else {
+            wallet_client
+                .pay_for_storage(claimed_addrs.into_iter())
+                .await?
+        };
+        info!(
+            "Paid {storage_cost} and {royalties_fees} royalties for a batch of {} Registers, {} of them already existing",
+            registers.len(),
+            skipped.len()
+        );
+        if let Err(err) = wallet_client.store_local_wallet() {
+            warn!("Failed to store wallet with cached payment proofs: {err:?}");
+        }
 
-            while !stored {
-                info!("Register not completely stored on the network yet. Retrying...");
-                // this verify store call here ensures we get the record from Quorum::all
-                let (reg, top_up_cost, royalties_top_up) =
-                    ClientRegister::create_online(self.clone(), address, wallet_client, true)
-                        .await?;
-                let reg_address = reg.address();
+        // existing Registers don't get paid for, they are only checked to be ours
+        let mut publishes = vec![];
+        for ((reg, net_addr), claim) in registers.into_iter().zip(net_addrs).zip(claims) {
+            let payment = match net_addr.as_xorname() {
+                Some(xorname) if skipped.contains(&xorname) => None,
+                _ => Some(wallet_client.get_payment_for_addr(&net_addr)),
+            };
+            publishes.push((reg, claim, payment));
+        }
 
-                total_cost = total_cost
-                    .checked_add(top_up_cost)
-                    .ok_or(Error::TotalPriceTooHigh)?;
-                total_royalties =
-                    total_cost
-                        .checked_add(royalties_top_up)
-                        .ok_or(Error::Transfers(sn_transfers::WalletError::from(
-                            sn_transfers::Error::ExcessiveNanoValue,
-                        )))?;
-                stored = self.verify_register_stored(*reg_address).await.is_ok();
+        let mut results: Vec<Result<ClientRegister>> = futures::stream::iter(publishes)
+            .map(|(reg, claim, payment)| self.publish_batched_register(reg, claim, payment))
+            .buffered(REGISTERS_BATCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        if verify_store {
+            let verifications: Vec<_> = futures::stream::iter(results.iter())
+                .map(|result| async move {
+                    match result {
+                        Ok(reg) => Some(self.verify_register_stored(*reg.address()).await),
+                        Err(_) => None,
+                    }
+                })
+                .buffered(REGISTERS_BATCH_CONCURRENCY)
+                .collect()
+                .await;
+
+            for (result, verification) in results.iter_mut().zip(verifications) {
+                if let Some(Err(err)) = verification {
+                    warn!("Register of the batch failed verification: {err:?}");
+                    *result = Err(err);
+                }
             }
         }
 
-        Ok((reg, total_cost, total_royalties))
+        Ok((results, storage_cost, royalties_fees))
+    }
+
+    /// Publish a Register of a batch with its payment, or check it is ours if it already existed.
+    async fn publish_batched_register(
+        &self,
+        reg: ClientRegister,
+        claim: Result<SignedRegisterClaim>,
+        payment: Option<WalletResult<Payment>>,
+    ) -> Result<ClientRegister> {
+        let claim = claim?;
+        match payment {
+            Some(payment) => {
+                reg.publish_creation(payment?, claim, false).await?;
+                Ok(reg)
+            }
+            None => {
+                let existing = self
+                    .get_register(*reg.address(), RegisterReadOptions::default())
+                    .await?;
+                if existing.owner() != reg.owner() {
+                    return Err(ProtocolError::RegisterAlreadyClaimed(existing.owner()))?;
+                }
+                Ok(existing)
+            }
+        }
     }
 
     /// Store `Chunk` as a record.
@@ -386,6 +1190,8 @@ impl Client {
         verify_store: bool,
     ) -> Result<()> {
         info!("Store chunk: {:?}", chunk.address());
+        let addr = *chunk.address();
+        let cost = payment.quote.cost;
         let key = chunk.network_address().to_record_key();
 
         let record_kind = RecordKind::ChunkWithPayment;
@@ -401,7 +1207,7 @@ impl Client {
                 get_quorum: Quorum::N(
                     NonZeroUsize::new(2).ok_or(Error::NonZeroUsizeWasInitialisedAsZero)?,
                 ),
-                re_attempt: true,
+                retry_policy: self.retry_policy,
                 target_record: None, // Not used since we use ChunkProof
                 expected_holders: Default::default(),
             };
@@ -409,13 +1215,12 @@ impl Client {
             // The holders shall only hold the `Chunk` copies.
             // Hence the fetched copies shall only be a `Chunk`
 
-            let stored_on_node = try_serialize_record(&chunk, RecordKind::Chunk)?.to_vec();
+            let stored_on_node = try_serialize_record(&chunk, RecordKind::Chunk)?;
             let random_nonce = thread_rng().gen::<u64>();
-            let expected_proof = ChunkProof::new(&stored_on_node, random_nonce);
 
             Some((
                 VerificationKind::ChunkProof {
-                    expected_proof,
+                    stored_bytes: stored_on_node,
                     nonce: random_nonce,
                 },
                 verification_cfg,
@@ -425,70 +1230,305 @@ impl Client {
         };
         let put_cfg = PutRecordCfg {
             put_quorum: Quorum::One,
-            re_attempt: true,
+            retry_policy: self.retry_policy,
             use_put_record_to: Some(vec![payee]),
             verification,
         };
-        Ok(self.network.put_record(record, &put_cfg).await?)
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let result = {
+            let _permit = self.acquire_op_permit().await;
+            self.cancellable(async { Ok(self.network.put_record(record, &put_cfg).await?) })
+                .await
+        };
+        #[cfg(feature = "open-metrics")]
+        {
+            self.metrics
+                .record_operation(Operation::ChunkPut, result.is_ok(), start.elapsed());
+            if result.is_ok() {
+                self.metrics.record_payment(cost);
+            }
+        }
+        match result {
+            Ok(()) => {
+                self.events_channel
+                    .notify(ClientEvent::ChunkStored { addr, cost });
+                Ok(())
+            }
+            Err(err) => {
+                self.events_channel.notify(ClientEvent::ChunkStoreFailed {
+                    addr,
+                    error: err.to_string(),
+                });
+                Err(err)
+            }
+        }
     }
 
     /// Retrieve a `Chunk` from the kad network.
     pub async fn get_chunk(&self, address: ChunkAddress, show_holders: bool) -> Result<Chunk> {
-        info!("Getting chunk: {address:?}");
-        let key = NetworkAddress::from_chunk_address(address).to_record_key();
+        let (chunk, _holders) = self.get_chunk_and_holders(address, show_holders).await?;
+        Ok(chunk)
+    }
 
-        let expected_holders = if show_holders {
-            let result: HashSet<_> = self
-                .network
-                .get_closest_peers(&NetworkAddress::from_chunk_address(address), true)
-                .await?
-                .iter()
-                .cloned()
-                .collect();
-            result
-        } else {
-            Default::default()
-        };
+    /// Retrieve a `Chunk` from the kad network, reading it as set by the `read_cfg`.
+    ///
+    /// See `get_chunk`.
+    pub async fn get_chunk_with_cfg(
+        &self,
+        address: ChunkAddress,
+        show_holders: bool,
+        read_cfg: ReadCfg,
+    ) -> Result<Chunk> {
+        let (chunk, _holders) = self
+            .get_chunk_and_holders_with_cfg(address, show_holders, read_cfg)
+            .await?;
+        Ok(chunk)
+    }
 
-        let get_cfg = GetRecordCfg {
-            get_quorum: Quorum::One,
-            re_attempt: true,
+    /// Retrieve several `Chunk`s from the network, fetching up to `max_concurrency` of them at once.
+    ///
+    /// The chunks are returned in the same order as `addrs`. If any of them can't be fetched,
+    /// the error lists the failed addresses along with the chunks that were fetched,
+    /// so only the failed ones need to be retried.
+    pub async fn get_chunks(
+        &self,
+        addrs: &[ChunkAddress],
+        max_concurrency: usize,
+    ) -> Result<Vec<Chunk>> {
+        info!(
+            "Getting {} chunks, {max_concurrency} at a time",
+            addrs.len()
+        );
+        let results: Vec<_> = futures::stream::iter(addrs)
+            .map(|address| self.get_chunk(*address, false))
+            .buffered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut failed = vec![];
+        let mut fetched = Vec::with_capacity(addrs.len());
+        for (address, result) in addrs.iter().zip(results) {
+            match result {
+                Ok(chunk) => fetched.push(Some(chunk)),
+                Err(err) => {
+                    warn!("Failed to fetch chunk {address:?}: {err}");
+                    failed.push(*address);
+                    fetched.push(None);
+                }
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(fetched.into_iter().flatten().collect())
+        } else {
+            Err(Error::ChunksNotFetched { failed, fetched })
+        }
+    }
+
+    /// Retrieve several `Chunk`s from the network like `get_chunks`, until the deadline.
+    ///
+    /// No chunk is requested once the deadline passed, and the requests in flight then are
+    /// cancelled. The chunks fetched by then are returned, along with the addresses which failed,
+    /// were cancelled, or were not attempted.
+    pub async fn get_chunks_with_deadline(
+        &self,
+        addrs: &[ChunkAddress],
+        max_concurrency: usize,
+        deadline: Deadline,
+    ) -> BatchOutcome<ChunkAddress, Chunk> {
+        info!(
+            "Getting {} chunks, {max_concurrency} at a time, for {:?} at most",
+            addrs.len(),
+            deadline.remaining()
+        );
+        run_batch(addrs, max_concurrency, Some(deadline), |address| {
+            self.get_chunk(address, false)
+        })
+        .await
+    }
+
+    /// Retrieve a `Chunk` from the network, along with the peers that served it.
+    ///
+    /// See `get_chunk`.
+    pub async fn get_chunk_and_holders(
+        &self,
+        address: ChunkAddress,
+        show_holders: bool,
+    ) -> Result<(Chunk, Vec<PeerId>)> {
+        self.get_chunk_and_holders_with_cfg(address, show_holders, ReadCfg::new(Quorum::One))
+            .await
+    }
+
+    async fn get_chunk_and_holders_with_cfg(
+        &self,
+        address: ChunkAddress,
+        show_holders: bool,
+        read_cfg: ReadCfg,
+    ) -> Result<(Chunk, Vec<PeerId>)> {
+        info!("Getting chunk: {address:?}");
+        let net_addr = NetworkAddress::from_chunk_address(address);
+        let key = net_addr.to_record_key();
+
+        let fetch = async {
+            let _permit = self.acquire_op_permit().await;
+            // chunks are verified against their address, a single copy of them is enough
+            if read_cfg.quorum == Quorum::One {
+                if let Some(close_group) = self.standby.fresh_close_group(&net_addr) {
+                    if let Some(fetched) =
+                        self.get_chunk_from_close_group(address, &close_group).await
+                    {
+                        return Ok(fetched);
+                    }
+                }
+            }
+
+            let expected_holders = if show_holders {
+                let result: HashSet<_> =
+                    self.close_group(&net_addr).await?.iter().cloned().collect();
+                result
+            } else {
+                Default::default()
+            };
+
+            let get_cfg = read_cfg.get_record_cfg(expected_holders, self.retry_policy);
+            self.get_chunk_with_record_cfg(address, key, &get_cfg).await
+        };
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let result = self
+            .cancellable(with_timeout(net_addr.clone(), read_cfg.timeout, fetch))
+            .await
+            .and_then(|fetched| fetched);
+        #[cfg(feature = "open-metrics")]
+        self.metrics
+            .record_operation(Operation::ChunkGet, result.is_ok(), start.elapsed());
+        result
+    }
+
+    /// Retrieve a `Chunk` from the network, along with the peers that served it.
+    pub async fn get_chunk_with_provenance(
+        &self,
+        address: ChunkAddress,
+    ) -> Result<(Chunk, Vec<PeerId>)> {
+        info!("Getting chunk with provenance: {address:?}");
+        let key = NetworkAddress::from_chunk_address(address).to_record_key();
+        let get_cfg = GetRecordCfg {
+            get_quorum: Quorum::One,
+            retry_policy: self.retry_policy,
             target_record: None,
-            expected_holders,
+            expected_holders: Default::default(),
         };
-        let record = self.network.get_record_from_network(key, &get_cfg).await?;
+        self.get_chunk_with_record_cfg(address, key, &get_cfg).await
+    }
+
+    /// Retrieve a record of the given kind from the network, along with the peers that served it.
+    /// e.g. `RecordKind::Register` for a `SignedRegister`, or `RecordKind::Spend` for `Vec<SignedSpend>`.
+    pub async fn get_record_with_provenance<T: DeserializeOwned>(
+        &self,
+        address: NetworkAddress,
+        kind: RecordKind,
+    ) -> Result<(T, Vec<PeerId>)> {
+        let get_cfg = GetRecordCfg {
+            get_quorum: Quorum::One,
+            retry_policy: self.retry_policy,
+            target_record: None,
+            expected_holders: Default::default(),
+        };
+        self.standby.record_lookup();
+        let (record, holders) = self
+            .network
+            .get_record_and_holders_from_network(address.to_record_key(), &get_cfg)
+            .await?;
+        let header = RecordHeader::from_record(&record)?;
+        if header.kind == kind {
+            Ok((try_deserialize_record(&record)?, holders))
+        } else {
+            Err(NetworkError::RecordKindMismatch(kind).into())
+        }
+    }
+
+    /// Fetch a chunk, rejecting payloads that don't match the chunk's address.
+    /// Such payloads are attributed to the peers that served them.
+    async fn get_chunk_with_record_cfg(
+        &self,
+        address: ChunkAddress,
+        key: RecordKey,
+        get_cfg: &GetRecordCfg,
+    ) -> Result<(Chunk, Vec<PeerId>)> {
+        self.standby.record_lookup();
+        let (record, holders) = self
+            .network
+            .get_record_and_holders_from_network(key, get_cfg)
+            .await?;
         let header = RecordHeader::from_record(&record)?;
         if let RecordKind::Chunk = header.kind {
             let chunk: Chunk = try_deserialize_record(&record)?;
-            Ok(chunk)
+            if XorName::from_content(chunk.value()) != *address.xorname() {
+                error!("Chunk {address:?} served by {holders:?} does not match its address");
+                return Err(Error::CorruptedChunk { address, holders });
+            }
+            Ok((chunk, holders))
         } else {
             Err(NetworkError::RecordKindMismatch(RecordKind::Chunk).into())
         }
     }
 
     /// Verify if a `Chunk` is stored by expected nodes on the network.
-    pub async fn verify_chunk_stored(&self, chunk: &Chunk) -> Result<()> {
+    ///
+    /// Returns which of the close nodes to the chunk hold it, or `Error::ChunkVerificationFailed`
+    /// if too few of them do. The report of the failed verification can then be had with
+    /// `sn_networking::Error::chunk_replication_report` on its source.
+    pub async fn verify_chunk_stored(&self, chunk: &Chunk) -> Result<ChunkReplicationReport> {
+        let quorum =
+            Quorum::N(NonZeroUsize::new(2).ok_or(Error::NonZeroUsizeWasInitialisedAsZero)?);
+        self.verify_chunk_stored_with_quorum(chunk, quorum).await
+    }
+
+    /// Whether a majority of the close group to the chunk prove to hold it, e.g. for an upload
+    /// to skip it.
+    ///
+    /// A chunk held by fewer nodes, e.g. one whose upload is still being replicated, is not
+    /// deemed stored, for it to be uploaded again rather than left short of replicas.
+    pub(crate) async fn is_chunk_stored(&self, chunk: &Chunk) -> bool {
+        match self
+            .verify_chunk_stored_with_quorum(chunk, Quorum::Majority)
+            .await
+        {
+            Ok(report) => report.quorum_met,
+            Err(err) => {
+                debug!("Chunk {:?} is not deemed stored: {err}", chunk.name());
+                false
+            }
+        }
+    }
+
+    async fn verify_chunk_stored_with_quorum(
+        &self,
+        chunk: &Chunk,
+        quorum: Quorum,
+    ) -> Result<ChunkReplicationReport> {
         let address = chunk.network_address();
         info!("Verifying chunk: {address:?}");
         let random_nonce = thread_rng().gen::<u64>();
         let record_value = try_serialize_record(&chunk, RecordKind::Chunk)?;
-        let expected_proof = ChunkProof::new(record_value.as_ref(), random_nonce);
 
-        if let Err(err) = self
-            .network
+        self.network
             .verify_chunk_existence(
                 address.clone(),
                 random_nonce,
-                expected_proof,
-                Quorum::N(NonZeroUsize::new(2).ok_or(Error::NonZeroUsizeWasInitialisedAsZero)?),
-                false,
+                record_value.as_ref(),
+                quorum,
+                &RetryPolicy::none(),
             )
             .await
-        {
-            error!("Failed to verify the existence of chunk {address:?} with err {err:?}");
-        }
-
-        Ok(())
+            .map_err(|source| {
+                error!("Failed to verify the existence of chunk {address:?} with err {source:?}");
+                Error::ChunkVerificationFailed {
+                    addr: address.clone(),
+                    source,
+                }
+            })
     }
 
     /// Verify if a `Register` is stored by expected nodes on the network.
@@ -497,16 +1537,62 @@ impl Client {
         self.get_signed_register_from_network(address, true).await
     }
 
+    /// Verify a `Register` is stored on the network with all the `expected_entries`, e.g. the
+    /// ones just written to it, reading it with the given `quorum`.
+    ///
+    /// Fails with `Error::RegisterEntriesMissing` if the Register read, merged from the replicas
+    /// returned, lacks any of them.
+    pub async fn verify_register_stored_with(
+        &self,
+        address: RegisterAddress,
+        expected_entries: &BTreeSet<EntryHash>,
+        quorum: Quorum,
+    ) -> Result<SignedRegister> {
+        info!(
+            "Verifying register {address:?} holds {} entries, with quorum {quorum:?}",
+            expected_entries.len()
+        );
+        let signed_register = self
+            .get_signed_register_from_network_with_cfg(address, ReadCfg::new(quorum))
+            .await?;
+        let register = signed_register.clone().register()?;
+        let missing: BTreeSet<_> = expected_entries
+            .iter()
+            .filter(|hash| register.get(**hash).is_err())
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            warn!("Register {address:?} is missing the entries {missing:?}");
+            return Err(Error::RegisterEntriesMissing {
+                address: Box::new(address),
+                missing,
+            });
+        }
+        Ok(signed_register)
+    }
+
     /// Send a `SpendCashNote` request to the network
+    ///
+    /// Unless `force` is set, the spend is verified and its parent spends are checked
+    /// to exist on the network before it is broadcast.
     pub(crate) async fn network_store_spend(
         &self,
         spend: SignedSpend,
         verify_store: bool,
+        force: bool,
     ) -> Result<()> {
         let unique_pubkey = *spend.unique_pubkey();
         let cash_note_addr = SpendAddress::from_unique_pubkey(&unique_pubkey);
         let network_address = NetworkAddress::from_spend_address(cash_note_addr);
 
+        check_spend_size(&spend, MAX_SPEND_SIZE)?;
+
+        if force {
+            warn!("Broadcasting spend {cash_note_addr:?} without checking its parent spends");
+        } else {
+            self.check_spend_parents(&spend).await?;
+        }
+
         trace!("Sending spend {unique_pubkey:?} to the network via put_record, with addr of {cash_note_addr:?}");
         let key = network_address.to_record_key();
         let record_kind = RecordKind::Spend;
@@ -519,8 +1605,7 @@ impl Client {
 
         let (record_to_verify, expected_holders) = if verify_store {
             let expected_holders: HashSet<_> = self
-                .network
-                .get_closest_peers(&network_address, true)
+                .close_group(&network_address)
                 .await?
                 .iter()
                 .cloned()
@@ -532,122 +1617,143 @@ impl Client {
 
         let verification_cfg = GetRecordCfg {
             get_quorum: Quorum::Majority,
-            re_attempt: true,
+            retry_policy: self.retry_policy,
             target_record: record_to_verify,
             expected_holders,
         };
         let put_cfg = PutRecordCfg {
             put_quorum: Quorum::All,
-            re_attempt: true,
+            retry_policy: self.retry_policy,
             use_put_record_to: None,
             verification: Some((VerificationKind::Network, verification_cfg)),
         };
-        Ok(self.network.put_record(record, &put_cfg).await?)
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let result = self.network.put_record(record, &put_cfg).await;
+        #[cfg(feature = "open-metrics")]
+        self.metrics
+            .record_operation(Operation::SpendPut, result.is_ok(), start.elapsed());
+        Ok(result?)
     }
 
-    /// Get a spend from network
-    pub async fn get_spend_from_network(&self, address: SpendAddress) -> Result<SignedSpend> {
-        let key = NetworkAddress::from_spend_address(address).to_record_key();
-
-        trace!(
-            "Getting spend at {address:?} with record_key {:?}",
-            PrettyPrintRecordKey::from(&key)
-        );
-        let get_cfg = GetRecordCfg {
-            get_quorum: Quorum::Majority,
-            re_attempt: true,
-            target_record: None,
-            expected_holders: Default::default(),
-        };
-        let record = self
-            .network
-            .get_record_from_network(key.clone(), &get_cfg)
-            .await
-            .map_err(|err| match err {
-                sn_networking::Error::GetRecordError(GetRecordError::RecordNotFound) => {
-                    Error::MissingSpendRecord(address)
-                }
-                _ => Error::CouldNotVerifyTransfer(format!(
-                    "failed to get spend at {address:?}: {err:?}"
-                )),
-            })?;
-        debug!(
-            "For spend at {address:?} got record from the network, {:?}",
-            PrettyPrintRecordKey::from(&record.key)
-        );
+    /// Verify the spend, and check all of its parent spends exist on the network,
+    /// as nodes would before accepting it.
+    pub(crate) async fn check_spend_parents(&self, spend: &SignedSpend) -> Result<()> {
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+        spend
+            .verify(spend.spent_tx_hash())
+            .map_err(|source| Error::InvalidSpend { address, source })?;
+
+        let parent_tx = &spend.spend.parent_tx;
+        if is_genesis_parent_tx(parent_tx) && spend.unique_pubkey() == &GENESIS_CASHNOTE.id {
+            trace!("Spend {address:?} is the genesis spend, it has no parent");
+            return Ok(());
+        }
 
-        let header = RecordHeader::from_record(&record).map_err(|err| {
-            Error::CouldNotVerifyTransfer(format!(
-                "Can't parse RecordHeader for the spend at {address:?} with error {err:?}"
-            ))
-        })?;
-
-        if let RecordKind::Spend = header.kind {
-            let mut deserialized_record = try_deserialize_record::<Vec<SignedSpend>>(&record)
-                .map_err(|err| {
-                    Error::CouldNotVerifyTransfer(format!(
-                        "Can't deserialize record for the spend at {address:?} with error {err:?}"
-                    ))
-                })?;
+        let tasks = parent_tx.inputs.iter().map(|input| async move {
+            let parent_address = SpendAddress::from_unique_pubkey(input.unique_pubkey());
+            (
+                parent_address,
+                self.get_spend_from_network(parent_address).await,
+            )
+        });
 
-            match deserialized_record.len() {
-                0 => {
-                    trace!("Found no spend for {address:?}");
-                    Err(Error::CouldNotVerifyTransfer(format!(
-                        "Fetched record shows no spend for cash_note {address:?}."
-                    )))
-                }
-                1 => {
-                    let signed_spend = deserialized_record.remove(0);
-                    trace!("Spend get for address: {address:?} successful");
-                    if address == SpendAddress::from_unique_pubkey(signed_spend.unique_pubkey()) {
-                        match signed_spend.verify(signed_spend.spent_tx_hash()) {
-                            Ok(_) => {
-                                trace!("Verified signed spend got from network for {address:?}");
-                                Ok(signed_spend)
-                            }
-                            Err(err) => {
-                                warn!("Invalid signed spend got from network for {address:?}: {err:?}.");
-                                Err(Error::CouldNotVerifyTransfer(format!(
-                                "Spend failed verifiation for the unique_pubkey {address:?} with error {err:?}")))
-                            }
-                        }
-                    } else {
-                        warn!("Signed spend ({:?}) got from network mismatched the expected one {address:?}.", signed_spend.unique_pubkey());
-                        Err(Error::CouldNotVerifyTransfer(format!(
-                                "Signed spend ({:?}) got from network mismatched the expected one {address:?}.", signed_spend.unique_pubkey())))
-                    }
+        let mut parent_spends = BTreeSet::new();
+        let mut missing = vec![];
+        for (parent_address, result) in join_all(tasks).await {
+            match result {
+                Ok(parent_spend) => {
+                    let _ = parent_spends.insert(parent_spend);
                 }
-                _ => {
-                    // each one is 0 as it shifts remaining elements
-                    let one = deserialized_record.remove(0);
-                    let two = deserialized_record.remove(0);
-                    error!("Found double spend for {address:?}");
-                    Err(Error::CouldNotVerifyTransfer(format!(
-                "Found double spend for the unique_pubkey {address:?} - {:?}: spend_one {:?} and spend_two {:?}",
-                PrettyPrintRecordKey::from(&key), one.derived_key_sig, two.derived_key_sig
-            )))
+                Err(Error::MissingSpendRecord(_)) => missing.push(parent_address),
+                Err(err) => {
+                    warn!("Failed to get parent spend {parent_address:?} of {address:?}: {err:?}");
+                    return Err(err);
                 }
             }
-        } else {
-            error!("RecordKind mismatch while trying to retrieve a cash_note spend");
-            Err(NetworkError::RecordKindMismatch(RecordKind::Spend).into())
         }
-    }
+        if !missing.is_empty() {
+            warn!("Spend {address:?} has parent spends missing from the network: {missing:?}");
+            return Err(Error::MissingParentSpends { address, missing });
+        }
 
-    /// Subscribe to given gossipsub topic
-    pub fn subscribe_to_topic(&self, topic_id: String) -> Result<()> {
-        info!("Subscribing to topic id: {topic_id}");
-        self.network.subscribe_to_topic(topic_id)?;
-        self.network.start_handle_gossip()?;
+        parent_tx
+            .verify_against_inputs_spent(&parent_spends)
+            .map_err(|source| Error::InvalidSpend { address, source })?;
+
+        trace!("Parent spends of {address:?} are all present on the network");
         Ok(())
     }
 
-    /// Unsubscribe from given gossipsub topic
-    pub fn unsubscribe_from_topic(&self, topic_id: String) -> Result<()> {
-        info!("Unsubscribing from topic id: {topic_id}");
-        self.network.unsubscribe_from_topic(topic_id)?;
-        Ok(())
+    /// Get a spend from network
+    pub async fn get_spend_from_network(&self, address: SpendAddress) -> Result<SignedSpend> {
+        self.get_spend_from_network_with_cfg(address, ReadCfg::new(Quorum::Majority))
+            .await
+    }
+
+    /// Get several spends from the network, fetching up to `max_concurrency` of them at once
+    /// until the deadline.
+    ///
+    /// See `get_chunks_with_deadline`.
+    pub async fn get_spends_with_deadline(
+        &self,
+        addrs: &[SpendAddress],
+        max_concurrency: usize,
+        deadline: Deadline,
+    ) -> BatchOutcome<SpendAddress, SignedSpend> {
+        info!(
+            "Getting {} spends, {max_concurrency} at a time, for {:?} at most",
+            addrs.len(),
+            deadline.remaining()
+        );
+        run_batch(addrs, max_concurrency, Some(deadline), |address| {
+            self.get_spend_from_network(address)
+        })
+        .await
+    }
+
+    /// Get a spend from network, reading it as set by the `read_cfg`.
+    ///
+    /// See `get_spend_from_network`.
+    pub async fn get_spend_from_network_with_cfg(
+        &self,
+        address: SpendAddress,
+        read_cfg: ReadCfg,
+    ) -> Result<SignedSpend> {
+        #[cfg(feature = "open-metrics")]
+        let start = Instant::now();
+        let result = self.fetch_spend_from_network(address, read_cfg).await;
+        #[cfg(feature = "open-metrics")]
+        self.metrics
+            .record_operation(Operation::SpendGet, result.is_ok(), start.elapsed());
+        result
+    }
+
+    async fn fetch_spend_from_network(
+        &self,
+        address: SpendAddress,
+        read_cfg: ReadCfg,
+    ) -> Result<SignedSpend> {
+        let key = NetworkAddress::from_spend_address(address).to_record_key();
+
+        trace!(
+            "Getting spend at {address:?} with record_key {:?}",
+            PrettyPrintRecordKey::from(&key)
+        );
+        let get_cfg = read_cfg.get_record_cfg(Default::default(), self.retry_policy);
+        self.standby.record_lookup();
+        let fetched = with_timeout(
+            NetworkAddress::from_spend_address(address),
+            read_cfg.timeout,
+            self.network.get_record_from_network(key, &get_cfg),
+        )
+        .await?;
+
+        spend_from_fetched_record(
+            address,
+            fetched,
+            &self.network.root_dir_path.join(DOUBLE_SPENDS_DIR_NAME),
+        )
     }
 
     /// Publish message on given topic
@@ -660,7 +1766,9 @@ impl Client {
     /// This function is used to receive a list of CashNoteRedemptions and turn it back into spendable CashNotes.
     /// Needs Network connection.
     /// Verify CashNoteRedemptions and rebuild spendable currency from them.
-    /// Returns an `Error::InvalidTransfer` if any CashNoteRedemption is not valid
+    /// The spends needed are fetched concurrently, see `sn_networking::MAX_CONCURRENT_SPEND_GETS`.
+    /// Returns an `Error::InvalidCashNoteRedemption` from the network if any CashNoteRedemption
+    /// is not valid, telling which one, none of them being redeemed then.
     /// Else returns a list of CashNotes that can be spent by the owner.
     pub async fn verify_cash_notes_redemptions(
         &self,
@@ -674,49 +1782,141 @@ impl Client {
         Ok(cash_notes)
     }
 
+    /// Verify that chunks were uploaded, yielding the result for each of them as soon as it is
+    /// known, rather than in the order of `chunks_paths`.
+    ///
+    /// Up to `batch_size` chunks are verified at once. A chunk whose file is missing fails with
+    /// `VerificationStatus::FileMissing`, and one which can't be read otherwise along with the
+    /// error reading it.
+    pub fn verify_uploaded_chunks_stream(
+        &self,
+        chunks_paths: Vec<(XorName, PathBuf)>,
+        batch_size: usize,
+    ) -> BoxStream<'static, (XorName, PathBuf, VerificationStatus)> {
+        let client = self.clone();
+        futures::stream::iter(chunks_paths)
+            .map(move |(name, chunk_path)| {
+                let client = client.clone();
+                async move {
+                    let status = match std::fs::read(&chunk_path) {
+                        Ok(bytes) => client.verify_chunk_status(&Chunk::new(bytes.into())).await,
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                            warn!("Cannot verify chunk {name:?}, {chunk_path:?} is missing");
+                            VerificationStatus::FileMissing
+                        }
+                        Err(err) => {
+                            warn!("Cannot verify chunk {name:?}, failed to read {chunk_path:?}: {err}");
+                            VerificationStatus::Failed(ChunksError::from(err).into())
+                        }
+                    };
+                    (name, chunk_path, status)
+                }
+            })
+            .buffer_unordered(batch_size.max(1))
+            .boxed()
+    }
+
+    /// Verify that the chunks are stored, up to `batch_size` of them at once, as
+    /// `verify_chunk_stored` does for each of them.
+    ///
+    /// Returns the names of the chunks that could not be verified, in the order of `chunks`.
+    pub async fn verify_chunks(&self, chunks: &[Chunk], batch_size: usize) -> Vec<XorName> {
+        let failed: HashSet<_> = futures::stream::iter(chunks)
+            .map(|chunk| async move { (*chunk.name(), self.verify_chunk_status(chunk).await) })
+            .buffer_unordered(batch_size.max(1))
+            .filter_map(|(name, status)| async move { (!status.is_verified()).then_some(name) })
+            .collect()
+            .await;
+
+        chunks
+            .iter()
+            .map(|chunk| *chunk.name())
+            .filter(|name| failed.contains(name))
+            .collect()
+    }
+
+    async fn verify_chunk_status(&self, chunk: &Chunk) -> VerificationStatus {
+        match self.verify_chunk_stored(chunk).await {
+            Ok(report) => VerificationStatus::Verified(report),
+            Err(err) => {
+                warn!("Failed to verify chunk {:?} is stored: {err}", chunk.name());
+                VerificationStatus::Failed(err)
+            }
+        }
+    }
+
     /// Verify that chunks were uploaded
     ///
-    /// Returns a vec of any chunks that could not be verified
+    /// Returns a vec of any chunks that could not be verified, in the order of `chunks_paths`.
+    /// See `verify_uploaded_chunks_stream`.
     pub async fn verify_uploaded_chunks(
         &self,
         chunks_paths: &[(XorName, PathBuf)],
         batch_size: usize,
     ) -> Result<Vec<(XorName, PathBuf)>> {
-        let mut failed_chunks = Vec::new();
-
-        for chunks_batch in chunks_paths.chunks(batch_size) {
-            // now we try and get batched chunks, keep track of any that fail
-            // Iterate over each uploaded chunk
-            let mut verify_handles = Vec::new();
-            for (name, chunk_path) in chunks_batch.iter().cloned() {
-                let client = self.clone();
-                // Spawn a new task to fetch each chunk concurrently
-                let handle = tokio::spawn(async move {
-                    // make sure the chunk is stored;
-                    let chunk = Chunk::new(Bytes::from(std::fs::read(&chunk_path)?));
-                    let res = client.verify_chunk_stored(&chunk).await;
-
-                    Ok::<_, ChunksError>(((name, chunk_path), res.is_err()))
-                });
-                verify_handles.push(handle);
-            }
+        let failed: HashSet<_> = self
+            .verify_uploaded_chunks_stream(chunks_paths.to_vec(), batch_size)
+            .filter_map(|(name, chunk_path, status)| async move {
+                (!status.is_verified()).then_some((name, chunk_path))
+            })
+            .collect()
+            .await;
+
+        Ok(chunks_paths
+            .iter()
+            .filter(|chunk| failed.contains(*chunk))
+            .cloned()
+            .collect())
+    }
+}
+
+/// Whether an uploaded chunk is stored, see `Client::verify_uploaded_chunks_stream`.
+#[derive(Debug)]
+pub enum VerificationStatus {
+    /// The chunk is held by the nodes expected to hold it, as told by the report.
+    Verified(ChunkReplicationReport),
+    /// The chunk could not be verified, with the reason why.
+    Failed(Error),
+    /// The file of the chunk is missing, so the chunk could not be verified.
+    FileMissing,
+}
 
-            // Await all fetch tasks and collect the results
-            let verify_results = join_all(verify_handles).await;
+impl VerificationStatus {
+    /// Whether the chunk was verified to be stored.
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Self::Verified(_))
+    }
 
-            // Check for any errors during fetch
-            for result in verify_results {
-                if let ((chunk_addr, path), true) = result?? {
-                    warn!("Failed to fetch a chunk {chunk_addr:?}");
-                    failed_chunks.push((chunk_addr, path));
-                }
+    /// Which of the close nodes to the chunk hold it, unless the chunk could not be asked for.
+    pub fn replication_report(&self) -> Option<&ChunkReplicationReport> {
+        match self {
+            Self::Verified(report) => Some(report),
+            Self::Failed(Error::ChunkVerificationFailed { source, .. }) => {
+                source.chunk_replication_report()
             }
+            Self::Failed(_) | Self::FileMissing => None,
         }
-
-        Ok(failed_chunks)
     }
 }
 
+/// Await the network operation on the given address, failing with `Error::OperationTimedOut` if
+/// it takes longer than the timeout, if any.
+async fn with_timeout<T>(
+    addr: NetworkAddress,
+    timeout: Option<Duration>,
+    operation: impl std::future::Future<Output = T>,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return Ok(operation.await);
+    };
+    let start = Instant::now();
+    tokio::time::timeout(timeout, operation).await.map_err(|_| {
+        let elapsed = start.elapsed();
+        warn!("Operation on {addr:?} timed out after {elapsed:?}");
+        Error::OperationTimedOut { addr, elapsed }
+    })
+}
+
 fn get_register_from_record(record: &Record) -> Result<SignedRegister> {
     let header = RecordHeader::from_record(record)?;
 
@@ -729,6 +1929,133 @@ fn get_register_from_record(record: &Record) -> Result<SignedRegister> {
     }
 }
 
+/// Get the spend at the address from the outcome of fetching its record. A split record is
+/// resolved, the evidence of the double spend it may hold being archived into `evidence_dir`.
+fn spend_from_fetched_record(
+    address: SpendAddress,
+    fetched: std::result::Result<Record, NetworkError>,
+    evidence_dir: &Path,
+) -> Result<SignedSpend> {
+    let record = match fetched {
+        Ok(record) => record,
+        Err(err) => match err.last_cause() {
+            NetworkError::GetRecordError(GetRecordError::RecordNotFound) => {
+                return Err(Error::MissingSpendRecord(address));
+            }
+            NetworkError::GetRecordError(GetRecordError::SplitRecord { result_map }) => {
+                warn!("Got a split record for the spend at {address:?}");
+                return resolve_split_spend_record(address, result_map, evidence_dir);
+            }
+            _ => {
+                return Err(Error::CouldNotVerifyTransfer(format!(
+                    "failed to get spend at {address:?}: {err:?}"
+                )));
+            }
+        },
+    };
+    debug!(
+        "For spend at {address:?} got record from the network, {:?}",
+        PrettyPrintRecordKey::from(&record.key)
+    );
+
+    get_spend_from_record(address, &record)
+}
+
+/// Get the spend held by a spend record.
+/// Returns `Error::DoubleSpendDetected` with all the spends if the record holds conflicting ones.
+fn get_spend_from_record(address: SpendAddress, record: &Record) -> Result<SignedSpend> {
+    let header = RecordHeader::from_record(record).map_err(|err| {
+        Error::CouldNotVerifyTransfer(format!(
+            "Can't parse RecordHeader for the spend at {address:?} with error {err:?}"
+        ))
+    })?;
+
+    if let RecordKind::Spend = header.kind {
+        let mut deserialized_record =
+            try_deserialize_record::<Vec<SignedSpend>>(record).map_err(|err| {
+                Error::CouldNotVerifyTransfer(format!(
+                    "Can't deserialize record for the spend at {address:?} with error {err:?}"
+                ))
+            })?;
+
+        match deserialized_record.len() {
+            0 => {
+                trace!("Found no spend for {address:?}");
+                Err(Error::CouldNotVerifyTransfer(format!(
+                    "Fetched record shows no spend for cash_note {address:?}."
+                )))
+            }
+            1 => {
+                let signed_spend = deserialized_record.remove(0);
+                trace!("Spend get for address: {address:?} successful");
+                if address == SpendAddress::from_unique_pubkey(signed_spend.unique_pubkey()) {
+                    match signed_spend.verify(signed_spend.spent_tx_hash()) {
+                        Ok(_) => {
+                            trace!("Verified signed spend got from network for {address:?}");
+                            Ok(signed_spend)
+                        }
+                        Err(err) => {
+                            warn!(
+                                "Invalid signed spend got from network for {address:?}: {err:?}."
+                            );
+                            Err(Error::CouldNotVerifyTransfer(format!(
+                            "Spend failed verifiation for the unique_pubkey {address:?} with error {err:?}")))
+                        }
+                    }
+                } else {
+                    warn!("Signed spend ({:?}) got from network mismatched the expected one {address:?}.", signed_spend.unique_pubkey());
+                    Err(Error::CouldNotVerifyTransfer(format!(
+                            "Signed spend ({:?}) got from network mismatched the expected one {address:?}.", signed_spend.unique_pubkey())))
+                }
+            }
+            _ => {
+                // only the spends signed by the owner of the address are evidence of a double
+                // spend, anyone could have stored the others
+                let mut valid_spends: Vec<_> = deserialized_record
+                    .into_iter()
+                    .filter(|spend| is_valid_spend_at(address, spend))
+                    .collect();
+                match valid_spends.len() {
+                    0 => Err(Error::CouldNotVerifyTransfer(format!(
+                        "Fetched record holds no valid spend for cash_note {address:?}."
+                    ))),
+                    1 => {
+                        warn!("Record for {address:?} only holds one valid spend");
+                        Ok(valid_spends.remove(0))
+                    }
+                    conflicting => {
+                        error!(
+                            "Found double spend for {address:?}: {conflicting} conflicting spends"
+                        );
+                        Err(Error::DoubleSpendDetected {
+                            address,
+                            spends: valid_spends,
+                        })
+                    }
+                }
+            }
+        }
+    } else {
+        error!("RecordKind mismatch while trying to retrieve a cash_note spend");
+        Err(NetworkError::RecordKindMismatch(RecordKind::Spend).into())
+    }
+}
+
+/// Fail with `Error::TransactionTooLarge` if the spend takes more than `max` bytes, i.e. wouldn't
+/// fit in a record.
+fn check_spend_size(spend: &SignedSpend, max: usize) -> Result<()> {
+    let size = spend.serialised_size().map_err(WalletError::from)?;
+    if size > max {
+        return Err(Error::TransactionTooLarge {
+            size,
+            max,
+            inputs: spend.spend.spent_tx.inputs.len(),
+            outputs: spend.spend.spent_tx.outputs.len(),
+        });
+    }
+    Ok(())
+}
+
 /// if multiple register records where found for a given key, merge them into a single register
 fn merge_split_register_records(
     address: RegisterAddress,
@@ -774,6 +2101,9 @@ mod tests {
     use std::collections::BTreeSet;
 
     use sn_registers::Register;
+    use sn_transfers::{
+        DerivationIndex, DerivedSecretKey, Hash, MainSecretKey, Spend, Transaction,
+    };
 
     use super::*;
 
@@ -861,4 +2191,214 @@ mod tests {
 
         Ok(())
     }
+
+    fn signed_spend(derived_key: &DerivedSecretKey, amount: u64) -> SignedSpend {
+        let spend = Spend {
+            unique_pubkey: derived_key.unique_pubkey(),
+            spent_tx: Transaction::empty(),
+            reason: Hash::default(),
+            token: NanoTokens::from(amount),
+            parent_tx: Transaction::empty(),
+            network_royalties: vec![],
+        };
+        let derived_key_sig = derived_key.sign(&spend.to_bytes());
+        SignedSpend {
+            spend,
+            derived_key_sig,
+        }
+    }
+
+    fn spend_record(address: SpendAddress, spends: &[SignedSpend]) -> eyre::Result<Record> {
+        Ok(Record {
+            key: NetworkAddress::from_spend_address(address).to_record_key(),
+            value: try_serialize_record(&spends, RecordKind::Spend)?.to_vec(),
+            publisher: None,
+            expires: None,
+        })
+    }
+
+    /// The split record a get of the spends would fail with after retrying, one copy per
+    /// spends.
+    fn split_spend_record(
+        address: SpendAddress,
+        copies: &[&[SignedSpend]],
+    ) -> eyre::Result<NetworkError> {
+        let mut result_map = HashMap::new();
+        for spends in copies {
+            let record = spend_record(address, spends)?;
+            let peers = HashSet::from_iter([PeerId::random()]);
+            let _ = result_map.insert(XorName::from_content(&record.value), (record, peers));
+        }
+        Ok(NetworkError::RetriesExhausted {
+            attempts: 3,
+            last_error: Box::new(NetworkError::GetRecordError(GetRecordError::SplitRecord {
+                result_map,
+            })),
+        })
+    }
+
+    #[test]
+    fn conflicting_spends_in_a_record_are_a_double_spend() -> eyre::Result<()> {
+        let mut rng = rand::thread_rng();
+        let derived_key = MainSecretKey::random().random_derived_key(&mut rng);
+        let address = SpendAddress::from_unique_pubkey(&derived_key.unique_pubkey());
+        let spends = vec![
+            signed_spend(&derived_key, 10),
+            signed_spend(&derived_key, 20),
+        ];
+        let record = spend_record(address, &spends)?;
+
+        match get_spend_from_record(address, &record) {
+            Err(Error::DoubleSpendDetected {
+                address: double_spent,
+                spends: conflicting,
+            }) => {
+                assert_eq!(double_spent, address);
+                assert_eq!(conflicting, spends);
+            }
+            other => panic!("Expected a double spend, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn spends_too_large_for_a_record_are_not_sent() -> eyre::Result<()> {
+        let key = MainSecretKey::random();
+        let cash_note = sn_transfers::create_first_cash_note_from_key(&key)?;
+        let derived_key = cash_note.derived_key(&key)?;
+        let mut rng = rand::thread_rng();
+        let recipients = (0..60)
+            .map(|_| {
+                (
+                    NanoTokens::from(1),
+                    key.main_pubkey(),
+                    DerivationIndex::random(&mut rng),
+                )
+            })
+            .collect();
+        let transfer = sn_transfers::create_offline_transfer(
+            vec![(cash_note, derived_key)],
+            recipients,
+            key.main_pubkey(),
+            Hash::default(),
+        )?;
+        let spend = transfer
+            .all_spend_requests
+            .first()
+            .ok_or_else(|| eyre::eyre!("The transfer spends nothing"))?;
+        let size = spend.serialised_size()?;
+
+        check_spend_size(spend, size)?;
+        match check_spend_size(spend, size - 1) {
+            Err(Error::TransactionTooLarge {
+                size: reported_size,
+                max,
+                inputs,
+                outputs,
+            }) => {
+                assert_eq!(reported_size, size);
+                assert_eq!(max, size - 1);
+                assert_eq!(inputs, 1);
+                // the recipients and the change
+                assert_eq!(outputs, 61);
+            }
+            other => panic!("Expected the spend to be too large, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_spends_in_a_record_are_no_double_spend() -> eyre::Result<()> {
+        let mut rng = rand::thread_rng();
+        let derived_key = MainSecretKey::random().random_derived_key(&mut rng);
+        let address = SpendAddress::from_unique_pubkey(&derived_key.unique_pubkey());
+        let spend = signed_spend(&derived_key, 10);
+        // signed by someone else, or the spend of another address
+        let mut forged = signed_spend(&derived_key, 20);
+        forged.derived_key_sig = MainSecretKey::random()
+            .random_derived_key(&mut rng)
+            .sign(&forged.spend.to_bytes());
+        let other = signed_spend(&MainSecretKey::random().random_derived_key(&mut rng), 30);
+
+        let record = spend_record(address, &[spend.clone(), forged.clone(), other.clone()])?;
+        assert_eq!(get_spend_from_record(address, &record)?, spend);
+
+        let record = spend_record(address, &[forged, other])?;
+        assert!(matches!(
+            get_spend_from_record(address, &record),
+            Err(Error::CouldNotVerifyTransfer(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn a_split_record_of_conflicting_spends_is_archived_as_a_double_spend() -> eyre::Result<()> {
+        let mut rng = rand::thread_rng();
+        let derived_key = MainSecretKey::random().random_derived_key(&mut rng);
+        let address = SpendAddress::from_unique_pubkey(&derived_key.unique_pubkey());
+        let spend_one = signed_spend(&derived_key, 10);
+        let spend_two = signed_spend(&derived_key, 20);
+        let fetched = Err(split_spend_record(
+            address,
+            &[&[spend_one.clone()], &[spend_two.clone()]],
+        )?);
+
+        let evidence_dir = tempfile::tempdir()?;
+        let evidence_dir = evidence_dir.path().join(DOUBLE_SPENDS_DIR_NAME);
+        match spend_from_fetched_record(address, fetched, &evidence_dir) {
+            Err(Error::DoubleSpendDetected {
+                address: double_spent,
+                spends,
+            }) => {
+                assert_eq!(double_spent, address);
+                assert_eq!(
+                    BTreeSet::from_iter(spends),
+                    BTreeSet::from_iter([spend_one.clone(), spend_two.clone()])
+                );
+            }
+            other => panic!("Expected a double spend, got {other:?}"),
+        }
+
+        let archived: Vec<_> = std::fs::read_dir(&evidence_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::result::Result<_, _>>()?;
+        assert_eq!(archived.len(), 1);
+        let evidence = crate::DoubleSpendEvidence::load(&archived[0])?;
+        assert_eq!(evidence.address, address);
+        assert_eq!(evidence.copies.len(), 2);
+        assert_eq!(
+            evidence.valid_spends(),
+            BTreeSet::from_iter([spend_one, spend_two])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn a_split_record_with_a_single_valid_spend_resolves_to_it() -> eyre::Result<()> {
+        let mut rng = rand::thread_rng();
+        let derived_key = MainSecretKey::random().random_derived_key(&mut rng);
+        let address = SpendAddress::from_unique_pubkey(&derived_key.unique_pubkey());
+        let spend = signed_spend(&derived_key, 10);
+        // a copy signed by someone else is not a valid spend for this address
+        let mut forged = signed_spend(&derived_key, 20);
+        forged.derived_key_sig = MainSecretKey::random()
+            .random_derived_key(&mut rng)
+            .sign(&forged.spend.to_bytes());
+        let fetched = Err(split_spend_record(address, &[&[spend.clone()], &[forged]])?);
+
+        let evidence_dir = tempfile::tempdir()?;
+        let evidence_dir = evidence_dir.path().join(DOUBLE_SPENDS_DIR_NAME);
+        assert_eq!(
+            spend_from_fetched_record(address, fetched, &evidence_dir)?,
+            spend
+        );
+        assert!(!evidence_dir.exists());
+
+        let missing = Err(NetworkError::GetRecordError(GetRecordError::RecordNotFound));
+        assert!(matches!(
+            spend_from_fetched_record(address, missing, &evidence_dir),
+            Err(Error::MissingSpendRecord(missing)) if missing == address
+        ));
+        Ok(())
+    }
 }
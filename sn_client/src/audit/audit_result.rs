@@ -0,0 +1,119 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use sn_transfers::{NanoTokens, SignedSpend, SpendAddress};
+use std::{collections::BTreeSet, time::Duration};
+
+/// What `Client::follow_spend` found following the descendants of a spend.
+///
+/// The values are those of the outputs of the transactions followed, as the spends walked hold
+/// them, none being fetched from the Network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditResult {
+    /// The addresses of the outputs found unspent
+    pub utxos: BTreeSet<SpendAddress>,
+    /// The double spends found on the way, whose branches were not followed
+    pub double_spends: Vec<DoubleSpendReport>,
+    /// How far the audit went
+    pub stats: AuditStats,
+    /// The value of the UTXOs, the royalties not claimed yet included, those of the frontier
+    /// an audit was resumed from too
+    pub total_utxo_value: NanoTokens,
+    /// The value of the royalties paid by the spends followed, claimed or not
+    pub total_royalties: NanoTokens,
+    /// The value held at the addresses whose branches were not followed, double spent or
+    /// rejected, unspendable as far as the audit is concerned, since the audit started
+    pub total_unfollowed_value: NanoTokens,
+}
+
+impl AuditResult {
+    /// The double spent addresses.
+    pub fn double_spent_addresses(&self) -> BTreeSet<SpendAddress> {
+        self.double_spends
+            .iter()
+            .map(|report| report.address)
+            .collect()
+    }
+
+    /// Check the value found by the audit adds up to the value of the transaction the spend it
+    /// started from went into, i.e. the sum of all the outputs of that transaction: the value of
+    /// the UTXOs, and of the branches not followed. That is the genesis amount when auditing the
+    /// whole currency from the genesis spend, be it over several sessions resumed from their
+    /// `AuditFrontier`.
+    ///
+    /// Returns `Error::SupplyMismatch` with the value found otherwise.
+    pub fn supply_check(&self, genesis_amount: NanoTokens) -> Result<()> {
+        let found = NanoTokens::from(
+            self.total_utxo_value
+                .as_nano()
+                .saturating_add(self.total_unfollowed_value.as_nano()),
+        );
+        if found == genesis_amount {
+            Ok(())
+        } else {
+            Err(Error::SupplyMismatch {
+                expected: genesis_amount,
+                found,
+            })
+        }
+    }
+}
+
+/// A double spend found by an audit: the conflicting spends held by the Network at an address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DoubleSpendReport {
+    /// The double spent address
+    pub address: SpendAddress,
+    /// The conflicting spends found at the address
+    pub spends: Vec<SignedSpend>,
+}
+
+/// How far an audit went.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditStats {
+    /// The number of generations followed
+    pub generations: usize,
+    /// The number of transactions verified
+    pub txs_verified: usize,
+    /// The time spent following the descendants
+    pub elapsed: Duration,
+    /// The addresses of the invalid spends, whose branches were not followed
+    pub rejected: BTreeSet<SpendAddress>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit_result(utxo_value: u64, unfollowed_value: u64) -> AuditResult {
+        AuditResult {
+            utxos: BTreeSet::new(),
+            double_spends: vec![],
+            stats: AuditStats::default(),
+            total_utxo_value: NanoTokens::from(utxo_value),
+            total_royalties: NanoTokens::from(10),
+            total_unfollowed_value: NanoTokens::from(unfollowed_value),
+        }
+    }
+
+    #[test]
+    fn the_supply_adds_up_with_the_branches_not_followed() {
+        assert!(audit_result(1000, 0)
+            .supply_check(NanoTokens::from(1000))
+            .is_ok());
+        assert!(audit_result(700, 300)
+            .supply_check(NanoTokens::from(1000))
+            .is_ok());
+        assert!(matches!(
+            audit_result(700, 200).supply_check(NanoTokens::from(1000)),
+            Err(Error::SupplyMismatch { expected, found })
+                if expected == NanoTokens::from(1000) && found == NanoTokens::from(900)
+        ));
+    }
+}
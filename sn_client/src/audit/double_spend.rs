@@ -0,0 +1,314 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::Client;
+use crate::{Error, Result};
+
+use libp2p::{kad::Record, PeerId};
+use serde::{Deserialize, Serialize};
+use sn_protocol::{
+    messages::{Query, QueryResponse, Request, Response},
+    storage::{try_deserialize_record, RecordHeader, RecordKind},
+    NetworkAddress,
+};
+use sn_transfers::{SignedSpend, SpendAddress};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use xor_name::XorName;
+
+/// Name of the directory, under the client's root dir, where double spend evidence is archived.
+pub const DOUBLE_SPENDS_DIR_NAME: &str = "double_spends";
+
+/// One of the diverging copies of a spend record, as held by a set of peers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendRecordCopy {
+    /// The signed spends found inside this copy of the record
+    pub spends: Vec<SignedSpend>,
+    /// The peers that returned this copy of the record
+    pub holders: Vec<String>,
+}
+
+/// The evidence bundle persisted when conflicting spends are found at a single `SpendAddress`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoubleSpendEvidence {
+    /// The address the conflicting spends were found at
+    pub address: SpendAddress,
+    /// Seconds since the UNIX epoch at which the split record was observed
+    pub detected_at: u64,
+    /// Every copy of the record returned by the network
+    pub copies: Vec<SpendRecordCopy>,
+}
+
+impl DoubleSpendEvidence {
+    /// Build the evidence from the copies of a split spend record.
+    /// Copies that are not spend records or fail to deserialize are skipped.
+    pub(crate) fn from_split_record(
+        address: SpendAddress,
+        result_map: &HashMap<XorName, (Record, HashSet<PeerId>)>,
+    ) -> Self {
+        let mut copies = vec![];
+        for (record, peers) in result_map.values() {
+            let spends = match RecordHeader::from_record(record) {
+                Ok(header) if header.kind == RecordKind::Spend => {
+                    match try_deserialize_record::<Vec<SignedSpend>>(record) {
+                        Ok(spends) => spends,
+                        Err(err) => {
+                            warn!("Ignoring undeserializable spend copy at {address:?} from {peers:?}: {err:?}");
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    warn!("Ignoring non spend record copy at {address:?} from {peers:?}");
+                    continue;
+                }
+            };
+
+            let mut holders: Vec<_> = peers.iter().map(|peer| peer.to_string()).collect();
+            holders.sort();
+            copies.push(SpendRecordCopy { spends, holders });
+        }
+
+        let detected_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            address,
+            detected_at,
+            copies,
+        }
+    }
+
+    /// Returns the distinct spends, across all the copies, that are valid for this address.
+    pub fn valid_spends(&self) -> BTreeSet<SignedSpend> {
+        self.copies
+            .iter()
+            .flat_map(|copy| copy.spends.iter())
+            .filter(|spend| is_valid_spend_at(self.address, spend))
+            .cloned()
+            .collect()
+    }
+
+    /// Write the evidence as json into the given directory, returning the path of the file written.
+    pub fn persist(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "{}-{}.json",
+            self.address.to_hex(),
+            self.detected_at
+        ));
+        let serialized =
+            serde_json::to_vec_pretty(self).map_err(Error::DoubleSpendEvidenceSerialisation)?;
+        std::fs::write(&path, serialized)?;
+        Ok(path)
+    }
+
+    /// Read back evidence previously written with `persist`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes).map_err(Error::DoubleSpendEvidenceSerialisation)
+    }
+}
+
+/// Whether the spend is one of the unique pubkey of the address, signed by its owner.
+pub(crate) fn is_valid_spend_at(address: SpendAddress, spend: &SignedSpend) -> bool {
+    address == SpendAddress::from_unique_pubkey(spend.unique_pubkey())
+        && spend.verify(spend.spent_tx_hash()).is_ok()
+}
+
+/// Resolve a split spend record.
+/// If the copies only hold a single valid spend, that spend is returned.
+/// If they hold genuinely conflicting spends, the evidence is archived into the `evidence_dir`
+/// and `Error::DoubleSpendDetected` is returned.
+pub(crate) fn resolve_split_spend_record(
+    address: SpendAddress,
+    result_map: &HashMap<XorName, (Record, HashSet<PeerId>)>,
+    evidence_dir: &Path,
+) -> Result<SignedSpend> {
+    let evidence = DoubleSpendEvidence::from_split_record(address, result_map);
+    let mut valid_spends = evidence.valid_spends();
+
+    if valid_spends.len() > 1 {
+        error!("Found double spend for {address:?} across split record copies");
+        match evidence.persist(evidence_dir) {
+            Ok(path) => info!("Archived double spend evidence for {address:?} at {path:?}"),
+            Err(err) => {
+                error!("Failed to archive double spend evidence for {address:?}: {err}")
+            }
+        }
+        return Err(Error::DoubleSpendDetected {
+            address,
+            spends: valid_spends.into_iter().collect(),
+        });
+    }
+
+    match valid_spends.pop_first() {
+        Some(spend) => {
+            debug!("Split record for {address:?} only holds one valid spend");
+            Ok(spend)
+        }
+        None => Err(Error::CouldNotVerifyTransfer(format!(
+            "Split record for {address:?} holds no valid spend"
+        ))),
+    }
+}
+
+impl Client {
+    /// Ask the close group of a spend address for the conflicting spends they hold there.
+    ///
+    /// Returns the distinct spends, across all the nodes, that are valid for this address.
+    /// This is empty if none of the nodes witnessed a double spend at that address.
+    pub async fn get_spend_conflicts(
+        &self,
+        address: SpendAddress,
+    ) -> Result<BTreeSet<SignedSpend>> {
+        let net_addr = NetworkAddress::from_spend_address(address);
+        let close_nodes = self.close_group(&net_addr).await?;
+        let request = Request::Query(Query::GetSpendConflicts { address });
+        let responses = self
+            .network
+            .send_and_get_responses(&close_nodes, &request, true)
+            .await;
+
+        let mut conflicts = BTreeSet::new();
+        for (peer, response) in responses {
+            match response {
+                Ok(Response::Query(QueryResponse::GetSpendConflicts(Ok(spends)))) => {
+                    for spend in spends {
+                        let is_valid = address
+                            == SpendAddress::from_unique_pubkey(spend.unique_pubkey())
+                            && spend.verify(spend.spent_tx_hash()).is_ok();
+                        if is_valid {
+                            let _ = conflicts.insert(spend);
+                        } else {
+                            warn!(
+                                "Ignoring invalid conflicting spend at {address:?} from {peer:?}"
+                            );
+                        }
+                    }
+                }
+                other => debug!("No spend conflicts at {address:?} from {peer:?}: {other:?}"),
+            }
+        }
+
+        Ok(conflicts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_protocol::storage::try_serialize_record;
+    use sn_transfers::{Hash, NanoTokens, Spend, Transaction, UniquePubkey};
+
+    fn signed_spend(sk: &bls::SecretKey, reason: &[u8]) -> SignedSpend {
+        let spend = Spend {
+            unique_pubkey: UniquePubkey::new(sk.public_key()),
+            spent_tx: Transaction::default(),
+            reason: Hash::hash(reason),
+            token: NanoTokens::zero(),
+            parent_tx: Transaction::default(),
+            network_royalties: vec![],
+        };
+        let derived_key_sig = sk.sign(spend.to_bytes());
+        SignedSpend {
+            spend,
+            derived_key_sig,
+        }
+    }
+
+    fn spend_record(address: SpendAddress, spends: &[SignedSpend]) -> eyre::Result<Record> {
+        Ok(Record {
+            key: NetworkAddress::from_spend_address(address).to_record_key(),
+            value: try_serialize_record(&spends, RecordKind::Spend)?.to_vec(),
+            publisher: None,
+            expires: None,
+        })
+    }
+
+    #[test]
+    fn test_split_record_with_conflicting_spends_is_archived() -> eyre::Result<()> {
+        let sk = bls::SecretKey::random();
+        let spend_one = signed_spend(&sk, b"one");
+        let spend_two = signed_spend(&sk, b"two");
+        let address = SpendAddress::from_unique_pubkey(spend_one.unique_pubkey());
+
+        let record_one = spend_record(address, &[spend_one.clone()])?;
+        let record_two = spend_record(address, &[spend_two.clone()])?;
+        let peers_one = HashSet::from_iter([PeerId::random(), PeerId::random()]);
+        let peers_two = HashSet::from_iter([PeerId::random()]);
+        let result_map = HashMap::from_iter([
+            (
+                XorName::from_content(&record_one.value),
+                (record_one, peers_one.clone()),
+            ),
+            (
+                XorName::from_content(&record_two.value),
+                (record_two, peers_two),
+            ),
+        ]);
+
+        let evidence = DoubleSpendEvidence::from_split_record(address, &result_map);
+        assert_eq!(evidence.copies.len(), 2);
+        assert_eq!(
+            evidence.valid_spends(),
+            BTreeSet::from_iter([spend_one.clone(), spend_two])
+        );
+
+        let tmp_dir = tempfile::tempdir()?;
+        let path = evidence.persist(&tmp_dir.path().join(DOUBLE_SPENDS_DIR_NAME))?;
+        let loaded = DoubleSpendEvidence::load(&path)?;
+        assert_eq!(loaded, evidence);
+        assert_eq!(loaded.address, address);
+
+        let copy_one = loaded
+            .copies
+            .iter()
+            .find(|copy| copy.spends == vec![spend_one.clone()])
+            .ok_or_else(|| eyre::eyre!("missing first copy"))?;
+        let mut expected_holders: Vec<_> = peers_one.iter().map(|p| p.to_string()).collect();
+        expected_holders.sort();
+        assert_eq!(copy_one.holders, expected_holders);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_record_with_a_single_valid_spend_is_not_a_double_spend() -> eyre::Result<()> {
+        let sk = bls::SecretKey::random();
+        let spend = signed_spend(&sk, b"one");
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+
+        // a copy signed by someone else is not a valid spend for this address
+        let mut forged = signed_spend(&sk, b"two");
+        forged.derived_key_sig = bls::SecretKey::random().sign(forged.spend.to_bytes());
+
+        let record_one = spend_record(address, &[spend.clone()])?;
+        let record_two = spend_record(address, &[forged])?;
+        let result_map = HashMap::from_iter([
+            (
+                XorName::from_content(&record_one.value),
+                (record_one, HashSet::from_iter([PeerId::random()])),
+            ),
+            (
+                XorName::from_content(&record_two.value),
+                (record_two, HashSet::from_iter([PeerId::random()])),
+            ),
+        ]);
+
+        let evidence = DoubleSpendEvidence::from_split_record(address, &result_map);
+        assert_eq!(evidence.valid_spends(), BTreeSet::from_iter([spend]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sn_transfers::{Hash, NanoTokens, SpendAddress};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// The file name the audit frontier is stored at under the client's data dir.
+pub const AUDIT_FRONTIER_FILE_NAME: &str = "audit_frontier.json";
+
+/// Where an audit following the spends stopped: the UTXOs found, and the transactions verified
+/// on the way to them, along with the value found so far for the supply to be checked.
+///
+/// Stored between sessions, it lets `Client::follow_spend_from` carry on with the spends made
+/// since, rather than auditing the whole currency again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditFrontier {
+    /// The addresses of the outputs found unspent
+    pub utxos: BTreeSet<SpendAddress>,
+    /// The hashes of the transactions verified, not to be followed again
+    pub verified_txs: BTreeSet<Hash>,
+    /// The value of the UTXOs, as found in the transaction they are an output of. The UTXOs
+    /// the frontier was created with have none until spent.
+    #[serde(default, with = "map_as_pairs")]
+    pub utxo_values: BTreeMap<SpendAddress, NanoTokens>,
+    /// The value held at the addresses whose branches were not followed, double spent or
+    /// rejected, since the audit started
+    #[serde(default)]
+    pub unfollowed_value: NanoTokens,
+}
+
+impl AuditFrontier {
+    /// A frontier to follow the given spends from, nothing being verified yet.
+    pub fn new(utxos: BTreeSet<SpendAddress>) -> Self {
+        Self {
+            utxos,
+            ..Default::default()
+        }
+    }
+
+    /// The value of the UTXOs known to the frontier.
+    pub fn utxo_value(&self) -> NanoTokens {
+        let value = self
+            .utxos
+            .iter()
+            .filter_map(|addr| self.utxo_values.get(addr))
+            .fold(0u64, |value, amount| value.saturating_add(amount.as_nano()));
+        NanoTokens::from(value)
+    }
+
+    /// Load the frontier stored under the given root dir, if any.
+    pub fn load_from(root_dir: &Path) -> Result<Option<Self>> {
+        let path = root_dir.join(AUDIT_FRONTIER_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = std::fs::read(path)?;
+        let frontier = serde_json::from_slice(&bytes).map_err(Error::AuditFrontierSerialisation)?;
+        Ok(Some(frontier))
+    }
+
+    /// Store the frontier under the given root dir, replacing the one stored before.
+    pub fn save_to(&self, root_dir: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec(self).map_err(Error::AuditFrontierSerialisation)?;
+        std::fs::write(root_dir.join(AUDIT_FRONTIER_FILE_NAME), bytes)?;
+        Ok(())
+    }
+}
+
+/// Maps are stored as a list of pairs, their keys not being strings.
+pub(super) mod map_as_pairs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub(in crate::audit) fn serialize<K, V, S>(
+        map: &BTreeMap<K, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub(in crate::audit) fn deserialize<'de, K, V, D>(
+        deserializer: D,
+    ) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let pairs = Vec::<(K, V)>::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xor_name::XorName;
+
+    #[test]
+    fn frontiers_round_trip_through_the_data_dir() -> Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        assert_eq!(AuditFrontier::load_from(root_dir.path())?, None);
+
+        let mut frontier = AuditFrontier::new(BTreeSet::from([
+            SpendAddress::new(XorName([1; 32])),
+            SpendAddress::new(XorName([2; 32])),
+        ]));
+        let _ = frontier.verified_txs.insert(Hash::hash(b"tx"));
+        let _ = frontier
+            .utxo_values
+            .insert(SpendAddress::new(XorName([1; 32])), NanoTokens::from(700));
+        frontier.unfollowed_value = NanoTokens::from(300);
+        frontier.save_to(root_dir.path())?;
+        let loaded = AuditFrontier::load_from(root_dir.path())?;
+        assert_eq!(
+            loaded.as_ref().map(|f| f.utxo_value()),
+            Some(NanoTokens::from(700))
+        );
+        assert_eq!(loaded, Some(frontier));
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{is_valid_spend_at, AuditProgress, SpendDag};
+use crate::{Client, Error, Result, TopicSubscription};
+
+use bls::PK_SIZE;
+use sn_transfers::{CashNoteRedemption, SignedSpend, SpendAddress};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::{sync::mpsc, task::AbortHandle};
+
+/// The most spends `Client::follow_spends_live` holds back until their parents are in the DAG,
+/// the oldest being dropped past it.
+pub const MAX_PENDING_LIVE_SPENDS: usize = 1024;
+
+/// A `SpendDag` kept up to date with the spends announced over gossipsub, see
+/// `Client::follow_spends_live`.
+///
+/// The DAG stops being updated once this is dropped.
+pub struct LiveSpendDag {
+    dag: Arc<Mutex<SpendDag>>,
+    progress: mpsc::UnboundedReceiver<AuditProgress>,
+    task: AbortHandle,
+}
+
+impl LiveSpendDag {
+    /// A copy of the DAG as it stands.
+    pub fn dag(&self) -> SpendDag {
+        lock(&self.dag).clone()
+    }
+
+    /// Wait for the next spend announced to be inserted, queued or rejected.
+    ///
+    /// None once the subscription to the topic has ended.
+    pub async fn next_progress(&mut self) -> Option<AuditProgress> {
+        self.progress.recv().await
+    }
+
+    /// Stop updating the DAG, returning it as it stands.
+    pub fn stop(self) -> SpendDag {
+        self.task.abort();
+        self.dag()
+    }
+}
+
+impl Drop for LiveSpendDag {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The state of the task following the spends announced.
+struct LiveFollower {
+    client: Client,
+    dag: Arc<Mutex<SpendDag>>,
+    progress: mpsc::UnboundedSender<AuditProgress>,
+    /// The spends whose parents aren't in the DAG yet, the oldest first
+    pending: VecDeque<SignedSpend>,
+}
+
+impl LiveFollower {
+    async fn run(mut self, mut subscription: TopicSubscription) {
+        while let Some(msg) = subscription.recv().await {
+            let Some(addrs) = announced_spends(&msg) else {
+                warn!(
+                    "Message received on topic {} isn't a transfer notification",
+                    subscription.topic()
+                );
+                continue;
+            };
+            for addr in addrs {
+                self.follow(addr).await;
+            }
+        }
+        debug!("Subscription to {} ended", subscription.topic());
+    }
+
+    /// Fetch the spend announced and insert it into the DAG, or queue it if its parents aren't
+    /// there yet.
+    async fn follow(&mut self, addr: SpendAddress) {
+        if lock(&self.dag).is_spent(&addr) {
+            trace!("Spend at {addr:?} already in the live DAG");
+            return;
+        }
+        if self
+            .pending
+            .iter()
+            .any(|spend| SpendAddress::from_unique_pubkey(spend.unique_pubkey()) == addr)
+        {
+            trace!("Spend at {addr:?} already queued");
+            return;
+        }
+
+        match self.client.get_spend_from_network(addr).await {
+            Ok(spend) => {
+                if lock(&self.dag).has_parents_of(&spend) {
+                    self.insert(addr, spend);
+                    self.insert_pending();
+                } else {
+                    self.queue(addr, spend);
+                }
+            }
+            Err(Error::DoubleSpendDetected { spends, .. }) => {
+                warn!("Found double spend at {addr:?}, not following its descendants");
+                // only verified spends make it into the DAG
+                let spends: Vec<_> = spends
+                    .into_iter()
+                    .filter(|spend| is_valid_spend_at(addr, spend))
+                    .collect();
+                {
+                    let mut dag = lock(&self.dag);
+                    for spend in spends.iter() {
+                        dag.insert(addr, spend.clone());
+                    }
+                }
+                self.report(AuditProgress::SpendConflicts {
+                    address: addr,
+                    conflicts: spends,
+                });
+            }
+            Err(err) => self.report(AuditProgress::LiveSpendRejected {
+                address: addr,
+                error: err.to_string(),
+            }),
+        }
+    }
+
+    fn insert(&self, addr: SpendAddress, spend: SignedSpend) {
+        lock(&self.dag).insert(addr, spend);
+        self.report(AuditProgress::LiveSpendInserted(addr));
+    }
+
+    fn queue(&mut self, addr: SpendAddress, spend: SignedSpend) {
+        if self.pending.len() >= MAX_PENDING_LIVE_SPENDS {
+            if let Some(dropped) = self.pending.pop_front() {
+                warn!(
+                    "Too many spends pending, dropping the one at {:?}",
+                    SpendAddress::from_unique_pubkey(dropped.unique_pubkey())
+                );
+            }
+        }
+        self.pending.push_back(spend);
+        self.report(AuditProgress::LiveSpendQueued(addr));
+    }
+
+    /// Insert the pending spends whose parents are now in the DAG, until none are left.
+    fn insert_pending(&mut self) {
+        loop {
+            let ready = {
+                let dag = lock(&self.dag);
+                self.pending
+                    .iter()
+                    .position(|spend| dag.has_parents_of(spend))
+            };
+            let Some(spend) = ready.and_then(|idx| self.pending.remove(idx)) else {
+                return;
+            };
+            self.insert(
+                SpendAddress::from_unique_pubkey(spend.unique_pubkey()),
+                spend,
+            );
+        }
+    }
+
+    fn report(&self, event: AuditProgress) {
+        // nobody waiting on the progress is no reason to stop updating the DAG
+        let _ = self.progress.send(event);
+    }
+}
+
+/// The addresses of the spends a transfer notification announces: those of the parent spends
+/// of its `CashNoteRedemption`s. The notification starts with the public key of the beneficiary.
+fn announced_spends(msg: &[u8]) -> Option<Vec<SpendAddress>> {
+    let redemptions: Vec<CashNoteRedemption> = rmp_serde::from_slice(msg.get(PK_SIZE..)?).ok()?;
+    Some(
+        redemptions
+            .iter()
+            .map(|redemption| redemption.parent_spend)
+            .collect(),
+    )
+}
+
+fn lock(dag: &Mutex<SpendDag>) -> MutexGuard<'_, SpendDag> {
+    // inserting a spend is no reason to leave the DAG unusable if it panics
+    dag.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Client {
+    /// Keep the DAG updated with the spends announced on the given gossipsub topic, e.g. the
+    /// network royalties transfer notifications the nodes publish as they're paid.
+    ///
+    /// The spends announced are fetched and verified before being inserted, each of them
+    /// reported with `AuditProgress::LiveSpendInserted`. Those whose parents aren't in the DAG
+    /// yet are queued with `AuditProgress::LiveSpendQueued`, up to `MAX_PENDING_LIVE_SPENDS` of
+    /// them, and inserted once their parents are.
+    pub fn follow_spends_live(
+        &self,
+        dag: SpendDag,
+        topic: impl Into<String>,
+    ) -> Result<LiveSpendDag> {
+        let subscription = self.subscribe(topic)?;
+        let dag = Arc::new(Mutex::new(dag));
+        let (sender, progress) = mpsc::unbounded_channel();
+        let follower = LiveFollower {
+            // the task doesn't share the tasks, otherwise they'd never be aborted on drop
+            client: Client {
+                tasks: Default::default(),
+                ..self.clone()
+            },
+            dag: dag.clone(),
+            progress: sender,
+            pending: VecDeque::new(),
+        };
+        info!("Following the spends announced on {}", subscription.topic());
+        let task = tokio::spawn(follower.run(subscription));
+
+        Ok(LiveSpendDag {
+            dag,
+            progress,
+            task: task.abort_handle(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::{BufMut, BytesMut};
+    use serde::Serialize;
+    use sn_transfers::{DerivationIndex, MainSecretKey, NETWORK_ROYALTIES_PK};
+
+    #[test]
+    fn the_parent_spends_of_transfer_notifications_are_announced() -> eyre::Result<()> {
+        let parent_spend = SpendAddress::from_unique_pubkey(
+            &MainSecretKey::random()
+                .main_pubkey()
+                .new_unique_pubkey(&DerivationIndex([1; 32])),
+        );
+        let redemptions = vec![CashNoteRedemption::new(
+            DerivationIndex([2; 32]),
+            parent_spend,
+        )];
+
+        // as the nodes publish them
+        let mut msg = BytesMut::new();
+        msg.extend_from_slice(&NETWORK_ROYALTIES_PK.public_key().to_bytes());
+        let mut msg = msg.writer();
+        redemptions.serialize(&mut rmp_serde::Serializer::new(&mut msg))?;
+        let msg = msg.into_inner().freeze();
+
+        assert_eq!(announced_spends(&msg), Some(vec![parent_spend]));
+        assert_eq!(announced_spends(&msg[..PK_SIZE - 1]), None);
+        assert_eq!(announced_spends(b"not a transfer notification"), None);
+        Ok(())
+    }
+}
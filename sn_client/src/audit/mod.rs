@@ -6,25 +6,75 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod audit_result;
+mod double_spend;
+mod frontier;
+mod live;
+mod progress;
+mod royalty_report;
 mod spend_dag;
+mod spend_verification;
+
+pub use audit_result::{AuditResult, AuditStats, DoubleSpendReport};
+pub(crate) use double_spend::{is_valid_spend_at, resolve_split_spend_record};
+pub use double_spend::{DoubleSpendEvidence, SpendRecordCopy, DOUBLE_SPENDS_DIR_NAME};
+pub use frontier::{AuditFrontier, AUDIT_FRONTIER_FILE_NAME};
+pub use live::{LiveSpendDag, MAX_PENDING_LIVE_SPENDS};
+pub use progress::{print_audit_progress, AuditProgress, AuditProgressCallback};
+pub use royalty_report::{
+    royalty_report_csv, write_royalty_report, BucketSize, RoyaltyBucket, RoyaltyObservation,
+    RoyaltyTracker, ROYALTY_OBSERVATIONS_FILE_NAME,
+};
+pub use spend_dag::{
+    write_anomaly_report, Anomaly, AnomalySeverity, SpendDag, DUST_TX_MAX_VALUE,
+    HIGH_FAN_OUT_MIN_OUTPUTS, MAX_SPEND_PATHS, MAX_SPEND_PATH_LEN, SPEND_DAG_FILE_NAME,
+    SPEND_DAG_FORMAT_VERSION,
+};
+pub use spend_verification::SpendVerificationReport;
 
 use super::{
     error::{Error, Result},
     Client,
 };
 
-use futures::future::join_all;
+use futures::{future::join_all, stream, Future, StreamExt};
 use sn_transfers::{
-    CashNoteRedemption, SignedSpend, SpendAddress, Transfer, WalletError, WalletResult,
-    NETWORK_ROYALTIES_PK,
+    is_genesis_parent_tx, CashNote, CashNoteRedemption, Error as TransferError, Hash, MainPubkey,
+    NanoTokens, Result as TransferResult, SignedRoyaltiesClaim, SignedSpend, SpendAddress,
+    Transaction, Transfer, UniquePubkey, UnsignedRoyaltiesClaim, WalletError, WalletResult,
+    GENESIS_CASHNOTE, NETWORK_ROYALTIES_PK,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter::Iterator,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
 };
-use std::{collections::BTreeSet, iter::Iterator, path::Path};
+
+/// The most royalties an audit looking for them redeems with a single `Transfer`.
+pub const MAX_ROYALTIES_PER_TRANSFER: usize = 100;
+
+/// Where an audit following the spends records them, and reports its progress.
+struct AuditRecorders<'a, 'p> {
+    find_royalties: bool,
+    royalty_tracker: Option<&'a mut RoyaltyTracker>,
+    spend_dag: Option<&'a mut SpendDag>,
+    root_dir: &'a Path,
+    progress: AuditProgressCallback<'p>,
+    /// The royalties found since the last redemption, by their unique pubkey
+    royalties: BTreeMap<UniquePubkey, CashNoteRedemption>,
+    /// The royalties which could not be redeemed, tried again with those of the next generation
+    unredeemed_royalties: BTreeMap<UniquePubkey, CashNoteRedemption>,
+    /// The amounts of all the royalties paid by the spends followed, by their unique pubkey
+    royalty_outputs: BTreeMap<UniquePubkey, NanoTokens>,
+}
 
 impl Client {
     /// Verify that a spend is valid on the network.
     /// Optionally verify its ancestors as well, all the way to genesis (might take a LONG time)
     ///
-    /// Prints progress on stdout.
+    /// Returns what was verified, see `Client::verify_spend_with_progress` to follow it as it
+    /// goes.
     ///
     /// When verifying all the way back to genesis, it only verifies Spends that are ancestors of the given Spend,
     /// ignoring all other branches.
@@ -47,91 +97,294 @@ impl Client {
     /// ```
     ///
     /// This function will return an error if any spend in the way is invalid.
-    pub async fn verify_spend(&self, addr: SpendAddress, to_genesis: bool) -> WalletResult<()> {
+    ///
+    /// The walk to genesis stops early once `max_depth` generations of ancestors were verified,
+    /// or once `max_spends` spends were fetched, if given. The report is then partial, holding the
+    /// frontier of the transactions left to verify, from which
+    /// `Client::resume_spend_verification` carries on later. Stopping at those limits isn't an
+    /// error.
+    ///
+    /// The spends of the parent transactions of a generation are fetched for
+    /// `Client::audit_concurrency` of them at once.
+    pub async fn verify_spend(
+        &self,
+        addr: SpendAddress,
+        to_genesis: bool,
+        max_depth: Option<usize>,
+        max_spends: Option<usize>,
+    ) -> WalletResult<SpendVerificationReport> {
+        self.verify_spend_with_progress(addr, to_genesis, max_depth, max_spends, None)
+            .await
+    }
+
+    /// Verify that a spend is valid on the network, as `Client::verify_spend` does, reporting
+    /// its progress to the `progress` callback, if any.
+    pub async fn verify_spend_with_progress(
+        &self,
+        addr: SpendAddress,
+        to_genesis: bool,
+        max_depth: Option<usize>,
+        max_spends: Option<usize>,
+        progress: AuditProgressCallback<'_>,
+    ) -> WalletResult<SpendVerificationReport> {
         let first_spend = self
             .get_spend_from_network(addr)
             .await
-            .map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
+            .map_err(|err| spend_error_to_wallet_error(err, None))?;
 
+        let report = SpendVerificationReport::new(addr);
         if !to_genesis {
-            return Ok(());
+            return Ok(report);
         }
+        self.verify_ancestors(
+            report,
+            vec![first_spend.spend.parent_tx],
+            max_depth,
+            max_spends,
+            progress,
+        )
+        .await
+    }
+
+    /// Carry on with a verification to genesis which stopped at its limits, from its frontier,
+    /// within the new limits given. A complete report is returned as it is.
+    ///
+    /// The limits apply to this run alone, not counting what the report already verified.
+    pub async fn resume_spend_verification(
+        &self,
+        mut report: SpendVerificationReport,
+        max_depth: Option<usize>,
+        max_spends: Option<usize>,
+        progress: AuditProgressCallback<'_>,
+    ) -> WalletResult<SpendVerificationReport> {
+        let frontier = std::mem::take(&mut report.frontier);
+        self.verify_ancestors(report, frontier, max_depth, max_spends, progress)
+            .await
+    }
 
+    /// Verify the ancestors of the spend of the report, from the given frontier of transactions,
+    /// adding what was verified to the report.
+    async fn verify_ancestors(
+        &self,
+        mut report: SpendVerificationReport,
+        frontier: Vec<Transaction>,
+        max_depth: Option<usize>,
+        max_spends: Option<usize>,
+        mut progress: AuditProgressCallback<'_>,
+    ) -> WalletResult<SpendVerificationReport> {
+        let addr = report.address;
         // use iteration instead of recursion to avoid stack overflow
-        let mut txs_to_verify = BTreeSet::from_iter([first_spend.spend.parent_tx]);
-        let mut depth = 0;
-        let mut verified_tx = BTreeSet::new();
+        // the txs left to verify per generation, a budget cut leaving two of them at the frontier
+        let mut pending: BTreeMap<usize, BTreeSet<Transaction>> = BTreeMap::new();
+        let frontier_depths = std::mem::take(&mut report.frontier_depths);
+        for tx in frontier {
+            let generation = frontier_depths
+                .get(&tx.hash())
+                .copied()
+                .unwrap_or(report.depth + 1);
+            let _ = pending.entry(generation).or_default().insert(tx);
+        }
+        let mut verified_tx: BTreeSet<_> = report.verified_txs.iter().copied().collect();
+        let elapsed_before = report.elapsed;
         let start = std::time::Instant::now();
+        let mut generations = 0;
+        let mut spends_fetched = 0;
 
-        while !txs_to_verify.is_empty() {
+        while let Some(mut entry) = pending.first_entry() {
+            // the same tx may be reached through branches of different lengths
+            entry
+                .get_mut()
+                .retain(|tx| !verified_tx.contains(&tx.hash()));
+            if entry.get().is_empty() {
+                let _ = entry.remove();
+                continue;
+            }
+            let generation = *entry.key();
+            let depth = generation - 1;
+            if max_depth.is_some_and(|max_depth| generations >= max_depth) {
+                debug!("Depth {depth} - Reached the max depth of {generations} generations");
+                break;
+            }
+            if max_spends.is_some_and(|max_spends| spends_fetched >= max_spends) {
+                debug!("Depth {depth} - Reached the max of {spends_fetched} spends fetched");
+                break;
+            }
+            let txs_to_verify = entry.remove();
             let mut next_gen_tx = BTreeSet::new();
+            let mut left_unverified = vec![];
+            progress::report(
+                &mut progress,
+                AuditProgress::GenerationStarted {
+                    generation,
+                    txs: txs_to_verify.len(),
+                },
+            );
 
-            for parent_tx in txs_to_verify {
+            // pick the txs within budget first, their spends being fetched concurrently
+            let mut txs = txs_to_verify.into_iter();
+            let mut to_verify = vec![];
+            let mut spends_to_fetch = spends_fetched;
+            for parent_tx in txs.by_ref() {
+                if max_spends.is_some_and(|max_spends| spends_to_fetch >= max_spends) {
+                    debug!("Depth {depth} - Reached the max of {spends_to_fetch} spends to fetch");
+                    left_unverified.push(parent_tx);
+                    break;
+                }
+                // the genesis Tx doesn't change, once verified there's no need to fetch it again
+                let verified_genesis = is_genesis_parent_tx(&parent_tx)
+                    && self.genesis_verified.load(Ordering::SeqCst);
+                if !verified_genesis {
+                    spends_to_fetch += parent_tx.inputs.len();
+                }
+                to_verify.push((parent_tx, verified_genesis));
+            }
+
+            let (fetched, concurrent) = fetch_concurrently(
+                to_verify.iter(),
+                self.audit_concurrency,
+                |(parent_tx, verified_genesis)| async move {
+                    if *verified_genesis {
+                        None
+                    } else {
+                        Some(self.get_input_spends(parent_tx).await)
+                    }
+                },
+            )
+            .await;
+            progress::report(
+                &mut progress,
+                AuditProgress::TxsFetched {
+                    generation: depth + 1,
+                    txs: fetched.len(),
+                    concurrent,
+                },
+            );
+
+            for ((parent_tx, _), spends_res) in to_verify.into_iter().zip(fetched) {
                 let parent_tx_hash = parent_tx.hash();
-                let parent_keys = parent_tx.inputs.iter().map(|input| input.unique_pubkey);
-                let addrs_to_verify = parent_keys.map(|k| SpendAddress::from_unique_pubkey(&k));
-                debug!("Depth {depth} - Verifying parent Tx : {parent_tx_hash:?}");
+                let spends_res = match spends_res {
+                    Some(spends_res) => spends_res,
+                    None => {
+                        debug!(
+                            "Depth {depth} - Reached already verified genesis Tx: {parent_tx_hash:?}"
+                        );
+                        if verified_tx.insert(parent_tx_hash) {
+                            report.verified_txs.push(parent_tx_hash);
+                        }
+                        report.reached_genesis = true;
+                        continue;
+                    }
+                };
 
-                // get all parent spends in parallel
-                let tasks: Vec<_> = addrs_to_verify
-                    .into_iter()
-                    .map(|a| self.get_spend_from_network(a))
-                    .collect();
-                let spends = join_all(tasks).await
+                let spends = spends_res
                     .into_iter()
                     .collect::<Result<BTreeSet<_>>>()
-                    .map_err(|err| WalletError::CouldNotVerifyTransfer(format!("at depth {depth} - Failed to get spends from network for parent Tx {parent_tx_hash:?}: {err}")))?;
+                    .map_err(|err| spend_error_to_wallet_error(err, Some(format!("at depth {depth} - Failed to get spends from network for parent Tx {parent_tx_hash:?}"))))?;
                 debug!(
                     "Depth {depth} - Got {:?} spends for parent Tx: {parent_tx_hash:?}",
                     spends.len()
                 );
+                spends_fetched += spends.len();
+                report.spends_fetched += spends.len();
+                progress::report(
+                    &mut progress,
+                    AuditProgress::SpendsFetched {
+                        generation: depth + 1,
+                        tx: parent_tx_hash,
+                        spends: spends.len(),
+                    },
+                );
                 trace!("Spends for {parent_tx_hash:?} - {spends:?}");
 
+                // verify tx with those spends
+                let is_genesis = verify_parent_tx(&parent_tx, &spends).map_err(|err| {
+                    WalletError::CouldNotVerifyTransfer(format!(
+                        "at depth {depth} - Failed to verify parent Tx {parent_tx_hash:?}: {err}"
+                    ))
+                })?;
+                if verified_tx.insert(parent_tx_hash) {
+                    report.verified_txs.push(parent_tx_hash);
+                }
+
                 // check if we reached the genesis Tx
-                if parent_tx == sn_transfers::GENESIS_CASHNOTE.src_tx
-                    && spends
-                        .iter()
-                        .all(|s| s.spend.unique_pubkey == sn_transfers::GENESIS_CASHNOTE.id)
-                    && spends.len() == 1
-                {
+                if is_genesis {
                     debug!("Depth {depth} - Reached genesis Tx on one branch: {parent_tx_hash:?}");
-                    verified_tx.insert(parent_tx_hash);
+                    self.genesis_verified.store(true, Ordering::SeqCst);
+                    report.reached_genesis = true;
                     continue;
                 }
-
-                // verify tx with those spends
-                parent_tx
-                    .verify_against_inputs_spent(&spends)
-                    .map_err(|err| WalletError::CouldNotVerifyTransfer(format!("at depth {depth} - Failed to verify parent Tx {parent_tx_hash:?}: {err}")))?;
-                verified_tx.insert(parent_tx_hash);
                 debug!("Depth {depth} - Verified parent Tx: {parent_tx_hash:?}");
 
                 // add new parent spends to next gen
                 next_gen_tx.extend(spends.into_iter().map(|s| s.spend.parent_tx));
             }
-
             // only verify parents we haven't already verified
-            txs_to_verify = next_gen_tx
-                .into_iter()
-                .filter(|tx| !verified_tx.contains(&tx.hash()))
-                .collect();
+            next_gen_tx.retain(|tx| !verified_tx.contains(&tx.hash()));
+            if !next_gen_tx.is_empty() {
+                pending
+                    .entry(generation + 1)
+                    .or_default()
+                    .extend(next_gen_tx);
+            }
+            // what's left of the generation once out of budget is part of the frontier
+            left_unverified.extend(txs);
+            report.elapsed = elapsed_before + start.elapsed();
+            if !left_unverified.is_empty() {
+                pending
+                    .entry(generation)
+                    .or_default()
+                    .extend(left_unverified);
+                break;
+            }
 
-            depth += 1;
-            let elapsed = start.elapsed();
-            let n = verified_tx.len();
-            println!("Now at depth {depth} - Verified {n} transactions in {elapsed:?}");
+            generations += 1;
+            report.depth = report.depth.max(generation);
+            debug!(
+                "Now at depth {generation} - Verified {} transactions in {:?}",
+                report.txs_verified(),
+                report.elapsed
+            );
+            progress::report(
+                &mut progress,
+                AuditProgress::GenerationVerified(report.clone()),
+            );
         }
 
-        let elapsed = start.elapsed();
-        let n = verified_tx.len();
-        println!("Verified all the way to genesis! Through {depth} generations, verifying {n} transactions in {elapsed:?}");
-        Ok(())
+        report.elapsed = elapsed_before + start.elapsed();
+        // the frontier keeps the generation of each tx, for the depth to be right once resumed
+        for (generation, txs) in pending {
+            for tx in txs {
+                let tx_hash = tx.hash();
+                if verified_tx.contains(&tx_hash) {
+                    continue;
+                }
+                let _ = report.frontier_depths.insert(tx_hash, generation);
+                report.frontier.push(tx);
+            }
+        }
+        let depth = report.depth;
+        if report.is_partial() {
+            info!(
+                "Stopped verifying {addr:?} at its limits at depth {depth}, having verified {} transactions in {:?}, with {} left at the frontier",
+                report.txs_verified(),
+                report.elapsed,
+                report.frontier.len()
+            );
+        } else {
+            info!(
+                "Verified {addr:?} through {depth} generations, verifying {} transactions in {:?}",
+                report.txs_verified(),
+                report.elapsed
+            );
+        }
+        Ok(report)
     }
 
     /// This function does the opposite of verify_spend.
     /// It recursively follows the descendants of a Spend, all the way to unspent Transaction Outputs (UTXOs).
     ///
-    /// Prints progress on stdout
+    /// Reports its progress to the `progress` callback, if any, `print_audit_progress` printing
+    /// it on stdout.
     ///
     /// Starting from Genesis, this amounts to Auditing the entire currency.
     /// This is how the DAG it follows could look like:
@@ -150,24 +403,232 @@ impl Client {
     /// ```
     ///
     /// This function will return the UTXOs (Spend addresses not spent yet)
-    /// Future calls to this function could start from those UTXOs to avoid
+    /// Use `Client::follow_spend_from` to carry on from those UTXOs later, avoiding
     /// re-checking all previously checked branches.
+    ///
+    /// Double spends don't stop the audit: they are returned along with the UTXOs, and their
+    /// branches left out while the others are followed.
+    ///
+    /// The spends of the descendant transactions of a generation are fetched for
+    /// `Client::audit_concurrency` of them at once.
+    ///
+    /// The royalties paid by the followed spends are recorded by the `royalty_tracker`, if any.
+    /// The followed spends are inserted into the `spend_dag`, if any, for its anomalies to be
+    /// detected with `SpendDag::detect_anomalies` once done.
     pub async fn follow_spend(
         &self,
         spend_addr: SpendAddress,
         find_royalties: bool,
+        royalty_tracker: Option<&mut RoyaltyTracker>,
+        spend_dag: Option<&mut SpendDag>,
         root_dir: &Path,
-    ) -> WalletResult<BTreeSet<SpendAddress>> {
-        let first_spend = self
-            .get_spend_from_network(spend_addr)
-            .await
-            .map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
-        println!("Generation 0 - Found first spend: {spend_addr:#?}");
+        progress: AuditProgressCallback<'_>,
+    ) -> WalletResult<AuditResult> {
+        let mut recorders = AuditRecorders {
+            find_royalties,
+            royalty_tracker,
+            spend_dag,
+            root_dir,
+            progress,
+            royalties: BTreeMap::new(),
+            unredeemed_royalties: BTreeMap::new(),
+            royalty_outputs: BTreeMap::new(),
+        };
+        let first_spend = match self.get_spend_from_network(spend_addr).await {
+            Ok(spend) => spend,
+            Err(Error::DoubleSpendDetected { address, spends }) => {
+                warn!("The first spend {address:?} is double spent, there is nothing to follow");
+                return Ok(AuditResult {
+                    utxos: BTreeSet::new(),
+                    double_spends: vec![DoubleSpendReport { address, spends }],
+                    stats: AuditStats::default(),
+                    total_utxo_value: NanoTokens::zero(),
+                    total_royalties: NanoTokens::zero(),
+                    total_unfollowed_value: NanoTokens::zero(),
+                });
+            }
+            Err(err) => return Err(spend_error_to_wallet_error(err, None)),
+        };
+        progress::report(
+            &mut recorders.progress,
+            AuditProgress::FirstSpendFound(spend_addr),
+        );
+        if let Some(dag) = recorders.spend_dag.as_deref_mut() {
+            dag.insert(spend_addr, first_spend.clone());
+        }
+        collect_royalty_outputs(
+            std::slice::from_ref(&first_spend),
+            &mut recorders.royalty_outputs,
+        );
+
+        self.follow_txs(
+            BTreeSet::from_iter([first_spend.spend.spent_tx]),
+            &mut AuditFrontier::default(),
+            BTreeSet::new(),
+            vec![],
+            &mut recorders,
+        )
+        .await
+    }
 
+    /// Carry on with an audit from the frontier a previous one stopped at, following the UTXOs
+    /// it found which were spent since, and skipping the transactions it verified. The spends of
+    /// the UTXOs it found have to come from the transactions it verified.
+    ///
+    /// Returns the new frontier to carry on from next time: the UTXOs still unspent and those
+    /// found on the way, along with all the transactions verified. `AuditFrontier::save_to` stores
+    /// it between sessions.
+    ///
+    /// The spends followed are recorded as `Client::follow_spend` does, and the `AuditResult` of
+    /// this session is returned along with the frontier, with the double spends found on the way.
+    pub async fn follow_spend_from(
+        &self,
+        frontier: AuditFrontier,
+        find_royalties: bool,
+        royalty_tracker: Option<&mut RoyaltyTracker>,
+        spend_dag: Option<&mut SpendDag>,
+        root_dir: &Path,
+        progress: AuditProgressCallback<'_>,
+    ) -> WalletResult<(AuditFrontier, AuditResult)> {
+        let mut recorders = AuditRecorders {
+            find_royalties,
+            royalty_tracker,
+            spend_dag,
+            root_dir,
+            progress,
+            royalties: BTreeMap::new(),
+            unredeemed_royalties: BTreeMap::new(),
+            royalty_outputs: BTreeMap::new(),
+        };
+        debug!(
+            "Resuming the audit from {} UTXOs, {} Transactions being verified already",
+            frontier.utxos.len(),
+            frontier.verified_txs.len()
+        );
+
+        // get all the spends of the frontier in parallel
+        let tasks: Vec<_> = frontier
+            .utxos
+            .iter()
+            .map(|addr| self.get_spend_from_network(*addr))
+            .collect();
+        let spends_res = join_all(tasks).await.into_iter().collect::<Vec<_>>();
+        let (utxos, spends, double_spent) = split_utxos_and_spends(spends_res).map_err(|err| {
+            WalletError::CouldNotVerifyTransfer(format!(
+                "Failed to get spends from network for the frontier: {err}"
+            ))
+        })?;
+        if !double_spent.is_empty() {
+            warn!(
+                "Found double spends in the frontier: {:?}",
+                double_spent
+                    .iter()
+                    .map(|report| report.address)
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        // the value of a UTXO of the frontier, as stored or else from the Tx it is an output of
+        let value_at = |addr: &SpendAddress, parent_tx: &Transaction| {
+            frontier
+                .utxo_values
+                .get(addr)
+                .copied()
+                .unwrap_or_else(|| output_value(parent_tx, addr))
+                .as_nano()
+        };
+        let mut unfollowed_value = frontier.unfollowed_value.as_nano();
+        for report in &double_spent {
+            if let Some(spend) = report.spends.first() {
+                unfollowed_value = unfollowed_value
+                    .saturating_add(value_at(&report.address, &spend.spend.parent_tx));
+            }
+        }
+
+        // the UTXOs spent since, each checked against the Tx it was found an output of. The UTXOs
+        // found by the audit, having a value stored, are outputs of Txs it verified: a spend
+        // claiming another parent Tx is rejected. Those the frontier was created with have none.
+        let mut rejected = BTreeSet::new();
+        let mut spent_since = vec![];
+        for spend in spends {
+            let addr = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+            let parent_tx = spend.spend.parent_tx.clone();
+            if frontier.utxo_values.contains_key(&addr)
+                && !frontier.verified_txs.contains(&parent_tx.hash())
+            {
+                warn!(
+                    "Rejected spend {addr:?} of the frontier: its parent Tx {:?} wasn't verified",
+                    parent_tx.hash()
+                );
+                unfollowed_value = unfollowed_value.saturating_add(value_at(&addr, &parent_tx));
+                let _ = rejected.insert(addr);
+                continue;
+            }
+            let (valid, invalid) = verify_descendant_spends(&parent_tx, vec![spend]);
+            for (addr, err) in invalid {
+                warn!("Rejected spend {addr:?} of the frontier: {err}");
+                unfollowed_value = unfollowed_value.saturating_add(value_at(&addr, &parent_tx));
+                let _ = rejected.insert(addr);
+            }
+            spent_since.extend(valid);
+        }
+        if !rejected.is_empty() {
+            progress::report(
+                &mut recorders.progress,
+                AuditProgress::SpendsRejected {
+                    generation: 0,
+                    addresses: rejected.clone(),
+                },
+            );
+        }
+        self.record_followed_spends(&spent_since, &mut recorders);
+        self.redeem_royalties(&mut recorders).await?;
+
+        let utxos: BTreeSet<_> = utxos.into_iter().collect();
+        let mut next_frontier = AuditFrontier {
+            utxo_values: frontier
+                .utxo_values
+                .into_iter()
+                .filter(|(addr, _)| utxos.contains(addr))
+                .collect(),
+            utxos,
+            verified_txs: frontier.verified_txs,
+            unfollowed_value: NanoTokens::from(unfollowed_value),
+        };
+        let txs_to_follow = spent_since
+            .into_iter()
+            .map(|spend| spend.spend.spent_tx)
+            .filter(|tx| !next_frontier.verified_txs.contains(&tx.hash()))
+            .collect();
+        let audit = self
+            .follow_txs(
+                txs_to_follow,
+                &mut next_frontier,
+                rejected,
+                double_spent,
+                &mut recorders,
+            )
+            .await?;
+        Ok((next_frontier, audit))
+    }
+
+    /// Follow the descendants of the given transactions all the way to the UTXOs, adding those
+    /// and the transactions verified to the frontier, along with the value found.
+    ///
+    /// The spends already rejected, or found double spent, are reported along with those found
+    /// on the way once done, and returned with the stats of the audit and the value of the
+    /// frontier, along with all its UTXOs.
+    async fn follow_txs(
+        &self,
+        mut txs_to_follow: BTreeSet<Transaction>,
+        frontier: &mut AuditFrontier,
+        mut rejected: BTreeSet<SpendAddress>,
+        mut double_spends: Vec<DoubleSpendReport>,
+        recorders: &mut AuditRecorders<'_, '_>,
+    ) -> WalletResult<AuditResult> {
         // use iteration instead of recursion to avoid stack overflow
-        let mut txs_to_follow = BTreeSet::from_iter([first_spend.spend.spent_tx]);
-        let mut all_utxos = BTreeSet::new();
-        let mut verified_tx = BTreeSet::new();
+        let verified_before = frontier.verified_txs.len();
+        let mut unfollowed_value = frontier.unfollowed_value.as_nano();
         let mut gen = 0;
         let start = std::time::Instant::now();
 
@@ -175,133 +636,577 @@ impl Client {
             let mut next_gen_tx = BTreeSet::new();
             let mut next_gen_spends = BTreeSet::new();
             let mut next_gen_utxos = BTreeSet::new();
+            let mut next_gen_rejected = BTreeSet::new();
+            progress::report(
+                &mut recorders.progress,
+                AuditProgress::GenerationStarted {
+                    generation: gen + 1,
+                    txs: txs_to_follow.len(),
+                },
+            );
+
+            // get the spends of the outputs of several txs at once, recording them one tx after
+            // the other, so the royalties are redeemed to the wallet one batch at a time
+            let (fetched, concurrent) = fetch_concurrently(
+                txs_to_follow.iter(),
+                self.audit_concurrency,
+                |descendant_tx| self.get_output_spends(descendant_tx),
+            )
+            .await;
+            progress::report(
+                &mut recorders.progress,
+                AuditProgress::TxsFetched {
+                    generation: gen + 1,
+                    txs: fetched.len(),
+                    concurrent,
+                },
+            );
 
-            for descendant_tx in txs_to_follow.iter() {
+            for (descendant_tx, spends_res) in txs_to_follow.iter().zip(fetched) {
                 let descendant_tx_hash = descendant_tx.hash();
-                let descendant_keys = descendant_tx
-                    .outputs
-                    .iter()
-                    .map(|output| output.unique_pubkey);
-                let addrs_to_follow = descendant_keys.map(|k| SpendAddress::from_unique_pubkey(&k));
                 debug!("Gen {gen} - Following descendant Tx : {descendant_tx_hash:?}");
 
-                // get all descendant spends in parallel
-                let tasks: Vec<_> = addrs_to_follow
-                    .into_iter()
-                    .map(|a| self.get_spend_from_network(a))
-                    .collect();
-                let spends_res = join_all(tasks).await.into_iter().collect::<Vec<_>>();
-
-                // split spends into utxos and spends
-                let (utxos, spends) = split_utxos_and_spends(spends_res)
+                // split spends into utxos, spends and double spent addresses
+                let (utxos, spends, double_spent) = split_utxos_and_spends(spends_res)
                     .map_err(|err| WalletError::CouldNotVerifyTransfer(format!("at gen {gen} - Failed to get spends from network for descendant Tx {descendant_tx_hash:?}: {err}")))?;
+                progress::report(
+                    &mut recorders.progress,
+                    AuditProgress::SpendsFetched {
+                        generation: gen + 1,
+                        tx: descendant_tx_hash,
+                        spends: spends.len(),
+                    },
+                );
+
+                // don't follow spends that are invalid or weren't made from this Tx
+                let (spends, rejected) = verify_descendant_spends(descendant_tx, spends);
+                for (addr, err) in &rejected {
+                    warn!("Gen {gen} - Rejected spend {addr:?} of descendant Tx {descendant_tx_hash:?}: {err}");
+                }
+                // the value of the outputs, from this Tx rather than the network
+                let value_at = |addr: &SpendAddress| output_value(descendant_tx, addr).as_nano();
+                for addr in &utxos {
+                    let _ = frontier
+                        .utxo_values
+                        .insert(*addr, output_value(descendant_tx, addr));
+                }
+                unfollowed_value = rejected
+                    .iter()
+                    .map(|(addr, _)| addr)
+                    .chain(double_spent.iter().map(|report| &report.address))
+                    .fold(unfollowed_value, |value, addr| {
+                        value.saturating_add(value_at(addr))
+                    });
+
+                next_gen_rejected.extend(rejected.into_iter().map(|(addr, _)| addr));
                 debug!("Gen {gen} - Got {:?} spends and {:?} utxos for descendant Tx: {descendant_tx_hash:?}", spends.len(), utxos.len());
                 trace!("Spends for {descendant_tx_hash:?} - {spends:?}");
                 next_gen_utxos.extend(utxos);
+                for report in double_spent {
+                    warn!("Gen {gen} - Found double spend at {:?} for descendant Tx {descendant_tx_hash:?}, not following its branch", report.address);
+                    double_spends.push(report);
+                }
                 next_gen_spends.extend(
                     spends
                         .iter()
                         .map(|s| SpendAddress::from_unique_pubkey(&s.spend.unique_pubkey)),
                 );
-
-                // look for royalties
-                self.redeem_royalties(find_royalties, &spends, root_dir)
-                    .await;
+                self.record_followed_spends(&spends, recorders);
 
                 // add new descendant spends to next gen
                 next_gen_tx.extend(spends.into_iter().map(|s| s.spend.spent_tx));
             }
 
-            // print stats
+            // redeem the royalties of the whole generation at once
+            self.redeem_royalties(recorders).await?;
+
+            // report stats
             gen += 1;
-            let elapsed = start.elapsed();
-            let u = next_gen_utxos.len();
-            let s = next_gen_spends.len();
-            println!("Generation {gen} - Found {u} UTXOs and {s} Spends in {elapsed:?}");
+            progress::report(
+                &mut recorders.progress,
+                AuditProgress::GenerationFollowed {
+                    generation: gen,
+                    utxos: next_gen_utxos.len(),
+                    spends: next_gen_spends.len(),
+                    elapsed: start.elapsed(),
+                },
+            );
+            if !next_gen_rejected.is_empty() {
+                progress::report(
+                    &mut recorders.progress,
+                    AuditProgress::SpendsRejected {
+                        generation: gen,
+                        addresses: next_gen_rejected.clone(),
+                    },
+                );
+            }
             debug!("Generation {gen} - UTXOs: {:#?}", next_gen_utxos);
             debug!("Generation {gen} - Spends: {:#?}", next_gen_spends);
-            all_utxos.extend(next_gen_utxos);
+            frontier.utxos.extend(next_gen_utxos);
+            rejected.extend(next_gen_rejected);
 
             // only verify tx we haven't already verified
-            verified_tx.extend(txs_to_follow.iter().map(|tx| tx.hash()));
+            frontier
+                .verified_txs
+                .extend(txs_to_follow.iter().map(|tx| tx.hash()));
             txs_to_follow = next_gen_tx
                 .into_iter()
-                .filter(|tx| !verified_tx.contains(&tx.hash()))
+                .filter(|tx| !frontier.verified_txs.contains(&tx.hash()))
                 .collect();
         }
 
-        let elapsed = start.elapsed();
-        let n = all_utxos.len();
-        let tx = verified_tx.len();
-        println!("Finished auditing! Through {gen} generations, found {n} UTXOs and verified {tx} Transactions in {elapsed:?}");
-        Ok(all_utxos)
+        frontier.unfollowed_value = NanoTokens::from(unfollowed_value);
+        let stats = AuditStats {
+            generations: gen,
+            txs_verified: frontier.verified_txs.len() - verified_before,
+            elapsed: start.elapsed(),
+            rejected,
+        };
+        info!(
+            "Finished auditing through {gen} generations, found {} UTXOs and verified {} Transactions in {:?}",
+            frontier.utxos.len(),
+            stats.txs_verified,
+            stats.elapsed
+        );
+        let poisoned: BTreeSet<_> = double_spends.iter().map(|report| report.address).collect();
+        progress::report(
+            &mut recorders.progress,
+            AuditProgress::AuditFinished {
+                generations: stats.generations,
+                utxos: frontier.utxos.len(),
+                txs_verified: stats.txs_verified,
+                elapsed: stats.elapsed,
+                rejected: stats.rejected.clone(),
+                double_spent: poisoned.clone(),
+            },
+        );
+        if !poisoned.is_empty() {
+            self.report_spend_conflicts(&poisoned, &mut recorders.progress)
+                .await;
+        }
+        let total_royalties = recorders
+            .royalty_outputs
+            .values()
+            .fold(0u64, |value, amount| value.saturating_add(amount.as_nano()));
+        Ok(AuditResult {
+            utxos: frontier.utxos.clone(),
+            double_spends,
+            stats,
+            total_utxo_value: frontier.utxo_value(),
+            total_royalties: NanoTokens::from(total_royalties),
+            total_unfollowed_value: frontier.unfollowed_value,
+        })
+    }
+
+    /// Get the spends of the inputs of the transaction from the network, in parallel.
+    async fn get_input_spends(&self, tx: &Transaction) -> Vec<Result<SignedSpend>> {
+        let tasks: Vec<_> = tx
+            .inputs
+            .iter()
+            .map(|input| {
+                self.get_spend_from_network(SpendAddress::from_unique_pubkey(&input.unique_pubkey))
+            })
+            .collect();
+        join_all(tasks).await
     }
 
-    /// This function serves as a proof of concept of royalties collection
-    async fn redeem_royalties(
+    /// Get the spends of the outputs of the transaction from the network, in parallel, those
+    /// not spent yet being missing.
+    async fn get_output_spends(&self, tx: &Transaction) -> Vec<Result<SignedSpend>> {
+        let tasks: Vec<_> = tx
+            .outputs
+            .iter()
+            .map(|output| {
+                self.get_spend_from_network(SpendAddress::from_unique_pubkey(&output.unique_pubkey))
+            })
+            .collect();
+        join_all(tasks).await
+    }
+
+    /// Record the spends followed by an audit in its spend DAG and royalty tracker, if any, and
+    /// the royalties they paid if looking for them, to be redeemed with
+    /// `Client::redeem_royalties`.
+    fn record_followed_spends(
         &self,
-        find_royalties: bool,
-        spends: &Vec<SignedSpend>,
-        root_dir: &Path,
+        spends: &[SignedSpend],
+        recorders: &mut AuditRecorders<'_, '_>,
     ) {
-        if !find_royalties {
-            return;
+        if let Some(dag) = recorders.spend_dag.as_deref_mut() {
+            for spend in spends {
+                dag.insert(
+                    SpendAddress::from_unique_pubkey(spend.unique_pubkey()),
+                    spend.clone(),
+                );
+            }
         }
 
-        // Turn those royalties into a Transfer and redeems them
-        // This involves encrypting/decrypting the Transfer, which is a waste
-        // This involves re-verifying, which we don't need as we're already auditing
-        // This prints out a Transfer for each royalty, which is not ideal but keeps the transfers reasonnably small
-        // This might print out duplicates as it doens't keep track of what's coming, but that's ok as the cli will know what to do with them
-        // It is sub-optimial, but it's a working proof of concept that will need to be refined.
-        // If we decide to adopt this, we will need to turn this indentation space ship into a proper piece of optimized code.
-        let mut count = 0;
-        let royalties_key = *NETWORK_ROYALTIES_PK;
-        let mut wallet =
-            sn_transfers::LocalWallet::load_from(root_dir).expect("Failed to load wallet");
-        for spend in spends {
-            for derivation_idx in spend.spend.network_royalties.iter() {
-                count += 1;
-                let spend_addr = SpendAddress::from_unique_pubkey(&spend.spend.unique_pubkey);
-                let royalties = vec![CashNoteRedemption::new(*derivation_idx, spend_addr)];
-                match Transfer::create(royalties, royalties_key) {
-                    Ok(transfer) => {
-                        let unique_key = royalties_key.new_unique_pubkey(derivation_idx);
-                        println!("Identified royalties token: {unique_key:?}");
-                        match self.receive(&transfer, &wallet).await {
-                            Ok(cn) => {
-                                println!(
-                                    "Successfully received royalties CashNotes, depositing..."
-                                );
-                                let old_balance = wallet.balance();
-                                if let Err(e) = wallet.deposit_and_store_to_disk(&cn) {
-                                    println!("Failed to store redeemed royalties CashNotes: {e}");
-                                } else {
-                                    let new_balance = wallet.balance();
-                                    println!("Successfully deposited royalties CashNotes, new balance: {new_balance} (was {old_balance})");
-                                }
-                            }
-                            Err(e) => {
-                                println!("Failed to redeem royalties CashNotes: {e}");
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        println!("Error creating royalties transfer: {e}");
+        // look for royalties
+        if let Some(tracker) = recorders.royalty_tracker.as_deref_mut() {
+            let _ = tracker.observe_spends(spends);
+        }
+        collect_royalty_outputs(spends, &mut recorders.royalty_outputs);
+        if recorders.find_royalties {
+            collect_royalties(spends, &mut recorders.royalties);
+        }
+    }
+
+    /// Report the conflicting spends the network holds for each of the double spent addresses.
+    async fn report_spend_conflicts(
+        &self,
+        poisoned: &BTreeSet<SpendAddress>,
+        progress: &mut AuditProgressCallback<'_>,
+    ) {
+        for address in poisoned {
+            let event = match self.get_spend_conflicts(*address).await {
+                Ok(conflicts) => AuditProgress::SpendConflicts {
+                    address: *address,
+                    conflicts,
+                },
+                Err(err) => {
+                    warn!("Failed to get the conflicting spends at {address:?}: {err}");
+                    AuditProgress::SpendConflictsFetchFailed {
+                        address: *address,
+                        error: err.to_string(),
                     }
                 }
+            };
+            progress::report(progress, event);
+        }
+    }
+
+    /// Redeem the royalties recorded since the last time to the wallet under the root dir of the
+    /// audit, `MAX_ROYALTIES_PER_TRANSFER` of them per `Transfer`, depositing each batch at once.
+    ///
+    /// A batch that can't be redeemed or deposited is reported to the progress callback, and its
+    /// royalties redeemed one by one. Those which still fail are reported too, and tried again
+    /// with the royalties of the next generation. Only failing to load the wallet is an error.
+    async fn redeem_royalties(&self, recorders: &mut AuditRecorders<'_, '_>) -> WalletResult<()> {
+        let mut royalties = std::mem::take(&mut recorders.royalties);
+        let count = royalties.len();
+        for unique_key in royalties.keys() {
+            progress::report(
+                &mut recorders.progress,
+                AuditProgress::RoyaltyIdentified(*unique_key),
+            );
+        }
+        royalties.append(&mut recorders.unredeemed_royalties);
+        if royalties.is_empty() {
+            return Ok(());
+        }
+
+        let mut wallet = sn_transfers::LocalWallet::load_from(recorders.root_dir)?;
+        let royalties: Vec<_> = royalties.into_iter().collect();
+        for batch in royalties.chunks(MAX_ROYALTIES_PER_TRANSFER) {
+            let redemptions = batch.iter().map(|(_, royalty)| royalty.clone()).collect();
+            match self.redeem_royalties_batch(redemptions, &mut wallet).await {
+                Ok(event) => {
+                    progress::report(&mut recorders.progress, event);
+                    continue;
+                }
+                Err(error) => progress::report(
+                    &mut recorders.progress,
+                    AuditProgress::RoyaltyRedemptionFailed(error),
+                ),
+            }
+            if let [(unique_key, royalty)] = batch {
+                warn!("Royalty {unique_key:?} left to be redeemed with the next generation");
+                let _ = recorders
+                    .unredeemed_royalties
+                    .insert(*unique_key, royalty.clone());
+                continue;
+            }
+
+            // a single royalty failing fails the whole batch
+            warn!(
+                "Failed to redeem a batch of {} royalties, redeeming them one by one",
+                batch.len()
+            );
+            for (unique_key, royalty) in batch {
+                let event = match self
+                    .redeem_royalties_batch(vec![royalty.clone()], &mut wallet)
+                    .await
+                {
+                    Ok(event) => event,
+                    Err(error) => {
+                        warn!("Royalty {unique_key:?} left to be redeemed with the next generation: {error}");
+                        let _ = recorders
+                            .unredeemed_royalties
+                            .insert(*unique_key, royalty.clone());
+                        AuditProgress::RoyaltyRedemptionFailed(format!(
+                            "royalty {unique_key:?}: {error}"
+                        ))
+                    }
+                };
+                progress::report(&mut recorders.progress, event);
             }
         }
 
-        println!("Found {count:?} royalties");
+        progress::report(
+            &mut recorders.progress,
+            AuditProgress::RoyaltiesFound(count),
+        );
+        Ok(())
+    }
+
+    /// Redeem the royalties in a single `Transfer`, depositing them to the wallet.
+    ///
+    /// Returns the event to report, or why they could not be redeemed.
+    async fn redeem_royalties_batch(
+        &self,
+        redemptions: Vec<CashNoteRedemption>,
+        wallet: &mut sn_transfers::LocalWallet,
+    ) -> std::result::Result<AuditProgress, String> {
+        let transfer = Transfer::create(redemptions, *NETWORK_ROYALTIES_PK)
+            .map_err(|e| format!("failed to create the royalties transfer: {e}"))?;
+        let cash_notes = self
+            .receive(&transfer, wallet)
+            .await
+            .map_err(|e| e.to_string())?;
+        debug!(
+            "Received {} royalties CashNotes, depositing...",
+            cash_notes.len()
+        );
+        let old_balance = wallet.balance();
+        wallet
+            .deposit_and_store_to_disk(&cash_notes)
+            .map_err(|e| format!("failed to store the redeemed CashNotes: {e}"))?;
+        Ok(AuditProgress::RoyaltyDeposited {
+            old_balance,
+            new_balance: wallet.balance(),
+        })
     }
+
+    /// Prepare the claim of the given royalties by the recipient, without the royalties key.
+    ///
+    /// The claim is to be signed offline with the royalties key, then broadcast with
+    /// `broadcast_royalties_claim`. The royalties already spent are left out of the claim.
+    pub async fn create_royalties_claim(
+        &self,
+        redemptions: Vec<CashNoteRedemption>,
+        recipient: MainPubkey,
+    ) -> WalletResult<UnsignedRoyaltiesClaim> {
+        let cash_notes = self
+            .verify_cash_notes_redemptions(*NETWORK_ROYALTIES_PK, &redemptions)
+            .await
+            .map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
+
+        let tasks: Vec<_> = cash_notes
+            .iter()
+            .map(|cash_note| {
+                self.get_spend_from_network(SpendAddress::from_unique_pubkey(
+                    &cash_note.unique_pubkey(),
+                ))
+            })
+            .collect();
+        let mut unspent = vec![];
+        for (royalty, spend_res) in redemptions
+            .into_iter()
+            .zip(cash_notes)
+            .zip(join_all(tasks).await)
+        {
+            match spend_res {
+                Ok(_) => debug!(
+                    "Royalty {:?} was already claimed",
+                    royalty.1.unique_pubkey()
+                ),
+                Err(Error::MissingSpendRecord(_)) => unspent.push(royalty),
+                Err(err) => return Err(spend_error_to_wallet_error(err, None)),
+            }
+        }
+        info!("Claiming {} unspent royalties", unspent.len());
+
+        let claim = UnsignedRoyaltiesClaim::new(unspent, recipient, Hash::default())?;
+        Ok(claim)
+    }
+
+    /// Send the spends of a royalties claim signed offline to the network.
+    /// Returns the CashNote of the recipient, once the spends are stored.
+    pub async fn broadcast_royalties_claim(
+        &self,
+        claim: &SignedRoyaltiesClaim,
+    ) -> WalletResult<CashNote> {
+        let cash_note = &claim.cash_note;
+        cash_note
+            .src_tx
+            .verify_against_inputs_spent(&cash_note.signed_spends)?;
+        if cash_note.signed_spends.iter().ne(claim.spends.iter()) {
+            return Err(TransferError::SpendsDoNotMatchInputs.into());
+        }
+
+        self.send_spends(claim.spends.iter(), true).await?;
+        Ok(cash_note.clone())
+    }
+}
+
+/// Verify a parent Tx against the spends of its inputs.
+/// Returns whether it is the genesis Tx, which has no parents to verify in turn.
+///
+/// The genesis Tx spends the genesis CashNote into itself, so it can't be verified against its
+/// inputs like the others. Instead, its single spend must be the validly signed spend of the
+/// genesis CashNote, created in that very Tx.
+fn verify_parent_tx(
+    parent_tx: &Transaction,
+    spends: &BTreeSet<SignedSpend>,
+) -> TransferResult<bool> {
+    if !is_genesis_parent_tx(parent_tx) {
+        parent_tx.verify_against_inputs_spent(spends)?;
+        return Ok(false);
+    }
+
+    let genesis_spend = match spends.first() {
+        Some(spend) if spends.len() == 1 => spend,
+        _ => {
+            return Err(TransferError::SignedSpendInputLenMismatch {
+                got: spends.len(),
+                expected: 1,
+            })
+        }
+    };
+    if genesis_spend.unique_pubkey() != &GENESIS_CASHNOTE.id {
+        return Err(TransferError::SpendsDoNotMatchInputs);
+    }
+    if &genesis_spend.spend.parent_tx != parent_tx {
+        return Err(TransferError::TransactionHashMismatch(
+            parent_tx.hash(),
+            genesis_spend.spend.parent_tx.hash(),
+        ));
+    }
+    genesis_spend.verify(genesis_spend.spent_tx_hash())?;
+    Ok(true)
 }
 
+/// Verify the spends fetched for the outputs of a descendant Tx, splitting them into the valid
+/// ones and the rejected ones with the reason they were rejected.
+///
+/// A valid spend is validly signed, conserves the value of the output it spends, and was created
+/// in the descendant Tx, so that nodes can't make the audit follow branches of their own.
+fn verify_descendant_spends(
+    descendant_tx: &Transaction,
+    spends: Vec<SignedSpend>,
+) -> (Vec<SignedSpend>, Vec<(SpendAddress, TransferError)>) {
+    let descendant_tx_hash = descendant_tx.hash();
+    let mut valid = vec![];
+    let mut rejected = vec![];
+
+    for spend in spends {
+        let addr = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+        let parent_tx_hash = spend.spend.parent_tx.hash();
+        let res = if parent_tx_hash != descendant_tx_hash {
+            Err(TransferError::TransactionHashMismatch(
+                descendant_tx_hash,
+                parent_tx_hash,
+            ))
+        } else {
+            spend.verify(spend.spent_tx_hash())
+        };
+        match res {
+            Ok(()) => valid.push(spend),
+            Err(err) => rejected.push((addr, err)),
+        }
+    }
+
+    (valid, rejected)
+}
+
+/// Add the royalties paid by the spends to those found, by their unique pubkey.
+///
+/// All the spends of a Tx list its royalties, those are only kept once.
+fn collect_royalties(
+    spends: &[SignedSpend],
+    royalties: &mut BTreeMap<UniquePubkey, CashNoteRedemption>,
+) {
+    for spend in spends {
+        let spend_addr = SpendAddress::from_unique_pubkey(&spend.spend.unique_pubkey);
+        for derivation_idx in spend.spend.network_royalties.iter() {
+            let unique_key = NETWORK_ROYALTIES_PK.new_unique_pubkey(derivation_idx);
+            let _ = royalties
+                .entry(unique_key)
+                .or_insert_with(|| CashNoteRedemption::new(*derivation_idx, spend_addr));
+        }
+    }
+}
+
+/// Add the royalties paid by the spends to those found, with their amounts, from the Txs the
+/// spends were spent in.
+fn collect_royalty_outputs(
+    spends: &[SignedSpend],
+    royalty_outputs: &mut BTreeMap<UniquePubkey, NanoTokens>,
+) {
+    for spend in spends {
+        for derivation_idx in spend.spend.network_royalties.iter() {
+            let unique_key = NETWORK_ROYALTIES_PK.new_unique_pubkey(derivation_idx);
+            if let Some(output) = spend
+                .spend
+                .spent_tx
+                .outputs
+                .iter()
+                .find(|output| output.unique_pubkey == unique_key)
+            {
+                let _ = royalty_outputs.insert(unique_key, output.amount);
+            }
+        }
+    }
+}
+
+/// Run the fetches of a generation, at most `concurrency` of them at once, returning their
+/// results in the order of the items, along with the most fetches which ran at once.
+async fn fetch_concurrently<I, F, Fut>(
+    items: I,
+    concurrency: usize,
+    mut fetch: F,
+) -> (Vec<Fut::Output>, usize)
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future,
+{
+    let running = &AtomicUsize::new(0);
+    let most_running = &AtomicUsize::new(0);
+    let results = stream::iter(items)
+        .map(|item| {
+            let fetching = fetch(item);
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                let _ = most_running.fetch_max(now_running, Ordering::SeqCst);
+                let output = fetching.await;
+                let _ = running.fetch_sub(1, Ordering::SeqCst);
+                output
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+    (results, most_running.load(Ordering::SeqCst))
+}
+
+/// The amount of the output of the Tx at the address, zero if it has none there.
+fn output_value(tx: &Transaction, addr: &SpendAddress) -> NanoTokens {
+    tx.outputs
+        .iter()
+        .find(|output| SpendAddress::from_unique_pubkey(&output.unique_pubkey) == *addr)
+        .map(|output| output.amount)
+        .unwrap_or(NanoTokens::zero())
+}
+
+/// Convert an error got while fetching a spend into a `WalletError`.
+/// The conflicting spends of a double spend are kept, so they can be persisted as proof.
+fn spend_error_to_wallet_error(err: Error, context: Option<String>) -> WalletError {
+    match (err, context) {
+        (Error::DoubleSpendDetected { address, spends }, _) => {
+            WalletError::DoubleSpendDetected { address, spends }
+        }
+        (err, Some(context)) => WalletError::CouldNotVerifyTransfer(format!("{context}: {err}")),
+        (err, None) => WalletError::CouldNotVerifyTransfer(err.to_string()),
+    }
+}
+
+/// Split the fetched spends into UTXOs, valid spends and double spends
 fn split_utxos_and_spends(
     spends_res: Vec<Result<SignedSpend>>,
-) -> Result<(Vec<SpendAddress>, Vec<SignedSpend>)> {
+) -> Result<(Vec<SpendAddress>, Vec<SignedSpend>, Vec<DoubleSpendReport>)> {
     let mut utxos = Vec::new();
     let mut spends = Vec::new();
+    let mut double_spent = Vec::new();
 
     for res in spends_res {
         match res {
@@ -311,6 +1216,9 @@ fn split_utxos_and_spends(
             Err(Error::MissingSpendRecord(addr)) => {
                 utxos.push(addr);
             }
+            Err(Error::DoubleSpendDetected { address, spends }) => {
+                double_spent.push(DoubleSpendReport { address, spends });
+            }
             Err(err) => {
                 warn!("Error while following spends: {err}");
                 return Err(err);
@@ -318,5 +1226,189 @@ fn split_utxos_and_spends(
         }
     }
 
-    Ok((utxos, spends))
+    Ok((utxos, spends, double_spent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{
+        create_first_cash_note_from_key, DerivationIndex, MainSecretKey, Spend, GENESIS_CASHNOTE_SK,
+    };
+
+    /// A spend of the first CashNote created from the given key, signed with the given key.
+    fn first_cash_note_spend(
+        main_key: &MainSecretKey,
+        signing_key: &MainSecretKey,
+    ) -> eyre::Result<(Transaction, SignedSpend)> {
+        let cash_note = create_first_cash_note_from_key(main_key)?;
+        let amount = cash_note.value()?;
+        // the first CashNote is both the input and output of its Tx
+        let spent_tx = Transaction {
+            inputs: cash_note.src_tx.inputs.clone(),
+            outputs: vec![],
+        };
+        let spend = Spend {
+            unique_pubkey: cash_note.unique_pubkey(),
+            spent_tx,
+            reason: Default::default(),
+            token: amount,
+            parent_tx: cash_note.src_tx.clone(),
+            network_royalties: vec![],
+        };
+        let derived_key_sig = signing_key
+            .derive_key(&DerivationIndex([0u8; 32]))
+            .sign(&spend.to_bytes());
+        Ok((
+            cash_note.src_tx,
+            SignedSpend {
+                spend,
+                derived_key_sig,
+            },
+        ))
+    }
+
+    fn genesis_key() -> eyre::Result<MainSecretKey> {
+        Ok(MainSecretKey::new(bls::SecretKey::from_hex(
+            GENESIS_CASHNOTE_SK,
+        )?))
+    }
+
+    #[tokio::test]
+    async fn fetches_run_concurrently_up_to_the_limit_in_order() {
+        let fetch = |millis: u64| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+            millis
+        };
+        let durations = [30, 10, 20, 10, 5];
+
+        let (results, concurrent) = fetch_concurrently(durations, 2, fetch).await;
+        assert_eq!(results, durations);
+        assert_eq!(concurrent, 2);
+
+        let (results, concurrent) = fetch_concurrently(durations, 1, fetch).await;
+        assert_eq!(results, durations);
+        assert_eq!(concurrent, 1);
+
+        let (results, concurrent) = fetch_concurrently(durations, 8, fetch).await;
+        assert_eq!(results, durations);
+        assert_eq!(concurrent, durations.len());
+    }
+
+    #[test]
+    fn royalties_listed_by_several_spends_are_collected_once() -> eyre::Result<()> {
+        let mut rng = sn_transfers::rng::thread_rng();
+        let royalties: Vec<_> = (0..3).map(|_| DerivationIndex::random(&mut rng)).collect();
+        let mut spends = vec![];
+        for _ in 0..2 {
+            let key = MainSecretKey::random();
+            let (_, mut spend) = first_cash_note_spend(&key, &key)?;
+            spend.spend.network_royalties = royalties.clone();
+            spends.push(spend);
+        }
+
+        let mut collected = BTreeMap::new();
+        collect_royalties(&spends, &mut collected);
+        collect_royalties(&spends[..1], &mut collected);
+        let expected: BTreeSet<_> = royalties
+            .iter()
+            .map(|idx| NETWORK_ROYALTIES_PK.new_unique_pubkey(idx))
+            .collect();
+        assert_eq!(collected.keys().copied().collect::<BTreeSet<_>>(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn genesis_tx_is_verified_with_its_signed_spend() -> eyre::Result<()> {
+        let genesis_key = genesis_key()?;
+        let (genesis_tx, genesis_spend) = first_cash_note_spend(&genesis_key, &genesis_key)?;
+
+        assert!(verify_parent_tx(
+            &genesis_tx,
+            &BTreeSet::from_iter([genesis_spend])
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn forged_genesis_lookalike_fails_verification() -> eyre::Result<()> {
+        let genesis_key = genesis_key()?;
+        let forger_key = MainSecretKey::random();
+
+        // a Tx built like the genesis one, from another key, is no genesis
+        let (lookalike_tx, lookalike_spend) = first_cash_note_spend(&forger_key, &forger_key)?;
+        assert!(!is_genesis_parent_tx(&lookalike_tx));
+        assert!(verify_parent_tx(&lookalike_tx, &BTreeSet::from_iter([lookalike_spend])).is_err());
+
+        // the genesis Tx with a fabricated genesis spend, not signed by the genesis key
+        let (genesis_tx, forged_spend) = first_cash_note_spend(&genesis_key, &forger_key)?;
+        assert_eq!(
+            verify_parent_tx(&genesis_tx, &BTreeSet::from_iter([forged_spend.clone()])),
+            Err(TransferError::InvalidSpendSignature(GENESIS_CASHNOTE.id))
+        );
+
+        // the genesis Tx with extra spends alongside the genesis one
+        let (_, genuine_spend) = first_cash_note_spend(&genesis_key, &genesis_key)?;
+        let (_, other_spend) = first_cash_note_spend(&forger_key, &forger_key)?;
+        assert!(verify_parent_tx(
+            &genesis_tx,
+            &BTreeSet::from_iter([genuine_spend.clone(), other_spend])
+        )
+        .is_err());
+
+        // the genesis spend claiming another parent Tx
+        let mut relinked_spend = genuine_spend;
+        relinked_spend.spend.parent_tx = lookalike_tx;
+        assert!(verify_parent_tx(&genesis_tx, &BTreeSet::from_iter([relinked_spend])).is_err());
+
+        // no spend at all
+        assert!(verify_parent_tx(&genesis_tx, &BTreeSet::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_descendant_spends_are_rejected() -> eyre::Result<()> {
+        let key = MainSecretKey::random();
+        let (tx, spend) = first_cash_note_spend(&key, &key)?;
+        let unique_pubkey = *spend.unique_pubkey();
+        let addr = SpendAddress::from_unique_pubkey(&unique_pubkey);
+
+        // a genuine spend from another Tx, injected as a descendant of ours
+        let other_key = MainSecretKey::random();
+        let (_, other_spend) = first_cash_note_spend(&other_key, &other_key)?;
+        let other_addr = SpendAddress::from_unique_pubkey(other_spend.unique_pubkey());
+
+        // a spend of ours claiming more than the output it spends
+        let mut inflated_spend = spend.clone();
+        inflated_spend.spend.token = NanoTokens::from(spend.spend.token.as_nano() + 1);
+
+        // a spend of ours with its signature forged
+        let mut forged_spend = spend.clone();
+        forged_spend.derived_key_sig = MainSecretKey::random()
+            .derive_key(&DerivationIndex([0u8; 32]))
+            .sign(&forged_spend.spend.to_bytes());
+
+        let (valid, rejected) = verify_descendant_spends(&tx, vec![spend.clone()]);
+        assert_eq!(valid, vec![spend]);
+        assert!(rejected.is_empty());
+
+        let (valid, rejected) =
+            verify_descendant_spends(&tx, vec![other_spend.clone(), inflated_spend, forged_spend]);
+        assert!(valid.is_empty());
+        assert_eq!(
+            rejected,
+            vec![
+                (
+                    other_addr,
+                    TransferError::TransactionHashMismatch(
+                        tx.hash(),
+                        other_spend.spend.parent_tx.hash()
+                    )
+                ),
+                (addr, TransferError::InvalidSpendValue(unique_pubkey)),
+                (addr, TransferError::InvalidSpendSignature(unique_pubkey)),
+            ]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,324 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::SpendVerificationReport;
+use sn_transfers::{Hash, NanoTokens, SignedSpend, SpendAddress, UniquePubkey};
+use std::{collections::BTreeSet, fmt, time::Duration};
+
+/// The callback `Client::verify_spend_with_progress` and `Client::follow_spend` report their
+/// progress to, if any.
+pub type AuditProgressCallback<'a> = Option<&'a mut (dyn FnMut(AuditProgress) + Send)>;
+
+/// The progress of an audit of the spends, as `Client::verify_spend_with_progress` goes through
+/// the ancestors of a spend, `Client::follow_spend` through its descendants, or
+/// `Client::follow_spends_live` through the spends announced.
+///
+/// Displaying the events gives the text the CLI prints, see `print_audit_progress`.
+#[derive(Clone, Debug)]
+pub enum AuditProgress {
+    /// The spend followed was found
+    FirstSpendFound(SpendAddress),
+    /// A generation of transactions is to be verified, or followed
+    GenerationStarted {
+        /// The generation, from 1
+        generation: usize,
+        /// The number of transactions of the generation
+        txs: usize,
+    },
+    /// The spends of the transactions of a generation were fetched, several transactions at once
+    TxsFetched {
+        /// The generation of the transactions
+        generation: usize,
+        /// The number of transactions whose spends were fetched
+        txs: usize,
+        /// The most transactions whose spends were being fetched at once
+        concurrent: usize,
+    },
+    /// The spends of the inputs, or outputs, of a transaction were fetched
+    SpendsFetched {
+        /// The generation of the transaction
+        generation: usize,
+        /// The hash of the transaction
+        tx: Hash,
+        /// The number of spends fetched
+        spends: usize,
+    },
+    /// A generation of ancestors was verified, the report standing as it does then
+    GenerationVerified(SpendVerificationReport),
+    /// A generation of descendants was followed, and the UTXOs it holds found
+    GenerationFollowed {
+        /// The generation, from 1
+        generation: usize,
+        /// The number of UTXOs found in the generation
+        utxos: usize,
+        /// The number of spends found in the generation
+        spends: usize,
+        /// The time spent following the descendants so far
+        elapsed: Duration,
+    },
+    /// Spends of a generation were rejected as invalid, their branches not followed
+    SpendsRejected {
+        /// The generation of the spends
+        generation: usize,
+        /// The addresses of the spends
+        addresses: BTreeSet<SpendAddress>,
+    },
+    /// All the descendants were followed
+    AuditFinished {
+        /// The number of generations followed
+        generations: usize,
+        /// The number of UTXOs found
+        utxos: usize,
+        /// The number of transactions verified
+        txs_verified: usize,
+        /// The time spent following the descendants
+        elapsed: Duration,
+        /// The addresses of the invalid spends, whose branches were not followed
+        rejected: BTreeSet<SpendAddress>,
+        /// The double spent addresses, whose branches were not followed
+        double_spent: BTreeSet<SpendAddress>,
+    },
+    /// The conflicting spends held by the Network at a double spent address
+    SpendConflicts {
+        /// The double spent address
+        address: SpendAddress,
+        /// The conflicting spends, none if no node returned them
+        conflicts: Vec<SignedSpend>,
+    },
+    /// The conflicting spends at a double spent address could not be fetched
+    SpendConflictsFetchFailed {
+        /// The double spent address
+        address: SpendAddress,
+        /// Why they could not be fetched
+        error: String,
+    },
+    /// A network royalties payment was identified
+    RoyaltyIdentified(UniquePubkey),
+    /// The royalties identified were redeemed and deposited to the local wallet
+    RoyaltyDeposited {
+        /// The balance of the wallet before
+        old_balance: NanoTokens,
+        /// The balance of the wallet after
+        new_balance: NanoTokens,
+    },
+    /// The royalties identified could not be redeemed or deposited
+    RoyaltyRedemptionFailed(String),
+    /// The number of royalties payments identified among a batch of spends
+    RoyaltiesFound(usize),
+    /// A spend announced over gossipsub was inserted into the live DAG
+    LiveSpendInserted(SpendAddress),
+    /// A spend announced over gossipsub was queued, its parents not being in the live DAG yet
+    LiveSpendQueued(SpendAddress),
+    /// A spend announced over gossipsub could not be fetched or verified
+    LiveSpendRejected {
+        /// The address of the spend
+        address: SpendAddress,
+        /// Why it was rejected
+        error: String,
+    },
+}
+
+impl fmt::Display for AuditProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FirstSpendFound(address) => {
+                write!(f, "Generation 0 - Found first spend: {address:#?}")
+            }
+            Self::GenerationStarted { generation, txs } => {
+                write!(f, "Generation {generation} - Going through {txs} transactions")
+            }
+            Self::TxsFetched {
+                generation,
+                txs,
+                concurrent,
+            } => write!(
+                f,
+                "Generation {generation} - Got the spends of {txs} transactions, up to {concurrent} at once"
+            ),
+            Self::SpendsFetched {
+                generation,
+                tx,
+                spends,
+            } => write!(
+                f,
+                "Generation {generation} - Got {spends} spends for Tx {tx:?}"
+            ),
+            Self::GenerationVerified(report) => write!(
+                f,
+                "Now at depth {} - Verified {} transactions in {:?}",
+                report.depth,
+                report.txs_verified(),
+                report.elapsed
+            ),
+            Self::GenerationFollowed {
+                generation,
+                utxos,
+                spends,
+                elapsed,
+            } => write!(
+                f,
+                "Generation {generation} - Found {utxos} UTXOs and {spends} Spends in {elapsed:?}"
+            ),
+            Self::SpendsRejected {
+                generation,
+                addresses,
+            } => write!(
+                f,
+                "Generation {generation} - Rejected {} invalid Spends: {addresses:#?}",
+                addresses.len()
+            ),
+            Self::AuditFinished {
+                generations,
+                utxos,
+                txs_verified,
+                elapsed,
+                rejected,
+                double_spent,
+            } => {
+                write!(f, "Finished auditing! Through {generations} generations, found {utxos} UTXOs and verified {txs_verified} Transactions in {elapsed:?}")?;
+                if !rejected.is_empty() {
+                    write!(
+                        f,
+                        "\nRejected {} invalid Spends, their branches were not followed: {rejected:#?}",
+                        rejected.len()
+                    )?;
+                }
+                if !double_spent.is_empty() {
+                    write!(
+                        f,
+                        "\nFound {} double spent addresses, their branches were not followed: {double_spent:#?}",
+                        double_spent.len()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::SpendConflicts { address, conflicts } if conflicts.is_empty() => {
+                write!(f, "No node returned the conflicting spends at {address:?}")
+            }
+            Self::SpendConflicts { address, conflicts } => {
+                write!(
+                    f,
+                    "Double spent address {address:?} holds {} conflicting spends:",
+                    conflicts.len()
+                )?;
+                for spend in conflicts {
+                    write!(
+                        f,
+                        "\n  - spent in Tx {:?} for {}",
+                        spend.spent_tx_hash(),
+                        spend.token()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::SpendConflictsFetchFailed { address, error } => {
+                write!(
+                    f,
+                    "Failed to get the conflicting spends at {address:?}: {error}"
+                )
+            }
+            Self::RoyaltyIdentified(unique_pubkey) => {
+                write!(f, "Identified royalties token: {unique_pubkey:?}")
+            }
+            Self::RoyaltyDeposited {
+                old_balance,
+                new_balance,
+            } => write!(
+                f,
+                "Successfully deposited royalties CashNotes, new balance: {new_balance} (was {old_balance})"
+            ),
+            Self::RoyaltyRedemptionFailed(error) => {
+                write!(f, "Failed to redeem royalties CashNotes: {error}")
+            }
+            Self::RoyaltiesFound(count) => write!(f, "Found {count} royalties"),
+            Self::LiveSpendInserted(address) => {
+                write!(f, "Live - Inserted the spend at {address:?}")
+            }
+            Self::LiveSpendQueued(address) => write!(
+                f,
+                "Live - Queued the spend at {address:?} until its parents are found"
+            ),
+            Self::LiveSpendRejected { address, error } => {
+                write!(f, "Live - Rejected the spend at {address:?}: {error}")
+            }
+        }
+    }
+}
+
+/// Print the progress of an audit on stdout, as the CLI does.
+///
+/// The start of the generations, and the spends fetched for their transactions, are left out as
+/// too detailed.
+pub fn print_audit_progress(progress: AuditProgress) {
+    match progress {
+        AuditProgress::GenerationStarted { .. }
+        | AuditProgress::TxsFetched { .. }
+        | AuditProgress::SpendsFetched { .. } => {}
+        progress => println!("{progress}"),
+    }
+}
+
+pub(super) fn report(progress: &mut AuditProgressCallback, event: AuditProgress) {
+    if let Some(callback) = progress.as_deref_mut() {
+        callback(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{DerivationIndex, MainSecretKey};
+    use xor_name::XorName;
+
+    #[test]
+    fn events_are_reported_to_the_callback_if_any() {
+        let address = SpendAddress::from_unique_pubkey(
+            &MainSecretKey::random()
+                .main_pubkey()
+                .new_unique_pubkey(&DerivationIndex([0; 32])),
+        );
+        let mut events = vec![];
+        let mut collect = |event: AuditProgress| events.push(event);
+        let mut progress: AuditProgressCallback = Some(&mut collect);
+        report(&mut progress, AuditProgress::FirstSpendFound(address));
+        report(&mut progress, AuditProgress::RoyaltiesFound(2));
+        report(&mut None, AuditProgress::RoyaltiesFound(3));
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                AuditProgress::FirstSpendFound(found),
+                AuditProgress::RoyaltiesFound(2)
+            ] if *found == address
+        ));
+    }
+
+    #[test]
+    fn the_end_of_an_audit_renders_what_was_not_followed() {
+        let finished = |rejected: BTreeSet<SpendAddress>| AuditProgress::AuditFinished {
+            generations: 3,
+            utxos: 4,
+            txs_verified: 5,
+            elapsed: Duration::from_secs(1),
+            rejected,
+            double_spent: BTreeSet::new(),
+        };
+        assert_eq!(
+            finished(BTreeSet::new()).to_string(),
+            "Finished auditing! Through 3 generations, found 4 UTXOs and verified 5 Transactions in 1s"
+        );
+
+        let rejected = BTreeSet::from([SpendAddress::new(XorName([1; 32]))]);
+        let rendered = finished(rejected).to_string();
+        assert!(rendered
+            .lines()
+            .nth(1)
+            .is_some_and(|line| line.starts_with("Rejected 1 invalid Spends")));
+        assert!(!rendered.contains("double spent"));
+    }
+}
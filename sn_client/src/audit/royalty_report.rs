@@ -0,0 +1,430 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+
+use serde::{Deserialize, Serialize};
+use sn_transfers::{
+    CashNoteRedemption, NanoTokens, SignedSpend, SpendAddress, UniquePubkey, NETWORK_ROYALTIES_PK,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file, under the client's root dir, where the royalties observed by the audits
+/// are checkpointed.
+pub const ROYALTY_OBSERVATIONS_FILE_NAME: &str = "royalty_observations.json";
+
+const SECS_IN_DAY: u64 = 24 * 60 * 60;
+const SECS_IN_WEEK: u64 = 7 * SECS_IN_DAY;
+/// The UNIX epoch was a Thursday, weeks start 3 days before it on Monday.
+const WEEK_START_OFFSET: u64 = 3 * SECS_IN_DAY;
+
+/// A royalty payment, as first observed when following the spends.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoyaltyObservation {
+    /// The hex encoded UniquePubkey of the royalty output.
+    pub royalty_key: String,
+    pub amount: NanoTokens,
+    /// Seconds since the UNIX epoch at which the royalty was first observed.
+    /// Spends carry no timestamp, so this is the only time known for a royalty.
+    pub first_observed_at: u64,
+    /// Whether the royalty was observed when backfilling the history of the currency, in which
+    /// case it may have been paid long before it was observed.
+    pub backfilled: bool,
+    /// The redemption of the royalty, for it to be claimed with the royalties key.
+    /// Only known for the royalties observed while following the spends.
+    #[serde(default)]
+    pub redemption: Option<CashNoteRedemption>,
+}
+
+/// The size of the time buckets royalties are summarised by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BucketSize {
+    Day,
+    Week,
+}
+
+impl BucketSize {
+    /// The start of the bucket the given time falls in, in seconds since the UNIX epoch.
+    /// Days start at midnight UTC and weeks on Monday.
+    pub fn bucket_start(&self, time: u64) -> u64 {
+        match self {
+            Self::Day => time - time % SECS_IN_DAY,
+            Self::Week => {
+                let shifted = time + WEEK_START_OFFSET;
+                (shifted - shifted % SECS_IN_WEEK).saturating_sub(WEEK_START_OFFSET)
+            }
+        }
+    }
+}
+
+impl FromStr for BucketSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            other => Err(format!(
+                "Unknown bucket size {other:?}, expected 'day' or 'week'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BucketSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Day => write!(f, "day"),
+            Self::Week => write!(f, "week"),
+        }
+    }
+}
+
+/// The royalties first observed within a time bucket.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoyaltyBucket {
+    /// Seconds since the UNIX epoch at which the bucket starts.
+    pub bucket_start: u64,
+    pub royalty_count: usize,
+    pub total_nanos: u64,
+    /// Whether some of the royalties of the bucket were backfilled, so the bucket only tells
+    /// when they were observed rather than when they were paid.
+    pub backfilled: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RoyaltyObservations {
+    observations: Vec<RoyaltyObservation>,
+}
+
+/// Keeps track of when the royalties were first observed while following the spends,
+/// for them to be reported by time bucket.
+///
+/// Everything observed before the first checkpoint is backfilled, as the audit then walks
+/// through spends made before we started watching. Royalties observed by later audits, resuming
+/// from the checkpoint, are live-followed.
+#[derive(Clone)]
+pub struct RoyaltyTracker {
+    observations: BTreeMap<String, RoyaltyObservation>,
+    backfilling: bool,
+    clock: Arc<dyn Fn() -> u64 + Send + Sync>,
+}
+
+impl Default for RoyaltyTracker {
+    fn default() -> Self {
+        Self {
+            observations: BTreeMap::new(),
+            backfilling: true,
+            clock: Arc::new(now_secs),
+        }
+    }
+}
+
+impl fmt::Debug for RoyaltyTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoyaltyTracker")
+            .field("observations", &self.observations.len())
+            .field("backfilling", &self.backfilling)
+            .finish()
+    }
+}
+
+impl RoyaltyTracker {
+    /// A tracker without any previous observation, backfilling all it observes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use the given clock, returning seconds since the UNIX epoch, to timestamp observations.
+    pub fn with_clock(mut self, clock: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Resume from the checkpoint under the given root dir, if any.
+    pub fn load_from(root_dir: &Path) -> Result<Self> {
+        let path = root_dir.join(ROYALTY_OBSERVATIONS_FILE_NAME);
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+
+        let bytes = std::fs::read(path)?;
+        let checkpoint: RoyaltyObservations =
+            serde_json::from_slice(&bytes).map_err(Error::RoyaltyReportSerialisation)?;
+        let observations = checkpoint
+            .observations
+            .into_iter()
+            .map(|observation| (observation.royalty_key.clone(), observation))
+            .collect();
+        Ok(Self {
+            observations,
+            backfilling: false,
+            ..Self::default()
+        })
+    }
+
+    /// Checkpoint the observations under the given root dir.
+    /// Royalties observed from then on are no longer backfilled.
+    pub fn save_to(&mut self, root_dir: &Path) -> Result<()> {
+        let checkpoint = RoyaltyObservations {
+            observations: self.observations.values().cloned().collect(),
+        };
+        let bytes =
+            serde_json::to_vec_pretty(&checkpoint).map_err(Error::RoyaltyReportSerialisation)?;
+        std::fs::write(root_dir.join(ROYALTY_OBSERVATIONS_FILE_NAME), bytes)?;
+        self.backfilling = false;
+        Ok(())
+    }
+
+    /// Record the royalties paid by the given spends, if not already observed.
+    /// Returns the number of newly observed royalties.
+    pub fn observe_spends(&mut self, spends: &[SignedSpend]) -> usize {
+        self.record(spends.iter().flat_map(royalties_paid_by))
+    }
+
+    /// Record the given royalties, by the UniquePubkey of their output, if not already observed.
+    /// Returns the number of newly observed royalties.
+    pub fn observe(
+        &mut self,
+        royalties: impl IntoIterator<Item = (UniquePubkey, NanoTokens)>,
+    ) -> usize {
+        self.record(
+            royalties
+                .into_iter()
+                .map(|(royalty_key, amount)| (royalty_key, amount, None)),
+        )
+    }
+
+    /// The redemptions of all the royalties observed so far, when known.
+    pub fn redemptions(&self) -> Vec<CashNoteRedemption> {
+        self.observations
+            .values()
+            .filter_map(|observation| observation.redemption.clone())
+            .collect()
+    }
+
+    fn record(
+        &mut self,
+        royalties: impl IntoIterator<Item = (UniquePubkey, NanoTokens, Option<CashNoteRedemption>)>,
+    ) -> usize {
+        let now = (self.clock)();
+        let mut newly_observed = 0;
+        for (royalty_key, amount, redemption) in royalties {
+            let royalty_key = royalty_key.to_hex();
+            if self.observations.contains_key(&royalty_key) {
+                continue;
+            }
+            let observation = RoyaltyObservation {
+                royalty_key: royalty_key.clone(),
+                amount,
+                first_observed_at: now,
+                backfilled: self.backfilling,
+                redemption,
+            };
+            let _ = self.observations.insert(royalty_key, observation);
+            newly_observed += 1;
+        }
+        newly_observed
+    }
+
+    /// All the royalties observed so far.
+    pub fn observations(&self) -> impl Iterator<Item = &RoyaltyObservation> {
+        self.observations.values()
+    }
+
+    /// Summarise the observed royalties by time bucket, ordered by bucket start.
+    pub fn report(&self, bucket_size: BucketSize) -> Vec<RoyaltyBucket> {
+        let mut buckets: BTreeMap<u64, RoyaltyBucket> = BTreeMap::new();
+        for observation in self.observations.values() {
+            let bucket_start = bucket_size.bucket_start(observation.first_observed_at);
+            let bucket = buckets.entry(bucket_start).or_insert(RoyaltyBucket {
+                bucket_start,
+                royalty_count: 0,
+                total_nanos: 0,
+                backfilled: false,
+            });
+            bucket.royalty_count += 1;
+            bucket.total_nanos = bucket
+                .total_nanos
+                .saturating_add(observation.amount.as_nano());
+            bucket.backfilled |= observation.backfilled;
+        }
+        buckets.into_values().collect()
+    }
+}
+
+/// Write the royalty report to the given path, as JSON if its extension is `json`, else as CSV.
+pub fn write_royalty_report(path: &Path, buckets: &[RoyaltyBucket]) -> Result<()> {
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let content = if is_json {
+        serde_json::to_string_pretty(buckets).map_err(Error::RoyaltyReportSerialisation)?
+    } else {
+        royalty_report_csv(buckets)
+    };
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// The royalty report in CSV format.
+pub fn royalty_report_csv(buckets: &[RoyaltyBucket]) -> String {
+    let mut csv = "bucket_start,royalty_count,total_nanos,backfilled\n".to_string();
+    for bucket in buckets {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            bucket.bucket_start, bucket.royalty_count, bucket.total_nanos, bucket.backfilled
+        ));
+    }
+    csv
+}
+
+/// The royalties paid by a spend, as the UniquePubkey and amount of their outputs,
+/// along with their redemption.
+fn royalties_paid_by(
+    spend: &SignedSpend,
+) -> Vec<(UniquePubkey, NanoTokens, Option<CashNoteRedemption>)> {
+    let parent_spend = SpendAddress::from_unique_pubkey(&spend.spend.unique_pubkey);
+    spend
+        .spend
+        .network_royalties
+        .iter()
+        .filter_map(|derivation_index| {
+            let royalty_key = NETWORK_ROYALTIES_PK.new_unique_pubkey(derivation_index);
+            spend
+                .spend
+                .spent_tx
+                .outputs
+                .iter()
+                .find(|output| output.unique_pubkey == royalty_key)
+                .map(|output| {
+                    let redemption = CashNoteRedemption::new(*derivation_index, parent_spend);
+                    (royalty_key, output.amount, Some(redemption))
+                })
+        })
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // Monday 2024-01-01 00:00:00 UTC
+    const MONDAY: u64 = 1_704_067_200;
+
+    fn random_royalty(amount: u64) -> (UniquePubkey, NanoTokens) {
+        (
+            UniquePubkey::new(bls::SecretKey::random().public_key()),
+            NanoTokens::from(amount),
+        )
+    }
+
+    #[test]
+    fn buckets_start_at_midnight_and_on_mondays() {
+        let wednesday_noon = MONDAY + 2 * SECS_IN_DAY + SECS_IN_DAY / 2;
+        assert_eq!(
+            BucketSize::Day.bucket_start(wednesday_noon),
+            MONDAY + 2 * SECS_IN_DAY
+        );
+        assert_eq!(BucketSize::Week.bucket_start(wednesday_noon), MONDAY);
+        assert_eq!(BucketSize::Week.bucket_start(MONDAY), MONDAY);
+        assert_eq!(
+            BucketSize::Week.bucket_start(MONDAY - 1),
+            MONDAY - SECS_IN_WEEK
+        );
+    }
+
+    #[test]
+    fn royalties_are_bucketed_by_first_observation_across_two_days() -> eyre::Result<()> {
+        let root_dir = tempfile::tempdir()?;
+        let clock = Arc::new(AtomicU64::new(MONDAY + 60));
+        let tracker_clock = clock.clone();
+
+        // day one: the first audit backfills the history
+        let mut tracker =
+            RoyaltyTracker::new().with_clock(move || tracker_clock.load(Ordering::SeqCst));
+        let day_one = [random_royalty(10), random_royalty(20)];
+        assert_eq!(tracker.observe(day_one), 2);
+        tracker.save_to(root_dir.path())?;
+
+        // day two: the follower resumes from the checkpoint, seeing the same royalties again
+        clock.store(MONDAY + SECS_IN_DAY + 60, Ordering::SeqCst);
+        let tracker_clock = clock.clone();
+        let mut tracker = RoyaltyTracker::load_from(root_dir.path())?
+            .with_clock(move || tracker_clock.load(Ordering::SeqCst));
+        let day_two = [random_royalty(5)];
+        assert_eq!(tracker.observe(day_one.into_iter().chain(day_two)), 1);
+
+        assert_eq!(
+            tracker.report(BucketSize::Day),
+            vec![
+                RoyaltyBucket {
+                    bucket_start: MONDAY,
+                    royalty_count: 2,
+                    total_nanos: 30,
+                    backfilled: true,
+                },
+                RoyaltyBucket {
+                    bucket_start: MONDAY + SECS_IN_DAY,
+                    royalty_count: 1,
+                    total_nanos: 5,
+                    backfilled: false,
+                },
+            ]
+        );
+        assert_eq!(
+            tracker.report(BucketSize::Week),
+            vec![RoyaltyBucket {
+                bucket_start: MONDAY,
+                royalty_count: 3,
+                total_nanos: 35,
+                backfilled: true,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn report_is_written_as_csv_or_json() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let buckets = vec![RoyaltyBucket {
+            bucket_start: MONDAY,
+            royalty_count: 2,
+            total_nanos: 30,
+            backfilled: true,
+        }];
+
+        let csv_path = dir.path().join("report.csv");
+        write_royalty_report(&csv_path, &buckets)?;
+        assert_eq!(
+            std::fs::read_to_string(csv_path)?,
+            format!("bucket_start,royalty_count,total_nanos,backfilled\n{MONDAY},2,30,true\n")
+        );
+
+        let json_path = dir.path().join("report.json");
+        write_royalty_report(&json_path, &buckets)?;
+        let parsed: Vec<RoyaltyBucket> = serde_json::from_slice(&std::fs::read(json_path)?)?;
+        assert_eq!(parsed, buckets);
+        Ok(())
+    }
+}
@@ -7,13 +7,128 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::Client;
-use crate::Error;
+use crate::{Error, Result};
 
 use futures::future::join_all;
+use petgraph::algo::tarjan_scc;
 use petgraph::dot::Dot;
 use petgraph::graph::{DiGraph, NodeIndex};
-use sn_transfers::{NanoTokens, SignedSpend, SpendAddress, WalletError, WalletResult};
-use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use sn_transfers::{
+    is_genesis_parent_tx, Hash, NanoTokens, SignedSpend, SpendAddress, Transaction, WalletError,
+    WalletResult,
+};
+use std::{
+    cmp::Reverse,
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    path::Path,
+};
+
+/// Transactions of at most this value, none of whose outputs were spent, are flagged as dust.
+pub const DUST_TX_MAX_VALUE: NanoTokens = NanoTokens::from(10);
+
+/// Transactions with more outputs than this are flagged for their fan-out.
+/// Paying for the upload of a batch of chunks takes two outputs per chunk, plus the change.
+pub const HIGH_FAN_OUT_MIN_OUTPUTS: usize = 1024;
+
+/// The most paths `SpendDag::find_paths` returns, as their number grows exponentially with the
+/// transactions merging and splitting again on the way.
+pub const MAX_SPEND_PATHS: usize = 64;
+
+/// The most spends a path found by `SpendDag::find_paths` goes through, both ends included.
+pub const MAX_SPEND_PATH_LEN: usize = 256;
+
+/// The file name the `SpendDag` is stored at under the client's data dir.
+pub const SPEND_DAG_FILE_NAME: &str = "spend_dag";
+
+/// The version of the format `SpendDag::save` writes, to be bumped whenever it changes.
+pub const SPEND_DAG_FORMAT_VERSION: u8 = 1;
+
+/// The bytes a stored `SpendDag` starts with, followed by its format version.
+const SPEND_DAG_MAGIC: &[u8; 8] = b"SPENDDAG";
+
+/// How worrying an `Anomaly` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalySeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// A structural anomaly of the `SpendDag`, found by `SpendDag::detect_anomalies`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Anomaly {
+    /// A transaction of dust value, none of whose outputs were spent, possibly griefing the
+    /// network with UTXOs.
+    DustTransaction {
+        tx_hash: Hash,
+        value: NanoTokens,
+        outputs: usize,
+    },
+    /// A spend whose parent transaction was never observed, none of the spends of the DAG
+    /// having created it.
+    UnobservedParentTx {
+        spend: SpendAddress,
+        parent_tx_hash: Hash,
+    },
+    /// A transaction with abnormally many outputs.
+    HighFanOut { tx_hash: Hash, outputs: usize },
+    /// Spends descending from one another, which is impossible unless something is seriously
+    /// wrong.
+    Cycle { spends: Vec<SpendAddress> },
+}
+
+impl Anomaly {
+    pub fn severity(&self) -> AnomalySeverity {
+        match self {
+            Anomaly::DustTransaction { .. } => AnomalySeverity::Low,
+            Anomaly::HighFanOut { .. } => AnomalySeverity::Medium,
+            Anomaly::UnobservedParentTx { .. } => AnomalySeverity::High,
+            Anomaly::Cycle { .. } => AnomalySeverity::Critical,
+        }
+    }
+
+    /// The kind of the anomaly, in snake case.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Anomaly::DustTransaction { .. } => "dust_transaction",
+            Anomaly::UnobservedParentTx { .. } => "unobserved_parent_tx",
+            Anomaly::HighFanOut { .. } => "high_fan_out",
+            Anomaly::Cycle { .. } => "cycle",
+        }
+    }
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::DustTransaction {
+                tx_hash,
+                value,
+                outputs,
+            } => write!(
+                f,
+                "Tx {tx_hash:?} created {outputs} outputs worth {value} in total, none of them spent"
+            ),
+            Anomaly::UnobservedParentTx {
+                spend,
+                parent_tx_hash,
+            } => write!(
+                f,
+                "Spend {spend:?} refers to parent Tx {parent_tx_hash:?}, which was never observed"
+            ),
+            Anomaly::HighFanOut { tx_hash, outputs } => {
+                write!(f, "Tx {tx_hash:?} has {outputs} outputs")
+            }
+            Anomaly::Cycle { spends } => {
+                write!(f, "Spends {spends:?} descend from one another")
+            }
+        }
+    }
+}
 
 /// A DAG representing the spends from a specific Spend all the way to the UTXOs.
 /// Starting from Genesis, this would encompass all the spends that have happened on the network
@@ -109,6 +224,43 @@ impl SpendDag {
         }
     }
 
+    /// Insert the spend fetched at the address, or the conflicting spends found there, and
+    /// queue its spent Tx to be followed. Double spends are not followed.
+    fn insert_fetched(
+        &mut self,
+        addr: SpendAddress,
+        res: &Result<SignedSpend>,
+        next_gen_tx: &mut BTreeSet<Transaction>,
+    ) {
+        match res {
+            Ok(spend) => {
+                self.insert(addr, spend.clone());
+                next_gen_tx.insert(spend.spend.spent_tx.clone());
+            }
+            Err(Error::MissingSpendRecord(_)) => {
+                trace!("Reached UTXO at {addr:?}");
+            }
+            Err(Error::DoubleSpendDetected { spends, .. }) => {
+                // insert all the conflicting spends, but do not follow their branches
+                warn!("Found double spend at {addr:?}, not following its descendants");
+                for spend in spends {
+                    self.insert(addr, spend.clone());
+                }
+            }
+            Err(err) => {
+                error!("Could not verify transfer at {addr:?}: {err:?}");
+            }
+        }
+    }
+
+    /// All the spends of the DAG, the conflicting ones of double spent addresses included.
+    pub fn spends(&self) -> impl Iterator<Item = &SignedSpend> {
+        self.spends
+            .values()
+            .flatten()
+            .filter_map(|(spend, _)| spend.as_ref())
+    }
+
     pub fn get_utxos(&self) -> Vec<SpendAddress> {
         let mut leaves = Vec::new();
         for node_index in self.dag.node_indices() {
@@ -124,13 +276,404 @@ impl SpendDag {
         leaves
     }
 
+    /// The UTXOs to walk forward from to find the spends made since the DAG was built: those
+    /// created by a spend of the DAG that isn't double spent, as their branches aren't
+    /// followed, nor the parent Tx of the first spend.
+    fn utxo_frontier(&self) -> BTreeSet<SpendAddress> {
+        self.dag
+            .node_indices()
+            .filter(|node| {
+                let addr = self.dag[*node];
+                let is_leaf = !self
+                    .dag
+                    .neighbors_directed(*node, petgraph::Direction::Outgoing)
+                    .any(|_| true);
+                let created_by_followed_spend = self
+                    .dag
+                    .neighbors_directed(*node, petgraph::Direction::Incoming)
+                    .any(|parent| !self.is_double_spent(&self.dag[parent]));
+                is_leaf && !self.is_spent(&addr) && created_by_followed_spend
+            })
+            .map(|node| self.dag[node])
+            .collect()
+    }
+
+    /// Store the DAG at the given path, replacing the one stored before.
+    ///
+    /// Only the spends are stored, in a versioned binary format, the DAG being rebuilt from them
+    /// by `SpendDag::load`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let stored = StoredSpendDag {
+            spends: self.spends().cloned().collect(),
+        };
+        let mut bytes = SPEND_DAG_MAGIC.to_vec();
+        bytes.push(SPEND_DAG_FORMAT_VERSION);
+        bytes.extend(rmp_serde::to_vec(&stored).map_err(Error::SpendDagSerialisation)?);
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Load the DAG stored at the given path by `SpendDag::save`.
+    ///
+    /// Returns `Error::SpendDagCorrupted` or `Error::SpendDagVersionMismatch` when it can't be
+    /// read, the DAG then having to be built from scratch.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let corrupted = |reason: String| Error::SpendDagCorrupted {
+            path: path.to_path_buf(),
+            reason,
+        };
+
+        let (version, payload) = match bytes.strip_prefix(SPEND_DAG_MAGIC.as_slice()) {
+            Some([version, payload @ ..]) => (*version, payload),
+            _ => return Err(corrupted("not a stored SpendDag".to_string())),
+        };
+        if version != SPEND_DAG_FORMAT_VERSION {
+            return Err(Error::SpendDagVersionMismatch {
+                path: path.to_path_buf(),
+                found: version,
+                expected: SPEND_DAG_FORMAT_VERSION,
+            });
+        }
+        let stored: StoredSpendDag =
+            rmp_serde::from_slice(payload).map_err(|err| corrupted(err.to_string()))?;
+
+        let mut dag = SpendDag::new();
+        for spend in stored.spends {
+            dag.insert(
+                SpendAddress::from_unique_pubkey(spend.unique_pubkey()),
+                spend,
+            );
+        }
+        Ok(dag)
+    }
+
+    /// Bring the DAG up to date with the spends made since it was built, walking forward from
+    /// its UTXOs only: the spends found are added, and their outputs left unspent recorded as
+    /// the new UTXOs.
+    ///
+    /// Returns the number of spends added.
+    pub async fn update_from_network(&mut self, client: &Client) -> WalletResult<usize> {
+        let spends_before = self.spends().count();
+        let frontier = self.utxo_frontier();
+        debug!("Updating the SpendDAG from {} UTXOs", frontier.len());
+
+        let tasks: Vec<_> = frontier
+            .iter()
+            .map(|addr| client.get_spend_from_network(*addr))
+            .collect();
+        let spends_res = join_all(tasks).await;
+        let mut txs_to_follow = BTreeSet::new();
+        for (res, addr) in spends_res.iter().zip(frontier) {
+            self.insert_fetched(addr, res, &mut txs_to_follow);
+        }
+        client.follow_spend_dag(self, txs_to_follow, None).await;
+
+        let added = self.spends().count() - spends_before;
+        info!("Added {added} spends to the SpendDAG");
+        Ok(added)
+    }
+
     pub fn dump_dot_format(&self) -> String {
         format!("{:?}", Dot::with_config(&self.dag, &[]))
     }
+
+    /// Look for the structural anomalies of the DAG, the most severe first: dust transactions
+    /// left unspent, spends of parent transactions never observed, transactions of high
+    /// fan-out, and cycles.
+    ///
+    /// The parent transaction of the first spend of a DAG not built from Genesis is never
+    /// observed, as the DAG starts there.
+    pub fn detect_anomalies(&self) -> Vec<Anomaly> {
+        let spends: Vec<&SignedSpend> = self
+            .spends
+            .values()
+            .flatten()
+            .filter_map(|(spend, _)| spend.as_ref())
+            .collect();
+        let txs: BTreeMap<Hash, &Transaction> = spends
+            .iter()
+            .map(|spend| (spend.spent_tx_hash(), &spend.spend.spent_tx))
+            .collect();
+        let mut anomalies = vec![];
+
+        for (tx_hash, tx) in &txs {
+            let outputs = tx.outputs.len();
+            if outputs > HIGH_FAN_OUT_MIN_OUTPUTS {
+                anomalies.push(Anomaly::HighFanOut {
+                    tx_hash: *tx_hash,
+                    outputs,
+                });
+            }
+
+            let value = tx.outputs.iter().fold(0u64, |value, output| {
+                value.saturating_add(output.amount.as_nano())
+            });
+            let none_spent = tx.outputs.iter().all(|output| {
+                !self.is_spent(&SpendAddress::from_unique_pubkey(&output.unique_pubkey))
+            });
+            if outputs > 0 && none_spent && value <= DUST_TX_MAX_VALUE.as_nano() {
+                anomalies.push(Anomaly::DustTransaction {
+                    tx_hash: *tx_hash,
+                    value: NanoTokens::from(value),
+                    outputs,
+                });
+            }
+        }
+
+        for spend in &spends {
+            let parent_tx = &spend.spend.parent_tx;
+            let parent_tx_hash = parent_tx.hash();
+            if !is_genesis_parent_tx(parent_tx) && !txs.contains_key(&parent_tx_hash) {
+                anomalies.push(Anomaly::UnobservedParentTx {
+                    spend: SpendAddress::from_unique_pubkey(spend.unique_pubkey()),
+                    parent_tx_hash,
+                });
+            }
+        }
+
+        for component in tarjan_scc(&self.dag) {
+            let is_cycle = match component.as_slice() {
+                // Genesis spends the Genesis CashNote into itself
+                [node] => self.dag.contains_edge(*node, *node) && !self.is_genesis_spend(*node),
+                _ => true,
+            };
+            if is_cycle {
+                let mut spends: Vec<_> = component.iter().map(|node| self.dag[*node]).collect();
+                spends.sort();
+                anomalies.push(Anomaly::Cycle { spends });
+            }
+        }
+
+        anomalies.sort_by_key(|anomaly| Reverse(anomaly.severity()));
+        anomalies
+    }
+
+    /// The paths from one spend to another through their descendants, both ends included, at
+    /// most `MAX_SPEND_PATHS` of them, of at most `MAX_SPEND_PATH_LEN` spends each.
+    ///
+    /// Empty if `to` doesn't descend from `from`, or either of them isn't in the DAG.
+    pub fn find_paths(&self, from: SpendAddress, to: SpendAddress) -> Vec<Vec<SpendAddress>> {
+        let targets: BTreeSet<NodeIndex> = self.nodes_at(&to).collect();
+        // only go through the spends `to` descends from, close enough to reach it within the
+        // length of a path, not to wander in the other branches
+        let distances = self.distances_to(&targets);
+
+        let mut paths = BTreeSet::new();
+        for start in self.nodes_at(&from) {
+            if distances
+                .get(&start)
+                .is_some_and(|distance| *distance < MAX_SPEND_PATH_LEN)
+            {
+                let mut path = vec![start];
+                self.collect_paths(&targets, &distances, &mut path, &mut paths);
+            }
+        }
+        paths.into_iter().collect()
+    }
+
+    /// Whether the spend at `descendant` descends from the one at `ancestor`.
+    pub fn is_ancestor_of(&self, ancestor: SpendAddress, descendant: SpendAddress) -> bool {
+        let descendants = self.reachable_from(
+            self.nodes_at(&ancestor),
+            petgraph::Direction::Outgoing,
+            false,
+        );
+        self.nodes_at(&descendant)
+            .any(|node| descendants.contains(&node))
+    }
+
+    /// The indexes of the nodes at the address: its spends, or its UTXO.
+    fn nodes_at<'a>(&'a self, addr: &SpendAddress) -> impl Iterator<Item = NodeIndex> + 'a {
+        self.spends
+            .get(addr)
+            .into_iter()
+            .flatten()
+            .map(|(_, idx)| NodeIndex::new(*idx))
+    }
+
+    /// The nodes reachable from the given ones in the given direction, including them or not.
+    fn reachable_from(
+        &self,
+        nodes: impl Iterator<Item = NodeIndex>,
+        direction: petgraph::Direction,
+        including_them: bool,
+    ) -> BTreeSet<NodeIndex> {
+        let mut to_visit: Vec<NodeIndex> = if including_them {
+            nodes.collect()
+        } else {
+            nodes
+                .flat_map(|node| self.dag.neighbors_directed(node, direction))
+                .collect()
+        };
+        let mut reached = BTreeSet::new();
+        while let Some(node) = to_visit.pop() {
+            if reached.insert(node) {
+                to_visit.extend(self.dag.neighbors_directed(node, direction));
+            }
+        }
+        reached
+    }
+
+    /// The fewest edges leading from each of the nodes the targets descend from to one of
+    /// them, the targets being at zero.
+    fn distances_to(&self, targets: &BTreeSet<NodeIndex>) -> BTreeMap<NodeIndex, usize> {
+        let mut distances: BTreeMap<NodeIndex, usize> =
+            targets.iter().map(|target| (*target, 0)).collect();
+        let mut to_visit: VecDeque<NodeIndex> = targets.iter().copied().collect();
+        while let Some(node) = to_visit.pop_front() {
+            let distance = distances[&node] + 1;
+            for parent in self
+                .dag
+                .neighbors_directed(node, petgraph::Direction::Incoming)
+            {
+                if let Entry::Vacant(entry) = distances.entry(parent) {
+                    let _ = entry.insert(distance);
+                    to_visit.push_back(parent);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Extend the path through the descendants of its last node, recording it as it reaches one
+    /// of the targets.
+    ///
+    /// Only the descendants from which a target can still be reached within
+    /// `MAX_SPEND_PATH_LEN` are gone through, so that every branch followed leads to a path.
+    fn collect_paths(
+        &self,
+        targets: &BTreeSet<NodeIndex>,
+        distances: &BTreeMap<NodeIndex, usize>,
+        path: &mut Vec<NodeIndex>,
+        paths: &mut BTreeSet<Vec<SpendAddress>>,
+    ) {
+        let last = match path.last() {
+            Some(last) => *last,
+            None => return,
+        };
+        if targets.contains(&last) {
+            let _ = paths.insert(path.iter().map(|node| self.dag[*node]).collect());
+            return;
+        }
+
+        for next in self
+            .dag
+            .neighbors_directed(last, petgraph::Direction::Outgoing)
+        {
+            if paths.len() >= MAX_SPEND_PATHS {
+                return;
+            }
+            let Some(distance) = distances.get(&next) else {
+                continue;
+            };
+            // the shortest path through `next` would be too long
+            if path.len() + 1 + distance > MAX_SPEND_PATH_LEN {
+                continue;
+            }
+            // going round a cycle leads nowhere new
+            if path.contains(&next) {
+                continue;
+            }
+            path.push(next);
+            self.collect_paths(targets, distances, path, paths);
+            let _ = path.pop();
+        }
+    }
+
+    /// Whether any of the parent spends of the spend, those of the inputs of its parent Tx, is
+    /// in the DAG.
+    pub(super) fn has_parents_of(&self, spend: &SignedSpend) -> bool {
+        spend
+            .spend
+            .parent_tx
+            .inputs
+            .iter()
+            .any(|input| self.is_spent(&SpendAddress::from_unique_pubkey(&input.unique_pubkey)))
+    }
+
+    /// Whether there is a spend at the address, rather than an UTXO.
+    pub(super) fn is_spent(&self, addr: &SpendAddress) -> bool {
+        self.spends
+            .get(addr)
+            .is_some_and(|entries| entries.iter().any(|(spend, _)| spend.is_some()))
+    }
+
+    /// Whether there are conflicting spends at the address.
+    fn is_double_spent(&self, addr: &SpendAddress) -> bool {
+        self.spends
+            .get(addr)
+            .is_some_and(|entries| entries.iter().filter(|(spend, _)| spend.is_some()).count() > 1)
+    }
+
+    fn is_genesis_spend(&self, node: NodeIndex) -> bool {
+        self.spends
+            .get(&self.dag[node])
+            .into_iter()
+            .flatten()
+            .any(|(spend, idx)| {
+                *idx == node.index()
+                    && spend
+                        .as_ref()
+                        .is_some_and(|spend| is_genesis_parent_tx(&spend.spend.parent_tx))
+            })
+    }
+}
+
+/// What `SpendDag::save` stores after the magic bytes and format version.
+#[derive(Serialize, Deserialize)]
+struct StoredSpendDag {
+    spends: Vec<SignedSpend>,
+}
+
+#[derive(Serialize)]
+struct AnomalyReportEntry {
+    severity: AnomalySeverity,
+    kind: &'static str,
+    description: String,
+}
+
+/// Write the anomalies found in the DAG to the given path, as JSON.
+pub fn write_anomaly_report(path: &Path, anomalies: &[Anomaly]) -> Result<()> {
+    let entries: Vec<_> = anomalies
+        .iter()
+        .map(|anomaly| AnomalyReportEntry {
+            severity: anomaly.severity(),
+            kind: anomaly.kind(),
+            description: anomaly.to_string(),
+        })
+        .collect();
+    let content =
+        serde_json::to_string_pretty(&entries).map_err(Error::AnomalyReportSerialisation)?;
+    std::fs::write(path, content)?;
+    Ok(())
 }
 
 impl Client {
     pub async fn build_spend_dag_from(&self, spend_addr: SpendAddress) -> WalletResult<SpendDag> {
+        self.build_spend_dag(spend_addr, None).await
+    }
+
+    /// Build the part of the DAG needed to tell how the spend at `to` descends from the one at
+    /// `from`: the descendants of `from`, up to the generation `to` is found in.
+    ///
+    /// All the descendants of `from` are followed when `to` isn't one of them, the DAG then
+    /// having no path between them.
+    pub async fn build_spend_dag_between(
+        &self,
+        from: SpendAddress,
+        to: SpendAddress,
+    ) -> WalletResult<SpendDag> {
+        self.build_spend_dag(from, Some(to)).await
+    }
+
+    /// Build the DAG of the descendants of the spend, stopping after the generation the given
+    /// address is found in, if any.
+    async fn build_spend_dag(
+        &self,
+        spend_addr: SpendAddress,
+        stop_at: Option<SpendAddress>,
+    ) -> WalletResult<SpendDag> {
         let mut dag = SpendDag::new();
 
         // get first spend
@@ -140,12 +683,28 @@ impl Client {
             .map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
         dag.insert(spend_addr, first_spend.clone());
 
-        // use iteration instead of recursion to avoid stack overflow
-        let mut txs_to_follow = BTreeSet::from_iter([first_spend.spend.spent_tx]);
+        let txs_to_follow = BTreeSet::from_iter([first_spend.spend.spent_tx]);
+        let start = std::time::Instant::now();
+        self.follow_spend_dag(&mut dag, txs_to_follow, stop_at)
+            .await;
+
+        let elapsed = start.elapsed();
+        info!("Finished building SpendDAG in {elapsed:?}");
+        Ok(dag)
+    }
+
+    /// Follow the given transactions down to the UTXOs, adding the spends of their outputs to
+    /// the DAG, and stopping after the generation the given address is found in, if any.
+    async fn follow_spend_dag(
+        &self,
+        dag: &mut SpendDag,
+        mut txs_to_follow: BTreeSet<Transaction>,
+        stop_at: Option<SpendAddress>,
+    ) {
         let mut verified_tx = BTreeSet::new();
         let mut gen = 0;
-        let start = std::time::Instant::now();
 
+        // use iteration instead of recursion to avoid stack overflow
         while !txs_to_follow.is_empty() {
             let mut next_gen_tx = BTreeSet::new();
 
@@ -166,19 +725,15 @@ impl Client {
                 let spends_res = join_all(tasks).await.into_iter().collect::<Vec<_>>();
 
                 // add spends to dag
-                for res in spends_res.iter().zip(addrs_to_follow) {
-                    match res {
-                        (Ok(spend), addr) => {
-                            dag.insert(addr, spend.clone());
-                            next_gen_tx.insert(spend.spend.spent_tx.clone());
-                        }
-                        (Err(Error::MissingSpendRecord(_)), addr) => {
-                            trace!("Reached UTXO at {addr:?}");
-                        }
-                        (Err(err), addr) => {
-                            error!("Could not verify transfer at {addr:?}: {err:?}");
-                        }
-                    }
+                for (res, addr) in spends_res.iter().zip(addrs_to_follow) {
+                    dag.insert_fetched(addr, res, &mut next_gen_tx);
+                }
+            }
+
+            if let Some(addr) = stop_at {
+                if dag.spends.contains_key(&addr) {
+                    debug!("Gen {gen} - Found {addr:?}, not following its descendants");
+                    break;
                 }
             }
 
@@ -190,9 +745,370 @@ impl Client {
                 .filter(|tx| !verified_tx.contains(&tx.hash()))
                 .collect();
         }
+    }
+}
 
-        let elapsed = start.elapsed();
-        info!("Finished building SpendDAG in {elapsed:?}");
-        Ok(dag)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{Spend, UniquePubkey, GENESIS_CASHNOTE};
+
+    fn random_key() -> UniquePubkey {
+        UniquePubkey::new(bls::SecretKey::random().public_key())
+    }
+
+    fn tx(inputs: &[(UniquePubkey, u64)], outputs: &[(UniquePubkey, u64)]) -> Transaction {
+        Transaction::from_keys(inputs, outputs)
+    }
+
+    /// Insert the spend of `key`, from `parent_tx` into `spent_tx`. The DAG doesn't check
+    /// signatures, so any will do.
+    fn insert_spend(
+        dag: &mut SpendDag,
+        key: UniquePubkey,
+        parent_tx: &Transaction,
+        spent_tx: &Transaction,
+    ) -> SpendAddress {
+        let token = parent_tx
+            .outputs
+            .iter()
+            .find(|output| output.unique_pubkey == key)
+            .map(|output| output.amount)
+            .unwrap_or(NanoTokens::zero());
+        let spend = SignedSpend {
+            spend: Spend {
+                unique_pubkey: key,
+                spent_tx: spent_tx.clone(),
+                reason: Hash::default(),
+                token,
+                parent_tx: parent_tx.clone(),
+                network_royalties: vec![],
+            },
+            derived_key_sig: bls::SecretKey::random().sign(b"any"),
+        };
+        let addr = SpendAddress::from_unique_pubkey(&key);
+        dag.insert(addr, spend);
+        addr
+    }
+
+    /// A root spend, of an output of a Tx outside of the DAG, into two outputs of 500, the first
+    /// spent in turn into one output of 500, the second left unspent.
+    struct Chain {
+        dag: SpendDag,
+        root: SpendAddress,
+        root_parent_tx: Transaction,
+        unspent: UniquePubkey,
+        unspent_tx: Transaction,
+    }
+
+    fn chain() -> Chain {
+        let mut dag = SpendDag::new();
+        let (root_key, first, second) = (random_key(), random_key(), random_key());
+        let root_parent_tx = tx(&[(random_key(), 1000)], &[(root_key, 1000)]);
+        let root_tx = tx(&[(root_key, 1000)], &[(first, 500), (second, 500)]);
+        let first_tx = tx(&[(first, 500)], &[(random_key(), 500)]);
+        let root = insert_spend(&mut dag, root_key, &root_parent_tx, &root_tx);
+        let _ = insert_spend(&mut dag, first, &root_tx, &first_tx);
+        Chain {
+            dag,
+            root,
+            root_parent_tx,
+            unspent: second,
+            unspent_tx: root_tx,
+        }
+    }
+
+    #[test]
+    fn healthy_chain_only_has_its_root_parent_unobserved() {
+        let chain = chain();
+        assert_eq!(
+            chain.dag.detect_anomalies(),
+            vec![Anomaly::UnobservedParentTx {
+                spend: chain.root,
+                parent_tx_hash: chain.root_parent_tx.hash(),
+            }]
+        );
+    }
+
+    #[test]
+    fn genesis_spend_is_no_anomaly() {
+        // the Genesis Tx spends the Genesis CashNote into itself, its parent is never observed
+        let mut dag = SpendDag::new();
+        let genesis_key = GENESIS_CASHNOTE.unique_pubkey();
+        let first_tx = tx(
+            &[(genesis_key, 1000)],
+            &[(random_key(), 500), (random_key(), 500)],
+        );
+        let _ = insert_spend(&mut dag, genesis_key, &GENESIS_CASHNOTE.src_tx, &first_tx);
+        assert!(dag.detect_anomalies().is_empty());
+    }
+
+    #[test]
+    fn unspent_dust_tx_is_detected() {
+        let mut chain = chain();
+        let dust_tx = tx(
+            &[(chain.unspent, 500)],
+            &[(random_key(), 1), (random_key(), 1), (random_key(), 1)],
+        );
+        let _ = insert_spend(&mut chain.dag, chain.unspent, &chain.unspent_tx, &dust_tx);
+
+        let anomalies = chain.dag.detect_anomalies();
+        let dust = Anomaly::DustTransaction {
+            tx_hash: dust_tx.hash(),
+            value: NanoTokens::from(3),
+            outputs: 3,
+        };
+        assert!(anomalies.contains(&dust), "{anomalies:?}");
+        assert_eq!(dust.severity(), AnomalySeverity::Low);
+
+        // not once one of its outputs is spent
+        let spent_output = dust_tx.outputs[0].unique_pubkey;
+        let next_tx = tx(&[(spent_output, 1)], &[(random_key(), 1)]);
+        let _ = insert_spend(&mut chain.dag, spent_output, &dust_tx, &next_tx);
+        let anomalies = chain.dag.detect_anomalies();
+        assert!(!anomalies.contains(&dust), "{anomalies:?}");
+    }
+
+    #[test]
+    fn spend_of_unobserved_parent_tx_is_detected() {
+        let mut chain = chain();
+        // claims to spend an output of a Tx none of the spends of the DAG created
+        let forged_parent_tx = tx(&[(random_key(), 500)], &[(chain.unspent, 500)]);
+        let forged_spent_tx = tx(&[(chain.unspent, 500)], &[(random_key(), 500)]);
+        let forged = insert_spend(
+            &mut chain.dag,
+            chain.unspent,
+            &forged_parent_tx,
+            &forged_spent_tx,
+        );
+
+        let anomaly = Anomaly::UnobservedParentTx {
+            spend: forged,
+            parent_tx_hash: forged_parent_tx.hash(),
+        };
+        assert!(chain.dag.detect_anomalies().contains(&anomaly));
+        assert_eq!(anomaly.severity(), AnomalySeverity::High);
+    }
+
+    #[test]
+    fn high_fan_out_tx_is_detected() {
+        let mut chain = chain();
+        let outputs: Vec<_> = (0..=HIGH_FAN_OUT_MIN_OUTPUTS)
+            .map(|_| (random_key(), 1000))
+            .collect();
+        let fan_out_tx = tx(&[(chain.unspent, 500)], &outputs);
+        let _ = insert_spend(
+            &mut chain.dag,
+            chain.unspent,
+            &chain.unspent_tx,
+            &fan_out_tx,
+        );
+
+        let anomalies = chain.dag.detect_anomalies();
+        assert!(anomalies.contains(&Anomaly::HighFanOut {
+            tx_hash: fan_out_tx.hash(),
+            outputs: HIGH_FAN_OUT_MIN_OUTPUTS + 1,
+        }));
+        // worth too much to be dust, though unspent
+        assert!(!anomalies
+            .iter()
+            .any(|anomaly| matches!(anomaly, Anomaly::DustTransaction { .. })));
+    }
+
+    #[test]
+    fn cycle_is_detected_first() {
+        let mut chain = chain();
+        // a spend creating the output spent by the root spend, its own ancestor
+        let root_key = chain.root_parent_tx.outputs[0].unique_pubkey;
+        let cycling_tx = tx(&[(chain.unspent, 500)], &[(root_key, 500)]);
+        let cycling = insert_spend(
+            &mut chain.dag,
+            chain.unspent,
+            &chain.unspent_tx,
+            &cycling_tx,
+        );
+
+        let mut spends = vec![chain.root, cycling];
+        spends.sort();
+        let anomalies = chain.dag.detect_anomalies();
+        assert_eq!(anomalies.first(), Some(&Anomaly::Cycle { spends }));
+        assert_eq!(anomalies[0].severity(), AnomalySeverity::Critical);
+    }
+
+    /// Spend `from` into a Tx of two outputs, both spent into a single output, returning the
+    /// latter with the Tx creating it: A -> (B, C) -> D
+    fn insert_diamond(
+        dag: &mut SpendDag,
+        from: UniquePubkey,
+        from_parent_tx: &Transaction,
+    ) -> (UniquePubkey, Transaction) {
+        let (left, right, merged) = (random_key(), random_key(), random_key());
+        let split_tx = tx(&[(from, 1000)], &[(left, 500), (right, 500)]);
+        let merge_tx = tx(&[(left, 500), (right, 500)], &[(merged, 1000)]);
+        let _ = insert_spend(dag, from, from_parent_tx, &split_tx);
+        let _ = insert_spend(dag, left, &split_tx, &merge_tx);
+        let _ = insert_spend(dag, right, &split_tx, &merge_tx);
+        (merged, merge_tx)
+    }
+
+    #[test]
+    fn both_sides_of_a_diamond_are_found() {
+        let mut dag = SpendDag::new();
+        let root_key = random_key();
+        let root_parent_tx = tx(&[(random_key(), 1000)], &[(root_key, 1000)]);
+        let (merged, _) = insert_diamond(&mut dag, root_key, &root_parent_tx);
+
+        let root = SpendAddress::from_unique_pubkey(&root_key);
+        let merged = SpendAddress::from_unique_pubkey(&merged);
+        let paths = dag.find_paths(root, merged);
+        assert_eq!(paths.len(), 2, "{paths:?}");
+        assert!(paths
+            .iter()
+            .all(|path| path.len() == 3 && path[0] == root && path[2] == merged));
+        assert_ne!(paths[0][1], paths[1][1]);
+
+        assert!(dag.is_ancestor_of(root, merged));
+        assert!(dag.is_ancestor_of(root, paths[0][1]));
+        assert!(!dag.is_ancestor_of(merged, root));
+        assert!(!dag.is_ancestor_of(root, root));
+    }
+
+    #[test]
+    fn no_path_leads_to_an_unrelated_spend() {
+        let chain = chain();
+        let spent = chain.unspent_tx.outputs[0].unique_pubkey;
+        let first = SpendAddress::from_unique_pubkey(&spent);
+        let second = SpendAddress::from_unique_pubkey(&chain.unspent);
+
+        // siblings, both descending from the root
+        assert!(chain.dag.find_paths(first, second).is_empty());
+        assert!(!chain.dag.is_ancestor_of(first, second));
+        assert!(!chain.dag.is_ancestor_of(second, first));
+        assert!(chain.dag.is_ancestor_of(chain.root, second));
+
+        // nor to, or from, a spend outside of the DAG
+        let outsider = SpendAddress::from_unique_pubkey(&random_key());
+        assert!(chain.dag.find_paths(chain.root, outsider).is_empty());
+        assert!(chain.dag.find_paths(outsider, chain.root).is_empty());
+        assert!(!chain.dag.is_ancestor_of(chain.root, outsider));
+    }
+
+    #[test]
+    fn paths_through_stacked_diamonds_are_capped() {
+        // each diamond doubles the paths, seven of them make more than the cap
+        let mut dag = SpendDag::new();
+        let root_key = random_key();
+        let mut key = root_key;
+        let mut parent_tx = tx(&[(random_key(), 1000)], &[(root_key, 1000)]);
+        for _ in 0..7 {
+            (key, parent_tx) = insert_diamond(&mut dag, key, &parent_tx);
+        }
+        assert!(2usize.pow(7) > MAX_SPEND_PATHS);
+
+        let root = SpendAddress::from_unique_pubkey(&root_key);
+        let last = SpendAddress::from_unique_pubkey(&key);
+        let paths = dag.find_paths(root, last);
+        assert_eq!(paths.len(), MAX_SPEND_PATHS);
+        assert!(paths.iter().all(|path| path.len() == 15
+            && path.first() == Some(&root)
+            && path.last() == Some(&last)));
+        assert!(dag.is_ancestor_of(root, last));
+    }
+
+    #[test]
+    fn paths_too_long_are_not_searched() {
+        // more diamonds than make a path of at most MAX_SPEND_PATH_LEN spends, the paths
+        // through them being more than could ever be searched one by one
+        let diamonds = MAX_SPEND_PATH_LEN / 2 + 1;
+        assert!(diamonds > 128);
+        let mut dag = SpendDag::new();
+        let root_key = random_key();
+        let mut key = root_key;
+        let mut parent_tx = tx(&[(random_key(), 1000)], &[(root_key, 1000)]);
+        let mut keys = vec![root_key];
+        for _ in 0..diamonds {
+            (key, parent_tx) = insert_diamond(&mut dag, key, &parent_tx);
+            keys.push(key);
+        }
+
+        let root = SpendAddress::from_unique_pubkey(&root_key);
+        let last = SpendAddress::from_unique_pubkey(&key);
+        assert!(dag.find_paths(root, last).is_empty());
+        assert!(dag.is_ancestor_of(root, last));
+
+        // the longest paths allowed are still found, each diamond making two spends of them
+        let within_reach = SpendAddress::from_unique_pubkey(&keys[(MAX_SPEND_PATH_LEN - 1) / 2]);
+        let paths = dag.find_paths(root, within_reach);
+        assert_eq!(paths.len(), MAX_SPEND_PATHS);
+        assert!(paths
+            .iter()
+            .all(|path| path.len() <= MAX_SPEND_PATH_LEN && path.last() == Some(&within_reach)));
+    }
+
+    #[test]
+    fn anomaly_report_is_written_as_json() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("anomalies.json");
+        let chain = chain();
+        write_anomaly_report(&path, &chain.dag.detect_anomalies())?;
+
+        let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        assert_eq!(report[0]["severity"], "high");
+        assert_eq!(report[0]["kind"], "unobserved_parent_tx");
+        Ok(())
+    }
+
+    #[test]
+    fn stored_dag_is_rebuilt_the_same() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(SPEND_DAG_FILE_NAME);
+        let chain = chain();
+        chain.dag.save(&path)?;
+
+        let loaded = SpendDag::load(&path)?;
+        assert_eq!(
+            loaded.spends().collect::<BTreeSet<_>>(),
+            chain.dag.spends().collect::<BTreeSet<_>>()
+        );
+        assert_eq!(
+            loaded.get_utxos().into_iter().collect::<BTreeSet<_>>(),
+            chain.dag.get_utxos().into_iter().collect::<BTreeSet<_>>()
+        );
+        assert_eq!(loaded.detect_anomalies(), chain.dag.detect_anomalies());
+
+        // the parent of the root is no UTXO to walk forward from
+        let frontier = loaded.utxo_frontier();
+        assert_eq!(frontier.len(), 2);
+        assert!(frontier.contains(&SpendAddress::from_unique_pubkey(&chain.unspent)));
+        Ok(())
+    }
+
+    #[test]
+    fn unreadable_stored_dag_is_to_be_rebuilt() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(SPEND_DAG_FILE_NAME);
+        chain().dag.save(&path)?;
+        let mut bytes = std::fs::read(&path)?;
+
+        bytes[SPEND_DAG_MAGIC.len()] = SPEND_DAG_FORMAT_VERSION + 1;
+        std::fs::write(&path, &bytes)?;
+        assert!(matches!(
+            SpendDag::load(&path),
+            Err(Error::SpendDagVersionMismatch { found, expected, .. })
+                if found == SPEND_DAG_FORMAT_VERSION + 1 && expected == SPEND_DAG_FORMAT_VERSION
+        ));
+
+        bytes[SPEND_DAG_MAGIC.len()] = SPEND_DAG_FORMAT_VERSION;
+        bytes.truncate(bytes.len() / 2);
+        std::fs::write(&path, &bytes)?;
+        assert!(matches!(
+            SpendDag::load(&path),
+            Err(Error::SpendDagCorrupted { .. })
+        ));
+
+        std::fs::write(&path, b"not a dag")?;
+        let err = SpendDag::load(&path).expect_err("garbage should not load");
+        assert!(err.to_string().contains("rebuild it from scratch"), "{err}");
+        Ok(())
     }
 }
@@ -0,0 +1,116 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::frontier::map_as_pairs;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+use sn_transfers::{Hash, SpendAddress, Transaction};
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+/// What `Client::verify_spend` verified of a spend and its ancestors.
+///
+/// Reported by `Client::verify_spend_with_progress` as well, as it stands once each generation of
+/// ancestors is verified, with `AuditProgress::GenerationVerified`.
+///
+/// A partial report can be saved, to carry on with the verification in another session.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendVerificationReport {
+    /// The address of the spend verified
+    pub address: SpendAddress,
+    /// The number of generations of ancestors verified, zero when not verifying to genesis
+    ///
+    /// A generation cut short by the max number of spends to fetch is counted once resumed.
+    pub depth: usize,
+    /// The hashes of the parent transactions verified, in the order they were verified
+    pub verified_txs: Vec<Hash>,
+    /// The time spent verifying the ancestors
+    pub elapsed: Duration,
+    /// Whether the ancestors were verified all the way to the genesis transaction, on one of
+    /// their branches at least
+    pub reached_genesis: bool,
+    /// The number of parent spends fetched from the Network
+    pub spends_fetched: usize,
+    /// The transactions left to verify when the verification stopped at its limits, from which
+    /// `Client::resume_spend_verification` carries on, empty once verified to genesis
+    pub frontier: Vec<Transaction>,
+    /// The generation each transaction of the frontier belongs to, i.e. the depth reached once it
+    /// is verified. A verification cut short by the max number of spends to fetch leaves what's
+    /// left of a generation at the frontier, along with some of the next one.
+    #[serde(default, with = "map_as_pairs")]
+    pub frontier_depths: BTreeMap<Hash, usize>,
+}
+
+impl SpendVerificationReport {
+    pub(super) fn new(address: SpendAddress) -> Self {
+        Self {
+            address,
+            depth: 0,
+            verified_txs: vec![],
+            elapsed: Duration::ZERO,
+            reached_genesis: false,
+            spends_fetched: 0,
+            frontier: vec![],
+            frontier_depths: BTreeMap::new(),
+        }
+    }
+
+    /// Whether the verification stopped at its limits, short of genesis.
+    pub fn is_partial(&self) -> bool {
+        !self.frontier.is_empty()
+    }
+
+    /// The hashes of the transactions left to verify.
+    pub fn frontier_hashes(&self) -> Vec<Hash> {
+        self.frontier.iter().map(Transaction::hash).collect()
+    }
+
+    /// The number of parent transactions verified.
+    pub fn txs_verified(&self) -> usize {
+        self.verified_txs.len()
+    }
+
+    /// Load a report saved with `save_to`.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes).map_err(Error::SpendVerificationReportSerialisation)
+    }
+
+    /// Save the report to the given file, replacing the one saved there before.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let bytes =
+            serde_json::to_vec(self).map_err(Error::SpendVerificationReportSerialisation)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xor_name::XorName;
+
+    #[test]
+    fn partial_reports_round_trip_through_a_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("verification.json");
+
+        let mut report = SpendVerificationReport::new(SpendAddress::new(XorName([1; 32])));
+        report.depth = 2;
+        report.verified_txs = vec![Hash::hash(b"tx"), Hash::hash(b"parent tx")];
+        report.elapsed = Duration::from_millis(1_500);
+        report.spends_fetched = 3;
+        report.frontier = vec![Transaction::empty()];
+        report.frontier_depths = BTreeMap::from([(Transaction::empty().hash(), 3)]);
+        report.save_to(&path)?;
+
+        let loaded = SpendVerificationReport::load_from(&path)?;
+        assert!(loaded.is_partial());
+        assert_eq!(loaded, report);
+        Ok(())
+    }
+}
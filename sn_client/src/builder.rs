@@ -0,0 +1,225 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client, ReconnectPolicy};
+use bls::SecretKey;
+use libp2p::Multiaddr;
+#[cfg(feature = "network-contacts")]
+use sn_networking::DohResolver;
+use sn_networking::{multiaddr_is_global, RetryPolicy};
+use sn_peers_acquisition::PeerSource;
+use std::time::Duration;
+
+/// Builds a [`Client`] connected to the network.
+///
+/// Unset options keep their defaults: a random signer, no bootstrap peers (as with the
+/// `local-discovery` feature), gossip disabled, a 180s connection timeout, the default
+/// `RetryPolicy` and `ReconnectPolicy`, `DEFAULT_MAX_CONCURRENT_OPS` network operations at once,
+/// and a spinner showing the connection progress.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), sn_client::Error> {
+/// use sn_client::ClientBuilder;
+///
+/// let client = ClientBuilder::default()
+///     .signer(bls::SecretKey::random())
+///     .enable_gossip(true)
+///     .build()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClientBuilder {
+    signer: Option<SecretKey>,
+    peers: Option<Vec<Multiaddr>>,
+    peer_sources: Vec<(Multiaddr, PeerSource)>,
+    enable_gossip: bool,
+    connection_timeout: Option<Duration>,
+    force_local: Option<bool>,
+    retry_policy: Option<RetryPolicy>,
+    register_creation_attempts: Option<usize>,
+    headless: bool,
+    reconnect_policy: Option<ReconnectPolicy>,
+    max_concurrent_ops: Option<usize>,
+    audit_concurrency: Option<usize>,
+    #[cfg(feature = "network-contacts")]
+    doh_resolver: Option<DohResolver>,
+}
+
+impl ClientBuilder {
+    /// The key the client signs the data it stores with.
+    pub fn signer(mut self, signer: SecretKey) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// The peers to bootstrap from. An empty list is the same as not setting any.
+    pub fn peers(mut self, peers: Vec<Multiaddr>) -> Self {
+        self.peers = if peers.is_empty() { None } else { Some(peers) };
+        self.peer_sources.clear();
+        self
+    }
+
+    /// The peers to bootstrap from, along with where each was obtained from, as returned by
+    /// `sn_peers_acquisition::get_peers_with_sources`. The source of each peer is logged when the
+    /// client starts dialing them.
+    pub fn peers_with_sources(mut self, peers: Vec<(Multiaddr, PeerSource)>) -> Self {
+        let sources = peers.clone();
+        self = self.peers(peers.into_iter().map(|(peer, _source)| peer).collect());
+        self.peer_sources = sources;
+        self
+    }
+
+    /// Whether the client joins the gossipsub network.
+    pub fn enable_gossip(mut self, enable_gossip: bool) -> Self {
+        self.enable_gossip = enable_gossip;
+        self
+    }
+
+    /// The maximum time to wait for a connection to the network before timing out.
+    pub fn connection_timeout(mut self, connection_timeout: Duration) -> Self {
+        self.connection_timeout = Some(connection_timeout);
+        self
+    }
+
+    /// Set whether the network is a local one, rather than inferring it from the peers.
+    pub fn force_local(mut self, local: bool) -> Self {
+        self.force_local = Some(local);
+        self
+    }
+
+    /// The network is a local one, whatever the peers.
+    pub fn local(self) -> Self {
+        self.force_local(true)
+    }
+
+    /// How the network operations of the client are retried, see `Client::set_retry_policy`.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// How many times a Register is published before giving up on verifying it is stored, see
+    /// `Client::set_register_creation_attempts`.
+    pub fn register_creation_attempts(mut self, attempts: usize) -> Self {
+        self.register_creation_attempts = Some(attempts);
+        self
+    }
+
+    /// Whether the client runs without a terminal of its own to draw on, e.g. embedded in a TUI or
+    /// a service. A headless client shows no connection spinner, its progress is only reported
+    /// through the events channel and the logs.
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// How the client reconnects to the network once it lost all its peers for a while.
+    pub fn reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+
+    /// How many chunk and register operations the client runs at once, see
+    /// `Client::set_max_concurrent_ops`.
+    pub fn max_concurrent_ops(mut self, max_concurrent_ops: usize) -> Self {
+        self.max_concurrent_ops = Some(max_concurrent_ops);
+        self
+    }
+
+    /// How many transactions of a generation an audit goes through at once, see
+    /// `Client::set_audit_concurrency`.
+    pub fn audit_concurrency(mut self, audit_concurrency: usize) -> Self {
+        self.audit_concurrency = Some(audit_concurrency);
+        self
+    }
+
+    /// Resolve the host names of the peers dialed through DNS-over-HTTPS, instead of the system
+    /// resolver.
+    #[cfg(feature = "network-contacts")]
+    pub fn doh_resolver(mut self, doh_resolver: DohResolver) -> Self {
+        self.doh_resolver = Some(doh_resolver);
+        self
+    }
+
+    /// Whether the network is a local one.
+    ///
+    /// Unless forced, we'll assume we're in a global network if any of our peers has a global
+    /// address.
+    pub fn is_local(&self) -> bool {
+        match (self.force_local, &self.peers) {
+            (Some(local), _) => local,
+            (None, Some(peers)) => !peers.iter().any(multiaddr_is_global),
+            (None, None) => true,
+        }
+    }
+
+    /// Build the client, waiting for it to be connected to the network.
+    pub async fn build(self) -> Result<Client> {
+        let local = self.is_local();
+        for (peer, source) in &self.peer_sources {
+            info!("Bootstrap peer {peer} obtained from {source}");
+        }
+        let mut client = Client::connect(
+            self.signer.unwrap_or_else(SecretKey::random),
+            self.peers,
+            local,
+            self.enable_gossip,
+            self.connection_timeout,
+            self.headless,
+            self.reconnect_policy.unwrap_or_default(),
+            #[cfg(feature = "network-contacts")]
+            self.doh_resolver,
+        )
+        .await?;
+        if let Some(retry_policy) = self.retry_policy {
+            client.set_retry_policy(retry_policy);
+        }
+        if let Some(attempts) = self.register_creation_attempts {
+            client.set_register_creation_attempts(attempts);
+        }
+        if let Some(max_concurrent_ops) = self.max_concurrent_ops {
+            client.set_max_concurrent_ops(max_concurrent_ops);
+        }
+        if let Some(audit_concurrency) = self.audit_concurrency {
+            client.set_audit_concurrency(audit_concurrency);
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_is_inferred_from_the_peers_unless_forced() -> eyre::Result<()> {
+        let global: Multiaddr = "/ip4/1.2.3.4/tcp/12000".parse()?;
+        let loopback: Multiaddr = "/ip4/127.0.0.1/tcp/12000".parse()?;
+
+        assert!(ClientBuilder::default().is_local());
+        assert!(ClientBuilder::default()
+            .peers(vec![loopback.clone()])
+            .is_local());
+        assert!(!ClientBuilder::default()
+            .peers(vec![loopback, global.clone()])
+            .is_local());
+
+        assert!(ClientBuilder::default()
+            .peers(vec![global.clone()])
+            .local()
+            .is_local());
+        assert!(!ClientBuilder::default()
+            .peers(vec![global])
+            .force_local(false)
+            .is_local());
+        assert!(!ClientBuilder::default().force_local(false).is_local());
+        Ok(())
+    }
+}
@@ -68,6 +68,25 @@ pub enum Error {
     #[error("Chunk could not be retrieved from the network: {0:?}")]
     ChunkMissing(XorName),
 
+    #[error("The target chunk size of {size} bytes is below the minimum of {minimum} bytes.")]
+    TargetChunkSizeTooSmall {
+        /// The requested chunk size
+        size: usize,
+        /// The minimum chunk size
+        minimum: usize,
+    },
+
+    #[error(
+        "The target chunk size of {size} bytes exceeds the maximum of {maximum} bytes that nodes \
+        accept for a chunk record."
+    )]
+    TargetChunkSizeTooLarge {
+        /// The requested chunk size
+        size: usize,
+        /// The maximum chunk size
+        maximum: usize,
+    },
+
     #[error("Not all data was chunked, expected {expected}, but we have {chunked}.)")]
     NotAllDataWasChunked {
         /// Number of Chunks expected to be generated
@@ -9,12 +9,12 @@
 use super::Result;
 use bytes::{BufMut, Bytes, BytesMut};
 use rayon::prelude::*;
-use self_encryption::{DataMap, StreamSelfEncryptor, MAX_CHUNK_SIZE};
+use self_encryption::{DataMap, StreamSelfEncryptor, MAX_CHUNK_SIZE, MIN_ENCRYPTABLE_BYTES};
 use serde::{Deserialize, Serialize};
 use sn_protocol::storage::Chunk;
 use std::{
     fs::File,
-    io::Write,
+    io::{Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
 };
 use xor_name::XorName;
@@ -27,13 +27,16 @@ pub(crate) enum DataMapLevel {
     // resulting from chunking up a previous level data map.
     // This happens when that previous level data map was too big to fit in a chunk itself.
     Additional(DataMap),
+    // Holds the data maps to consecutive segments of the source data, each self-encrypted on its own.
+    // This happens when the source data was chunked with a target chunk size.
+    Segmented(Vec<DataMap>),
 }
 
 #[allow(unused)]
 pub(crate) fn encrypt_from_path(path: &Path, output_dir: &Path) -> Result<(Chunk, Vec<XorName>)> {
     let (data_map, mut encrypted_chunks) = encrypt_file(path, output_dir)?;
 
-    let (data_map_chunk, additional_chunks) = pack_data_map(data_map)?;
+    let (data_map_chunk, additional_chunks) = pack_data_map(DataMapLevel::First(data_map))?;
 
     for chunk in additional_chunks.iter() {
         encrypted_chunks.push(*chunk.name());
@@ -76,7 +79,7 @@ pub(crate) fn encrypt_large(
         .collect();
 
     // Pack the datamap into chunks that under the same output folder as well.
-    let (data_map_chunk, additional_chunks) = pack_data_map(data_map)?;
+    let (data_map_chunk, additional_chunks) = pack_data_map(DataMapLevel::First(data_map))?;
     for chunk in additional_chunks.iter() {
         let file_path = output_dir.join(&hex::encode(chunk.name()));
         encrypted_chunks.push((*chunk.name(), file_path.to_path_buf()));
@@ -87,19 +90,91 @@ pub(crate) fn encrypt_large(
     Ok((data_map_chunk, encrypted_chunks))
 }
 
+/// Self-encrypts the file in consecutive segments of three chunks of `chunk_size` bytes each,
+/// the last segment holding the remainder. The chunks are written to the output folder.
+///
+/// Returns the data map of all the segments as a chunk, and the resulting chunks.
+pub(crate) fn encrypt_large_segmented(
+    file_path: &Path,
+    output_dir: &Path,
+    chunk_size: usize,
+) -> Result<(Chunk, Vec<(XorName, PathBuf)>)> {
+    let file_size = File::open(file_path)?.metadata()?.len() as usize;
+    let mut segments = vec![];
+    let mut offset = 0;
+    for segment_size in segment_sizes(file_size, chunk_size) {
+        segments.push((offset, segment_size));
+        offset += segment_size;
+    }
+
+    // each segment is read and self-encrypted on its own, the data maps being kept in order
+    let encrypted_segments = segments
+        .into_par_iter()
+        .map(|(offset, segment_size)| {
+            let mut file = File::open(file_path)?;
+            let _ = file.seek(SeekFrom::Start(offset as u64))?;
+            let mut segment = vec![0; segment_size];
+            file.read_exact(&mut segment)?;
+
+            let (data_map, chunks) = self_encryption::encrypt(Bytes::from(segment))?;
+            let mut segment_chunks = vec![];
+            for chunk in chunks {
+                let chunk = to_chunk(chunk.content);
+                let file_path = output_dir.join(hex::encode(chunk.name()));
+                let mut output_file = File::create(&file_path)?;
+                output_file.write_all(&chunk.value)?;
+                segment_chunks.push((*chunk.name(), file_path));
+            }
+            Ok((data_map, segment_chunks))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut data_maps = vec![];
+    let mut encrypted_chunks = vec![];
+    for (data_map, segment_chunks) in encrypted_segments {
+        data_maps.push(data_map);
+        encrypted_chunks.extend(segment_chunks);
+    }
+
+    let (data_map_chunk, additional_chunks) = pack_data_map(DataMapLevel::Segmented(data_maps))?;
+    for chunk in additional_chunks.iter() {
+        let file_path = output_dir.join(hex::encode(chunk.name()));
+        encrypted_chunks.push((*chunk.name(), file_path.to_path_buf()));
+        let mut output_file = File::create(file_path)?;
+        output_file.write_all(&chunk.value)?;
+    }
+
+    Ok((data_map_chunk, encrypted_chunks))
+}
+
+// The sizes of the segments a file is self-encrypted in, so that each segment is split into three
+// chunks of `chunk_size` bytes. A remainder too small to be self-encrypted is added to the last segment.
+fn segment_sizes(file_size: usize, chunk_size: usize) -> Vec<usize> {
+    let full_segment_size = 3 * chunk_size;
+    let mut sizes = vec![full_segment_size; file_size / full_segment_size];
+    let remainder = file_size % full_segment_size;
+    if remainder > 0 {
+        match sizes.last_mut() {
+            Some(last) if remainder < MIN_ENCRYPTABLE_BYTES => *last += remainder,
+            _ => sizes.push(remainder),
+        }
+    }
+    sizes
+}
+
 pub(crate) fn to_chunk(chunk_content: Bytes) -> Chunk {
     Chunk::new(chunk_content)
 }
 
-// Produces a chunk out of the first `DataMap`, which is validated for its size.
+// Produces a chunk out of the first `DataMapLevel`, which is validated for its size.
 // If the chunk is too big, it is self-encrypted and the resulting (additional level) `DataMap` is put into a chunk.
 // The above step is repeated as many times as required until the chunk size is valid.
 // In other words: If the chunk content is too big, it will be
 // self encrypted into additional chunks, and now we have a new `DataMap`
 // which points to all of those additional chunks.. and so on.
-fn pack_data_map(data_map: DataMap) -> Result<(Chunk, Vec<Chunk>)> {
+fn pack_data_map(data_map: DataMapLevel) -> Result<(Chunk, Vec<Chunk>)> {
     let mut chunks = vec![];
-    let mut chunk_content = wrap_data_map(&DataMapLevel::First(data_map))?;
+    let mut chunk_content = wrap_data_map(&data_map)?;
 
     let (data_map_chunk, additional_chunks) = loop {
         let chunk = to_chunk(chunk_content);
@@ -139,3 +214,75 @@ fn encrypt_file(file: &Path, output_dir: &Path) -> Result<(DataMap, Vec<XorName>
     let encrypted_chunks = self_encryption::encrypt_from_file(file, output_dir)?;
     Ok(encrypted_chunks)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use rand::RngCore;
+    use tempfile::tempdir;
+
+    #[test]
+    fn segments_are_three_chunks_of_the_target_size() {
+        assert_eq!(segment_sizes(12 * 1024, 1024), vec![3 * 1024; 4]);
+        assert_eq!(
+            segment_sizes(10 * 1024, 1024),
+            vec![3 * 1024, 3 * 1024, 3 * 1024, 1024]
+        );
+        // a remainder too small to be self-encrypted is added to the last segment
+        assert_eq!(
+            segment_sizes(6 * 1024 + 1, 1024),
+            vec![3 * 1024, 3 * 1024 + 1]
+        );
+        assert_eq!(segment_sizes(2, 1024), vec![2]);
+    }
+
+    #[test]
+    fn segmented_file_is_chunked_to_the_target_size_and_decrypts() -> Result<()> {
+        let chunk_size = 8 * 1024;
+        let mut content = vec![0; 10 * chunk_size + 100];
+        rand::thread_rng().fill_bytes(&mut content);
+
+        let temp_dir = tempdir()?;
+        let file_path = temp_dir.path().join("file");
+        std::fs::write(&file_path, &content)?;
+        let chunks_dir = temp_dir.path().join("chunks");
+        std::fs::create_dir(&chunks_dir)?;
+
+        let (data_map_chunk, chunks) =
+            encrypt_large_segmented(&file_path, &chunks_dir, chunk_size)?;
+        let data_maps = match rmp_serde::from_slice(data_map_chunk.value())? {
+            DataMapLevel::Segmented(data_maps) => data_maps,
+            _ => eyre::bail!("Expected a segmented data map"),
+        };
+        assert_eq!(data_maps.len(), 4);
+        assert_eq!(
+            chunks.len(),
+            data_maps.iter().map(|m| m.infos().len()).sum::<usize>()
+        );
+
+        let mut decrypted = vec![];
+        for (i, data_map) in data_maps.iter().enumerate() {
+            let infos = data_map.infos();
+            if i < data_maps.len() - 1 {
+                assert!(infos.iter().all(|info| info.src_size == chunk_size));
+            }
+            let encrypted_chunks = infos
+                .iter()
+                .map(|info| {
+                    let content = std::fs::read(chunks_dir.join(hex::encode(info.dst_hash)))?;
+                    Ok(self_encryption::EncryptedChunk {
+                        index: info.index,
+                        content: Bytes::from(content),
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            decrypted.extend(self_encryption::decrypt_full_set(
+                data_map,
+                &encrypted_chunks,
+            )?);
+        }
+        assert_eq!(decrypted, content);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Error, Result};
+use futures::{future, Future, StreamExt};
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// When a batch operation stops issuing new work and cancels the work in flight, returning what
+/// completed by then.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// The deadline at the given instant.
+    pub fn at(instant: std::time::Instant) -> Self {
+        Self(Instant::from_std(instant))
+    }
+
+    /// The deadline once the given duration elapsed from now.
+    pub fn after(duration: Duration) -> Self {
+        Self(Instant::now() + duration)
+    }
+
+    /// Whether the deadline has passed.
+    pub fn has_passed(&self) -> bool {
+        Instant::now() >= self.0
+    }
+
+    /// The time left until the deadline, zero once it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+
+    pub(crate) fn instant(&self) -> Instant {
+        self.0
+    }
+}
+
+/// The outcome of a batch operation with a deadline, each of its addresses classified by how far
+/// it got.
+#[derive(Debug)]
+pub struct BatchOutcome<A, T> {
+    /// The addresses done with, and their items, in the order of the batch.
+    pub completed: Vec<(A, T)>,
+    /// The addresses which failed before the deadline, and their errors.
+    pub failed: Vec<(A, Error)>,
+    /// The addresses in flight when the deadline passed, cancelled then.
+    pub cancelled: Vec<A>,
+    /// The addresses not attempted at all, the deadline having passed before their turn.
+    pub not_attempted: Vec<A>,
+}
+
+impl<A, T> BatchOutcome<A, T> {
+    /// Whether every address of the batch completed.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && !self.deadline_passed()
+    }
+
+    /// Whether the deadline cut the batch short, leaving addresses cancelled or not attempted.
+    pub fn deadline_passed(&self) -> bool {
+        !self.cancelled.is_empty() || !self.not_attempted.is_empty()
+    }
+
+    /// The addresses which didn't complete, whether failed, cancelled or not attempted.
+    pub fn incomplete(&self) -> impl Iterator<Item = &A> {
+        self.failed
+            .iter()
+            .map(|(address, _)| address)
+            .chain(&self.cancelled)
+            .chain(&self.not_attempted)
+    }
+}
+
+/// Run `op` on each of the addresses, `max_concurrency` of them at once, until they're all done
+/// or the deadline passes.
+///
+/// No address is started once the deadline passed, and those in flight then are cancelled.
+pub(crate) async fn run_batch<A, T, F, Fut>(
+    addrs: &[A],
+    max_concurrency: usize,
+    deadline: Option<Deadline>,
+    op: F,
+) -> BatchOutcome<A, T>
+where
+    A: Copy + Debug,
+    F: Fn(A) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    // the addresses are started in order, so those before this count are the ones started
+    let started = AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<T>>> = addrs.iter().map(|_| None).collect();
+    {
+        let mut stream = futures::stream::iter(addrs.iter().copied().enumerate())
+            .take_while(|_| future::ready(!deadline.is_some_and(|deadline| deadline.has_passed())))
+            .map(|(index, address)| {
+                let _ = started.fetch_add(1, Ordering::Relaxed);
+                let operation = op(address);
+                async move { (index, operation.await) }
+            })
+            .buffer_unordered(max_concurrency.max(1));
+
+        loop {
+            let next = match deadline {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline.instant(), stream.next()).await {
+                        Ok(next) => next,
+                        Err(_) => break,
+                    }
+                }
+                None => stream.next().await,
+            };
+            let Some((index, result)) = next else {
+                break;
+            };
+            results[index] = Some(result);
+        }
+    }
+
+    let started = started.load(Ordering::Relaxed);
+    let mut outcome = BatchOutcome {
+        completed: vec![],
+        failed: vec![],
+        cancelled: vec![],
+        not_attempted: vec![],
+    };
+    for (index, (address, result)) in addrs.iter().zip(results).enumerate() {
+        match result {
+            Some(Ok(item)) => outcome.completed.push((*address, item)),
+            Some(Err(err)) => {
+                warn!("Batch operation on {address:?} failed: {err}");
+                outcome.failed.push((*address, err));
+            }
+            None if index < started => outcome.cancelled.push(*address),
+            None => outcome.not_attempted.push(*address),
+        }
+    }
+    if outcome.deadline_passed() {
+        info!(
+            "Deadline passed with {} of {} addresses completed, {} failed, {} cancelled and {} not attempted",
+            outcome.completed.len(),
+            addrs.len(),
+            outcome.failed.len(),
+            outcome.cancelled.len(),
+            outcome.not_attempted.len()
+        );
+    }
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the tests run on a paused clock, auto-advanced as the tasks sleep, for the steps not to
+    // drift with the load of the machine
+    const STEP: Duration = Duration::from_millis(100);
+
+    /// Address `n` takes `n` steps to complete, or to fail if among the `failing` ones.
+    async fn slow_op(address: u32, failing: &[u32]) -> Result<u32> {
+        tokio::time::sleep(STEP * address).await;
+        if failing.contains(&address) {
+            Err(Error::EmptyDataMap)
+        } else {
+            Ok(address * 10)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn without_deadline_every_address_is_done() {
+        let addrs: Vec<u32> = (0..6).rev().collect();
+        let outcome = run_batch(&addrs, 3, None, |address| slow_op(address, &[1, 3, 5])).await;
+
+        assert_eq!(outcome.completed, vec![(4, 40), (2, 20), (0, 0)]);
+        let failed: Vec<_> = outcome.failed.iter().map(|(address, _)| *address).collect();
+        assert_eq!(failed, vec![5, 3, 1]);
+        assert!(!outcome.deadline_passed());
+        assert!(!outcome.is_complete());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_deadline_classifies_what_was_done_in_flight_and_left() {
+        // with 2 at once: 1 and 2 complete at steps 1 and 2, 3 starts at step 1 and fails at 4,
+        // 4 starts at step 2 and would complete at 6, 5 starts at step 4 and would fail at 9
+        let addrs: Vec<u32> = (1..=8).collect();
+        let deadline = Deadline::after(STEP * 5);
+        let outcome = run_batch(&addrs, 2, Some(deadline), |address| slow_op(address, &[3])).await;
+
+        assert!(deadline.has_passed());
+        assert_eq!(outcome.completed, vec![(1, 10), (2, 20)]);
+        let failed: Vec<_> = outcome.failed.iter().map(|(address, _)| *address).collect();
+        assert_eq!(failed, vec![3]);
+        assert_eq!(outcome.cancelled, vec![4, 5]);
+        assert_eq!(outcome.not_attempted, vec![6, 7, 8]);
+        assert!(outcome.deadline_passed());
+        assert_eq!(outcome.incomplete().count(), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_is_attempted_past_the_deadline() {
+        let addrs: Vec<u32> = (0..4).collect();
+        let deadline = Deadline::after(Duration::ZERO);
+        let outcome = run_batch(&addrs, 2, Some(deadline), |address| slow_op(address, &[])).await;
+
+        assert!(outcome.completed.is_empty());
+        assert!(outcome.cancelled.is_empty());
+        assert_eq!(outcome.not_attempted, addrs);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_batch_done_before_the_deadline_is_complete() {
+        let addrs: Vec<u32> = (0..4).collect();
+        let deadline = Deadline::after(STEP * 100);
+        let outcome = run_batch(&addrs, 4, Some(deadline), |address| slow_op(address, &[])).await;
+
+        assert!(outcome.is_complete());
+        assert_eq!(outcome.completed, vec![(0, 0), (1, 10), (2, 20), (3, 30)]);
+        assert!(!deadline.has_passed());
+    }
+}
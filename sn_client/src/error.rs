@@ -9,10 +9,16 @@
 pub(crate) type Result<T> = std::result::Result<T, Error>;
 
 use super::ClientEvent;
-use sn_registers::{Entry, EntryHash};
-use sn_transfers::SpendAddress;
-use std::{collections::BTreeSet, time::Duration};
+use libp2p::PeerId;
+use sn_protocol::{
+    storage::{Chunk, ChunkAddress},
+    NetworkAddress,
+};
+use sn_registers::{Entry, EntryHash, RegisterAddress};
+use sn_transfers::{NanoTokens, RoyaltyRate, SignedSpend, SpendAddress, WalletError};
+use std::{collections::BTreeSet, path::PathBuf, time::Duration};
 use thiserror::Error;
+use xor_name::XorName;
 
 /// Internal error.
 #[derive(Debug, Error)]
@@ -22,10 +28,13 @@ pub enum Error {
     GenesisError(#[from] sn_transfers::GenesisError),
 
     #[error("Transfer Error {0}.")]
-    Transfers(#[from] sn_transfers::WalletError),
+    Transfers(sn_transfers::WalletError),
 
     #[error("Network Error {0}.")]
-    Network(#[from] sn_networking::Error),
+    Network(sn_networking::Error),
+
+    #[error("Failed to render the metrics: {0}")]
+    MetricsRendering(#[from] std::fmt::Error),
 
     #[error("Protocol error {0}.")]
     Protocol(#[from] sn_protocol::error::Error),
@@ -58,12 +67,87 @@ pub enum Error {
     #[error("There is no Spend record at this address: {0:?}")]
     MissingSpendRecord(SpendAddress),
 
+    #[error("Spend at {address:?} is invalid: {source}")]
+    InvalidSpend {
+        address: SpendAddress,
+        source: sn_transfers::Error,
+    },
+
+    #[error("Parent spends {missing:?} of the spend at {address:?} are not on the network")]
+    MissingParentSpends {
+        address: SpendAddress,
+        missing: Vec<SpendAddress>,
+    },
+
+    #[error("Chunk {address:?} served by {holders:?} does not match its address")]
+    CorruptedChunk {
+        address: ChunkAddress,
+        holders: Vec<PeerId>,
+    },
+
+    #[error("Failed to fetch {} of the chunks: {failed:?}", failed.len())]
+    ChunksNotFetched {
+        /// The addresses of the chunks that couldn't be fetched.
+        failed: Vec<ChunkAddress>,
+        /// All the chunks, in the order they were asked for, `None` for those that failed.
+        fetched: Vec<Option<Chunk>>,
+    },
+
+    #[error("Found {} conflicting spends at {address:?}", spends.len())]
+    DoubleSpendDetected {
+        address: SpendAddress,
+        spends: Vec<SignedSpend>,
+    },
+
     #[error(
         "Content branches detected in the Register which need to be merged/resolved by user. \
         Entries hashes of branches are: {0:?}"
     )]
     ContentBranchDetected(BTreeSet<(EntryHash, Entry)>),
 
+    #[error(
+        "The Register is still being uploaded by {claimant:?} under nonce {nonce}, its claim expires in {remaining:?}"
+    )]
+    RegisterClaimed {
+        claimant: bls::PublicKey,
+        /// Tells apart the clients of the same owner, as claims are signed by the owner.
+        nonce: u64,
+        remaining: Duration,
+    },
+
+    #[error("The Register could not be verified as stored after {attempts} attempts, {total_paid} paid for it")]
+    RegisterCreationVerificationFailed {
+        attempts: usize,
+        total_paid: NanoTokens,
+    },
+
+    #[error("The Register at {address:?} is missing {} of the expected entries: {missing:?}", missing.len())]
+    RegisterEntriesMissing {
+        address: Box<RegisterAddress>,
+        missing: BTreeSet<EntryHash>,
+    },
+
+    #[error(
+        "The spend takes {size} bytes, more than the {max} bytes a record can hold, its \
+        transaction having {inputs} inputs and {outputs} outputs. Consolidate the cash notes of \
+        the wallet, or send to fewer recipients at once."
+    )]
+    TransactionTooLarge {
+        size: usize,
+        max: usize,
+        inputs: usize,
+        outputs: usize,
+    },
+
+    #[error(
+        "The nodes expect the network royalties at {theirs:?} while we pay them at {ours:?}, \
+        this client needs to be updated to the rate of the network."
+    )]
+    RoyaltyRateMismatch {
+        ours: RoyaltyRate,
+        theirs: RoyaltyRate,
+    },
+
     #[error("The provided amount contains zero nanos")]
     AmountIsZero,
 
@@ -77,6 +161,31 @@ pub enum Error {
     #[error("Could not connect to the network in {0:?}")]
     ConnectionTimeout(Duration),
 
+    #[error("The client was shut down before the operation completed")]
+    ClientShutDown,
+
+    /// Checked before chunking or downloading a file, see `DiskSpaceCheck`.
+    #[error("Not enough disk space at {path:?}: {required} bytes required, {available} available")]
+    InsufficientDiskSpace {
+        required: u64,
+        available: u64,
+        path: PathBuf,
+    },
+
+    /// A chunk couldn't be verified to be held by the nodes expected to store it.
+    #[error("Failed to verify chunk {addr:?} is stored: {source}")]
+    ChunkVerificationFailed {
+        addr: NetworkAddress,
+        source: sn_networking::Error,
+    },
+
+    /// A read with a timeout set didn't complete in time.
+    #[error("Operation on {addr:?} timed out after {elapsed:?}")]
+    OperationTimedOut {
+        addr: NetworkAddress,
+        elapsed: Duration,
+    },
+
     #[error("Too many sequential upload payment failures")]
     SequentialUploadPaymentError,
 
@@ -91,4 +200,86 @@ pub enum Error {
 
     #[error("Error occurred while assembling the downloaded chunks")]
     FailedToAssembleDownloadedChunks,
+
+    #[error("The downloaded chunk {0:?} does not match its entry in the data map")]
+    DownloadedChunkMismatch(XorName),
+
+    #[error("Could not (de)serialise the manifest: {0}")]
+    ManifestSerialisation(serde_json::Error),
+
+    #[error("The manifest lists a chunk at an invalid address: {0:?}")]
+    InvalidManifestChunk(String),
+
+    #[error("Could not (de)serialise the royalty report: {0}")]
+    RoyaltyReportSerialisation(serde_json::Error),
+
+    #[error("Could not serialise the anomaly report: {0}")]
+    AnomalyReportSerialisation(serde_json::Error),
+
+    #[error("Could not (de)serialise the audit frontier: {0}")]
+    AuditFrontierSerialisation(serde_json::Error),
+
+    #[error("Could not (de)serialise the double spend evidence: {0}")]
+    DoubleSpendEvidenceSerialisation(serde_json::Error),
+
+    #[error("Could not (de)serialise the spend verification report: {0}")]
+    SpendVerificationReportSerialisation(serde_json::Error),
+
+    #[error("Could not serialise the SpendDag: {0}")]
+    SpendDagSerialisation(rmp_serde::encode::Error),
+
+    #[error("The SpendDag stored at {path:?} is corrupted ({reason}), rebuild it from scratch")]
+    SpendDagCorrupted { path: PathBuf, reason: String },
+
+    #[error(
+        "The SpendDag stored at {path:?} is of format version {found} while this client reads \
+        version {expected}, rebuild it from scratch"
+    )]
+    SpendDagVersionMismatch {
+        path: PathBuf,
+        found: u8,
+        expected: u8,
+    },
+
+    #[error("The audit found {found} where {expected} were expected, a discrepancy in the supply")]
+    SupplyMismatch {
+        expected: NanoTokens,
+        found: NanoTokens,
+    },
+
+    #[error("The gossipsub message payload of {size} bytes exceeds the limit of {max} bytes")]
+    GossipMsgTooLarge { size: usize, max: usize },
+
+    #[error("Unsupported version {0} of signed gossipsub message")]
+    UnsupportedGossipMsgVersion(u8),
+
+    #[error("Invalid signed gossipsub message: {0}")]
+    InvalidGossipMsg(String),
+}
+
+impl From<sn_networking::Error> for Error {
+    fn from(error: sn_networking::Error) -> Self {
+        // the nodes refused our payment: their rate is the one we have to pay at
+        if let sn_networking::Error::ProtocolError(
+            sn_protocol::error::Error::RoyaltyRateMismatch { ours, theirs },
+        ) = error.last_cause()
+        {
+            return Error::RoyaltyRateMismatch {
+                ours: *theirs,
+                theirs: *ours,
+            };
+        }
+        Error::Network(error)
+    }
+}
+
+impl From<WalletError> for Error {
+    fn from(error: WalletError) -> Self {
+        match error {
+            WalletError::RoyaltyRateMismatch { ours, theirs } => {
+                Error::RoyaltyRateMismatch { ours, theirs }
+            }
+            error => Error::Transfers(error),
+        }
+    }
 }
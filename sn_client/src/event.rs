@@ -6,10 +6,13 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::error::Result;
+use super::{error::Result, SessionCosts};
 
 use bytes::Bytes;
 use serde::Serialize;
+use sn_protocol::storage::ChunkAddress;
+use sn_transfers::NanoTokens;
+use std::time::{Duration, SystemTime};
 use tokio::sync::broadcast;
 
 // Channel where events will be broadcasted by the client.
@@ -18,11 +21,17 @@ pub(super) struct ClientEventsChannel(broadcast::Sender<ClientEvent>);
 
 impl Default for ClientEventsChannel {
     fn default() -> Self {
-        Self(broadcast::channel(100).0)
+        Self::with_capacity(100)
     }
 }
 
 impl ClientEventsChannel {
+    /// A channel keeping up to `capacity` events for its slowest receiver, dropping the oldest
+    /// ones beyond that.
+    fn with_capacity(capacity: usize) -> Self {
+        Self(broadcast::channel(capacity).0)
+    }
+
     /// Returns a new receiver to listen to the channel.
     /// Multiple receivers can be actively listening.
     pub(super) fn subscribe(&self) -> ClientEventsReceiver {
@@ -34,6 +43,14 @@ impl ClientEventsChannel {
         let _subscriber_count = self.0.send(event)?;
         Ok(())
     }
+
+    // Broadcast a progress event, whether anyone listens or not. This never blocks: a receiver
+    // too slow to keep up misses the oldest events instead.
+    pub(crate) fn notify(&self, event: ClientEvent) {
+        if let Err(err) = self.0.send(event) {
+            trace!("No receiver for the client event {:?}", err.0);
+        }
+    }
 }
 
 /// Type of events broadcasted by the client to the public API.
@@ -41,9 +58,38 @@ impl ClientEventsChannel {
 pub enum ClientEvent {
     /// The client has been connected to the network
     ConnectedToNetwork,
-    /// No network activity has been received for a given duration
-    /// we should error out
-    InactiveClient(std::time::Duration),
+    /// Some of the peers needed to connect to the network have been found
+    InitialPeersFound {
+        /// The number of peers found so far
+        found: usize,
+        /// The number of peers needed to be connected
+        needed: usize,
+    },
+    /// No network activity has been received for a given duration,
+    /// while we still hold connections to some peers
+    Idle(Duration),
+    /// The connections to all our peers have been lost
+    Disconnected {
+        /// When the connection to the last of our peers was lost
+        last_peer_lost_at: SystemTime,
+    },
+    /// The client has been disconnected for too long, and starts redialling its peers
+    Reconnecting {
+        /// When the connection to the last of our peers was lost
+        last_peer_lost_at: SystemTime,
+    },
+    /// The client got a connection to a peer back while reconnecting
+    Reconnected {
+        /// The number of times the peers were redialled
+        attempts: u32,
+    },
+    /// A node asked us to back off as it is busy
+    NodeBusy {
+        /// How long the node asked us to wait before retrying
+        retry_after: Duration,
+        /// The number of such requests to back off received so far
+        total_busy_responses: usize,
+    },
     /// Gossipsub message received on a topic the client has subscribed to
     GossipsubMsg {
         /// Topic the message was published on
@@ -52,6 +98,36 @@ pub enum ClientEvent {
         #[debug(skip)]
         msg: Bytes,
     },
+    /// A chunk has been stored on the network
+    ChunkStored {
+        /// Address of the chunk
+        addr: ChunkAddress,
+        /// The store cost paid for the chunk
+        cost: NanoTokens,
+    },
+    /// A chunk failed to be stored on the network, it might be retried
+    ChunkStoreFailed {
+        /// Address of the chunk
+        addr: ChunkAddress,
+        /// Why the chunk could not be stored
+        error: String,
+    },
+    /// The storage of a batch of records has been paid for
+    PaymentMade {
+        /// The store cost paid for the records
+        total: NanoTokens,
+        /// The network royalties paid on top of the store cost
+        royalties: NanoTokens,
+        /// The fees paid by the client so far, this payment included, see `Client::session_costs`
+        session: SessionCosts,
+    },
+    /// A batch of chunks of an upload has been paid for and sent out to the network
+    UploadBatchCompleted {
+        /// The number of the batch, from 1
+        batch: usize,
+        /// The number of batches of the upload
+        total_batches: usize,
+    },
 }
 
 /// Receiver Channel where users of the public API can listen to events broadcasted by the client.
@@ -60,8 +136,341 @@ pub struct ClientEventsReceiver(pub(super) broadcast::Receiver<ClientEvent>);
 
 impl ClientEventsReceiver {
     /// Receive a new event, meant to be used by the user of the public API.
+    ///
+    /// The events which were dropped as the receiver couldn't keep up are skipped.
     pub async fn recv(&mut self) -> Result<ClientEvent> {
-        let event = self.0.recv().await?;
-        Ok(event)
+        loop {
+            match self.0.recv().await {
+                Ok(event) => return Ok(event),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(
+                        "Could not keep up with the client events, {missed} of them were dropped"
+                    );
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+/// The state of the client's connection to the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// Not yet connected to enough peers to reach the network.
+    Connecting,
+    /// Connected to the network, holding live connections to this many peers.
+    Connected { live_peers: usize },
+    /// The connections to all our peers have been lost since we got connected.
+    Disconnected { last_peer_lost_at: SystemTime },
+}
+
+/// How a client which lost the connections to all its peers reconnects to the network.
+///
+/// Once the client has been disconnected for `after_inactive_periods` consecutive periods of
+/// inactivity (30s each), its peers are redialled every `interval`, up to `attempts` times. If
+/// none of them is reachable by then, the client gives up until it's been disconnected for as
+/// long again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub after_inactive_periods: u32,
+    pub attempts: u32,
+    pub interval: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            after_inactive_periods: 2,
+            attempts: 10,
+            interval: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Tracks the peers we hold live connections to, telling an idle client from a disconnected one.
+#[derive(Debug, Default)]
+pub(crate) struct Connectivity {
+    connected: bool,
+    live_peers: usize,
+    last_peer_lost_at: Option<SystemTime>,
+    // The consecutive periods of inactivity spent disconnected.
+    disconnected_periods: u32,
+    // The redials made so far, while reconnecting.
+    reconnect_attempts: Option<u32>,
+}
+
+impl Connectivity {
+    /// We're connected to enough peers to reach the network.
+    pub(crate) fn set_connected(&mut self) {
+        self.connected = true;
+    }
+
+    /// Returns the `Reconnected` event to broadcast if we were reconnecting.
+    pub(crate) fn peer_connected(&mut self, live_peers: usize) -> Option<ClientEvent> {
+        self.live_peers = live_peers;
+        self.last_peer_lost_at = None;
+        self.disconnected_periods = 0;
+        self.reconnect_attempts
+            .take()
+            .map(|attempts| ClientEvent::Reconnected { attempts })
+    }
+
+    /// Returns the `Disconnected` event to broadcast if that was the last of our peers.
+    pub(crate) fn peer_disconnected(
+        &mut self,
+        live_peers: usize,
+        now: SystemTime,
+    ) -> Option<ClientEvent> {
+        self.live_peers = live_peers;
+        if live_peers > 0 || !self.connected || self.last_peer_lost_at.is_some() {
+            return None;
+        }
+        self.last_peer_lost_at = Some(now);
+        Some(ClientEvent::Disconnected {
+            last_peer_lost_at: now,
+        })
+    }
+
+    /// The event to broadcast when no network activity was received for the given duration.
+    pub(crate) fn inactivity_event(&self, inactivity: Duration) -> ClientEvent {
+        match self.last_peer_lost_at {
+            Some(last_peer_lost_at) => ClientEvent::Disconnected { last_peer_lost_at },
+            None => ClientEvent::Idle(inactivity),
+        }
+    }
+
+    /// Count a period of inactivity, returning the `Reconnecting` event to broadcast if we've
+    /// been disconnected for long enough to start reconnecting.
+    pub(crate) fn start_reconnecting(&mut self, policy: &ReconnectPolicy) -> Option<ClientEvent> {
+        let last_peer_lost_at = self.last_peer_lost_at?;
+        if self.reconnect_attempts.is_some() {
+            return None;
+        }
+        self.disconnected_periods += 1;
+        if self.disconnected_periods < policy.after_inactive_periods {
+            return None;
+        }
+        self.reconnect_attempts = Some(0);
+        Some(ClientEvent::Reconnecting { last_peer_lost_at })
+    }
+
+    pub(crate) fn is_reconnecting(&self) -> bool {
+        self.reconnect_attempts.is_some()
+    }
+
+    /// The number of the next redial, from 1, or `None` once out of attempts, in which case we
+    /// stop reconnecting for now.
+    pub(crate) fn next_reconnect_attempt(&mut self, policy: &ReconnectPolicy) -> Option<u32> {
+        let attempts = self.reconnect_attempts.as_mut()?;
+        if *attempts >= policy.attempts {
+            self.reconnect_attempts = None;
+            self.disconnected_periods = 0;
+            return None;
+        }
+        *attempts += 1;
+        Some(*attempts)
+    }
+
+    pub(crate) fn status(&self) -> ConnectionStatus {
+        match (self.connected, self.last_peer_lost_at) {
+            (false, _) => ConnectionStatus::Connecting,
+            (true, Some(last_peer_lost_at)) => ConnectionStatus::Disconnected { last_peer_lost_at },
+            (true, None) => ConnectionStatus::Connected {
+                live_peers: self.live_peers,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xor_name::XorName;
+
+    #[tokio::test]
+    async fn slow_receiver_misses_the_oldest_events() -> eyre::Result<()> {
+        let channel = ClientEventsChannel::with_capacity(2);
+        // nobody listening yet, the event is just dropped
+        channel.notify(ClientEvent::ConnectedToNetwork);
+
+        let mut receiver = channel.subscribe();
+        for batch in 1..=4 {
+            channel.notify(ClientEvent::UploadBatchCompleted {
+                batch,
+                total_batches: 4,
+            });
+        }
+
+        for expected in 3..=4 {
+            assert!(matches!(
+                receiver.recv().await?,
+                ClientEvent::UploadBatchCompleted { batch, .. } if batch == expected
+            ));
+        }
+
+        let addr = ChunkAddress::new(XorName::random(&mut rand::thread_rng()));
+        channel.notify(ClientEvent::ChunkStored {
+            addr,
+            cost: NanoTokens::from(1),
+        });
+        assert!(matches!(
+            receiver.recv().await?,
+            ClientEvent::ChunkStored { addr: stored, .. } if stored == addr
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn quiet_but_connected_client_is_idle() {
+        let mut connectivity = Connectivity::default();
+        connectivity.peer_connected(3);
+        connectivity.set_connected();
+
+        // losing some of our peers doesn't disconnect us
+        assert!(connectivity
+            .peer_disconnected(2, SystemTime::now())
+            .is_none());
+
+        let inactivity = Duration::from_secs(30);
+        assert!(matches!(
+            connectivity.inactivity_event(inactivity),
+            ClientEvent::Idle(duration) if duration == inactivity
+        ));
+        assert_eq!(
+            connectivity.status(),
+            ConnectionStatus::Connected { live_peers: 2 }
+        );
+    }
+
+    #[test]
+    fn losing_all_peers_disconnects_until_one_is_back() {
+        let mut connectivity = Connectivity::default();
+        connectivity.peer_connected(1);
+        connectivity.set_connected();
+
+        let lost_at = SystemTime::now();
+        assert!(matches!(
+            connectivity.peer_disconnected(0, lost_at),
+            Some(ClientEvent::Disconnected { last_peer_lost_at }) if last_peer_lost_at == lost_at
+        ));
+        // reported once, then on inactivity
+        assert!(connectivity
+            .peer_disconnected(0, SystemTime::now())
+            .is_none());
+        assert!(matches!(
+            connectivity.inactivity_event(Duration::from_secs(30)),
+            ClientEvent::Disconnected { last_peer_lost_at } if last_peer_lost_at == lost_at
+        ));
+        assert_eq!(
+            connectivity.status(),
+            ConnectionStatus::Disconnected {
+                last_peer_lost_at: lost_at
+            }
+        );
+
+        connectivity.peer_connected(1);
+        assert_eq!(
+            connectivity.status(),
+            ConnectionStatus::Connected { live_peers: 1 }
+        );
+    }
+
+    #[test]
+    fn prolonged_disconnection_triggers_a_reconnection() {
+        let policy = ReconnectPolicy {
+            after_inactive_periods: 2,
+            attempts: 2,
+            interval: Duration::from_secs(1),
+        };
+        let mut connectivity = Connectivity::default();
+        connectivity.peer_connected(1);
+        connectivity.set_connected();
+
+        // idle periods don't count
+        assert!(connectivity.start_reconnecting(&policy).is_none());
+        assert!(connectivity.start_reconnecting(&policy).is_none());
+
+        let lost_at = SystemTime::now();
+        let _ = connectivity.peer_disconnected(0, lost_at);
+        assert!(connectivity.start_reconnecting(&policy).is_none());
+        assert!(matches!(
+            connectivity.start_reconnecting(&policy),
+            Some(ClientEvent::Reconnecting { last_peer_lost_at }) if last_peer_lost_at == lost_at
+        ));
+        assert!(connectivity.is_reconnecting());
+
+        // out of attempts, we start over
+        assert_eq!(connectivity.next_reconnect_attempt(&policy), Some(1));
+        assert_eq!(connectivity.next_reconnect_attempt(&policy), Some(2));
+        assert_eq!(connectivity.next_reconnect_attempt(&policy), None);
+        assert!(!connectivity.is_reconnecting());
+        assert!(connectivity.start_reconnecting(&policy).is_none());
+        assert!(connectivity.start_reconnecting(&policy).is_some());
+
+        assert_eq!(connectivity.next_reconnect_attempt(&policy), Some(1));
+        assert!(matches!(
+            connectivity.peer_connected(1),
+            Some(ClientEvent::Reconnected { attempts: 1 })
+        ));
+        assert!(!connectivity.is_reconnecting());
+        assert!(connectivity.peer_connected(2).is_none());
+    }
+
+    #[tokio::test]
+    async fn reconnecting_with_nobody_listening_drops_the_events() -> eyre::Result<()> {
+        let policy = ReconnectPolicy {
+            after_inactive_periods: 1,
+            attempts: 1,
+            interval: Duration::from_secs(1),
+        };
+        let channel = ClientEventsChannel::default();
+        let mut connectivity = Connectivity::default();
+        connectivity.peer_connected(1);
+        connectivity.set_connected();
+
+        // the client notifies them as it does handling the network events
+        if let Some(event) = connectivity.peer_disconnected(0, SystemTime::now()) {
+            channel.notify(event);
+        }
+        if let Some(event) = connectivity.start_reconnecting(&policy) {
+            channel.notify(event);
+        }
+        assert_eq!(connectivity.next_reconnect_attempt(&policy), Some(1));
+        let reconnected = connectivity.peer_connected(1);
+        assert!(matches!(
+            reconnected,
+            Some(ClientEvent::Reconnected { attempts: 1 })
+        ));
+        if let Some(event) = reconnected {
+            channel.notify(event);
+        }
+        assert!(matches!(
+            connectivity.status(),
+            ConnectionStatus::Connected { live_peers: 1 }
+        ));
+
+        // while `broadcast` fails without receivers
+        assert!(channel
+            .broadcast(ClientEvent::Reconnected { attempts: 1 })
+            .is_err());
+
+        // the events dropped aren't delivered to later receivers
+        let mut receiver = channel.subscribe();
+        channel.notify(ClientEvent::ConnectedToNetwork);
+        assert!(matches!(
+            receiver.recv().await?,
+            ClientEvent::ConnectedToNetwork
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn losing_peers_while_connecting_is_not_a_disconnection() {
+        let mut connectivity = Connectivity::default();
+        connectivity.peer_connected(1);
+        assert!(connectivity
+            .peer_disconnected(0, SystemTime::now())
+            .is_none());
+        assert_eq!(connectivity.status(), ConnectionStatus::Connecting);
     }
 }
@@ -6,10 +6,16 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{wallet::send, Client, Result};
+use crate::{wallet::send, Client, Error, Result};
+use serde::{Deserialize, Serialize};
 use sn_transfers::{
     create_faucet_wallet, load_genesis_wallet, CashNote, LocalWallet, MainPubkey, NanoTokens,
+    WalletError,
 };
+use std::path::{Path, PathBuf};
+
+/// The file, in the wallet dir, tracking the progress of splitting the wallet balance.
+const SPLIT_PROGRESS_FILE_NAME: &str = "split_progress";
 
 /// Returns a cash_note with the requested number of tokens, for use by E2E test instances.
 /// Note this will create a faucet having a Genesis balance
@@ -80,3 +86,126 @@ pub async fn load_faucet_wallet_from_genesis_wallet(client: &Client) -> Result<L
 
     Ok(faucet_wallet)
 }
+
+/// The progress of splitting a wallet balance, so a restarted faucet resumes it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SplitProgress {
+    denomination: NanoTokens,
+    notes: usize,
+    split: usize,
+}
+
+impl SplitProgress {
+    fn path(wallet_dir: &Path) -> PathBuf {
+        wallet_dir.join(SPLIT_PROGRESS_FILE_NAME)
+    }
+
+    fn load(wallet_dir: &Path) -> Result<Option<Self>> {
+        let bytes = match std::fs::read(Self::path(wallet_dir)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let progress = rmp_serde::from_slice(&bytes).map_err(WalletError::from)?;
+        Ok(Some(progress))
+    }
+
+    fn store(&self, wallet_dir: &Path) -> Result<()> {
+        let bytes = rmp_serde::to_vec(self).map_err(WalletError::from)?;
+        std::fs::write(Self::path(wallet_dir), bytes)?;
+        Ok(())
+    }
+}
+
+/// Split the faucet balance into `notes` cash notes of equal value, over transactions of up
+/// to `notes_per_tx` notes, so that payouts can be made concurrently from disjoint inputs.
+///
+/// See `split_wallet_balance`.
+pub async fn split_faucet_wallet(
+    client: &Client,
+    notes: usize,
+    notes_per_tx: usize,
+) -> Result<LocalWallet> {
+    split_wallet_balance(client, create_faucet_wallet(), notes, notes_per_tx).await
+}
+
+/// Split the wallet balance into `notes` cash notes of equal value, over transactions of up
+/// to `notes_per_tx` notes.
+///
+/// The progress is tracked in the wallet dir: once done, or when resumed after a restart,
+/// the balance is not split again.
+pub async fn split_wallet_balance(
+    client: &Client,
+    mut wallet: LocalWallet,
+    notes: usize,
+    notes_per_tx: usize,
+) -> Result<LocalWallet> {
+    let wallet_dir = wallet.wallet_dir().to_path_buf();
+    let notes_per_tx = notes_per_tx.max(1);
+
+    // Spends of an interrupted split, which may not have reached the network.
+    if wallet.unconfirmed_spend_requests_exist() {
+        info!("Resending the spends of an interrupted split of the wallet balance");
+        client
+            .send_spends(wallet.unconfirmed_spend_requests().iter(), true)
+            .await?;
+        wallet.clear_confirmed_spend_requests();
+        wallet.store_unconfirmed_spend_requests()?;
+    }
+
+    let mut progress = match SplitProgress::load(&wallet_dir)? {
+        Some(progress) => progress,
+        None => {
+            let denomination = NanoTokens::from(
+                wallet
+                    .balance()
+                    .as_nano()
+                    .checked_div(notes as u64)
+                    .unwrap_or(0),
+            );
+            if denomination.is_zero() {
+                return Err(Error::AmountIsZero);
+            }
+            SplitProgress {
+                denomination,
+                notes,
+                split: 0,
+            }
+        }
+    };
+
+    while progress.split < progress.notes {
+        let count = notes_per_tx.min(progress.notes - progress.split);
+        println!(
+            "Splitting {count} notes of {} off the wallet balance ({}/{} split)",
+            progress.denomination, progress.split, progress.notes
+        );
+        info!(
+            "Splitting {count} notes of {} off the wallet balance ({}/{} split)",
+            progress.denomination, progress.split, progress.notes
+        );
+
+        let _split_notes = wallet.local_split(progress.denomination, count)?;
+        // persist the spends and the progress before reaching the network, so a restart
+        // resends the spends instead of splitting again
+        wallet.store_unconfirmed_spend_requests()?;
+        progress.split += count;
+        progress.store(&wallet_dir)?;
+
+        client
+            .send_spends(wallet.unconfirmed_spend_requests().iter(), true)
+            .await?;
+        wallet.clear_confirmed_spend_requests();
+        wallet.store_unconfirmed_spend_requests()?;
+    }
+
+    println!(
+        "Wallet balance split into {} notes of {}",
+        progress.notes, progress.denomination
+    );
+    info!(
+        "Wallet balance split into {} notes of {}",
+        progress.notes, progress.denomination
+    );
+    Ok(wallet)
+}
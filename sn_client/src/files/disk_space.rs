@@ -0,0 +1,213 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::error::{Error, Result};
+use self_encryption::MAX_CHUNK_SIZE;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Probes the space available on the filesystem holding a path.
+pub trait SpaceProbe: Send + Sync {
+    /// The number of bytes available to us on the filesystem holding the existing `path`.
+    fn available_space(&self, path: &Path) -> io::Result<u64>;
+}
+
+/// A `SpaceProbe` asking the OS, on any platform.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FsSpaceProbe;
+
+impl SpaceProbe for FsSpaceProbe {
+    fn available_space(&self, path: &Path) -> io::Result<u64> {
+        fs2::available_space(path)
+    }
+}
+
+/// Checks there is enough disk space before chunking a file or downloading one, so that we fail
+/// early rather than halfway through.
+///
+/// The space required is estimated from the size of the file, see `required_space`. Where that
+/// estimate is wrong, e.g. on sparse or compressed filesystems, the check can be skipped.
+#[derive(Clone)]
+pub struct DiskSpaceCheck {
+    probe: Arc<dyn SpaceProbe>,
+    skip: bool,
+}
+
+impl Default for DiskSpaceCheck {
+    fn default() -> Self {
+        Self::with_probe(Arc::new(FsSpaceProbe))
+    }
+}
+
+impl DiskSpaceCheck {
+    /// A check of the space available as told by the given probe.
+    pub fn with_probe(probe: Arc<dyn SpaceProbe>) -> Self {
+        Self { probe, skip: false }
+    }
+
+    /// A check letting everything through.
+    pub fn skipped() -> Self {
+        Self {
+            skip: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the check lets everything through.
+    pub fn is_skipped(&self) -> bool {
+        self.skip
+    }
+
+    /// The space required to chunk, or to download, a file of the given size: the file itself, a
+    /// tenth of it for the intermediate files of the self-encryption, and the data map chunk.
+    pub fn required_space(file_size: u64) -> u64 {
+        file_size
+            .saturating_add(file_size / 10)
+            .saturating_add(MAX_CHUNK_SIZE as u64)
+    }
+
+    /// Fail with `Error::InsufficientDiskSpace` if there is less than `required` bytes available
+    /// on the filesystem where `path` is, or is to be, written.
+    ///
+    /// If the available space can't be told, the check passes.
+    pub fn ensure_space(&self, path: &Path, required: u64) -> Result<()> {
+        if self.skip {
+            return Ok(());
+        }
+        let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+            warn!("No existing ancestor of {path:?} to check the disk space of");
+            return Ok(());
+        };
+        let available = match self.probe.available_space(existing) {
+            Ok(available) => available,
+            Err(err) => {
+                warn!("Could not tell the disk space available at {existing:?}: {err}");
+                return Ok(());
+            }
+        };
+        if available < required {
+            error!("Not enough disk space at {path:?}: {required} bytes required, {available} available");
+            return Err(Error::InsufficientDiskSpace {
+                required,
+                available,
+                path: PathBuf::from(path),
+            });
+        }
+        trace!("{available} bytes available at {path:?}, {required} required");
+        Ok(())
+    }
+
+    /// Same as `ensure_space`, for files of the given sizes all written at `path`, so that a batch
+    /// of files is checked at once rather than each file fitting on its own while they don't all.
+    pub fn ensure_space_for_files(
+        &self,
+        path: &Path,
+        file_sizes: impl IntoIterator<Item = u64>,
+    ) -> Result<()> {
+        if self.skip {
+            return Ok(());
+        }
+        let required = file_sizes
+            .into_iter()
+            .map(Self::required_space)
+            .fold(0u64, u64::saturating_add);
+        self.ensure_space(path, required)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FilesApi;
+    use std::fs;
+
+    struct FixedSpace(u64);
+
+    impl SpaceProbe for FixedSpace {
+        fn available_space(&self, _path: &Path) -> io::Result<u64> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn chunking_fails_early_without_enough_space() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("file");
+        let file_size = 5 * MAX_CHUNK_SIZE as u64;
+        fs::write(&file_path, vec![7; file_size as usize])?;
+        let chunk_dir = dir.path().join("chunks");
+        fs::create_dir(&chunk_dir)?;
+
+        let check = DiskSpaceCheck::with_probe(Arc::new(FixedSpace(file_size)));
+        match FilesApi::chunk_file_with_disk_space_check(&file_path, &chunk_dir, true, &check) {
+            Err(Error::InsufficientDiskSpace {
+                required,
+                available,
+                path,
+            }) => {
+                assert_eq!(required, DiskSpaceCheck::required_space(file_size));
+                assert_eq!(available, file_size);
+                assert_eq!(path, chunk_dir);
+            }
+            other => eyre::bail!("Unexpected chunking result {:?}", other.map(|_| ())),
+        }
+        // nothing was written before failing
+        assert_eq!(fs::read_dir(&chunk_dir)?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn skipped_check_lets_chunking_through() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("file");
+        fs::write(&file_path, vec![7; 5 * MAX_CHUNK_SIZE])?;
+        let chunk_dir = dir.path().join("chunks");
+        fs::create_dir(&chunk_dir)?;
+
+        let check = DiskSpaceCheck::skipped();
+        let (_, _, _, chunks) =
+            FilesApi::chunk_file_with_disk_space_check(&file_path, &chunk_dir, true, &check)?;
+        assert!(!chunks.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn space_is_probed_on_the_closest_existing_ancestor() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let destination = dir.path().join("not").join("yet").join("there");
+
+        let check = DiskSpaceCheck::with_probe(Arc::new(FixedSpace(100)));
+        check.ensure_space(&destination, 100)?;
+        assert!(matches!(
+            check.ensure_space(&destination, 101),
+            Err(Error::InsufficientDiskSpace { available: 100, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn files_are_checked_at_once() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_size = 5 * MAX_CHUNK_SIZE as u64;
+        let required = DiskSpaceCheck::required_space(file_size);
+
+        // each file fits on its own, not both
+        let check = DiskSpaceCheck::with_probe(Arc::new(FixedSpace(required)));
+        check.ensure_space_for_files(dir.path(), [file_size])?;
+        match check.ensure_space_for_files(dir.path(), [file_size, file_size]) {
+            Err(Error::InsufficientDiskSpace { required: sum, .. }) => {
+                assert_eq!(sum, 2 * required)
+            }
+            other => eyre::bail!("Unexpected check result {other:?}"),
+        }
+        Ok(())
+    }
+}
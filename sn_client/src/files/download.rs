@@ -9,14 +9,26 @@
 use crate::{
     chunks::{DataMapLevel, Error as ChunksError},
     error::{Error as ClientError, Result},
-    Client, FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES,
+    Client, DiskSpaceCheck, DownloadManifest, FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
 };
-use bytes::Bytes;
-use futures::StreamExt;
 use itertools::Itertools;
-use self_encryption::{decrypt_full_set, DataMap, EncryptedChunk, StreamSelfDecryptor};
+use libp2p::PeerId;
+use self_encryption::{decrypt_full_set, ChunkInfo, DataMap, EncryptedChunk, StreamSelfDecryptor};
 use sn_protocol::storage::{Chunk, ChunkAddress};
-use std::{collections::HashMap, fs, path::PathBuf, time::Instant};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
 use tokio::sync::mpsc::{self};
 use xor_name::XorName;
 
@@ -49,6 +61,7 @@ pub struct FilesDownload {
     // Configurations
     batch_size: usize,
     show_holders: bool,
+    write_manifest: bool,
     // todo: controlled by GetRecordCfg, need to expose things.
     max_retries: usize,
     // API
@@ -56,6 +69,8 @@ pub struct FilesDownload {
     // Events
     event_sender: Option<mpsc::Sender<FilesDownloadEvent>>,
     logged_event_sender_absence: bool,
+    // The peers which served each downloaded chunk
+    chunk_holders: HashMap<XorName, Vec<PeerId>>,
 }
 
 impl FilesDownload {
@@ -65,16 +80,21 @@ impl FilesDownload {
         Self {
             batch_size: BATCH_SIZE,
             show_holders: false,
+            write_manifest: false,
             max_retries: MAX_UPLOAD_RETRIES,
             api: files_api,
             event_sender: None,
             logged_event_sender_absence: false,
+            chunk_holders: HashMap::new(),
         }
     }
 
     /// Sets the default batch size that determines the number of chunks that are downloaded in parallel
     ///
     /// By default, this option is set to the constant `BATCH_SIZE: usize = 64`.
+    ///
+    /// The chunks of a batch are fetched at most `Client::set_max_concurrent_ops` at a time, the
+    /// others waiting for a slot, so a batch size above that limit only queues more of them.
     pub fn set_batch_size(mut self, batch_size: usize) -> Self {
         self.batch_size = batch_size;
         self
@@ -88,6 +108,15 @@ impl FilesDownload {
         self
     }
 
+    /// Sets the option to write a `DownloadManifest` next to the files downloaded to a path, so that
+    /// they can later be verified locally.
+    ///
+    /// By default, this option is set to false.
+    pub fn set_write_manifest(mut self, write_manifest: bool) -> Self {
+        self.write_manifest = write_manifest;
+        self
+    }
+
     /// Sets the maximum number of retries to perform if a chunk fails to download.
     ///
     /// By default, this option is set to the constant `MAX_UPLOAD_RETRIES: usize = 3`.
@@ -148,33 +177,21 @@ impl FilesDownload {
 
         // First try to deserialize a LargeFile, if it works, we go and seek it.
         // If an error occurs, we consider it to be a SmallFile.
-        if let Ok(data_map) = self.unpack_chunk(chunk.clone()).await {
-            let info = self_encryption::seek_info(data_map.file_size(), position, length);
-            let range = &info.index_range;
-            let all_infos = data_map.infos();
-
-            let to_download = (range.start..range.end + 1)
-                .clone()
-                .map(|i| all_infos[i].clone())
-                .collect_vec();
-            let to_download = DataMap::new(to_download);
-
-            // not written to file and return the encrypted chunks
-            if let DownloadReturnType::EncryptedChunks(encrypted_chunks) =
-                self.read(to_download, None, true, false).await?
-            {
-                let bytes = self_encryption::decrypt_range(
-                    &data_map,
-                    &encrypted_chunks,
-                    info.relative_pos,
-                    length,
-                )
-                .map_err(ChunksError::SelfEncryption)?;
-                return Ok(bytes);
-            } else {
-                error!("IncorrectDownloadOption: expected to get the encrypted chunks back");
-                return Err(ClientError::IncorrectDownloadOption);
+        if let Ok(data_maps) = self.unpack_chunk(chunk.clone()).await {
+            // read the requested range from each of the segments it overlaps
+            let end = position.saturating_add(length);
+            let mut bytes = BytesMut::new();
+            let mut segment_start = 0;
+            for data_map in data_maps {
+                let segment_end = segment_start + data_map.file_size();
+                if segment_start < end && position < segment_end {
+                    let from = position.saturating_sub(segment_start);
+                    let to = end.min(segment_end) - segment_start;
+                    bytes.extend_from_slice(&self.read_range(data_map, from, to - from).await?);
+                }
+                segment_start = segment_end;
             }
+            return Ok(bytes.freeze());
         }
 
         // The error above is ignored to avoid leaking the storage format detail of SmallFiles and LargeFiles.
@@ -188,6 +205,102 @@ impl FilesDownload {
         Ok(bytes)
     }
 
+    /// Read `length` bytes from `position` of the data the data map points to.
+    async fn read_range(
+        &mut self,
+        data_map: DataMap,
+        position: usize,
+        length: usize,
+    ) -> Result<Bytes> {
+        let info = self_encryption::seek_info(data_map.file_size(), position, length);
+        let range = &info.index_range;
+        let all_infos = data_map.infos();
+
+        let to_download = (range.start..range.end + 1)
+            .clone()
+            .map(|i| all_infos[i].clone())
+            .collect_vec();
+        let to_download = DataMap::new(to_download);
+
+        // not written to file and return the encrypted chunks
+        if let DownloadReturnType::EncryptedChunks(encrypted_chunks) =
+            self.read(to_download, None, true, false).await?
+        {
+            let bytes = self_encryption::decrypt_range(
+                &data_map,
+                &encrypted_chunks,
+                info.relative_pos,
+                length,
+            )
+            .map_err(ChunksError::SelfEncryption)?;
+            Ok(bytes)
+        } else {
+            error!("IncorrectDownloadOption: expected to get the encrypted chunks back");
+            Err(ClientError::IncorrectDownloadOption)
+        }
+    }
+
+    /// Download a file from the network as a stream of its decrypted bytes, in order.
+    ///
+    /// The DataMap is fetched first, then the chunks are fetched `batch_size` at a time and each
+    /// of them is yielded, decrypted, as soon as it is verified and all the chunks before it
+    /// have been yielded. The stream ends after the first error, e.g. a chunk missing.
+    pub async fn download_stream(
+        &mut self,
+        address: ChunkAddress,
+    ) -> Result<BoxStream<'static, Result<Bytes>>> {
+        let head_chunk = self.api.client.get_chunk(address, false).await?;
+
+        // if an error occurs, we assume it's a SmallFile
+        let Ok(data_maps) = self.unpack_chunk(head_chunk.clone()).await else {
+            return Ok(stream::iter([Ok(head_chunk.value().clone())]).boxed());
+        };
+
+        let blocks = data_maps.into_iter().flat_map(|data_map| {
+            let data_map = Arc::new(data_map);
+            data_map
+                .infos()
+                .into_iter()
+                .map(move |info| (data_map.clone(), info))
+        });
+        let client = self.api.client.clone();
+        let stream = stream::iter(blocks.collect_vec())
+            .map(move |(data_map, info)| Self::get_decrypted_block(client.clone(), data_map, info))
+            // keeps the order, with at most batch_size blocks in memory
+            .buffered(self.batch_size)
+            .scan(false, |failed, result| {
+                if *failed {
+                    return future::ready(None);
+                }
+                *failed = result.is_err();
+                future::ready(Some(result))
+            });
+
+        Ok(stream.boxed())
+    }
+
+    /// Fetch the chunk holding the given block of the data map, and decrypt it once verified.
+    async fn get_decrypted_block(
+        client: Client,
+        data_map: Arc<DataMap>,
+        info: ChunkInfo,
+    ) -> Result<Bytes> {
+        let (chunk_address, index, encrypted_chunk, _) =
+            Self::get_chunk(client, info.dst_hash, info.index, false).await?;
+        if XorName::from_content(&encrypted_chunk.content) != info.dst_hash {
+            error!("Chunk {chunk_address:?} of index {index} does not match its content");
+            return Err(ClientError::DownloadedChunkMismatch(info.dst_hash));
+        }
+
+        let bytes = self_encryption::decrypt_range(&data_map, &[encrypted_chunk], 0, info.src_size)
+            .map_err(ChunksError::SelfEncryption)?;
+        if XorName::from_content(&bytes) != info.src_hash {
+            error!("Chunk {chunk_address:?} of index {index} did not decrypt to its source");
+            return Err(ClientError::DownloadedChunkMismatch(info.dst_hash));
+        }
+        Ok(bytes)
+    }
+
     /// Download a file from the network and get the decrypted bytes.
     /// If the data_map_chunk is not provided, the DataMap is fetched from the network using the provided address.
     pub async fn download_file(
@@ -228,6 +341,25 @@ impl FilesDownload {
         }
     }
 
+    /// The size of a file, fetching only its data maps from the network rather than its content,
+    /// e.g. to check there is enough disk space to download a batch of files.
+    /// If the data_map_chunk is not provided, the DataMap is fetched from the network using the provided address.
+    pub async fn file_size(
+        &mut self,
+        address: ChunkAddress,
+        data_map_chunk: Option<Chunk>,
+    ) -> Result<usize> {
+        let head_chunk = match data_map_chunk {
+            Some(chunk) => chunk,
+            None => self.api.client.get_chunk(address, false).await?,
+        };
+        match self.unpack_chunk(head_chunk.clone()).await {
+            Ok(data_maps) => Ok(data_maps.iter().map(|data_map| data_map.file_size()).sum()),
+            // not a data map, a SmallFile
+            Err(_) => Ok(head_chunk.value().len()),
+        }
+    }
+
     /// Download a file from the network.
     /// If you want to track the download progress, use the `get_events` method.
     async fn download_entire_file(
@@ -238,6 +370,7 @@ impl FilesDownload {
     ) -> Result<Option<Bytes>> {
         // clean up the trackers/stats
         self.logged_event_sender_absence = false;
+        self.chunk_holders.clear();
 
         let result = self
             .download_entire_file_inner(address, data_map_chunk, downloaded_file_path)
@@ -261,12 +394,17 @@ impl FilesDownload {
         data_map_chunk: Option<Chunk>,
         downloaded_file_path: Option<PathBuf>,
     ) -> Result<Option<Bytes>> {
-        let head_chunk = if let Some(chunk) = data_map_chunk {
+        let (head_chunk, head_holders) = if let Some(chunk) = data_map_chunk {
             info!("Downloading via supplied local datamap");
-            chunk
+            (chunk, vec![])
         } else {
-            match self.api.client.get_chunk(address, self.show_holders).await {
-                Ok(chunk) => chunk,
+            match self
+                .api
+                .client
+                .get_chunk_and_holders(address, self.show_holders)
+                .await
+            {
+                Ok(chunk_and_holders) => chunk_and_holders,
                 Err(err) => {
                     error!("Failed to fetch head chunk {address:?}");
                     return Err(err);
@@ -275,26 +413,56 @@ impl FilesDownload {
         };
 
         // first try to deserialize a LargeFile, if it works, we go and seek it
-        if let Ok(data_map) = self.unpack_chunk(head_chunk.clone()).await {
-            // read_all emits
-            match self
-                .read(data_map, downloaded_file_path, false, false)
-                .await?
-            {
-                DownloadReturnType::EncryptedChunks(_) => {
-                    error!("IncorrectDownloadOption: we should not be getting the encrypted chunks back as it is set to false.");
-                    Err(ClientError::IncorrectDownloadOption)
+        if let Ok(data_maps) = self.unpack_chunk(head_chunk.clone()).await {
+            if let Some(path) = &downloaded_file_path {
+                let file_size = data_maps.iter().map(|data_map| data_map.file_size()).sum();
+                self.ensure_space_for(path, file_size)?;
+            }
+            let bytes = match data_maps.as_slice() {
+                // read_all emits
+                [data_map] => match self
+                    .read(data_map.clone(), downloaded_file_path.clone(), false, false)
+                    .await?
+                {
+                    DownloadReturnType::EncryptedChunks(_) => {
+                        error!("IncorrectDownloadOption: we should not be getting the encrypted chunks back as it is set to false.");
+                        return Err(ClientError::IncorrectDownloadOption);
+                    }
+                    DownloadReturnType::DecryptedBytes(bytes) => Some(bytes),
+                    DownloadReturnType::WrittenToFileSystem => None,
+                },
+                _ => {
+                    self.read_segments(&data_maps, downloaded_file_path.as_deref())
+                        .await?
                 }
-                DownloadReturnType::DecryptedBytes(bytes) => Ok(Some(bytes)),
-                DownloadReturnType::WrittenToFileSystem => Ok(None),
+            };
+
+            if let Some(path) = downloaded_file_path {
+                let chunks = data_maps
+                    .iter()
+                    .flat_map(|data_map| data_map.infos())
+                    .map(|info| {
+                        let holders = self
+                            .chunk_holders
+                            .get(&info.dst_hash)
+                            .cloned()
+                            .unwrap_or_default();
+                        (info.dst_hash, info.src_size, holders)
+                    })
+                    .collect();
+                self.write_manifest_for(&path, address, chunks)?;
             }
+            Ok(bytes)
         } else {
             self.send_event(FilesDownloadEvent::ChunksCount(1)).await?;
             self.send_event(FilesDownloadEvent::Downloaded(address))
                 .await?;
             // if an error occurs, we assume it's a SmallFile
             if let Some(path) = downloaded_file_path {
-                fs::write(path, head_chunk.value().clone())?;
+                self.ensure_space_for(&path, head_chunk.value().len())?;
+                fs::write(&path, head_chunk.value().clone())?;
+                let chunks = vec![(*address.xorname(), head_chunk.value().len(), head_holders)];
+                self.write_manifest_for(&path, address, chunks)?;
                 Ok(None)
             } else {
                 Ok(Some(head_chunk.value().clone()))
@@ -302,6 +470,66 @@ impl FilesDownload {
         }
     }
 
+    /// Download the segments of a file chunked with a target chunk size, one after the other.
+    /// If the decrypted_file_path is provided, the segments are appended to the file there,
+    /// else the decrypted bytes are returned.
+    async fn read_segments(
+        &mut self,
+        data_maps: &[DataMap],
+        decrypted_file_path: Option<&Path>,
+    ) -> Result<Option<Bytes>> {
+        let mut file = decrypted_file_path.map(File::create).transpose()?;
+        let mut collected = BytesMut::new();
+        for data_map in data_maps {
+            let bytes = match self.read(data_map.clone(), None, false, false).await? {
+                DownloadReturnType::DecryptedBytes(bytes) => bytes,
+                _ => {
+                    error!(
+                        "IncorrectDownloadOption: we should be getting the decrypted bytes back."
+                    );
+                    return Err(ClientError::IncorrectDownloadOption);
+                }
+            };
+            match file.as_mut() {
+                Some(file) => file.write_all(&bytes)?,
+                None => collected.extend_from_slice(&bytes),
+            }
+        }
+
+        if file.is_some() {
+            Ok(None)
+        } else {
+            Ok(Some(collected.freeze()))
+        }
+    }
+
+    /// Fail early if a file of the given size can't be downloaded to the path.
+    fn ensure_space_for(&self, path: &Path, file_size: usize) -> Result<()> {
+        self.api
+            .disk_space_check
+            .ensure_space(path, DiskSpaceCheck::required_space(file_size as u64))
+    }
+
+    /// Write the manifest of the file downloaded to the path, if enabled.
+    /// The chunks are given in order, with the size of their content and the peers which served them.
+    fn write_manifest_for(
+        &self,
+        path: &Path,
+        address: ChunkAddress,
+        chunks: Vec<(XorName, usize, Vec<PeerId>)>,
+    ) -> Result<()> {
+        if !self.write_manifest {
+            return Ok(());
+        }
+        let manifest = DownloadManifest::new(*address.xorname(), path, chunks)?;
+        manifest.write_for(path)?;
+        info!(
+            "Wrote the download manifest of {address:?} to {:?}",
+            DownloadManifest::path_for(path)
+        );
+        Ok(())
+    }
+
     /// The internal logic to download the provided chunks inside the datamap.
     /// If the decrypted_file_path is provided, we return DownloadReturnType::WrittenToFileSystem
     /// If return_encrypted_chunks is true, we return DownloadReturnType::EncryptedChunks
@@ -366,7 +594,8 @@ impl FilesDownload {
         let mut chunk_download_cache = HashMap::new();
 
         while let Some(result) = stream.next().await {
-            let (chunk_address, index, encrypted_chunk) = result?;
+            let (chunk_address, index, encrypted_chunk, holders) = result?;
+            let _ = self.chunk_holders.insert(*chunk_address.xorname(), holders);
             // notify about the download
             self.send_event(FilesDownloadEvent::Downloaded(chunk_address))
                 .await?;
@@ -444,11 +673,20 @@ impl FilesDownload {
     /// Extracts a file DataMapLevel from a chunk.
     /// If the DataMapLevel is not the first level mapping directly to the user's contents,
     /// the process repeats itself until it obtains the first level DataMapLevel.
-    pub async fn unpack_chunk(&mut self, mut chunk: Chunk) -> Result<DataMap> {
+    ///
+    /// Returns the data maps of the consecutive segments of the file, which is a single one
+    /// unless the file was chunked with a target chunk size.
+    pub async fn unpack_chunk(&mut self, mut chunk: Chunk) -> Result<Vec<DataMap>> {
         loop {
             match rmp_serde::from_slice(chunk.value()).map_err(ChunksError::Deserialisation)? {
                 DataMapLevel::First(data_map) => {
-                    return Ok(data_map);
+                    return Ok(vec![data_map]);
+                }
+                DataMapLevel::Segmented(data_maps) => {
+                    if data_maps.is_empty() {
+                        return Err(ClientError::EmptyDataMap);
+                    }
+                    return Ok(data_maps);
                 }
                 DataMapLevel::Additional(data_map) => {
                     if let DownloadReturnType::DecryptedBytes(serialized_chunk) =
@@ -483,9 +721,9 @@ impl FilesDownload {
         address: XorName,
         index: usize,
         show_holders: bool,
-    ) -> std::result::Result<(ChunkAddress, usize, EncryptedChunk), ChunksError> {
-        let chunk = client
-            .get_chunk(ChunkAddress::new(address), show_holders)
+    ) -> std::result::Result<(ChunkAddress, usize, EncryptedChunk, Vec<PeerId>), ChunksError> {
+        let (chunk, holders) = client
+            .get_chunk_and_holders(ChunkAddress::new(address), show_holders)
             .await
             .map_err(|err| {
                 error!("Chunk missing {address:?} with {err:?}",);
@@ -495,6 +733,6 @@ impl FilesDownload {
             index,
             content: chunk.value,
         };
-        Ok((chunk.address, index, encrypted_chunk))
+        Ok((chunk.address, index, encrypted_chunk, holders))
     }
 }
@@ -0,0 +1,309 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    chunks::{DataMapLevel, Error as ChunksError},
+    deadline::{run_batch, Deadline},
+    error::{Error, Result},
+    Client, BATCH_SIZE,
+};
+use libp2p::PeerId;
+use rand::{thread_rng, Rng};
+use self_encryption::{decrypt_full_set, DataMap, EncryptedChunk};
+use sn_protocol::{
+    storage::{Chunk, ChunkAddress},
+    NetworkAddress,
+};
+use std::time::Instant;
+
+/// Whether a file can be retrieved from the network, see `Client::check_file_health`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileHealth {
+    /// All the chunks of the file are held by the network.
+    Complete,
+    /// Some of the chunks of the file are not held by any of their close nodes.
+    Degraded { missing: usize },
+    /// The data map of the file could not be fetched, or none of its chunks are held.
+    Missing,
+    /// Some of the chunks could not be checked, as the deadline passed or their check failed,
+    /// none of those checked missing.
+    Unchecked { unchecked: usize },
+}
+
+/// The nodes holding a chunk of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHealth {
+    pub address: ChunkAddress,
+    /// The close nodes to the chunk which hold it, none if the chunk is missing.
+    pub holders: Vec<PeerId>,
+}
+
+impl ChunkHealth {
+    /// Whether any node holds the chunk.
+    pub fn is_present(&self) -> bool {
+        !self.holders.is_empty()
+    }
+}
+
+/// The state of each chunk of a file, along with the overall verdict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileHealthReport {
+    pub head_address: ChunkAddress,
+    /// The checked chunks of the file contents, in the order of the data map. Empty if the data
+    /// map could not be fetched.
+    pub chunks: Vec<ChunkHealth>,
+    /// The chunks not checked before the deadline, if any, not known to be present or missing.
+    pub unchecked: Vec<ChunkAddress>,
+    /// The chunks whose check failed, not known to be present or missing either.
+    pub failed: Vec<ChunkAddress>,
+    pub verdict: FileHealth,
+}
+
+impl FileHealthReport {
+    fn new(
+        head_address: ChunkAddress,
+        chunks: Vec<ChunkHealth>,
+        unchecked: Vec<ChunkAddress>,
+        failed: Vec<ChunkAddress>,
+    ) -> Self {
+        let missing = chunks.iter().filter(|chunk| !chunk.is_present()).count();
+        let unknown = unchecked.len() + failed.len();
+        let verdict = if chunks.is_empty() && unknown == 0 {
+            FileHealth::Missing
+        } else if missing == 0 && unknown == 0 {
+            FileHealth::Complete
+        } else if missing == 0 {
+            FileHealth::Unchecked { unchecked: unknown }
+        } else if missing == chunks.len() && unknown == 0 {
+            FileHealth::Missing
+        } else {
+            FileHealth::Degraded { missing }
+        };
+        Self {
+            head_address,
+            chunks,
+            unchecked,
+            failed,
+            verdict,
+        }
+    }
+
+    /// The addresses of the chunks no node holds.
+    pub fn missing_chunks(&self) -> impl Iterator<Item = &ChunkAddress> {
+        self.chunks
+            .iter()
+            .filter(|chunk| !chunk.is_present())
+            .map(|chunk| &chunk.address)
+    }
+}
+
+impl Client {
+    /// Check whether the file at `head_addr` can be retrieved, without downloading it.
+    ///
+    /// The data map of the file is fetched, then the close nodes to each of its chunks are
+    /// asked whether they hold it, `BATCH_SIZE` chunks at a time. The chunks are not fetched,
+    /// so a chunk reported as held is not known to be intact. The chunks which could not be
+    /// checked are reported as `failed`, the others still being checked, and a data map which
+    /// could not be fetched reports the file as `Missing`.
+    pub async fn check_file_health(&self, head_addr: ChunkAddress) -> Result<FileHealthReport> {
+        self.check_file_health_until(head_addr, None).await
+    }
+
+    /// Check whether the file at `head_addr` can be retrieved like `check_file_health`, until
+    /// the deadline.
+    ///
+    /// The chunks not checked by the deadline are reported as `unchecked`. Fails with
+    /// `Error::OperationTimedOut` if the deadline passes before the data map of the file is
+    /// fetched.
+    pub async fn check_file_health_with_deadline(
+        &self,
+        head_addr: ChunkAddress,
+        deadline: Deadline,
+    ) -> Result<FileHealthReport> {
+        self.check_file_health_until(head_addr, Some(deadline))
+            .await
+    }
+
+    async fn check_file_health_until(
+        &self,
+        head_addr: ChunkAddress,
+        deadline: Option<Deadline>,
+    ) -> Result<FileHealthReport> {
+        info!("Checking the health of the file at {head_addr:?}");
+        let start = Instant::now();
+        let fetch_data_maps = async {
+            let head_chunk = self.get_chunk(head_addr, false).await?;
+            self.unpack_data_maps(head_chunk).await
+        };
+        let data_maps = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.instant(), fetch_data_maps)
+                .await
+                .map_err(|_| Error::OperationTimedOut {
+                    addr: NetworkAddress::from_chunk_address(head_addr),
+                    elapsed: start.elapsed(),
+                })?,
+            None => fetch_data_maps.await,
+        };
+        let data_maps = match data_maps {
+            Ok(data_maps) => data_maps,
+            Err(err) => {
+                warn!("Could not fetch the data map of the file at {head_addr:?}: {err}");
+                return Ok(FileHealthReport::new(head_addr, vec![], vec![], vec![]));
+            }
+        };
+
+        let addresses: Vec<_> = data_maps
+            .iter()
+            .flat_map(|data_map| data_map.infos())
+            .map(|info| ChunkAddress::new(info.dst_hash))
+            .collect();
+        let nonce = thread_rng().gen::<u64>();
+        let outcome = run_batch(&addresses, BATCH_SIZE, deadline, |address| async move {
+            let holders = self
+                .network
+                .get_chunk_existence_holders(NetworkAddress::from_chunk_address(address), nonce)
+                .await?;
+            Ok::<_, Error>(holders)
+        })
+        .await;
+        let chunks = outcome
+            .completed
+            .into_iter()
+            .map(|(address, holders)| ChunkHealth { address, holders })
+            .collect();
+        let unchecked = outcome
+            .cancelled
+            .into_iter()
+            .chain(outcome.not_attempted)
+            .collect();
+        let failed = outcome
+            .failed
+            .into_iter()
+            .map(|(address, err)| {
+                warn!("Could not check whether chunk {address:?} is held: {err}");
+                address
+            })
+            .collect();
+        let report = FileHealthReport::new(head_addr, chunks, unchecked, failed);
+        info!(
+            "The file at {head_addr:?} is {:?}, out of {} chunks",
+            report.verdict,
+            report.chunks.len() + report.unchecked.len() + report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// The data maps the head chunk of a file resolves to, fetching the additional levels of
+    /// data maps there may be in between.
+    async fn unpack_data_maps(&self, mut chunk: Chunk) -> Result<Vec<DataMap>> {
+        loop {
+            match rmp_serde::from_slice(chunk.value()).map_err(ChunksError::Deserialisation)? {
+                DataMapLevel::First(data_map) => return Ok(vec![data_map]),
+                DataMapLevel::Segmented(data_maps) => return Ok(data_maps),
+                DataMapLevel::Additional(data_map) => {
+                    let addresses: Vec<_> = data_map
+                        .infos()
+                        .iter()
+                        .map(|info| ChunkAddress::new(info.dst_hash))
+                        .collect();
+                    let encrypted_chunks: Vec<_> = self
+                        .get_chunks(&addresses, BATCH_SIZE)
+                        .await?
+                        .into_iter()
+                        .zip(data_map.infos())
+                        .map(|(chunk, info)| EncryptedChunk {
+                            index: info.index,
+                            content: chunk.value,
+                        })
+                        .collect();
+                    let serialized_chunk = decrypt_full_set(&data_map, &encrypted_chunks)
+                        .map_err(ChunksError::SelfEncryption)?;
+                    chunk = rmp_serde::from_slice(&serialized_chunk)
+                        .map_err(ChunksError::Deserialisation)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xor_name::XorName;
+
+    fn chunk(held: bool) -> ChunkHealth {
+        ChunkHealth {
+            address: ChunkAddress::new(XorName::random(&mut thread_rng())),
+            holders: if held { vec![PeerId::random()] } else { vec![] },
+        }
+    }
+
+    #[test]
+    fn the_verdict_follows_the_missing_chunks() {
+        let head = ChunkAddress::new(XorName::random(&mut thread_rng()));
+
+        let report = FileHealthReport::new(head, vec![chunk(true), chunk(true)], vec![], vec![]);
+        assert_eq!(report.verdict, FileHealth::Complete);
+        assert_eq!(report.missing_chunks().count(), 0);
+
+        let chunks = vec![chunk(true), chunk(false), chunk(true)];
+        let missing = chunks[1].address;
+        let report = FileHealthReport::new(head, chunks, vec![], vec![]);
+        assert_eq!(report.verdict, FileHealth::Degraded { missing: 1 });
+        assert_eq!(report.missing_chunks().collect::<Vec<_>>(), vec![&missing]);
+
+        let report = FileHealthReport::new(head, vec![chunk(false), chunk(false)], vec![], vec![]);
+        assert_eq!(report.verdict, FileHealth::Missing);
+
+        // the data map could not be fetched
+        let report = FileHealthReport::new(head, vec![], vec![], vec![]);
+        assert_eq!(report.verdict, FileHealth::Missing);
+    }
+
+    #[test]
+    fn unchecked_chunks_are_neither_present_nor_missing() {
+        let head = ChunkAddress::new(XorName::random(&mut thread_rng()));
+        let unchecked = || vec![chunk(false).address, chunk(false).address];
+
+        let report = FileHealthReport::new(head, vec![chunk(true)], unchecked(), vec![]);
+        assert_eq!(report.verdict, FileHealth::Unchecked { unchecked: 2 });
+        assert_eq!(report.missing_chunks().count(), 0);
+
+        let report = FileHealthReport::new(head, vec![], unchecked(), vec![]);
+        assert_eq!(report.verdict, FileHealth::Unchecked { unchecked: 2 });
+
+        // the missing chunks found before the deadline degrade the file, the rest may be held
+        let report = FileHealthReport::new(head, vec![chunk(false)], unchecked(), vec![]);
+        assert_eq!(report.verdict, FileHealth::Degraded { missing: 1 });
+        let report =
+            FileHealthReport::new(head, vec![chunk(true), chunk(false)], unchecked(), vec![]);
+        assert_eq!(report.verdict, FileHealth::Degraded { missing: 1 });
+    }
+
+    #[test]
+    fn chunks_whose_check_failed_are_neither_present_nor_missing() {
+        let head = ChunkAddress::new(XorName::random(&mut thread_rng()));
+        let failed = vec![chunk(false).address];
+
+        let report = FileHealthReport::new(head, vec![chunk(true)], vec![], failed.clone());
+        assert_eq!(report.verdict, FileHealth::Unchecked { unchecked: 1 });
+        assert_eq!(report.missing_chunks().count(), 0);
+        assert_eq!(report.failed, failed);
+
+        // a file none of whose chunks could be checked isn't known to be missing
+        let report = FileHealthReport::new(head, vec![], vec![], failed.clone());
+        assert_eq!(report.verdict, FileHealth::Unchecked { unchecked: 1 });
+
+        let unchecked = vec![chunk(false).address];
+        let report = FileHealthReport::new(head, vec![], unchecked, failed.clone());
+        assert_eq!(report.verdict, FileHealth::Unchecked { unchecked: 2 });
+
+        let report = FileHealthReport::new(head, vec![chunk(false)], vec![], failed);
+        assert_eq!(report.verdict, FileHealth::Degraded { missing: 1 });
+    }
+}
@@ -0,0 +1,384 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::error::{Error, Result};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use tiny_keccak::{Hasher, Sha3};
+use xor_name::XorName;
+
+/// The suffix appended to a downloaded file's name to get its manifest's.
+pub const MANIFEST_SUFFIX: &str = ".safe-manifest.json";
+
+const MANIFEST_VERSION: u32 = 1;
+
+/// An auditable record of a downloaded file, written next to it as `<file>.safe-manifest.json`.
+///
+/// It records where each chunk of the file came from, and the hashes to later re-check the
+/// file against, without network access.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadManifest {
+    pub version: u32,
+    /// The hex encoded head address of the file.
+    pub head_address: String,
+    pub file_size: usize,
+    /// The hex encoded sha3-256 of the whole file.
+    pub file_hash: String,
+    pub chunks: Vec<ManifestChunk>,
+}
+
+/// A chunk of a downloaded file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestChunk {
+    pub index: usize,
+    /// The hex encoded network address of the chunk.
+    pub xorname: String,
+    /// Where the chunk's content lies in the file.
+    pub offset: usize,
+    pub size: usize,
+    /// The hex encoded sha3-256 of the chunk's content.
+    pub content_hash: String,
+    /// The peers which served the chunk.
+    pub holders: Vec<String>,
+}
+
+impl ManifestChunk {
+    /// The range of bytes of the file this chunk holds.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.size
+    }
+}
+
+/// The outcome of checking a file against its manifest.
+#[derive(Clone, Debug)]
+pub struct LocalVerification {
+    pub expected_size: usize,
+    pub actual_size: usize,
+    pub file_hash_matches: bool,
+    /// The chunks whose content doesn't match the manifest.
+    pub corrupted_chunks: Vec<ManifestChunk>,
+}
+
+impl LocalVerification {
+    /// Whether the file matches its manifest.
+    pub fn is_verified(&self) -> bool {
+        self.expected_size == self.actual_size
+            && self.file_hash_matches
+            && self.corrupted_chunks.is_empty()
+    }
+}
+
+impl DownloadManifest {
+    /// Create the manifest of a file, made of the given chunks in order: their network address,
+    /// the size of their content and the peers which served them.
+    ///
+    /// The file is read one chunk at a time.
+    pub fn new(
+        head_address: XorName,
+        file: &Path,
+        chunks: Vec<(XorName, usize, Vec<PeerId>)>,
+    ) -> Result<Self> {
+        let hashes = FileHashes::of(file, chunks.iter().map(|(_, size, _)| *size))?;
+        let mut offset = 0;
+        let chunks = chunks
+            .into_iter()
+            .zip(hashes.chunks)
+            .enumerate()
+            .map(|(index, ((xorname, size, holders), (_, content_hash)))| {
+                let chunk = ManifestChunk {
+                    index,
+                    xorname: hex::encode(xorname),
+                    offset,
+                    size,
+                    content_hash,
+                    holders: holders.iter().map(|peer| peer.to_string()).collect(),
+                };
+                offset += size;
+                chunk
+            })
+            .collect();
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            head_address: hex::encode(head_address),
+            file_size: hashes.file_size,
+            file_hash: hashes.file_hash,
+            chunks,
+        })
+    }
+
+    /// The path of the manifest of the given file.
+    pub fn path_for(file: &Path) -> PathBuf {
+        let mut path = file.as_os_str().to_owned();
+        path.push(MANIFEST_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Write the manifest next to the given file.
+    pub fn write_for(&self, file: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(Error::ManifestSerialisation)?;
+        std::fs::write(Self::path_for(file), bytes)?;
+        Ok(())
+    }
+
+    /// Read the manifest written next to the given file.
+    pub fn read_for(file: &Path) -> Result<Self> {
+        let bytes = std::fs::read(Self::path_for(file))?;
+        serde_json::from_slice(&bytes).map_err(Error::ManifestSerialisation)
+    }
+
+    /// Check the given file against this manifest, reading it one chunk at a time.
+    pub fn verify(&self, file: &Path) -> Result<LocalVerification> {
+        let hashes = FileHashes::of(file, self.chunks.iter().map(|chunk| chunk.size))?;
+        let corrupted_chunks = self
+            .chunks
+            .iter()
+            .zip(hashes.chunks)
+            .filter(|(chunk, (read, content_hash))| {
+                *read != chunk.size || *content_hash != chunk.content_hash
+            })
+            .map(|(chunk, _)| chunk.clone())
+            .collect();
+
+        Ok(LocalVerification {
+            expected_size: self.file_size,
+            actual_size: hashes.file_size,
+            file_hash_matches: hashes.file_hash == self.file_hash,
+            corrupted_chunks,
+        })
+    }
+}
+
+/// The chunks of an upload, along with where their content lies on disk, to later check which of
+/// them made it to the network should the upload be interrupted. See `FilesApi::reconcile_upload`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadManifest {
+    pub version: u32,
+    pub chunks: Vec<UploadManifestChunk>,
+}
+
+/// A chunk of an upload.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadManifestChunk {
+    /// The hex encoded network address of the chunk.
+    pub xorname: String,
+    /// The file holding the content of the chunk.
+    pub path: PathBuf,
+}
+
+impl UploadManifest {
+    /// Create the manifest of an upload of the given chunks.
+    pub fn new(chunks: &[(XorName, PathBuf)]) -> Self {
+        let chunks = chunks
+            .iter()
+            .map(|(name, path)| UploadManifestChunk {
+                xorname: hex::encode(name),
+                path: path.clone(),
+            })
+            .collect();
+        Self {
+            version: MANIFEST_VERSION,
+            chunks,
+        }
+    }
+
+    /// The chunks of the upload, as uploaded by `FilesUpload::upload_chunks`.
+    pub fn chunks(&self) -> Result<Vec<(XorName, PathBuf)>> {
+        self.chunks
+            .iter()
+            .map(|chunk| {
+                let bytes = hex::decode(&chunk.xorname)
+                    .map_err(|_| Error::InvalidManifestChunk(chunk.xorname.clone()))?;
+                let name: [u8; xor_name::XOR_NAME_LEN] = bytes
+                    .try_into()
+                    .map_err(|_| Error::InvalidManifestChunk(chunk.xorname.clone()))?;
+                Ok((XorName(name), chunk.path.clone()))
+            })
+            .collect()
+    }
+
+    /// Write the manifest to the given path.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(Error::ManifestSerialisation)?;
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Read the manifest at the given path.
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        serde_json::from_slice(&bytes).map_err(Error::ManifestSerialisation)
+    }
+}
+
+/// Check a downloaded file against the manifest written next to it, without network access.
+pub fn verify_local_file(file: &Path) -> Result<LocalVerification> {
+    let manifest = DownloadManifest::read_for(file)?;
+    manifest.verify(file)
+}
+
+/// The hashes of a file, and of the consecutive chunks it is made of.
+struct FileHashes {
+    file_size: usize,
+    file_hash: String,
+    /// The number of bytes read for each chunk, fewer than its size if the file is shorter,
+    /// and the hash of those bytes.
+    chunks: Vec<(usize, String)>,
+}
+
+impl FileHashes {
+    /// Hash the file through a buffered reader, holding a single chunk in memory at a time.
+    fn of(file: &Path, chunk_sizes: impl IntoIterator<Item = usize>) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(file)?);
+        let mut file_hasher = Sha3::v256();
+        let mut file_size = 0;
+        let mut chunks = Vec::new();
+        let mut buffer = Vec::new();
+        for size in chunk_sizes {
+            buffer.clear();
+            let read = reader.by_ref().take(size as u64).read_to_end(&mut buffer)?;
+            file_hasher.update(&buffer);
+            file_size += read;
+            chunks.push((read, content_hash(&buffer)));
+        }
+        // whatever the file holds past its chunks
+        loop {
+            buffer.clear();
+            let read = reader.by_ref().take(8192).read_to_end(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            file_hasher.update(&buffer);
+            file_size += read;
+        }
+
+        let mut file_hash = [0; 32];
+        file_hasher.finalize(&mut file_hash);
+        Ok(Self {
+            file_size,
+            file_hash: hex::encode(file_hash),
+            chunks,
+        })
+    }
+}
+
+fn content_hash(content: &[u8]) -> String {
+    hex::encode(XorName::from_content(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, RngCore};
+
+    const CHUNK_SIZES: [usize; 3] = [1024, 2048, 512];
+
+    fn random_file(file: &Path) -> eyre::Result<(Vec<u8>, DownloadManifest)> {
+        let mut content = vec![0; CHUNK_SIZES.iter().sum()];
+        thread_rng().fill_bytes(&mut content);
+        std::fs::write(file, &content)?;
+        let chunks = CHUNK_SIZES
+            .iter()
+            .map(|size| {
+                (
+                    XorName::random(&mut thread_rng()),
+                    *size,
+                    vec![PeerId::random()],
+                )
+            })
+            .collect();
+        let manifest = DownloadManifest::new(XorName::random(&mut thread_rng()), file, chunks)?;
+        Ok((content, manifest))
+    }
+
+    #[test]
+    fn untouched_file_is_verified() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("file");
+        let (content, manifest) = random_file(&file)?;
+        manifest.write_for(&file)?;
+
+        assert_eq!(manifest.file_size, content.len());
+        assert_eq!(manifest.file_hash, content_hash(&content));
+        assert_eq!(DownloadManifest::read_for(&file)?, manifest);
+        assert!(verify_local_file(&file)?.is_verified());
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_file_fails_on_the_tampered_chunk() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("file");
+        let (mut content, manifest) = random_file(&file)?;
+        // flip a byte within the second chunk
+        content[CHUNK_SIZES[0] + 10] ^= 0xff;
+        std::fs::write(&file, &content)?;
+        manifest.write_for(&file)?;
+
+        let verification = verify_local_file(&file)?;
+        assert!(!verification.is_verified());
+        assert!(!verification.file_hash_matches);
+        let corrupted_ranges: Vec<_> = verification
+            .corrupted_chunks
+            .iter()
+            .map(|chunk| chunk.range())
+            .collect();
+        assert_eq!(
+            corrupted_ranges,
+            vec![CHUNK_SIZES[0]..CHUNK_SIZES[0] + CHUNK_SIZES[1]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn upload_manifest_lists_the_chunks_back() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let chunks: Vec<_> = (0..3)
+            .map(|index| {
+                (
+                    XorName::random(&mut thread_rng()),
+                    dir.path().join(format!("chunk_{index}")),
+                )
+            })
+            .collect();
+        let path = dir.path().join("upload.json");
+        UploadManifest::new(&chunks).write(&path)?;
+
+        assert_eq!(UploadManifest::read(&path)?.chunks()?, chunks);
+
+        let mut manifest = UploadManifest::new(&chunks);
+        manifest.chunks[1].xorname = "not hex".to_string();
+        assert!(matches!(
+            manifest.chunks(),
+            Err(Error::InvalidManifestChunk(xorname)) if xorname == "not hex"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_file_fails_on_the_missing_chunk() -> eyre::Result<()> {
+        let (content, manifest) = random_file();
+        let truncated = &content[..content.len() - 1];
+
+        let verification = manifest.verify(truncated);
+        assert_eq!(verification.actual_size, verification.expected_size - 1);
+        let corrupted_indexes: Vec<_> = verification
+            .corrupted_chunks
+            .iter()
+            .map(|chunk| chunk.index)
+            .collect();
+        assert_eq!(corrupted_indexes, vec![2]);
+        Ok(())
+    }
+}
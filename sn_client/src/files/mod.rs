@@ -6,17 +6,21 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+pub(crate) mod disk_space;
 pub(crate) mod download;
+pub(crate) mod health;
+pub(crate) mod manifest;
+pub(crate) mod reconcile;
 pub(crate) mod upload;
 
 use crate::{
     chunks::{to_chunk, Error as ChunksError, SmallFile},
     error::Result,
-    Client, WalletClient,
+    Client, DiskSpaceCheck, PaymentStore, QuotePolicy, WalletClient,
 };
 use bytes::Bytes;
 use libp2p::PeerId;
-use self_encryption::{self, MIN_ENCRYPTABLE_BYTES};
+use self_encryption::{self, MAX_CHUNK_SIZE, MIN_ENCRYPTABLE_BYTES};
 use sn_protocol::{
     storage::{Chunk, ChunkAddress},
     NetworkAddress,
@@ -26,22 +30,41 @@ use std::{
     fs::{self, create_dir_all, File},
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tempfile::tempdir;
 use tracing::trace;
+use upload::PayeeBreakdown;
 use xor_name::XorName;
 
+/// The storage cost, royalties and new balance of a payment for chunks, then the payee of each
+/// chunk and the skipped chunks.
+pub(crate) type ChunksPayment = (
+    (NanoTokens, NanoTokens, NanoTokens),
+    (Vec<(XorName, PeerId)>, Vec<XorName>),
+);
+
 /// `BATCH_SIZE` determines the number of chunks that are processed in parallel during the payment and upload process.
+///
+/// The chunks of a batch are then fetched or stored at most `Client::set_max_concurrent_ops` at
+/// a time, which defaults to the same number.
 pub const BATCH_SIZE: usize = 16;
 
 /// The maximum number of retries to perform on a failed chunk.
 pub const MAX_UPLOAD_RETRIES: usize = 3;
 
+/// The smallest target chunk size that can be set, to keep the number of chunks of a file reasonable.
+pub const MIN_TARGET_CHUNK_SIZE: usize = 4 * 1024;
+
 /// File APIs.
 #[derive(Clone)]
 pub struct FilesApi {
     pub(crate) client: Client,
     pub(crate) wallet_dir: PathBuf,
+    pub(crate) payment_store: Option<Arc<dyn PaymentStore>>,
+    pub(crate) target_chunk_size: Option<usize>,
+    pub(crate) quote_policy: QuotePolicy,
+    pub(crate) disk_space_check: DiskSpaceCheck,
 }
 
 /// This is the (file xorname, datamap_data, filesize, and chunks)
@@ -51,7 +74,71 @@ type ChunkFileResult = Result<(ChunkAddress, Option<Bytes>, u64, Vec<(XorName, P
 impl FilesApi {
     /// Create file apis instance.
     pub fn new(client: Client, wallet_dir: PathBuf) -> Self {
-        Self { client, wallet_dir }
+        Self {
+            client,
+            wallet_dir,
+            payment_store: None,
+            target_chunk_size: None,
+            quote_policy: QuotePolicy::default(),
+            disk_space_check: DiskSpaceCheck::default(),
+        }
+    }
+
+    /// The maximum size of a chunk, as accepted by the nodes for a chunk record.
+    pub fn max_chunk_size() -> usize {
+        MAX_CHUNK_SIZE
+    }
+
+    /// Chunk the files with `chunk_file_with_target_size` into chunks of the given size, rather than
+    /// chunks of up to `max_chunk_size()`. The size can also be given to
+    /// `chunk_file_with_target_size_and_disk_space_check` once validated here.
+    ///
+    /// The size must be between `MIN_TARGET_CHUNK_SIZE` and `max_chunk_size()`.
+    pub fn set_target_chunk_size(&mut self, bytes: usize) -> Result<()> {
+        if bytes < MIN_TARGET_CHUNK_SIZE {
+            return Err(ChunksError::TargetChunkSizeTooSmall {
+                size: bytes,
+                minimum: MIN_TARGET_CHUNK_SIZE,
+            }
+            .into());
+        }
+        if bytes > MAX_CHUNK_SIZE {
+            return Err(ChunksError::TargetChunkSizeTooLarge {
+                size: bytes,
+                maximum: MAX_CHUNK_SIZE,
+            }
+            .into());
+        }
+        self.target_chunk_size = Some(bytes);
+        Ok(())
+    }
+
+    /// The target chunk size, if one was set.
+    pub fn target_chunk_size(&self) -> Option<usize> {
+        self.target_chunk_size
+    }
+
+    /// Share payments with other wallets through the given store.
+    /// Chunks already paid for in the store won't be paid for again.
+    pub fn set_payment_store(&mut self, payment_store: Arc<dyn PaymentStore>) {
+        self.payment_store = Some(payment_store);
+    }
+
+    /// Pick the payees of the chunks as set by the given policy, see
+    /// `WalletClient::set_quote_policy`.
+    pub fn set_quote_policy(&mut self, quote_policy: QuotePolicy) {
+        self.quote_policy = quote_policy;
+    }
+
+    /// Check the disk space before chunking and downloading files as set by the given check,
+    /// e.g. `DiskSpaceCheck::skipped()` on filesystems where the space required is overestimated.
+    pub fn set_disk_space_check(&mut self, disk_space_check: DiskSpaceCheck) {
+        self.disk_space_check = disk_space_check;
+    }
+
+    /// The check of the disk space done before chunking and downloading files.
+    pub fn disk_space_check(&self) -> &DiskSpaceCheck {
+        &self.disk_space_check
     }
 
     /// Return the client instance
@@ -64,19 +151,82 @@ impl FilesApi {
         let path = self.wallet_dir.as_path();
         let wallet = LocalWallet::load_from(path)?;
 
-        Ok(WalletClient::new(self.client.clone(), wallet))
+        let mut wallet_client = WalletClient::new(self.client.clone(), wallet);
+        if let Some(payment_store) = &self.payment_store {
+            wallet_client.set_payment_store(payment_store.clone());
+        }
+        wallet_client.set_quote_policy(self.quote_policy);
+        Ok(wallet_client)
     }
 
     /// Tries to chunk the file, returning `(head_address, data_map_chunk, file_size, chunk_names)`
     /// and writes encrypted chunks to disk.
+    ///
+    /// Fails early with `Error::InsufficientDiskSpace` if the chunks can't fit in the `chunk_dir`.
     pub fn chunk_file(
         file_path: &Path,
         chunk_dir: &Path,
         include_data_map_in_chunks: bool,
+    ) -> ChunkFileResult {
+        Self::chunk_file_with_disk_space_check(
+            file_path,
+            chunk_dir,
+            include_data_map_in_chunks,
+            &DiskSpaceCheck::default(),
+        )
+    }
+
+    /// Same as `chunk_file`, with the disk space checked as set by the given check.
+    pub fn chunk_file_with_disk_space_check(
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+        disk_space_check: &DiskSpaceCheck,
+    ) -> ChunkFileResult {
+        Self::chunk_file_with_target_size_and_disk_space_check(
+            file_path,
+            chunk_dir,
+            include_data_map_in_chunks,
+            None,
+            disk_space_check,
+        )
+    }
+
+    /// Same as `chunk_file`, but splits the file into chunks of the target chunk size if one was set.
+    ///
+    /// The file is then self-encrypted in segments, which are all recorded in its data map,
+    /// so it is downloaded the same way as a file chunked with `chunk_file`.
+    pub fn chunk_file_with_target_size(
+        &self,
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+    ) -> ChunkFileResult {
+        Self::chunk_file_with_target_size_and_disk_space_check(
+            file_path,
+            chunk_dir,
+            include_data_map_in_chunks,
+            self.target_chunk_size,
+            &self.disk_space_check,
+        )
+    }
+
+    /// Same as `chunk_file`, split into chunks of the given target chunk size if any, e.g. as
+    /// set with `set_target_chunk_size`, and with the disk space checked as set by the given check.
+    ///
+    /// Unlike `chunk_file_with_target_size`, it doesn't need a `FilesApi`, so files can be
+    /// chunked in parallel.
+    pub fn chunk_file_with_target_size_and_disk_space_check(
+        file_path: &Path,
+        chunk_dir: &Path,
+        include_data_map_in_chunks: bool,
+        target_chunk_size: Option<usize>,
+        disk_space_check: &DiskSpaceCheck,
     ) -> ChunkFileResult {
         let mut file = File::open(file_path)?;
         let metadata = file.metadata()?;
         let file_size = metadata.len();
+        disk_space_check.ensure_space(chunk_dir, DiskSpaceCheck::required_space(file_size))?;
 
         let (head_address, data_map_chunk, mut chunks_paths) =
             if file_size < MIN_ENCRYPTABLE_BYTES as u64 {
@@ -96,7 +246,10 @@ impl FilesApi {
                     vec![(*chunk.name(), small_chunk_file_path)],
                 )
             } else {
-                let (data_map_chunk, chunks) = encrypt_large(file_path, chunk_dir)?;
+                let (data_map_chunk, chunks) = match target_chunk_size {
+                    Some(chunk_size) => encrypt_large_segmented(file_path, chunk_dir, chunk_size)?,
+                    None => encrypt_large(file_path, chunk_dir)?,
+                };
                 (*data_map_chunk.name(), Some(data_map_chunk), chunks)
             };
 
@@ -166,21 +319,50 @@ impl FilesApi {
         (NanoTokens, NanoTokens, NanoTokens),
         (Vec<(XorName, PeerId)>, Vec<XorName>),
     )> {
+        let (paid, _payments) = self.pay_for_chunks_itemised(chunks).await?;
+        Ok(paid)
+    }
+
+    /// Like `pay_for_chunks`, also returning what was paid to each payee, one entry per chunk
+    /// paid for by this call.
+    pub(crate) async fn pay_for_chunks_itemised(
+        &self,
+        chunks: Vec<XorName>,
+    ) -> Result<(ChunksPayment, Vec<PayeeBreakdown>)> {
         let mut wallet_client = self.wallet()?;
         info!("Paying for and uploading {:?} chunks", chunks.len());
 
-        let ((storage_cost, royalties_fees), (payee_map, skipped_chunks)) =
+        let (((storage_cost, royalties_fees), (payee_map, skipped_chunks)), paid) =
             wallet_client
-                .pay_for_storage(chunks.iter().map(|name| {
+                .pay_for_storage_listing_paid(chunks.iter().map(|name| {
                     sn_protocol::NetworkAddress::ChunkAddress(ChunkAddress::new(*name))
                 }))
                 .await?;
 
         wallet_client.store_local_wallet()?;
         let new_balance = wallet_client.balance();
+
+        let wallet = wallet_client.mut_wallet();
+        let payments = paid
+            .iter()
+            .filter_map(|name| {
+                let (_, payee) = payee_map.iter().find(|(paid_name, _)| paid_name == name)?;
+                let payment = wallet.get_cached_payment_for_xorname(name)?;
+                Some(PayeeBreakdown {
+                    peer_id: *payee,
+                    chunks: 1,
+                    storage_cost: payment.transfer.1,
+                    royalties: payment.royalties.1,
+                })
+            })
+            .collect();
+
         Ok((
-            (storage_cost, royalties_fees, new_balance),
-            (payee_map, skipped_chunks),
+            (
+                (storage_cost, royalties_fees, new_balance),
+                (payee_map, skipped_chunks),
+            ),
+            payments,
         ))
     }
 
@@ -199,7 +381,7 @@ impl FilesApi {
         create_dir_all(chunk_path.clone())?;
 
         let (head_address, _data_map, _file_size, chunks_paths) =
-            Self::chunk_file(&file_path, &chunk_path, true)?;
+            self.chunk_file_with_target_size(&file_path, &chunk_path, true)?;
 
         for (_chunk_name, chunk_path) in chunks_paths {
             let chunk = Chunk::new(Bytes::from(fs::read(chunk_path)?));
@@ -220,6 +402,17 @@ fn encrypt_large(file_path: &Path, output_dir: &Path) -> Result<(Chunk, Vec<(Xor
     Ok(crate::chunks::encrypt_large(file_path, output_dir)?)
 }
 
+/// Same as `encrypt_large`, with the file split into chunks of the given size.
+fn encrypt_large_segmented(
+    file_path: &Path,
+    output_dir: &Path,
+    chunk_size: usize,
+) -> Result<(Chunk, Vec<(XorName, PathBuf)>)> {
+    Ok(crate::chunks::encrypt_large_segmented(
+        file_path, output_dir, chunk_size,
+    )?)
+}
+
 /// Packages a [`SmallFile`] and returns the resulting address and the chunk.
 /// Does not store anything to the network.
 fn package_small(file: SmallFile) -> Result<Chunk> {
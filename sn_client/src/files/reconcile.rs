@@ -0,0 +1,255 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{manifest::UploadManifest, upload::FilesUpload, FilesApi, BATCH_SIZE};
+use crate::error::Result;
+use bytes::Bytes;
+use futures::StreamExt;
+use libp2p::PeerId;
+use rand::{thread_rng, Rng};
+use sn_protocol::{
+    storage::{Chunk, ChunkAddress},
+    NetworkAddress,
+};
+use sn_transfers::{LocalWallet, PaymentDetails};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// What is left to do for the chunks of an upload manifest, for the manifest, the wallet and the
+/// network to agree. See `FilesApi::reconcile_upload`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReconcilePlan {
+    /// The chunks held by the network, nothing is left to do for them.
+    pub complete: Vec<(XorName, PathBuf)>,
+    /// The chunks not held by the network, with a payment in the wallet whose payee is still
+    /// in their close group: they are to be uploaded again without paying.
+    pub to_push: Vec<(XorName, PathBuf, PeerId)>,
+    /// The chunks not held by the network, with no payment in the wallet or one to a payee which
+    /// left their close group: they are to be paid for and uploaded again.
+    pub to_repay: Vec<(XorName, PathBuf)>,
+    /// The chunks neither held by the network nor on disk anymore, which can't be uploaded again
+    /// without chunking their file again.
+    pub lost: Vec<(XorName, PathBuf)>,
+    /// The contents the wallet holds a payment for which are not in the manifest, possibly paid
+    /// for twice, or by another upload. They are left untouched.
+    pub unmatched_payments: Vec<XorName>,
+}
+
+impl ReconcilePlan {
+    /// Whether all the chunks of the manifest are held by the network, or lost.
+    pub fn is_converged(&self) -> bool {
+        self.to_push.is_empty() && self.to_repay.is_empty()
+    }
+}
+
+/// Where a chunk of the manifest stands.
+enum ChunkState {
+    Complete,
+    Push(PeerId),
+    Repay,
+    Lost,
+}
+
+impl FilesApi {
+    /// Cross-check the chunks of an upload manifest against the payments held by the wallet and
+    /// the chunks held by the network, e.g. after the upload was interrupted, and plan what is
+    /// left to do for the upload to complete.
+    ///
+    /// The close nodes to each chunk are asked whether they hold it, `BATCH_SIZE` chunks at a
+    /// time, then the payee of each missing chunk paid for is looked up in its close group.
+    pub async fn reconcile_upload(&self, manifest: &UploadManifest) -> Result<ReconcilePlan> {
+        let chunks = manifest.chunks()?;
+        let wallet = LocalWallet::load_from(&self.wallet_dir)?;
+        info!("Reconciling an upload of {} chunks", chunks.len());
+
+        let nonce = thread_rng().gen::<u64>();
+        let states = futures::stream::iter(chunks.iter())
+            .map(|(name, path)| {
+                let payment = wallet.get_cached_payment_for_xorname(name);
+                self.chunk_state(*name, path, payment, nonce)
+            })
+            .buffered(BATCH_SIZE)
+            .collect::<Vec<Result<_>>>()
+            .await;
+
+        let mut plan = ReconcilePlan::default();
+        for ((name, path), state) in chunks.iter().cloned().zip(states) {
+            match state? {
+                ChunkState::Complete => plan.complete.push((name, path)),
+                ChunkState::Push(payee) => plan.to_push.push((name, path, payee)),
+                ChunkState::Repay => plan.to_repay.push((name, path)),
+                ChunkState::Lost => plan.lost.push((name, path)),
+            }
+        }
+
+        let names: BTreeSet<_> = chunks.iter().map(|(name, _)| *name).collect();
+        plan.unmatched_payments = wallet
+            .cached_payments()
+            .map(|(name, _)| *name)
+            .filter(|name| !names.contains(name))
+            .collect();
+
+        info!(
+            "Upload reconciled: {} complete, {} to push, {} to pay for again, {} lost, {} unmatched payments",
+            plan.complete.len(),
+            plan.to_push.len(),
+            plan.to_repay.len(),
+            plan.lost.len(),
+            plan.unmatched_payments.len()
+        );
+        Ok(plan)
+    }
+
+    /// Carry out the plan of `reconcile_upload`: the chunks to push are uploaded to their payee,
+    /// then the chunks to pay for again are paid for and uploaded.
+    ///
+    /// Returns the plan of what is left to do afterwards, reconciled anew.
+    pub async fn apply_reconcile_plan(
+        &self,
+        manifest: &UploadManifest,
+        plan: &ReconcilePlan,
+    ) -> Result<ReconcilePlan> {
+        let pushed = futures::stream::iter(plan.to_push.iter())
+            .map(|(name, path, payee)| async move {
+                let chunk = Chunk::new(Bytes::from(std::fs::read(path)?));
+                let result = self
+                    .get_local_payment_and_upload_chunk(chunk, *payee, true)
+                    .await;
+                if let Err(err) = &result {
+                    warn!("Failed to push {name:?} to its payee {payee:?}: {err}");
+                }
+                result
+            })
+            .buffer_unordered(BATCH_SIZE)
+            .collect::<Vec<_>>()
+            .await;
+        info!(
+            "Pushed {} of the {} chunks already paid for",
+            pushed.iter().filter(|result| result.is_ok()).count(),
+            plan.to_push.len()
+        );
+
+        if !plan.to_repay.is_empty() {
+            FilesUpload::new(self.clone())
+                .upload_chunks(plan.to_repay.clone())
+                .await?;
+        }
+
+        self.reconcile_upload(manifest).await
+    }
+
+    /// Upload again chunks already paid for to their payee, e.g. those found missing by
+    /// `WalletClient::audit_payments` whose quote is still valid, `BATCH_SIZE` chunks at a time.
+    ///
+    /// The chunks with no payment in the wallet, or whose payee left their close group, can't
+    /// be uploaded without paying again and are skipped. Returns the chunks uploaded.
+    pub async fn repair_chunks(
+        &self,
+        chunks: &[(XorName, PathBuf)],
+        verify_store: bool,
+    ) -> Result<Vec<XorName>> {
+        let wallet = LocalWallet::load_from(&self.wallet_dir)?;
+        let repaired = futures::stream::iter(chunks.iter())
+            .map(|(name, path)| {
+                let payment = wallet.get_cached_payment_for_xorname(name);
+                async move {
+                    match self.repair_chunk(*name, path, payment, verify_store).await {
+                        Ok(true) => Some(*name),
+                        Ok(false) => None,
+                        Err(err) => {
+                            warn!("Failed to upload {name:?} again: {err}");
+                            None
+                        }
+                    }
+                }
+            })
+            .buffer_unordered(BATCH_SIZE)
+            .filter_map(futures::future::ready)
+            .collect::<Vec<_>>()
+            .await;
+        info!(
+            "Uploaded again {} of the {} chunks to repair",
+            repaired.len(),
+            chunks.len()
+        );
+        Ok(repaired)
+    }
+
+    /// Whether the chunk could be uploaded to its payee.
+    async fn repair_chunk(
+        &self,
+        name: XorName,
+        path: &Path,
+        payment: Option<&PaymentDetails>,
+        verify_store: bool,
+    ) -> Result<bool> {
+        let Some(payment) = payment else {
+            warn!("No payment for {name:?} in the wallet, it is to be paid for again");
+            return Ok(false);
+        };
+        let address = NetworkAddress::from_chunk_address(ChunkAddress::new(name));
+        let Some(payee) = self.payee_of(address, payment).await? else {
+            warn!("The payee of {name:?} left its close group, it is to be paid for again");
+            return Ok(false);
+        };
+        let chunk = Chunk::new(Bytes::from(std::fs::read(path)?));
+        self.get_local_payment_and_upload_chunk(chunk, payee, verify_store)
+            .await?;
+        Ok(true)
+    }
+
+    async fn chunk_state(
+        &self,
+        name: XorName,
+        path: &Path,
+        payment: Option<&PaymentDetails>,
+        nonce: u64,
+    ) -> Result<ChunkState> {
+        let address = NetworkAddress::from_chunk_address(ChunkAddress::new(name));
+        let holders = self
+            .client
+            .network
+            .get_chunk_existence_holders(address.clone(), nonce)
+            .await?;
+        if !holders.is_empty() {
+            return Ok(ChunkState::Complete);
+        }
+        if !path.exists() {
+            warn!("Chunk {name:?} is neither held by the network, nor at {path:?}");
+            return Ok(ChunkState::Lost);
+        }
+        let Some(payment) = payment else {
+            return Ok(ChunkState::Repay);
+        };
+
+        match self.payee_of(address, payment).await? {
+            Some(payee) => Ok(ChunkState::Push(payee)),
+            None => {
+                warn!("The payee of {name:?} left its close group, it is to be paid for again");
+                Ok(ChunkState::Repay)
+            }
+        }
+    }
+
+    /// The node a content was paid to, if it is still in its close group.
+    async fn payee_of(
+        &self,
+        address: NetworkAddress,
+        payment: &PaymentDetails,
+    ) -> Result<Option<PeerId>> {
+        // the payee is only known by its key, find it among the close group
+        let quotes = self.client.network.get_store_cost_quotes(address).await?;
+        Ok(quotes
+            .into_iter()
+            .find(|(_, main_pubkey, _)| *main_pubkey == payment.recipient)
+            .map(|(payee, ..)| payee))
+    }
+}
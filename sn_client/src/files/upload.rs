@@ -8,14 +8,17 @@
 
 use crate::{
     error::{Error as ClientError, Result},
-    FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES,
+    ClientEvent, FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES,
 };
 use bytes::Bytes;
 use futures::{stream::FuturesUnordered, StreamExt};
 use libp2p::PeerId;
 use sn_protocol::storage::{Chunk, ChunkAddress};
 use sn_transfers::NanoTokens;
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
 use tokio::{
     sync::mpsc::{self},
     task::JoinHandle,
@@ -44,6 +47,19 @@ pub enum FileUploadEvent {
     Error,
 }
 
+/// What was paid to one of the nodes storing the uploaded chunks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayeeBreakdown {
+    /// The node paid.
+    pub peer_id: PeerId,
+    /// The number of chunks it was paid to store.
+    pub chunks: usize,
+    /// The total paid to it.
+    pub storage_cost: NanoTokens,
+    /// The network royalties paid along with the payments to it.
+    pub royalties: NanoTokens,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct ChunkInfo {
     name: XorName,
@@ -59,6 +75,7 @@ pub struct FilesUpload {
     verify_store: bool,
     show_holders: bool,
     max_retries: usize,
+    skip_existing: bool,
     // API
     api: FilesApi,
     // Uploads
@@ -68,6 +85,8 @@ pub struct FilesUpload {
     upload_storage_cost: NanoTokens,
     upload_royalty_fees: NanoTokens,
     upload_final_balance: NanoTokens,
+    upload_payees: BTreeMap<PeerId, PayeeBreakdown>,
+    already_stored_chunks: HashSet<XorName>,
     // Events
     event_sender: Option<mpsc::Sender<FileUploadEvent>>,
     logged_event_sender_absence: bool,
@@ -82,12 +101,15 @@ impl FilesUpload {
             verify_store: true,
             show_holders: false,
             max_retries: MAX_UPLOAD_RETRIES,
+            skip_existing: true,
             api: files_api,
             failed_chunks: Default::default(),
             uploading_chunks: Default::default(),
             upload_storage_cost: NanoTokens::zero(),
             upload_royalty_fees: NanoTokens::zero(),
             upload_final_balance: NanoTokens::zero(),
+            upload_payees: Default::default(),
+            already_stored_chunks: Default::default(),
             event_sender: None,
             logged_event_sender_absence: false,
         }
@@ -97,6 +119,9 @@ impl FilesUpload {
     /// payment and upload process.
     ///
     /// By default, this option is set to the constant `BATCH_SIZE: usize = 64`.
+    ///
+    /// The chunks of a batch are stored at most `Client::set_max_concurrent_ops` at a time, the
+    /// others waiting for a slot, so a batch size above that limit only queues more of them.
     pub fn set_batch_size(mut self, batch_size: usize) -> Self {
         self.batch_size = batch_size;
         self
@@ -126,6 +151,18 @@ impl FilesUpload {
         self
     }
 
+    /// Sets the option to probe the network for the chunks of a batch before paying for it,
+    /// skipping the chunks a majority of their close group prove to hold. They are neither paid
+    /// for nor uploaded, and reported as `FileUploadEvent::AlreadyExistsInNetwork`.
+    ///
+    /// A chunk held by fewer nodes is paid for and uploaded as usual, unless quoted for free.
+    ///
+    /// By default, this option is set to true.
+    pub fn set_skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+
     /// Returns a receiver for file upload events.
     /// This method is optional and the upload process can be performed without it.
     pub fn get_upload_events(&mut self) -> mpsc::Receiver<FileUploadEvent> {
@@ -150,6 +187,19 @@ impl FilesUpload {
         self.upload_final_balance
     }
 
+    /// Returns what was paid to each node during the upload, once it completes, ordered by
+    /// `PeerId`. Payments taken from a payment store, rather than made by this upload, are not
+    /// included.
+    pub fn get_upload_payee_breakdown(&self) -> Vec<PayeeBreakdown> {
+        self.upload_payees.values().cloned().collect()
+    }
+
+    /// Returns the chunks found already stored in the network during the upload, which were
+    /// neither paid for nor uploaded.
+    pub fn get_already_stored_chunks(&self) -> HashSet<XorName> {
+        self.already_stored_chunks.clone()
+    }
+
     /// get the set of failed chunks that could not be uploaded
     pub fn get_failed_chunks(&self) -> HashSet<XorName> {
         self.failed_chunks
@@ -171,6 +221,8 @@ impl FilesUpload {
         self.upload_storage_cost = NanoTokens::zero();
         self.upload_royalty_fees = NanoTokens::zero();
         self.upload_final_balance = NanoTokens::zero();
+        self.upload_payees = Default::default();
+        self.already_stored_chunks = Default::default();
 
         let result = self.upload(chunks).await;
 
@@ -200,20 +252,26 @@ impl FilesUpload {
             // to get +1 if there is a remainder
             (total_elements + self.batch_size - 1) / self.batch_size
         };
-        let mut batch = 1;
         let chunk_batches = chunk_batches.chunks(self.batch_size);
 
-        for chunks_batch in chunk_batches {
+        for (index, chunks_batch) in chunk_batches.enumerate() {
+            let batch = index + 1;
             trace!("Uploading batch {batch}/{n_batches}");
             if sequential_payment_fails >= MAX_SEQUENTIAL_PAYMENT_FAILS {
                 return Err(ClientError::SequentialUploadPaymentError);
             }
             // if the payment fails, we can continue to the next batch
             let res = self.handle_chunk_batch(chunks_batch, false).await;
-            batch += 1;
             match res {
                 Ok(()) => {
                     trace!("Uploaded batch {batch}/{n_batches}");
+                    self.api
+                        .client
+                        .events_channel
+                        .notify(ClientEvent::UploadBatchCompleted {
+                            batch,
+                            total_batches: n_batches,
+                        });
                 }
                 Err(err) => match err {
                     ClientError::CouldNotVerifyTransfer(err) => {
@@ -277,13 +335,32 @@ impl FilesUpload {
         // we can pay for the next batch and carry on
         self.progress_uploading_chunks(false).await?;
 
+        // the chunks of a failed batch were paid for already, there is nothing to save
+        let chunks_batch = if self.skip_existing && !failed_batch {
+            let (stored, missing) = self.probe_stored_chunks(chunks_batch).await;
+            for name in stored {
+                let _ = self.already_stored_chunks.insert(name);
+                self.send_event(FileUploadEvent::AlreadyExistsInNetwork(ChunkAddress::new(
+                    name,
+                )))
+                .await?;
+            }
+            missing
+        } else {
+            chunks_batch.to_vec()
+        };
+
         // pay for and verify payment... if we don't verify here, chunks uploads will surely fail
         let (payee_map, skipped_chunks) = match self
             .api
-            .pay_for_chunks(chunks_batch.iter().map(|info| info.name).collect())
+            .pay_for_chunks_itemised(chunks_batch.iter().map(|info| info.name).collect())
             .await
         {
-            Ok(((storage_cost, royalty_fees, new_balance), (payee_map, skipped_chunks))) => {
+            Ok((
+                ((storage_cost, royalty_fees, new_balance), (payee_map, skipped_chunks)),
+                payments,
+            )) => {
+                self.record_payments(payments)?;
                 // store the stats and emit event too
                 self.upload_storage_cost = self
                     .upload_storage_cost
@@ -305,7 +382,7 @@ impl FilesUpload {
             Err(err) => return Err(err),
         };
 
-        let mut chunks_to_upload = chunks_batch.to_vec();
+        let mut chunks_to_upload = chunks_batch;
         // don't reupload skipped chunks
         chunks_to_upload.retain(|info| !skipped_chunks.contains(&info.name));
 
@@ -317,6 +394,7 @@ impl FilesUpload {
                     .await?;
             } else {
                 // if during the first try we skip the chunk, then it was already uploaded.
+                let _ = self.already_stored_chunks.insert(chunk);
                 self.send_event(FileUploadEvent::AlreadyExistsInNetwork(ChunkAddress::new(
                     chunk,
                 )))
@@ -359,6 +437,76 @@ impl FilesUpload {
         Ok(())
     }
 
+    /// Split the batch into the chunks a majority of their close group prove to hold, and the
+    /// others, which are kept in order. A chunk whose file can't be read is deemed missing.
+    async fn probe_stored_chunks(
+        &self,
+        chunks_batch: &[ChunkInfo],
+    ) -> (Vec<XorName>, Vec<ChunkInfo>) {
+        let client = &self.api.client;
+        let probes: Vec<bool> = futures::stream::iter(chunks_batch)
+            .map(|info| async move {
+                match tokio::fs::read(&info.path).await {
+                    Ok(bytes) => {
+                        client
+                            .is_chunk_stored(&Chunk::new(Bytes::from(bytes)))
+                            .await
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Cannot probe chunk {:?}, failed to read {:?}: {err}",
+                            info.name, info.path
+                        );
+                        false
+                    }
+                }
+            })
+            .buffered(self.batch_size)
+            .collect()
+            .await;
+
+        let mut stored = vec![];
+        let mut missing = vec![];
+        for (info, is_stored) in chunks_batch.iter().zip(probes) {
+            if is_stored {
+                stored.push(info.name);
+            } else {
+                missing.push(info.clone());
+            }
+        }
+        debug!(
+            "{} of the {} chunks of the batch are already stored",
+            stored.len(),
+            chunks_batch.len()
+        );
+        (stored, missing)
+    }
+
+    /// Add the payments of a batch to the totals of their payees.
+    fn record_payments(&mut self, payments: Vec<PayeeBreakdown>) -> Result<()> {
+        for payment in payments {
+            let payee = self
+                .upload_payees
+                .entry(payment.peer_id)
+                .or_insert_with(|| PayeeBreakdown {
+                    peer_id: payment.peer_id,
+                    chunks: 0,
+                    storage_cost: NanoTokens::zero(),
+                    royalties: NanoTokens::zero(),
+                });
+            payee.chunks += payment.chunks;
+            payee.storage_cost = payee
+                .storage_cost
+                .checked_add(payment.storage_cost)
+                .ok_or(ClientError::TotalPriceTooHigh)?;
+            payee.royalties = payee
+                .royalties
+                .checked_add(payment.royalties)
+                .ok_or(ClientError::TotalPriceTooHigh)?;
+        }
+        Ok(())
+    }
+
     /// Progresses the uploading of chunks. If the number of ongoing uploading chunks is less than the batch size,
     /// it pays for the next batch and continues. If an error occurs during the upload, it will be returned.
     ///
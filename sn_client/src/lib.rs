@@ -11,32 +11,89 @@ extern crate tracing;
 
 mod api;
 mod audit;
+mod builder;
 mod chunks;
+mod deadline;
 mod error;
 mod event;
 mod faucet;
 mod files;
+#[cfg(feature = "open-metrics")]
+mod metrics;
+mod payment_audit;
+mod payment_store;
+mod quote_policy;
 mod register;
+mod session_costs;
+mod signed_gossip;
+mod standby;
+mod storage_vouchers;
+mod subscriptions;
 mod wallet;
 
 pub(crate) use error::Result;
 
 pub use self::{
+    api::{ReadCfg, VerificationStatus, DEFAULT_AUDIT_CONCURRENCY, DEFAULT_MAX_CONCURRENT_OPS},
+    audit::{
+        print_audit_progress, royalty_report_csv, write_anomaly_report, write_royalty_report,
+        Anomaly, AnomalySeverity, AuditFrontier, AuditProgress, AuditProgressCallback, AuditResult,
+        AuditStats, BucketSize, DoubleSpendEvidence, DoubleSpendReport, LiveSpendDag,
+        RoyaltyBucket, RoyaltyObservation, RoyaltyTracker, SpendDag, SpendRecordCopy,
+        SpendVerificationReport, AUDIT_FRONTIER_FILE_NAME, DOUBLE_SPENDS_DIR_NAME,
+        DUST_TX_MAX_VALUE, HIGH_FAN_OUT_MIN_OUTPUTS, MAX_PENDING_LIVE_SPENDS,
+        MAX_ROYALTIES_PER_TRANSFER, MAX_SPEND_PATHS, MAX_SPEND_PATH_LEN,
+        ROYALTY_OBSERVATIONS_FILE_NAME, SPEND_DAG_FILE_NAME, SPEND_DAG_FORMAT_VERSION,
+    },
+    builder::ClientBuilder,
+    deadline::{BatchOutcome, Deadline},
     error::Error,
-    event::{ClientEvent, ClientEventsReceiver},
-    faucet::{get_tokens_from_faucet, load_faucet_wallet_from_genesis_wallet},
+    event::{ClientEvent, ClientEventsReceiver, ConnectionStatus, ReconnectPolicy},
+    faucet::{
+        get_tokens_from_faucet, load_faucet_wallet_from_genesis_wallet, split_faucet_wallet,
+        split_wallet_balance,
+    },
     files::{
+        disk_space::{DiskSpaceCheck, FsSpaceProbe, SpaceProbe},
         download::{FilesDownload, FilesDownloadEvent},
-        upload::{FileUploadEvent, FilesUpload},
-        FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES,
+        health::{ChunkHealth, FileHealth, FileHealthReport},
+        manifest::{
+            verify_local_file, DownloadManifest, LocalVerification, ManifestChunk, UploadManifest,
+            UploadManifestChunk, MANIFEST_SUFFIX,
+        },
+        reconcile::ReconcilePlan,
+        upload::{FileUploadEvent, FilesUpload, PayeeBreakdown},
+        FilesApi, BATCH_SIZE, MAX_UPLOAD_RETRIES, MIN_TARGET_CHUNK_SIZE,
+    },
+    payment_audit::{AuditedPayment, PaymentAuditReport, PaymentAuditStatus},
+    payment_store::{DirPaymentStore, PaymentStore, DEFAULT_QUOTE_VALIDITY},
+    quote_policy::{QuotePolicy, DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE},
+    register::{
+        ClientRegister, RegisterReadConsistency, RegisterReadOptions,
+        DEFAULT_REGISTER_CREATION_ATTEMPTS,
     },
-    register::ClientRegister,
+    session_costs::SessionCosts,
+    signed_gossip::{MAX_SIGNED_GOSSIP_PAYLOAD_SIZE, SIGNED_GOSSIP_MSG_VERSION},
+    standby::StandbyStats,
+    storage_vouchers::StorageVoucherUsage,
+    subscriptions::{TopicSubscription, TOPIC_SUBSCRIPTION_CAPACITY},
     wallet::{send, WalletClient},
 };
 
-use self::event::ClientEventsChannel;
+use self::{
+    api::ClientTasks,
+    event::{ClientEventsChannel, Connectivity},
+    standby::StandbyCache,
+    subscriptions::TopicSubscriptions,
+};
 use indicatif::ProgressBar;
-use sn_networking::Network;
+use sn_networking::{Network, RetryPolicy};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize},
+    Arc, Mutex,
+};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
 
 /// Client API implementation to store and get data.
 #[derive(Clone)]
@@ -45,5 +102,33 @@ pub struct Client {
     events_channel: ClientEventsChannel,
     signer: bls::SecretKey,
     peers_added: usize,
+    // The number of responses from nodes asking us to back off as they are busy.
+    busy_responses: Arc<AtomicUsize>,
+    // The peers we hold live connections to, shared with the task handling the network events.
+    connectivity: Arc<Mutex<Connectivity>>,
+    // Set once an audit has verified the genesis spend, so it isn't fetched again.
+    genesis_verified: Arc<AtomicBool>,
     progress: Option<ProgressBar>,
+    tasks: Arc<ClientTasks>,
+    // Cancelled once the client is shut down, or its last clone dropped, stopping the operations
+    // in flight.
+    cancellation: CancellationToken,
+    // Tells apart our claims on the Registers we create from other clients sharing our key.
+    register_claim_nonce: u64,
+    // How the network operations of the client are retried.
+    retry_policy: RetryPolicy,
+    // How many times a Register is published before giving up on verifying it is stored.
+    register_creation_attempts: usize,
+    // The close groups of the working set the client stands by for, see `Client::standby`.
+    standby: Arc<StandbyCache>,
+    // Bounds the chunk and register operations running at once, shared between the clones.
+    ops_limiter: Arc<Semaphore>,
+    // How many transactions of a generation an audit goes through at once.
+    audit_concurrency: usize,
+    // The fees paid to the network since connecting, shared between the clones.
+    session_costs: Arc<Mutex<SessionCosts>>,
+    // The subscriptions made with `Client::subscribe`, shared between the clones.
+    topic_subscriptions: Arc<TopicSubscriptions>,
+    #[cfg(feature = "open-metrics")]
+    metrics: metrics::ClientMetrics,
 }
@@ -0,0 +1,174 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client};
+use prometheus_client::{
+    encoding::{text::encode, EncodeLabelSet, EncodeLabelValue},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        histogram::{exponential_buckets, Histogram},
+    },
+    registry::Registry,
+};
+use sn_transfers::NanoTokens;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The network operations of the client whose outcome and latency are recorded.
+#[derive(EncodeLabelValue, Hash, Clone, Copy, Eq, PartialEq, Debug)]
+pub(crate) enum Operation {
+    ChunkPut,
+    ChunkGet,
+    RegisterGet,
+    SpendPut,
+    SpendGet,
+}
+
+#[derive(EncodeLabelValue, Hash, Clone, Copy, Eq, PartialEq, Debug)]
+enum Outcome {
+    Success,
+    Failure,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct OperationLabels {
+    operation: Operation,
+    outcome: Outcome,
+}
+
+#[derive(EncodeLabelSet, Hash, Clone, Eq, PartialEq, Debug)]
+struct LatencyLabels {
+    operation: Operation,
+}
+
+/// The metrics recorded by a client, shared by all its clones.
+#[derive(Clone)]
+pub(crate) struct ClientMetrics {
+    registry: Arc<Mutex<Registry>>,
+    /// operations
+    operations: Family<OperationLabels, Counter>,
+    operation_latency: Family<LatencyLabels, Histogram, fn() -> Histogram>,
+
+    /// payments
+    payment_amount: Histogram,
+}
+
+impl Default for ClientMetrics {
+    fn default() -> Self {
+        let mut registry = Registry::default();
+        let sub_registry = registry.sub_registry_with_prefix("sn_client");
+
+        let operations = Family::default();
+        sub_registry.register(
+            "operations",
+            "Number of network operations, by operation and outcome",
+            operations.clone(),
+        );
+
+        // from 10ms to about 5min
+        let operation_latency: Family<_, _, fn() -> Histogram> =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.01, 2.0, 16)));
+        sub_registry.register(
+            "operation_latency_seconds",
+            "Latency of the network operations, by operation",
+            operation_latency.clone(),
+        );
+
+        // from 1 nano to 10^11 nanos
+        let payment_amount = Histogram::new(exponential_buckets(1.0, 10.0, 12));
+        sub_registry.register(
+            "payment_amount_nanos",
+            "Amount paid to store the chunks uploaded",
+            payment_amount.clone(),
+        );
+
+        Self {
+            registry: Arc::new(Mutex::new(registry)),
+            operations,
+            operation_latency,
+            payment_amount,
+        }
+    }
+}
+
+impl ClientMetrics {
+    /// Record the outcome and latency of a network operation.
+    pub(crate) fn record_operation(
+        &self,
+        operation: Operation,
+        succeeded: bool,
+        elapsed: Duration,
+    ) {
+        let outcome = if succeeded {
+            Outcome::Success
+        } else {
+            Outcome::Failure
+        };
+        let _ = self
+            .operations
+            .get_or_create(&OperationLabels { operation, outcome })
+            .inc();
+        self.operation_latency
+            .get_or_create(&LatencyLabels { operation })
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record the amount paid to store a chunk.
+    pub(crate) fn record_payment(&self, amount: NanoTokens) {
+        self.payment_amount.observe(amount.as_nano() as f64);
+    }
+
+    fn render(&self) -> Result<String> {
+        let mut text = String::new();
+        let registry = self
+            .registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        encode(&mut text, &registry)?;
+        Ok(text)
+    }
+}
+
+impl Client {
+    /// The registry holding the metrics of the client, to embed them in the caller's exporter.
+    pub fn metrics_registry(&self) -> Arc<Mutex<Registry>> {
+        self.metrics.registry.clone()
+    }
+
+    /// The metrics of the client, rendered in the OpenMetrics text format.
+    pub fn metrics_text(&self) -> Result<String> {
+        self.metrics.render()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_operations_are_rendered() -> eyre::Result<()> {
+        let metrics = ClientMetrics::default();
+        metrics.record_operation(Operation::ChunkPut, true, Duration::from_millis(20));
+        metrics.record_operation(Operation::ChunkGet, false, Duration::from_secs(1));
+        metrics.record_payment(NanoTokens::from(1_000));
+
+        let text = metrics.render()?;
+        assert!(text
+            .contains(r#"sn_client_operations_total{operation="ChunkPut",outcome="Success"} 1"#));
+        assert!(text
+            .contains(r#"sn_client_operations_total{operation="ChunkGet",outcome="Failure"} 1"#));
+        assert!(
+            text.contains(r#"sn_client_operation_latency_seconds_count{operation="ChunkGet"} 1"#)
+        );
+        assert!(text.contains("sn_client_payment_amount_nanos_count 1"));
+        Ok(())
+    }
+}
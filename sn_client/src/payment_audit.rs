@@ -0,0 +1,180 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::payment_store::is_quote_expired;
+
+use sn_transfers::{NanoTokens, PaymentDetails};
+use std::time::{Duration, SystemTime};
+use xor_name::XorName;
+
+/// What became of the content a payment was made for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentAuditStatus {
+    /// The content is stored on the network.
+    Stored,
+    /// The content never made it to the network, but its quote is still valid:
+    /// it can be uploaded again without paying for it again.
+    MissingButQuoteValid,
+    /// The content never made it to the network and its quote has expired:
+    /// the payment is lost.
+    MissingAndExpired,
+    /// Whether the content is stored could not be checked, e.g. as its close group didn't
+    /// answer: the payment is to be audited again.
+    Unknown,
+}
+
+impl PaymentAuditStatus {
+    /// Classify a payment, given whether its content is stored and when it was quoted.
+    pub(crate) fn classify(
+        stored: bool,
+        payment: &PaymentDetails,
+        quote_validity: Duration,
+        now: SystemTime,
+    ) -> Self {
+        if stored {
+            Self::Stored
+        } else if is_quote_expired(&payment.quote, quote_validity, now) {
+            Self::MissingAndExpired
+        } else {
+            Self::MissingButQuoteValid
+        }
+    }
+}
+
+/// A payment cached in the wallet, and what became of the content it was made for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditedPayment {
+    /// The content paid for.
+    pub name: XorName,
+    /// The amount paid, royalties included.
+    pub cost: NanoTokens,
+    pub status: PaymentAuditStatus,
+}
+
+impl AuditedPayment {
+    pub(crate) fn new(name: XorName, payment: &PaymentDetails, status: PaymentAuditStatus) -> Self {
+        let cost = payment
+            .transfer
+            .1
+            .checked_add(payment.royalties.1)
+            .unwrap_or(payment.transfer.1);
+        Self { name, cost, status }
+    }
+}
+
+/// The outcome of auditing the payments cached in a wallet, see `WalletClient::audit_payments`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaymentAuditReport {
+    pub payments: Vec<AuditedPayment>,
+}
+
+impl PaymentAuditReport {
+    /// The number of payments with the given status, and the total amount paid for them.
+    pub fn totals(&self, status: PaymentAuditStatus) -> (usize, NanoTokens) {
+        self.payments
+            .iter()
+            .filter(|payment| payment.status == status)
+            .fold((0, NanoTokens::zero()), |(count, total), payment| {
+                let total = total.checked_add(payment.cost).unwrap_or(total);
+                (count + 1, total)
+            })
+    }
+
+    /// The content which can be uploaded again without paying for it again.
+    pub fn repairable(&self) -> impl Iterator<Item = &XorName> {
+        self.payments
+            .iter()
+            .filter(|payment| payment.status == PaymentAuditStatus::MissingButQuoteValid)
+            .map(|payment| &payment.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_QUOTE_VALIDITY;
+    use sn_transfers::{MainSecretKey, PaymentQuote, Transfer};
+
+    fn payment(name: XorName, quoted_at: SystemTime, cost: u64) -> PaymentDetails {
+        let mut quote = PaymentQuote::test_dummy(name, NanoTokens::from(cost));
+        quote.timestamp = quoted_at;
+        let transfer = Transfer::NetworkRoyalties(vec![]);
+        PaymentDetails {
+            recipient: MainSecretKey::random().main_pubkey(),
+            transfer: (transfer.clone(), NanoTokens::from(cost)),
+            royalties: (transfer, NanoTokens::from(1)),
+            quote,
+            royalty_rate: Default::default(),
+            voucher: None,
+            voucher_inputs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn payments_are_classified_by_storage_and_quote_age() {
+        let now = SystemTime::now();
+        let fresh = now - DEFAULT_QUOTE_VALIDITY / 2;
+        let expired = now - DEFAULT_QUOTE_VALIDITY * 2;
+        let classify = |stored, quoted_at| {
+            let name = XorName::random(&mut rand::thread_rng());
+            PaymentAuditStatus::classify(
+                stored,
+                &payment(name, quoted_at, 10),
+                DEFAULT_QUOTE_VALIDITY,
+                now,
+            )
+        };
+
+        assert_eq!(classify(true, fresh), PaymentAuditStatus::Stored);
+        assert_eq!(classify(true, expired), PaymentAuditStatus::Stored);
+        assert_eq!(
+            classify(false, fresh),
+            PaymentAuditStatus::MissingButQuoteValid
+        );
+        assert_eq!(
+            classify(false, expired),
+            PaymentAuditStatus::MissingAndExpired
+        );
+    }
+
+    #[test]
+    fn report_totals_count_the_royalties() {
+        let now = SystemTime::now();
+        let mut rng = rand::thread_rng();
+        let mut audited = |status, cost| {
+            let name = XorName::random(&mut rng);
+            AuditedPayment::new(name, &payment(name, now, cost), status)
+        };
+        let report = PaymentAuditReport {
+            payments: vec![
+                audited(PaymentAuditStatus::Stored, 10),
+                audited(PaymentAuditStatus::MissingButQuoteValid, 20),
+                audited(PaymentAuditStatus::MissingButQuoteValid, 30),
+                audited(PaymentAuditStatus::MissingAndExpired, 40),
+            ],
+        };
+
+        assert_eq!(
+            report.totals(PaymentAuditStatus::Stored),
+            (1, NanoTokens::from(11))
+        );
+        assert_eq!(
+            report.totals(PaymentAuditStatus::MissingButQuoteValid),
+            (2, NanoTokens::from(52))
+        );
+        assert_eq!(
+            report.totals(PaymentAuditStatus::MissingAndExpired),
+            (1, NanoTokens::from(41))
+        );
+        let repairable: Vec<_> = report.repairable().collect();
+        assert_eq!(
+            repairable,
+            vec![&report.payments[1].name, &report.payments[2].name]
+        );
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use fs2::FileExt;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sn_transfers::{PaymentDetails, PaymentQuote, WalletError, WalletResult};
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use xor_name::XorName;
+
+/// Nodes reject quotes older than this, so payments made against them can't be reused.
+pub const DEFAULT_QUOTE_VALIDITY: Duration = Duration::from_secs(3600);
+
+const PAYMENT_STORE_LOCK_FILE_NAME: &str = "payment_store.lock";
+const PAYMENT_FILE_EXTENSION: &str = "payment";
+const PAYMENT_TMP_FILE_EXTENSION: &str = "payment.tmp";
+
+/// A cache of the payments made for content, keyed by the content's `XorName`.
+///
+/// By default a `WalletClient` only uses the payments cached in its own wallet. Setting a
+/// `PaymentStore` lets several wallets, possibly in different processes, share their payments
+/// so that content is paid for only once.
+pub trait PaymentStore: Send + Sync {
+    /// Get the payment made for the given content, and the payee to upload it to.
+    /// Returns `None` if there is no payment, or if it can no longer be used.
+    fn get_payment(&self, name: &XorName) -> WalletResult<Option<(PeerId, PaymentDetails)>>;
+
+    /// Record the payment made for the given content, and the payee to upload it to.
+    fn insert_payment(
+        &self,
+        name: XorName,
+        payee: PeerId,
+        payment: &PaymentDetails,
+    ) -> WalletResult<()>;
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredPayment {
+    payee: Vec<u8>,
+    payment: PaymentDetails,
+}
+
+/// A `PaymentStore` backed by a directory, holding one file per paid `XorName`.
+///
+/// Access is guarded by a lock file in the directory, so the store can be shared
+/// across processes. Payments are written to a temporary file first, then renamed into place,
+/// so an interrupted write never leaves a truncated payment behind. Payments whose quote has
+/// expired are treated as absent.
+#[derive(Clone, Debug)]
+pub struct DirPaymentStore {
+    dir: PathBuf,
+    quote_validity: Duration,
+}
+
+impl DirPaymentStore {
+    /// Open (creating if needed) a payment store at the given directory.
+    pub fn new(dir: &Path) -> WalletResult<Self> {
+        std::fs::create_dir_all(dir).map_err(|source| io_error(dir, source))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            quote_validity: DEFAULT_QUOTE_VALIDITY,
+        })
+    }
+
+    /// Set how long after being quoted a payment is still considered usable.
+    pub fn set_quote_validity(mut self, quote_validity: Duration) -> Self {
+        self.quote_validity = quote_validity;
+        self
+    }
+
+    fn payment_path(&self, name: &XorName) -> PathBuf {
+        self.dir
+            .join(hex::encode(name))
+            .with_extension(PAYMENT_FILE_EXTENSION)
+    }
+
+    fn lock(&self, exclusive: bool) -> WalletResult<File> {
+        let path = self.dir.join(PAYMENT_STORE_LOCK_FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|source| io_error(&path, source))?;
+        let locked = if exclusive {
+            file.lock_exclusive()
+        } else {
+            file.lock_shared()
+        };
+        locked.map_err(|source| io_error(&path, source))?;
+        Ok(file)
+    }
+
+    fn is_expired(&self, payment: &PaymentDetails) -> bool {
+        is_quote_expired(&payment.quote, self.quote_validity, SystemTime::now())
+    }
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> WalletError {
+    WalletError::PaymentStoreIo {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+fn serialisation_error(path: &Path, reason: impl ToString) -> WalletError {
+    WalletError::PaymentStoreSerialisation {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Write the bytes to a temporary file next to the path, then rename it into place, so readers
+/// find either the previous content or the new one, never part of it.
+fn write_atomically(path: &Path, bytes: &[u8]) -> WalletResult<()> {
+    let tmp_path = path.with_extension(PAYMENT_TMP_FILE_EXTENSION);
+    let mut file = File::create(&tmp_path).map_err(|source| io_error(&tmp_path, source))?;
+    file.write_all(bytes)
+        .and_then(|()| file.sync_all())
+        .map_err(|source| io_error(&tmp_path, source))?;
+    std::fs::rename(&tmp_path, path).map_err(|source| io_error(path, source))
+}
+
+/// Whether the quote is older than the given validity at the given time, in which case nodes
+/// would refuse payments made against it.
+pub(crate) fn is_quote_expired(
+    quote: &PaymentQuote,
+    quote_validity: Duration,
+    now: SystemTime,
+) -> bool {
+    match now.duration_since(quote.timestamp) {
+        Ok(age) => age > quote_validity,
+        // quoted in the future, nothing to expire yet
+        Err(_) => false,
+    }
+}
+
+impl PaymentStore for DirPaymentStore {
+    fn get_payment(&self, name: &XorName) -> WalletResult<Option<(PeerId, PaymentDetails)>> {
+        let path = self.payment_path(name);
+        let _shared_access = self.lock(false)?;
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(io_error(&path, source)),
+        };
+        let stored: StoredPayment =
+            rmp_serde::from_slice(&bytes).map_err(|err| serialisation_error(&path, err))?;
+
+        if self.is_expired(&stored.payment) {
+            debug!("Ignoring payment for {name:?} from payment store, as its quote has expired");
+            return Ok(None);
+        }
+
+        let payee = PeerId::from_bytes(&stored.payee)
+            .map_err(|err| serialisation_error(&path, format!("invalid payee: {err}")))?;
+        Ok(Some((payee, stored.payment)))
+    }
+
+    fn insert_payment(
+        &self,
+        name: XorName,
+        payee: PeerId,
+        payment: &PaymentDetails,
+    ) -> WalletResult<()> {
+        let stored = StoredPayment {
+            payee: payee.to_bytes(),
+            payment: payment.clone(),
+        };
+        let path = self.payment_path(&name);
+        let bytes = rmp_serde::to_vec(&stored).map_err(|err| serialisation_error(&path, err))?;
+        let _exclusive_access = self.lock(true)?;
+        write_atomically(&path, &bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{MainSecretKey, NanoTokens, PaymentQuote, Transfer};
+
+    fn payment_details(name: XorName, timestamp: SystemTime) -> PaymentDetails {
+        let mut quote = PaymentQuote::test_dummy(name, NanoTokens::from(10));
+        quote.timestamp = timestamp;
+        PaymentDetails {
+            recipient: MainSecretKey::random().main_pubkey(),
+            transfer: (Transfer::NetworkRoyalties(vec![]), NanoTokens::from(10)),
+            royalties: (Transfer::NetworkRoyalties(vec![]), NanoTokens::from(1)),
+            quote,
+            royalty_rate: Default::default(),
+            voucher: None,
+            voucher_inputs: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_payments_are_shared_across_store_instances() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let name = XorName::random(&mut rand::thread_rng());
+        let payee = PeerId::random();
+        let payment = payment_details(name, SystemTime::now());
+
+        let store_one = DirPaymentStore::new(dir.path())?;
+        let store_two = DirPaymentStore::new(dir.path())?;
+        assert!(store_two.get_payment(&name)?.is_none());
+
+        store_one.insert_payment(name, payee, &payment)?;
+        let (stored_payee, stored_payment) = store_two
+            .get_payment(&name)?
+            .ok_or_else(|| eyre::eyre!("payment not found"))?;
+        assert_eq!(stored_payee, payee);
+        assert_eq!(stored_payment.to_payment(), payment.to_payment());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_payments_are_replaced_without_leaving_temporary_files() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let name = XorName::random(&mut rand::thread_rng());
+        let store = DirPaymentStore::new(dir.path())?;
+
+        let payee = PeerId::random();
+        store.insert_payment(
+            name,
+            PeerId::random(),
+            &payment_details(name, SystemTime::now()),
+        )?;
+        store.insert_payment(name, payee, &payment_details(name, SystemTime::now()))?;
+        let (stored_payee, _) = store
+            .get_payment(&name)?
+            .ok_or_else(|| eyre::eyre!("payment not found"))?;
+        assert_eq!(stored_payee, payee);
+
+        let mut files: Vec<_> = std::fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                format!("{}.{PAYMENT_FILE_EXTENSION}", hex::encode(name)).into(),
+                std::ffi::OsString::from(PAYMENT_STORE_LOCK_FILE_NAME),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_payments_are_reported_as_such() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let name = XorName::random(&mut rand::thread_rng());
+        let store = DirPaymentStore::new(dir.path())?;
+        std::fs::write(store.payment_path(&name), b"not a payment")?;
+
+        assert!(matches!(
+            store.get_payment(&name),
+            Err(WalletError::PaymentStoreSerialisation { path, .. }) if path == store.payment_path(&name)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_payments_are_ignored() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let name = XorName::random(&mut rand::thread_rng());
+        let quoted_at = SystemTime::now() - Duration::from_secs(120);
+        let payment = payment_details(name, quoted_at);
+
+        let store = DirPaymentStore::new(dir.path())?;
+        store.insert_payment(name, PeerId::random(), &payment)?;
+        assert!(store.get_payment(&name)?.is_some());
+
+        let store = store.set_quote_validity(Duration::from_secs(60));
+        assert!(store.get_payment(&name)?.is_none());
+
+        Ok(())
+    }
+}
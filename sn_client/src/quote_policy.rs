@@ -0,0 +1,226 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use libp2p::PeerId;
+use sn_networking::close_group_majority;
+use sn_protocol::NetworkAddress;
+use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote, WalletError, WalletResult};
+
+/// By default, quotes over this many times the median quote of the close group are not paid.
+pub const DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE: u64 = 10;
+
+/// How the payee of a record is picked out of the store cost quotes of its close group.
+///
+/// The cheapest of the quotes of the peers closest to the record is paid. Quotes over
+/// `max_median_multiple` times the median quote of the close group are outliers: they are logged
+/// and never paid, unless `accept_any_price` is set. A free median quote leaves no outliers, as
+/// any price would be infinitely many times over it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotePolicy {
+    pub max_median_multiple: u64,
+    pub accept_any_price: bool,
+}
+
+impl Default for QuotePolicy {
+    fn default() -> Self {
+        Self {
+            max_median_multiple: DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE,
+            accept_any_price: false,
+        }
+    }
+}
+
+impl QuotePolicy {
+    /// A policy paying the cheapest close quote, whatever its price.
+    pub fn accept_any_price() -> Self {
+        Self {
+            accept_any_price: true,
+            ..Default::default()
+        }
+    }
+
+    /// Pick the payee for the record at the given address, out of the quotes of its close group
+    /// sorted from the closest peer to the farthest.
+    pub(crate) fn select_payee(
+        &self,
+        address: &NetworkAddress,
+        quotes: Vec<(PeerId, MainPubkey, PaymentQuote)>,
+    ) -> WalletResult<(PeerId, MainPubkey, PaymentQuote)> {
+        let outliers = self.outliers(&quotes);
+        if let Some(median) = median_cost(&quotes) {
+            for (peer_id, _, quote) in quotes
+                .iter()
+                .filter(|(peer_id, ..)| outliers.contains(peer_id))
+            {
+                warn!(
+                    "Ignoring the store cost quote of {} from {peer_id:?} for {address:?}, over {}x the median quote of {median}",
+                    quote.cost, self.max_median_multiple
+                );
+            }
+        }
+
+        quotes
+            .into_iter()
+            .filter(|(peer_id, ..)| !outliers.contains(peer_id))
+            .take(close_group_majority())
+            // the first of the cheapest quotes is from the closest peer
+            .min_by_key(|(_, _, quote)| quote.cost)
+            .ok_or_else(|| {
+                WalletError::CouldNotSendMoney(format!(
+                    "No acceptable store cost quote for {address:?}"
+                ))
+            })
+    }
+
+    /// The peers quoting over the allowed multiple of the median quote.
+    fn outliers(&self, quotes: &[(PeerId, MainPubkey, PaymentQuote)]) -> Vec<PeerId> {
+        let Some(median) = median_cost(quotes) else {
+            return vec![];
+        };
+        if self.accept_any_price || median.is_zero() {
+            return vec![];
+        }
+        let max_cost = median.as_nano().saturating_mul(self.max_median_multiple);
+        quotes
+            .iter()
+            .filter(|(_, _, quote)| quote.cost.as_nano() > max_cost)
+            .map(|(peer_id, ..)| *peer_id)
+            .collect()
+    }
+}
+
+/// The median cost of the quotes, the lower one of the two middle quotes for an even count.
+fn median_cost(quotes: &[(PeerId, MainPubkey, PaymentQuote)]) -> Option<NanoTokens> {
+    let mut costs: Vec<_> = quotes.iter().map(|(_, _, quote)| quote.cost).collect();
+    costs.sort();
+    costs.get(costs.len().saturating_sub(1) / 2).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    fn quotes(costs: &[u64]) -> Vec<(PeerId, MainPubkey, PaymentQuote)> {
+        costs
+            .iter()
+            .map(|cost| {
+                (
+                    PeerId::random(),
+                    MainPubkey::new(bls::SecretKey::random().public_key()),
+                    PaymentQuote::test_dummy(Default::default(), NanoTokens::from(*cost)),
+                )
+            })
+            .collect()
+    }
+
+    /// The logs written while running `f`.
+    fn logged<T>(f: impl FnOnce() -> T) -> (T, String) {
+        #[derive(Clone, Default)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Logs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let result = tracing::subscriber::with_default(subscriber, f);
+
+        let bytes = logs
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        (result, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    #[test]
+    fn an_outlier_quote_is_not_paid() -> eyre::Result<()> {
+        let address = NetworkAddress::from_peer(PeerId::random());
+        // fault injection: the closest peer quotes an absurd price
+        let quotes = quotes(&[1_000_000, 12, 11, 10, 13]);
+        let outlier = quotes[0].0;
+
+        let policy = QuotePolicy::default();
+        assert_eq!(policy.outliers(&quotes), vec![outlier]);
+
+        // the outlier makes room for the next closest peer, the cheapest one
+        let (selected, logs) = logged(|| policy.select_payee(&address, quotes.clone()));
+        let (payee, _, quote) = selected?;
+        assert_ne!(payee, outlier);
+        assert_eq!(payee, quotes[3].0);
+        assert_eq!(quote.cost, NanoTokens::from(10));
+
+        let warnings: Vec<_> = logs
+            .lines()
+            .filter(|line| line.contains("Ignoring the store cost quote"))
+            .collect();
+        assert_eq!(warnings.len(), 1, "only the outlier is logged: {logs}");
+        assert!(warnings[0].contains(&format!("{outlier:?}")));
+        assert!(warnings[0].contains(&NanoTokens::from(1_000_000).to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn a_free_median_quote_leaves_no_outliers() -> eyre::Result<()> {
+        let address = NetworkAddress::from_peer(PeerId::random());
+        let quotes = quotes(&[5, 0, 0, 0, 7]);
+
+        let policy = QuotePolicy::default();
+        assert!(policy.outliers(&quotes).is_empty());
+
+        let (selected, logs) = logged(|| policy.select_payee(&address, quotes.clone()));
+        let (payee, _, quote) = selected?;
+        assert_eq!(payee, quotes[1].0);
+        assert!(quote.cost.is_zero());
+        assert!(!logs.contains("Ignoring the store cost quote"));
+        Ok(())
+    }
+
+    #[test]
+    fn any_price_is_accepted_when_asked_to() -> eyre::Result<()> {
+        let address = NetworkAddress::from_peer(PeerId::random());
+        let quotes = quotes(&[1_000_000, 12, 11, 10, 13]);
+
+        let policy = QuotePolicy::accept_any_price();
+        assert!(policy.outliers(&quotes).is_empty());
+
+        // only the closest peers are considered, the outlier among them
+        let (payee, _, quote) = policy.select_payee(&address, quotes.clone())?;
+        assert_eq!(payee, quotes[2].0);
+        assert_eq!(quote.cost, NanoTokens::from(11));
+        Ok(())
+    }
+
+    #[test]
+    fn the_median_is_the_lower_middle_quote() {
+        let median = |costs: &[u64]| median_cost(&quotes(costs)).map(|cost| cost.as_nano());
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[7]), Some(7));
+        assert_eq!(median(&[30, 10, 20]), Some(20));
+        assert_eq!(median(&[40, 10, 30, 20]), Some(20));
+    }
+}
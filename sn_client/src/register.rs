@@ -8,21 +8,71 @@
 
 use crate::{Client, Error, Result, WalletClient};
 
+use async_trait::async_trait;
 use bls::PublicKey;
 use libp2p::kad::{Quorum, Record};
-use sn_networking::{GetRecordCfg, PutRecordCfg, VerificationKind};
+use sn_networking::{Error as NetworkError, GetRecordCfg, PutRecordCfg, VerificationKind};
 use sn_protocol::{
     error::Error as ProtocolError,
-    messages::RegisterCmd,
+    messages::{RegisterClaim, RegisterCmd, SignedRegisterClaim, REGISTER_CLAIM_GRACE_WINDOW},
     storage::{try_serialize_record, RecordKind},
     NetworkAddress,
 };
 use sn_registers::{Entry, EntryHash, Permissions, Register, RegisterAddress, SignedRegister};
 use sn_transfers::{NanoTokens, Payment};
 
-use std::collections::{BTreeSet, HashSet, LinkedList};
+use std::{
+    collections::{BTreeSet, HashSet, LinkedList},
+    time::{Duration, Instant},
+};
 use xor_name::XorName;
 
+/// How many times a Register is published, by default, before giving up on verifying it is
+/// stored. See `Client::set_register_creation_attempts`.
+pub const DEFAULT_REGISTER_CREATION_ATTEMPTS: usize = 3;
+/// How often a Register being created under someone else's claim is looked up.
+const CLAIMED_REGISTER_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long we wait for a Register being created under someone else's claim, before giving up.
+const CLAIMED_REGISTER_MAX_WAIT: Duration =
+    Duration::from_secs(2 * REGISTER_CLAIM_GRACE_WINDOW.as_secs());
+
+/// How consistent reading a Register from the network is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RegisterReadConsistency {
+    /// Read the copy of any node of the close group, merging the copies only when the nodes
+    /// answering first disagree. Under heavy writes, consecutive reads may flap between views.
+    #[default]
+    One,
+    /// Read the copies of every reachable node of the close group, and merge all valid copies.
+    /// Slower, but never misses an entry held by any of the nodes reached.
+    MergeAll,
+}
+
+/// Options to read a Register from the network with.
+///
+/// With a `timeout` set, a read taking longer than it fails with `Error::OperationTimedOut`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RegisterReadOptions {
+    pub consistency: RegisterReadConsistency,
+    pub timeout: Option<Duration>,
+}
+
+impl RegisterReadOptions {
+    /// Read the copies of every reachable node of the close group, merging them.
+    pub fn merge_all() -> Self {
+        Self {
+            consistency: RegisterReadConsistency::MergeAll,
+            ..Default::default()
+        }
+    }
+
+    /// Fail the read with `Error::OperationTimedOut` if it takes longer than the given timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
 /// Ops made to an offline Register instance are applied locally only,
 /// and accumulated till the user explicitly calls 'sync'. The user can
 /// switch back to sync with the network for every op by invoking `online` API.
@@ -78,9 +128,88 @@ impl ClientRegister {
         Ok((reg, storage_cost, royalties_fees))
     }
 
+    /// Retrieve the Register with the given meta and our key from the network, or create it
+    /// with the given permissions if it doesn't exist yet.
+    ///
+    /// Only the client holding the claim on the Register pays for it. The others, e.g. the same
+    /// user racing us from another device, wait for it to be created and retrieve it. An
+    /// existing Register is returned as is, whatever its permissions.
+    ///
+    /// Returns the Register, whether it was created by this call, and what was paid for it.
+    pub(super) async fn get_or_create_online(
+        client: Client,
+        meta: XorName,
+        wallet_client: &mut WalletClient,
+        perms: Permissions,
+        verify_store: bool,
+    ) -> Result<(Self, bool, NanoTokens)> {
+        let reg = Self::create_register(client, meta, perms)?;
+        let address = *reg.address();
+        let wait_until = Instant::now() + CLAIMED_REGISTER_MAX_WAIT;
+        let claim = loop {
+            if let Some(existing) = reg.retrieve_existing().await? {
+                return Ok((existing, false, NanoTokens::zero()));
+            }
+            match reg.claim_creation().await {
+                Ok(claim) => break claim,
+                Err(Error::RegisterClaimed {
+                    claimant,
+                    nonce,
+                    remaining,
+                }) if Instant::now() < wait_until => {
+                    debug!("Register {address:?} is being created by {claimant:?} under nonce {nonce}, whose claim expires in {remaining:?}, waiting for it");
+                    tokio::time::sleep(CLAIMED_REGISTER_POLL_INTERVAL).await;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        // the Register may have been created, and its claim released, since we looked it up
+        if let Some(existing) = reg.retrieve_existing().await? {
+            return Ok((existing, false, NanoTokens::zero()));
+        }
+
+        let net_addr = NetworkAddress::from_register_address(address);
+        let ((storage_cost, royalties_fees), _) = wallet_client
+            .pay_for_storage(std::iter::once(net_addr.clone()))
+            .await?;
+        let cost = storage_cost
+            .checked_add(royalties_fees)
+            .ok_or(Error::TotalPriceTooHigh)?;
+        info!("Paid {cost} to create the Register {address:?}");
+        if let Err(err) = wallet_client.store_local_wallet() {
+            warn!("Failed to store wallet with cached payment proofs: {err:?}");
+        }
+        let payment = wallet_client.get_payment_for_addr(&net_addr)?;
+
+        if let Err(err) = reg.publish_creation(payment, claim, verify_store).await {
+            // someone sharing our key may still have created it first
+            warn!("Failed to create the Register {address:?}, looking it up: {err:?}");
+            return match reg.retrieve_existing().await? {
+                Some(existing) => Ok((existing, false, cost)),
+                None => Err(err),
+            };
+        }
+        Ok((reg, true, cost))
+    }
+
     /// Retrieve a Register from the network to work on it offline.
-    pub(super) async fn retrieve(client: Client, address: RegisterAddress) -> Result<Self> {
-        let register = Self::get_register_from_network(&client, address).await?;
+    pub(super) async fn retrieve(
+        client: Client,
+        address: RegisterAddress,
+        consistency: RegisterReadConsistency,
+    ) -> Result<Self> {
+        let register = match consistency {
+            RegisterReadConsistency::One => {
+                Self::get_register_from_network(&client, address).await?
+            }
+            RegisterReadConsistency::MergeAll => {
+                let (register, divergence) =
+                    client.get_signed_register_merging_all(address).await?;
+                debug!("Read Register {address:?} merging {divergence} distinct copies");
+                register.verify_with_address(address)?;
+                register.register()?
+            }
+        };
 
         Ok(Self {
             client,
@@ -203,6 +332,9 @@ impl ClientRegister {
                     signature: self.client.sign(self.register.bytes()?),
                 };
 
+                // Make sure no one else is uploading the Register before paying for it
+                let claim = self.claim_creation().await?;
+
                 // Let's check if the user has already paid for this address first
                 let net_addr = sn_protocol::NetworkAddress::RegisterAddress(addr);
                 // Let's make the storage payment
@@ -234,7 +366,7 @@ impl ClientRegister {
                 let payment = wallet_client.get_payment_for_addr(&net_addr)?;
 
                 debug!("payments found: {payment:?}");
-                self.publish_register(cmd, Some(payment), verify_store)
+                self.publish_register(cmd, Some((payment, claim)), verify_store)
                     .await?;
                 self.register.clone()
             }
@@ -245,8 +377,66 @@ impl ClientRegister {
         Ok((storage_cost, royalties_fees))
     }
 
+    /// Publish the creation of this Register, paid for with the given payment and made
+    /// under the given claim.
+    pub(crate) async fn publish_creation(
+        &self,
+        payment: Payment,
+        claim: SignedRegisterClaim,
+        verify_store: bool,
+    ) -> Result<()> {
+        let cmd = RegisterCmd::Create {
+            register: self.register.clone(),
+            signature: self.client.sign(self.register.bytes()?),
+        };
+        self.publish_register(cmd, Some((payment, claim)), verify_store)
+            .await
+    }
+
+    /// Have the close group of this Register hold our claim on it, before paying for its creation.
+    /// Fails with `Error::RegisterClaimed` if someone else is uploading the Register. When the
+    /// close group neither holds our claim nor a competing one, the Register is created unclaimed.
+    ///
+    /// The claim is signed with our key, which must be the one of the owner of the Register.
+    pub(crate) async fn claim_creation(&self) -> Result<SignedRegisterClaim> {
+        let claim = SignedRegisterClaim::new(
+            self.address(),
+            RegisterClaim::new(self.client.signer_pk(), self.client.register_claim_nonce),
+            self.client.signer(),
+        );
+        match self
+            .client
+            .network
+            .claim_register(*self.address(), claim.clone())
+            .await
+        {
+            Ok(true) => Ok(claim),
+            Ok(false) => {
+                warn!(
+                    "Our claim on Register {:?} isn't held by the majority of its close group, creating it unclaimed",
+                    self.address()
+                );
+                Ok(claim)
+            }
+            Err(NetworkError::RegisterClaimed(competing_claim)) => {
+                warn!(
+                    "Register {:?} is being uploaded under a competing claim {competing_claim:?}",
+                    self.address()
+                );
+                Err(Error::RegisterClaimed {
+                    claimant: competing_claim.claimant,
+                    nonce: competing_claim.nonce,
+                    remaining: competing_claim.remaining(),
+                })
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
     /// Push all operations made locally to the replicas of this Register on the network.
-    /// This optionally verifies that the stored Register is the same as our local register
+    /// This optionally verifies that the stored Register is the same as our local register,
+    /// and that a majority of the close group returns the entries written, retrying as set by
+    /// the retry policy of the client. The operations are kept to be pushed again until then.
     pub async fn push(&mut self, verify_store: bool) -> Result<()> {
         let ops_len = self.ops.len();
         if ops_len > 0 {
@@ -254,6 +444,7 @@ impl ClientRegister {
             debug!("Pushing {ops_len} cached Register cmds at {address}!");
 
             // TODO: send them all concurrently
+            let mut pushed = vec![];
             while let Some(cmd) = self.ops.pop_back() {
                 // We don't need to send the payment proofs here since
                 // these are all Register mutation cmds which don't require payment.
@@ -265,14 +456,62 @@ impl ClientRegister {
                     self.ops.push_back(cmd);
                     return Err(err);
                 }
+                pushed.push(cmd);
             }
 
             debug!("Successfully pushed {ops_len} Register cmds at {address}!");
+
+            // the entries written are to be read back, not only some copy of the Register
+            let written: BTreeSet<_> = pushed
+                .iter()
+                .filter_map(|cmd| match cmd {
+                    RegisterCmd::Edit(op) => Some(op.entry_hash()),
+                    _ => None,
+                })
+                .collect();
+            if verify_store && !written.is_empty() {
+                if let Err(err) = self.verify_entries_stored(address, &written).await {
+                    // We keep the cmds for next sync to push and verify again
+                    for cmd in pushed {
+                        self.ops.push_front(cmd);
+                    }
+                    return Err(err);
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Verify the Register holds the entries written, retrying as set by the retry policy of the
+    /// client while the nodes catch up with them.
+    async fn verify_entries_stored(
+        &self,
+        address: RegisterAddress,
+        written: &BTreeSet<EntryHash>,
+    ) -> Result<()> {
+        let retry_policy = self.client.retry_policy();
+        let mut attempt = 1;
+        loop {
+            let err = match self
+                .client
+                .verify_register_stored_with(address, written, Quorum::Majority)
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(err) => err,
+            };
+            if attempt >= retry_policy.attempts() {
+                warn!("Register {address} does not hold the entries written after {attempt} attempts: {err}");
+                return Err(err);
+            }
+            let backoff = retry_policy.backoff(attempt);
+            debug!("Register {address} does not hold the entries written yet, verifying again after {backoff:?}: {err}");
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
     /// Write a new value onto the Register atop latest value.
     /// It returns an error if it finds branches in the content/entries; if it is
     /// required to merge/resolve the branches, invoke the `write_merging_branches` API.
@@ -309,14 +548,49 @@ impl ClientRegister {
         self.push(verify_store).await
     }
 
+    /// Hand this Register over to the `new_owner`, pushing the transfer to the network along
+    /// with the operations made locally before it.
+    /// The Register keeps its address, and can't be written to by our signer anymore.
+    pub async fn transfer_ownership(
+        &mut self,
+        new_owner: PublicKey,
+        verify_store: bool,
+    ) -> Result<()> {
+        let transfer = self
+            .register
+            .transfer_ownership(new_owner, self.client.signer())?;
+        info!(
+            "Handing the Register {:?} over to {new_owner:?}",
+            self.address()
+        );
+        self.ops
+            .push_front(RegisterCmd::TransferOwnership(transfer));
+        self.push(verify_store).await
+    }
+
     // ********* Private helpers  *********
 
+    /// Retrieve this Register from the network, `None` if it isn't there.
+    async fn retrieve_existing(&self) -> Result<Option<Self>> {
+        match Self::retrieve(
+            self.client.clone(),
+            *self.address(),
+            RegisterReadConsistency::One,
+        )
+        .await
+        {
+            Ok(existing) => Ok(Some(existing)),
+            Err(Error::Protocol(ProtocolError::RegisterNotFound(_))) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Publish a `Register` command on the network.
     /// If `verify_store` is true, it will verify the Register was stored on the network.
     async fn publish_register(
         &self,
         cmd: RegisterCmd,
-        payment: Option<Payment>,
+        payment: Option<(Payment, SignedRegisterClaim)>,
         verify_store: bool,
     ) -> Result<()> {
         let cmd_dst = cmd.dst();
@@ -344,15 +618,20 @@ impl ClientRegister {
                 reg.add_op(op)?;
                 reg
             }
+            RegisterCmd::TransferOwnership(transfer) => {
+                let mut reg = network_reg?;
+                reg.add_ownership_transfer(transfer)?;
+                reg
+            }
         };
 
         let network_address = NetworkAddress::from_register_address(*register.address());
         let key = network_address.to_record_key();
         let record = match payment {
-            Some(payment) => Record {
+            Some((payment, claim)) => Record {
                 key: key.clone(),
                 value: try_serialize_record(
-                    &(payment, &register),
+                    &(payment, &register, claim),
                     RecordKind::RegisterWithPayment,
                 )?
                 .to_vec(),
@@ -370,8 +649,7 @@ impl ClientRegister {
         let (record_to_verify, expected_holders) = if verify_store {
             let expected_holders: HashSet<_> = self
                 .client
-                .network
-                .get_closest_peers(&network_address, true)
+                .close_group(&network_address)
                 .await?
                 .iter()
                 .cloned()
@@ -391,19 +669,22 @@ impl ClientRegister {
 
         let verification_cfg = GetRecordCfg {
             get_quorum: Quorum::One,
-            re_attempt: true,
+            retry_policy: self.client.retry_policy,
             target_record: record_to_verify,
             expected_holders,
         };
         let put_cfg = PutRecordCfg {
             put_quorum: Quorum::All,
-            re_attempt: true,
+            retry_policy: self.client.retry_policy,
             use_put_record_to: None,
             verification: Some((VerificationKind::Network, verification_cfg)),
         };
 
         // Register edits might exist so we cannot be sure that just because we get a record back that this should fail
-        Ok(self.client.network.put_record(record, &put_cfg).await?)
+        let _permit = self.client.acquire_op_permit().await;
+        self.client
+            .cancellable(async { Ok(self.client.network.put_record(record, &put_cfg).await?) })
+            .await
     }
 
     // Retrieve a `Register` from the Network.
@@ -419,3 +700,218 @@ impl ClientRegister {
         Ok(reg.register()?)
     }
 }
+
+/// The steps of creating a Register on the network, apart so its attempts can be retried.
+#[async_trait]
+pub(crate) trait RegisterCreation: Send {
+    /// Whether a payment for the Register is held, which can still be used to store it.
+    fn holds_valid_payment(&self) -> bool;
+
+    /// Pay for the Register, returning the storage cost and royalties paid.
+    async fn pay(&mut self) -> Result<(NanoTokens, NanoTokens)>;
+
+    /// Publish the Register along with the payment held for it.
+    async fn publish(&mut self) -> Result<()>;
+
+    /// Check the Register is stored on the network.
+    async fn verify(&self) -> Result<()>;
+}
+
+/// Creating a Register on the network, paid for from a wallet.
+pub(crate) struct NetworkRegisterCreation<'a> {
+    reg: &'a ClientRegister,
+    wallet_client: &'a mut WalletClient,
+}
+
+impl<'a> NetworkRegisterCreation<'a> {
+    pub(crate) fn new(reg: &'a ClientRegister, wallet_client: &'a mut WalletClient) -> Self {
+        Self { reg, wallet_client }
+    }
+
+    fn net_addr(&self) -> NetworkAddress {
+        NetworkAddress::from_register_address(*self.reg.address())
+    }
+}
+
+#[async_trait]
+impl RegisterCreation for NetworkRegisterCreation<'_> {
+    fn holds_valid_payment(&self) -> bool {
+        self.wallet_client.holds_valid_payment_for(&self.net_addr())
+    }
+
+    async fn pay(&mut self) -> Result<(NanoTokens, NanoTokens)> {
+        let net_addr = self.net_addr();
+        let (paid, _) = self
+            .wallet_client
+            .pay_for_storage(std::iter::once(net_addr))
+            .await?;
+        if let Err(err) = self.wallet_client.store_local_wallet() {
+            warn!("Failed to store wallet with cached payment proofs: {err:?}");
+        }
+        Ok(paid)
+    }
+
+    async fn publish(&mut self) -> Result<()> {
+        // renewing our claim, made under the same nonce
+        let claim = self.reg.claim_creation().await?;
+        let payment = self.wallet_client.get_payment_for_addr(&self.net_addr())?;
+        self.reg.publish_creation(payment, claim, false).await
+    }
+
+    async fn verify(&self) -> Result<()> {
+        let _ = self
+            .reg
+            .client
+            .verify_register_stored(*self.reg.address())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Verify a Register whose creation was attempted once is stored, attempting it again until it
+/// is, for at most `max_attempts` attempts in total.
+///
+/// The Register is only paid for again once the payment held for it can't be used anymore.
+/// Returns the storage cost and royalties paid in total, `paid` being what the first attempt
+/// cost.
+pub(crate) async fn retry_register_creation(
+    creation: &mut impl RegisterCreation,
+    max_attempts: usize,
+    paid: (NanoTokens, NanoTokens),
+) -> Result<(NanoTokens, NanoTokens)> {
+    let (mut storage_cost, mut royalties_fees) = paid;
+    let mut attempts = 1;
+    loop {
+        match creation.verify().await {
+            Ok(()) => return Ok((storage_cost, royalties_fees)),
+            Err(err) => {
+                info!("Register not completely stored on the network yet, after {attempts} attempt(s): {err:?}");
+            }
+        }
+        if attempts >= max_attempts {
+            let total_paid = storage_cost
+                .checked_add(royalties_fees)
+                .ok_or(Error::TotalPriceTooHigh)?;
+            return Err(Error::RegisterCreationVerificationFailed {
+                attempts,
+                total_paid,
+            });
+        }
+        attempts += 1;
+
+        if !creation.holds_valid_payment() {
+            let (top_up_cost, royalties_top_up) = creation.pay().await?;
+            storage_cost = storage_cost
+                .checked_add(top_up_cost)
+                .ok_or(Error::TotalPriceTooHigh)?;
+            royalties_fees = royalties_fees
+                .checked_add(royalties_top_up)
+                .ok_or(Error::TotalPriceTooHigh)?;
+        }
+        if let Err(err) = creation.publish().await {
+            warn!("Failed to publish the Register again: {err:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COST: NanoTokens = NanoTokens::from(10);
+    const ROYALTIES: NanoTokens = NanoTokens::from(1);
+
+    /// A creation which never verifies, whose payments expire after `payment_uses` publishes.
+    struct NeverStored {
+        payment_uses: usize,
+        uses_left: usize,
+        payments: usize,
+        publishes: usize,
+    }
+
+    impl NeverStored {
+        fn new(payment_uses: usize) -> Self {
+            Self {
+                payment_uses,
+                // the first attempt used the payment once already
+                uses_left: payment_uses.saturating_sub(1),
+                payments: 0,
+                publishes: 0,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RegisterCreation for NeverStored {
+        fn holds_valid_payment(&self) -> bool {
+            self.uses_left > 0
+        }
+
+        async fn pay(&mut self) -> Result<(NanoTokens, NanoTokens)> {
+            self.payments += 1;
+            self.uses_left = self.payment_uses;
+            Ok((COST, ROYALTIES))
+        }
+
+        async fn publish(&mut self) -> Result<()> {
+            self.publishes += 1;
+            self.uses_left = self.uses_left.saturating_sub(1);
+            Ok(())
+        }
+
+        async fn verify(&self) -> Result<()> {
+            Err(Error::CouldNotVerifyTransfer("never stored".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn attempts_are_bounded_and_valid_payments_reused() {
+        let mut creation = NeverStored::new(usize::MAX);
+        let result = retry_register_creation(&mut creation, 3, (COST, ROYALTIES)).await;
+
+        match result {
+            Err(Error::RegisterCreationVerificationFailed {
+                attempts,
+                total_paid,
+            }) => {
+                assert_eq!(attempts, 3);
+                assert_eq!(total_paid, NanoTokens::from(11));
+            }
+            other => panic!("Unexpected result {other:?}"),
+        }
+        assert_eq!(creation.publishes, 2);
+        assert_eq!(creation.payments, 0);
+    }
+
+    #[tokio::test]
+    async fn expired_payments_are_paid_again_at_most_once_per_attempt() {
+        let mut creation = NeverStored::new(1);
+        let result = retry_register_creation(&mut creation, 4, (COST, ROYALTIES)).await;
+
+        match result {
+            Err(Error::RegisterCreationVerificationFailed {
+                attempts,
+                total_paid,
+            }) => {
+                assert_eq!(attempts, 4);
+                assert_eq!(total_paid, NanoTokens::from(44));
+            }
+            other => panic!("Unexpected result {other:?}"),
+        }
+        assert_eq!(creation.publishes, 3);
+        assert_eq!(creation.payments, 3);
+    }
+
+    #[tokio::test]
+    async fn a_single_attempt_is_not_retried() {
+        let mut creation = NeverStored::new(1);
+        let result = retry_register_creation(&mut creation, 1, (COST, ROYALTIES)).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::RegisterCreationVerificationFailed { attempts: 1, .. })
+        ));
+        assert_eq!(creation.publishes, 0);
+        assert_eq!(creation.payments, 0);
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use serde::Serialize;
+use sn_transfers::NanoTokens;
+
+/// The fees paid to the network by a client and its clones, through the `WalletClient`s built
+/// from them, since the client connected or the costs were last reset.
+///
+/// See `Client::session_costs`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct SessionCosts {
+    /// The store costs paid to the nodes.
+    pub storage_cost: NanoTokens,
+    /// The network royalties paid on top of the store costs.
+    pub royalties: NanoTokens,
+    /// The number of payments made, each covering a batch of records.
+    pub payments: usize,
+}
+
+impl Default for SessionCosts {
+    fn default() -> Self {
+        Self {
+            storage_cost: NanoTokens::zero(),
+            royalties: NanoTokens::zero(),
+            payments: 0,
+        }
+    }
+}
+
+impl SessionCosts {
+    /// The store costs and royalties paid altogether.
+    pub fn total(&self) -> NanoTokens {
+        saturating_add(self.storage_cost, self.royalties)
+    }
+
+    /// Add a payment to the costs.
+    pub(crate) fn record_payment(&mut self, storage_cost: NanoTokens, royalties: NanoTokens) {
+        self.storage_cost = saturating_add(self.storage_cost, storage_cost);
+        self.royalties = saturating_add(self.royalties, royalties);
+        self.payments += 1;
+    }
+}
+
+fn saturating_add(lhs: NanoTokens, rhs: NanoTokens) -> NanoTokens {
+    NanoTokens::from(lhs.as_nano().saturating_add(rhs.as_nano()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payments_are_accumulated() {
+        let mut costs = SessionCosts::default();
+        assert_eq!(costs.total(), NanoTokens::zero());
+
+        costs.record_payment(NanoTokens::from(100), NanoTokens::from(15));
+        costs.record_payment(NanoTokens::from(40), NanoTokens::from(6));
+        assert_eq!(costs.storage_cost, NanoTokens::from(140));
+        assert_eq!(costs.royalties, NanoTokens::from(21));
+        assert_eq!(costs.total(), NanoTokens::from(161));
+        assert_eq!(costs.payments, 2);
+
+        costs.record_payment(NanoTokens::from(u64::MAX), NanoTokens::zero());
+        assert_eq!(costs.total(), NanoTokens::from(u64::MAX));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Error, Result};
+use bls::{PublicKey, SecretKey, Signature, PK_SIZE, SIG_SIZE};
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// The version of the envelope of the messages published with `Client::publish_signed`.
+pub const SIGNED_GOSSIP_MSG_VERSION: u8 = 1;
+
+/// The largest payload `Client::publish_signed` publishes, keeping the envelope within the size
+/// of the messages gossipsub transmits.
+pub const MAX_SIGNED_GOSSIP_PAYLOAD_SIZE: usize = 60 * 1024;
+
+/// The version byte, the public key of the publisher and its signature, ahead of the payload.
+const ENVELOPE_HEADER_SIZE: usize = 1 + PK_SIZE + SIG_SIZE;
+
+/// Wrap the payload into an envelope signed by `signer`:
+/// `version (1 byte) | public key (48 bytes) | signature (96 bytes) | payload`.
+///
+/// The signature is over the version byte, the topic the message is published on and the
+/// payload, so that the message can't be replayed on another topic.
+fn sign_gossip_msg(signer: &SecretKey, topic: &str, payload: &[u8]) -> Result<Bytes> {
+    check_payload_size(payload.len())?;
+    let signature = signer.sign(signed_bytes(SIGNED_GOSSIP_MSG_VERSION, topic, payload));
+
+    let mut envelope = BytesMut::with_capacity(ENVELOPE_HEADER_SIZE + payload.len());
+    envelope.put_u8(SIGNED_GOSSIP_MSG_VERSION);
+    envelope.put_slice(&signer.public_key().to_bytes());
+    envelope.put_slice(&signature.to_bytes());
+    envelope.put_slice(payload);
+    Ok(envelope.freeze())
+}
+
+fn verify_gossip_msg(topic: &str, msg: &[u8]) -> Result<(PublicKey, Bytes)> {
+    let (&version, rest) = msg
+        .split_first()
+        .ok_or_else(|| Error::InvalidGossipMsg("the message is empty".to_string()))?;
+    if version != SIGNED_GOSSIP_MSG_VERSION {
+        return Err(Error::UnsupportedGossipMsgVersion(version));
+    }
+    if rest.len() < PK_SIZE + SIG_SIZE {
+        return Err(Error::InvalidGossipMsg(format!(
+            "{} bytes are too few for the envelope",
+            msg.len()
+        )));
+    }
+    let (public_key, rest) = rest.split_at(PK_SIZE);
+    let (signature, payload) = rest.split_at(SIG_SIZE);
+    check_payload_size(payload.len())?;
+
+    let public_key = public_key
+        .try_into()
+        .ok()
+        .and_then(|bytes| PublicKey::from_bytes(bytes).ok())
+        .ok_or_else(|| Error::InvalidGossipMsg("invalid public key".to_string()))?;
+    let signature = signature
+        .try_into()
+        .ok()
+        .and_then(|bytes| Signature::from_bytes(bytes).ok())
+        .ok_or_else(|| Error::InvalidGossipMsg("invalid signature".to_string()))?;
+
+    if !public_key.verify(&signature, signed_bytes(version, topic, payload)) {
+        return Err(Error::InvalidGossipMsg(format!(
+            "the signature does not match the payload published on {topic}"
+        )));
+    }
+    Ok((public_key, Bytes::copy_from_slice(payload)))
+}
+
+/// `version (1 byte) | topic length (8 bytes, big endian) | topic | payload`, the length of the
+/// topic telling where it ends and the payload starts.
+fn signed_bytes(version: u8, topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(1 + 8 + topic.len() + payload.len());
+    bytes.push(version);
+    bytes.extend_from_slice(&(topic.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(topic.as_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn check_payload_size(size: usize) -> Result<()> {
+    if size > MAX_SIGNED_GOSSIP_PAYLOAD_SIZE {
+        return Err(Error::GossipMsgTooLarge {
+            size,
+            max: MAX_SIGNED_GOSSIP_PAYLOAD_SIZE,
+        });
+    }
+    Ok(())
+}
+
+impl Client {
+    /// Publish the payload on the given topic, signed by the client's key so that receivers can
+    /// tell who published it, and that it wasn't tampered with, using `Client::verify_gossip_msg`.
+    ///
+    /// Use `Client::publish_on_topic` to publish unsigned messages instead.
+    pub fn publish_signed(&self, topic_id: String, payload: Bytes) -> Result<()> {
+        let msg = sign_gossip_msg(&self.signer, &topic_id, &payload)?;
+        self.publish_on_topic(topic_id, msg)
+    }
+
+    /// Verify a message received on the given topic, as published with `Client::publish_signed`,
+    /// returning the public key of its publisher and its payload.
+    ///
+    /// A message published on another topic fails to verify.
+    pub fn verify_gossip_msg(topic: &str, msg: &[u8]) -> Result<(PublicKey, Bytes)> {
+        verify_gossip_msg(topic, msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPIC: &str = "TestTopic";
+
+    #[test]
+    fn signed_msgs_round_trip() -> Result<()> {
+        let signer = SecretKey::random();
+        for payload in [&b""[..], b"gossip", &[7; MAX_SIGNED_GOSSIP_PAYLOAD_SIZE]] {
+            let msg = sign_gossip_msg(&signer, TOPIC, payload)?;
+            assert_eq!(msg.len(), ENVELOPE_HEADER_SIZE + payload.len());
+            assert_eq!(msg[0], SIGNED_GOSSIP_MSG_VERSION);
+
+            let (publisher, verified) = verify_gossip_msg(TOPIC, &msg)?;
+            assert_eq!(publisher, signer.public_key());
+            assert_eq!(verified, payload);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_msgs_fail_to_verify() -> Result<()> {
+        let signer = SecretKey::random();
+        let msg = sign_gossip_msg(&signer, TOPIC, b"gossip")?.to_vec();
+
+        let mut tampered_payload = msg.clone();
+        *tampered_payload.last_mut().expect("a payload") ^= 1;
+        assert!(matches!(
+            verify_gossip_msg(TOPIC, &tampered_payload),
+            Err(Error::InvalidGossipMsg(_))
+        ));
+
+        // re-signed by someone else, but claiming to be the signer
+        let other = sign_gossip_msg(&SecretKey::random(), TOPIC, b"gossip")?;
+        let mut impersonated = msg.clone();
+        impersonated[1 + PK_SIZE..ENVELOPE_HEADER_SIZE]
+            .copy_from_slice(&other[1 + PK_SIZE..ENVELOPE_HEADER_SIZE]);
+        assert!(matches!(
+            verify_gossip_msg(TOPIC, &impersonated),
+            Err(Error::InvalidGossipMsg(_))
+        ));
+
+        let mut other_version = msg;
+        other_version[0] = SIGNED_GOSSIP_MSG_VERSION + 1;
+        assert!(matches!(
+            verify_gossip_msg(TOPIC, &other_version),
+            Err(Error::UnsupportedGossipMsgVersion(version)) if version == SIGNED_GOSSIP_MSG_VERSION + 1
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn msgs_fail_to_verify_on_another_topic() -> Result<()> {
+        let msg = sign_gossip_msg(&SecretKey::random(), TOPIC, b"gossip")?;
+        assert!(matches!(
+            verify_gossip_msg("AnotherTopic", &msg),
+            Err(Error::InvalidGossipMsg(_))
+        ));
+
+        // nor is the start of the payload mistaken for the end of the topic
+        let msg = sign_gossip_msg(&SecretKey::random(), "Topic", b"gossip")?;
+        let shifted = [&msg[..ENVELOPE_HEADER_SIZE], b"sip"].concat();
+        assert!(matches!(
+            verify_gossip_msg("Topicgos", &shifted),
+            Err(Error::InvalidGossipMsg(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_msgs_fail_to_verify() -> Result<()> {
+        let msg = sign_gossip_msg(&SecretKey::random(), TOPIC, b"")?;
+        for len in 0..msg.len() {
+            assert!(verify_gossip_msg(TOPIC, &msg[..len]).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_payloads_are_rejected() -> Result<()> {
+        let signer = SecretKey::random();
+        let payload = vec![7; MAX_SIGNED_GOSSIP_PAYLOAD_SIZE + 1];
+        assert!(matches!(
+            sign_gossip_msg(&signer, TOPIC, &payload),
+            Err(Error::GossipMsgTooLarge { size, .. }) if size == payload.len()
+        ));
+
+        // nor accepted from a publisher not checking the limit
+        let signature = signer.sign(signed_bytes(SIGNED_GOSSIP_MSG_VERSION, TOPIC, &payload));
+        let msg = [
+            &[SIGNED_GOSSIP_MSG_VERSION][..],
+            &signer.public_key().to_bytes(),
+            &signature.to_bytes(),
+            &payload,
+        ]
+        .concat();
+        assert!(matches!(
+            verify_gossip_msg(TOPIC, &msg),
+            Err(Error::GossipMsgTooLarge { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{error::Result, Client};
+use futures::{stream::FuturesUnordered, StreamExt};
+use libp2p::{kad::Record, PeerId};
+use rand::random;
+use sn_networking::CLOSE_GROUP_SIZE;
+use sn_protocol::{
+    messages::{Query, QueryResponse, Request, Response},
+    storage::{try_deserialize_record, Chunk, ChunkAddress, RecordHeader, RecordKind},
+    NetworkAddress,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{task::AbortHandle, time::MissedTickBehavior};
+use xor_name::XorName;
+
+/// How often the close groups of the working set are pinged, below the 30s after which idle
+/// connections are closed.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(20);
+
+/// The number of close groups of the working set refreshed at once.
+const REFRESH_CONCURRENCY: usize = 8;
+
+/// The freshness of the close group caches of a client on standby, see `Client::standby`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StandbyStats {
+    /// The number of addresses in the working set
+    pub working_set: usize,
+    /// The addresses of the working set whose close group was refreshed within the last two
+    /// refresh intervals
+    pub fresh: usize,
+    /// How long ago the least recently refreshed close group of the working set was refreshed,
+    /// `None` while some have never been
+    pub oldest_refresh: Option<Duration>,
+    /// The number of close group refreshes which succeeded
+    pub refreshes: u64,
+    /// The number of close group refreshes which failed
+    pub failed_refreshes: u64,
+    /// The number of operations served by a fresh cached close group
+    pub warm_hits: u64,
+    /// The number of lookups of the peers close to an address, by a closest peers query or by a
+    /// record query, made by the client
+    pub lookups: u64,
+    /// The number of cached close groups invalidated by churn, or flushed
+    pub invalidations: u64,
+}
+
+/// The close group of an address of the working set, as of its last refresh.
+#[derive(Clone, Debug, Default)]
+struct CloseGroup {
+    peers: Vec<PeerId>,
+    refreshed_at: Option<Instant>,
+}
+
+impl CloseGroup {
+    /// Whether the close group was refreshed recently enough to be trusted, i.e. no more than
+    /// one refresh was missed.
+    fn is_fresh(&self, now: Instant, refresh_interval: Duration) -> bool {
+        self.refreshed_at.is_some_and(|refreshed_at| {
+            now.saturating_duration_since(refreshed_at) <= refresh_interval.saturating_mul(2)
+        })
+    }
+
+    /// Whether a peer joining the network would be part of the close group of the address,
+    /// i.e. if it's closer to the address than the furthest peer of the group.
+    fn would_include(&self, address: &NetworkAddress, peer: &PeerId) -> bool {
+        if self.peers.len() < CLOSE_GROUP_SIZE {
+            return true;
+        }
+        let distance = address.distance(&NetworkAddress::from_peer(*peer));
+        self.peers
+            .iter()
+            .map(|member| address.distance(&NetworkAddress::from_peer(*member)))
+            .max()
+            .is_some_and(|furthest| distance < furthest)
+    }
+
+    /// Stop trusting the close group until its next refresh.
+    fn invalidate(&mut self) {
+        self.refreshed_at = None;
+    }
+}
+
+#[derive(Debug, Default)]
+struct StandbyState {
+    refresh_interval: Duration,
+    close_groups: HashMap<NetworkAddress, CloseGroup>,
+    refreshes: u64,
+    failed_refreshes: u64,
+    invalidations: u64,
+    // Stops the refresh task of the current working set when another one is set.
+    task: Option<AbortHandle>,
+}
+
+/// The close group caches of the working set of a client on standby.
+/// Shared by all the clones of the client, empty unless `Client::standby` is called.
+#[derive(Debug, Default)]
+pub(crate) struct StandbyCache {
+    state: Mutex<StandbyState>,
+    warm_hits: AtomicU64,
+    lookups: AtomicU64,
+}
+
+impl StandbyCache {
+    /// The fresh cached close group of the address, if it is in the working set.
+    pub(crate) fn fresh_close_group(&self, address: &NetworkAddress) -> Option<Vec<PeerId>> {
+        self.fresh_close_group_at(address, Instant::now())
+    }
+
+    /// Count an operation served by a cached close group.
+    pub(crate) fn record_warm_hit(&self) {
+        let _ = self.warm_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a lookup of the peers close to an address.
+    pub(crate) fn record_lookup(&self) {
+        let _ = self.lookups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Invalidate the cached close groups the peer was part of, as it left the network.
+    /// The departed peer is dropped from them, so it isn't kept alive anymore.
+    pub(crate) fn peer_departed(&self, peer: &PeerId) {
+        self.invalidate_where(|_, group| {
+            let was_member = group.peers.contains(peer);
+            group.peers.retain(|member| member != peer);
+            was_member
+        });
+    }
+
+    /// Invalidate the cached close groups the peer would now be part of, as it joined the
+    /// network.
+    pub(crate) fn peer_added(&self, peer: &PeerId) {
+        self.invalidate_where(|address, group| group.would_include(address, peer));
+    }
+
+    /// Invalidate all the cached close groups.
+    fn flush(&self) {
+        self.invalidate_where(|_, _| true);
+    }
+
+    /// Invalidate the cached close groups matching the predicate, counting the ones which had
+    /// been refreshed.
+    fn invalidate_where(
+        &self,
+        mut predicate: impl FnMut(&NetworkAddress, &mut CloseGroup) -> bool,
+    ) {
+        let mut state = self.lock();
+        let mut invalidated = 0;
+        for (address, group) in state.close_groups.iter_mut() {
+            if predicate(address, group) && group.refreshed_at.is_some() {
+                group.invalidate();
+                invalidated += 1;
+            }
+        }
+        if invalidated > 0 {
+            debug!("Invalidated {invalidated} cached close groups");
+            state.invalidations += invalidated;
+        }
+    }
+
+    fn stats(&self) -> StandbyStats {
+        let now = Instant::now();
+        let state = self.lock();
+        let fresh = state
+            .close_groups
+            .values()
+            .filter(|group| group.is_fresh(now, state.refresh_interval))
+            .count();
+        let oldest_refresh = state
+            .close_groups
+            .values()
+            .map(|group| {
+                group
+                    .refreshed_at
+                    .map(|at| now.saturating_duration_since(at))
+            })
+            .collect::<Option<Vec<_>>>()
+            .and_then(|ages| ages.into_iter().max());
+
+        StandbyStats {
+            working_set: state.close_groups.len(),
+            fresh,
+            oldest_refresh,
+            refreshes: state.refreshes,
+            failed_refreshes: state.failed_refreshes,
+            invalidations: state.invalidations,
+            warm_hits: self.warm_hits.load(Ordering::Relaxed),
+            lookups: self.lookups.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Replace the working set, stopping the refresh task of the previous one.
+    fn set_working_set(&self, working_set: Vec<NetworkAddress>, refresh_interval: Duration) {
+        let mut state = self.lock();
+        if let Some(task) = state.task.take() {
+            task.abort();
+        }
+        state.refresh_interval = refresh_interval;
+        state.close_groups = working_set
+            .into_iter()
+            .map(|address| (address, CloseGroup::default()))
+            .collect();
+    }
+
+    fn set_task(&self, task: AbortHandle) {
+        self.lock().task = Some(task);
+    }
+
+    fn working_set(&self) -> Vec<NetworkAddress> {
+        self.lock().close_groups.keys().cloned().collect()
+    }
+
+    /// The peers of all the cached close groups, to keep the connections to alive.
+    fn cached_peers(&self) -> Vec<(NetworkAddress, PeerId)> {
+        self.lock()
+            .close_groups
+            .iter()
+            .flat_map(|(address, group)| group.peers.iter().map(|peer| (address.clone(), *peer)))
+            .collect()
+    }
+
+    fn refreshed(&self, address: &NetworkAddress, result: Option<Vec<PeerId>>, now: Instant) {
+        let mut state = self.lock();
+        match result {
+            Some(peers) => {
+                state.refreshes += 1;
+                // the working set may have been replaced in the meantime
+                if let Some(group) = state.close_groups.get_mut(address) {
+                    group.peers = peers;
+                    group.refreshed_at = Some(now);
+                }
+            }
+            None => state.failed_refreshes += 1,
+        }
+    }
+
+    fn fresh_close_group_at(&self, address: &NetworkAddress, now: Instant) -> Option<Vec<PeerId>> {
+        let state = self.lock();
+        state
+            .close_groups
+            .get(address)
+            .filter(|group| group.is_fresh(now, state.refresh_interval))
+            .map(|group| group.peers.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, StandbyState> {
+        // a panic while holding the lock can't leave the caches in an inconsistent state
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Client {
+    /// Keep the client warm for a working set of addresses, for services whose requests on them
+    /// shouldn't wait for the network.
+    ///
+    /// The close groups of the working set are looked up before returning, then refreshed every
+    /// `refresh_interval` in the background, while the connections to them are kept alive.
+    /// Operations on an address of the working set go straight to its cached close group, as
+    /// long as it is fresh, skipping the lookup of the peers close to it. See `standby_stats`.
+    ///
+    /// Calling it again replaces the working set. The background task stops on `shutdown`.
+    pub async fn standby(
+        &self,
+        working_set: Vec<NetworkAddress>,
+        refresh_interval: Duration,
+    ) -> Result<()> {
+        info!(
+            "Standing by for {} addresses, refreshed every {refresh_interval:?}",
+            working_set.len()
+        );
+        self.standby.set_working_set(working_set, refresh_interval);
+        self.refresh_close_groups().await;
+
+        // the task doesn't share the tasks, otherwise they'd never be aborted on drop
+        let client = Client {
+            tasks: Default::default(),
+            ..self.clone()
+        };
+        let task = self.tasks.spawn_helper(async move {
+            let mut refresh = tokio::time::interval(refresh_interval);
+            refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+            keep_alive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // first ticks complete immediately, the close groups were just refreshed
+            refresh.tick().await;
+            keep_alive.tick().await;
+
+            loop {
+                tokio::select! {
+                    _ = refresh.tick() => client.refresh_close_groups().await,
+                    _ = keep_alive.tick() => client.keep_close_groups_alive().await,
+                }
+            }
+        });
+        self.standby.set_task(task);
+        Ok(())
+    }
+
+    /// The freshness of the close group caches of the working set, and how many operations
+    /// were served by them.
+    pub fn standby_stats(&self) -> StandbyStats {
+        self.standby.stats()
+    }
+
+    /// Stop trusting the cached close groups of the working set until their next refresh, so
+    /// the next operations on them look their close group up again. Meant for tests and tools,
+    /// churn invalidates the affected close groups on its own.
+    pub fn flush_close_group_cache(&self) {
+        info!("Flushing the cached close groups");
+        self.standby.flush();
+    }
+
+    /// The close group of the address, from the standby cache if it's fresh there, otherwise
+    /// looked up on the network.
+    pub(crate) async fn close_group(&self, address: &NetworkAddress) -> Result<Vec<PeerId>> {
+        if let Some(peers) = self.standby.fresh_close_group(address) {
+            trace!("Using the cached close group of {address:?}");
+            self.standby.record_warm_hit();
+            return Ok(peers);
+        }
+        self.standby.record_lookup();
+        self.get_closest_peers(address).await
+    }
+
+    /// Fetch a chunk straight from its cached close group, taking the first valid copy.
+    /// Returns `None` if none of the peers served one, for the caller to fall back to a lookup.
+    pub(crate) async fn get_chunk_from_close_group(
+        &self,
+        address: ChunkAddress,
+        close_group: &[PeerId],
+    ) -> Option<(Chunk, Vec<PeerId>)> {
+        let net_addr = NetworkAddress::from_chunk_address(address);
+        let request = Request::Query(Query::GetReplicatedRecord {
+            requester: NetworkAddress::from_peer(self.network.peer_id),
+            key: net_addr.clone(),
+        });
+        let mut responses: FuturesUnordered<_> = close_group
+            .iter()
+            .map(|peer| {
+                let request = request.clone();
+                async move { (*peer, self.network.send_request(request, *peer).await) }
+            })
+            .collect();
+
+        while let Some((peer, response)) = responses.next().await {
+            let value = match response {
+                Ok(Response::Query(QueryResponse::GetReplicatedRecord(Ok((_, value))))) => value,
+                other => {
+                    trace!("No copy of chunk {address:?} from {peer:?}: {other:?}");
+                    continue;
+                }
+            };
+            let record = Record {
+                key: net_addr.to_record_key(),
+                value: value.to_vec(),
+                publisher: None,
+                expires: None,
+            };
+            let chunk = match RecordHeader::from_record(&record) {
+                Ok(header) if header.kind == RecordKind::Chunk => {
+                    try_deserialize_record::<Chunk>(&record).ok()
+                }
+                _ => None,
+            };
+            match chunk {
+                Some(chunk) if XorName::from_content(chunk.value()) == *address.xorname() => {
+                    self.standby.record_warm_hit();
+                    return Some((chunk, vec![peer]));
+                }
+                _ => warn!("Chunk {address:?} served by {peer:?} does not match its address"),
+            }
+        }
+
+        debug!("No valid copy of chunk {address:?} from its cached close group");
+        None
+    }
+
+    async fn refresh_close_groups(&self) {
+        let working_set = self.standby.working_set();
+        debug!(
+            "Refreshing the close groups of {} addresses",
+            working_set.len()
+        );
+        let mut refreshes = futures::stream::iter(working_set)
+            .map(|address| async move {
+                self.standby.record_lookup();
+                let result = self.get_closest_peers(&address).await;
+                (address, result)
+            })
+            .buffer_unordered(REFRESH_CONCURRENCY);
+
+        while let Some((address, result)) = refreshes.next().await {
+            let peers = match result {
+                Ok(peers) => Some(peers),
+                Err(err) => {
+                    warn!("Failed to refresh the close group of {address:?}: {err}");
+                    None
+                }
+            };
+            self.standby.refreshed(&address, peers, Instant::now());
+        }
+    }
+
+    /// Send a cheap query to the peers of the cached close groups, so their connections don't
+    /// get closed for being idle.
+    async fn keep_close_groups_alive(&self) {
+        let mut pings: FuturesUnordered<_> = self
+            .standby
+            .cached_peers()
+            .into_iter()
+            .map(|(address, peer)| async move {
+                let request = Request::Query(Query::GetChunkExistenceProof {
+                    key: address,
+                    nonce: random(),
+                });
+                (peer, self.network.send_request(request, peer).await)
+            })
+            .collect();
+
+        while let Some((peer, result)) = pings.next().await {
+            if let Err(err) = result {
+                debug!("Keep alive of {peer:?} failed: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_protocol::storage::ChunkAddress;
+    use xor_name::XorName;
+
+    fn random_address() -> NetworkAddress {
+        NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::random(
+            &mut rand::thread_rng(),
+        )))
+    }
+
+    #[test]
+    fn only_fresh_close_groups_of_the_working_set_are_used() {
+        let cache = StandbyCache::default();
+        let refresh_interval = Duration::from_secs(10);
+        let warm = random_address();
+        let cold = random_address();
+        cache.set_working_set(vec![warm.clone()], refresh_interval);
+
+        let now = Instant::now();
+        // not refreshed yet
+        assert_eq!(cache.fresh_close_group_at(&warm, now), None);
+
+        let peers = vec![PeerId::random(), PeerId::random()];
+        cache.refreshed(&warm, Some(peers.clone()), now);
+        cache.refreshed(&cold, Some(vec![PeerId::random()]), now);
+        assert_eq!(cache.fresh_close_group_at(&warm, now), Some(peers.clone()));
+        assert_eq!(cache.fresh_close_group_at(&cold, now), None);
+
+        // a single missed refresh is tolerated
+        let later = now + refresh_interval * 2;
+        assert_eq!(cache.fresh_close_group_at(&warm, later), Some(peers));
+        let later = later + Duration::from_secs(1);
+        assert_eq!(cache.fresh_close_group_at(&warm, later), None);
+    }
+
+    #[test]
+    fn stats_tell_the_freshness_of_the_working_set() {
+        let cache = StandbyCache::default();
+        let first = random_address();
+        let second = random_address();
+        cache.set_working_set(vec![first.clone(), second.clone()], Duration::from_secs(60));
+
+        cache.refreshed(&first, Some(vec![PeerId::random()]), Instant::now());
+        cache.refreshed(&second, None, Instant::now());
+        cache.record_warm_hit();
+        cache.record_lookup();
+
+        let stats = cache.stats();
+        assert_eq!(stats.working_set, 2);
+        assert_eq!(stats.fresh, 1);
+        assert_eq!(stats.oldest_refresh, None);
+        assert_eq!(stats.refreshes, 1);
+        assert_eq!(stats.failed_refreshes, 1);
+        assert_eq!(stats.warm_hits, 1);
+        assert_eq!(stats.lookups, 1);
+
+        cache.refreshed(&second, Some(vec![PeerId::random()]), Instant::now());
+        let stats = cache.stats();
+        assert_eq!(stats.fresh, 2);
+        assert!(stats.oldest_refresh.is_some());
+    }
+
+    #[test]
+    fn churn_invalidates_the_affected_close_groups() {
+        let cache = StandbyCache::default();
+        let first = random_address();
+        let second = random_address();
+        cache.set_working_set(vec![first.clone(), second.clone()], Duration::from_secs(60));
+        let peers: Vec<_> = (0..CLOSE_GROUP_SIZE).map(|_| PeerId::random()).collect();
+        let departed = peers[0];
+        let now = Instant::now();
+        cache.refreshed(&first, Some(peers), now);
+        cache.refreshed(&second, Some(vec![PeerId::random()]), now);
+
+        // a peer that was part of none of them leaves
+        cache.peer_departed(&PeerId::random());
+        assert_eq!(cache.stats().invalidations, 0);
+
+        cache.peer_departed(&departed);
+        assert_eq!(cache.fresh_close_group_at(&first, now), None);
+        assert!(cache.fresh_close_group_at(&second, now).is_some());
+        assert_eq!(cache.stats().invalidations, 1);
+        assert!(!cache
+            .cached_peers()
+            .iter()
+            .any(|(_, peer)| *peer == departed));
+
+        // the second close group isn't full, any joining peer belongs to it
+        cache.peer_added(&PeerId::random());
+        assert_eq!(cache.fresh_close_group_at(&second, now), None);
+        assert_eq!(cache.stats().invalidations, 2);
+
+        cache.refreshed(&first, Some(vec![PeerId::random()]), now);
+        cache.refreshed(&second, Some(vec![PeerId::random()]), now);
+        cache.flush();
+        let stats = cache.stats();
+        assert_eq!(stats.fresh, 0);
+        assert_eq!(stats.invalidations, 4);
+    }
+
+    #[test]
+    fn only_peers_closer_than_the_furthest_member_join_a_close_group() {
+        let address = random_address();
+        let mut peers: Vec<_> = (0..CLOSE_GROUP_SIZE + 2)
+            .map(|_| PeerId::random())
+            .collect();
+        peers.sort_by_key(|peer| address.distance(&NetworkAddress::from_peer(*peer)));
+        let closer = peers.remove(0);
+        let further = peers.pop().expect("peers to not be empty");
+        let group = CloseGroup {
+            peers,
+            refreshed_at: Some(Instant::now()),
+        };
+
+        assert!(group.would_include(&address, &closer));
+        assert!(!group.would_include(&address, &further));
+    }
+}
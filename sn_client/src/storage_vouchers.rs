@@ -0,0 +1,203 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Error, Result};
+use sn_transfers::{
+    calculate_royalties_fee, LocalWallet, NanoTokens, SpendAddress, StorageVoucher,
+    StorageVoucherGrant, UniquePubkey, WalletError, NETWORK_ROYALTIES_PK,
+};
+use std::{collections::BTreeSet, path::Path, time::SystemTime};
+
+/// How much of a storage voucher was used, as seen on the Network.
+///
+/// The tokens reserved for the voucher add up to the storage and royalties paid, the tokens
+/// left unspent and those spent otherwise than with the voucher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageVoucherUsage {
+    /// The number of chunks paid for with the voucher, one network royalties payment each
+    pub chunks_paid: usize,
+    /// The tokens paid to the nodes storing the chunks
+    pub storage_paid: NanoTokens,
+    /// The tokens paid as network royalties
+    pub royalties_paid: NanoTokens,
+    /// The tokens reserved for the voucher which are yet to be spent
+    pub unspent: NanoTokens,
+    /// The tokens the holder of the voucher key spent without the voucher
+    pub spent_otherwise: NanoTokens,
+}
+
+impl StorageVoucherUsage {
+    /// The tokens paid for storage, network royalties included.
+    pub fn total_paid(&self) -> NanoTokens {
+        add(self.storage_paid, self.royalties_paid)
+    }
+}
+
+fn add(total: NanoTokens, amount: NanoTokens) -> NanoTokens {
+    NanoTokens::from(total.as_nano().saturating_add(amount.as_nano()))
+}
+
+/// Split the outputs of a payment Tx into the storage payments, as the amounts paid to the node
+/// and as network royalties for each chunk, and the change.
+///
+/// Payments add the output paying the node storing a chunk, then the network royalties output
+/// for it, whose amount is the royalties fee of the node's. A royalties output is paired with
+/// the output right before it only if that one isn't a royalties output too and the amounts
+/// match; otherwise it is counted without a node payment, and that output is left as change.
+fn storage_payments(
+    outputs: &[(UniquePubkey, NanoTokens)],
+    royalties: &BTreeSet<UniquePubkey>,
+) -> (
+    Vec<(NanoTokens, NanoTokens)>,
+    Vec<(UniquePubkey, NanoTokens)>,
+) {
+    let mut payments = vec![];
+    let mut paired = BTreeSet::new();
+    for (index, (unique_pubkey, royalties_amount)) in outputs.iter().enumerate() {
+        if !royalties.contains(unique_pubkey) {
+            continue;
+        }
+        let _ = paired.insert(index);
+        let node_output = index
+            .checked_sub(1)
+            .filter(|node_index| !paired.contains(node_index))
+            .map(|node_index| (node_index, &outputs[node_index]))
+            .filter(|(_, (node_key, node_amount))| {
+                !royalties.contains(node_key)
+                    && calculate_royalties_fee(*node_amount) == *royalties_amount
+            });
+        match node_output {
+            Some((node_index, (_, node_amount))) => {
+                let _ = paired.insert(node_index);
+                payments.push((*node_amount, *royalties_amount));
+            }
+            None => {
+                warn!("Network royalties output {unique_pubkey:?} has no node payment before it");
+                payments.push((NanoTokens::zero(), *royalties_amount));
+            }
+        }
+    }
+    let change = outputs
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !paired.contains(index))
+        .map(|(_, output)| *output)
+        .collect();
+    (payments, change)
+}
+
+impl Client {
+    /// Redeem a storage voucher grant into a wallet at `root_dir`, holding the voucher key and
+    /// the reserve, which pays for storage with the voucher.
+    ///
+    /// The reserve is verified to be spent to the voucher key on the Network first. Redeeming a
+    /// grant again loads the wallet it was redeemed into as it is.
+    pub async fn redeem_storage_voucher(
+        &self,
+        grant: &StorageVoucherGrant,
+        root_dir: &Path,
+    ) -> Result<LocalWallet> {
+        grant.verify()?;
+        if grant.voucher.is_expired_at(SystemTime::now()) {
+            return Err(WalletError::StorageVoucherExpired(grant.voucher.expiry).into());
+        }
+
+        if LocalWallet::exists_at(root_dir) {
+            let wallet = LocalWallet::try_load_from(root_dir)?;
+            if wallet.storage_voucher()?.as_ref() == Some(&grant.voucher) {
+                return Ok(wallet);
+            }
+            return Err(WalletError::InvalidStorageVoucherGrant(format!(
+                "another wallet is already at {}",
+                root_dir.display()
+            ))
+            .into());
+        }
+
+        self.verify_cashnote(&grant.reserve).await?;
+        let mut wallet = LocalWallet::create_from_key(root_dir, grant.voucher_key()?)?;
+        wallet.set_storage_voucher(&grant.voucher)?;
+        wallet.deposit_and_store_to_disk(&vec![grant.reserve.clone()])?;
+        info!(
+            "Redeemed storage voucher {:?} for {}",
+            grant.voucher.hash(),
+            grant.voucher.max_amount
+        );
+        Ok(wallet)
+    }
+
+    /// Account for the usage of a storage voucher, following the spends of its reserve, and of
+    /// the change of the payments made with it, on the Network.
+    ///
+    /// This is the only accounting of vouchers: nodes don't track how much of a voucher was used.
+    ///
+    /// Payments made with the voucher have its hash as their reason. Each chunk they pay for has
+    /// its network royalties output right after the output paying its node, the other outputs
+    /// being the change, followed in turn.
+    pub async fn storage_voucher_usage(
+        &self,
+        voucher: &StorageVoucher,
+    ) -> Result<StorageVoucherUsage> {
+        let voucher_hash = voucher.hash();
+        let mut usage = StorageVoucherUsage {
+            chunks_paid: 0,
+            storage_paid: NanoTokens::zero(),
+            royalties_paid: NanoTokens::zero(),
+            unspent: NanoTokens::zero(),
+            spent_otherwise: NanoTokens::zero(),
+        };
+
+        let mut visited_txs = BTreeSet::new();
+        let mut pending: Vec<(UniquePubkey, NanoTokens)> =
+            vec![(voucher.reserve, voucher.max_amount)];
+        while let Some((unique_pubkey, amount)) = pending.pop() {
+            let address = SpendAddress::from_unique_pubkey(&unique_pubkey);
+            let signed_spend = match self.get_spend_from_network(address).await {
+                Ok(signed_spend) => signed_spend,
+                Err(Error::MissingSpendRecord(_)) => {
+                    usage.unspent = add(usage.unspent, amount);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if signed_spend.reason() != voucher_hash {
+                debug!("Voucher {voucher_hash:?} funds at {address:?} spent without it");
+                usage.spent_otherwise = add(usage.spent_otherwise, amount);
+                continue;
+            }
+            // the other inputs of the transaction lead to it all the same
+            if !visited_txs.insert(signed_spend.spent_tx_hash()) {
+                continue;
+            }
+
+            let royalties: BTreeSet<_> = signed_spend
+                .spend
+                .network_royalties
+                .iter()
+                .map(|derivation_index| NETWORK_ROYALTIES_PK.new_unique_pubkey(derivation_index))
+                .collect();
+            let outputs: Vec<_> = signed_spend
+                .spend
+                .spent_tx
+                .outputs
+                .iter()
+                .map(|output| (output.unique_pubkey, output.amount))
+                .collect();
+            let (payments, change) = storage_payments(&outputs, &royalties);
+            for (node_amount, royalties_amount) in payments {
+                usage.chunks_paid += 1;
+                usage.storage_paid = add(usage.storage_paid, node_amount);
+                usage.royalties_paid = add(usage.royalties_paid, royalties_amount);
+            }
+            pending.extend(change);
+        }
+
+        info!("Usage of storage voucher {voucher_hash:?}: {usage:?}");
+        Ok(usage)
+    }
+}
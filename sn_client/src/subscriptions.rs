@@ -0,0 +1,345 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{Client, Result};
+use bytes::Bytes;
+use sn_networking::Network;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::sync::mpsc;
+
+/// The number of messages a `TopicSubscription` holds until they're received. Messages arriving
+/// while it's full are dropped.
+pub const TOPIC_SUBSCRIPTION_CAPACITY: usize = 256;
+
+/// The subscriptions to gossipsub topics made with `Client::subscribe` and
+/// `Client::subscribe_to_topic`, shared between the clones of the client. A topic stays
+/// subscribed to on the network as long as either holds a subscription to it.
+#[derive(Debug, Default)]
+pub(crate) struct TopicSubscriptions {
+    inner: Mutex<Subscribers>,
+}
+
+#[derive(Debug, Default)]
+struct Subscribers {
+    topics: HashMap<String, BTreeMap<u64, mpsc::Sender<Bytes>>>,
+    /// The number of `Client::subscribe_to_topic` calls not yet undone per topic, the messages
+    /// of those being broadcast as `ClientEvent::GossipsubMsg` only
+    unscoped: HashMap<String, usize>,
+    next_id: u64,
+}
+
+impl Subscribers {
+    fn is_subscribed(&self, topic: &str) -> bool {
+        self.topics.contains_key(topic) || self.unscoped.contains_key(topic)
+    }
+}
+
+impl TopicSubscriptions {
+    /// Add a subscription to the topic, returning its id and receiver. The first subscription to
+    /// the topic calls `subscribe`, under the same lock as `remove`, so that subscribing and
+    /// unsubscribing never go out of order.
+    fn add(
+        &self,
+        topic: &str,
+        subscribe: impl FnOnce() -> Result<()>,
+    ) -> Result<(u64, mpsc::Receiver<Bytes>)> {
+        let mut inner = self.lock();
+        if !inner.is_subscribed(topic) {
+            subscribe()?;
+        }
+        inner.next_id += 1;
+        let id = inner.next_id;
+        let (sender, receiver) = mpsc::channel(TOPIC_SUBSCRIPTION_CAPACITY);
+        let _ = inner
+            .topics
+            .entry(topic.to_string())
+            .or_default()
+            .insert(id, sender);
+        Ok((id, receiver))
+    }
+
+    /// Remove a subscription to the topic, calling `unsubscribe` if it was the last one.
+    fn remove(&self, topic: &str, id: u64, unsubscribe: impl FnOnce()) {
+        let mut inner = self.lock();
+        let Some(senders) = inner.topics.get_mut(topic) else {
+            return;
+        };
+        let _ = senders.remove(&id);
+        if senders.is_empty() {
+            let _ = inner.topics.remove(topic);
+            if !inner.is_subscribed(topic) {
+                unsubscribe();
+            }
+        }
+    }
+
+    /// Count a `Client::subscribe_to_topic` call, calling `subscribe` if the topic isn't
+    /// subscribed to yet.
+    fn add_unscoped(&self, topic: &str, subscribe: impl FnOnce() -> Result<()>) -> Result<()> {
+        let mut inner = self.lock();
+        if !inner.is_subscribed(topic) {
+            subscribe()?;
+        }
+        *inner.unscoped.entry(topic.to_string()).or_default() += 1;
+        Ok(())
+    }
+
+    /// Undo a `Client::subscribe_to_topic` call, calling `unsubscribe` unless the topic is still
+    /// subscribed to otherwise.
+    fn remove_unscoped(&self, topic: &str, unsubscribe: impl FnOnce() -> Result<()>) -> Result<()> {
+        let mut inner = self.lock();
+        if let Some(count) = inner.unscoped.get_mut(topic) {
+            *count -= 1;
+            if *count == 0 {
+                let _ = inner.unscoped.remove(topic);
+            }
+        }
+        if inner.is_subscribed(topic) {
+            debug!("Topic {topic} still subscribed to, not unsubscribing from it");
+            return Ok(());
+        }
+        unsubscribe()
+    }
+
+    /// Hand a message over to the subscriptions to its topic.
+    pub(crate) fn dispatch(&self, topic: &str, msg: &Bytes) {
+        let inner = self.lock();
+        let Some(senders) = inner.topics.get(topic) else {
+            return;
+        };
+        for sender in senders.values() {
+            if let Err(mpsc::error::TrySendError::Full(_)) = sender.try_send(msg.clone()) {
+                warn!("A subscription to topic {topic} is full, dropping a message");
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Subscribers> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A subscription to a gossipsub topic, receiving only the messages of that topic.
+///
+/// Several subscriptions to the same topic can be held at once, each receiving all of its
+/// messages. The topic is unsubscribed from once the last of them is dropped.
+pub struct TopicSubscription {
+    topic: String,
+    id: u64,
+    receiver: mpsc::Receiver<Bytes>,
+    subscriptions: Arc<TopicSubscriptions>,
+    network: Network,
+}
+
+impl TopicSubscription {
+    /// The topic subscribed to.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Wait for the next message of the topic.
+    pub async fn recv(&mut self) -> Option<Bytes> {
+        self.receiver.recv().await
+    }
+
+    /// Receive the next message of the topic if one arrived already.
+    pub fn try_recv(&mut self) -> Option<Bytes> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Drop for TopicSubscription {
+    fn drop(&mut self) {
+        let (topic, network) = (&self.topic, &self.network);
+        self.subscriptions.remove(topic, self.id, || {
+            info!("Unsubscribing from topic id: {topic}");
+            if let Err(err) = network.unsubscribe_from_topic(topic.clone()) {
+                warn!("Failed to unsubscribe from topic {topic}: {err:?}");
+            }
+        });
+    }
+}
+
+impl Client {
+    /// Subscribe to the given gossipsub topic, returning a subscription that receives only the
+    /// messages of that topic.
+    ///
+    /// The messages keep being broadcast as `ClientEvent::GossipsubMsg` as well.
+    pub fn subscribe(&self, topic: impl Into<String>) -> Result<TopicSubscription> {
+        let topic = topic.into();
+        let (id, receiver) = self
+            .topic_subscriptions
+            .add(&topic, || self.subscribe_on_network(&topic))?;
+        Ok(TopicSubscription {
+            topic,
+            id,
+            receiver,
+            subscriptions: self.topic_subscriptions.clone(),
+            network: self.network.clone(),
+        })
+    }
+
+    /// Subscribe to given gossipsub topic
+    ///
+    /// Its messages are broadcast as `ClientEvent::GossipsubMsg`.
+    ///
+    /// The calls are reference counted per topic, across the clones of the client: only the
+    /// first one subscribes on the network, and each has to be undone by its own
+    /// `unsubscribe_from_topic` call. The topic stays subscribed to on the network until all of
+    /// them were undone and the `TopicSubscription`s to it are dropped, see `subscribe`.
+    pub fn subscribe_to_topic(&self, topic_id: String) -> Result<()> {
+        self.topic_subscriptions
+            .add_unscoped(&topic_id, || self.subscribe_on_network(&topic_id))
+    }
+
+    /// Unsubscribe from given gossipsub topic, see `subscribe_to_topic`
+    ///
+    /// This undoes a single `subscribe_to_topic` call: the topic is only unsubscribed from on the
+    /// network once no other call is left to undo and no `TopicSubscription` to it is held.
+    /// Called with no `subscribe_to_topic` call left to undo, it unsubscribes from the topic
+    /// unless a `TopicSubscription` holds it.
+    pub fn unsubscribe_from_topic(&self, topic_id: String) -> Result<()> {
+        self.topic_subscriptions.remove_unscoped(&topic_id, || {
+            info!("Unsubscribing from topic id: {topic_id}");
+            self.network.unsubscribe_from_topic(topic_id.clone())?;
+            Ok(())
+        })
+    }
+
+    fn subscribe_on_network(&self, topic_id: &str) -> Result<()> {
+        info!("Subscribing to topic id: {topic_id}");
+        self.network.subscribe_to_topic(topic_id.to_string())?;
+        self.network.start_handle_gossip()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn messages_are_dispatched_to_the_subscriptions_to_their_topic() -> Result<()> {
+        let subscriptions = TopicSubscriptions::default();
+        let subscribed = Cell::new(0);
+        let subscribe = || {
+            subscribed.set(subscribed.get() + 1);
+            Ok(())
+        };
+        let (first_id, mut first) = subscriptions.add("topic", subscribe)?;
+        let (_, mut second) = subscriptions.add("topic", subscribe)?;
+        let (_, mut other) = subscriptions.add("other topic", subscribe)?;
+        assert_eq!(subscribed.get(), 2, "subscribed once per topic");
+
+        subscriptions.dispatch("topic", &Bytes::from_static(b"msg"));
+        assert_eq!(first.try_recv().ok(), Some(Bytes::from_static(b"msg")));
+        assert_eq!(second.try_recv().ok(), Some(Bytes::from_static(b"msg")));
+        assert!(other.try_recv().is_err());
+
+        subscriptions.remove("topic", first_id, || {
+            panic!("Unsubscribed while subscribed")
+        });
+        subscriptions.dispatch("topic", &Bytes::from_static(b"next"));
+        assert_eq!(second.try_recv().ok(), Some(Bytes::from_static(b"next")));
+        Ok(())
+    }
+
+    #[test]
+    fn the_last_subscription_removed_unsubscribes() -> Result<()> {
+        let subscriptions = TopicSubscriptions::default();
+        let (first_id, _first) = subscriptions.add("topic", || Ok(()))?;
+        let (second_id, _second) = subscriptions.add("topic", || Ok(()))?;
+
+        let unsubscribed = Cell::new(false);
+        subscriptions.remove("topic", first_id, || unsubscribed.set(true));
+        assert!(!unsubscribed.get());
+        subscriptions.remove("topic", second_id, || unsubscribed.set(true));
+        assert!(unsubscribed.get());
+
+        // subscribing again subscribes to the topic again
+        let subscribed = Cell::new(false);
+        let _third = subscriptions.add("topic", || {
+            subscribed.set(true);
+            Ok(())
+        })?;
+        assert!(subscribed.get());
+        Ok(())
+    }
+
+    #[test]
+    fn failing_to_subscribe_adds_no_subscription() {
+        let subscriptions = TopicSubscriptions::default();
+        let result = subscriptions.add("topic", || Err(crate::Error::CouldNotSendFilesEvent));
+        assert!(result.is_err());
+        subscriptions.dispatch("topic", &Bytes::from_static(b"msg"));
+        assert!(subscriptions.lock().topics.is_empty());
+    }
+
+    #[test]
+    fn full_subscriptions_drop_messages() -> Result<()> {
+        let subscriptions = TopicSubscriptions::default();
+        let (_, mut receiver) = subscriptions.add("topic", || Ok(()))?;
+        for _ in 0..TOPIC_SUBSCRIPTION_CAPACITY + 1 {
+            subscriptions.dispatch("topic", &Bytes::from_static(b"msg"));
+        }
+        let mut received = 0;
+        while receiver.try_recv().is_ok() {
+            received += 1;
+        }
+        assert_eq!(received, TOPIC_SUBSCRIPTION_CAPACITY);
+        Ok(())
+    }
+
+    #[test]
+    fn subscribe_to_topic_and_subscriptions_keep_each_other_subscribed() -> Result<()> {
+        let subscriptions = TopicSubscriptions::default();
+        let subscribed = Cell::new(0);
+        let subscribe = || {
+            subscribed.set(subscribed.get() + 1);
+            Ok(())
+        };
+        let unsubscribed = Cell::new(0);
+        let unsubscribe = || {
+            unsubscribed.set(unsubscribed.get() + 1);
+            Ok(())
+        };
+
+        subscriptions.add_unscoped("topic", subscribe)?;
+        let (id, _subscription) = subscriptions.add("topic", subscribe)?;
+        assert_eq!(subscribed.get(), 1);
+
+        // dropping the subscription leaves the topic subscribed with `subscribe_to_topic`
+        subscriptions.remove("topic", id, || panic!("Unsubscribed while subscribed"));
+        let (id, _subscription) = subscriptions.add("topic", subscribe)?;
+        assert_eq!(subscribed.get(), 1);
+
+        // and undoing `subscribe_to_topic` leaves it subscribed for the subscription
+        subscriptions.remove_unscoped("topic", unsubscribe)?;
+        assert_eq!(unsubscribed.get(), 0);
+
+        subscriptions.remove("topic", id, || unsubscribed.set(unsubscribed.get() + 1));
+        assert_eq!(unsubscribed.get(), 1);
+
+        // each `subscribe_to_topic` call is undone by its own `unsubscribe_from_topic`
+        subscriptions.add_unscoped("topic", subscribe)?;
+        subscriptions.add_unscoped("topic", subscribe)?;
+        assert_eq!(subscribed.get(), 2);
+        subscriptions.remove_unscoped("topic", unsubscribe)?;
+        assert_eq!(unsubscribed.get(), 1);
+        subscriptions.remove_unscoped("topic", unsubscribe)?;
+        assert_eq!(unsubscribed.get(), 2);
+        assert!(!subscriptions.lock().is_subscribed("topic"));
+        Ok(())
+    }
+}
@@ -6,36 +6,73 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::Error;
+use crate::{payment_store::is_quote_expired, Error};
 
-use super::{error::Result, Client};
+use super::{
+    error::Result, AuditedPayment, Client, ClientEvent, PaymentAuditReport, PaymentAuditStatus,
+    PaymentStore, QuotePolicy, DEFAULT_QUOTE_VALIDITY,
+};
 use backoff::{backoff::Backoff, ExponentialBackoff};
-use futures::{future::join_all, TryFutureExt};
+use futures::{future::join_all, stream, StreamExt, TryFutureExt};
 use libp2p::PeerId;
 use sn_networking::GetRecordError;
-use sn_protocol::NetworkAddress;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
 use sn_transfers::{
     CashNote, LocalWallet, MainPubkey, NanoTokens, Payment, PaymentQuote, SignedSpend,
-    SpendAddress, Transfer, UniquePubkey, WalletError, WalletResult,
+    SpendAddress, StorageVoucherGrant, Transfer, UniquePubkey, WalletError, WalletResult,
+    ROYALTY_RATE,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
     iter::Iterator,
-    time::Duration,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    task::{spawn_blocking, JoinSet},
+    time::sleep,
 };
-use tokio::{task::JoinSet, time::sleep};
 use xor_name::XorName;
+
+/// The number of paid for addresses probed concurrently when auditing the payments.
+const PAYMENT_AUDIT_CONCURRENCY: usize = 32;
+
+/// The storage cost and royalties paid, then the payee of each content and the skipped contents.
+pub(crate) type StoragePayment = (
+    (NanoTokens, NanoTokens),
+    (Vec<(XorName, PeerId)>, Vec<XorName>),
+);
+
 /// A wallet client can be used to send and
 /// receive tokens to/from other wallets.
 pub struct WalletClient {
     client: Client,
     wallet: LocalWallet,
+    payment_store: Option<Arc<dyn PaymentStore>>,
+    quote_policy: QuotePolicy,
 }
 
 impl WalletClient {
     /// Create a new wallet client.
     pub fn new(client: Client, wallet: LocalWallet) -> Self {
-        Self { client, wallet }
+        Self {
+            client,
+            wallet,
+            payment_store: None,
+            quote_policy: QuotePolicy::default(),
+        }
+    }
+
+    /// Share payments through the given store, on top of the wallet's own payment cache.
+    /// Content already paid for in the store won't be quoted nor paid for again.
+    pub fn set_payment_store(&mut self, payment_store: Arc<dyn PaymentStore>) {
+        self.payment_store = Some(payment_store);
+    }
+
+    /// Pick the payees of the content paid for as set by the given policy, rather than refusing
+    /// the quotes over `DEFAULT_MAX_QUOTE_MEDIAN_MULTIPLE` times the median quote.
+    pub fn set_quote_policy(&mut self, quote_policy: QuotePolicy) {
+        self.quote_policy = quote_policy;
     }
 
     /// Stores the wallet to disk.
@@ -74,6 +111,18 @@ impl WalletClient {
         }
     }
 
+    /// Whether the wallet holds a payment for the address whose quote hasn't expired yet, so it
+    /// can still be used to store the content.
+    pub fn holds_valid_payment_for(&self, address: &NetworkAddress) -> bool {
+        address
+            .as_xorname()
+            .and_then(|xorname| self.wallet.get_cached_payment_for_xorname(&xorname))
+            .map(|payment| {
+                !is_quote_expired(&payment.quote, DEFAULT_QUOTE_VALIDITY, SystemTime::now())
+            })
+            .unwrap_or(false)
+    }
+
     /// Remove CashNote from available_cash_notes
     pub fn mark_note_as_spent(&mut self, cash_note_key: UniquePubkey) {
         self.wallet.mark_note_as_spent(cash_note_key);
@@ -119,17 +168,72 @@ impl WalletClient {
         }
     }
 
+    /// Issue a storage voucher for someone else to pay for storage on our behalf, reserving
+    /// `max_amount` of our tokens for it. See `LocalWallet::issue_storage_voucher`.
+    ///
+    /// The spends of the reserve are sent to the network, so that the returned grant can be
+    /// redeemed by the uploader with `Client::redeem_storage_voucher`.
+    pub async fn issue_storage_voucher(
+        &mut self,
+        max_amount: NanoTokens,
+        expiry: SystemTime,
+        address_prefix: Option<Vec<u8>>,
+        verify_store: bool,
+    ) -> WalletResult<StorageVoucherGrant> {
+        let grant = self
+            .wallet
+            .issue_storage_voucher(max_amount, expiry, address_prefix)?;
+
+        if let Err(error) = self
+            .client
+            .send_spends(
+                self.wallet.unconfirmed_spend_requests().iter(),
+                verify_store,
+            )
+            .await
+        {
+            return Err(WalletError::CouldNotSendMoney(format!(
+                "The reserve of the voucher was not successfully registered in the network: {error:?}"
+            )));
+        }
+        self.wallet.clear_confirmed_spend_requests();
+
+        Ok(grant)
+    }
+
     /// Get storecost from the network
-    /// Returns the MainPubkey of the node to pay and the price in NanoTokens
+    /// Returns the MainPubkey of the node to pay and the price in NanoTokens,
+    /// the payee being picked as set by the `QuotePolicy`.
     pub async fn get_store_cost_at_address(
         &self,
         address: NetworkAddress,
     ) -> WalletResult<(PeerId, MainPubkey, PaymentQuote)> {
-        self.client
+        Self::get_store_cost(&self.client, self.quote_policy, address).await
+    }
+
+    async fn get_store_cost(
+        client: &Client,
+        quote_policy: QuotePolicy,
+        address: NetworkAddress,
+    ) -> WalletResult<(PeerId, MainPubkey, PaymentQuote)> {
+        let quotes = client
             .network
-            .get_store_costs_from_network(address)
+            .get_store_cost_quotes(address.clone())
             .await
-            .map_err(|error| WalletError::CouldNotSendMoney(error.to_string()))
+            .map_err(|error| WalletError::CouldNotSendMoney(error.to_string()))?;
+        let (peer_id, main_pubkey, quote) = quote_policy.select_payee(&address, quotes)?;
+        // don't pay at a rate the node would reject
+        if quote.cost != NanoTokens::zero() && quote.royalty_rate != ROYALTY_RATE {
+            warn!(
+                "{peer_id:?} quoted {address:?} for royalties at {:?} rather than {ROYALTY_RATE:?}",
+                quote.royalty_rate
+            );
+            return Err(WalletError::RoyaltyRateMismatch {
+                ours: ROYALTY_RATE,
+                theirs: quote.royalty_rate,
+            });
+        }
+        Ok((peer_id, main_pubkey, quote))
     }
 
     /// Send tokens to nodes closest to the data we want to make storage payment for.
@@ -150,6 +254,16 @@ impl WalletClient {
         (NanoTokens, NanoTokens),
         (Vec<(XorName, PeerId)>, Vec<XorName>),
     )> {
+        let (cost, _paid) = self.pay_for_storage_listing_paid(content_addrs).await?;
+        Ok(cost)
+    }
+
+    /// Like `pay_for_storage`, also returning the contents actually paid for by this call,
+    /// i.e. leaving out the skipped ones and those whose payment was taken from the payment store.
+    pub(crate) async fn pay_for_storage_listing_paid(
+        &mut self,
+        content_addrs: impl Iterator<Item = NetworkAddress>,
+    ) -> WalletResult<(StoragePayment, Vec<XorName>)> {
         let verify_store = true;
         let c: Vec<_> = content_addrs.collect();
         // Using default ExponentialBackoff doesn't make sense,
@@ -182,20 +296,21 @@ impl WalletClient {
         &mut self,
         content_addrs: impl Iterator<Item = NetworkAddress>,
         verify_store: bool,
-    ) -> WalletResult<(
-        (NanoTokens, NanoTokens),
-        (Vec<(XorName, PeerId)>, Vec<XorName>),
-    )> {
+    ) -> WalletResult<(StoragePayment, Vec<XorName>)> {
+        let mut payee_map = vec![];
+
         // get store cost from network in parrallel
         let mut tasks = JoinSet::new();
         for content_addr in content_addrs {
+            if let Some((xorname, payee)) = self.use_stored_payment(&content_addr).await? {
+                payee_map.push((xorname, payee));
+                continue;
+            }
+
             let client = self.client.clone();
+            let quote_policy = self.quote_policy;
             tasks.spawn(async move {
-                let cost = client
-                    .network
-                    .get_store_costs_from_network(content_addr.clone())
-                    .await
-                    .map_err(|error| WalletError::CouldNotSendMoney(error.to_string()));
+                let cost = Self::get_store_cost(&client, quote_policy, content_addr.clone()).await;
 
                 debug!("Storecosts retrieved for {content_addr:?} {cost:?}");
                 (content_addr, cost)
@@ -206,7 +321,6 @@ impl WalletClient {
         // collect store costs
         let mut cost_map = BTreeMap::default();
         let mut skipped_chunks = vec![];
-        let mut payee_map = vec![];
         while let Some(res) = tasks.join_next().await {
             match res {
                 Ok((content_addr, Ok(cost))) => {
@@ -236,11 +350,85 @@ impl WalletClient {
         }
         info!("Storecosts retrieved");
 
+        // nothing left to pay for, don't touch the wallet
+        if cost_map.is_empty() {
+            return Ok((
+                (
+                    (NanoTokens::zero(), NanoTokens::zero()),
+                    (payee_map, skipped_chunks),
+                ),
+                vec![],
+            ));
+        }
+
         // pay for records
-        Ok((
-            self.pay_for_records(&cost_map, verify_store).await?,
-            (payee_map, skipped_chunks),
-        ))
+        let total_cost = self.pay_for_records(&cost_map, verify_store).await?;
+        self.share_payments(cost_map.keys(), &payee_map).await;
+
+        let paid = cost_map.into_keys().collect();
+        Ok(((total_cost, (payee_map, skipped_chunks)), paid))
+    }
+
+    /// If the payment store holds a payment for this content, copy it into our wallet
+    /// and return the payee to upload to, so the content isn't paid for again.
+    ///
+    /// The store is read on a blocking thread, as it may wait on a lock held by another process.
+    async fn use_stored_payment(
+        &mut self,
+        content_addr: &NetworkAddress,
+    ) -> WalletResult<Option<(XorName, PeerId)>> {
+        let (Some(store), Some(xorname)) = (&self.payment_store, content_addr.as_xorname()) else {
+            return Ok(None);
+        };
+
+        let store = store.clone();
+        let stored = spawn_blocking(move || store.get_payment(&xorname))
+            .await
+            .map_err(|err| {
+                WalletError::CouldNotSendMoney(format!("Payment store task failed: {err:?}"))
+            })??;
+        match stored {
+            Some((payee, payment)) => {
+                debug!("Payment for {content_addr:?} found in payment store");
+                self.wallet.insert_payment_transaction(xorname, payment);
+                Ok(Some((xorname, payee)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Write the payments just made into the payment store, if any, on a blocking thread.
+    /// Failing to do so only means others may pay for the same content again.
+    async fn share_payments<'a>(
+        &self,
+        paid: impl Iterator<Item = &'a XorName>,
+        payee_map: &[(XorName, PeerId)],
+    ) {
+        let Some(store) = &self.payment_store else {
+            return;
+        };
+
+        let payments: Vec<_> = paid
+            .filter_map(|xorname| {
+                let payee = payee_map
+                    .iter()
+                    .find(|(name, _)| name == xorname)
+                    .map(|(_, payee)| *payee)?;
+                let payment = self.wallet.get_cached_payment_for_xorname(xorname)?;
+                Some((*xorname, payee, payment.clone()))
+            })
+            .collect();
+        let store = store.clone();
+        let sharing = spawn_blocking(move || {
+            for (xorname, payee, payment) in payments {
+                if let Err(err) = store.insert_payment(xorname, payee, &payment) {
+                    warn!("Failed to write payment for {xorname:?} to payment store: {err:?}");
+                }
+            }
+        });
+        if let Err(err) = sharing.await {
+            warn!("Failed to write payments to payment store: {err:?}");
+        }
     }
 
     /// Send tokens to nodes closest to the data we want to make storage payment for.
@@ -300,6 +488,14 @@ impl WalletClient {
             self.wallet.clear_confirmed_spend_requests();
         }
 
+        let (storage_cost, royalties) = total_cost;
+        let session = self.client.record_session_payment(storage_cost, royalties);
+        self.client.events_channel.notify(ClientEvent::PaymentMade {
+            total: storage_cost,
+            royalties,
+            session,
+        });
+
         Ok(total_cost)
     }
 
@@ -323,6 +519,52 @@ impl WalletClient {
         }
     }
 
+    /// Check whether the content paid for with this wallet made it to the network.
+    ///
+    /// Interrupted uploads leave payments for content that was never stored. Those whose quote
+    /// is still valid can be uploaded again without paying again, see `FilesApi::repair_chunks`,
+    /// the others are lost. Payments are probed with a store cost query, which nodes answer as
+    /// already stored for chunks and registers alike. Those whose probe failed are reported as
+    /// `PaymentAuditStatus::Unknown`, to be audited again.
+    pub async fn audit_payments(&self) -> WalletResult<PaymentAuditReport> {
+        let now = SystemTime::now();
+        let client = &self.client;
+        let mut probes = stream::iter(self.wallet.cached_payments())
+            .map(|(name, payment)| async move {
+                let stored = Self::is_stored(client, *name).await;
+                (*name, payment, stored)
+            })
+            .buffer_unordered(PAYMENT_AUDIT_CONCURRENCY);
+
+        let mut payments = vec![];
+        while let Some((name, payment, stored)) = probes.next().await {
+            let status = match stored {
+                Ok(stored) => {
+                    PaymentAuditStatus::classify(stored, payment, DEFAULT_QUOTE_VALIDITY, now)
+                }
+                Err(err) => {
+                    warn!("Could not check whether {name:?} is stored: {err}");
+                    PaymentAuditStatus::Unknown
+                }
+            };
+            debug!("Payment for {name:?} audited as {status:?}");
+            payments.push(AuditedPayment::new(name, payment, status));
+        }
+        payments.sort_by_key(|payment| payment.name);
+        Ok(PaymentAuditReport { payments })
+    }
+
+    async fn is_stored(client: &Client, name: XorName) -> WalletResult<bool> {
+        let address = NetworkAddress::ChunkAddress(ChunkAddress::new(name));
+        let quotes = client
+            .network
+            .get_store_cost_quotes(address)
+            .await
+            .map_err(|err| WalletError::CouldNotVerifyTransfer(err.to_string()))?;
+        // existing records are quoted for free by a single node
+        Ok(matches!(quotes.as_slice(), [(_, _, quote)] if quote.cost.is_zero()))
+    }
+
     /// Return the wallet.
     pub fn into_wallet(self) -> LocalWallet {
         self.wallet
@@ -337,43 +579,76 @@ impl WalletClient {
 impl Client {
     /// Send spend requests to the network.
     /// This can optionally verify the spends have been correctly stored before returning
+    ///
+    /// Spends whose parent spends can't be found on the network are refused before being sent.
     pub async fn send_spends(
         &self,
         spend_requests: impl Iterator<Item = &SignedSpend>,
         verify_store: bool,
     ) -> WalletResult<()> {
-        let mut tasks = Vec::new();
-
-        for spend_request in spend_requests {
-            debug!(
-                "sending spend request to the network: {:?}: {spend_request:#?}",
-                spend_request.unique_pubkey()
-            );
-
-            let the_task = async move {
-                let cash_note_key = spend_request.unique_pubkey();
-                let result = self
-                    .network_store_spend(spend_request.clone(), verify_store)
-                    .await;
+        self.send_spends_inner(spend_requests, verify_store, false)
+            .await
+    }
 
-                (cash_note_key, result)
-            };
-            tasks.push(the_task);
-        }
+    /// Send spend requests to the network, without checking their parent spends exist.
+    /// Only meant for recovery scenarios, nodes will still reject spends with invalid parents.
+    pub async fn force_send_spends(
+        &self,
+        spend_requests: impl Iterator<Item = &SignedSpend>,
+        verify_store: bool,
+    ) -> WalletResult<()> {
+        self.send_spends_inner(spend_requests, verify_store, true)
+            .await
+    }
 
+    async fn send_spends_inner(
+        &self,
+        spend_requests: impl Iterator<Item = &SignedSpend>,
+        verify_store: bool,
+        force: bool,
+    ) -> WalletResult<()> {
         let mut spent_cash_notes = BTreeSet::default();
-        for (cash_note_key, spend_attempt_result) in join_all(tasks).await {
-            // This is a record mismatch on spend, we need to clean up and remove the spent CashNote from the wallet
-            // This only happens if we're verifying the store
-            if let Err(Error::Network(sn_networking::Error::GetRecordError(
-                GetRecordError::RecordDoesNotMatch(record_key),
-            ))) = spend_attempt_result
-            {
-                warn!("Record mismatch on spend, removing CashNote from wallet: {record_key:?}");
-                spent_cash_notes.insert(*cash_note_key);
-            } else {
-                return spend_attempt_result
-                    .map_err(|err| WalletError::CouldNotSendMoney(err.to_string()));
+        // the spends of a transaction spending the change of another one in the batch can only
+        // be accepted once those of the other one are stored
+        for wave in spend_waves(spend_requests) {
+            let mut tasks = Vec::new();
+            for spend_request in wave {
+                debug!(
+                    "sending spend request to the network: {:?}: {spend_request:#?}",
+                    spend_request.unique_pubkey()
+                );
+
+                let the_task = async move {
+                    let cash_note_key = spend_request.unique_pubkey();
+                    let result = self
+                        .network_store_spend(spend_request.clone(), verify_store, force)
+                        .await;
+
+                    (cash_note_key, result)
+                };
+                tasks.push(the_task);
+            }
+
+            for (cash_note_key, spend_attempt_result) in join_all(tasks).await {
+                // This is a record mismatch on spend, we need to clean up and remove the spent CashNote from the wallet
+                // This only happens if we're verifying the store
+                let is_record_mismatch = matches!(
+                    &spend_attempt_result,
+                    Err(Error::Network(err)) if matches!(
+                        err.last_cause(),
+                        sn_networking::Error::GetRecordError(GetRecordError::RecordDoesNotMatch(_))
+                    )
+                );
+                if is_record_mismatch {
+                    warn!("Record mismatch on spend, removing CashNote from wallet: {cash_note_key:?}");
+                    spent_cash_notes.insert(*cash_note_key);
+                } else {
+                    spend_attempt_result
+                        .map_err(|err| WalletError::CouldNotSendMoney(err.to_string()))?;
+                }
+            }
+            if !spent_cash_notes.is_empty() {
+                break;
             }
         }
 
@@ -387,6 +662,14 @@ impl Client {
     }
 
     /// Receive a Transfer, verify and redeem CashNotes from the Network.
+    ///
+    /// The transfer is decrypted with the wallet's key, and the parent spends of the CashNotes
+    /// it redeems fetched from the Network. Those spends, and the spends of the inputs of their
+    /// transactions, must be stored and valid, and the transactions must have outputs to the
+    /// wallet, for the CashNotes to be returned.
+    ///
+    /// The CashNotes are not deposited to the wallet, that's up to the caller, e.g. with
+    /// `LocalWallet::deposit_and_store_to_disk`.
     pub async fn receive(
         &self,
         transfer: &Transfer,
@@ -400,6 +683,17 @@ impl Client {
         Ok(cashnotes)
     }
 
+    /// Receive a Transfer from its hex encoding, as given by `Transfer::to_hex`, verify and
+    /// redeem CashNotes from the Network. See `Client::receive`.
+    pub async fn receive_from_hex(
+        &self,
+        transfer_hex: &str,
+        wallet: &LocalWallet,
+    ) -> WalletResult<Vec<CashNote>> {
+        let transfer = Transfer::from_hex(transfer_hex.trim())?;
+        self.receive(&transfer, wallet).await
+    }
+
     /// Verify that the spends refered to in the CashNote exist on the network.
     pub async fn verify_cashnote(&self, cash_note: &CashNote) -> WalletResult<()> {
         // We need to get all the spends in the cash_note from the network,
@@ -433,6 +727,27 @@ impl Client {
     }
 }
 
+/// Group the spends in waves, the spends of each wave only spending cash notes created by
+/// transactions of earlier waves, or outside of the given spends.
+fn spend_waves<'a>(spends: impl Iterator<Item = &'a SignedSpend>) -> Vec<Vec<&'a SignedSpend>> {
+    let mut pending: Vec<_> = spends.collect();
+    let mut waves = vec![];
+    while !pending.is_empty() {
+        let pending_txs: BTreeSet<_> = pending.iter().map(|spend| spend.spent_tx_hash()).collect();
+        let (ready, blocked): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|spend| !pending_txs.contains(&spend.parent_tx_hash()));
+        if ready.is_empty() {
+            // can't be, as a transaction can't spend its own outputs
+            waves.push(blocked);
+            break;
+        }
+        waves.push(ready);
+        pending = blocked;
+    }
+    waves
+}
+
 /// Use the client to send a CashNote from a local wallet to an address.
 /// This marks the spent CashNote as spent in the Network
 pub async fn send(
@@ -509,3 +824,63 @@ pub async fn send(
 
     Ok(new_cash_note)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{
+        create_first_cash_note_from_key, create_offline_transfer, DerivationIndex, Hash,
+        MainSecretKey,
+    };
+
+    /// Send the whole cash note back to its owner, returning the spend made and the cash note
+    /// created.
+    fn send_to_self(
+        key: &MainSecretKey,
+        cash_note: CashNote,
+    ) -> eyre::Result<(SignedSpend, CashNote)> {
+        let derived_key = cash_note.derived_key(key)?;
+        let amount = cash_note.value()?;
+        let mut rng = sn_transfers::rng::thread_rng();
+        let transfer = create_offline_transfer(
+            vec![(cash_note, derived_key)],
+            vec![(amount, key.main_pubkey(), DerivationIndex::random(&mut rng))],
+            key.main_pubkey(),
+            Hash::default(),
+        )?;
+        let spend = transfer
+            .all_spend_requests
+            .into_iter()
+            .next()
+            .ok_or_else(|| eyre::eyre!("The transfer spends nothing"))?;
+        let created = transfer
+            .created_cash_notes
+            .into_iter()
+            .next()
+            .ok_or_else(|| eyre::eyre!("The transfer creates nothing"))?;
+        Ok((spend, created))
+    }
+
+    #[test]
+    fn spends_are_sent_after_those_of_their_parent_tx() -> eyre::Result<()> {
+        let key = MainSecretKey::random();
+        let (first, created) = send_to_self(&key, create_first_cash_note_from_key(&key)?)?;
+        let (second, created) = send_to_self(&key, created)?;
+        let (third, _) = send_to_self(&key, created)?;
+        let other_key = MainSecretKey::random();
+        let (unrelated, _) =
+            send_to_self(&other_key, create_first_cash_note_from_key(&other_key)?)?;
+
+        let waves = spend_waves([&third, &second, &unrelated, &first].into_iter());
+        assert_eq!(
+            waves,
+            vec![vec![&unrelated, &first], vec![&second], vec![&third]]
+        );
+
+        // spends of transactions outside of the given ones are sent straight away
+        let waves = spend_waves([&third, &second].into_iter());
+        assert_eq!(waves, vec![vec![&second], vec![&third]]);
+        assert!(spend_waves(std::iter::empty()).is_empty());
+        Ok(())
+    }
+}
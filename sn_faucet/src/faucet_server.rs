@@ -8,10 +8,13 @@
 
 use crate::{claim_genesis, send_tokens};
 use color_eyre::eyre::{eyre, Result};
-use sn_client::Client;
+use sn_client::{split_faucet_wallet, Client};
 use sn_transfers::{LocalWallet, NanoTokens};
-use std::path::{self, Path, PathBuf};
-use tiny_http::{Response, Server};
+use std::{
+    path::{self, Path, PathBuf},
+    sync::Arc,
+};
+use tiny_http::{Request, Response, Server};
 use tracing::{debug, error, trace};
 
 /// Run the faucet server.
@@ -32,17 +35,29 @@ use tracing::{debug, error, trace};
 ///
 /// # balance should be updated
 /// ```
-pub async fn run_faucet_server(client: &Client) -> Result<()> {
+pub async fn run_faucet_server(
+    client: &Client,
+    split_notes: usize,
+    split_notes_per_tx: usize,
+    workers: usize,
+) -> Result<()> {
     claim_genesis(client).await.map_err(|err| {
         println!("Faucet Server couldn't start as we failed to claim Genesis");
         eprintln!("Faucet Server couldn't start as we failed to claim Genesis");
         error!("Faucet Server couldn't start as we failed to claim Genesis");
         err
     })?;
-    startup_server(client).await
+    split_faucet_wallet(client, split_notes, split_notes_per_tx)
+        .await
+        .map_err(|err| {
+            eprintln!("Faucet Server couldn't start as we failed to split its balance: {err}");
+            error!("Faucet Server couldn't start as we failed to split its balance: {err}");
+            err
+        })?;
+    startup_server(client, workers).await
 }
 
-pub async fn restart_faucet_server(client: &Client) -> Result<()> {
+pub async fn restart_faucet_server(client: &Client, workers: usize) -> Result<()> {
     let root_dir = get_test_faucet_data_dir_path()?;
     println!("Loading the previous wallet at {root_dir:?}");
     debug!("Loading the previous wallet at {root_dir:?}");
@@ -52,52 +67,80 @@ pub async fn restart_faucet_server(client: &Client) -> Result<()> {
     println!("Previous wallet loaded");
     debug!("Previous wallet loaded");
 
-    startup_server(client).await
+    startup_server(client, workers).await
 }
 
-async fn startup_server(client: &Client) -> Result<()> {
-    let server =
-        Server::http("0.0.0.0:8000").map_err(|err| eyre!("Failed to start server: {err}"))?;
+async fn startup_server(client: &Client, workers: usize) -> Result<()> {
+    let server = Arc::new(
+        Server::http("0.0.0.0:8000").map_err(|err| eyre!("Failed to start server: {err}"))?,
+    );
 
     // This println is used in sn_testnet to wait for the faucet to start.
     println!("Starting http server listening on port 8000...");
-    debug!("Starting http server listening on port 8000...");
-    for request in server.incoming_requests() {
-        println!(
-            "received request! method: {:?}, url: {:?}, headers: {:?}",
-            request.method(),
-            request.url(),
-            request.headers()
-        );
-        trace!(
-            "received request! method: {:?}, url: {:?}, headers: {:?}",
-            request.method(),
-            request.url(),
-            request.headers()
-        );
-        let key = request.url().trim_matches(path::is_separator);
-
-        match send_tokens(client, "100", key).await {
-            Ok(transfer) => {
-                println!("Sent tokens to {key}");
-                debug!("Sent tokens to {key}");
-                let response = Response::from_string(transfer);
-                let _ = request.respond(response).map_err(|err| {
-                    eprintln!("Failed to send response: {err}");
-                    error!("Failed to send response: {err}");
-                });
+    debug!("Starting http server listening on port 8000 with {workers} workers...");
+
+    // Each worker pays out from its own inputs, as the faucet balance has been split.
+    let handles: Vec<_> = (0..workers.max(1))
+        .map(|_| tokio::spawn(serve_requests(server.clone(), client.clone())))
+        .collect();
+    for handle in handles {
+        handle.await?;
+    }
+    Ok(())
+}
+
+async fn serve_requests(server: Arc<Server>, client: Client) {
+    loop {
+        let server = server.clone();
+        let request = match tokio::task::spawn_blocking(move || server.recv()).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(err)) => {
+                error!("Failed to receive request: {err}");
+                return;
             }
             Err(err) => {
-                eprintln!("Failed to send tokens to {key}: {err}");
-                error!("Failed to send tokens to {key}: {err}");
-                let response = Response::from_string(format!("Failed to send tokens: {err}"));
-                let _ = request
-                    .respond(response.with_status_code(500))
-                    .map_err(|err| eprintln!("Failed to send response: {err}"));
+                error!("Failed to wait for a request: {err}");
+                return;
             }
+        };
+        handle_request(&client, request).await;
+    }
+}
+
+async fn handle_request(client: &Client, request: Request) {
+    println!(
+        "received request! method: {:?}, url: {:?}, headers: {:?}",
+        request.method(),
+        request.url(),
+        request.headers()
+    );
+    trace!(
+        "received request! method: {:?}, url: {:?}, headers: {:?}",
+        request.method(),
+        request.url(),
+        request.headers()
+    );
+    let key = request.url().trim_matches(path::is_separator);
+
+    match send_tokens(client, "100", key).await {
+        Ok(transfer) => {
+            println!("Sent tokens to {key}");
+            debug!("Sent tokens to {key}");
+            let response = Response::from_string(transfer);
+            let _ = request.respond(response).map_err(|err| {
+                eprintln!("Failed to send response: {err}");
+                error!("Failed to send response: {err}");
+            });
+        }
+        Err(err) => {
+            eprintln!("Failed to send tokens to {key}: {err}");
+            error!("Failed to send tokens to {key}: {err}");
+            let response = Response::from_string(format!("Failed to send tokens: {err}"));
+            let _ = request
+                .respond(response.with_status_code(500))
+                .map_err(|err| eprintln!("Failed to send response: {err}"));
         }
     }
-    Ok(())
 }
 
 fn get_test_faucet_data_dir_path() -> Result<PathBuf> {
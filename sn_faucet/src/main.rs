@@ -11,11 +11,17 @@ mod faucet_server;
 use clap::{Parser, Subcommand};
 use color_eyre::eyre::{bail, eyre, Result};
 use faucet_server::{restart_faucet_server, run_faucet_server};
-use sn_client::{get_tokens_from_faucet, load_faucet_wallet_from_genesis_wallet, Client};
+use sn_client::{
+    get_tokens_from_faucet, load_faucet_wallet_from_genesis_wallet, print_audit_progress, Client,
+    RoyaltyTracker,
+};
 use sn_logging::{LogBuilder, LogOutputDest};
 use sn_peers_acquisition::{get_peers_from_args, PeersArgs};
-use sn_transfers::{MainPubkey, NanoTokens, Transfer};
-use std::path::PathBuf;
+use sn_transfers::{
+    bls_secret_from_hex, create_faucet_wallet, MainPubkey, MainSecretKey, NanoTokens,
+    SignedRoyaltiesClaim, SpendAddress, Transfer, UnsignedRoyaltiesClaim, GENESIS_CASHNOTE,
+};
+use std::path::{Path, PathBuf};
 use tracing::{error, info};
 use tracing_core::Level;
 
@@ -23,14 +29,6 @@ use tracing_core::Level;
 async fn main() -> Result<()> {
     let opt = Opt::parse();
 
-    let bootstrap_peers = get_peers_from_args(opt.peers).await?;
-    let bootstrap_peers = if bootstrap_peers.is_empty() {
-        // empty vec is returned if `local-discovery` flag is provided
-        None
-    } else {
-        Some(bootstrap_peers)
-    };
-
     let _log_appender_guard = if let Some(log_output_dest) = opt.log_output_dest {
         let logging_targets = vec![
             // TODO: Reset to nice and clean defaults once we have a better idea of what we want
@@ -52,6 +50,24 @@ async fn main() -> Result<()> {
         None
     };
 
+    // the royalties key is only ever used offline, without connecting to the network
+    if let SubCmd::SignRoyaltiesClaim {
+        claim,
+        royalties_sk,
+        out,
+    } = &opt.cmd
+    {
+        return sign_royalties_claim(claim, royalties_sk, out);
+    }
+
+    let bootstrap_peers = get_peers_from_args(opt.peers).await?;
+    let bootstrap_peers = if bootstrap_peers.is_empty() {
+        // empty vec is returned if `local-discovery` flag is provided
+        None
+    } else {
+        Some(bootstrap_peers)
+    };
+
     info!("Instantiating a SAFE Test Faucet...");
 
     let secret_key = bls::SecretKey::random();
@@ -108,7 +124,18 @@ enum SubCmd {
     },
     /// Starts an http server that will send tokens to anyone who requests them.
     /// curl http://localhost:8000/your-hex-encoded-wallet-public-address
-    Server,
+    Server {
+        /// Once genesis is claimed, split the faucet balance into this many cash notes of equal
+        /// value, so payouts can be made concurrently. An interrupted split resumes on restart.
+        #[clap(long, default_value_t = 1000)]
+        split_notes: usize,
+        /// The maximum number of cash notes created by each transaction splitting the balance.
+        #[clap(long, default_value_t = 100)]
+        split_notes_per_tx: usize,
+        /// The number of requests served concurrently.
+        #[clap(long, default_value_t = 8)]
+        workers: usize,
+    },
     /// Restart the faucet_server from the last breaking point.
     ///
     /// Before firing this cmd, ensure:
@@ -117,7 +144,41 @@ enum SubCmd {
     ///   3, The old `wallet` and `wallet.lock` files shall also be removed.
     /// The command will create a new wallet with the same key,
     /// then deposit all valid cash_notes into wallet and startup the faucet_server.
-    RestartServer,
+    RestartServer {
+        /// The number of requests served concurrently.
+        #[clap(long, default_value_t = 8)]
+        workers: usize,
+    },
+    /// Audit the Currency for the royalties paid so far, and export the claim of the unclaimed
+    /// ones to a file, for it to be signed offline. The royalties key is not needed.
+    ExportRoyaltiesClaim {
+        /// This must be a hex-encoded `MainPubkey`, the recipient of the claimed royalties.
+        #[clap(name = "to")]
+        to: String,
+        /// The file to write the unsigned claim to.
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Sign an exported royalties claim with the royalties key.
+    /// This never connects to the network, and is meant to be run on an offline machine.
+    SignRoyaltiesClaim {
+        /// The file holding the unsigned claim.
+        #[clap(name = "claim")]
+        claim: PathBuf,
+        /// The file holding the hex-encoded royalties secret key.
+        #[clap(long)]
+        royalties_sk: PathBuf,
+        /// The file to write the signed claim to.
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Broadcast a royalties claim signed offline. The claimed royalties are deposited to the
+    /// faucet wallet if it is the recipient, else the transfer to the recipient is printed out.
+    BroadcastRoyaltiesClaim {
+        /// The file holding the signed claim.
+        #[clap(name = "claim")]
+        claim: PathBuf,
+    },
 }
 
 async fn faucet_cmds(cmds: SubCmd, client: &Client) -> Result<()> {
@@ -128,13 +189,26 @@ async fn faucet_cmds(cmds: SubCmd, client: &Client) -> Result<()> {
         SubCmd::Send { amount, to } => {
             send_tokens(client, &amount, &to).await?;
         }
-        SubCmd::Server => {
+        SubCmd::Server {
+            split_notes,
+            split_notes_per_tx,
+            workers,
+        } => {
             // shouldn't return except on error
-            run_faucet_server(client).await?;
+            run_faucet_server(client, split_notes, split_notes_per_tx, workers).await?;
         }
-        SubCmd::RestartServer => {
+        SubCmd::RestartServer { workers } => {
             // shouldn't return except on error
-            restart_faucet_server(client).await?;
+            restart_faucet_server(client, workers).await?;
+        }
+        SubCmd::ExportRoyaltiesClaim { to, out } => {
+            export_royalties_claim(client, &to, &out).await?;
+        }
+        SubCmd::SignRoyaltiesClaim { .. } => {
+            bail!("Royalties claims are signed offline, without connecting to the network")
+        }
+        SubCmd::BroadcastRoyaltiesClaim { claim } => {
+            broadcast_royalties_claim(client, &claim).await?;
         }
     }
     Ok(())
@@ -172,6 +246,89 @@ async fn send_tokens(client: &Client, amount: &str, to: &str) -> Result<String>
     Ok(transfer_hex)
 }
 
+async fn export_royalties_claim(client: &Client, to: &str, out: &Path) -> Result<()> {
+    let recipient = MainPubkey::from_hex(to)?;
+    let root_dir = faucet_data_dir()?;
+
+    // resume from the royalties observed by the previous audits
+    println!("Auditing the Currency for royalties, note that this might take a very long time...");
+    let mut tracker = RoyaltyTracker::load_from(&root_dir)?;
+    let genesis_addr = SpendAddress::from_unique_pubkey(&GENESIS_CASHNOTE.unique_pubkey());
+    let _audit = client
+        .follow_spend(
+            genesis_addr,
+            false,
+            Some(&mut tracker),
+            None,
+            &root_dir,
+            Some(&mut print_audit_progress),
+        )
+        .await?;
+    tracker.save_to(&root_dir)?;
+
+    let redemptions = tracker.redemptions();
+    if redemptions.is_empty() {
+        bail!("No royalties found to claim");
+    }
+    let claim = client
+        .create_royalties_claim(redemptions, recipient)
+        .await?;
+    std::fs::write(out, claim.to_hex()?)?;
+    println!(
+        "Unsigned claim of {} royalties, for a total of {}, written to {out:?}",
+        claim.spends.len(),
+        claim.total()?
+    );
+    Ok(())
+}
+
+fn sign_royalties_claim(claim: &Path, royalties_sk: &Path, out: &Path) -> Result<()> {
+    let claim = UnsignedRoyaltiesClaim::from_hex(&std::fs::read_to_string(claim)?)?;
+    let royalties_sk = std::fs::read_to_string(royalties_sk)?;
+    let royalties_sk = MainSecretKey::new(bls_secret_from_hex(royalties_sk.trim())?);
+
+    println!(
+        "Signing the claim of {} royalties, for a total of {}, to {:?}",
+        claim.spends.len(),
+        claim.total()?,
+        claim.recipient
+    );
+    let signed = claim.sign(&royalties_sk)?;
+    std::fs::write(out, signed.to_hex()?)?;
+    println!("Signed claim written to {out:?}");
+    Ok(())
+}
+
+async fn broadcast_royalties_claim(client: &Client, claim: &Path) -> Result<()> {
+    let claim = SignedRoyaltiesClaim::from_hex(&std::fs::read_to_string(claim)?)?;
+    let cash_note = client.broadcast_royalties_claim(&claim).await?;
+    println!("Royalties claim broadcast, claimed {}", cash_note.value()?);
+
+    let mut wallet = create_faucet_wallet();
+    if wallet.address() == *cash_note.main_pubkey() {
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+        println!(
+            "Deposited to the faucet wallet, new balance: {}",
+            wallet.balance()
+        );
+    } else {
+        let transfer_hex = Transfer::transfer_from_cash_note(&cash_note)?.to_hex()?;
+        println!("Transfer to the recipient:");
+        println!("{transfer_hex}");
+    }
+    Ok(())
+}
+
+/// The faucet data dir, where the royalties observed by the audits are checkpointed.
+fn faucet_data_dir() -> Result<PathBuf> {
+    let dir = dirs_next::data_dir()
+        .ok_or_else(|| eyre!("could not obtain data directory path".to_string()))?
+        .join("safe")
+        .join("test_faucet");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
 fn parse_log_output(val: &str) -> Result<LogOutputDest> {
     match val {
         "stdout" => Ok(LogOutputDest::Stdout),
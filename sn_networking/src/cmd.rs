@@ -9,8 +9,10 @@
 use crate::{
     driver::{PendingGetClosestType, SwarmDriver},
     error::{Error, Result},
-    multiaddr_pop_p2p, GetRecordCfg, GetRecordError, MsgResponder, NetworkEvent, CLOSE_GROUP_SIZE,
-    REPLICATE_RANGE,
+    get_record_handler::GetRecordResultSender,
+    multiaddr_pop_p2p,
+    record_store::RecordKindUsage,
+    GetRecordCfg, MsgResponder, NetworkEvent, CLOSE_GROUP_SIZE, REPLICATE_RANGE,
 };
 use bytes::Bytes;
 use libp2p::{
@@ -56,6 +58,11 @@ pub enum SwarmCmd {
     GetKBuckets {
         sender: oneshot::Sender<BTreeMap<u32, Vec<PeerId>>>,
     },
+    /// Get the peers of the local Routing Table along with their known addresses, from the
+    /// closest to the farthest.
+    GetRoutingTableAddrs {
+        sender: oneshot::Sender<Vec<(PeerId, Vec<Multiaddr>)>>,
+    },
     // Returns up to K_VALUE peers from all the k-buckets from the local Routing Table.
     // And our PeerId as well.
     GetClosestKLocalPeers {
@@ -98,10 +105,14 @@ pub enum SwarmCmd {
     GetAllLocalRecordAddresses {
         sender: oneshot::Sender<HashMap<NetworkAddress, RecordType>>,
     },
+    /// Get the bytes held by the local RecordStore for each kind of record
+    GetRecordKindUsage {
+        sender: oneshot::Sender<RecordKindUsage>,
+    },
     /// Get Record from the Kad network
     GetNetworkRecord {
         key: RecordKey,
-        sender: oneshot::Sender<std::result::Result<Record, GetRecordError>>,
+        sender: GetRecordResultSender,
         cfg: GetRecordCfg,
     },
     /// GetLocalStoreCost for this node
@@ -111,6 +122,10 @@ pub enum SwarmCmd {
     },
     /// Notify the node received a payment.
     PaymentReceived,
+    /// Stop the SwarmDriver, acknowledging once it is about to exit.
+    Shutdown {
+        sender: oneshot::Sender<()>,
+    },
     /// Get data from the local RecordStore
     GetLocalRecord {
         key: RecordKey,
@@ -247,6 +262,9 @@ impl Debug for SwarmCmd {
             SwarmCmd::PaymentReceived => {
                 write!(f, "SwarmCmd::PaymentReceived")
             }
+            SwarmCmd::Shutdown { .. } => {
+                write!(f, "SwarmCmd::Shutdown")
+            }
             SwarmCmd::GetLocalRecord { key, .. } => {
                 write!(
                     f,
@@ -257,12 +275,18 @@ impl Debug for SwarmCmd {
             SwarmCmd::GetAllLocalRecordAddresses { .. } => {
                 write!(f, "SwarmCmd::GetAllLocalRecordAddresses")
             }
+            SwarmCmd::GetRecordKindUsage { .. } => {
+                write!(f, "SwarmCmd::GetRecordKindUsage")
+            }
             SwarmCmd::GetAllLocalPeers { .. } => {
                 write!(f, "SwarmCmd::GetAllLocalPeers")
             }
             SwarmCmd::GetKBuckets { .. } => {
                 write!(f, "SwarmCmd::GetKBuckets")
             }
+            SwarmCmd::GetRoutingTableAddrs { .. } => {
+                write!(f, "SwarmCmd::GetRoutingTableAddrs")
+            }
             SwarmCmd::GetSwarmLocalState { .. } => {
                 write!(f, "SwarmCmd::GetSwarmLocalState")
             }
@@ -348,6 +372,10 @@ impl SwarmDriver {
                     .store_mut()
                     .payment_received();
             }
+            SwarmCmd::Shutdown { .. } => {
+                // the run loop intercepts it, as it is the one to exit
+                warn!("SwarmCmd::Shutdown reached the cmd handler, ignoring it");
+            }
             SwarmCmd::GetLocalRecord { key, sender } => {
                 let record = self
                     .swarm
@@ -453,7 +481,7 @@ impl SwarmDriver {
                             ));
                         }
                     }
-                    Err(err) => return Err(err.into()),
+                    Err(err) => return Err(err),
                 };
             }
             SwarmCmd::AddLocalRecordAsStored { key, record_type } => self
@@ -484,6 +512,15 @@ impl SwarmDriver {
                     .record_addresses();
                 let _ = sender.send(addresses);
             }
+            SwarmCmd::GetRecordKindUsage { sender } => {
+                let usage = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .store_mut()
+                    .record_kind_usage();
+                let _ = sender.send(usage);
+            }
 
             SwarmCmd::StartListening { addr, sender } => {
                 let _ = match self.swarm.listen_on(addr) {
@@ -546,6 +583,27 @@ impl SwarmDriver {
                 }
                 let _ = sender.send(ilog2_kbuckets);
             }
+            SwarmCmd::GetRoutingTableAddrs { sender } => {
+                // the kbuckets are iterated from the closest to the farthest
+                let peers = self
+                    .swarm
+                    .behaviour_mut()
+                    .kademlia
+                    .kbuckets()
+                    .flat_map(|kbucket| {
+                        kbucket
+                            .iter()
+                            .map(|peer_entry| {
+                                (
+                                    *peer_entry.node.key.preimage(),
+                                    peer_entry.node.value.iter().cloned().collect(),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+                let _ = sender.send(peers);
+            }
             SwarmCmd::GetCloseGroupLocalPeers { key, sender } => {
                 let key = key.as_kbucket_key();
                 // calls `kbuckets.closest_keys(key)` internally, which orders the peers by
@@ -0,0 +1,395 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::error::{Error, Result};
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::*;
+use url::Url;
+
+/// How long a DNS-over-HTTPS query is given before falling back to the system resolver.
+pub const DOH_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+/// The longest an answer is cached for, whatever its TTL.
+pub const MAX_DOH_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The DNS record types queried, as numbered by the DNS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RecordType {
+    A,
+    Aaaa,
+}
+
+impl RecordType {
+    fn code(&self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::Aaaa => 28,
+        }
+    }
+
+    fn matches(&self, ip: &IpAddr) -> bool {
+        match self {
+            Self::A => ip.is_ipv4(),
+            Self::Aaaa => ip.is_ipv6(),
+        }
+    }
+}
+
+/// A response in the JSON format of DNS-over-HTTPS, as served by the public resolvers.
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL", default)]
+    ttl: u64,
+    data: String,
+}
+
+type DohCache = HashMap<(String, RecordType), (Vec<IpAddr>, Instant)>;
+
+/// Resolves the host names of `dns`, `dns4` and `dns6` multiaddrs through DNS-over-HTTPS, for
+/// peers to be dialed where the system resolver can't be trusted, e.g. behind DNS poisoning.
+/// Set on the `NetworkBuilder`, it resolves every address dialed by the transport.
+///
+/// The answers are cached as long as their TTL, up to `MAX_DOH_CACHE_TTL`, and shared by the
+/// clones of the resolver. Empty answers aren't cached, the host being queried again next time.
+/// If a query fails, the system resolver is used instead.
+#[derive(Debug, Clone)]
+pub struct DohResolver {
+    server: Url,
+    client: reqwest::Client,
+    cache: Arc<Mutex<DohCache>>,
+}
+
+impl DohResolver {
+    /// A resolver querying the DNS-over-HTTPS server at `server`, in its JSON format, e.g.
+    /// `https://cloudflare-dns.com/dns-query`.
+    pub fn new(server: Url) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(DOH_QUERY_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            server,
+            client,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// The multiaddr with its `dns`, `dns4` and `dns6` components replaced by the address they
+    /// resolve to. A `dns` component resolves to an IPv4 address first.
+    ///
+    /// The host name of `tls` and `wss` multiaddrs is kept as their `sni`, as the one the TLS
+    /// handshake is made for, e.g. `/dns4/<host>/tcp/443/wss` resolves to
+    /// `/ip4/<ip>/tcp/443/tls/sni/<host>/ws`.
+    ///
+    /// The multiaddr is returned as is if a host name resolves to no address, so it is left to
+    /// the system resolver when dialing.
+    pub async fn resolve_multiaddr(&self, addr: &Multiaddr) -> Multiaddr {
+        let mut resolved = Multiaddr::empty();
+        let mut host_name = None;
+        for protocol in addr.iter() {
+            let host_and_record_types = match &protocol {
+                Protocol::Dns(host) => {
+                    Some((host.to_string(), vec![RecordType::A, RecordType::Aaaa]))
+                }
+                Protocol::Dns4(host) => Some((host.to_string(), vec![RecordType::A])),
+                Protocol::Dns6(host) => Some((host.to_string(), vec![RecordType::Aaaa])),
+                _ => None,
+            };
+            let Some((host, record_types)) = host_and_record_types else {
+                match protocol {
+                    Protocol::Tls => {
+                        resolved.push(Protocol::Tls);
+                        if let Some(host) = host_name.take() {
+                            resolved.push(Protocol::Sni(host.into()));
+                        }
+                    }
+                    Protocol::Wss(path) => {
+                        resolved.push(Protocol::Tls);
+                        if let Some(host) = host_name.take() {
+                            resolved.push(Protocol::Sni(host.into()));
+                        }
+                        resolved.push(Protocol::Ws(path));
+                    }
+                    protocol => resolved.push(protocol),
+                }
+                continue;
+            };
+            match self.resolve(&host, &record_types).await {
+                Some(IpAddr::V4(ip)) => resolved.push(Protocol::Ip4(ip)),
+                Some(IpAddr::V6(ip)) => resolved.push(Protocol::Ip6(ip)),
+                None => {
+                    warn!("Could not resolve {host} of {addr}, leaving it to the system resolver");
+                    return addr.clone();
+                }
+            }
+            host_name = Some(host);
+        }
+        debug!("Resolved {addr} to {resolved}");
+        resolved
+    }
+
+    /// The first address `host` resolves to, querying the record types in turn.
+    async fn resolve(&self, host: &str, record_types: &[RecordType]) -> Option<IpAddr> {
+        for record_type in record_types {
+            match self.lookup(host, *record_type).await {
+                Ok(ips) => {
+                    if let Some(ip) = ips.first() {
+                        return Some(*ip);
+                    }
+                    debug!("No {record_type:?} record for {host}");
+                }
+                Err(err) => {
+                    warn!("Failed to resolve {host} with DNS-over-HTTPS, using the system resolver: {err}");
+                    return system_lookup(host, record_types).await;
+                }
+            }
+        }
+        None
+    }
+
+    /// The addresses of `host` for the record type, from the cache if fresh enough.
+    async fn lookup(&self, host: &str, record_type: RecordType) -> Result<Vec<IpAddr>> {
+        let key = (host.to_string(), record_type);
+        if let Ok(cache) = self.cache.lock() {
+            if let Some((ips, expiry)) = cache.get(&key) {
+                if *expiry > Instant::now() {
+                    trace!("Using the cached {record_type:?} records of {host}");
+                    return Ok(ips.clone());
+                }
+            }
+        }
+
+        let (ips, ttl) = self.query(host, record_type).await?;
+        if ips.is_empty() {
+            // the host may just not be published yet
+            return Ok(ips);
+        }
+        if let Ok(mut cache) = self.cache.lock() {
+            let _ = cache.insert(key, (ips.clone(), Instant::now() + ttl));
+        }
+        Ok(ips)
+    }
+
+    /// Query the server for the `record_type` records of `host`, returning the addresses along
+    /// with how long they can be cached for.
+    async fn query(&self, host: &str, record_type: RecordType) -> Result<(Vec<IpAddr>, Duration)> {
+        let doh_error = |reason: String| Error::DohQueryFailed {
+            host: host.to_string(),
+            reason,
+        };
+
+        let code = record_type.code().to_string();
+        let response = self
+            .client
+            .get(self.server.clone())
+            .query(&[("name", host), ("type", code.as_str())])
+            .header(reqwest::header::ACCEPT, "application/dns-json")
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(doh_error(format!("status {}", response.status().as_u16())));
+        }
+        let text = response.text().await?;
+        let response: DohResponse =
+            serde_json::from_str(&text).map_err(|err| doh_error(err.to_string()))?;
+        if response.status != 0 {
+            return Err(doh_error(format!("DNS response code {}", response.status)));
+        }
+
+        let mut ttl = MAX_DOH_CACHE_TTL;
+        let ips: Vec<IpAddr> = response
+            .answer
+            .into_iter()
+            // other records, e.g. CNAMEs, are part of the answer too
+            .filter(|answer| answer.record_type == record_type.code())
+            .filter_map(|answer| {
+                ttl = ttl.min(Duration::from_secs(answer.ttl));
+                answer.data.parse().ok()
+            })
+            .filter(|ip| record_type.matches(ip))
+            .collect();
+        trace!("DNS-over-HTTPS resolved the {record_type:?} records of {host} to {ips:?}");
+        Ok((ips, ttl))
+    }
+}
+
+/// The addresses of `host` from the system resolver, of the first record type having some.
+async fn system_lookup(host: &str, record_types: &[RecordType]) -> Option<IpAddr> {
+    let ips: Vec<IpAddr> = match tokio::net::lookup_host((host, 0)).await {
+        Ok(addrs) => addrs.map(|addr| addr.ip()).collect(),
+        Err(err) => {
+            warn!("The system resolver failed to resolve {host}: {err}");
+            return None;
+        }
+    };
+    record_types
+        .iter()
+        .find_map(|record_type| ips.iter().find(|ip| record_type.matches(ip)).copied())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use eyre::Result;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    pub(crate) type Queries = Arc<Mutex<Vec<String>>>;
+
+    /// Serve DNS-over-HTTPS answers on a local port, recording the queries as `<name>/<type>`.
+    /// Hosts missing from `answers` get an empty answer, and all queries fail if `status` is not
+    /// 200.
+    pub(crate) async fn mock_doh_server(
+        answers: HashMap<&'static str, IpAddr>,
+        status: u16,
+    ) -> Result<(Url, Queries)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!("http://{}/dns-query", listener.local_addr()?))?;
+        let queries = Arc::new(Mutex::new(vec![]));
+        let queries_seen = queries.clone();
+
+        let _handle = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let read = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..read]).to_string();
+                let query = request
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|path| Url::parse(&format!("http://localhost{path}")).ok());
+                let param = |key: &str| {
+                    query.as_ref().and_then(|query| {
+                        query
+                            .query_pairs()
+                            .find(|(name, _)| name == key)
+                            .map(|(_, value)| value.to_string())
+                    })
+                };
+                let (name, record_type) = (param("name"), param("type"));
+                if let (Ok(mut queries), Some(name), Some(record_type)) =
+                    (queries_seen.lock(), &name, &record_type)
+                {
+                    queries.push(format!("{name}/{record_type}"));
+                }
+
+                let answer = name
+                    .as_deref()
+                    .and_then(|name| answers.get(name))
+                    .filter(|ip| match record_type.as_deref() {
+                        Some("1") => ip.is_ipv4(),
+                        Some("28") => ip.is_ipv6(),
+                        _ => false,
+                    })
+                    .map(|ip| {
+                        format!(
+                            r#"{{"name":"{}.","type":{},"TTL":300,"data":"{ip}"}}"#,
+                            name.as_deref().unwrap_or_default(),
+                            record_type.as_deref().unwrap_or_default()
+                        )
+                    })
+                    .unwrap_or_default();
+                let body = format!(r#"{{"Status":0,"Answer":[{answer}]}}"#);
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: application/dns-json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        Ok((url, queries))
+    }
+
+    pub(crate) fn multiaddr(addr: &str) -> Result<Multiaddr> {
+        Ok(addr.parse()?)
+    }
+
+    #[tokio::test]
+    async fn answers_are_cached_across_clones() -> Result<()> {
+        let answers = HashMap::from([("bootstrap.test", IpAddr::from([10, 1, 2, 3]))]);
+        let (server, queries) = mock_doh_server(answers, 200).await?;
+        let resolver = DohResolver::new(server)?;
+
+        let addr = multiaddr("/dns4/bootstrap.test/tcp/12000")?;
+        let expected = multiaddr("/ip4/10.1.2.3/tcp/12000")?;
+        assert_eq!(resolver.resolve_multiaddr(&addr).await, expected);
+        assert_eq!(resolver.clone().resolve_multiaddr(&addr).await, expected);
+        assert_eq!(queries.lock().map(|queries| queries.len()).ok(), Some(1));
+
+        // a host without answer is left to the transport
+        let unknown = multiaddr("/dns4/unknown.test/tcp/12000")?;
+        assert_eq!(resolver.resolve_multiaddr(&unknown).await, unknown);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_answers_are_not_cached() -> Result<()> {
+        let (server, queries) = mock_doh_server(HashMap::new(), 200).await?;
+        let resolver = DohResolver::new(server)?;
+
+        let unknown = multiaddr("/dns4/unknown.test/tcp/12000")?;
+        assert_eq!(resolver.resolve_multiaddr(&unknown).await, unknown);
+        assert_eq!(resolver.resolve_multiaddr(&unknown).await, unknown);
+        assert_eq!(
+            queries.lock().map(|queries| queries.clone()).ok(),
+            Some(vec![
+                "unknown.test/1".to_string(),
+                "unknown.test/1".to_string()
+            ])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tls_multiaddrs_keep_their_host_name() -> Result<()> {
+        let answers = HashMap::from([("bootstrap.test", IpAddr::from([10, 1, 2, 3]))]);
+        let (server, _queries) = mock_doh_server(answers, 200).await?;
+        let resolver = DohResolver::new(server)?;
+
+        let expected = multiaddr("/ip4/10.1.2.3/tcp/443/tls/sni/bootstrap.test/ws")?;
+        for addr in [
+            "/dns4/bootstrap.test/tcp/443/wss",
+            "/dns4/bootstrap.test/tcp/443/tls/ws",
+        ] {
+            assert_eq!(
+                resolver.resolve_multiaddr(&multiaddr(addr)?).await,
+                expected
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_doh_queries_fall_back_to_the_system_resolver() -> Result<()> {
+        let answers = HashMap::from([("localhost", IpAddr::from([10, 1, 2, 3]))]);
+        let (server, queries) = mock_doh_server(answers, 500).await?;
+        let resolver = DohResolver::new(server)?;
+
+        let resolved = resolver
+            .resolve_multiaddr(&multiaddr("/dns4/localhost/tcp/12000")?)
+            .await;
+        assert_eq!(resolved, multiaddr("/ip4/127.0.0.1/tcp/12000")?);
+        assert_eq!(queries.lock().map(|queries| queries.len()).ok(), Some(1));
+        Ok(())
+    }
+}
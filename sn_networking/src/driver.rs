@@ -20,18 +20,21 @@ use crate::{
     get_record_handler::PendingGetRecord,
     multiaddr_pop_p2p,
     network_discovery::NetworkDiscovery,
-    record_store::{ClientRecordStore, NodeRecordStore, NodeRecordStoreConfig},
+    record_store::{ClientRecordStore, NodeRecordStore, NodeRecordStoreConfig, RecordKindQuotas},
     record_store_api::UnifiedRecordStore,
     replication_fetcher::ReplicationFetcher,
+    retry::RetryPolicy,
+    transport::build_transport,
     Network, CLOSE_GROUP_SIZE,
 };
+#[cfg(feature = "network-contacts")]
+use crate::{transport::DohTransport, DohResolver};
+use bytes::Bytes;
 use futures::StreamExt;
-#[cfg(feature = "quic")]
-use libp2p::core::muxing::StreamMuxerBox;
 #[cfg(feature = "local-discovery")]
 use libp2p::mdns;
-#[cfg(feature = "quic")]
-use libp2p::quic;
+#[cfg(feature = "network-contacts")]
+use libp2p::Transport;
 use libp2p::{
     autonat,
     identity::Keypair,
@@ -43,12 +46,12 @@ use libp2p::{
         dial_opts::{DialOpts, PeerCondition},
         ConnectionId, DialError, NetworkBehaviour, StreamProtocol, Swarm,
     },
-    Multiaddr, PeerId, Transport,
+    Multiaddr, PeerId,
 };
 #[cfg(feature = "open-metrics")]
 use prometheus_client::registry::Registry;
 use sn_protocol::{
-    messages::{ChunkProof, Nonce, Request, Response},
+    messages::{Nonce, Request, Response},
     NetworkAddress, PrettyPrintKBucketKey, PrettyPrintRecordKey,
 };
 use std::{
@@ -115,8 +118,8 @@ pub(crate) fn truncate_patch_version(full_str: &str) -> &str {
 pub struct GetRecordCfg {
     /// The query will result in an error if we get records less than the provided Quorum
     pub get_quorum: Quorum,
-    /// If set to true, we retry upto GET_RETRY_ATTEMPTS times
-    pub re_attempt: bool,
+    /// How the query is retried when it fails.
+    pub retry_policy: RetryPolicy,
     /// Only return if we fetch the provided record.
     pub target_record: Option<Record>,
     /// Logs if the record was not fetched from the provided set of peers.
@@ -133,7 +136,7 @@ impl Debug for GetRecordCfg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut f = f.debug_struct("GetRecordCfg");
         f.field("get_quorum", &self.get_quorum)
-            .field("re_attempt", &self.re_attempt);
+            .field("retry_policy", &self.retry_policy);
 
         match &self.target_record {
             Some(record) => {
@@ -156,8 +159,8 @@ pub struct PutRecordCfg {
     /// just makes sure that we get atleast `n` successful responses defined by the Quorum.
     /// Our nodes currently send `Ok()` response for every KAD PUT. Thus this field does not do anything atm.
     pub put_quorum: Quorum,
-    /// If set to true, we retry upto PUT_RETRY_ATTEMPTS times
-    pub re_attempt: bool,
+    /// How the PUT, verification included, is retried when it fails.
+    pub retry_policy: RetryPolicy,
     /// Use the `kad::put_record_to` to PUT the record only to the specified peers. If this option is set to None, we
     /// will be using `kad::put_record` which would PUT the record to all the closest members of the record.
     pub use_put_record_to: Option<Vec<PeerId>>,
@@ -166,15 +169,30 @@ pub struct PutRecordCfg {
 }
 
 /// The methods in which verification on a PUT can be carried out.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum VerificationKind {
     /// Uses the default KAD GET to perform verification.
     Network,
-    /// Uses the hash based verification for chunks.
-    ChunkProof {
-        expected_proof: ChunkProof,
-        nonce: Nonce,
-    },
+    /// Uses the hash based verification for chunks: the proofs of the holders are checked with
+    /// `ChunkProof::verify` against the bytes they should be storing.
+    ChunkProof { stored_bytes: Bytes, nonce: Nonce },
+}
+
+impl Debug for VerificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationKind::Network => f.write_str("Network"),
+            // the stored bytes of a chunk are too many to be logged
+            VerificationKind::ChunkProof {
+                stored_bytes,
+                nonce,
+            } => f
+                .debug_struct("ChunkProof")
+                .field("stored_bytes_len", &stored_bytes.len())
+                .field("nonce", nonce)
+                .finish(),
+        }
+    }
 }
 
 /// NodeBehaviour struct
@@ -199,6 +217,10 @@ pub struct NetworkBuilder {
     enable_gossip: bool,
     request_timeout: Option<Duration>,
     concurrency_limit: Option<usize>,
+    record_kind_quotas: RecordKindQuotas,
+    restore_records: bool,
+    #[cfg(feature = "network-contacts")]
+    doh_resolver: Option<DohResolver>,
     #[cfg(feature = "open-metrics")]
     metrics_registry: Option<Registry>,
     #[cfg(feature = "open-metrics")]
@@ -215,6 +237,10 @@ impl NetworkBuilder {
             enable_gossip: false,
             request_timeout: None,
             concurrency_limit: None,
+            record_kind_quotas: RecordKindQuotas::default(),
+            restore_records: false,
+            #[cfg(feature = "network-contacts")]
+            doh_resolver: None,
             #[cfg(feature = "open-metrics")]
             metrics_registry: None,
             #[cfg(feature = "open-metrics")]
@@ -239,6 +265,24 @@ impl NetworkBuilder {
         self.concurrency_limit = Some(concurrency_limit);
     }
 
+    /// Limit the bytes the node stores for each kind of record. Unlimited by default.
+    pub fn record_kind_quotas(&mut self, record_kind_quotas: RecordKindQuotas) {
+        self.record_kind_quotas = record_kind_quotas;
+    }
+
+    /// Restore the records already in the record store dir at startup, see
+    /// `NodeRecordStore::restore_records_from_disk`. Off by default.
+    pub fn restore_records(&mut self, restore_records: bool) {
+        self.restore_records = restore_records;
+    }
+
+    /// Resolve the host names of the addresses dialed through DNS-over-HTTPS, instead of the
+    /// system resolver.
+    #[cfg(feature = "network-contacts")]
+    pub fn doh_resolver(&mut self, doh_resolver: DohResolver) {
+        self.doh_resolver = Some(doh_resolver);
+    }
+
     #[cfg(feature = "open-metrics")]
     pub fn metrics_registry(&mut self, metrics_registry: Registry) {
         self.metrics_registry = Some(metrics_registry);
@@ -302,6 +346,8 @@ impl NetworkBuilder {
             NodeRecordStoreConfig {
                 max_value_bytes: MAX_PACKET_SIZE, // TODO, does this need to be _less_ than MAX_PACKET_SIZE
                 storage_dir: storage_dir_path,
+                quotas: self.record_kind_quotas,
+                restore_records: self.restore_records,
                 ..Default::default()
             }
         };
@@ -406,6 +452,7 @@ impl NetworkBuilder {
         let kademlia = {
             match record_store_cfg {
                 Some(store_cfg) => {
+                    let restore_records = store_cfg.restore_records;
                     let node_record_store = NodeRecordStore::with_config(
                         peer_id,
                         store_cfg,
@@ -413,7 +460,13 @@ impl NetworkBuilder {
                     );
                     #[cfg(feature = "open-metrics")]
                     let node_record_store = node_record_store
-                        .set_record_count_metric(network_metrics.records_stored.clone());
+                        .set_record_count_metric(network_metrics.records_stored.clone())
+                        .set_record_kind_bytes_metrics(network_metrics.record_kind_bytes.clone());
+                    let node_record_store = if restore_records {
+                        node_record_store.restore_records_from_disk()
+                    } else {
+                        node_record_store
+                    };
                     let store = UnifiedRecordStore::Node(node_record_store);
                     debug!("Using Kademlia with NodeRecordStore!");
                     kad::Behaviour::with_config(peer_id, store, kad_cfg)
@@ -449,21 +502,12 @@ impl NetworkBuilder {
             libp2p::identify::Behaviour::new(cfg)
         };
 
-        // Transport
-        #[cfg(not(feature = "quic"))]
-        let mut transport = libp2p::tcp::tokio::Transport::new(libp2p::tcp::Config::default())
-            .upgrade(libp2p::core::upgrade::Version::V1)
-            .authenticate(
-                libp2p::noise::Config::new(&self.keypair)
-                    .expect("Signing libp2p-noise static DH keypair failed."),
-            )
-            .multiplex(libp2p::yamux::Config::default())
-            .boxed();
-
-        #[cfg(feature = "quic")]
-        let mut transport = libp2p::quic::tokio::Transport::new(quic::Config::new(&self.keypair))
-            .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
-            .boxed();
+        let transport = build_transport(&self.keypair, self.local)?;
+        #[cfg(feature = "network-contacts")]
+        let transport = match self.doh_resolver.clone() {
+            Some(doh_resolver) => DohTransport::new(transport, doh_resolver).boxed(),
+            None => transport,
+        };
 
         let gossipsub = if self.enable_gossip {
             // Gossipsub behaviour
@@ -501,12 +545,6 @@ impl NetworkBuilder {
 
         let gossipsub = Toggle::from(gossipsub);
 
-        if !self.local {
-            debug!("Preventing non-global dials");
-            // Wrap TCP or UDP in a transport that prevents dialing local addresses.
-            transport = libp2p::core::transport::global_only::Transport::new(transport).boxed();
-        }
-
         // Disable AutoNAT if we are either running locally or a client.
         let autonat = if !self.local && !is_client {
             let cfg = libp2p::autonat::Config {
@@ -636,6 +674,11 @@ impl SwarmDriver {
                     }
                 },
                 some_cmd = self.cmd_receiver.recv() => match some_cmd {
+                    Some(SwarmCmd::Shutdown { sender }) => {
+                        info!("SwarmDriver is shutting down");
+                        let _ = sender.send(());
+                        break;
+                    }
                     Some(cmd) => {
                         let start = std::time::Instant::now();
                         let cmd_string = format!("{cmd:?}");
@@ -644,7 +687,10 @@ impl SwarmDriver {
                         }
                         trace!("SwarmCmd handled in {:?}: {cmd_string:?}", start.elapsed());
                     },
-                    None =>  continue,
+                    None => {
+                        info!("All SwarmCmd senders are gone, SwarmDriver is shutting down");
+                        break;
+                    }
                 },
                 // runs every bootstrap_interval time
                 _ = bootstrap_interval.tick() => {
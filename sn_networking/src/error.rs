@@ -13,18 +13,26 @@ use libp2p::{
     swarm::DialError,
     PeerId, TransportError,
 };
-use sn_protocol::{messages::Response, storage::RecordKind, NetworkAddress, PrettyPrintRecordKey};
+use sn_protocol::{
+    error::Error as ProtocolError,
+    messages::{RegisterClaim, Response},
+    storage::RecordKind,
+    NetworkAddress, PrettyPrintRecordKey,
+};
 use sn_transfers::{SignedSpend, SpendAddress};
 use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
     io,
     path::PathBuf,
+    time::Duration,
 };
 use thiserror::Error;
 use tokio::sync::oneshot;
 use xor_name::XorName;
 
+use crate::{ChunkReplicationReport, MAX_BUSY_RETRY_AFTER};
+
 pub(super) type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// GetRecord Query errors
@@ -98,6 +106,9 @@ pub enum Error {
     #[error("Kademlia Store error: {0}")]
     KademliaStoreError(#[from] kad::store::Error),
 
+    #[error("Storage quota for {kind:?} records reached")]
+    StorageFull { kind: RecordKind },
+
     #[error("Transport Error")]
     TransportError(#[from] TransportError<std::io::Error>),
 
@@ -129,9 +140,19 @@ pub enum Error {
     #[error("Transfer is invalid: {0}")]
     InvalidTransfer(String),
 
+    #[error("CashNoteRedemption #{index}, of parent spend {parent_spend:?}, is invalid: {reason}")]
+    InvalidCashNoteRedemption {
+        index: usize,
+        parent_spend: SpendAddress,
+        reason: String,
+    },
+
     // ---------- Chunk Errors
-    #[error("Failed to verify the ChunkProof with the provided quorum")]
-    FailedToVerifyChunkProof(NetworkAddress),
+    #[error(
+        "Failed to verify the ChunkProof with the provided quorum, {} of the close nodes hold the chunk",
+        .1.holders_ok.len()
+    )]
+    FailedToVerifyChunkProof(NetworkAddress, Box<ChunkReplicationReport>),
 
     // ---------- Spend Errors
     #[error("Spend not found: {0:?}")]
@@ -144,6 +165,12 @@ pub enum Error {
     #[error("No Store Cost Responses")]
     NoStoreCostResponses,
 
+    #[error("Too many peers are busy to fulfil the request, retry after {retry_after:?}")]
+    PeersBusy { retry_after: Duration },
+
+    #[error("The Register is being uploaded under a competing claim: {0:?}")]
+    RegisterClaimed(RegisterClaim),
+
     #[error("Could not create storage dir: {path:?}, error: {source}")]
     FailedToCreateRecordStoreDir {
         path: PathBuf,
@@ -160,6 +187,13 @@ pub enum Error {
     #[error("Gossipsub subscribe Error: {0}")]
     GossipsubSubscriptionError(#[from] SubscriptionError),
 
+    // ---------- Retry Errors
+    #[error("Gave up after {attempts} attempts, the last one failed with: {last_error}")]
+    RetriesExhausted {
+        attempts: usize,
+        last_error: Box<Error>,
+    },
+
     // ---------- Internal Network Errors
     #[error("Could not get enough peers ({required}) to satisfy the request, found {found}")]
     NotEnoughPeers { found: usize, required: usize },
@@ -177,6 +211,14 @@ pub enum Error {
     #[error("Network Metric error")]
     NetworkMetricError,
 
+    #[cfg(feature = "network-contacts")]
+    #[error("Could not resolve {host} with DNS-over-HTTPS: {reason}")]
+    DohQueryFailed { host: String, reason: String },
+
+    #[cfg(feature = "network-contacts")]
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+
     // ---------- Channel Errors
     #[error("Outbound Error")]
     OutboundError(#[from] OutboundFailure),
@@ -197,6 +239,38 @@ pub enum Error {
     OutgoingResponseDropped(Response),
 }
 
+impl Error {
+    /// How long to wait before retrying, if this error is due to busy peers. Looks through
+    /// `RetriesExhausted`. The hint given by the peers is capped at `MAX_BUSY_RETRY_AFTER`.
+    pub fn busy_retry_after(&self) -> Option<Duration> {
+        let retry_after = match self.last_cause() {
+            Self::PeersBusy { retry_after } => *retry_after,
+            Self::ProtocolError(ProtocolError::Busy { retry_after_ms }) => {
+                Duration::from_millis(*retry_after_ms)
+            }
+            _ => return None,
+        };
+        Some(retry_after.min(MAX_BUSY_RETRY_AFTER))
+    }
+
+    /// Which of the close nodes held the chunk, if this error is due to a failed chunk
+    /// verification. Looks through `RetriesExhausted`.
+    pub fn chunk_replication_report(&self) -> Option<&ChunkReplicationReport> {
+        match self.last_cause() {
+            Self::FailedToVerifyChunkProof(_, report) => Some(report),
+            _ => None,
+        }
+    }
+
+    /// The error the operation last failed with, looking through `RetriesExhausted`.
+    pub fn last_cause(&self) -> &Error {
+        match self {
+            Self::RetriesExhausted { last_error, .. } => last_error.last_cause(),
+            _ => self,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use sn_protocol::{storage::ChunkAddress, NetworkAddress, PrettyPrintKBucketKey};
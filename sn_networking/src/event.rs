@@ -33,7 +33,8 @@ use libp2p::{
 };
 
 use sn_protocol::{
-    messages::{CmdResponse, Query, Request, Response},
+    error::Error as ProtocolError,
+    messages::{CmdResponse, Query, QueryResponse, Request, Response},
     storage::RecordType,
     NetworkAddress, PrettyPrintRecordKey,
 };
@@ -125,6 +126,12 @@ pub enum NetworkEvent {
     PeerAdded(PeerId, usize),
     // Peer has been removed from the Routing Table. And the number of connected peers.
     PeerRemoved(PeerId, usize),
+    /// A first connection to a peer has been established. And the number of peers we now hold
+    /// a live connection to.
+    PeerConnected(PeerId, usize),
+    /// The last connection to a peer has been closed. And the number of peers we still hold
+    /// a live connection to.
+    PeerDisconnected(PeerId, usize),
     /// The records bearing these keys are to be fetched from the holder or the network
     KeysToFetchForReplication(Vec<(PeerId, RecordKey)>),
     /// Started listening on a new address
@@ -137,6 +144,13 @@ pub enum NetworkEvent {
     FailedToWrite(RecordKey),
     /// Report a completed write so we can safely add this to the record store now
     CompletedWrite((RecordKey, RecordType)),
+    /// A peer responded it is busy, asking to be left alone for a while
+    PeerBusy {
+        /// The busy peer
+        peer: PeerId,
+        /// How long the peer asks to be left alone for
+        retry_after: Duration,
+    },
     /// Gossipsub message received
     GossipsubMsgReceived {
         /// Topic the message was published on
@@ -172,6 +186,15 @@ impl Debug for NetworkEvent {
                     "NetworkEvent::PeerRemoved({peer_id:?}, {connected_peers})"
                 )
             }
+            NetworkEvent::PeerConnected(peer_id, live_peers) => {
+                write!(f, "NetworkEvent::PeerConnected({peer_id:?}, {live_peers})")
+            }
+            NetworkEvent::PeerDisconnected(peer_id, live_peers) => {
+                write!(
+                    f,
+                    "NetworkEvent::PeerDisconnected({peer_id:?}, {live_peers})"
+                )
+            }
             NetworkEvent::KeysToFetchForReplication(list) => {
                 let keys_len = list.len();
                 write!(f, "NetworkEvent::KeysForReplication({keys_len:?})")
@@ -197,6 +220,9 @@ impl Debug for NetworkEvent {
                     "NetworkEvent::CompletedWrite({pretty_key:?}, {record_type:?})"
                 )
             }
+            NetworkEvent::PeerBusy { peer, retry_after } => {
+                write!(f, "NetworkEvent::PeerBusy({peer:?}, {retry_after:?})")
+            }
             NetworkEvent::GossipsubMsgReceived { topic, .. } => {
                 write!(f, "NetworkEvent::GossipsubMsgReceived({topic})")
             }
@@ -475,6 +501,13 @@ impl SwarmDriver {
                         .push(peer_id)
                         .map_err(|_| Error::CircularVecPopFrontError)?;
                 }
+
+                if num_established.get() == 1 {
+                    self.send_event(NetworkEvent::PeerConnected(
+                        peer_id,
+                        self.live_peers_count(),
+                    ));
+                }
             }
             SwarmEvent::ConnectionClosed {
                 peer_id,
@@ -487,6 +520,13 @@ impl SwarmDriver {
                 // info!(%peer_id, ?connection_id, "ConnectionClosed: {:?}", self.swarm.network_info());
                 trace!(%peer_id, ?connection_id, ?cause, num_established, "ConnectionClosed: {}", endpoint_str(&endpoint));
                 let _ = self.live_connected_peers.remove(&connection_id);
+
+                if num_established == 0 {
+                    self.send_event(NetworkEvent::PeerDisconnected(
+                        peer_id,
+                        self.live_peers_count(),
+                    ));
+                }
             }
             SwarmEvent::OutgoingConnectionError {
                 peer_id: Some(failed_peer_id),
@@ -663,6 +703,23 @@ impl SwarmDriver {
                     response,
                 } => {
                     trace!("Got response {request_id:?} from peer {peer:?}, res: {response}.");
+                    if let Response::Query(
+                        QueryResponse::GetStoreCost {
+                            quote: Err(ProtocolError::Busy { retry_after_ms }),
+                            ..
+                        }
+                        | QueryResponse::GetChunkExistenceProof(Err(ProtocolError::Busy {
+                            retry_after_ms,
+                        })),
+                    ) = &response
+                    {
+                        #[cfg(feature = "open-metrics")]
+                        let _ = self.network_metrics.busy_responses.inc();
+                        self.send_event(NetworkEvent::PeerBusy {
+                            peer,
+                            retry_after: Duration::from_millis(*retry_after_ms),
+                        });
+                    }
                     if let Some(sender) = self.pending_requests.remove(&request_id) {
                         // The sender will be provided if the caller (Requester) is awaiting for a response
                         // at the call site.
@@ -909,11 +966,11 @@ impl SwarmDriver {
                 }
 
                 info!("kad_event::RoutingUpdated {:?}: {peer:?}, is_new_peer: {is_new_peer:?} old_peer: {old_peer:?}", self.connected_peers);
-                if old_peer.is_some() {
+                if let Some(old_peer) = old_peer {
                     self.connected_peers = self.connected_peers.saturating_sub(1);
 
                     info!("Evicted old peer on new peer join: {old_peer:?}");
-                    self.send_event(NetworkEvent::PeerRemoved(peer, self.connected_peers));
+                    self.send_event(NetworkEvent::PeerRemoved(old_peer, self.connected_peers));
                     self.log_kbuckets(&peer);
                 }
                 let _ = self.check_for_change_in_our_close_group();
@@ -1052,6 +1109,15 @@ impl SwarmDriver {
         }
     }
 
+    // The number of distinct peers we hold a live connection to.
+    fn live_peers_count(&self) -> usize {
+        self.live_connected_peers
+            .values()
+            .map(|(peer_id, _)| peer_id)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
     // Remove outdated connection to a peer if it is not in the RT.
     fn remove_outdated_connections(&mut self) {
         let mut shall_removed = vec![];
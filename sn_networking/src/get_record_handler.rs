@@ -20,14 +20,11 @@ use xor_name::XorName;
 
 /// Using XorName to differentiate different record content under the same key.
 type GetRecordResultMap = HashMap<XorName, (Record, HashSet<PeerId>)>;
-pub(crate) type PendingGetRecord = HashMap<
-    QueryId,
-    (
-        oneshot::Sender<std::result::Result<Record, GetRecordError>>,
-        GetRecordResultMap,
-        GetRecordCfg,
-    ),
->;
+/// The fetched record, along with the peers that returned it.
+pub(crate) type GetRecordResultSender =
+    oneshot::Sender<std::result::Result<(Record, Vec<PeerId>), GetRecordError>>;
+pub(crate) type PendingGetRecord =
+    HashMap<QueryId, (GetRecordResultSender, GetRecordResultMap, GetRecordCfg)>;
 
 // For `get_record` returning behaviour:
 //   1, targeting a non-existing entry
@@ -110,7 +107,16 @@ impl SwarmDriver {
                 let (sender, result_map, _) = entry.remove();
 
                 if result_map.len() == 1 {
-                    Self::send_record_after_checking_target(sender, peer_record.record, &cfg)?;
+                    let holders = result_map
+                        .into_values()
+                        .flat_map(|(_, peers)| peers)
+                        .collect();
+                    Self::send_record_after_checking_target(
+                        sender,
+                        peer_record.record,
+                        holders,
+                        &cfg,
+                    )?;
                 } else {
                     debug!("For record {pretty_key:?} task {query_id:?}, fetch completed with split record");
                     sender
@@ -274,7 +280,12 @@ impl SwarmDriver {
                 // if we have enough responses here, we can return the record
                 if let Some((record, peers)) = result_map.values().next() {
                     if peers.len() >= required_response_count {
-                        Self::send_record_after_checking_target(sender, record.clone(), &cfg)?;
+                        Self::send_record_after_checking_target(
+                            sender,
+                            record.clone(),
+                            peers.iter().cloned().collect(),
+                            &cfg,
+                        )?;
                         return Ok(());
                     }
                 }
@@ -291,13 +302,14 @@ impl SwarmDriver {
     }
 
     fn send_record_after_checking_target(
-        sender: oneshot::Sender<std::result::Result<Record, GetRecordError>>,
+        sender: GetRecordResultSender,
         record: Record,
+        holders: Vec<PeerId>,
         cfg: &GetRecordCfg,
     ) -> Result<()> {
         if cfg.target_record.is_none() || cfg.does_target_match(&record) {
             sender
-                .send(Ok(record))
+                .send(Ok((record, holders)))
                 .map_err(|_| Error::InternalMsgChannelDropped)
         } else {
             sender
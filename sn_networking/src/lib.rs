@@ -12,6 +12,8 @@ extern crate tracing;
 mod bootstrap;
 mod circular_vec;
 mod cmd;
+#[cfg(feature = "network-contacts")]
+mod doh;
 mod driver;
 mod error;
 mod event;
@@ -24,15 +26,22 @@ mod network_discovery;
 mod record_store;
 mod record_store_api;
 mod replication_fetcher;
+mod replication_report;
+mod retry;
 mod transfers;
+mod transport;
 
+#[cfg(feature = "network-contacts")]
+pub use self::doh::{DohResolver, DOH_QUERY_TIMEOUT, MAX_DOH_CACHE_TTL};
 pub use self::{
     cmd::SwarmLocalState,
     driver::{GetRecordCfg, NetworkBuilder, PutRecordCfg, SwarmDriver, VerificationKind},
     error::{Error, GetRecordError},
     event::{MsgResponder, NetworkEvent},
-    record_store::NodeRecordStore,
-    transfers::get_singed_spends_from_record,
+    record_store::{NodeRecordStore, RecordKindQuotas, RecordKindUsage},
+    replication_report::ChunkReplicationReport,
+    retry::{RetryPolicy, DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_ATTEMPTS, DEFAULT_MAX_BACKOFF},
+    transfers::{get_singed_spends_from_record, MAX_CONCURRENT_SPEND_GETS},
 };
 
 use self::{cmd::SwarmCmd, error::Result};
@@ -48,15 +57,16 @@ use libp2p::{
 use rand::Rng;
 use sn_protocol::{
     error::Error as ProtocolError,
-    messages::{ChunkProof, Nonce, Query, QueryResponse, Request, Response},
-    storage::RecordType,
+    messages::{Nonce, Query, QueryResponse, Request, Response, SignedRegisterClaim},
+    storage::{RecordType, RegisterAddress},
     NetworkAddress, PrettyPrintKBucketKey, PrettyPrintRecordKey,
 };
 use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote};
 use std::{
     collections::{BTreeMap, HashMap},
+    future::Future,
     path::PathBuf,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::sync::{mpsc, oneshot};
 
@@ -79,18 +89,14 @@ pub const fn close_group_majority() -> usize {
     CLOSE_GROUP_SIZE / 2 + 1
 }
 
-/// Max duration for all GET attempts
-const MAX_GET_RETRY_DURATION_MS: u64 = 6800;
-const MAX_GET_RETRY_DURATION: Duration = Duration::from_millis(MAX_GET_RETRY_DURATION_MS);
-/// Max duration for all PUT attempts
-const MAX_PUT_RETRY_DURATION: Duration = Duration::from_millis(MAX_GET_RETRY_DURATION_MS * 3);
-
 /// Max duration to wait for verification.
 const MAX_WAIT_BEFORE_READING_A_PUT: Duration = Duration::from_millis(750);
 /// Min duration to wait for verification
 const MIN_WAIT_BEFORE_READING_A_PUT: Duration = Duration::from_millis(300);
-/// Number of attempts to get a ChunkProof
-const GET_CHUNK_PROOF_RETRY_ATTEMPTS: usize = 3;
+/// The longest we honour a busy peer's retry-after hint for, whatever it asks
+pub const MAX_BUSY_RETRY_AFTER: Duration = Duration::from_secs(10);
+/// Max duration for all attempts at a request while peers report being busy
+const MAX_BUSY_RETRY_DURATION: Duration = Duration::from_secs(60);
 
 /// Sort the provided peers by their distance to the given `NetworkAddress`.
 /// Return with the closest expected number of entries if has.
@@ -196,6 +202,17 @@ impl Network {
             .map_err(|_e| Error::InternalMsgChannelDropped)
     }
 
+    /// Returns the peers of our local Routing Table along with their known addresses, from the
+    /// closest to the farthest.
+    /// Does not include self
+    pub async fn get_routing_table_addrs(&self) -> Result<Vec<(PeerId, Vec<Multiaddr>)>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_swarm_cmd(SwarmCmd::GetRoutingTableAddrs { sender })?;
+        receiver
+            .await
+            .map_err(|_e| Error::InternalMsgChannelDropped)
+    }
+
     /// Returns the closest peers to the given `NetworkAddress` that is fetched from the local
     /// Routing Table. It is ordered by increasing distance of the peers
     /// Note self peer_id is not included in the result.
@@ -257,24 +274,29 @@ impl Network {
             .map_err(|_e| Error::InternalMsgChannelDropped)
     }
 
-    /// Get the Chunk existence proof from the close nodes to the provided chunk address.
+    /// Get the Chunk existence proof from the close nodes to the provided chunk address, each
+    /// checked against the bytes the nodes should be storing.
+    /// Retries as set by the `retry_policy`.
+    ///
+    /// Returns which of the close nodes proved to hold the chunk on the last attempt. If the
+    /// quorum was never met, the report of the last attempt is carried by the
+    /// `FailedToVerifyChunkProof` error. Fails with `PeersBusy` as soon as the quorum is only
+    /// missed because of peers too busy to have taken the chunk, for it to be put again, and with
+    /// the `RoyaltyRateMismatch` of the peers which refused the payment for the chunk.
     pub async fn verify_chunk_existence(
         &self,
         chunk_address: NetworkAddress,
         nonce: Nonce,
-        expected_proof: ChunkProof,
+        stored_bytes: &[u8],
         quorum: Quorum,
-        re_attempt: bool,
-    ) -> Result<()> {
-        let total_attempts = if re_attempt {
-            GET_CHUNK_PROOF_RETRY_ATTEMPTS
-        } else {
-            1
-        };
+        retry_policy: &RetryPolicy,
+    ) -> Result<ChunkReplicationReport> {
+        let total_attempts = retry_policy.attempts();
         let pretty_key = PrettyPrintRecordKey::from(&chunk_address.to_record_key()).into_owned();
         let expected_n_verified = get_quorum_value(&quorum);
 
         let mut close_nodes = Vec::new();
+        let mut report = ChunkReplicationReport::default();
         let mut retry_attempts = 0;
         while retry_attempts < total_attempts {
             // the check should happen before incrementing retry_attempts
@@ -296,48 +318,173 @@ impl Network {
             let responses = self
                 .send_and_get_responses(&close_nodes, &request, true)
                 .await;
-            let n_verified = responses
-                .into_iter()
-                .filter_map(|(peer, resp)| {
-                    if let Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(proof)))) =
-                        resp
-                    {
-                        if expected_proof.verify(&proof) {
+            let mut holders_ok = Vec::new();
+            let mut busy_retry_after = vec![];
+            let mut refusal = None;
+            for (peer, resp) in responses {
+                match resp {
+                    Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(proof)))) => {
+                        if proof.verify(stored_bytes, nonce) {
                             debug!("Got a valid ChunkProof from {peer:?}");
-                            Some(())
+                            holders_ok.push(peer);
                         } else {
                             warn!("Failed to verify the ChunkProof from {peer:?}. The chunk might have been tampered?");
-                            None
                         }
-                    } else {
+                    }
+                    Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Err(
+                        ProtocolError::Busy { retry_after_ms },
+                    )))) => {
+                        debug!("{peer:?} is too busy to have stored the chunk, asking to retry after {retry_after_ms}ms");
+                        busy_retry_after.push(Duration::from_millis(retry_after_ms));
+                    }
+                    Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Err(
+                        err @ ProtocolError::RoyaltyRateMismatch { .. },
+                    )))) => {
+                        warn!("{peer:?} refused the payment for the chunk: {err}");
+                        refusal = Some(err);
+                    }
+                    _ => {
                         debug!("Did not get a valid response for the ChunkProof from {peer:?}");
-                        None
                     }
-                })
-                .count();
+                }
+            }
+            let n_verified = holders_ok.len();
             debug!("Got {n_verified} verified chunk existence proofs for chunk_address {chunk_address:?}");
 
-            if n_verified >= expected_n_verified {
-                return Ok(());
+            // the close nodes which did not respond at all are missing as well
+            report = ChunkReplicationReport {
+                holders_missing: close_nodes
+                    .iter()
+                    .filter(|peer| !holders_ok.contains(peer))
+                    .cloned()
+                    .collect(),
+                holders_ok,
+                quorum_met: n_verified >= expected_n_verified,
+            };
+            if report.quorum_met {
+                return Ok(report);
             }
             warn!("The obtained {n_verified} verified proofs did not match the expected {expected_n_verified} verified proofs");
-            // Sleep to avoid firing queries too close to even choke the nodes further.
-            let waiting_time = if retry_attempts == 1 {
-                MIN_WAIT_BEFORE_READING_A_PUT
-            } else {
-                MIN_WAIT_BEFORE_READING_A_PUT + MIN_WAIT_BEFORE_READING_A_PUT
-            };
-            tokio::time::sleep(waiting_time).await;
+            // the chunk is to be paid for again, at the rate of the network
+            if let Some(refusal) = refusal {
+                return Err(Error::ProtocolError(refusal));
+            }
+            // the busy peers refused the chunk, which is to be put again once they can take it
+            let retry_after = busy_retry_after.iter().max().copied();
+            if let Some(retry_after) = retry_after {
+                if n_verified + busy_retry_after.len() >= expected_n_verified {
+                    return Err(Error::PeersBusy { retry_after });
+                }
+            }
+            if retry_attempts < total_attempts {
+                // Sleep to avoid firing queries too close to even choke the nodes further.
+                tokio::time::sleep(retry_policy.delay(retry_attempts)).await;
+            }
         }
 
-        Err(Error::FailedToVerifyChunkProof(chunk_address.clone()))
+        Err(retry_policy.exhausted(Error::FailedToVerifyChunkProof(
+            chunk_address,
+            Box::new(report),
+        )))
+    }
+
+    /// Ask the close nodes to the provided chunk address whether they hold the chunk, without
+    /// fetching it. Returns the peers which answered with a `ChunkProof`.
+    ///
+    /// The proofs are not checked as that requires the chunk content, the chunk is only known
+    /// to exist, not to be intact.
+    pub async fn get_chunk_existence_holders(
+        &self,
+        chunk_address: NetworkAddress,
+        nonce: Nonce,
+    ) -> Result<Vec<PeerId>> {
+        let close_nodes = self.get_closest_peers(&chunk_address, true).await?;
+        let request = Request::Query(Query::GetChunkExistenceProof {
+            key: chunk_address.clone(),
+            nonce,
+        });
+        let holders: Vec<_> = self
+            .send_and_get_responses(&close_nodes, &request, true)
+            .await
+            .into_iter()
+            .filter_map(|(peer, resp)| match resp {
+                Ok(Response::Query(QueryResponse::GetChunkExistenceProof(Ok(_)))) => Some(peer),
+                _ => None,
+            })
+            .collect();
+        debug!(
+            "{} of {} close nodes hold chunk_address {chunk_address:?}",
+            holders.len(),
+            close_nodes.len()
+        );
+        Ok(holders)
     }
 
     /// Get the store costs from the majority of the closest peers to the provided RecordKey.
+    ///
+    /// Retries, waiting as long as asked to, while the close group is too busy to quote.
     pub async fn get_store_costs_from_network(
         &self,
         record_address: NetworkAddress,
     ) -> Result<(PeerId, MainPubkey, PaymentQuote)> {
+        let record_address = &record_address;
+        retry_when_busy(|| async move {
+            let (all_costs, record_exists) =
+                self.get_close_group_quotes_once(record_address).await?;
+
+            // Ensure we dont have any further out nodes than `close_group_majority()`
+            // This should ensure that if we didnt get all responses from close nodes, we're less likely to be
+            // paying a node that is not in the CLOSE_GROUP
+            let all_costs = all_costs.into_iter().take(close_group_majority()).collect();
+
+            get_fees_from_store_cost_responses(all_costs, record_exists)
+        })
+        .await
+    }
+
+    /// Get the store cost quotes of all the close group peers of the provided RecordKey, from the
+    /// closest peer to the farthest, leaving it to the caller to pick the payee.
+    ///
+    /// If the majority of the close group already holds the record, a single zero cost quote of
+    /// one of them is returned instead. Retries, waiting as long as asked to, while the close
+    /// group is too busy to quote.
+    pub async fn get_store_cost_quotes(
+        &self,
+        record_address: NetworkAddress,
+    ) -> Result<Vec<(PeerId, MainPubkey, PaymentQuote)>> {
+        let record_address = &record_address;
+        retry_when_busy(|| async move {
+            let (all_costs, record_exists) =
+                self.get_close_group_quotes_once(record_address).await?;
+            if record_exists.len() >= close_group_majority() {
+                return get_fees_from_store_cost_responses(vec![], record_exists)
+                    .map(|payee| vec![payee]);
+            }
+
+            let quotes: Vec<_> = all_costs
+                .into_iter()
+                .filter_map(|(peer_address, payment_address, quote)| {
+                    let peer_id = peer_address.as_peer_id();
+                    if peer_id.is_none() {
+                        error!("Can't get PeerId from quoting peer {peer_address:?}");
+                    }
+                    peer_id.map(|peer_id| (peer_id, payment_address, quote))
+                })
+                .collect();
+            if quotes.is_empty() {
+                return Err(Error::NoStoreCostResponses);
+            }
+            Ok(quotes)
+        })
+        .await
+    }
+
+    /// Get the quotes of the close group of the record, sorted by their proximity to it, along
+    /// with the peers reporting the record as already stored.
+    async fn get_close_group_quotes_once(
+        &self,
+        record_address: &NetworkAddress,
+    ) -> Result<StoreCostResponses> {
         // The requirement of having at least CLOSE_GROUP_SIZE
         // close nodes will be checked internally automatically.
         let close_nodes = self.get_closest_peers(&record_address, true).await?;
@@ -349,6 +496,8 @@ impl Network {
 
         // loop over responses, generating an average fee and storing all responses along side
         let mut all_costs = vec![];
+        let mut record_exists = vec![];
+        let mut busy_retry_after = vec![];
         for response in responses.into_values().flatten() {
             debug!(
                 "StoreCostReq for {record_address:?} received response: {:?}",
@@ -367,7 +516,13 @@ impl Network {
                     payment_address,
                     peer_address,
                 }) => {
-                    all_costs.push((peer_address, payment_address, PaymentQuote::zero()));
+                    record_exists.push((peer_address, payment_address));
+                }
+                Response::Query(QueryResponse::GetStoreCost {
+                    quote: Err(ProtocolError::Busy { retry_after_ms }),
+                    ..
+                }) => {
+                    busy_retry_after.push(Duration::from_millis(retry_after_ms));
                 }
                 _ => {
                     error!("Non store cost response received,  was {:?}", response);
@@ -382,12 +537,63 @@ impl Network {
                 .cmp(&record_address.distance(peer_address_b))
         });
 
-        // Ensure we dont have any further out nodes than `close_group_majority()`
-        // This should ensure that if we didnt get all responses from close nodes, we're less likely to be
-        // paying a node that is not in the CLOSE_GROUP
-        let all_costs = all_costs.into_iter().take(close_group_majority()).collect();
+        // Too many of the close group are busy to rely on the few quotes we got
+        if let Some(retry_after) = busy_retry_after.into_iter().max() {
+            if all_costs.len() + record_exists.len() < close_group_majority() {
+                return Err(Error::PeersBusy { retry_after });
+            }
+        }
+
+        Ok((all_costs, record_exists))
+    }
+
+    /// Have the close group of a Register hold our claim on it, before paying for its creation.
+    ///
+    /// Returns whether the majority of the close group holds our claim. Fails with the competing
+    /// claim only if the majority rejected ours, as the Register is being uploaded by someone
+    /// else: nodes too busy to hold claims, or not knowing of them, don't keep the Register from
+    /// being created unclaimed.
+    pub async fn claim_register(
+        &self,
+        address: RegisterAddress,
+        claim: SignedRegisterClaim,
+    ) -> Result<bool> {
+        let close_nodes = self
+            .get_closest_peers(&NetworkAddress::from_register_address(address), true)
+            .await?;
+
+        let request = Request::Query(Query::ClaimRegister { address, claim });
+        let responses = self
+            .send_and_get_responses(&close_nodes, &request, true)
+            .await;
+
+        let mut held = 0;
+        let mut competing_claims = vec![];
+        for response in responses.into_values().flatten() {
+            debug!("ClaimRegister for {address:?} received response: {response:?}");
+            match response {
+                Response::Query(QueryResponse::ClaimRegister { result: Ok(()), .. }) => held += 1,
+                Response::Query(QueryResponse::ClaimRegister {
+                    result: Err(ProtocolError::RegisterAlreadyClaimed(_)),
+                    held_claim,
+                }) => competing_claims.push(held_claim),
+                Response::Query(QueryResponse::ClaimRegister {
+                    result: Err(err), ..
+                }) => {
+                    warn!("ClaimRegister for {address:?} not held: {err:?}");
+                }
+                _ => {
+                    error!("Non ClaimRegister response received, was {response:?}");
+                }
+            }
+        }
 
-        get_fees_from_store_cost_responses(all_costs)
+        match competing_claims.first() {
+            Some(competing_claim) if competing_claims.len() >= close_group_majority() => {
+                Err(Error::RegisterClaimed(*competing_claim))
+            }
+            _ => Ok(held >= close_group_majority()),
+        }
     }
 
     /// Subscribe to given gossipsub topic
@@ -417,33 +623,41 @@ impl Network {
         key: RecordKey,
         cfg: &GetRecordCfg,
     ) -> Result<Record> {
-        backoff::future::retry(
-            ExponentialBackoff {
-                max_elapsed_time: Some(MAX_GET_RETRY_DURATION),
-                ..Default::default()
-            },
-            || async {
-                let pretty_key = PrettyPrintRecordKey::from(&key);
-                info!("Getting record from network of {pretty_key:?}. with cfg {cfg:?}",);
+        self.get_record_and_holders_from_network(key, cfg)
+            .await
+            .map(|(record, _holders)| record)
+    }
+
+    /// Get the Record from the network, along with the peers that returned it.
+    /// Carry out re-attempts if required, the same as `get_record_from_network`.
+    pub async fn get_record_and_holders_from_network(
+        &self,
+        key: RecordKey,
+        cfg: &GetRecordCfg,
+    ) -> Result<(Record, Vec<PeerId>)> {
+        let key = &key;
+        let pretty_key = &PrettyPrintRecordKey::from(key);
+        cfg.retry_policy
+            .run(|attempt| async move {
+                info!(
+                    "Getting record from network of {pretty_key:?}, attempt {attempt}/{}. with cfg {cfg:?}",
+                    cfg.retry_policy.attempts()
+                );
                 let (sender, receiver) = oneshot::channel();
                 self.send_swarm_cmd(SwarmCmd::GetNetworkRecord {
                     key: key.clone(),
                     sender,
                     cfg: cfg.clone(),
-                })
-                .map_err(|err| BackoffError::Transient {
-                    err,
-                    retry_after: None,
                 })?;
                 let result = receiver.await.map_err(|e| {
-                error!("When fetching record {pretty_key:?}, encountered a channel error {e:?}");
-                Error::InternalMsgChannelDropped
-            }).map_err(|err| BackoffError::Transient { err,  retry_after: None })?;
+                    error!("When fetching record {pretty_key:?}, encountered a channel error {e:?}");
+                    Error::InternalMsgChannelDropped
+                })?;
 
                 // log the results
                 match &result {
-                    Ok(_) => {
-                        info!("Record returned: {pretty_key:?}.");
+                    Ok((_, holders)) => {
+                        info!("Record returned: {pretty_key:?} from {holders:?}.");
                     }
                     Err(GetRecordError::RecordDoesNotMatch(_)) => {
                         warn!("The returned record does not match target {pretty_key:?}.");
@@ -465,22 +679,9 @@ impl Network {
                     }
                 };
 
-                // if we dont want to retry, throw permanent error
-                if !cfg.re_attempt {
-                    if let Err(e) = result {
-                        return Err(BackoffError::Permanent(Error::from(e)));
-                    }
-                }
-                if result.is_err() {
-                    trace!("Getting record from network of {pretty_key:?} via backoff...");
-                }
-                result.map_err(|err| BackoffError::Transient {
-                    err: Error::from(err),
-                    retry_after: None,
-                })
-            },
-        )
-        .await
+                result.map_err(Error::from)
+            })
+            .await
     }
 
     /// Get the cost of storing the next record from the network
@@ -514,30 +715,27 @@ impl Network {
 
     /// Put `Record` to network
     /// Optionally verify the record is stored after putting it to network
-    /// If verify is on, retry multiple times within MAX_PUT_RETRY_DURATION duration.
+    /// Retries as set by the `retry_policy` of the cfg, waiting for at least as long as busy
+    /// peers ask to.
     pub async fn put_record(&self, record: Record, cfg: &PutRecordCfg) -> Result<()> {
         let pretty_key = PrettyPrintRecordKey::from(&record.key);
 
-        backoff::future::retry(ExponentialBackoff{
-            max_elapsed_time: Some(MAX_PUT_RETRY_DURATION),
-            ..Default::default()
-        }, || async {
-
-            info!(
-                "Attempting to PUT record with key: {pretty_key:?} to network, with cfg {cfg:?}, retrying via backoff..."
-            );
-            self.put_record_once(record.clone(), cfg).await.map_err(|err|
-            {
-                warn!("Failed to PUT record with key: {pretty_key:?} to network (retry via backoff) with error: {err:?}");
-
-                if cfg.re_attempt {
-                    BackoffError::Transient { err, retry_after: None }
-                } else {
-                    BackoffError::Permanent(err)
+        cfg.retry_policy
+            .run(|attempt| {
+                let record = record.clone();
+                let pretty_key = &pretty_key;
+                async move {
+                    info!(
+                        "Attempting to PUT record with key: {pretty_key:?} to network, attempt {attempt}/{}, with cfg {cfg:?}",
+                        cfg.retry_policy.attempts()
+                    );
+                    self.put_record_once(record, cfg).await.map_err(|err| {
+                        warn!("Failed to PUT record with key: {pretty_key:?} to network, attempt {attempt}, with error: {err:?}");
+                        err
+                    })
                 }
-
             })
-        }).await
+            .await
     }
 
     async fn put_record_once(&self, record: Record, cfg: &PutRecordCfg) -> Result<()> {
@@ -579,16 +777,16 @@ impl Network {
 
             // Verify the record is stored, requiring re-attempts
             if let VerificationKind::ChunkProof {
-                expected_proof,
+                stored_bytes,
                 nonce,
             } = verification_kind
             {
                 self.verify_chunk_existence(
                     NetworkAddress::from_record_key(&record_key),
                     *nonce,
-                    expected_proof.clone(),
+                    stored_bytes,
                     get_cfg.get_quorum,
-                    get_cfg.re_attempt,
+                    &get_cfg.retry_policy,
                 )
                 .await?;
             } else {
@@ -641,6 +839,16 @@ impl Network {
             .map_err(|_e| Error::InternalMsgChannelDropped)
     }
 
+    /// Returns the bytes held by the local RecordStore for each kind of record
+    pub async fn get_record_kind_usage(&self) -> Result<RecordKindUsage> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_swarm_cmd(SwarmCmd::GetRecordKindUsage { sender })?;
+
+        receiver
+            .await
+            .map_err(|_e| Error::InternalMsgChannelDropped)
+    }
+
     /// Returns the Addresses of all the locally stored Records
     pub async fn get_all_local_record_addresses(
         &self,
@@ -699,6 +907,15 @@ impl Network {
         self.send_swarm_cmd(SwarmCmd::TriggerIntervalReplication)
     }
 
+    /// Stop the SwarmDriver, waiting for it to acknowledge.
+    /// Any other clone of this `Network` won't be usable afterwards.
+    pub async fn shutdown(&self) -> Result<()> {
+        let (sender, receiver) = oneshot::channel();
+        self.send_swarm_cmd(SwarmCmd::Shutdown { sender })?;
+        receiver.await?;
+        Ok(())
+    }
+
     // Helper to send SwarmCmd
     fn send_swarm_cmd(&self, cmd: SwarmCmd) -> Result<()> {
         let capacity = self.swarm_cmd_sender.capacity();
@@ -803,11 +1020,41 @@ impl Network {
     }
 }
 
+/// The quotes of a close group, and the peers of it reporting the record as already stored.
+type StoreCostResponses = (
+    Vec<(NetworkAddress, MainPubkey, PaymentQuote)>,
+    Vec<(NetworkAddress, MainPubkey)>,
+);
+
 /// Given `all_costs` it will return the closest / lowest cost
 /// Closest requiring it to be within CLOSE_GROUP nodes
+/// If a majority of the close group reported the record as already existing, a zero quote is
+/// returned, meaning no payment is needed.
+/// Otherwise the `RecordExists` responses are disregarded and the lowest quote is picked.
 fn get_fees_from_store_cost_responses(
     mut all_costs: Vec<(NetworkAddress, MainPubkey, PaymentQuote)>,
+    mut record_exists: Vec<(NetworkAddress, MainPubkey)>,
 ) -> Result<(PeerId, MainPubkey, PaymentQuote)> {
+    if record_exists.len() >= close_group_majority() {
+        info!(
+            "Record reported as existing by {} peers, no payment needed",
+            record_exists.len()
+        );
+        record_exists.sort_by(|(address_a, _), (address_b, _)| address_a.cmp(address_b));
+        for (address, main_pubkey) in record_exists {
+            if let Some(peer_id) = address.as_peer_id() {
+                return Ok((peer_id, main_pubkey, PaymentQuote::zero()));
+            }
+        }
+        error!("Can't get PeerId from any of the peers reporting the record as existing");
+        return Err(Error::NoStoreCostResponses);
+    } else if !record_exists.is_empty() {
+        warn!(
+            "Only {} peers reported the record as existing, falling back to paying for it",
+            record_exists.len()
+        );
+    }
+
     // sort all costs by fee, lowest to highest
     // if there's a tie in cost, sort by pubkey
     all_costs.sort_by(
@@ -837,6 +1084,32 @@ fn get_fees_from_store_cost_responses(
     Ok((payee_id, payee.1, payee.2))
 }
 
+/// Retry the given operation for as long as it fails due to busy peers, waiting as long as they
+/// ask to, capped at `MAX_BUSY_RETRY_AFTER`, between attempts. Any other error is returned as is.
+async fn retry_when_busy<T, F, Fut>(mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let start = Instant::now();
+    backoff::future::retry(ExponentialBackoff::default(), || {
+        let attempt = operation();
+        async move {
+            attempt.await.map_err(|err| match err.busy_retry_after() {
+                Some(retry_after) if start.elapsed() + retry_after < MAX_BUSY_RETRY_DURATION => {
+                    warn!("Peers are busy, retrying after {retry_after:?}: {err:?}");
+                    BackoffError::Transient {
+                        err,
+                        retry_after: Some(retry_after),
+                    }
+                }
+                _ => BackoffError::Permanent(err),
+            })
+        }
+    })
+    .await
+}
+
 /// Get the value of the provided Quorum
 pub fn get_quorum_value(quorum: &Quorum) -> usize {
     match quorum {
@@ -890,6 +1163,7 @@ mod tests {
 
     use super::*;
     use sn_transfers::PaymentQuote;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_get_fee_from_store_cost_responses() -> Result<()> {
@@ -905,7 +1179,7 @@ mod tests {
             ));
         }
         let expected_price = costs[0].2.cost.as_nano();
-        let (_peer_id, _key, price) = get_fees_from_store_cost_responses(costs)?;
+        let (_peer_id, _key, price) = get_fees_from_store_cost_responses(costs, vec![])?;
 
         assert_eq!(
             price.cost.as_nano(),
@@ -936,7 +1210,7 @@ mod tests {
         // this should be the lowest price
         let expected_price = costs[0].2.cost.as_nano();
 
-        let (_peer_id, _key, price) = match get_fees_from_store_cost_responses(costs) {
+        let (_peer_id, _key, price) = match get_fees_from_store_cost_responses(costs, vec![]) {
             Err(_) => bail!("Should not have errored as we have enough responses"),
             Ok(cost) => cost,
         };
@@ -950,6 +1224,112 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_majority_record_exists_needs_no_payment() -> eyre::Result<()> {
+        let mut costs = vec![];
+        for i in 1..CLOSE_GROUP_SIZE {
+            costs.push((
+                NetworkAddress::from_peer(PeerId::random()),
+                MainPubkey::new(bls::SecretKey::random().public_key()),
+                PaymentQuote::test_dummy(Default::default(), NanoTokens::from(i as u64)),
+            ));
+        }
+        let record_exists: Vec<_> = (0..close_group_majority())
+            .map(|_| {
+                (
+                    NetworkAddress::from_peer(PeerId::random()),
+                    MainPubkey::new(bls::SecretKey::random().public_key()),
+                )
+            })
+            .collect();
+
+        let (_peer_id, _key, price) = get_fees_from_store_cost_responses(costs, record_exists)?;
+        assert_eq!(price.cost, NanoTokens::zero());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_minority_record_exists_falls_back_to_payment() -> eyre::Result<()> {
+        let mut costs = vec![];
+        for i in 1..CLOSE_GROUP_SIZE {
+            costs.push((
+                NetworkAddress::from_peer(PeerId::random()),
+                MainPubkey::new(bls::SecretKey::random().public_key()),
+                PaymentQuote::test_dummy(Default::default(), NanoTokens::from(i as u64)),
+            ));
+        }
+        let expected_price = costs[0].2.cost.as_nano();
+        let record_exists = vec![(
+            NetworkAddress::from_peer(PeerId::random()),
+            MainPubkey::new(bls::SecretKey::random().public_key()),
+        )];
+
+        let (_peer_id, _key, price) = get_fees_from_store_cost_responses(costs, record_exists)?;
+        assert_eq!(price.cost.as_nano(), expected_price);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn busy_peers_are_retried_after_their_hint() -> eyre::Result<()> {
+        let retry_after_ms = 1500;
+        let attempts = &AtomicUsize::new(0);
+        let start = Instant::now();
+
+        // fault injection: the peers are busy on the first attempt only
+        let result = retry_when_busy(move || async move {
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                Err(Error::PeersBusy {
+                    retry_after: Duration::from_millis(retry_after_ms),
+                })
+            } else {
+                Ok(())
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_millis(retry_after_ms));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn errors_other_than_busy_are_not_retried() {
+        let attempts = &AtomicUsize::new(0);
+        let result: Result<()> = retry_when_busy(move || async move {
+            let _ = attempts.fetch_add(1, Ordering::SeqCst);
+            Err(Error::NoStoreCostResponses)
+        })
+        .await;
+
+        assert!(matches!(result, Err(Error::NoStoreCostResponses)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn busy_retry_after_hint_is_capped() {
+        let err = Error::PeersBusy {
+            retry_after: Duration::from_secs(3600),
+        };
+        assert_eq!(err.busy_retry_after(), Some(MAX_BUSY_RETRY_AFTER));
+
+        let err = Error::PeersBusy {
+            retry_after: Duration::from_millis(200),
+        };
+        assert_eq!(err.busy_retry_after(), Some(Duration::from_millis(200)));
+        assert_eq!(Error::NoStoreCostResponses.busy_retry_after(), None);
+
+        let err = Error::RetriesExhausted {
+            attempts: 3,
+            last_error: Box::new(Error::ProtocolError(ProtocolError::Busy {
+                retry_after_ms: 3_600_000,
+            })),
+        };
+        assert_eq!(err.busy_retry_after(), Some(MAX_BUSY_RETRY_AFTER));
+    }
+
     #[test]
     fn test_network_sign_verify() -> eyre::Result<()> {
         let (network, _, _) =
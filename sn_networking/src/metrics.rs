@@ -7,13 +7,24 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use libp2p::metrics::{Metrics as Libp2pMetrics, Recorder};
-use prometheus_client::{metrics::gauge::Gauge, registry::Registry};
+use prometheus_client::{
+    metrics::{counter::Counter, gauge::Gauge},
+    registry::Registry,
+};
 use std::time::Duration;
 use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
 
 const UPDATE_INTERVAL: Duration = Duration::from_secs(15);
 const TO_MB: u64 = 1_000_000;
 
+/// The bytes stored per record kind
+#[derive(Clone, Debug)]
+pub(crate) struct RecordKindBytesMetrics {
+    pub(crate) chunks: Gauge,
+    pub(crate) spends: Gauge,
+    pub(crate) registers: Gauge,
+}
+
 pub(crate) struct NetworkMetrics {
     // Records libp2p related metrics
     // Must directly call self.libp2p_metrics.record(libp2p_event) with Recorder trait in scope. But since we have
@@ -22,6 +33,8 @@ pub(crate) struct NetworkMetrics {
 
     // metrics from sn_networking
     pub(crate) records_stored: Gauge,
+    pub(crate) record_kind_bytes: RecordKindBytesMetrics,
+    pub(crate) busy_responses: Counter,
 
     // system info
     process_memory_used_mb: Gauge,
@@ -40,6 +53,34 @@ impl NetworkMetrics {
             records_stored.clone(),
         );
 
+        let record_kind_bytes = RecordKindBytesMetrics {
+            chunks: Gauge::default(),
+            spends: Gauge::default(),
+            registers: Gauge::default(),
+        };
+        sub_registry.register(
+            "chunks_stored_bytes",
+            "The bytes of chunk records stored locally",
+            record_kind_bytes.chunks.clone(),
+        );
+        sub_registry.register(
+            "spends_stored_bytes",
+            "The bytes of spend records stored locally",
+            record_kind_bytes.spends.clone(),
+        );
+        sub_registry.register(
+            "registers_stored_bytes",
+            "The bytes of register records stored locally",
+            record_kind_bytes.registers.clone(),
+        );
+
+        let busy_responses = Counter::default();
+        sub_registry.register(
+            "busy_responses",
+            "The number of responses from peers asking us to back off as they are busy",
+            busy_responses.clone(),
+        );
+
         let process_memory_used_mb = Gauge::default();
         sub_registry.register(
             "process_memory_used_mb",
@@ -57,6 +98,8 @@ impl NetworkMetrics {
         let network_metrics = Self {
             libp2p_metrics,
             records_stored,
+            record_kind_bytes,
+            busy_responses,
             process_memory_used_mb,
             process_cpu_usage_percentage,
         };
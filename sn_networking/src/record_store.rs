@@ -7,7 +7,9 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 #![allow(clippy::mutable_key_type)] // for the Bytes in NetworkAddress
 
-use crate::event::NetworkEvent;
+#[cfg(feature = "open-metrics")]
+use crate::metrics::RecordKindBytesMetrics;
+use crate::{error::Error as NetworkError, event::NetworkEvent};
 use libp2p::{
     identity::PeerId,
     kad::{
@@ -18,7 +20,7 @@ use libp2p::{
 #[cfg(feature = "open-metrics")]
 use prometheus_client::metrics::gauge::Gauge;
 use sn_protocol::{
-    storage::{RecordHeader, RecordKind, RecordType},
+    storage::{validate_stored_record, RecordHeader, RecordKind, RecordType},
     NetworkAddress, PrettyPrintRecordKey,
 };
 use sn_transfers::NanoTokens;
@@ -51,6 +53,13 @@ pub struct NodeRecordStore {
     #[cfg(feature = "open-metrics")]
     /// Used to report the number of records held by the store to the metrics server.
     record_count_metric: Option<Gauge>,
+    #[cfg(feature = "open-metrics")]
+    /// Used to report the bytes held by the store per record kind to the metrics server.
+    record_kind_bytes_metrics: Option<RecordKindBytesMetrics>,
+    /// The kind and size of each record put to the store, used to enforce the quotas.
+    record_sizes: HashMap<Key, (RecordKind, usize)>,
+    /// The bytes currently held by the store per record kind.
+    usage: RecordKindUsage,
     /// Counting how many times got paid
     received_payment_count: usize,
 }
@@ -64,6 +73,11 @@ pub struct NodeRecordStoreConfig {
     pub max_records: usize,
     /// The maximum size of record values, in bytes.
     pub max_value_bytes: usize,
+    /// The maximum bytes stored per record kind.
+    pub quotas: RecordKindQuotas,
+    /// Whether to restore the records already in the storage dir at startup, see
+    /// `NodeRecordStore::restore_records_from_disk`.
+    pub restore_records: bool,
 }
 
 impl Default for NodeRecordStoreConfig {
@@ -72,6 +86,52 @@ impl Default for NodeRecordStoreConfig {
             storage_dir: std::env::temp_dir(),
             max_records: MAX_RECORDS_COUNT,
             max_value_bytes: 65 * 1024,
+            quotas: RecordKindQuotas::default(),
+            restore_records: false,
+        }
+    }
+}
+
+/// The maximum bytes a node stores for each kind of record. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordKindQuotas {
+    pub max_chunks_bytes: Option<usize>,
+    pub max_spends_bytes: Option<usize>,
+    pub max_registers_bytes: Option<usize>,
+}
+
+impl RecordKindQuotas {
+    fn max_bytes(&self, kind: RecordKind) -> Option<usize> {
+        match kind {
+            RecordKind::Chunk | RecordKind::ChunkWithPayment => self.max_chunks_bytes,
+            RecordKind::Spend => self.max_spends_bytes,
+            RecordKind::Register | RecordKind::RegisterWithPayment => self.max_registers_bytes,
+        }
+    }
+}
+
+/// The bytes held by a node's store for each kind of record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordKindUsage {
+    pub chunks_bytes: usize,
+    pub spends_bytes: usize,
+    pub registers_bytes: usize,
+}
+
+impl RecordKindUsage {
+    fn bytes_mut(&mut self, kind: RecordKind) -> &mut usize {
+        match kind {
+            RecordKind::Chunk | RecordKind::ChunkWithPayment => &mut self.chunks_bytes,
+            RecordKind::Spend => &mut self.spends_bytes,
+            RecordKind::Register | RecordKind::RegisterWithPayment => &mut self.registers_bytes,
+        }
+    }
+
+    fn bytes(&self, kind: RecordKind) -> usize {
+        match kind {
+            RecordKind::Chunk | RecordKind::ChunkWithPayment => self.chunks_bytes,
+            RecordKind::Spend => self.spends_bytes,
+            RecordKind::Register | RecordKind::RegisterWithPayment => self.registers_bytes,
         }
     }
 }
@@ -91,6 +151,10 @@ impl NodeRecordStore {
             distance_range: None,
             #[cfg(feature = "open-metrics")]
             record_count_metric: None,
+            #[cfg(feature = "open-metrics")]
+            record_kind_bytes_metrics: None,
+            record_sizes: Default::default(),
+            usage: Default::default(),
             received_payment_count: 0,
         }
     }
@@ -102,6 +166,141 @@ impl NodeRecordStore {
         self
     }
 
+    /// Set the metrics to report the bytes stored per record kind to the metrics server
+    #[cfg(feature = "open-metrics")]
+    pub(crate) fn set_record_kind_bytes_metrics(mut self, metrics: RecordKindBytesMetrics) -> Self {
+        self.record_kind_bytes_metrics = Some(metrics);
+        self
+    }
+
+    /// Hold the records already in the storage dir, e.g. written by a previous run of the node or
+    /// imported from another machine, so that they are served and replicated again.
+    ///
+    /// Files that are not named after a record key, or don't hold a valid record stored under
+    /// that key, are ignored, see `validate_stored_record`. No more than the maximum number of
+    /// records are restored.
+    pub fn restore_records_from_disk(mut self) -> Self {
+        let entries = match fs::read_dir(&self.config.storage_dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!(
+                    "Could not read the record store dir {:?} to restore its records: {err}",
+                    self.config.storage_dir
+                );
+                return self;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let Some(key) = entry
+                .file_name()
+                .to_str()
+                .and_then(|filename| hex::decode(filename).ok())
+                .map(|bytes| Key::new(&bytes))
+            else {
+                continue;
+            };
+            if self.records.len() >= self.config.max_records {
+                warn!(
+                    "Only restored the first {} records of {:?}, the store is full",
+                    self.records.len(),
+                    self.config.storage_dir
+                );
+                break;
+            }
+
+            let record = match fs::read(entry.path()) {
+                Ok(value) => Record {
+                    key: key.clone(),
+                    value,
+                    publisher: None,
+                    expires: None,
+                },
+                Err(err) => {
+                    warn!("Could not read the record file {:?}: {err}", entry.path());
+                    continue;
+                }
+            };
+            let kind = match validate_stored_record(&record) {
+                Ok(kind) => kind,
+                Err(err) => {
+                    warn!("Ignoring the invalid record file {:?}: {err}", entry.path());
+                    continue;
+                }
+            };
+            let record_type = match kind {
+                RecordKind::Chunk | RecordKind::ChunkWithPayment => RecordType::Chunk,
+                _ => RecordType::NonChunk(XorName::from_content(&record.value)),
+            };
+            self.reserve_record_kind_bytes(&key, kind, record.value.len());
+            self.mark_as_stored(key, record_type);
+        }
+
+        info!(
+            "Restored {} records from {:?}",
+            self.records.len(),
+            self.config.storage_dir
+        );
+        #[cfg(feature = "open-metrics")]
+        if let Some(metric) = &self.record_count_metric {
+            let _ = metric.set(self.records.len() as i64);
+        }
+        self
+    }
+
+    /// Check a record of the given kind and size, replacing any previous copy under the same key,
+    /// fits within the quota of its kind.
+    fn check_record_kind_quota(
+        &self,
+        key: &Key,
+        kind: RecordKind,
+        size: usize,
+    ) -> crate::error::Result<()> {
+        let Some(max_bytes) = self.config.quotas.max_bytes(kind) else {
+            return Ok(());
+        };
+
+        let replaced = match self.record_sizes.get(key) {
+            Some((existing_kind, existing_size)) if *existing_kind == kind => *existing_size,
+            _ => 0,
+        };
+        if self.usage.bytes(kind).saturating_sub(replaced) + size > max_bytes {
+            warn!(
+                "Record not stored (key: {:?}). Quota of {max_bytes} bytes for {kind:?} records reached.",
+                PrettyPrintRecordKey::from(key)
+            );
+            return Err(NetworkError::StorageFull { kind });
+        }
+
+        Ok(())
+    }
+
+    /// Account for a record of the given kind and size, replacing any previous copy under the same key.
+    fn reserve_record_kind_bytes(&mut self, key: &Key, kind: RecordKind, size: usize) {
+        self.release_record_kind_bytes(key);
+        *self.usage.bytes_mut(kind) += size;
+        let _ = self.record_sizes.insert(key.clone(), (kind, size));
+        self.update_record_kind_bytes_metrics();
+    }
+
+    /// Stop accounting for the record under the given key.
+    fn release_record_kind_bytes(&mut self, key: &Key) {
+        if let Some((kind, size)) = self.record_sizes.remove(key) {
+            let bytes = self.usage.bytes_mut(kind);
+            *bytes = bytes.saturating_sub(size);
+            self.update_record_kind_bytes_metrics();
+        }
+    }
+
+    fn update_record_kind_bytes_metrics(&self) {
+        #[cfg(feature = "open-metrics")]
+        if let Some(metrics) = &self.record_kind_bytes_metrics {
+            let _ = metrics.chunks.set(self.usage.chunks_bytes as i64);
+            let _ = metrics.spends.set(self.usage.spends_bytes as i64);
+            let _ = metrics.registers.set(self.usage.registers_bytes as i64);
+        }
+    }
+
     // Converts a Key into a Hex string.
     fn key_to_hex(key: &Key) -> String {
         let key_bytes = key.as_ref();
@@ -218,6 +417,11 @@ impl NodeRecordStore {
         &self.records
     }
 
+    /// Returns the bytes held by the store for each kind of record
+    pub(crate) fn record_kind_usage(&self) -> RecordKindUsage {
+        self.usage
+    }
+
     /// The follow up to `put_verified`, this only registers the RecordKey
     /// in the RecordStore records set. After this it should be safe
     /// to return the record as stored.
@@ -233,11 +437,20 @@ impl NodeRecordStore {
     ///
     /// The record is marked as written to disk once `mark_as_stored` is called,
     /// this avoids us returning half-written data or registering it as stored before it is.
-    pub(crate) fn put_verified(&mut self, r: Record, record_type: RecordType) -> Result<()> {
+    ///
+    /// An error is returned if storing the record would exceed the quota of its kind.
+    pub(crate) fn put_verified(
+        &mut self,
+        r: Record,
+        record_type: RecordType,
+    ) -> crate::error::Result<()> {
         let record_key = PrettyPrintRecordKey::from(&r.key).into_owned();
         trace!("PUT a verified Record: {record_key:?}");
 
+        let kind = RecordHeader::from_record(&r)?.kind;
+        self.check_record_kind_quota(&r.key, kind, r.value.len())?;
         self.prune_storage_if_needed_for_record(&r.key)?;
+        self.reserve_record_kind_bytes(&r.key, kind, r.value.len());
 
         let filename = Self::key_to_hex(&r.key);
         let file_path = self.config.storage_dir.join(&filename);
@@ -408,6 +621,7 @@ impl RecordStore for NodeRecordStore {
 
     fn remove(&mut self, k: &Key) {
         let _ = self.records.remove(k);
+        self.release_record_kind_bytes(k);
         #[cfg(feature = "open-metrics")]
         if let Some(metric) = &self.record_count_metric {
             let _ = metric.set(self.records.len() as i64);
@@ -473,7 +687,11 @@ impl ClientRecordStore {
         &self.empty_record_addresses
     }
 
-    pub(crate) fn put_verified(&mut self, _r: Record, _record_type: RecordType) -> Result<()> {
+    pub(crate) fn put_verified(
+        &mut self,
+        _r: Record,
+        _record_type: RecordType,
+    ) -> crate::error::Result<()> {
         Ok(())
     }
 
@@ -539,7 +757,7 @@ mod tests {
         kad::{KBucketKey, RecordKey},
     };
     use quickcheck::*;
-    use sn_protocol::storage::{try_serialize_record, ChunkAddress};
+    use sn_protocol::storage::{try_serialize_record, Chunk, ChunkAddress};
     use std::{collections::BTreeMap, time::Duration};
     use tokio::runtime::Runtime;
 
@@ -867,6 +1085,152 @@ mod tests {
         Ok(())
     }
 
+    fn record_of_kind(kind: RecordKind, value_len: usize) -> Record {
+        let value = match try_serialize_record(
+            &(0..value_len)
+                .map(|_| rand::random::<u8>())
+                .collect::<Bytes>(),
+            kind,
+        ) {
+            Ok(value) => value.to_vec(),
+            Err(err) => panic!("Cannot generate record value {err:?}"),
+        };
+        Record {
+            key: NetworkAddress::from_peer(PeerId::random()).to_record_key(),
+            value,
+            publisher: None,
+            expires: None,
+        }
+    }
+
+    #[test]
+    fn records_on_disk_are_restored() -> eyre::Result<()> {
+        let storage_dir =
+            std::env::temp_dir().join(format!("restore_records_{}", rand::random::<u64>()));
+        fs::create_dir_all(&storage_dir)?;
+
+        let chunk_record = || -> eyre::Result<Record> {
+            let chunk = Chunk::new(Bytes::from(rand::random::<[u8; 32]>().to_vec()));
+            Ok(Record {
+                key: chunk.network_address().to_record_key(),
+                value: try_serialize_record(&chunk, RecordKind::Chunk)?.to_vec(),
+                publisher: None,
+                expires: None,
+            })
+        };
+        let chunks = (0..3)
+            .map(|_| chunk_record())
+            .collect::<eyre::Result<Vec<_>>>()?;
+        // a register record holding no register, and a chunk under the key of another
+        let register = record_of_kind(RecordKind::Register, 50);
+        let mut misplaced_chunk = chunk_record()?;
+        misplaced_chunk.key = chunk_record()?.key;
+        for record in chunks.iter().chain([&register, &misplaced_chunk]) {
+            fs::write(
+                storage_dir.join(NodeRecordStore::key_to_hex(&record.key)),
+                &record.value,
+            )?;
+        }
+        // neither named after a record key, nor holding a record header
+        fs::write(storage_dir.join("not-a-record"), b"junk")?;
+        fs::write(storage_dir.join("abcd"), b"")?;
+
+        let store_config = NodeRecordStoreConfig {
+            storage_dir: storage_dir.clone(),
+            ..Default::default()
+        };
+        let store = NodeRecordStore::with_config(PeerId::random(), store_config, None)
+            .restore_records_from_disk();
+        fs::remove_dir_all(&storage_dir)?;
+
+        assert_eq!(store.records.len(), 3);
+        for record in &chunks {
+            assert!(matches!(
+                store.records.get(&record.key),
+                Some((_, RecordType::Chunk))
+            ));
+        }
+        assert_eq!(
+            store.record_kind_usage().chunks_bytes,
+            3 * chunks[0].value.len()
+        );
+        assert_eq!(store.record_kind_usage().registers_bytes, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn quota_refuses_register_puts_while_chunks_continue() -> eyre::Result<()> {
+        let register = record_of_kind(RecordKind::Register, 50);
+        let store_config = NodeRecordStoreConfig {
+            quotas: RecordKindQuotas {
+                // room for a single register
+                max_registers_bytes: Some(register.value.len()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut store = NodeRecordStore::with_config(PeerId::random(), store_config, None);
+
+        let register_type = RecordType::NonChunk(XorName::from_content(&register.value));
+        assert!(store.put_verified(register, register_type).is_ok());
+
+        for _ in 0..5 {
+            let register = record_of_kind(RecordKind::Register, 50);
+            let register_type = RecordType::NonChunk(XorName::from_content(&register.value));
+            assert!(matches!(
+                store.put_verified(register, register_type),
+                Err(NetworkError::StorageFull {
+                    kind: RecordKind::Register
+                })
+            ));
+
+            let chunk = record_of_kind(RecordKind::Chunk, 50);
+            assert!(store.put_verified(chunk, RecordType::Chunk).is_ok());
+        }
+
+        let usage = store.record_kind_usage();
+        assert!(usage.registers_bytes > 0);
+        assert!(usage.chunks_bytes > usage.registers_bytes);
+        assert_eq!(usage.spends_bytes, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn quota_frees_up_on_remove() -> eyre::Result<()> {
+        let register = record_of_kind(RecordKind::Register, 50);
+        let store_config = NodeRecordStoreConfig {
+            quotas: RecordKindQuotas {
+                max_registers_bytes: Some(register.value.len()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut store = NodeRecordStore::with_config(PeerId::random(), store_config, None);
+
+        let key = register.key.clone();
+        let register_type = RecordType::NonChunk(XorName::from_content(&register.value));
+        assert!(store.put_verified(register, register_type).is_ok());
+
+        // an updated copy under the same key replaces the existing one
+        let mut updated = record_of_kind(RecordKind::Register, 50);
+        updated.key = key.clone();
+        let updated_type = RecordType::NonChunk(XorName::from_content(&updated.value));
+        assert!(store.put_verified(updated, updated_type).is_ok());
+
+        let other = record_of_kind(RecordKind::Register, 50);
+        let other_type = RecordType::NonChunk(XorName::from_content(&other.value));
+        assert!(store
+            .put_verified(other.clone(), other_type.clone())
+            .is_err());
+
+        store.remove(&key);
+        assert_eq!(store.record_kind_usage(), RecordKindUsage::default());
+        assert!(store.put_verified(other, other_type).is_ok());
+
+        Ok(())
+    }
+
     #[test]
     fn address_distribution_sim() {
         // Map of peers and correspondent stats of `(num_of_records, Nano_earned, received_payment_count)`.
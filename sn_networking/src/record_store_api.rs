@@ -7,7 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 #![allow(clippy::mutable_key_type)] // for the Bytes in NetworkAddress
 
-use crate::record_store::{ClientRecordStore, NodeRecordStore};
+use crate::record_store::{ClientRecordStore, NodeRecordStore, RecordKindUsage};
 use libp2p::kad::{
     store::{RecordStore, Result},
     KBucketDistance as Distance, ProviderRecord, Record, RecordKey,
@@ -105,13 +105,27 @@ impl UnifiedRecordStore {
         }
     }
 
-    pub(crate) fn put_verified(&mut self, r: Record, record_type: RecordType) -> Result<()> {
+    pub(crate) fn put_verified(
+        &mut self,
+        r: Record,
+        record_type: RecordType,
+    ) -> crate::error::Result<()> {
         match self {
             Self::Client(store) => store.put_verified(r, record_type),
             Self::Node(store) => store.put_verified(r, record_type),
         }
     }
 
+    pub(crate) fn record_kind_usage(&self) -> RecordKindUsage {
+        match self {
+            Self::Client(_) => {
+                warn!("Calling record kind usage at Client. This should not happen");
+                RecordKindUsage::default()
+            }
+            Self::Node(store) => store.record_kind_usage(),
+        }
+    }
+
     pub(crate) fn store_cost(&self) -> NanoTokens {
         match self {
             Self::Client(_) => {
@@ -0,0 +1,20 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use libp2p::PeerId;
+
+/// Which of the close nodes to a chunk proved to hold it, see `Network::verify_chunk_existence`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChunkReplicationReport {
+    /// The close nodes which returned a valid proof of holding the chunk
+    pub holders_ok: Vec<PeerId>,
+    /// The close nodes which returned no proof, or an invalid one
+    pub holders_missing: Vec<PeerId>,
+    /// Whether enough of the close nodes hold the chunk for the quorum asked for
+    pub quorum_met: bool,
+}
@@ -0,0 +1,237 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::error::{Error, Result};
+use rand::Rng;
+use std::{future::Future, time::Duration};
+
+/// The default number of attempts of a network operation, the first one included.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 6;
+/// The default delay before the first retry of a network operation.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// The default maximum delay between two attempts of a network operation.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(4);
+
+/// How a failed network operation, such as a record GET or PUT, is retried.
+///
+/// The delay before each retry doubles, up to `max_backoff`. With `jitter` set, a random delay
+/// of up to half the backoff is added, so that operations failing together don't retry together.
+///
+/// Once `max_attempts` attempts have failed, the operation fails with `Error::RetriesExhausted`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, the first one included. `0` is the same as `1`.
+    pub max_attempts: usize,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay before a retry, jitter excluded.
+    pub max_backoff: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy making a single attempt, never retrying.
+    pub const fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            jitter: false,
+        }
+    }
+
+    /// The number of attempts to make, at least one.
+    pub fn attempts(&self) -> usize {
+        self.max_attempts.max(1)
+    }
+
+    /// Whether a failed attempt is retried at all.
+    pub fn retries(&self) -> bool {
+        self.attempts() > 1
+    }
+
+    /// The backoff before the given retry, counting from 1, jitter excluded.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// The delay to wait for before the given retry, counting from 1.
+    pub(crate) fn delay(&self, retry: usize) -> Duration {
+        let backoff = self.backoff(retry);
+        if !self.jitter {
+            return backoff;
+        }
+        let max_jitter = backoff.as_millis() as u64 / 2;
+        backoff + Duration::from_millis(rand::thread_rng().gen_range(0..=max_jitter))
+    }
+
+    /// The error of the last failed attempt, once there is no attempt left.
+    ///
+    /// With retries, it is wrapped into an `Error::RetriesExhausted` telling how many attempts
+    /// were made. Without, it is returned as is.
+    pub(crate) fn exhausted(&self, last_error: Error) -> Error {
+        if self.retries() {
+            Error::RetriesExhausted {
+                attempts: self.attempts(),
+                last_error: Box::new(last_error),
+            }
+        } else {
+            last_error
+        }
+    }
+
+    /// Run the given operation until it succeeds, or until all the attempts failed.
+    ///
+    /// The operation is given the number of the attempt, counting from 1. A failure due to
+    /// busy peers waits for at least as long as they asked to.
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut(usize) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match operation(attempt).await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            if attempt >= self.attempts() {
+                return Err(self.exhausted(err));
+            }
+
+            let delay = self
+                .delay(attempt)
+                .max(err.busy_retry_after().unwrap_or_default());
+            debug!(
+                "Attempt {attempt}/{} failed, retrying after {delay:?}: {err:?}",
+                self.attempts()
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let policy = RetryPolicy {
+            max_attempts: 6,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: false,
+        };
+
+        let schedule: Vec<_> = (1..=5).map(|retry| policy.delay(retry)).collect();
+        assert_eq!(
+            schedule,
+            [100, 200, 400, 500, 500].map(Duration::from_millis)
+        );
+        assert_eq!(policy.backoff(1_000), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn exhausted_attempts_carry_the_count_and_the_last_cause() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            jitter: false,
+        };
+        let calls = AtomicUsize::new(0);
+
+        let result: Result<()> = policy
+            .run(|attempt| {
+                let _ = calls.fetch_add(1, Ordering::SeqCst);
+                async move { Err(Error::InvalidTransfer(format!("attempt {attempt}"))) }
+            })
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        match result {
+            Err(Error::RetriesExhausted {
+                attempts,
+                last_error,
+            }) => {
+                assert_eq!(attempts, 3);
+                assert!(
+                    matches!(*last_error, Error::InvalidTransfer(ref msg) if msg == "attempt 3")
+                );
+            }
+            other => panic!("Unexpected result {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_single_attempt_returns_the_error_as_is() {
+        let calls = AtomicUsize::new(0);
+
+        let result: Result<()> = RetryPolicy::none()
+            .run(|_| {
+                let _ = calls.fetch_add(1, Ordering::SeqCst);
+                async { Err(Error::NoStoreCostResponses) }
+            })
+            .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(result, Err(Error::NoStoreCostResponses)));
+    }
+
+    #[tokio::test]
+    async fn busy_peers_are_retried_after_their_hint() {
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            jitter: false,
+        };
+        let retry_after = Duration::from_millis(200);
+        let attempted_at = std::sync::Mutex::new(vec![]);
+
+        // fault injection: the peers are busy on the first attempt only
+        let result = policy
+            .run(|attempt| {
+                if let Ok(mut attempted_at) = attempted_at.lock() {
+                    attempted_at.push(tokio::time::Instant::now());
+                }
+                async move {
+                    if attempt == 1 {
+                        Err(Error::PeersBusy { retry_after })
+                    } else {
+                        Ok(())
+                    }
+                }
+            })
+            .await;
+
+        assert!(result.is_ok());
+        let attempted_at = attempted_at.lock().map(|at| at.clone()).unwrap_or_default();
+        assert_eq!(attempted_at.len(), 2);
+        assert!(attempted_at[1] - attempted_at[0] >= retry_after);
+    }
+}
@@ -6,18 +6,22 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{close_group_majority, driver::GetRecordCfg, Error, GetRecordError, Network, Result};
+use crate::{
+    close_group_majority, driver::GetRecordCfg, Error, GetRecordError, Network, Result, RetryPolicy,
+};
+use futures::{stream, StreamExt};
 use libp2p::kad::{Quorum, Record};
 use sn_protocol::{
     storage::{try_deserialize_record, RecordHeader, RecordKind, SpendAddress},
     NetworkAddress, PrettyPrintRecordKey,
 };
 use sn_transfers::{
-    CashNote, CashNoteRedemption, DerivationIndex, LocalWallet, MainPubkey, SignedSpend,
-    Transaction, Transfer, UniquePubkey,
+    CashNote, CashNoteRedemption, LocalWallet, MainPubkey, SignedSpend, Transaction, Transfer,
 };
 use std::collections::BTreeSet;
-use tokio::task::JoinSet;
+
+/// The most spends fetched at once when verifying CashNoteRedemptions.
+pub const MAX_CONCURRENT_SPEND_GETS: usize = 32;
 
 fn parse_signed_spends(address: &SpendAddress, record: &Record) -> Result<SignedSpend> {
     match get_singed_spends_from_record(record)?.as_slice() {
@@ -48,7 +52,7 @@ impl Network {
         let key = NetworkAddress::from_spend_address(address).to_record_key();
         let get_cfg = GetRecordCfg {
             get_quorum: Quorum::Majority,
-            re_attempt: false,
+            retry_policy: RetryPolicy::none(),
             target_record: None,
             expected_holders: Default::default(),
         };
@@ -70,33 +74,24 @@ impl Network {
     /// If we get a quorum error, we enable re-try
     pub async fn get_spend(&self, address: SpendAddress) -> Result<SignedSpend> {
         let key = NetworkAddress::from_spend_address(address).to_record_key();
-        let mut get_cfg = GetRecordCfg {
+        let get_cfg = GetRecordCfg {
             get_quorum: Quorum::All,
-            re_attempt: true,
+            retry_policy: RetryPolicy::default(),
             target_record: None,
             expected_holders: Default::default(),
         };
         let record = match self.get_record_from_network(key.clone(), &get_cfg).await {
             Ok(record) => record,
-            Err(Error::GetRecordError(GetRecordError::NotEnoughCopies {
-                record,
-                expected,
-                got,
-            })) => {
+            Err(err) => match err.last_cause() {
                 // if majority holds the spend, it might be worth it to try again.
-                if got >= close_group_majority() {
+                Error::GetRecordError(GetRecordError::NotEnoughCopies { got, .. })
+                    if *got >= close_group_majority() =>
+                {
                     debug!("At least a majority nodes hold the spend {address:?}, so trying to get it again.");
-                    get_cfg.re_attempt = true;
                     self.get_record_from_network(key, &get_cfg).await?
-                } else {
-                    return Err(Error::GetRecordError(GetRecordError::NotEnoughCopies {
-                        record,
-                        expected,
-                        got,
-                    }));
                 }
-            }
-            Err(err) => return Err(err),
+                _ => return Err(err),
+            },
         };
         debug!(
             "Got record from the network, {:?}",
@@ -120,23 +115,26 @@ impl Network {
     ) -> Result<Vec<CashNote>> {
         // get CashNoteRedemptions from encrypted Transfer
         trace!("Decyphering Transfer");
-        let cashnote_redemptions = wallet.unwrap_transfer(transfer)?;
+        // a transfer to a retired address is verified against it, not the current one
+        let (main_pubkey, cashnote_redemptions) = wallet.unwrap_transfer(transfer)?;
 
-        self.verify_cash_notes_redemptions(wallet.address(), &cashnote_redemptions)
+        self.verify_cash_notes_redemptions(main_pubkey, &cashnote_redemptions)
             .await
     }
 
     /// This function is used to receive a list of CashNoteRedemptions and turn it back into spendable CashNotes.
     /// Needs Network connection.
     /// Verify CashNoteRedemptions and rebuild spendable currency from them.
-    /// Returns an `Error::InvalidTransfer` if any CashNoteRedemption is not valid
+    /// The spends needed are fetched concurrently, `MAX_CONCURRENT_SPEND_GETS` at most at once.
+    /// Returns an `Error::InvalidCashNoteRedemption` if any CashNoteRedemption is not valid,
+    /// for the first of them found invalid, none of them being redeemed then.
     /// Else returns a list of CashNotes that can be spent by the owner.
     pub async fn verify_cash_notes_redemptions(
         &self,
         main_pubkey: MainPubkey,
         cashnote_redemptions: &[CashNoteRedemption],
     ) -> Result<Vec<CashNote>> {
-        // get the parent transactions
+        // get the parent spends
         trace!(
             "Getting parent Tx for validation from {:?}",
             cashnote_redemptions.len()
@@ -145,38 +143,43 @@ impl Network {
             .iter()
             .map(|u| u.parent_spend)
             .collect();
-        let mut tasks = JoinSet::new();
-        for addr in parent_addrs.clone() {
-            let self_clone = self.clone();
-            let _ = tasks.spawn(async move { self_clone.get_spend(addr).await });
-        }
-        let mut parent_spends = BTreeSet::new();
-        while let Some(result) = tasks.join_next().await {
-            let signed_spend = result
-                .map_err(|_| Error::FailedToGetTransferParentSpend)?
-                .map_err(|e| Error::InvalidTransfer(format!("{e}")))?;
-            let _ = parent_spends.insert(signed_spend.clone());
-        }
+        let parent_spends =
+            self.get_spends_concurrently(parent_addrs)
+                .await
+                .map_err(|failures| {
+                    first_invalid_redemption(failures.into_iter().map(|(addr, err)| {
+                        invalid_redemption(
+                            cashnote_redemptions,
+                            |u| u.parent_spend == addr,
+                            format!("Failed to get its parent spend: {err}"),
+                        )
+                    }))
+                })?;
         let parent_txs: BTreeSet<Transaction> =
             parent_spends.iter().map(|s| s.spent_tx()).collect();
+        // the redemptions whose parent spend was spent by the given Tx
+        let redeemed_from = |tx: &Transaction| {
+            let tx_hash = tx.hash();
+            let parents: BTreeSet<SpendAddress> = parent_spends
+                .iter()
+                .filter(|s| s.spent_tx_hash() == tx_hash)
+                .map(|s| SpendAddress::from_unique_pubkey(s.unique_pubkey()))
+                .collect();
+            move |u: &CashNoteRedemption| parents.contains(&u.parent_spend)
+        };
 
         // get our outputs from Tx
-        let our_output_unique_pubkeys: Vec<(UniquePubkey, DerivationIndex)> = cashnote_redemptions
-            .iter()
-            .map(|u| {
-                let unique_pubkey = main_pubkey.new_unique_pubkey(&u.derivation_index);
-                (unique_pubkey, u.derivation_index)
-            })
-            .collect();
         let mut our_output_cash_notes = Vec::new();
-
-        for (id, derivation_index) in our_output_unique_pubkeys.into_iter() {
+        for (index, u) in cashnote_redemptions.iter().enumerate() {
+            let id = main_pubkey.new_unique_pubkey(&u.derivation_index);
             let src_tx = parent_txs
                 .iter()
                 .find(|tx| tx.outputs.iter().any(|o| o.unique_pubkey() == &id))
-                .ok_or(Error::InvalidTransfer(
-                    "None of the CashNoteRedemptions are refered to in upstream Txs".to_string(),
-                ))?
+                .ok_or_else(|| Error::InvalidCashNoteRedemption {
+                    index,
+                    parent_spend: u.parent_spend,
+                    reason: "None of the upstream Txs refer to it".to_string(),
+                })?
                 .clone();
             let signed_spends: BTreeSet<SignedSpend> = parent_spends
                 .iter()
@@ -188,46 +191,115 @@ impl Network {
                 src_tx,
                 signed_spends,
                 main_pubkey,
-                derivation_index,
+                derivation_index: u.derivation_index,
             };
             our_output_cash_notes.push(cash_note);
         }
 
-        // check Txs and parent spends are valid
+        // get the missing inputs spends of all the Txs from the network
         trace!("Validating parent spends");
-        for tx in parent_txs {
-            let tx_inputs_keys: Vec<_> = tx.inputs.iter().map(|i| i.unique_pubkey()).collect();
-
-            // get the missing inputs spends from the network
-            let mut tasks = JoinSet::new();
-            for input_key in tx_inputs_keys {
-                if parent_spends.iter().any(|s| s.unique_pubkey() == input_key) {
-                    continue;
-                }
-                let self_clone = self.clone();
-                let addr = SpendAddress::from_unique_pubkey(input_key);
-                let _ = tasks.spawn(async move { self_clone.get_spend(addr).await });
-            }
-            while let Some(result) = tasks.join_next().await {
-                let signed_spend = result
-                    .map_err(|_| Error::FailedToGetTransferParentSpend)?
-                    .map_err(|e| Error::InvalidTransfer(format!("{e}")))?;
-                let _ = parent_spends.insert(signed_spend.clone());
-            }
+        let missing_input_addrs: BTreeSet<SpendAddress> = parent_txs
+            .iter()
+            .flat_map(|tx| tx.inputs.iter().map(|i| i.unique_pubkey()))
+            .filter(|input_key| {
+                !parent_spends
+                    .iter()
+                    .any(|s| s.unique_pubkey() == *input_key)
+            })
+            .map(SpendAddress::from_unique_pubkey)
+            .collect();
+        let input_spends = self
+            .get_spends_concurrently(missing_input_addrs)
+            .await
+            .map_err(|failures| {
+                first_invalid_redemption(failures.into_iter().map(|(addr, err)| {
+                    let reason =
+                        format!("Failed to get the input spend {addr:?} of its parent Tx: {err}");
+                    match parent_txs.iter().find(|tx| {
+                        tx.inputs
+                            .iter()
+                            .any(|i| SpendAddress::from_unique_pubkey(i.unique_pubkey()) == addr)
+                    }) {
+                        Some(tx) => {
+                            invalid_redemption(cashnote_redemptions, redeemed_from(tx), reason)
+                        }
+                        None => Error::InvalidTransfer(reason),
+                    }
+                }))
+            })?;
 
-            // verify the Tx against the inputs spends
-            let input_spends = parent_spends
+        // verify the Txs against the inputs spends
+        for tx in &parent_txs {
+            let tx_input_spends = parent_spends
                 .iter()
+                .chain(&input_spends)
                 .filter(|s| s.spent_tx_hash() == tx.hash())
                 .cloned()
                 .collect();
-            tx.verify_against_inputs_spent(&input_spends).map_err(|e| {
-                Error::InvalidTransfer(format!("Payment parent Tx {:?} invalid: {e}", tx.hash()))
-            })?;
+            tx.verify_against_inputs_spent(&tx_input_spends)
+                .map_err(|e| {
+                    invalid_redemption(
+                        cashnote_redemptions,
+                        redeemed_from(tx),
+                        format!("Payment parent Tx {:?} invalid: {e}", tx.hash()),
+                    )
+                })?;
         }
 
         Ok(our_output_cash_notes)
     }
+
+    /// Get the spends, `MAX_CONCURRENT_SPEND_GETS` at most at once. All of them are fetched,
+    /// for the failures to be returned along with their address, whichever completes first.
+    async fn get_spends_concurrently(
+        &self,
+        addrs: BTreeSet<SpendAddress>,
+    ) -> std::result::Result<BTreeSet<SignedSpend>, Vec<(SpendAddress, Error)>> {
+        let mut results = stream::iter(addrs)
+            .map(|addr| async move { (addr, self.get_spend(addr).await) })
+            .buffer_unordered(MAX_CONCURRENT_SPEND_GETS);
+        let mut spends = BTreeSet::new();
+        let mut failures = vec![];
+        while let Some((addr, result)) = results.next().await {
+            match result {
+                Ok(spend) => {
+                    let _ = spends.insert(spend);
+                }
+                Err(err) => failures.push((addr, err)),
+            }
+        }
+        if failures.is_empty() {
+            Ok(spends)
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+/// The error for the first of the invalid redemptions, the one with the lowest index.
+fn first_invalid_redemption(errors: impl Iterator<Item = Error>) -> Error {
+    errors
+        .min_by_key(|err| match err {
+            Error::InvalidCashNoteRedemption { index, .. } => *index,
+            _ => usize::MAX,
+        })
+        .unwrap_or_else(|| Error::InvalidTransfer("No invalid redemption was reported".to_string()))
+}
+
+/// The error for the first of the redemptions matching `is_invalid`.
+fn invalid_redemption(
+    cashnote_redemptions: &[CashNoteRedemption],
+    is_invalid: impl Fn(&CashNoteRedemption) -> bool,
+    reason: String,
+) -> Error {
+    match cashnote_redemptions.iter().position(is_invalid) {
+        Some(index) => Error::InvalidCashNoteRedemption {
+            index,
+            parent_spend: cashnote_redemptions[index].parent_spend,
+            reason,
+        },
+        None => Error::InvalidTransfer(reason),
+    }
 }
 
 /// Tries to get the signed spend out of a record.
@@ -0,0 +1,350 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::error::Result;
+#[cfg(feature = "network-contacts")]
+use crate::DohResolver;
+#[cfg(feature = "network-contacts")]
+use futures::future::BoxFuture;
+#[cfg(feature = "quic")]
+use libp2p::quic;
+#[cfg(feature = "network-contacts")]
+use libp2p::{
+    core::transport::{ListenerId, TransportError, TransportEvent},
+    Multiaddr,
+};
+use libp2p::{
+    core::{muxing::StreamMuxerBox, transport::Boxed},
+    identity::Keypair,
+    PeerId, Transport,
+};
+#[cfg(feature = "network-contacts")]
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+/// Build the transport peers are dialed and listened on through: TCP, or QUIC with the `quic`
+/// feature.
+///
+/// The host names of the `dns`, `dns4`, `dns6` and `dnsaddr` multiaddrs dialed are resolved
+/// with the system resolver. Unless `local`, dialing non-global addresses is prevented, the
+/// addresses host names resolve to included.
+pub(crate) fn build_transport(
+    keypair: &Keypair,
+    local: bool,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>> {
+    #[cfg(not(feature = "quic"))]
+    let mut transport = libp2p::tcp::tokio::Transport::new(libp2p::tcp::Config::default())
+        .upgrade(libp2p::core::upgrade::Version::V1)
+        .authenticate(
+            libp2p::noise::Config::new(keypair)
+                .expect("Signing libp2p-noise static DH keypair failed."),
+        )
+        .multiplex(libp2p::yamux::Config::default())
+        .boxed();
+
+    #[cfg(feature = "quic")]
+    let mut transport = libp2p::quic::tokio::Transport::new(quic::Config::new(keypair))
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed();
+
+    if !local {
+        debug!("Preventing non-global dials");
+        // Wrap TCP or UDP in a transport that prevents dialing local addresses.
+        transport = libp2p::core::transport::global_only::Transport::new(transport).boxed();
+    }
+
+    Ok(libp2p::dns::tokio::Transport::system(transport)?.boxed())
+}
+
+/// Resolves the host names of the multiaddrs dialed through DNS-over-HTTPS before handing them
+/// to the inner transport. Those DNS-over-HTTPS fails to resolve are left to the inner
+/// transport, to be resolved with the system resolver.
+#[cfg(feature = "network-contacts")]
+pub(crate) struct DohTransport<T> {
+    inner: Arc<Mutex<T>>,
+    resolver: DohResolver,
+}
+
+#[cfg(feature = "network-contacts")]
+impl<T> DohTransport<T>
+where
+    T: Transport<Error = io::Error> + Send + Unpin + 'static,
+    T::Dial: Send,
+{
+    pub(crate) fn new(inner: T, resolver: DohResolver) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+            resolver,
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, T> {
+        // a panic while holding the lock can't leave the inner transport in an inconsistent state
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Resolve the address, then have the inner transport dial it with `dial`.
+    fn resolve_and_dial(
+        &self,
+        addr: Multiaddr,
+        dial: fn(&mut T, Multiaddr) -> std::result::Result<T::Dial, TransportError<io::Error>>,
+    ) -> BoxFuture<'static, io::Result<T::Output>> {
+        let inner = self.inner.clone();
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let addr = resolver.resolve_multiaddr(&addr).await;
+            let dialing = {
+                let mut inner = inner
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                dial(&mut inner, addr)
+            };
+            match dialing {
+                Ok(dialing) => dialing.await,
+                Err(TransportError::MultiaddrNotSupported(addr)) => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("Multiaddr {addr} not supported"),
+                )),
+                Err(TransportError::Other(err)) => Err(err),
+            }
+        })
+    }
+}
+
+#[cfg(feature = "network-contacts")]
+impl<T> Transport for DohTransport<T>
+where
+    T: Transport<Error = io::Error> + Send + Unpin + 'static,
+    T::Dial: Send,
+{
+    type Output = T::Output;
+    type Error = io::Error;
+    type ListenerUpgrade = T::ListenerUpgrade;
+    type Dial = BoxFuture<'static, io::Result<T::Output>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> std::result::Result<(), TransportError<Self::Error>> {
+        self.inner().listen_on(id, addr)
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.inner().remove_listener(id)
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+    ) -> std::result::Result<Self::Dial, TransportError<Self::Error>> {
+        Ok(self.resolve_and_dial(addr, T::dial))
+    }
+
+    fn dial_as_listener(
+        &mut self,
+        addr: Multiaddr,
+    ) -> std::result::Result<Self::Dial, TransportError<Self::Error>> {
+        Ok(self.resolve_and_dial(addr, T::dial_as_listener))
+    }
+
+    fn poll(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        Pin::new(&mut *self.inner()).poll(cx)
+    }
+
+    fn address_translation(&self, listen: &Multiaddr, observed: &Multiaddr) -> Option<Multiaddr> {
+        self.inner().address_translation(listen, observed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+    use futures::future::poll_fn;
+    use libp2p::{
+        core::transport::{ListenerId, TransportEvent},
+        multiaddr::Protocol,
+        Multiaddr,
+    };
+    use std::pin::Pin;
+
+    #[cfg(not(feature = "quic"))]
+    const LISTEN_ADDR: &str = "/ip4/127.0.0.1/tcp/0";
+    #[cfg(feature = "quic")]
+    const LISTEN_ADDR: &str = "/ip4/127.0.0.1/udp/0/quic-v1";
+
+    async fn next_event(
+        transport: &mut Boxed<(PeerId, StreamMuxerBox)>,
+    ) -> TransportEvent<
+        <Boxed<(PeerId, StreamMuxerBox)> as Transport>::ListenerUpgrade,
+        std::io::Error,
+    > {
+        poll_fn(|cx| Pin::new(&mut *transport).poll(cx)).await
+    }
+
+    #[tokio::test]
+    async fn peers_are_dialed_at_their_host_name() -> Result<()> {
+        let listener_keypair = Keypair::generate_ed25519();
+        let mut listener = build_transport(&listener_keypair, true)?;
+        let mut dialer = build_transport(&Keypair::generate_ed25519(), true)?;
+
+        listener.listen_on(ListenerId::next(), LISTEN_ADDR.parse()?)?;
+        let listen_addr = loop {
+            if let TransportEvent::NewAddress { listen_addr, .. } = next_event(&mut listener).await
+            {
+                break listen_addr;
+            }
+        };
+        let host_addr: Multiaddr = listen_addr
+            .iter()
+            .map(|protocol| match protocol {
+                Protocol::Ip4(_) => Protocol::Dns4("localhost".into()),
+                protocol => protocol,
+            })
+            .collect();
+
+        let dial = dialer.dial(host_addr)?;
+        let accept = async {
+            loop {
+                if let TransportEvent::Incoming { upgrade, .. } = next_event(&mut listener).await {
+                    return upgrade.await;
+                }
+            }
+        };
+        let (dialed, accepted) = tokio::join!(dial, accept);
+
+        let (dialed_peer, _) = dialed?;
+        assert_eq!(dialed_peer, listener_keypair.public().to_peer_id());
+        let _ = accepted?;
+
+        Ok(())
+    }
+
+    /// Records the addresses dialed, without connecting to them.
+    #[cfg(feature = "network-contacts")]
+    #[derive(Default)]
+    struct DialRecorder {
+        dialed: Arc<Mutex<Vec<Multiaddr>>>,
+    }
+
+    #[cfg(feature = "network-contacts")]
+    impl Transport for DialRecorder {
+        type Output = ();
+        type Error = io::Error;
+        type ListenerUpgrade = futures::future::Pending<io::Result<()>>;
+        type Dial = futures::future::Ready<io::Result<()>>;
+
+        fn listen_on(
+            &mut self,
+            _id: ListenerId,
+            addr: Multiaddr,
+        ) -> std::result::Result<(), TransportError<Self::Error>> {
+            Err(TransportError::MultiaddrNotSupported(addr))
+        }
+
+        fn remove_listener(&mut self, _id: ListenerId) -> bool {
+            false
+        }
+
+        fn dial(
+            &mut self,
+            addr: Multiaddr,
+        ) -> std::result::Result<Self::Dial, TransportError<Self::Error>> {
+            if let Ok(mut dialed) = self.dialed.lock() {
+                dialed.push(addr);
+            }
+            Ok(futures::future::ready(Ok(())))
+        }
+
+        fn dial_as_listener(
+            &mut self,
+            addr: Multiaddr,
+        ) -> std::result::Result<Self::Dial, TransportError<Self::Error>> {
+            self.dial(addr)
+        }
+
+        fn poll(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+            Poll::Pending
+        }
+
+        fn address_translation(
+            &self,
+            _listen: &Multiaddr,
+            _observed: &Multiaddr,
+        ) -> Option<Multiaddr> {
+            None
+        }
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn dns_multiaddrs_are_dialed_at_the_doh_answers() -> Result<()> {
+        use crate::doh::tests::{mock_doh_server, multiaddr};
+        use std::{collections::HashMap, net::IpAddr};
+
+        const PEER_ID: &str = "12D3KooWRi6wF7yxWLuPSNskXc6kQ5cJ6eaymeMbCRdTnMesPgFx";
+
+        let answers = HashMap::from([
+            ("bootstrap-v4.test", IpAddr::from([10, 1, 2, 3])),
+            ("bootstrap-v6.test", "fd00::1".parse()?),
+        ]);
+        let (server, queries) = mock_doh_server(answers, 200).await?;
+        let recorder = DialRecorder::default();
+        let dialed = recorder.dialed.clone();
+        let mut transport = DohTransport::new(recorder, DohResolver::new(server)?);
+
+        for addr in [
+            format!("/dns4/bootstrap-v4.test/tcp/12000/p2p/{PEER_ID}"),
+            "/dns6/bootstrap-v6.test/udp/12000/quic-v1".to_string(),
+            "/dns/bootstrap-v6.test/tcp/12001".to_string(),
+            "/dns4/bootstrap-v4.test/tcp/443/wss".to_string(),
+        ] {
+            transport.dial(multiaddr(&addr)?)?.await?;
+        }
+
+        let dialed = dialed.lock().map(|dialed| dialed.clone()).ok();
+        assert_eq!(
+            dialed,
+            Some(vec![
+                multiaddr(&format!("/ip4/10.1.2.3/tcp/12000/p2p/{PEER_ID}"))?,
+                multiaddr("/ip6/fd00::1/udp/12000/quic-v1")?,
+                multiaddr("/ip6/fd00::1/tcp/12001")?,
+                multiaddr("/ip4/10.1.2.3/tcp/443/tls/sni/bootstrap-v4.test/ws")?,
+            ])
+        );
+
+        let mut queries = queries
+            .lock()
+            .map(|queries| queries.clone())
+            .unwrap_or_default();
+        queries.sort();
+        assert_eq!(
+            queries,
+            vec![
+                "bootstrap-v4.test/1",
+                "bootstrap-v6.test/1",
+                "bootstrap-v6.test/28"
+            ],
+            "the answers are cached"
+        );
+        Ok(())
+    }
+}
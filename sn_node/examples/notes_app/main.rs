@@ -0,0 +1,152 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! A CLI notes app, storing encrypted notes as chunks indexed by a Register, and sharing them
+//! over gossip.
+//!
+//! Run it against a local testnet, e.g. started with `cargo run --bin testnet -- --interval 100`,
+//! with a funded wallet in the app's data dir:
+//!
+//! ```text
+//! cargo run --example notes_app -- create --title groceries --text "milk, eggs"
+//! cargo run --example notes_app -- list
+//! cargo run --example notes_app -- get --title groceries
+//! cargo run --example notes_app -- share --title groceries --to <recipient public key>
+//! cargo run --example notes_app -- receive
+//! ```
+
+mod notes;
+
+use bls::{PublicKey, SecretKey};
+use clap::{Parser, Subcommand};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use notes::{fetch_note, next_shared_note, subscribe_to_shared_notes, Notes};
+use sn_client::ClientBuilder;
+use sn_peers_acquisition::{get_peers_from_args, PeersArgs};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[clap(name = "notes app")]
+struct Opt {
+    #[command(flatten)]
+    peers: PeersArgs,
+
+    /// The directory holding the user's key and wallet. Defaults to the app's data dir.
+    #[clap(long)]
+    root_dir: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// Store a new note.
+    Create {
+        #[clap(long)]
+        title: String,
+        #[clap(long)]
+        text: String,
+    },
+    /// List the titles of the notes.
+    List,
+    /// Print a note.
+    Get {
+        #[clap(long)]
+        title: String,
+    },
+    /// Share a note with another user, given their public key.
+    Share {
+        #[clap(long)]
+        title: String,
+        #[clap(long)]
+        to: String,
+    },
+    /// Wait for a note to be shared with us, and print it.
+    Receive,
+    /// Print our public key, for other users to share notes with us.
+    Whoami,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let opt = Opt::parse();
+    let root_dir = match opt.root_dir {
+        Some(root_dir) => root_dir,
+        None => dirs_next::data_dir()
+            .ok_or_else(|| eyre!("could not obtain data directory path"))?
+            .join("safe")
+            .join("notes_app"),
+    };
+    let signer = load_or_create_key(&root_dir)?;
+
+    if let Cmd::Whoami = opt.cmd {
+        println!("{}", signer.public_key().to_hex());
+        return Ok(());
+    }
+
+    let peers = get_peers_from_args(opt.peers).await?;
+    let client = ClientBuilder::default()
+        .signer(signer)
+        .peers(peers)
+        .enable_gossip(true)
+        .build()
+        .await?;
+
+    match opt.cmd {
+        Cmd::Create { title, text } => {
+            let mut notes = Notes::open(client, root_dir).await?;
+            let estimate = notes.estimate_cost(&text).await?;
+            println!("Storing the note '{title}' is estimated to cost {estimate}");
+            let address = notes.create(&title, &text).await?;
+            println!("Stored the note '{title}' at {address:?}");
+        }
+        Cmd::List => {
+            let mut notes = Notes::open(client, root_dir).await?;
+            for (title, address) in notes.list().await? {
+                println!("{title}: {address:?}");
+            }
+        }
+        Cmd::Get { title } => {
+            let mut notes = Notes::open(client, root_dir).await?;
+            println!("{}", notes.get(&title).await?);
+        }
+        Cmd::Share { title, to } => {
+            let recipient = PublicKey::from_hex(&to)
+                .map_err(|err| eyre!("Invalid recipient public key {to:?}: {err}"))?;
+            let mut notes = Notes::open(client, root_dir).await?;
+            let address = notes.share(&title, &recipient).await?;
+            println!("Shared the note '{title}' with {to}, as {address:?}");
+        }
+        Cmd::Receive => {
+            let mut events = subscribe_to_shared_notes(&client)?;
+            println!("Waiting for a note to be shared with us...");
+            let shared = next_shared_note(&client, &mut events).await?;
+            let text = fetch_note(&client, shared.address).await?;
+            println!("Received the note '{}':\n{text}", shared.title);
+        }
+        Cmd::Whoami => {}
+    }
+
+    Ok(())
+}
+
+/// Load the user's key from the root dir, generating it on first use.
+fn load_or_create_key(root_dir: &Path) -> Result<SecretKey> {
+    let path = root_dir.join("secret_key");
+    if path.exists() {
+        let hex = std::fs::read_to_string(&path).wrap_err("Failed to read the secret key")?;
+        return SecretKey::from_hex(hex.trim())
+            .map_err(|err| eyre!("Invalid secret key at {path:?}: {err}"));
+    }
+
+    let secret_key = SecretKey::random();
+    std::fs::create_dir_all(root_dir)?;
+    std::fs::write(&path, secret_key.to_hex()).wrap_err("Failed to write the secret key")?;
+    Ok(secret_key)
+}
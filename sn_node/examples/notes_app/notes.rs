@@ -0,0 +1,248 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! The notes app logic, shared by the `notes_app` example and its integration test.
+//!
+//! - Each note is encrypted to its owner's key and stored as a single chunk.
+//! - An index Register, owned by the user, maps the note titles to their chunk addresses.
+//!   Every note is written as its own branch of the Register, so that reading the Register
+//!   lists them all.
+//! - A note is shared by encrypting it to the recipient's key, storing that copy as a chunk,
+//!   and publishing its address on the recipient's gossip topic.
+#![allow(dead_code)]
+
+use bls::{Ciphertext, PublicKey, SecretKey};
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use sn_client::{
+    Client, ClientEvent, ClientEventsReceiver, ClientRegister, FilesApi, RegisterReadOptions,
+    WalletClient,
+};
+use sn_protocol::storage::{Chunk, ChunkAddress, RegisterAddress};
+use sn_transfers::{LocalWallet, NanoTokens};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+use xor_name::XorName;
+
+/// The name the index Register address is derived from, along with the owner's key.
+const NOTES_INDEX_NAME: &str = "notes-app-index";
+
+/// The gossip topic the notes shared with the given user are published on.
+pub fn shared_notes_topic(recipient: &PublicKey) -> String {
+    format!("notes-app-shared-{}", recipient.to_hex())
+}
+
+/// A note shared by another user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedNote {
+    pub title: String,
+    pub address: ChunkAddress,
+}
+
+/// The notes of the client's user.
+pub struct Notes {
+    client: Client,
+    wallet_dir: PathBuf,
+    index: ClientRegister,
+}
+
+impl Notes {
+    /// Open the notes of the client's user, creating their index Register if it doesn't exist.
+    ///
+    /// The wallet at `wallet_dir` pays for the Register and the notes.
+    pub async fn open(client: Client, wallet_dir: PathBuf) -> Result<Self> {
+        let meta = XorName::from_content(NOTES_INDEX_NAME.as_bytes());
+        let address = RegisterAddress::new(meta, client.signer_pk());
+        let index = match client
+            .get_register(address, RegisterReadOptions::default())
+            .await
+        {
+            Ok(index) => index,
+            Err(_) => {
+                let mut wallet_client = wallet_client(&client, &wallet_dir)?;
+                let (index, _cost, _royalties_fees) = client
+                    .create_and_pay_for_register(meta, &mut wallet_client, true)
+                    .await?;
+                wallet_client.store_local_wallet()?;
+                index
+            }
+        };
+
+        Ok(Self {
+            client,
+            wallet_dir,
+            index,
+        })
+    }
+
+    /// The address of the index Register.
+    pub fn index_address(&self) -> RegisterAddress {
+        *self.index.address()
+    }
+
+    /// Estimate how much storing the given note would cost, without paying for it.
+    pub async fn estimate_cost(&self, content: &str) -> Result<NanoTokens> {
+        let chunk = encrypted_chunk(content, &self.client.signer_pk());
+        let wallet_client = wallet_client(&self.client, &self.wallet_dir)?;
+        chunk_cost(&wallet_client, &chunk).await
+    }
+
+    /// Store a new note, and add it to the index.
+    pub async fn create(&mut self, title: &str, content: &str) -> Result<ChunkAddress> {
+        let chunk = encrypted_chunk(content, &self.client.signer_pk());
+        let address = self.upload(chunk).await?;
+
+        let entry = index_entry(title, &address);
+        // a new branch, keeping the other notes in the latest entries of the index
+        self.index.write_atop(&entry, &BTreeSet::new())?;
+        self.index.push(true).await?;
+        Ok(address)
+    }
+
+    /// List the notes of the index by title.
+    pub async fn list(&mut self) -> Result<BTreeMap<String, ChunkAddress>> {
+        self.index = self
+            .client
+            .get_register(self.index_address(), RegisterReadOptions::merge_all())
+            .await?;
+        Ok(self
+            .index
+            .read()
+            .into_iter()
+            .filter_map(|(_, entry)| parse_index_entry(&entry))
+            .collect())
+    }
+
+    /// Fetch and decrypt the note with the given title.
+    pub async fn get(&mut self, title: &str) -> Result<String> {
+        let address = self
+            .list()
+            .await?
+            .remove(title)
+            .ok_or_else(|| eyre!("No note titled {title:?}"))?;
+        fetch_note(&self.client, address).await
+    }
+
+    /// Share the note with the given title with another user.
+    ///
+    /// Returns the address of the copy of the note encrypted to the recipient.
+    pub async fn share(&mut self, title: &str, recipient: &PublicKey) -> Result<ChunkAddress> {
+        let content = self.get(title).await?;
+        let address = self.upload(encrypted_chunk(&content, recipient)).await?;
+
+        let msg = index_entry(title, &address);
+        self.client
+            .publish_on_topic(shared_notes_topic(recipient), Bytes::from(msg))?;
+        Ok(address)
+    }
+
+    /// Pay for and store the chunk, unless it already exists.
+    async fn upload(&self, chunk: Chunk) -> Result<ChunkAddress> {
+        let address = ChunkAddress::new(*chunk.name());
+        let files_api = FilesApi::new(self.client.clone(), self.wallet_dir.clone());
+
+        let wallet_client = files_api.wallet()?;
+        let balance = wallet_client.balance();
+        let estimate = chunk_cost(&wallet_client, &chunk).await?;
+        if balance < estimate {
+            return Err(eyre!(
+                "Not enough funds to store the note: the balance is {balance}, the estimated cost {estimate}"
+            ));
+        }
+
+        let (_costs, (payees, _skipped)) = files_api.pay_for_chunks(vec![*chunk.name()]).await?;
+        if let Some((_, payee)) = payees.into_iter().find(|(name, _)| name == chunk.name()) {
+            files_api
+                .get_local_payment_and_upload_chunk(chunk, payee, true)
+                .await?;
+        }
+        Ok(address)
+    }
+}
+
+/// Start listening to the notes shared with the client's user.
+pub fn subscribe_to_shared_notes(client: &Client) -> Result<ClientEventsReceiver> {
+    let events = client.events_channel();
+    client.subscribe_to_topic(shared_notes_topic(&client.signer_pk()))?;
+    Ok(events)
+}
+
+/// Wait for the next note shared with the client's user.
+pub async fn next_shared_note(
+    client: &Client,
+    events: &mut ClientEventsReceiver,
+) -> Result<SharedNote> {
+    let topic = shared_notes_topic(&client.signer_pk());
+    loop {
+        if let ClientEvent::GossipsubMsg {
+            topic: msg_topic,
+            msg,
+        } = events.recv().await?
+        {
+            if msg_topic != topic {
+                continue;
+            }
+            match parse_index_entry(&msg) {
+                Some((title, address)) => return Ok(SharedNote { title, address }),
+                None => println!("Ignoring an invalid shared note message"),
+            }
+        }
+    }
+}
+
+/// Fetch a note, and decrypt it with the client's key.
+pub async fn fetch_note(client: &Client, address: ChunkAddress) -> Result<String> {
+    let chunk = client.get_chunk(address, false).await?;
+    decrypt_note(chunk.value(), client.signer())
+}
+
+fn wallet_client(client: &Client, wallet_dir: &Path) -> Result<WalletClient> {
+    let wallet = LocalWallet::load_from(wallet_dir)?;
+    Ok(WalletClient::new(client.clone(), wallet))
+}
+
+/// The cost quoted by the network to store the chunk.
+async fn chunk_cost(wallet_client: &WalletClient, chunk: &Chunk) -> Result<NanoTokens> {
+    let (_payee, _payment_address, quote) = wallet_client
+        .get_store_cost_at_address(chunk.network_address())
+        .await?;
+    Ok(quote.cost)
+}
+
+fn encrypted_chunk(content: &str, recipient: &PublicKey) -> Chunk {
+    let ciphertext = recipient.encrypt(content.as_bytes());
+    Chunk::new(Bytes::from(ciphertext.to_bytes()))
+}
+
+fn decrypt_note(bytes: &[u8], secret_key: &SecretKey) -> Result<String> {
+    let ciphertext =
+        Ciphertext::from_bytes(bytes).map_err(|err| eyre!("Invalid note ciphertext: {err}"))?;
+    let content = secret_key
+        .decrypt(&ciphertext)
+        .ok_or_else(|| eyre!("The note is not encrypted to our key"))?;
+    Ok(String::from_utf8(content)?)
+}
+
+/// An index entry: the chunk name followed by the title.
+fn index_entry(title: &str, address: &ChunkAddress) -> Vec<u8> {
+    let mut entry = address.xorname().0.to_vec();
+    entry.extend_from_slice(title.as_bytes());
+    entry
+}
+
+fn parse_index_entry(entry: &[u8]) -> Option<(String, ChunkAddress)> {
+    if entry.len() < xor_name::XOR_NAME_LEN {
+        return None;
+    }
+    let (name, title) = entry.split_at(xor_name::XOR_NAME_LEN);
+    let name = XorName(name.try_into().ok()?);
+    let title = String::from_utf8(title.to_vec()).ok()?;
+    Some((title, ChunkAddress::new(name)))
+}
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use sn_client::{Client, Error, WalletClient};
+use sn_client::{Client, Error, RegisterReadOptions, WalletClient};
 use sn_registers::RegisterAddress;
 use sn_transfers::LocalWallet;
 use xor_name::XorName;
@@ -66,7 +66,10 @@ async fn main() -> Result<()> {
     let meta = XorName::from_content(reg_nickname.as_bytes());
     let address = RegisterAddress::new(meta, client.signer_pk());
     println!("Retrieving Register '{reg_nickname}' from SAFE, as user '{user}'");
-    let mut reg_replica = match client.get_register(address).await {
+    let mut reg_replica = match client
+        .get_register(address, RegisterReadOptions::default())
+        .await
+    {
         Ok(register) => {
             println!(
                 "Register '{reg_nickname}' found at {:?}!",
@@ -11,14 +11,17 @@ extern crate tracing;
 
 mod rpc_service;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use eyre::{eyre, Result};
 use libp2p::{identity::Keypair, PeerId};
 #[cfg(feature = "metrics")]
 use sn_logging::metrics::init_metrics;
 use sn_logging::{LogFormat, LogOutputDest};
-use sn_node::{Marker, NodeBuilder, NodeEvent, NodeEventsReceiver};
-use sn_peers_acquisition::{get_peers_from_args, PeersArgs};
+#[cfg(feature = "network-contacts")]
+use sn_networking::DohResolver;
+use sn_networking::RecordKindQuotas;
+use sn_node::{export_store, import_store, Marker, NodeBuilder, NodeEvent, NodeEventsReceiver};
+use sn_peers_acquisition::{get_peers_with_sources, PeerSource, PeersArgs, RoutingTableSnapshot};
 use sn_protocol::node_rpc::NodeCtrl;
 use std::{
     env,
@@ -144,25 +147,123 @@ struct Opt {
     #[clap(long)]
     local: bool,
 
+    /// Specify the maximum bytes of chunk records the node stores.
+    ///
+    /// If not provided, the chunks stored are only limited by the maximum number of records.
+    #[clap(long)]
+    max_chunks_bytes: Option<usize>,
+
+    /// Specify the maximum bytes of spend records the node stores.
+    ///
+    /// If not provided, the spends stored are only limited by the maximum number of records.
+    #[clap(long)]
+    max_spends_bytes: Option<usize>,
+
+    /// Specify the maximum bytes of register records the node stores.
+    ///
+    /// If not provided, the registers stored are only limited by the maximum number of records.
+    #[clap(long)]
+    max_registers_bytes: Option<usize>,
+
     #[cfg(feature = "open-metrics")]
     /// Specify the port to start the OpenMetrics Server in.
     ///
     /// The special value `0` will cause the OS to assign a random port.
     #[clap(long, default_value_t = 0)]
     metrics_server_port: u16,
+
+    /// Import the records of an archive written by `export-store` before starting the node.
+    ///
+    /// Every record is validated against its key, the invalid ones are skipped and reported.
+    ///
+    /// The records the node already holds are left untouched.
+    #[clap(
+        long,
+        value_name = "ARCHIVE",
+        requires = "root_dir",
+        verbatim_doc_comment
+    )]
+    import_store: Option<PathBuf>,
+
+    /// Also import the secret key from the archive given with `--import-store`.
+    ///
+    /// The node then rejoins the network with the same peer ID as the exported node.
+    #[clap(long, requires = "import_store", verbatim_doc_comment)]
+    import_keypair: bool,
+
+    #[clap(subcommand)]
+    cmd: Option<Cmd>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// Export the record store of the node at `--root-dir` to an archive.
+    ///
+    /// This is used to move a node to another machine, with `--import-store` there.
+    ///
+    /// The node must be stopped while its records are exported.
+    #[clap(verbatim_doc_comment)]
+    ExportStore {
+        /// The archive to write, a zstd compressed tarball, e.g. `archive.tar.zst`.
+        #[clap(long, value_name = "PATH")]
+        out: PathBuf,
+        /// Also export the node's secret key, for the node to keep its peer ID once imported.
+        #[clap(long)]
+        include_keypair: bool,
+    },
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
     let opt = Opt::parse();
 
+    if let Some(Cmd::ExportStore {
+        out,
+        include_keypair,
+    }) = &opt.cmd
+    {
+        let root_dir = opt
+            .root_dir
+            .as_ref()
+            .ok_or_else(|| eyre!("--root-dir is required to export the record store"))?;
+        let report = export_store(root_dir, out, *include_keypair)?;
+        println!("Exported {} records to {out:?}", report.records);
+        if report.keypair_included {
+            println!("The archive holds the node's secret key, keep it private");
+        }
+        return Ok(());
+    }
+
+    if let (Some(archive), Some(root_dir)) = (&opt.import_store, &opt.root_dir) {
+        let report = import_store(archive, root_dir, opt.import_keypair)?;
+        println!(
+            "Imported {} records from {archive:?}, {} already present",
+            report.imported, report.already_present
+        );
+        for (entry, reason) in &report.skipped {
+            println!("Skipped {entry}: {reason}");
+        }
+        if report.keypair_imported {
+            println!("Imported the node's secret key");
+        } else if opt.import_keypair {
+            println!("The archive holds no secret key, the node keeps its own");
+        }
+    }
+
     let node_socket_addr = SocketAddr::new(opt.ip, opt.port);
     let (root_dir, keypair) = get_root_dir_and_keypair(&opt.root_dir)?;
 
     let (log_output_dest, _log_appender_guard) = init_logging(&opt, keypair.public().to_peer_id())?;
 
     let rt = Runtime::new()?;
-    let bootstrap_peers = rt.block_on(get_peers_from_args(opt.peers))?;
+    let mut peers_args = opt.peers.clone();
+    peers_args.set_routing_table_snapshot(RoutingTableSnapshot::new(&root_dir));
+    let bootstrap_peers = rt.block_on(get_peers_with_sources(peers_args))?;
+    let snapshot_peers = bootstrap_peers
+        .iter()
+        .filter(|(_, source)| *source == PeerSource::RoutingTableSnapshot)
+        .count();
+    let bootstrap_peers: Vec<_> = bootstrap_peers.into_iter().map(|(peer, _)| peer).collect();
     let msg = format!(
         "Running {} v{}",
         env!("CARGO_BIN_NAME"),
@@ -172,6 +273,11 @@ fn main() -> Result<()> {
     debug!("Built with git version: {}", sn_build_info::git_info());
 
     info!("Node started with initial_peers {bootstrap_peers:?}");
+    if snapshot_peers > 0 {
+        let msg = format!("Using {snapshot_peers} peers from the routing table snapshot");
+        info!("{msg}");
+        println!("{msg}");
+    }
 
     // Create a tokio runtime per `run_node` attempt, this ensures
     // any spawned tasks are closed before we would attempt to run
@@ -179,15 +285,24 @@ fn main() -> Result<()> {
     #[cfg(feature = "metrics")]
     rt.spawn(init_metrics(std::process::id()));
     rt.block_on(async move {
-        let node_builder = NodeBuilder::new(
+        let mut node_builder = NodeBuilder::new(
             keypair,
             node_socket_addr,
             bootstrap_peers,
             opt.local,
             root_dir,
         );
-        #[cfg(feature = "open-metrics")]
-        let mut node_builder = node_builder;
+        node_builder.record_kind_quotas(RecordKindQuotas {
+            max_chunks_bytes: opt.max_chunks_bytes,
+            max_spends_bytes: opt.max_spends_bytes,
+            max_registers_bytes: opt.max_registers_bytes,
+        });
+        // the records imported are only held once restored
+        node_builder.restore_records(opt.import_store.is_some());
+        #[cfg(feature = "network-contacts")]
+        if let Some(server) = &opt.peers.doh_server {
+            node_builder.doh_resolver(DohResolver::new(server.clone())?);
+        }
         #[cfg(feature = "open-metrics")]
         node_builder.metrics_server_port(opt.metrics_server_port);
         run_node(node_builder, opt.rpc, &log_output_dest).await?;
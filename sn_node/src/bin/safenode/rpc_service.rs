@@ -13,14 +13,17 @@ use bls::{PublicKey, PK_SIZE};
 use eyre::{ErrReport, Result};
 use sn_protocol::node_rpc::NodeCtrl;
 use sn_protocol::safenode_proto::{
-    k_buckets_response,
+    k_buckets_response, peer_scores_response,
     safe_node_server::{SafeNode, SafeNodeServer},
     GossipsubPublishRequest, GossipsubPublishResponse, GossipsubSubscribeRequest,
     GossipsubSubscribeResponse, GossipsubUnsubscribeRequest, GossipsubUnsubscribeResponse,
     KBucketsRequest, KBucketsResponse, NetworkInfoRequest, NetworkInfoResponse, NodeEvent,
-    NodeEventsRequest, NodeInfoRequest, NodeInfoResponse, RecordAddressesRequest,
-    RecordAddressesResponse, RestartRequest, RestartResponse, StopRequest, StopResponse,
-    TransferNotifsFilterRequest, TransferNotifsFilterResponse, UpdateRequest, UpdateResponse,
+    NodeEventsRequest, NodeInfoRequest, NodeInfoResponse, PeerScoresRequest, PeerScoresResponse,
+    RecordAddressesRequest, RecordAddressesResponse, RemoveRecordRequest, RemoveRecordResponse,
+    ReplicateCorruptedRecordRequest, ReplicateCorruptedRecordResponse, RestartRequest,
+    RestartResponse, SetRoyaltyRateRequest, SetRoyaltyRateResponse, StopRequest, StopResponse,
+    StoreStatsRequest, StoreStatsResponse, TransferNotifsFilterRequest,
+    TransferNotifsFilterResponse, UpdateRequest, UpdateResponse,
 };
 use std::collections::HashMap;
 use std::{
@@ -193,6 +196,34 @@ impl SafeNode for SafeNodeRpcService {
         Ok(Response::new(RecordAddressesResponse { addresses }))
     }
 
+    async fn store_stats(
+        &self,
+        request: Request<StoreStatsRequest>,
+    ) -> Result<Response<StoreStatsResponse>, Status> {
+        trace!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        let usage = self
+            .running_node
+            .get_record_kind_usage()
+            .await
+            .map_err(|err| {
+                Status::new(
+                    Code::Internal,
+                    format!("Failed to get the store stats: {err}"),
+                )
+            })?;
+
+        Ok(Response::new(StoreStatsResponse {
+            chunks_bytes: usage.chunks_bytes as u64,
+            spends_bytes: usage.spends_bytes as u64,
+            registers_bytes: usage.registers_bytes as u64,
+        }))
+    }
+
     async fn k_buckets(
         &self,
         request: Request<KBucketsRequest>,
@@ -219,6 +250,136 @@ impl SafeNode for SafeNodeRpcService {
         Ok(Response::new(KBucketsResponse { kbuckets }))
     }
 
+    async fn peer_scores(
+        &self,
+        request: Request<PeerScoresRequest>,
+    ) -> Result<Response<PeerScoresResponse>, Status> {
+        trace!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        let now = Instant::now();
+        let scores = self
+            .running_node
+            .peer_scores()
+            .into_iter()
+            .map(|(peer, score)| peer_scores_response::PeerScore {
+                peer: peer.to_bytes(),
+                score: score.score,
+                invalid_records: score.invalid_records,
+                refused_for_secs: score
+                    .refused_until
+                    .map(|until| until.saturating_duration_since(now).as_secs())
+                    .unwrap_or(0),
+            })
+            .collect();
+
+        Ok(Response::new(PeerScoresResponse { scores }))
+    }
+
+    async fn replicate_corrupted_record(
+        &self,
+        request: Request<ReplicateCorruptedRecordRequest>,
+    ) -> Result<Response<ReplicateCorruptedRecordResponse>, Status> {
+        trace!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        #[cfg(feature = "test-utils")]
+        {
+            let peer = libp2p::PeerId::from_bytes(&request.get_ref().peer).map_err(|err| {
+                Status::new(
+                    Code::InvalidArgument,
+                    format!("Failed to decode the PeerId: {err}"),
+                )
+            })?;
+            self.running_node
+                .replicate_corrupted_record(peer)
+                .map_err(|err| {
+                    Status::new(
+                        Code::Internal,
+                        format!("Failed to replicate a corrupted record to {peer:?}: {err}"),
+                    )
+                })?;
+            Ok(Response::new(ReplicateCorruptedRecordResponse {}))
+        }
+
+        #[cfg(not(feature = "test-utils"))]
+        Err(Status::new(
+            Code::Unimplemented,
+            "The node was not built with the test-utils feature",
+        ))
+    }
+
+    async fn remove_record(
+        &self,
+        request: Request<RemoveRecordRequest>,
+    ) -> Result<Response<RemoveRecordResponse>, Status> {
+        trace!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        #[cfg(feature = "test-utils")]
+        {
+            let key = libp2p::kad::RecordKey::new(&request.get_ref().key);
+            self.running_node
+                .remove_record(key.clone())
+                .map_err(|err| {
+                    Status::new(
+                        Code::Internal,
+                        format!("Failed to remove the record {key:?}: {err}"),
+                    )
+                })?;
+            Ok(Response::new(RemoveRecordResponse {}))
+        }
+
+        #[cfg(not(feature = "test-utils"))]
+        Err(Status::new(
+            Code::Unimplemented,
+            "The node was not built with the test-utils feature",
+        ))
+    }
+
+    async fn set_royalty_rate(
+        &self,
+        request: Request<SetRoyaltyRateRequest>,
+    ) -> Result<Response<SetRoyaltyRateResponse>, Status> {
+        trace!(
+            "RPC request received at {}: {:?}",
+            self.addr,
+            request.get_ref()
+        );
+
+        #[cfg(feature = "test-utils")]
+        {
+            let royalty_rate = sn_transfers::RoyaltyRate {
+                version: request.get_ref().version,
+                basis_points: request.get_ref().basis_points,
+            };
+            self.running_node
+                .set_royalty_rate(royalty_rate)
+                .map_err(|err| {
+                    Status::new(
+                        Code::Internal,
+                        format!("Failed to set the royalty rate to {royalty_rate:?}: {err}"),
+                    )
+                })?;
+            Ok(Response::new(SetRoyaltyRateResponse {}))
+        }
+
+        #[cfg(not(feature = "test-utils"))]
+        Err(Status::new(
+            Code::Unimplemented,
+            "The node was not built with the test-utils feature",
+        ))
+    }
+
     async fn subscribe_to_topic(
         &self,
         request: Request<GossipsubSubscribeRequest>,
@@ -9,6 +9,7 @@
 use sn_protocol::PrettyPrintRecordKey;
 use sn_registers::RegisterAddress;
 use sn_transfers::{NanoTokens, SpendAddress, WalletError};
+use std::path::PathBuf;
 use thiserror::Error;
 
 pub(super) type Result<T, E = Error> = std::result::Result<T, E>;
@@ -41,6 +42,17 @@ pub enum Error {
     #[error("Overflow occurred while adding values")]
     NumericOverflow,
 
+    #[error("I/O error {0}")]
+    Io(#[from] std::io::Error),
+
+    // ---------- Store Archive Errors
+    #[error("No secret key found at {0:?} to export")]
+    SecretKeyNotFound(PathBuf),
+    #[error("The node already has a different secret key at {0:?}, refusing to overwrite it")]
+    SecretKeyAlreadyExists(PathBuf),
+    #[error("The archived secret key is not a valid ed25519 key")]
+    InvalidArchivedSecretKey,
+
     // ---------- Record Errors
     #[error("Record was not stored as no payment supplied: {0:?}")]
     InvalidPutWithoutPayment(PrettyPrintRecordKey<'static>),
@@ -86,4 +98,60 @@ pub enum Error {
         paid: NanoTokens,
         expected: NanoTokens,
     },
+    /// The payment carries a voucher it wasn't made with
+    #[error("Payment proof received with record:{0:?} was not made with the voucher it carries")]
+    PaymentNotMadeWithVoucher(PrettyPrintRecordKey<'static>),
+}
+
+impl Error {
+    /// Whether the error is down to the record being invalid in itself, i.e. its content not
+    /// matching its key, or a hash or signature in it not matching what it signs, rather than to
+    /// the state of our node or of the network.
+    pub(crate) fn is_invalid_record(&self) -> bool {
+        matches!(
+            self,
+            Error::RecordKeyMismatch
+                | Error::MultipleUniquePubKey
+                | Error::Register(
+                    sn_registers::Error::InvalidSignature
+                        | sn_registers::Error::MissingSignature
+                        | sn_registers::Error::RegisterAddrMismatch { .. }
+                )
+                | Error::Transfers(
+                    sn_transfers::Error::InvalidSpendSignature(_)
+                        | sn_transfers::Error::TransactionHashMismatch(..)
+                )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{DerivationIndex, MainSecretKey, RoyaltyRate, ROYALTY_RATE};
+
+    #[test]
+    fn only_mismatches_within_the_record_make_it_invalid() {
+        let unique_pubkey = MainSecretKey::random()
+            .main_pubkey()
+            .new_unique_pubkey(&DerivationIndex([0; 32]));
+        assert!(Error::RecordKeyMismatch.is_invalid_record());
+        assert!(Error::Register(sn_registers::Error::InvalidSignature).is_invalid_record());
+        assert!(
+            Error::Transfers(sn_transfers::Error::InvalidSpendSignature(unique_pubkey))
+                .is_invalid_record()
+        );
+
+        // the record may well be valid, at another royalty rate or with the parents we lack
+        assert!(!Error::Protocol(sn_protocol::Error::RoyaltyRateMismatch {
+            ours: ROYALTY_RATE,
+            theirs: RoyaltyRate {
+                version: ROYALTY_RATE.version + 1,
+                basis_points: 1000,
+            },
+        })
+        .is_invalid_record());
+        assert!(!Error::SpendParentTxInvalid("parent not found".to_string()).is_invalid_record());
+        assert!(!Error::Transfers(sn_transfers::Error::OutputNotFound).is_invalid_record());
+    }
 }
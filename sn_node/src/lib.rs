@@ -33,24 +33,34 @@ mod log_markers;
 #[cfg(feature = "open-metrics")]
 mod metrics;
 mod node;
+mod peer_scores;
 mod put_validation;
 mod quote;
+mod refused_payments;
+mod register_claims;
 mod replication;
 mod spends;
+mod store_archive;
 
 pub use self::{
     event::{NodeEvent, NodeEventsChannel, NodeEventsReceiver},
     log_markers::Marker,
     node::{
-        NodeBuilder, NodeCmd, PERIODIC_REPLICATION_INTERVAL_MAX_S, ROYALTY_TRANSFER_NOTIF_TOPIC,
+        NodeBuilder, NodeCmd, DEFAULT_MAX_PENDING_RECORDS, PERIODIC_REPLICATION_INTERVAL_MAX_S,
+        ROYALTY_TRANSFER_NOTIF_TOPIC,
     },
+    peer_scores::{PeerScore, MAX_PEER_SCORE},
+    store_archive::{export_store, import_store, StoreExportReport, StoreImportReport},
 };
 
-use crate::error::{Error, Result};
+use crate::{
+    error::{Error, Result},
+    peer_scores::PeerScores,
+};
 use bls::PublicKey;
 use bytes::Bytes;
 use libp2p::PeerId;
-use sn_networking::{Network, SwarmLocalState};
+use sn_networking::{Network, RecordKindUsage, SwarmLocalState};
 use sn_protocol::NetworkAddress;
 use std::{
     collections::{BTreeMap, HashSet},
@@ -65,6 +75,7 @@ pub struct RunningNode {
     network: Network,
     node_events_channel: NodeEventsChannel,
     node_cmds: broadcast::Sender<NodeCmd>,
+    peer_scores: PeerScores,
 }
 
 impl RunningNode {
@@ -109,6 +120,12 @@ impl RunningNode {
         Ok(addresses)
     }
 
+    /// Returns the bytes held by the node for each kind of record
+    pub async fn get_record_kind_usage(&self) -> Result<RecordKindUsage> {
+        let usage = self.network.get_record_kind_usage().await?;
+        Ok(usage)
+    }
+
     /// Returns a map where each key is the ilog2 distance of that Kbucket and each value is a vector of peers in that
     /// bucket.
     pub async fn get_kbuckets(&self) -> Result<BTreeMap<u32, Vec<PeerId>>> {
@@ -150,4 +167,57 @@ impl RunningNode {
             .map_err(|err| Error::NodeCmdFailed(err.to_string()))?;
         Ok(())
     }
+
+    /// Returns the scores of the peers which replicated invalid records to this node.
+    pub fn peer_scores(&self) -> Vec<(PeerId, PeerScore)> {
+        self.peer_scores.scores()
+    }
+
+    /// Test hook: store a record whose content doesn't match its key, and replicate it to the
+    /// given peer, which is expected to reject it and penalise us.
+    #[cfg(feature = "test-utils")]
+    pub fn replicate_corrupted_record(&self, peer: PeerId) -> Result<()> {
+        use libp2p::kad::{Record, RecordKey};
+        use sn_protocol::{
+            messages::{Cmd, Request},
+            storage::{try_serialize_record, Chunk, RecordKind, RecordType},
+        };
+
+        // the record is keyed at the peer's own address, for it to be in its close range
+        let key = RecordKey::new(&peer.to_bytes());
+        let chunk = Chunk::new(Bytes::from(rand::random::<[u8; 32]>().to_vec()));
+        let record = Record {
+            key: key.clone(),
+            value: try_serialize_record(&chunk, RecordKind::Chunk)?.to_vec(),
+            publisher: None,
+            expires: None,
+        };
+        self.network.put_local_record(record)?;
+
+        let request = Request::Cmd(Cmd::Replicate {
+            holder: NetworkAddress::from_peer(self.network.peer_id),
+            keys: vec![(NetworkAddress::from_record_key(&key), RecordType::Chunk)],
+        });
+        self.network.send_req_ignore_reply(request, peer)?;
+        Ok(())
+    }
+
+    /// Test hook: quote and expect the network royalties at the given rate, rather than at
+    /// `ROYALTY_RATE`, e.g. for clients to find the network moved to another rate.
+    #[cfg(feature = "test-utils")]
+    pub fn set_royalty_rate(&self, royalty_rate: sn_transfers::RoyaltyRate) -> Result<()> {
+        let _ = self
+            .node_cmds
+            .send(NodeCmd::RoyaltyRate(royalty_rate))
+            .map_err(|err| Error::NodeCmdFailed(err.to_string()))?;
+        Ok(())
+    }
+
+    /// Test hook: remove the record at the given key from the store of this node, e.g. for the
+    /// data it held to go missing from the network.
+    #[cfg(feature = "test-utils")]
+    pub fn remove_record(&self, key: libp2p::kad::RecordKey) -> Result<()> {
+        self.network.remove_failed_local_record(key)?;
+        Ok(())
+    }
 }
@@ -66,6 +66,11 @@ pub enum Marker<'a> {
 
     /// Record rejected
     RecordRejected(&'a PrettyPrintRecordKey<'a>, &'a Error),
+
+    /// A peer replicated an invalid record to us, and was penalised for it
+    PeerPenalisedForInvalidRecord(PeerId),
+    /// Replication from a peer was refused, as it replicated too many invalid records to us
+    ReplicationRefusedFromPeer(PeerId),
 }
 
 impl<'a> Marker<'a> {
@@ -27,6 +27,8 @@ pub(crate) struct NodeMetrics {
     /// replication
     replication_triggered: Counter,
     replication_keys_to_fetch: Histogram,
+    peer_penalised_for_invalid_record: Counter,
+    replication_refused_from_peer: Counter,
 
     // routing table
     peer_added_to_routing_table: Counter,
@@ -80,6 +82,20 @@ impl NodeMetrics {
             replication_keys_to_fetch.clone(),
         );
 
+        let peer_penalised_for_invalid_record = Counter::default();
+        sub_registry.register(
+            "peer_penalised_for_invalid_record",
+            "Number of invalid records replicated to us, for which their sender was penalised",
+            peer_penalised_for_invalid_record.clone(),
+        );
+
+        let replication_refused_from_peer = Counter::default();
+        sub_registry.register(
+            "replication_refused_from_peer",
+            "Number of record keys not fetched, as replicated by peers with a too low score",
+            replication_refused_from_peer.clone(),
+        );
+
         let peer_added_to_routing_table = Counter::default();
         sub_registry.register(
             "peer_added_to_routing_table",
@@ -106,6 +122,8 @@ impl NodeMetrics {
             put_record_err,
             replication_triggered,
             replication_keys_to_fetch,
+            peer_penalised_for_invalid_record,
+            replication_refused_from_peer,
             peer_added_to_routing_table,
             peer_removed_from_routing_table,
             reward_wallet_balance,
@@ -154,6 +172,14 @@ impl NodeMetrics {
                 .replication_keys_to_fetch
                 .observe(fetching_keys_len as f64),
 
+            Marker::PeerPenalisedForInvalidRecord(_) => {
+                let _ = self.peer_penalised_for_invalid_record.inc();
+            }
+
+            Marker::ReplicationRefusedFromPeer(_) => {
+                let _ = self.replication_refused_from_peer.inc();
+            }
+
             Marker::PeerAddedToRoutingTable(_) => {
                 let _ = self.peer_added_to_routing_table.inc();
             }
@@ -6,23 +6,39 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use super::{error::Result, event::NodeEventsChannel, Marker, NodeEvent};
+use super::{
+    error::{Error, Result},
+    event::NodeEventsChannel,
+    Marker, NodeEvent,
+};
 #[cfg(feature = "open-metrics")]
 use crate::metrics::NodeMetrics;
-use crate::RunningNode;
+use crate::{
+    peer_scores::PeerScores, refused_payments::RefusedPayments, register_claims::RegisterClaims,
+    RunningNode,
+};
 use bls::{PublicKey, PK_SIZE};
 use bytes::Bytes;
 use libp2p::{autonat::NatStatus, identity::Keypair, Multiaddr};
 #[cfg(feature = "open-metrics")]
 use prometheus_client::registry::Registry;
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use sn_networking::{Network, NetworkBuilder, NetworkEvent, SwarmDriver, CLOSE_GROUP_SIZE};
+#[cfg(feature = "network-contacts")]
+use sn_networking::DohResolver;
+use sn_networking::{
+    Network, NetworkBuilder, NetworkEvent, RecordKindQuotas, SwarmDriver, CLOSE_GROUP_SIZE,
+};
+use sn_peers_acquisition::RoutingTableSnapshot;
 use sn_protocol::{
     error::Error as ProtocolError,
     messages::{ChunkProof, CmdResponse, Query, QueryResponse, Response},
+    storage::try_deserialize_record,
     NetworkAddress, PrettyPrintRecordKey,
 };
-use sn_transfers::{CashNoteRedemption, LocalWallet, MainPubkey, MainSecretKey, NanoTokens};
+use sn_transfers::{
+    CashNoteRedemption, LocalWallet, MainPubkey, MainSecretKey, NanoTokens, RoyaltyRate,
+    SignedSpend, ROYALTY_RATE,
+};
 use std::{
     net::SocketAddr,
     path::PathBuf,
@@ -50,6 +66,20 @@ const FORWARDER_CHOOSING_FACTOR: usize = 10;
 /// This is the max time it should take. Minimum interval at any ndoe will be half this
 pub const PERIODIC_REPLICATION_INTERVAL_MAX_S: u64 = 45;
 
+/// The number of records awaiting validation beyond which the node is too busy to quote for more.
+pub const DEFAULT_MAX_PENDING_RECORDS: usize = 512;
+
+/// How long a busy node asks clients to wait before asking it for a quote again.
+const BUSY_RETRY_AFTER_MS: u64 = 3000;
+
+/// Interval to refresh the snapshot of the routing table kept under the node's root dir, which
+/// the node bootstraps from when restarting.
+const ROUTING_TABLE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How long after startup the first snapshot of the routing table is taken, for the node to
+/// have joined the network by then. A node restarted before the first interval has one as well.
+const ROUTING_TABLE_SNAPSHOT_AT_STARTUP_DELAY: Duration = Duration::from_secs(30);
+
 /// Helper to build and run a Node
 pub struct NodeBuilder {
     keypair: Keypair,
@@ -57,6 +87,11 @@ pub struct NodeBuilder {
     initial_peers: Vec<Multiaddr>,
     local: bool,
     root_dir: PathBuf,
+    record_kind_quotas: RecordKindQuotas,
+    restore_records: bool,
+    max_pending_records: usize,
+    #[cfg(feature = "network-contacts")]
+    doh_resolver: Option<DohResolver>,
     #[cfg(feature = "open-metrics")]
     metrics_server_port: u16,
 }
@@ -76,11 +111,41 @@ impl NodeBuilder {
             initial_peers,
             local,
             root_dir,
+            record_kind_quotas: RecordKindQuotas::default(),
+            restore_records: false,
+            max_pending_records: DEFAULT_MAX_PENDING_RECORDS,
+            #[cfg(feature = "network-contacts")]
+            doh_resolver: None,
             #[cfg(feature = "open-metrics")]
             metrics_server_port: 0,
         }
     }
 
+    /// Limit the bytes stored for each kind of record. Unlimited by default
+    pub fn record_kind_quotas(&mut self, record_kind_quotas: RecordKindQuotas) {
+        self.record_kind_quotas = record_kind_quotas;
+    }
+
+    /// Hold the records found in the record store dir at startup, e.g. imported from another
+    /// machine, each being validated against its key first. Off by default
+    pub fn restore_records(&mut self, restore_records: bool) {
+        self.restore_records = restore_records;
+    }
+
+    /// Set the number of records awaiting validation beyond which the node reports being busy
+    /// when asked for a quote, and refuses the records put to it. Defaults to
+    /// `DEFAULT_MAX_PENDING_RECORDS`
+    pub fn max_pending_records(&mut self, max_pending_records: usize) {
+        self.max_pending_records = max_pending_records;
+    }
+
+    /// Resolve the host names of the peers dialed through DNS-over-HTTPS, instead of the system
+    /// resolver.
+    #[cfg(feature = "network-contacts")]
+    pub fn doh_resolver(&mut self, doh_resolver: DohResolver) {
+        self.doh_resolver = Some(doh_resolver);
+    }
+
     #[cfg(feature = "open-metrics")]
     /// Set the port for the OpenMetrics server. Defaults to a random port if not set
     pub fn metrics_server_port(&mut self, port: u16) {
@@ -115,10 +180,17 @@ impl NodeBuilder {
             (metrics_registry, node_metrics)
         };
 
+        let routing_table_snapshot = RoutingTableSnapshot::new(&self.root_dir);
         let mut network_builder = NetworkBuilder::new(self.keypair, self.local, self.root_dir);
 
         network_builder.enable_gossip();
         network_builder.listen_addr(self.addr);
+        network_builder.record_kind_quotas(self.record_kind_quotas);
+        network_builder.restore_records(self.restore_records);
+        #[cfg(feature = "network-contacts")]
+        if let Some(doh_resolver) = self.doh_resolver {
+            network_builder.doh_resolver(doh_resolver);
+        }
         #[cfg(feature = "open-metrics")]
         network_builder.metrics_registry(metrics_registry);
         #[cfg(feature = "open-metrics")]
@@ -127,6 +199,7 @@ impl NodeBuilder {
         let (network, network_event_receiver, swarm_driver) = network_builder.build_node()?;
         let node_events_channel = NodeEventsChannel::default();
         let (node_cmds, _) = broadcast::channel(10);
+        let peer_scores = PeerScores::default();
 
         let node = Node {
             network: network.clone(),
@@ -135,6 +208,13 @@ impl NodeBuilder {
             initial_peers: Arc::new(self.initial_peers),
             reward_address: Arc::new(reward_address),
             transfer_notifs_filter: None,
+            royalty_rate: ROYALTY_RATE,
+            register_claims: RegisterClaims::default(),
+            refused_payments: RefusedPayments::default(),
+            pending_records: Arc::new(AtomicUsize::new(0)),
+            max_pending_records: self.max_pending_records,
+            routing_table_snapshot: Arc::new(routing_table_snapshot),
+            peer_scores: peer_scores.clone(),
             #[cfg(feature = "open-metrics")]
             node_metrics,
         };
@@ -142,6 +222,7 @@ impl NodeBuilder {
             network,
             node_events_channel,
             node_cmds,
+            peer_scores,
         };
 
         // Run the node
@@ -168,6 +249,10 @@ impl NodeBuilder {
 pub enum NodeCmd {
     /// Set a PublicKey to start decoding and accepting Transfer notifications received over gossipsub.
     TransferNotifsFilter(Option<PublicKey>),
+    /// Set the rate the network royalties are quoted and expected at, to tell how clients
+    /// paying at another one are dealt with.
+    #[cfg(feature = "test-utils")]
+    RoyaltyRate(RoyaltyRate),
 }
 
 /// `Node` represents a single node in the distributed network. It handles
@@ -183,6 +268,19 @@ pub(crate) struct Node {
     initial_peers: Arc<Vec<Multiaddr>>,
     reward_address: Arc<MainPubkey>,
     transfer_notifs_filter: Option<PublicKey>,
+    // The rate the network royalties are quoted and expected at.
+    royalty_rate: RoyaltyRate,
+    // Claims on Registers still being uploaded by their creator.
+    pub(crate) register_claims: RegisterClaims,
+    // The records recently refused for their payment, reported to their putters.
+    refused_payments: RefusedPayments,
+    // The number of records received that are still being validated.
+    pending_records: Arc<AtomicUsize>,
+    max_pending_records: usize,
+    // Where the peers of the routing table are periodically saved to.
+    routing_table_snapshot: Arc<RoutingTableSnapshot>,
+    // The standing of the peers which replicated invalid records to us.
+    pub(crate) peer_scores: PeerScores,
     #[cfg(feature = "open-metrics")]
     pub(crate) node_metrics: NodeMetrics,
 }
@@ -212,6 +310,12 @@ impl Node {
             let mut replication_interval = tokio::time::interval(replication_interval_time);
             let _ = replication_interval.tick().await; // first tick completes immediately
 
+            // the first snapshot is taken at startup, once joined
+            let mut routing_table_snapshot_interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + ROUTING_TABLE_SNAPSHOT_AT_STARTUP_DELAY,
+                ROUTING_TABLE_SNAPSHOT_INTERVAL,
+            );
+
             loop {
                 let peers_connected = &peers_connected;
 
@@ -249,12 +353,33 @@ impl Node {
                             trace!("Periodic replication took {:?}", start.elapsed());
                         });
                     }
+                    // runs at startup, then every ROUTING_TABLE_SNAPSHOT_INTERVAL time
+                    _ = routing_table_snapshot_interval.tick() => {
+                        let network = self.network.clone();
+                        let snapshot = self.routing_table_snapshot.clone();
+
+                        let _handle = spawn(async move {
+                            match network.get_routing_table_addrs().await {
+                                Ok(peers) => {
+                                    if let Err(err) = snapshot.save(&peers) {
+                                        warn!("Failed to save the routing table snapshot: {err}");
+                                    }
+                                }
+                                Err(err) => error!("Error while fetching the routing table peers {err:?}"),
+                            }
+                        });
+                    }
                     node_cmd = cmds_receiver.recv() => {
                         match node_cmd {
                             Ok(NodeCmd::TransferNotifsFilter(filter)) => {
                                 self.transfer_notifs_filter = filter;
                                 let _ = self.network.start_handle_gossip();
                             }
+                            #[cfg(feature = "test-utils")]
+                            Ok(NodeCmd::RoyaltyRate(royalty_rate)) => {
+                                warn!("Now quoting and expecting the network royalties at {royalty_rate:?}");
+                                self.royalty_rate = royalty_rate;
+                            }
                             Err(err) => error!("When trying to read from the NodeCmds channel/receiver: {err:?}")
                         }
                     }
@@ -358,9 +483,22 @@ impl Node {
             NetworkEvent::QueryRequestReceived { query, channel } => {
                 let network = self.network.clone();
                 let payment_address = *self.reward_address;
+                let register_claims = self.register_claims.clone();
+                let refused_payments = self.refused_payments.clone();
+                let royalty_rate = self.royalty_rate;
+                let busy = self.is_busy();
 
                 let _handle = spawn(async move {
-                    let res = Self::handle_query(&network, query, payment_address).await;
+                    let res = Self::handle_query(
+                        &network,
+                        &register_claims,
+                        &refused_payments,
+                        query,
+                        payment_address,
+                        royalty_rate,
+                        busy,
+                    )
+                    .await;
                     trace!("Sending response {res:?}");
 
                     if let Err(error) = network.send_response(res, channel) {
@@ -368,19 +506,45 @@ impl Node {
                     }
                 });
             }
+            NetworkEvent::UnverifiedRecord(record) if self.is_busy() => {
+                // the putter is told to back off when it asks for the proof of the record
+                warn!(
+                    "Too many records awaiting validation, refusing {:?}",
+                    PrettyPrintRecordKey::from(&record.key)
+                );
+            }
             NetworkEvent::UnverifiedRecord(record) => {
                 // queries can be long running and require validation, so we spawn a task to handle them
                 let self_clone = self.clone();
+                let _ = self.pending_records.fetch_add(1, Ordering::SeqCst);
                 let _handle = spawn(async move {
+                    let record_key = record.key.clone();
                     let key = PrettyPrintRecordKey::from(&record.key).into_owned();
                     match self_clone.validate_and_store_record(record).await {
                         Ok(cmdok) => trace!("UnverifiedRecord {key} stored with {cmdok:?}."),
                         Err(err) => {
+                            // the putter has to pay again, at our rate
+                            if let Error::Protocol(
+                                reason @ ProtocolError::RoyaltyRateMismatch { .. },
+                            ) = &err
+                            {
+                                self_clone
+                                    .refused_payments
+                                    .refuse(record_key, reason.clone());
+                            }
                             self_clone.record_metrics(Marker::RecordRejected(&key, &err));
                         }
                     }
+                    let _ = self_clone.pending_records.fetch_sub(1, Ordering::SeqCst);
                 });
             }
+            NetworkEvent::PeerBusy { peer, retry_after } => {
+                debug!("Peer {peer:?} is busy, asking to retry after {retry_after:?}");
+            }
+            NetworkEvent::PeerConnected(peer_id, live_peers)
+            | NetworkEvent::PeerDisconnected(peer_id, live_peers) => {
+                trace!("Connectivity to {peer_id:?} changed, {live_peers} live peers");
+            }
             NetworkEvent::GossipsubMsgReceived { topic, msg }
             | NetworkEvent::GossipsubMsgPublished { topic, msg } => {
                 trace!("Received a gossip msg for the topic of {topic}");
@@ -421,6 +585,12 @@ impl Node {
         );
     }
 
+    /// Whether more records are awaiting validation than we are willing to queue up. Records put
+    /// to us are then refused, and their putters asked to retry after `BUSY_RETRY_AFTER_MS`.
+    fn is_busy(&self) -> bool {
+        self.pending_records.load(Ordering::SeqCst) > self.max_pending_records
+    }
+
     // Handle the response that was not awaited at the call site
     fn handle_response(&self, response: Response) -> Result<()> {
         match response {
@@ -441,10 +611,24 @@ impl Node {
 
     async fn handle_query(
         network: &Network,
+        register_claims: &RegisterClaims,
+        refused_payments: &RefusedPayments,
         query: Query,
         payment_address: MainPubkey,
+        royalty_rate: RoyaltyRate,
+        busy: bool,
     ) -> Response {
         let resp: QueryResponse = match query {
+            Query::GetStoreCost(address) if busy => {
+                warn!("Too many records awaiting validation to quote for {address:?}");
+                QueryResponse::GetStoreCost {
+                    quote: Err(ProtocolError::Busy {
+                        retry_after_ms: BUSY_RETRY_AFTER_MS,
+                    }),
+                    payment_address,
+                    peer_address: NetworkAddress::from_peer(network.peer_id),
+                }
+            }
             Query::GetStoreCost(address) => {
                 trace!("Got GetStoreCost request for {address:?}");
                 let record_key = address.to_record_key();
@@ -464,7 +648,12 @@ impl Node {
                             }
                         } else {
                             QueryResponse::GetStoreCost {
-                                quote: Self::create_quote_for_storecost(network, cost, &address),
+                                quote: Self::create_quote_for_storecost(
+                                    network,
+                                    cost,
+                                    royalty_rate,
+                                    &address,
+                                ),
                                 payment_address,
                                 peer_address: NetworkAddress::from_peer(self_id),
                             }
@@ -499,10 +688,19 @@ impl Node {
                 trace!("Got GetChunkExistenceProof for chunk {key:?}");
 
                 let mut result = Err(ProtocolError::ChunkDoesNotExist(key.clone()));
-                if let Ok(Some(record)) = network.get_local_record(&key.to_record_key()).await {
+                let record_key = key.to_record_key();
+                if let Ok(Some(record)) = network.get_local_record(&record_key).await {
                     let proof = ChunkProof::new(&record.value, nonce);
                     trace!("Chunk proof for {key:?} is {proof:?}");
                     result = Ok(proof)
+                } else if let Some(reason) = refused_payments.refusal(&record_key) {
+                    debug!("Chunk {key:?} was refused for its payment: {reason}");
+                    result = Err(reason);
+                } else if busy {
+                    warn!("Too many records awaiting validation to have stored chunk {key:?}");
+                    result = Err(ProtocolError::Busy {
+                        retry_after_ms: BUSY_RETRY_AFTER_MS,
+                    });
                 } else {
                     trace!(
                         "Could not get ChunkProof for {key:?} as we don't have the record locally."
@@ -511,6 +709,24 @@ impl Node {
 
                 QueryResponse::GetChunkExistenceProof(result)
             }
+            Query::ClaimRegister { address, claim } => {
+                trace!("Got ClaimRegister for {address:?} with claim {claim:?}");
+                let (result, held_claim) = register_claims.claim(address, &claim);
+                QueryResponse::ClaimRegister { result, held_claim }
+            }
+            Query::GetSpendConflicts { address } => {
+                trace!("Got GetSpendConflicts for {address:?}");
+                let record_key = NetworkAddress::from_spend_address(address).to_record_key();
+
+                // double spends are stored aggregated in the one record
+                let result = match network.get_local_record(&record_key).await {
+                    Ok(Some(record)) => try_deserialize_record::<Vec<SignedSpend>>(&record)
+                        .map(|spends| if spends.len() > 1 { spends } else { vec![] }),
+                    _ => Ok(vec![]),
+                };
+
+                QueryResponse::GetSpendConflicts(result)
+            }
         };
         Response::Query(resp)
     }
@@ -0,0 +1,164 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use libp2p::PeerId;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// The score a peer starts with, and gets back to once its replication cooldown is over.
+pub const MAX_PEER_SCORE: u32 = 100;
+
+/// The score a peer loses for each invalid record it replicates to us.
+const INVALID_RECORD_PENALTY: u32 = 30;
+
+/// Replication from a peer is refused once its score falls below this.
+const MIN_PEER_SCORE: u32 = 50;
+
+/// How long replication from a peer is refused for, once its score fell below `MIN_PEER_SCORE`.
+const REPLICATION_COOLDOWN: Duration = Duration::from_secs(600);
+
+/// The standing of a peer, as far as the records it replicated to us go.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeerScore {
+    /// Down from `MAX_PEER_SCORE`, as the peer replicates invalid records to us
+    pub score: u32,
+    /// The number of invalid records the peer replicated to us
+    pub invalid_records: u64,
+    /// Until when replication from the peer is refused, if it is
+    pub refused_until: Option<Instant>,
+}
+
+impl Default for PeerScore {
+    fn default() -> Self {
+        Self {
+            score: MAX_PEER_SCORE,
+            invalid_records: 0,
+            refused_until: None,
+        }
+    }
+}
+
+impl PeerScore {
+    /// Give the peer a fresh start once its cooldown is over.
+    fn lift_expired_refusal(&mut self, now: Instant) {
+        if matches!(self.refused_until, Some(until) if until <= now) {
+            self.refused_until = None;
+            self.score = MAX_PEER_SCORE;
+        }
+    }
+}
+
+/// The scores of the peers which replicated invalid records to us.
+/// Scores are kept in memory only, peers we never got an invalid record from are not tracked.
+#[derive(Clone, Default)]
+pub(crate) struct PeerScores {
+    scores: Arc<Mutex<HashMap<PeerId, PeerScore>>>,
+}
+
+impl PeerScores {
+    /// Lower the score of a peer which replicated an invalid record to us.
+    /// Returns whether replication from the peer is refused from now on.
+    pub(crate) fn penalise(&self, peer: PeerId) -> bool {
+        self.penalise_at(peer, Instant::now())
+    }
+
+    /// Whether replication from the peer is currently refused.
+    pub(crate) fn is_refused(&self, peer: &PeerId) -> bool {
+        self.is_refused_at(peer, Instant::now())
+    }
+
+    /// The scores of all the peers which replicated invalid records to us.
+    pub(crate) fn scores(&self) -> Vec<(PeerId, PeerScore)> {
+        let now = Instant::now();
+        self.lock()
+            .iter_mut()
+            .map(|(peer, score)| {
+                score.lift_expired_refusal(now);
+                (*peer, *score)
+            })
+            .collect()
+    }
+
+    fn penalise_at(&self, peer: PeerId, now: Instant) -> bool {
+        let mut scores = self.lock();
+        let score = scores.entry(peer).or_default();
+        score.lift_expired_refusal(now);
+
+        score.invalid_records += 1;
+        score.score = score.score.saturating_sub(INVALID_RECORD_PENALTY);
+        if score.score < MIN_PEER_SCORE && score.refused_until.is_none() {
+            score.refused_until = Some(now + REPLICATION_COOLDOWN);
+        }
+        score.refused_until.is_some()
+    }
+
+    fn is_refused_at(&self, peer: &PeerId, now: Instant) -> bool {
+        self.lock()
+            .get_mut(peer)
+            .map(|score| {
+                score.lift_expired_refusal(now);
+                score.refused_until.is_some()
+            })
+            .unwrap_or(false)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PeerId, PeerScore>> {
+        // a panic while holding the lock can't leave the scores in an inconsistent state
+        self.scores
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replication_is_refused_once_the_score_is_too_low() {
+        let scores = PeerScores::default();
+        let bad_peer = PeerId::random();
+        let good_peer = PeerId::random();
+        let now = Instant::now();
+
+        assert!(!scores.penalise_at(bad_peer, now));
+        assert!(!scores.is_refused_at(&bad_peer, now));
+        assert!(scores.penalise_at(bad_peer, now));
+        assert!(scores.is_refused_at(&bad_peer, now));
+        assert!(!scores.is_refused_at(&good_peer, now));
+
+        let tracked = scores.scores();
+        assert_eq!(tracked.len(), 1);
+        let (peer, score) = tracked[0];
+        assert_eq!(peer, bad_peer);
+        assert_eq!(score.score, MAX_PEER_SCORE - 2 * INVALID_RECORD_PENALTY);
+        assert_eq!(score.invalid_records, 2);
+    }
+
+    #[test]
+    fn refusal_is_lifted_after_the_cooldown() {
+        let scores = PeerScores::default();
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            let _ = scores.penalise_at(peer, now);
+        }
+        // further invalid records don't extend the cooldown
+        assert!(scores.is_refused_at(&peer, now + REPLICATION_COOLDOWN - Duration::from_secs(1)));
+        assert!(!scores.is_refused_at(&peer, now + REPLICATION_COOLDOWN));
+
+        let (_, score) = scores.scores()[0];
+        assert_eq!(score.score, MAX_PEER_SCORE);
+        assert_eq!(score.invalid_records, 3);
+        assert_eq!(score.refused_until, None);
+    }
+}
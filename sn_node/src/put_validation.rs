@@ -17,7 +17,8 @@ use libp2p::kad::{Record, RecordKey};
 use serde::Serialize;
 use sn_networking::{get_singed_spends_from_record, Error as NetworkError, GetRecordError};
 use sn_protocol::{
-    messages::CmdOk,
+    error::Error as ProtocolError,
+    messages::{CmdOk, SignedRegisterClaim},
     storage::{
         try_deserialize_record, try_serialize_record, Chunk, RecordHeader, RecordKind, RecordType,
         SpendAddress,
@@ -26,11 +27,14 @@ use sn_protocol::{
 };
 use sn_registers::SignedRegister;
 use sn_transfers::{
-    calculate_royalties_fee, is_genesis_parent_tx, CashNote, CashNoteRedemption, LocalWallet,
-    NanoTokens, Payment, SignedSpend, Transfer, UniquePubkey, WalletError, GENESIS_CASHNOTE,
+    is_genesis_parent_tx, CashNote, CashNoteRedemption, DerivationIndex, LocalWallet, NanoTokens,
+    Payment, SignedSpend, StorageVoucher, Transfer, UniquePubkey, WalletError, GENESIS_CASHNOTE,
     NETWORK_ROYALTIES_PK,
 };
-use std::collections::{BTreeSet, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    time::SystemTime,
+};
 use xor_name::XorName;
 
 impl Node {
@@ -125,8 +129,18 @@ impl Node {
                 result
             }
             RecordKind::RegisterWithPayment => {
-                let (payment, register) =
-                    try_deserialize_record::<(Payment, SignedRegister)>(&record)?;
+                // Registers paid for by clients which don't claim them are still accepted.
+                let (payment, register, claim) =
+                    match try_deserialize_record::<(Payment, SignedRegister, SignedRegisterClaim)>(
+                        &record,
+                    ) {
+                        Ok((payment, register, claim)) => (payment, register, Some(claim)),
+                        Err(_) => {
+                            let (payment, register) =
+                                try_deserialize_record::<(Payment, SignedRegister)>(&record)?;
+                            (payment, register, None)
+                        }
+                    };
 
                 // check if the deserialized value's RegisterAddress matches the record's key
                 let net_addr = NetworkAddress::from_register_address(*register.address());
@@ -141,6 +155,14 @@ impl Node {
 
                 let already_exists = self.validate_key_and_existence(&net_addr, &key).await?;
 
+                // A competing creation is rejected while the Register is claimed by someone else,
+                // before its payment is accepted. The claim held is only released once the
+                // Register is validated and stored.
+                if !already_exists {
+                    self.register_claims
+                        .check_creation(*register.address(), claim.as_ref())?;
+                }
+
                 // The register may already exist during the replication.
                 // The payment shall get deposit to self even the register already presents.
                 // However, if the register already presents, the incoming one maybe for edit only.
@@ -157,7 +179,12 @@ impl Node {
                     }
                 }
 
-                self.validate_and_store_register(register, true).await
+                let address = *register.address();
+                let result = self.validate_and_store_register(register, true).await;
+                if result.is_ok() {
+                    self.register_claims.release(&address);
+                }
+                result
             }
         }
     }
@@ -556,9 +583,34 @@ impl Node {
         self.verify_quote_for_storecost(payment.quote, address)?;
         trace!("Payment quote valid for record {pretty_key}");
 
+        // the royalties are to be paid at our rate, which the client may not know of
+        if payment.royalty_rate != self.royalty_rate {
+            warn!(
+                "Payment for record {pretty_key} made at the royalty rate {:?}, ours is {:?}",
+                payment.royalty_rate, self.royalty_rate
+            );
+            return Err(ProtocolError::RoyaltyRateMismatch {
+                ours: self.royalty_rate,
+                theirs: payment.royalty_rate,
+            }
+            .into());
+        }
+
+        // a payment made with a voucher must be backed by it, on behalf of its funder
+        if let Some(voucher) = &payment.voucher {
+            Self::verify_voucher_payment(
+                voucher,
+                &payment.voucher_inputs,
+                address,
+                &cash_notes,
+                pretty_key.clone(),
+            )?;
+            trace!("Payment for record {pretty_key} made with a valid voucher");
+        }
+
         // Let's check payment is sufficient both for our store cost and for network royalties
         // Since the storage payment is made to a single node, we can calculate the royalties fee based on that single payment.
-        let expected_royalties_fee = calculate_royalties_fee(storecost);
+        let expected_royalties_fee = self.royalty_rate.fee_for(storecost);
         let expected_fee = storecost
             .checked_add(expected_royalties_fee)
             .ok_or(Error::NumericOverflow)?;
@@ -578,6 +630,44 @@ impl Node {
         Ok(())
     }
 
+    /// Check the voucher, signed by its funder, is still valid and pays for the address, and
+    /// that the cash notes paid to us were created by spends made with it: each spend has the
+    /// voucher as its reason, and spends either its reserve or a cash note derived from its key.
+    ///
+    /// How much of the voucher was used isn't checked, each payment being checked on its own.
+    fn verify_voucher_payment(
+        voucher: &StorageVoucher,
+        voucher_inputs: &BTreeMap<UniquePubkey, DerivationIndex>,
+        address: &NetworkAddress,
+        cash_notes: &[CashNote],
+        pretty_key: PrettyPrintRecordKey<'static>,
+    ) -> Result<()> {
+        let xorname = address.as_xorname().ok_or(Error::InvalidQuoteContent)?;
+        voucher.check_payment_for(&xorname, SystemTime::now())?;
+
+        let voucher_hash = voucher.hash();
+        for spend in cash_notes
+            .iter()
+            .flat_map(|cash_note| &cash_note.signed_spends)
+        {
+            if spend.reason() != voucher_hash {
+                warn!("Payment for record {pretty_key} was not made with its voucher {voucher_hash:?}");
+                return Err(Error::PaymentNotMadeWithVoucher(pretty_key));
+            }
+
+            let input = spend.unique_pubkey();
+            let derived_from_voucher_key = *input == voucher.reserve
+                || voucher_inputs
+                    .get(input)
+                    .is_some_and(|index| voucher.voucher_key.new_unique_pubkey(index) == *input);
+            if !derived_from_voucher_key {
+                warn!("Payment for record {pretty_key} spends {input:?}, which isn't from its voucher {voucher_hash:?}");
+                return Err(Error::PaymentNotMadeWithVoucher(pretty_key));
+            }
+        }
+        Ok(())
+    }
+
     async fn register_validation(
         &self,
         register: &SignedRegister,
@@ -9,7 +9,7 @@
 use crate::{node::Node, Error, Result};
 use sn_networking::Network;
 use sn_protocol::{error::Error as ProtocolError, NetworkAddress};
-use sn_transfers::{NanoTokens, PaymentQuote};
+use sn_transfers::{NanoTokens, PaymentQuote, RoyaltyRate};
 
 /// The time in seconds that a quote is valid for
 const QUOTE_EXPIRATION_SECS: u64 = 3600;
@@ -18,11 +18,12 @@ impl Node {
     pub(crate) fn create_quote_for_storecost(
         network: &Network,
         cost: NanoTokens,
+        royalty_rate: RoyaltyRate,
         address: &NetworkAddress,
     ) -> Result<PaymentQuote, ProtocolError> {
         let content = address.as_xorname().unwrap_or_default();
         let timestamp = std::time::SystemTime::now();
-        let bytes = PaymentQuote::bytes_for_signing(content, cost, timestamp);
+        let bytes = PaymentQuote::bytes_for_signing(content, cost, timestamp, royalty_rate);
 
         let Ok(signature) = network.sign(&bytes) else {
             return Err(ProtocolError::QuoteGenerationFailed);
@@ -33,6 +34,7 @@ impl Node {
             cost,
             timestamp,
             signature,
+            royalty_rate,
         };
 
         debug!("Created payment quote for {address:?}: {quote:?}");
@@ -62,7 +64,12 @@ impl Node {
         }
 
         // check sig
-        let bytes = PaymentQuote::bytes_for_signing(quote.content, quote.cost, quote.timestamp);
+        let bytes = PaymentQuote::bytes_for_signing(
+            quote.content,
+            quote.cost,
+            quote.timestamp,
+            quote.royalty_rate,
+        );
         let signature = quote.signature;
         if !self.network.verify(&bytes, &signature) {
             return Err(Error::InvalidQuoteSignature);
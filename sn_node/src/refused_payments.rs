@@ -0,0 +1,105 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use libp2p::kad::RecordKey;
+use sn_protocol::error::Error as ProtocolError;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// The most refusals a node keeps at once. Past it, the oldest ones are dropped.
+pub(crate) const MAX_REFUSED_PAYMENTS: usize = 10_000;
+
+/// How long a refusal is reported for, long enough for the putter to ask for the proof of the
+/// record it put.
+pub(crate) const REFUSAL_REPORTED_FOR: Duration = Duration::from_secs(300);
+
+/// The records recently refused for a payment the putter has to make again, e.g. at another
+/// royalty rate.
+///
+/// Puts can't carry errors back to the putter, which is told why its record was refused when it
+/// asks for the proof of the record. Refusals are kept in memory only.
+#[derive(Clone, Default)]
+pub(crate) struct RefusedPayments {
+    refusals: Arc<Mutex<HashMap<RecordKey, (ProtocolError, Instant)>>>,
+}
+
+impl RefusedPayments {
+    /// Keep the reason the payment for the record was refused.
+    pub(crate) fn refuse(&self, key: RecordKey, reason: ProtocolError) {
+        let mut refusals = self.lock();
+        refusals.retain(|_, (_, refused_at)| refused_at.elapsed() < REFUSAL_REPORTED_FOR);
+        if !refusals.contains_key(&key) && refusals.len() >= MAX_REFUSED_PAYMENTS {
+            let oldest = refusals
+                .iter()
+                .min_by_key(|(_, (_, refused_at))| *refused_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                let _ = refusals.remove(&oldest);
+            }
+        }
+        let _ = refusals.insert(key, (reason, Instant::now()));
+    }
+
+    /// The reason the payment for the record was refused, if it was recently.
+    pub(crate) fn refusal(&self, key: &RecordKey) -> Option<ProtocolError> {
+        self.lock()
+            .get(key)
+            .filter(|(_, refused_at)| refused_at.elapsed() < REFUSAL_REPORTED_FOR)
+            .map(|(reason, _)| reason.clone())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<RecordKey, (ProtocolError, Instant)>> {
+        // a panic while holding the lock can't leave the refusals in an inconsistent state
+        self.refusals
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sn_transfers::{RoyaltyRate, ROYALTY_RATE};
+
+    fn mismatch() -> ProtocolError {
+        ProtocolError::RoyaltyRateMismatch {
+            ours: ROYALTY_RATE,
+            theirs: RoyaltyRate {
+                version: ROYALTY_RATE.version + 1,
+                basis_points: 1000,
+            },
+        }
+    }
+
+    #[test]
+    fn refusals_are_reported_for_their_record_only() {
+        let refused_payments = RefusedPayments::default();
+        let key = RecordKey::new(&[1]);
+        refused_payments.refuse(key.clone(), mismatch());
+
+        assert_eq!(refused_payments.refusal(&key), Some(mismatch()));
+        assert_eq!(refused_payments.refusal(&RecordKey::new(&[2])), None);
+    }
+
+    #[test]
+    fn the_oldest_refusals_are_dropped_past_the_max() {
+        let refused_payments = RefusedPayments::default();
+        for index in 0..=MAX_REFUSED_PAYMENTS as u32 {
+            refused_payments.refuse(RecordKey::new(&index.to_be_bytes()), mismatch());
+        }
+
+        assert_eq!(refused_payments.lock().len(), MAX_REFUSED_PAYMENTS);
+        let last = MAX_REFUSED_PAYMENTS as u32;
+        assert!(refused_payments
+            .refusal(&RecordKey::new(&last.to_be_bytes()))
+            .is_some());
+    }
+}
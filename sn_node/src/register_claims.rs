@@ -0,0 +1,255 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use sn_protocol::{
+    error::{Error as ProtocolError, Result as ProtocolResult},
+    messages::{RegisterClaim, SignedRegisterClaim, REGISTER_CLAIM_GRACE_WINDOW},
+    storage::RegisterAddress,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// The most claims a node holds at once. Past it, new claims are rejected as `Busy` until some
+/// expire, as claims are requested by any client before paying for anything.
+pub(crate) const MAX_REGISTER_CLAIMS: usize = 10_000;
+
+/// The claims held on Registers still being uploaded by their creator.
+/// Claims are kept in memory only, and dropped once past their grace window.
+#[derive(Clone, Default)]
+pub(crate) struct RegisterClaims {
+    claims: Arc<Mutex<HashMap<RegisterAddress, RegisterClaim>>>,
+}
+
+impl RegisterClaims {
+    /// Hold the claim on the Register, unless it isn't signed by the owner of the Register, or a
+    /// competing one is still within its grace window. The claim is timed by our clock.
+    /// Returns whether our claim is held, along with the claim held after the attempt.
+    pub(crate) fn claim(
+        &self,
+        address: RegisterAddress,
+        signed_claim: &SignedRegisterClaim,
+    ) -> (ProtocolResult<()>, RegisterClaim) {
+        let claim = RegisterClaim::new(signed_claim.claim.claimant, signed_claim.claim.nonce);
+        if let Err(err) = signed_claim.verify(&address) {
+            warn!("Rejected claim {claim:?} on Register {address:?}: {err}");
+            return (Err(err), claim);
+        }
+
+        let mut claims = self.lock();
+        claims.retain(|_, held_claim| !held_claim.has_expired());
+
+        if !claims.contains_key(&address) && claims.len() >= MAX_REGISTER_CLAIMS {
+            let retry_after = claims
+                .values()
+                .map(RegisterClaim::remaining)
+                .min()
+                .unwrap_or(REGISTER_CLAIM_GRACE_WINDOW);
+            warn!("Holding too many claims to hold {claim:?} on Register {address:?}");
+            return (
+                Err(ProtocolError::Busy {
+                    retry_after_ms: retry_after.as_millis() as u64,
+                }),
+                claim,
+            );
+        }
+
+        let held_claim = claims.entry(address).or_insert(claim);
+        if held_claim.is_same_claimer(&claim) {
+            trace!("Holding claim {claim:?} on Register {address:?}");
+            *held_claim = claim;
+            (Ok(()), claim)
+        } else {
+            let held_claim = *held_claim;
+            debug!(
+                "Rejected claim {claim:?} on Register {address:?}, already held by {held_claim:?}"
+            );
+            (
+                Err(ProtocolError::RegisterAlreadyClaimed(held_claim.claimant)),
+                held_claim,
+            )
+        }
+    }
+
+    /// Check the creation of a Register made under the given claim, if any, doesn't compete
+    /// with the claim we hold. Creations are accepted when no claim is held.
+    ///
+    /// This holds no claim: it's only once the Register is validated and stored that
+    /// `release` drops the claim held.
+    pub(crate) fn check_creation(
+        &self,
+        address: RegisterAddress,
+        signed_claim: Option<&SignedRegisterClaim>,
+    ) -> ProtocolResult<()> {
+        if let Some(signed_claim) = signed_claim {
+            signed_claim.verify(&address)?;
+        }
+
+        let claims = self.lock();
+        match claims.get(&address) {
+            Some(held_claim) if !held_claim.has_expired() => match signed_claim {
+                Some(signed_claim) if held_claim.is_same_claimer(&signed_claim.claim) => Ok(()),
+                _ => {
+                    debug!("Rejected creation of Register {address:?}, claimed by {held_claim:?}");
+                    Err(ProtocolError::RegisterAlreadyClaimed(held_claim.claimant))
+                }
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Drop the claim on a Register once it has been created.
+    pub(crate) fn release(&self, address: &RegisterAddress) {
+        let _ = self.lock().remove(address);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<RegisterAddress, RegisterClaim>> {
+        // a panic while holding the lock can't leave the claims in an inconsistent state
+        self.claims
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xor_name::XorName;
+
+    fn register_owner() -> (bls::SecretKey, RegisterAddress) {
+        let owner = bls::SecretKey::random();
+        let address =
+            RegisterAddress::new(XorName::random(&mut rand::thread_rng()), owner.public_key());
+        (owner, address)
+    }
+
+    fn signed_claim(
+        owner: &bls::SecretKey,
+        address: &RegisterAddress,
+        nonce: u64,
+    ) -> SignedRegisterClaim {
+        SignedRegisterClaim::new(
+            address,
+            RegisterClaim::new(owner.public_key(), nonce),
+            owner,
+        )
+    }
+
+    #[test]
+    fn competing_claims_are_rejected_within_the_grace_window() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        let first = signed_claim(&owner, &address, 1);
+        let second = signed_claim(&owner, &address, 2);
+
+        assert!(claims.claim(address, &first).0.is_ok());
+        // the claimant renews its claim by claiming again under the same nonce
+        let (result, held_claim) = claims.claim(address, &first);
+        assert_eq!(result, Ok(()));
+        assert!(held_claim.is_same_claimer(&first.claim));
+
+        let (result, held_claim) = claims.claim(address, &second);
+        assert_eq!(
+            result,
+            Err(ProtocolError::RegisterAlreadyClaimed(address.owner()))
+        );
+        assert!(held_claim.is_same_claimer(&first.claim));
+        assert!(claims.check_creation(address, Some(&second)).is_err());
+        assert!(claims.check_creation(address, None).is_err());
+        assert!(claims.check_creation(address, Some(&first)).is_ok());
+    }
+
+    #[test]
+    fn claims_are_timed_by_the_node() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        // a claim dated in the future can't outlive the grace window
+        let mut claim = signed_claim(&owner, &address, 1);
+        claim.claim.claimed_at += 10 * REGISTER_CLAIM_GRACE_WINDOW.as_millis() as u64;
+
+        let (result, held_claim) = claims.claim(address, &claim);
+        assert_eq!(result, Ok(()));
+        assert!(held_claim.remaining() <= REGISTER_CLAIM_GRACE_WINDOW);
+    }
+
+    #[test]
+    fn unsigned_claims_are_neither_held_nor_checked() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        let forger = bls::SecretKey::random();
+        let forged =
+            SignedRegisterClaim::new(&address, RegisterClaim::new(owner.public_key(), 1), &forger);
+
+        let (result, _) = claims.claim(address, &forged);
+        assert_eq!(
+            result,
+            Err(ProtocolError::RegisterClaimInvalid(Box::new(address)))
+        );
+        assert!(claims.lock().is_empty());
+        assert!(claims.check_creation(address, Some(&forged)).is_err());
+    }
+
+    #[test]
+    fn checking_a_creation_holds_no_claim() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        let first = signed_claim(&owner, &address, 1);
+        let second = signed_claim(&owner, &address, 2);
+
+        assert!(claims.check_creation(address, Some(&first)).is_ok());
+        assert!(claims.lock().is_empty());
+        assert!(claims.claim(address, &second).0.is_ok());
+    }
+
+    #[test]
+    fn expired_or_released_claims_can_be_replaced() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        let first = signed_claim(&owner, &address, 1);
+        let second = signed_claim(&owner, &address, 2);
+        let third = signed_claim(&owner, &address, 3);
+
+        assert!(claims.claim(address, &first).0.is_ok());
+        if let Some(held_claim) = claims.lock().get_mut(&address) {
+            held_claim.claimed_at -= REGISTER_CLAIM_GRACE_WINDOW.as_millis() as u64;
+        }
+        assert!(claims.claim(address, &second).0.is_ok());
+
+        claims.release(&address);
+        assert!(claims.claim(address, &third).0.is_ok());
+    }
+
+    #[test]
+    fn claims_are_capped() {
+        let claims = RegisterClaims::default();
+        let (owner, address) = register_owner();
+        {
+            let mut held = claims.lock();
+            for _ in 0..MAX_REGISTER_CLAIMS {
+                let other_address = RegisterAddress::new(
+                    XorName::random(&mut rand::thread_rng()),
+                    owner.public_key(),
+                );
+                let _ = held.insert(other_address, RegisterClaim::new(owner.public_key(), 0));
+            }
+        }
+
+        let (result, _) = claims.claim(address, &signed_claim(&owner, &address, 0));
+        assert!(matches!(result, Err(ProtocolError::Busy { .. })));
+
+        // expired claims make room for new ones
+        for held_claim in claims.lock().values_mut().take(1) {
+            held_claim.claimed_at -= REGISTER_CLAIM_GRACE_WINDOW.as_millis() as u64;
+        }
+        assert!(claims
+            .claim(address, &signed_claim(&owner, &address, 0))
+            .0
+            .is_ok());
+    }
+}
@@ -6,12 +6,16 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{error::Result, node::Node};
+use crate::{
+    error::{Error, Result},
+    node::Node,
+    Marker,
+};
 use libp2p::{
     kad::{Quorum, Record, RecordKey},
     PeerId,
 };
-use sn_networking::{sort_peers_by_address, GetRecordCfg, Network, REPLICATE_RANGE};
+use sn_networking::{sort_peers_by_address, GetRecordCfg, Network, RetryPolicy, REPLICATE_RANGE};
 use sn_protocol::{
     messages::{Cmd, Query, QueryResponse, Request, Response},
     storage::RecordType,
@@ -31,6 +35,14 @@ impl Node {
         keys_to_fetch: Vec<(PeerId, RecordKey)>,
     ) -> Result<()> {
         for (holder, key) in keys_to_fetch {
+            if self.peer_scores.is_refused(&holder) {
+                trace!(
+                    "Not fetching {:?} from {holder:?}, refusing replication from it",
+                    PrettyPrintRecordKey::from(&key)
+                );
+                self.record_metrics(Marker::ReplicationRefusedFromPeer(holder));
+                continue;
+            }
             let node = self.clone();
             let requester = NetworkAddress::from_peer(self.network.peer_id);
             let _handle: JoinHandle<Result<()>> = spawn(async move {
@@ -59,6 +71,7 @@ impl Node {
                     None
                 };
 
+                let from_holder = record_opt.is_some();
                 let record = if let Some(record_content) = record_opt {
                     Record::new(key, record_content.to_vec())
                 } else {
@@ -67,7 +80,7 @@ impl Node {
                     );
                     let get_cfg = GetRecordCfg {
                         get_quorum: Quorum::One,
-                        re_attempt: false,
+                        retry_policy: RetryPolicy::none(),
                         target_record: None,
                         expected_holders: Default::default(),
                     };
@@ -77,7 +90,14 @@ impl Node {
                 trace!(
                     "Got Replication Record {pretty_key:?} from network, validating and storing it"
                 );
-                let result = node.store_prepaid_record(record).await?;
+                let result = node.store_prepaid_record(record).await;
+                // the holder is only to blame for what it served us itself
+                if let (true, Err(err)) = (from_holder, &result) {
+                    if err.is_invalid_record() {
+                        node.penalise_replication_sender(holder, &pretty_key, err);
+                    }
+                }
+                let result = result?;
                 trace!(
                     "Completed storing Replication Record {pretty_key:?} from network, result: {result:?}"
                 );
@@ -88,6 +108,20 @@ impl Node {
         Ok(())
     }
 
+    /// Lower the score of a peer which replicated an invalid record to us.
+    fn penalise_replication_sender(
+        &self,
+        holder: PeerId,
+        pretty_key: &PrettyPrintRecordKey,
+        err: &Error,
+    ) {
+        warn!("Invalid record {pretty_key:?} replicated by {holder:?}: {err}");
+        self.record_metrics(Marker::PeerPenalisedForInvalidRecord(holder));
+        if self.peer_scores.penalise(holder) {
+            warn!("Refusing replication from {holder:?} for a while, too many invalid records");
+        }
+    }
+
     /// Replicate a fresh record to its close group peers.
     /// This should not be triggered by a record we receive via replicaiton fetch
     pub(crate) fn replicate_valid_fresh_record(
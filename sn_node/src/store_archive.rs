@@ -0,0 +1,273 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Export of a node's record store to a `.tar.zst` archive, and its import on another machine.
+
+use crate::{Error, Result};
+use libp2p::{
+    identity::Keypair,
+    kad::{Record, RecordKey},
+};
+use sn_protocol::storage::validate_stored_record;
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+/// The dir holding the records, under the node's root dir.
+const RECORD_STORE_DIR: &str = "record_store";
+/// The file holding the node's secret key, under the node's root dir.
+const SECRET_KEY_FILENAME: &str = "secret-key";
+
+/// What was written to a record store archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreExportReport {
+    /// The number of records archived.
+    pub records: usize,
+    /// Whether the node's secret key was archived.
+    pub keypair_included: bool,
+}
+
+/// What was imported from a record store archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreImportReport {
+    /// The number of records validated and imported.
+    pub imported: usize,
+    /// The number of records not imported as the node already holds them.
+    pub already_present: usize,
+    /// The entries not imported, with why.
+    pub skipped: Vec<(String, String)>,
+    /// Whether the node's secret key was imported.
+    pub keypair_imported: bool,
+}
+
+/// Archive the record store of the node at `root_dir` to a zstd compressed tarball, along with
+/// the node's secret key if `include_keypair` is set.
+///
+/// The node should be stopped, so that its records don't change while they are archived.
+pub fn export_store(
+    root_dir: &Path,
+    archive_path: &Path,
+    include_keypair: bool,
+) -> Result<StoreExportReport> {
+    let mut report = StoreExportReport::default();
+    let encoder = zstd::Encoder::new(File::create(archive_path)?, 0)?;
+    let mut archive = tar::Builder::new(encoder);
+
+    for entry in fs::read_dir(root_dir.join(RECORD_STORE_DIR))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        archive.append_path_with_name(
+            entry.path(),
+            Path::new(RECORD_STORE_DIR).join(entry.file_name()),
+        )?;
+        report.records += 1;
+    }
+
+    let secret_key_path = root_dir.join(SECRET_KEY_FILENAME);
+    if include_keypair {
+        if !secret_key_path.exists() {
+            return Err(Error::SecretKeyNotFound(secret_key_path));
+        }
+        archive.append_path_with_name(&secret_key_path, SECRET_KEY_FILENAME)?;
+        report.keypair_included = true;
+    }
+
+    let _ = archive.into_inner()?.finish()?;
+    info!(
+        "Exported {} records of {root_dir:?} to {archive_path:?}",
+        report.records
+    );
+    Ok(report)
+}
+
+/// Import the records of an archive written by `export_store` into the record store of the node
+/// at `root_dir`, along with the archived secret key if `import_keypair` is set, for the node to
+/// keep its identity.
+///
+/// Every record is validated against its key before it is imported, the invalid ones are
+/// skipped and reported. Records the node already holds are left untouched.
+pub fn import_store(
+    archive_path: &Path,
+    root_dir: &Path,
+    import_keypair: bool,
+) -> Result<StoreImportReport> {
+    let store_dir = root_dir.join(RECORD_STORE_DIR);
+    fs::create_dir_all(&store_dir)?;
+
+    let mut report = StoreImportReport::default();
+    let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(archive_path)?)?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let mut value = vec![];
+        let _ = entry.read_to_end(&mut value)?;
+
+        if path == Path::new(SECRET_KEY_FILENAME) {
+            if import_keypair {
+                import_secret_key(root_dir, value)?;
+                report.keypair_imported = true;
+            }
+            continue;
+        }
+
+        let filename = match (
+            path.parent(),
+            path.file_name().and_then(|name| name.to_str()),
+        ) {
+            (Some(parent), Some(filename)) if parent == Path::new(RECORD_STORE_DIR) => filename,
+            _ => {
+                report
+                    .skipped
+                    .push((path.display().to_string(), "not a record".to_string()));
+                continue;
+            }
+        };
+        let record_path = store_dir.join(filename);
+        if record_path.exists() {
+            report.already_present += 1;
+            continue;
+        }
+
+        match validate_archived_record(filename, value) {
+            Ok(record) => {
+                fs::write(record_path, record.value)?;
+                report.imported += 1;
+            }
+            Err(reason) => {
+                warn!("Skipping the archived record {filename}: {reason}");
+                report.skipped.push((filename.to_string(), reason));
+            }
+        }
+    }
+
+    info!(
+        "Imported {} records from {archive_path:?} into {root_dir:?}, {} already present, {} skipped",
+        report.imported,
+        report.already_present,
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// Write the archived secret key as the node's, unless it already has a different one.
+fn import_secret_key(root_dir: &Path, secret_key: Vec<u8>) -> Result<()> {
+    let path = root_dir.join(SECRET_KEY_FILENAME);
+    if let Ok(existing) = fs::read(&path) {
+        if existing == secret_key {
+            return Ok(());
+        }
+        return Err(Error::SecretKeyAlreadyExists(path));
+    }
+
+    let peer_id = Keypair::ed25519_from_bytes(secret_key.clone())
+        .map_err(|_| Error::InvalidArchivedSecretKey)?
+        .public()
+        .to_peer_id();
+    fs::write(&path, secret_key)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    info!("Imported the secret key of {peer_id} to {path:?}");
+    Ok(())
+}
+
+/// Check the archived record under the given file name is a valid record stored under its key.
+fn validate_archived_record(filename: &str, value: Vec<u8>) -> std::result::Result<Record, String> {
+    let key = hex::decode(filename)
+        .map(|bytes| RecordKey::new(&bytes))
+        .map_err(|_| "the file name is not a record key".to_string())?;
+    let record = Record {
+        key,
+        value,
+        publisher: None,
+        expires: None,
+    };
+    let _ = validate_stored_record(&record).map_err(|err| err.to_string())?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use sn_protocol::storage::{try_serialize_record, Chunk, RecordKind};
+
+    fn chunk_record() -> Result<(String, Vec<u8>)> {
+        let chunk = Chunk::new(Bytes::from(rand::random::<[u8; 32]>().to_vec()));
+        let value = try_serialize_record(&chunk, RecordKind::Chunk)?;
+        let filename = hex::encode(chunk.network_address().to_record_key());
+        Ok((filename, value.to_vec()))
+    }
+
+    #[test]
+    fn valid_chunk_record_is_accepted() -> Result<()> {
+        let (filename, value) = chunk_record()?;
+        assert!(validate_archived_record(&filename, value).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn records_not_matching_their_key_are_rejected() -> Result<()> {
+        let (filename, _) = chunk_record()?;
+        let (_, other_value) = chunk_record()?;
+        assert!(validate_archived_record(&filename, other_value).is_err());
+        assert!(validate_archived_record("not-hex", vec![]).is_err());
+        assert!(validate_archived_record(&filename, b"junk".to_vec()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn store_is_exported_and_imported() -> eyre::Result<()> {
+        let source = tempfile::tempdir()?;
+        let destination = tempfile::tempdir()?;
+        let archive = source.path().join("store.tar.zst");
+
+        let source_store = source.path().join(RECORD_STORE_DIR);
+        fs::create_dir_all(&source_store)?;
+        let records = (0..5).map(|_| chunk_record()).collect::<Result<Vec<_>>>()?;
+        for (filename, value) in &records {
+            fs::write(source_store.join(filename), value)?;
+        }
+        let (corrupt_filename, _) = chunk_record()?;
+        fs::write(source_store.join(&corrupt_filename), b"corrupt")?;
+        let secret_key = libp2p::identity::ed25519::SecretKey::generate();
+        fs::write(source.path().join(SECRET_KEY_FILENAME), secret_key.as_ref())?;
+
+        let exported = export_store(source.path(), &archive, true)?;
+        assert_eq!(exported.records, 6);
+        assert!(exported.keypair_included);
+
+        let imported = import_store(&archive, destination.path(), true)?;
+        assert_eq!(imported.imported, 5);
+        assert_eq!(imported.skipped.len(), 1);
+        assert_eq!(imported.skipped[0].0, corrupt_filename);
+        assert!(imported.keypair_imported);
+        for (filename, value) in &records {
+            assert_eq!(
+                &fs::read(destination.path().join(RECORD_STORE_DIR).join(filename))?,
+                value
+            );
+        }
+        assert_eq!(
+            fs::read(destination.path().join(SECRET_KEY_FILENAME))?,
+            secret_key.as_ref()
+        );
+
+        // importing again leaves the records in place
+        let reimported = import_store(&archive, destination.path(), true)?;
+        assert_eq!(reimported.imported, 0);
+        assert_eq!(reimported.already_present, 5);
+        Ok(())
+    }
+}
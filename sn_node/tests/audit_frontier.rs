@@ -0,0 +1,263 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use assert_fs::TempDir;
+use common::client::{get_gossip_client_and_wallet, get_wallet};
+use eyre::Result;
+use sn_client::{send, AuditFrontier, AuditProgress, SpendDag, SPEND_DAG_FILE_NAME};
+use sn_logging::LogBuilder;
+use sn_transfers::{DerivationIndex, MainSecretKey, NanoTokens, SpendAddress};
+use std::collections::BTreeSet;
+
+#[tokio::test]
+async fn a_resumed_audit_only_follows_the_spends_made_since() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("audit_frontier");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        second_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(
+        second_wallet,
+        NanoTokens::from(400),
+        third_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note.clone()])?;
+    let third_note_addr = SpendAddress::from_unique_pubkey(&third_note.unique_pubkey());
+
+    // first session: audit from the second wallet's note, to the third one and the change
+    let auditor_dir = TempDir::new()?;
+    let frontier = AuditFrontier::new(BTreeSet::from([SpendAddress::from_unique_pubkey(
+        &second_note.unique_pubkey(),
+    )]));
+    let (frontier, audit) = client
+        .follow_spend_from(frontier, false, None, None, auditor_dir.path(), None)
+        .await?;
+    assert!(audit.double_spends.is_empty());
+    assert_eq!(audit.utxos, frontier.utxos);
+    // the 1_000 of the second note went to the third note and the change
+    audit.supply_check(NanoTokens::from(1_000))?;
+    assert_eq!(frontier.utxos.len(), 2);
+    assert!(frontier.utxos.contains(&third_note_addr));
+    assert_eq!(
+        frontier.verified_txs,
+        BTreeSet::from([third_note.src_tx.hash()])
+    );
+    frontier.save_to(auditor_dir.path())?;
+
+    // new spends in between
+    let someone = MainSecretKey::random().main_pubkey();
+    let someone_note = send(third_wallet, NanoTokens::from(100), someone, &client, true).await?;
+    let new_tx = someone_note.src_tx.hash();
+
+    // second session: carry on from the stored frontier
+    let stored = AuditFrontier::load_from(auditor_dir.path())?
+        .ok_or_else(|| eyre::eyre!("No frontier stored"))?;
+    assert_eq!(stored, frontier);
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    let (resumed, resumed_audit) = client
+        .follow_spend_from(
+            stored,
+            false,
+            None,
+            None,
+            auditor_dir.path(),
+            Some(&mut progress),
+        )
+        .await?;
+
+    let visited: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            AuditProgress::SpendsFetched { tx, .. } => Some(*tx),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(visited, vec![new_tx], "only the new Tx should be followed");
+    let generations = events
+        .iter()
+        .filter(|event| matches!(event, AuditProgress::GenerationStarted { .. }))
+        .count();
+    assert_eq!(generations, 1);
+
+    // the change of the second wallet is still unspent, the third note replaced by its outputs
+    assert_eq!(resumed.utxos.len(), 3);
+    assert!(!resumed.utxos.contains(&third_note_addr));
+    assert!(resumed.utxos.contains(&SpendAddress::from_unique_pubkey(
+        &someone_note.unique_pubkey()
+    )));
+    assert_eq!(
+        resumed.verified_txs,
+        BTreeSet::from([third_note.src_tx.hash(), new_tx])
+    );
+    assert!(resumed_audit.double_spends.is_empty());
+    assert_eq!(resumed_audit.utxos, resumed.utxos);
+    // the value of the UTXOs carried over from the first session is accounted for
+    resumed_audit.supply_check(NanoTokens::from(1_000))?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_stored_spend_dag_is_updated_with_the_spends_made_since() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("audit_frontier");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        second_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+    let root = SpendAddress::from_unique_pubkey(&second_note.unique_pubkey());
+
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(
+        second_wallet,
+        NanoTokens::from(400),
+        third_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note.clone()])?;
+
+    let dag_dir = TempDir::new()?;
+    let dag_path = dag_dir.path().join(SPEND_DAG_FILE_NAME);
+    let dag = client.build_spend_dag_from(root).await?;
+    assert_eq!(dag.spends().count(), 1);
+    dag.save(&dag_path)?;
+
+    // extend the economy: spend the third note, and its change in turn
+    let fourth_wallet_dir = TempDir::new()?;
+    let mut fourth_wallet = get_wallet(fourth_wallet_dir.path());
+    let fourth_note = send(
+        third_wallet,
+        NanoTokens::from(100),
+        fourth_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    fourth_wallet.deposit_and_store_to_disk(&vec![fourth_note.clone()])?;
+    let someone = MainSecretKey::random().main_pubkey();
+    let _ = send(fourth_wallet, NanoTokens::from(10), someone, &client, true).await?;
+
+    let mut updated = SpendDag::load(&dag_path)?;
+    let added = updated.update_from_network(&client).await?;
+    assert_eq!(added, 2);
+
+    let rebuilt = client.build_spend_dag_from(root).await?;
+    assert_eq!(
+        updated.spends().collect::<BTreeSet<_>>(),
+        rebuilt.spends().collect::<BTreeSet<_>>()
+    );
+    assert_eq!(
+        updated.get_utxos().into_iter().collect::<BTreeSet<_>>(),
+        rebuilt.get_utxos().into_iter().collect::<BTreeSet<_>>()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_spend_dag_between_two_spends_stops_at_the_later_one() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("audit_frontier");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        second_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+    let root = SpendAddress::from_unique_pubkey(&second_note.unique_pubkey());
+
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(
+        second_wallet,
+        NanoTokens::from(400),
+        third_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note.clone()])?;
+    let third = SpendAddress::from_unique_pubkey(&third_note.unique_pubkey());
+
+    // the third note is spent in turn, and so is the note it pays
+    let fourth_wallet_dir = TempDir::new()?;
+    let mut fourth_wallet = get_wallet(fourth_wallet_dir.path());
+    let fourth_note = send(
+        third_wallet,
+        NanoTokens::from(100),
+        fourth_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    fourth_wallet.deposit_and_store_to_disk(&vec![fourth_note.clone()])?;
+    let someone = MainSecretKey::random().main_pubkey();
+    let _ = send(fourth_wallet, NanoTokens::from(10), someone, &client, true).await?;
+
+    // the spends of the second and third notes
+    let dag = client.build_spend_dag_between(root, third).await?;
+    assert_eq!(dag.find_paths(root, third), vec![vec![root, third]]);
+    assert!(dag.is_ancestor_of(root, third));
+    assert_eq!(dag.spends().count(), 2);
+
+    // without a path to the spend, all the descendants are followed, down to the fourth note
+    let unrelated = SpendAddress::from_unique_pubkey(
+        &MainSecretKey::random()
+            .main_pubkey()
+            .new_unique_pubkey(&DerivationIndex([0; 32])),
+    );
+    let dag = client.build_spend_dag_between(root, unrelated).await?;
+    assert!(dag.find_paths(root, unrelated).is_empty());
+    assert_eq!(dag.spends().count(), 3);
+
+    Ok(())
+}
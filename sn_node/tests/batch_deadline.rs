@@ -0,0 +1,79 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use eyre::Result;
+use sn_client::{Deadline, FilesUpload};
+use sn_logging::LogBuilder;
+use sn_networking::RetryPolicy;
+use sn_protocol::storage::ChunkAddress;
+use std::time::Duration;
+use xor_name::XorName;
+
+// Far longer than the deadline of the batch the absent chunk is fetched in.
+const ABSENT_CHUNK_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batch_past_its_deadline_returns_what_completed() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("batch_deadline");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _head_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let uploaded: Vec<_> = chunks
+        .iter()
+        .map(|(name, _)| ChunkAddress::new(*name))
+        .collect();
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    // with time enough, the whole batch completes
+    let outcome = client
+        .get_chunks_with_deadline(&uploaded, 4, Deadline::after(Duration::from_secs(60)))
+        .await;
+    assert!(outcome.is_complete(), "{outcome:?}");
+    assert_eq!(outcome.completed.len(), uploaded.len());
+
+    // the chunk never uploaded is only given up on after a retry it waits far longer than the
+    // deadline for, so fetching it first keeps the rest of the batch from being attempted
+    let mut slow_client = client.clone();
+    slow_client.set_retry_policy(RetryPolicy {
+        max_attempts: 2,
+        initial_backoff: ABSENT_CHUNK_RETRY_DELAY,
+        max_backoff: ABSENT_CHUNK_RETRY_DELAY,
+        jitter: false,
+    });
+    let absent = ChunkAddress::new(XorName::random(&mut rand::thread_rng()));
+    let batch: Vec<_> = std::iter::once(absent)
+        .chain(uploaded.iter().copied())
+        .collect();
+    let outcome = slow_client
+        .get_chunks_with_deadline(&batch, 1, Deadline::after(Duration::from_secs(5)))
+        .await;
+    println!(
+        "{} completed, {} failed, {} cancelled, {} not attempted",
+        outcome.completed.len(),
+        outcome.failed.len(),
+        outcome.cancelled.len(),
+        outcome.not_attempted.len()
+    );
+
+    assert!(outcome.deadline_passed());
+    assert!(outcome.completed.is_empty(), "{outcome:?}");
+    assert!(outcome.failed.is_empty(), "{outcome:?}");
+    assert_eq!(outcome.cancelled, vec![absent]);
+    assert_eq!(outcome.not_attempted, uploaded);
+
+    client.shutdown().await?;
+    Ok(())
+}
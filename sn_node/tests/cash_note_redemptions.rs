@@ -0,0 +1,76 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use assert_fs::TempDir;
+use common::client::{get_gossip_client_and_wallet, get_wallet};
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use sn_client::{send, split_wallet_balance, Error as ClientError};
+use sn_logging::LogBuilder;
+use sn_networking::Error as NetworkError;
+use sn_transfers::{CashNoteRedemption, MainSecretKey, NanoTokens, SpendAddress};
+use xor_name::XorName;
+
+const REDEMPTIONS: usize = 50;
+
+#[tokio::test]
+async fn many_redemptions_are_verified_concurrently() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("cash_note_redemptions");
+
+    let wallet_balance = 1_000_000_000;
+    let wallet_dir = TempDir::new()?;
+    let (client, wallet) = get_gossip_client_and_wallet(wallet_dir.path(), wallet_balance).await?;
+    let _wallet = split_wallet_balance(&client, wallet, REDEMPTIONS, REDEMPTIONS / 5).await?;
+
+    // payouts spending distinct cash notes, so that each redemption has a parent spend of its own
+    let recipient = MainSecretKey::random();
+    let payout = NanoTokens::from(100);
+    let payouts = (0..REDEMPTIONS).map(|_| {
+        send(
+            get_wallet(wallet_dir.path()),
+            payout,
+            recipient.main_pubkey(),
+            &client,
+            true,
+        )
+    });
+    let mut redemptions = vec![];
+    for result in join_all(payouts).await {
+        redemptions.push(CashNoteRedemption::from_cash_note(&result?)?);
+    }
+
+    let cash_notes = client
+        .verify_cash_notes_redemptions(recipient.main_pubkey(), &redemptions)
+        .await?;
+    assert_eq!(cash_notes.len(), REDEMPTIONS);
+
+    // invalid redemptions fail them all, telling which is the first of them
+    let invalid_index = REDEMPTIONS / 2;
+    for index in [REDEMPTIONS - 1, invalid_index] {
+        redemptions[index].parent_spend =
+            SpendAddress::new(XorName::random(&mut rand::thread_rng()));
+    }
+    match client
+        .verify_cash_notes_redemptions(recipient.main_pubkey(), &redemptions)
+        .await
+    {
+        Err(ClientError::Network(NetworkError::InvalidCashNoteRedemption {
+            index,
+            parent_spend,
+            ..
+        })) => {
+            assert_eq!(index, invalid_index);
+            assert_eq!(parent_spend, redemptions[invalid_index].parent_spend);
+        }
+        other => return Err(eyre!("Expected the invalid redemption, got {other:?}")),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::get_gossip_client;
+use eyre::Result;
+use sn_logging::LogBuilder;
+use sn_networking::CLOSE_GROUP_SIZE;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use xor_name::XorName;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_gets_the_close_group_of_an_address() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_closest_peers");
+
+    let client = get_gossip_client().await;
+    let mut rng = rand::thread_rng();
+    let addr = NetworkAddress::from_chunk_address(ChunkAddress::new(XorName::random(&mut rng)));
+
+    let peers = client.get_closest_peers(&addr).await?;
+    assert_eq!(peers.len(), CLOSE_GROUP_SIZE);
+
+    let with_distance = client.get_closest_peers_with_distance(&addr).await?;
+    assert_eq!(with_distance.len(), CLOSE_GROUP_SIZE);
+    assert!(with_distance.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    for (peer, distance) in &with_distance {
+        assert_eq!(*distance, addr.distance(&NetworkAddress::from_peer(*peer)));
+    }
+
+    client.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::{get_all_rpc_addresses, get_gossip_client};
+use eyre::{eyre, Result};
+use sn_client::{ClientEvent, ClientEventsReceiver, ConnectionStatus};
+use sn_logging::LogBuilder;
+use sn_protocol::safenode_proto::{safe_node_client::SafeNodeClient, StopRequest};
+use tokio::time::{timeout, Duration};
+use tonic::Request;
+
+// Longer than the client's inactivity timeout.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(90);
+
+// NB: this test stops all the nodes of the network, so has to run on its own, last.
+#[tokio::test(flavor = "multi_thread")]
+async fn client_tells_idle_from_disconnected() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_connectivity");
+
+    let client = get_gossip_client().await;
+    let mut events = client.events_channel();
+
+    // a quiet but healthy network leaves the client idle
+    let event = next_connectivity_event(&mut events).await?;
+    assert!(matches!(event, ClientEvent::Idle(_)), "Got {event:?}");
+    assert!(matches!(
+        client.connection_status(),
+        ConnectionStatus::Connected { live_peers } if live_peers > 0
+    ));
+
+    for addr in get_all_rpc_addresses()? {
+        let mut rpc_client = SafeNodeClient::connect(format!("https://{addr}")).await?;
+        let _response = rpc_client
+            .stop(Request::new(StopRequest { delay_millis: 0 }))
+            .await?;
+        println!("Node stop requested to RPC service at {addr}");
+    }
+
+    // with all the nodes gone, the client is disconnected rather than idle
+    let event = loop {
+        match next_connectivity_event(&mut events).await? {
+            ClientEvent::Idle(_) => continue,
+            event => break event,
+        }
+    };
+    assert!(
+        matches!(event, ClientEvent::Disconnected { .. }),
+        "Got {event:?}"
+    );
+    assert!(matches!(
+        client.connection_status(),
+        ConnectionStatus::Disconnected { .. }
+    ));
+
+    Ok(())
+}
+
+async fn next_connectivity_event(events: &mut ClientEventsReceiver) -> Result<ClientEvent> {
+    timeout(EVENT_TIMEOUT, async {
+        loop {
+            match events.recv().await? {
+                event @ (ClientEvent::Idle(_) | ClientEvent::Disconnected { .. }) => {
+                    return Ok(event)
+                }
+                _other => continue,
+            }
+        }
+    })
+    .await
+    .map_err(|_| eyre!("No connectivity event within {EVENT_TIMEOUT:?}"))?
+}
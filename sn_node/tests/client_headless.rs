@@ -0,0 +1,29 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::get_headless_client;
+use eyre::Result;
+use sn_client::ConnectionStatus;
+use sn_logging::LogBuilder;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn headless_client_connects() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_headless");
+
+    // building the client waits for it to broadcast `ConnectedToNetwork`
+    let client = get_headless_client().await;
+    assert!(matches!(
+        client.connection_status(),
+        ConnectionStatus::Connected { live_peers } if live_peers > 0
+    ));
+
+    client.shutdown().await?;
+    Ok(())
+}
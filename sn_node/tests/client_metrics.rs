@@ -0,0 +1,63 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#![cfg(feature = "open-metrics")]
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use eyre::Result;
+use sn_client::{FilesUpload, WalletClient};
+use sn_logging::LogBuilder;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use sn_transfers::{SpendAddress, GENESIS_CASHNOTE};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_operations_are_recorded_in_its_metrics() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_metrics");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    wallet_client.store_local_wallet()?;
+    let address = ChunkAddress::new(chunks[0].0);
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    let _chunk = client.get_chunk(address, false).await?;
+    let genesis_addr = SpendAddress::from_unique_pubkey(&GENESIS_CASHNOTE.unique_pubkey());
+    let _spend = client.get_spend_from_network(genesis_addr).await?;
+
+    let text = client.metrics_text()?;
+    println!("{text}");
+    for operation in ["ChunkPut", "ChunkGet", "SpendPut", "SpendGet"] {
+        let counter =
+            format!(r#"sn_client_operations_total{{operation="{operation}",outcome="Success"}} "#);
+        let count: u64 = text
+            .lines()
+            .find_map(|line| line.strip_prefix(&counter))
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or_default();
+        assert!(count > 0, "No successful {operation} recorded");
+    }
+    assert!(!text.contains("sn_client_payment_amount_nanos_count 0"));
+
+    client.shutdown().await?;
+    Ok(())
+}
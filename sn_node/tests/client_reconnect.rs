@@ -0,0 +1,111 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_all_rpc_addresses, get_client_from_builder, get_gossip_client_and_wallet},
+    random_content,
+};
+use assert_fs::TempDir;
+use eyre::{eyre, Result};
+use sn_client::{
+    ClientBuilder, ClientEvent, ClientEventsReceiver, ConnectionStatus, FilesUpload,
+    ReconnectPolicy, WalletClient,
+};
+use sn_logging::LogBuilder;
+use sn_protocol::{
+    safenode_proto::{safe_node_client::SafeNodeClient, RestartRequest},
+    storage::ChunkAddress,
+    NetworkAddress,
+};
+use tokio::time::{timeout, Duration};
+use tonic::Request;
+
+// Long enough for the client to notice it's disconnected, and for the nodes to come back.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(300);
+
+// NB: this test restarts all the nodes of the network, so has to run on its own.
+#[tokio::test(flavor = "multi_thread")]
+async fn client_reconnects_once_the_network_is_back() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_reconnect");
+
+    // some data to read back once reconnected
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (uploader, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) = random_content(
+        &uploader,
+        paying_wallet_dir.to_path_buf(),
+        chunks_dir.path(),
+    )?;
+    let _cost = WalletClient::new(uploader.clone(), paying_wallet)
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    let address = ChunkAddress::new(chunks[0].0);
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    let client =
+        get_client_from_builder(ClientBuilder::default().reconnect_policy(ReconnectPolicy {
+            after_inactive_periods: 1,
+            attempts: 30,
+            interval: Duration::from_secs(5),
+        }))
+        .await;
+    let mut events = client.events_channel();
+
+    // the nodes keep their records across the restart
+    for addr in get_all_rpc_addresses()? {
+        let mut rpc_client = SafeNodeClient::connect(format!("https://{addr}")).await?;
+        let _response = rpc_client
+            .restart(Request::new(RestartRequest { delay_millis: 0 }))
+            .await?;
+        println!("Node restart requested to RPC service at {addr}");
+    }
+
+    let event = next_reconnection_event(&mut events).await?;
+    assert!(
+        matches!(event, ClientEvent::Reconnecting { .. }),
+        "Got {event:?}"
+    );
+    let event = next_reconnection_event(&mut events).await?;
+    assert!(
+        matches!(event, ClientEvent::Reconnected { attempts } if (1..=30).contains(&attempts)),
+        "Got {event:?}"
+    );
+    assert!(matches!(
+        client.connection_status(),
+        ConnectionStatus::Connected { live_peers } if live_peers > 0
+    ));
+
+    // the same client instance reaches the network again
+    let _chunk = client.get_chunk(address, false).await?;
+
+    client.shutdown().await?;
+    Ok(())
+}
+
+async fn next_reconnection_event(events: &mut ClientEventsReceiver) -> Result<ClientEvent> {
+    timeout(RECONNECT_TIMEOUT, async {
+        loop {
+            match events.recv().await? {
+                event @ (ClientEvent::Reconnecting { .. } | ClientEvent::Reconnected { .. }) => {
+                    return Ok(event)
+                }
+                _other => continue,
+            }
+        }
+    })
+    .await
+    .map_err(|_| eyre!("No reconnection event within {RECONNECT_TIMEOUT:?}"))?
+}
@@ -0,0 +1,147 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::get_gossip_client;
+use eyre::Result;
+use sn_client::Error as ClientError;
+use sn_logging::LogBuilder;
+use sn_networking::RetryPolicy;
+use sn_protocol::storage::ChunkAddress;
+use tokio::time::{sleep, timeout, Duration};
+use xor_name::XorName;
+
+const CLIENTS_COUNT: usize = 20;
+
+// Some leeway for the short lived tasks and sockets not owned by the clients.
+const ALLOWED_TASKS_GROWTH: usize = 5;
+const ALLOWED_SOCKETS_GROWTH: usize = 5;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_shutdown_releases_tasks_and_sockets() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_shutdown");
+
+    // the first client initialises any process wide state, take the baseline after it
+    get_gossip_client().await.shutdown().await?;
+    sleep(Duration::from_secs(1)).await;
+    let tasks_baseline = alive_tasks();
+    let sockets_baseline = open_sockets()?;
+    println!("Baseline of {tasks_baseline} tasks and {sockets_baseline} sockets");
+
+    for i in 0..CLIENTS_COUNT {
+        let client = get_gossip_client().await;
+        client.shutdown().await?;
+        println!("Client {i} shut down");
+    }
+    sleep(Duration::from_secs(1)).await;
+
+    let tasks = alive_tasks();
+    let sockets = open_sockets()?;
+    println!("After {CLIENTS_COUNT} clients: {tasks} tasks and {sockets} sockets");
+
+    assert!(
+        tasks <= tasks_baseline + ALLOWED_TASKS_GROWTH,
+        "Tasks grew from {tasks_baseline} to {tasks}"
+    );
+    assert!(
+        sockets <= sockets_baseline + ALLOWED_SOCKETS_GROWTH,
+        "Sockets grew from {sockets_baseline} to {sockets}"
+    );
+
+    Ok(())
+}
+
+// Clients dropped without being shut down get their background tasks aborted.
+#[tokio::test(flavor = "multi_thread")]
+async fn dropped_clients_release_tasks_and_sockets() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_shutdown");
+
+    drop(get_gossip_client().await);
+    sleep(Duration::from_secs(1)).await;
+    let tasks_baseline = alive_tasks();
+    let sockets_baseline = open_sockets()?;
+    println!("Baseline of {tasks_baseline} tasks and {sockets_baseline} sockets");
+
+    for i in 0..CLIENTS_COUNT {
+        let client = get_gossip_client().await;
+        // the last of the clones aborts the tasks
+        let clone = client.clone();
+        drop(client);
+        drop(clone);
+        println!("Client {i} dropped");
+    }
+    sleep(Duration::from_secs(1)).await;
+
+    let tasks = alive_tasks();
+    let sockets = open_sockets()?;
+    println!("After {CLIENTS_COUNT} clients: {tasks} tasks and {sockets} sockets");
+
+    assert!(
+        tasks <= tasks_baseline + ALLOWED_TASKS_GROWTH,
+        "Tasks grew from {tasks_baseline} to {tasks}"
+    );
+    assert!(
+        sockets <= sockets_baseline + ALLOWED_SOCKETS_GROWTH,
+        "Sockets grew from {sockets_baseline} to {sockets}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn operations_in_flight_are_cancelled_on_shutdown() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_shutdown");
+
+    let mut client = get_gossip_client().await;
+    // enough attempts for the fetch of a chunk that doesn't exist to be in flight for minutes
+    client.set_retry_policy(RetryPolicy {
+        max_attempts: 100,
+        ..RetryPolicy::default()
+    });
+    let fetching_client = client.clone();
+    let address = ChunkAddress::new(XorName::random(&mut rand::thread_rng()));
+    let fetch = tokio::spawn(async move { fetching_client.get_chunk(address, false).await });
+
+    sleep(Duration::from_secs(2)).await;
+    assert!(!fetch.is_finished(), "The fetch should still be in flight");
+
+    client.shutdown().await?;
+    let result = timeout(Duration::from_secs(1), fetch).await??;
+    assert!(
+        matches!(result, Err(ClientError::ClientShutDown)),
+        "The fetch should have been cancelled, got {result:?}"
+    );
+
+    Ok(())
+}
+
+fn alive_tasks() -> usize {
+    tokio::runtime::Handle::current()
+        .metrics()
+        .num_alive_tasks()
+}
+
+// Counts the file descriptors of the process that are sockets.
+#[cfg(target_os = "linux")]
+fn open_sockets() -> Result<usize> {
+    let mut count = 0;
+    for entry in std::fs::read_dir("/proc/self/fd")? {
+        if let Ok(target) = std::fs::read_link(entry?.path()) {
+            if target.to_string_lossy().starts_with("socket:") {
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_sockets() -> Result<usize> {
+    Ok(0)
+}
@@ -0,0 +1,81 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use eyre::Result;
+use sn_client::{FilesUpload, WalletClient};
+use sn_logging::LogBuilder;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use std::time::{Duration, Instant};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn standby_client_skips_lookups_for_its_working_set() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("client_standby");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    let addresses: Vec<_> = chunks
+        .iter()
+        .map(|(name, _)| ChunkAddress::new(*name))
+        .collect();
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    let (warm, cold) = (addresses[0], addresses[1]);
+    client
+        .standby(
+            vec![NetworkAddress::from_chunk_address(warm)],
+            Duration::from_secs(60),
+        )
+        .await?;
+    let stats = client.standby_stats();
+    assert_eq!(stats.working_set, 1);
+    assert_eq!(stats.fresh, 1);
+    assert!(stats.oldest_refresh.is_some());
+
+    let before = client.standby_stats();
+    let start = Instant::now();
+    let _ = client.get_chunk(warm, false).await?;
+    let warm_latency = start.elapsed();
+    let after = client.standby_stats();
+    assert_eq!(after.warm_hits, before.warm_hits + 1);
+    assert_eq!(
+        after.lookups, before.lookups,
+        "the warm get looked up peers"
+    );
+
+    let start = Instant::now();
+    let _ = client.get_chunk(cold, false).await?;
+    let cold_latency = start.elapsed();
+    let cold_after = client.standby_stats();
+    assert_eq!(cold_after.warm_hits, after.warm_hits);
+    assert!(
+        cold_after.lookups > after.lookups,
+        "the cold get skipped the lookup"
+    );
+
+    println!("Got the warm chunk in {warm_latency:?}, the cold one in {cold_latency:?}");
+
+    client.shutdown().await?;
+    Ok(())
+}
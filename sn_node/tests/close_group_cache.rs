@@ -0,0 +1,103 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_all_rpc_addresses, get_gossip_client_and_wallet},
+    get_all_peer_ids, random_content,
+};
+use assert_fs::TempDir;
+use eyre::{eyre, Result};
+use sn_client::{FilesUpload, WalletClient};
+use sn_logging::LogBuilder;
+use sn_networking::sort_peers_by_key;
+use sn_protocol::{
+    safenode_proto::{safe_node_client::SafeNodeClient, StopRequest},
+    storage::ChunkAddress,
+    NetworkAddress,
+};
+use std::time::Duration;
+use tonic::Request;
+
+// Long enough for the client to notice the stopped node is gone.
+const INVALIDATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+// NB: this test stops a node of the network, so has to run on its own, after the tests
+// needing all the nodes.
+#[tokio::test(flavor = "multi_thread")]
+async fn churn_invalidates_the_cached_close_group() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("close_group_cache");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let _cost = WalletClient::new(client.clone(), paying_wallet)
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    let address = ChunkAddress::new(chunks[0].0);
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    // warm the cache
+    let net_addr = NetworkAddress::from_chunk_address(address);
+    client
+        .standby(vec![net_addr.clone()], Duration::from_secs(600))
+        .await?;
+    let before = client.standby_stats();
+    let _chunk = client.get_chunk(address, false).await?;
+    let warm = client.standby_stats();
+    assert_eq!(warm.warm_hits, before.warm_hits + 1);
+    assert_eq!(warm.lookups, before.lookups);
+
+    // stop the node closest to the chunk, without waiting for the next refresh. A restarted
+    // node would keep its PeerId, so could be back in the close group before it's noticed gone.
+    let rpc_addresses = get_all_rpc_addresses()?;
+    let peer_ids = get_all_peer_ids(&rpc_addresses).await?;
+    let closest = *sort_peers_by_key(&peer_ids, &net_addr.as_kbucket_key(), 1)?
+        .first()
+        .ok_or_else(|| eyre!("No peer close to {address:?}"))?;
+    let rpc_addr = peer_ids
+        .iter()
+        .position(|peer| peer == closest)
+        .map(|index| rpc_addresses[index])
+        .ok_or_else(|| eyre!("No RPC address for {closest:?}"))?;
+    let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_addr}")).await?;
+    let _response = rpc_client
+        .stop(Request::new(StopRequest { delay_millis: 0 }))
+        .await?;
+    println!("Stopped {closest:?}, the node closest to {address:?}");
+
+    tokio::time::timeout(INVALIDATION_TIMEOUT, async {
+        while client.standby_stats().invalidations == warm.invalidations {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .map_err(|_| eyre!("The cached close group wasn't invalidated"))?;
+
+    // the next get looks the close group up again, and still succeeds
+    let invalidated = client.standby_stats();
+    assert_eq!(invalidated.fresh, 0);
+    let _chunk = client.get_chunk(address, false).await?;
+    let after = client.standby_stats();
+    assert!(
+        after.lookups > invalidated.lookups,
+        "the get used the invalidated close group"
+    );
+    assert_eq!(after.warm_hits, invalidated.warm_hits);
+
+    client.shutdown().await?;
+    Ok(())
+}
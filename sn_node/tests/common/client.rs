@@ -8,7 +8,7 @@
 
 use eyre::{bail, Result};
 use lazy_static::lazy_static;
-use sn_client::{send, Client};
+use sn_client::{send, Client, ClientBuilder};
 use sn_peers_acquisition::parse_peer_addr;
 use sn_protocol::test_utils::DeploymentInventory;
 use sn_transfers::{create_faucet_wallet, LocalWallet, NanoTokens, Transfer};
@@ -80,6 +80,33 @@ pub async fn get_gossip_client() -> Client {
     }
 }
 
+/// Get a new Client showing no connection spinner, as when embedded in a TUI or a service.
+/// If SN_INVENTORY flag is passed, the client is bootstrapped to the droplet network
+/// Else to the local network.
+pub async fn get_headless_client() -> Client {
+    get_client_from_builder(ClientBuilder::default().enable_gossip(true).headless(true)).await
+}
+
+/// Get a new Client as set up by the given builder, its peers aside.
+/// If SN_INVENTORY flag is passed, the client is bootstrapped to the droplet network
+/// Else to the local network.
+pub async fn get_client_from_builder(builder: ClientBuilder) -> Client {
+    match DeploymentInventory::load() {
+        Ok(inventory) => Droplet::build_client(inventory.peers, builder).await,
+        Err(_) => NonDroplet::build_client(builder).await,
+    }
+}
+
+/// Get a new Client sharing the key of the given one, as if the same user ran both.
+/// If SN_INVENTORY flag is passed, the client is bootstrapped to the droplet network
+/// Else to the local network.
+pub async fn get_gossip_client_with_key(secret_key: bls::SecretKey) -> Client {
+    match DeploymentInventory::load() {
+        Ok(inventory) => Droplet::get_gossip_client_with_key(inventory.peers, secret_key).await,
+        Err(_) => NonDroplet::get_gossip_client_with_key(secret_key).await,
+    }
+}
+
 /// Get a funded wallet.
 /// If SN_INVENTORY flag is passed, the amount is retrieved from the faucet url
 /// else obtain it from the provided `from` LocalWallet
@@ -130,22 +157,37 @@ pub struct NonDroplet;
 impl NonDroplet {
     ///  Get a new Client for testing
     pub async fn get_gossip_client() -> Client {
-        let secret_key = bls::SecretKey::random();
+        Self::get_gossip_client_with_key(bls::SecretKey::random()).await
+    }
 
+    ///  Get a new Client for testing, using the given key
+    pub async fn get_gossip_client_with_key(secret_key: bls::SecretKey) -> Client {
+        Self::build_client(
+            ClientBuilder::default()
+                .signer(secret_key)
+                .enable_gossip(true),
+        )
+        .await
+    }
+
+    /// Build the Client, bootstrapped to the local network
+    async fn build_client(builder: ClientBuilder) -> Client {
         let bootstrap_peers = if !cfg!(feature = "local-discovery") {
             match std::env::var("SAFE_PEERS") {
                 Ok(str) => match parse_peer_addr(&str) {
-                    Ok(peer) => Some(vec![peer]),
+                    Ok(peer) => vec![peer],
                     Err(err) => panic!("Can't parse SAFE_PEERS {str:?} with error {err:?}"),
                 },
                 Err(err) => panic!("Can't get env var SAFE_PEERS with error {err:?}"),
             }
         } else {
-            None
+            vec![]
         };
 
         println!("Client bootstrap with peer {bootstrap_peers:?}");
-        Client::new(secret_key, bootstrap_peers, true, None)
+        builder
+            .peers(bootstrap_peers)
+            .build()
             .await
             .expect("Client shall be successfully created.")
     }
@@ -193,8 +235,25 @@ struct Droplet;
 impl Droplet {
     /// Create a new client and bootstrap from the provided safe_peers
     pub async fn get_gossip_client(safe_peers: Vec<String>) -> Client {
-        let secret_key = bls::SecretKey::random();
+        Self::get_gossip_client_with_key(safe_peers, bls::SecretKey::random()).await
+    }
+
+    /// Create a new client using the given key and bootstrap from the provided safe_peers
+    pub async fn get_gossip_client_with_key(
+        safe_peers: Vec<String>,
+        secret_key: bls::SecretKey,
+    ) -> Client {
+        Self::build_client(
+            safe_peers,
+            ClientBuilder::default()
+                .signer(secret_key)
+                .enable_gossip(true),
+        )
+        .await
+    }
 
+    /// Build the Client, bootstrapped from the provided safe_peers
+    async fn build_client(safe_peers: Vec<String>, builder: ClientBuilder) -> Client {
         let mut bootstrap_peers = Vec::new();
         for peer in safe_peers {
             match parse_peer_addr(&peer) {
@@ -207,7 +266,9 @@ impl Droplet {
         }
 
         println!("Client bootstrap with peer {bootstrap_peers:?}");
-        Client::new(secret_key, Some(bootstrap_peers), true, None)
+        builder
+            .peers(bootstrap_peers)
+            .build()
             .await
             .expect("Client shall be successfully created.")
     }
@@ -18,7 +18,9 @@ use common::{
 };
 use eyre::{bail, eyre, Result};
 use rand::{rngs::OsRng, Rng};
-use sn_client::{Client, Error, FilesApi, FilesDownload, FilesUpload, WalletClient};
+use sn_client::{
+    Client, Error, FilesApi, FilesDownload, FilesUpload, RegisterReadOptions, WalletClient,
+};
 use sn_logging::LogBuilder;
 use sn_protocol::{
     storage::{ChunkAddress, RegisterAddress, SpendAddress},
@@ -620,7 +622,9 @@ async fn query_content(
             }
         }
         NetworkAddress::RegisterAddress(addr) => {
-            let _ = client.get_register(*addr).await?;
+            let _ = client
+                .get_register(*addr, RegisterReadOptions::default())
+                .await?;
             Ok(())
         }
         NetworkAddress::ChunkAddress(addr) => {
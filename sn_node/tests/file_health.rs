@@ -0,0 +1,58 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_all_rpc_addresses, get_gossip_client_and_wallet},
+    random_content,
+};
+use assert_fs::TempDir;
+use eyre::Result;
+use sn_client::{FileHealth, FilesUpload};
+use sn_logging::LogBuilder;
+use sn_protocol::{
+    safenode_proto::{safe_node_client::SafeNodeClient, RemoveRecordRequest},
+    NetworkAddress,
+};
+use tonic::Request;
+
+// The nodes of the testnet are to be built with the `test-utils` feature.
+#[tokio::test(flavor = "multi_thread")]
+async fn file_missing_a_chunk_is_reported_degraded() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("file_health");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, head_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    FilesUpload::new(files_api).upload_chunks(chunks).await?;
+
+    let report = client.check_file_health(head_addr).await?;
+    assert_eq!(report.verdict, FileHealth::Complete);
+    assert!(report.chunks.len() > 1);
+
+    let removed = report.chunks[0].address;
+    let key = NetworkAddress::from_chunk_address(removed).to_record_key();
+    for rpc_address in get_all_rpc_addresses()? {
+        let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_address}")).await?;
+        rpc_client
+            .remove_record(Request::new(RemoveRecordRequest { key: key.to_vec() }))
+            .await?;
+    }
+    println!("Removed chunk {removed:?} from all the nodes");
+
+    let report = client.check_file_health(head_addr).await?;
+    assert_eq!(report.verdict, FileHealth::Degraded { missing: 1 });
+    assert_eq!(report.missing_chunks().collect::<Vec<_>>(), vec![&removed]);
+
+    client.shutdown().await?;
+    Ok(())
+}
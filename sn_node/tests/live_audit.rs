@@ -0,0 +1,94 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_gossip_client, get_gossip_client_and_wallet, get_wallet},
+    random_content,
+};
+use assert_fs::TempDir;
+use eyre::{eyre, Result};
+use sn_client::{send, AuditProgress, FilesUpload};
+use sn_logging::LogBuilder;
+use sn_node::ROYALTY_TRANSFER_NOTIF_TOPIC;
+use sn_transfers::{NanoTokens, SpendAddress};
+use tokio::time::{sleep, timeout, Duration};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn spends_of_another_client_appear_in_the_live_dag() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("live_audit");
+
+    let auditor_wallet_dir = TempDir::new()?;
+    let (auditor, auditor_wallet) =
+        get_gossip_client_and_wallet(auditor_wallet_dir.path(), 10_000_000_000).await?;
+
+    // fund the uploader from the auditor, to build the DAG from the spend funding it
+    let uploader_wallet_dir = TempDir::new()?;
+    let mut uploader_wallet = get_wallet(uploader_wallet_dir.path());
+    let uploader_note = send(
+        auditor_wallet,
+        NanoTokens::from(5_000_000_000),
+        uploader_wallet.address(),
+        &auditor,
+        true,
+    )
+    .await?;
+    uploader_wallet.deposit_and_store_to_disk(&vec![uploader_note.clone()])?;
+    let uploader_note_addr = SpendAddress::from_unique_pubkey(&uploader_note.unique_pubkey());
+
+    let funding_key = uploader_note
+        .src_tx
+        .inputs
+        .first()
+        .ok_or_else(|| eyre!("The uploader's note has no parent spend"))?
+        .unique_pubkey;
+    let dag = auditor
+        .build_spend_dag_from(SpendAddress::from_unique_pubkey(&funding_key))
+        .await?;
+    assert!(dag.get_utxos().contains(&uploader_note_addr));
+
+    let mut live = auditor.follow_spends_live(dag, ROYALTY_TRANSFER_NOTIF_TOPIC)?;
+    // small wait to ensure that the gossipsub subscription is in place
+    sleep(Duration::from_secs(20)).await;
+
+    // another client pays for storage, the nodes announcing the royalties paid by its spend
+    let uploader = get_gossip_client().await;
+    let chunks_dir = TempDir::new()?;
+    let (files_api, _content_bytes, _content_addr, chunks) = random_content(
+        &uploader,
+        uploader_wallet_dir.to_path_buf(),
+        chunks_dir.path(),
+    )?;
+    let mut files_upload = FilesUpload::new(files_api);
+    files_upload.upload_chunks(chunks).await?;
+
+    let inserted = timeout(Duration::from_secs(30), async {
+        while let Some(event) = live.next_progress().await {
+            println!("{event}");
+            if matches!(event, AuditProgress::LiveSpendInserted(addr) if addr == uploader_note_addr)
+            {
+                return true;
+            }
+        }
+        false
+    })
+    .await;
+    assert!(
+        matches!(inserted, Ok(true)),
+        "the uploader's spend should be inserted into the live DAG"
+    );
+
+    let dag = live.stop();
+    assert!(dag.spends().any(
+        |spend| SpendAddress::from_unique_pubkey(spend.unique_pubkey()) == uploader_note_addr
+    ));
+    assert!(!dag.get_utxos().contains(&uploader_note_addr));
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+#[path = "../examples/notes_app/notes.rs"]
+mod notes;
+
+use assert_fs::TempDir;
+use common::client::get_gossip_client_and_wallet;
+use eyre::{eyre, Result};
+use notes::{fetch_note, next_shared_note, subscribe_to_shared_notes, Notes};
+use sn_logging::LogBuilder;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn notes_are_created_listed_shared_and_fetched() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("notes_app");
+
+    let alice_dir = TempDir::new()?;
+    let bob_dir = TempDir::new()?;
+    let (alice, _alice_wallet) =
+        get_gossip_client_and_wallet(alice_dir.path(), 65_000_000_000).await?;
+    let (bob, _bob_wallet) = get_gossip_client_and_wallet(bob_dir.path(), 1_000_000).await?;
+
+    let mut alice_notes = Notes::open(alice.clone(), alice_dir.path().to_path_buf()).await?;
+    let estimate = alice_notes.estimate_cost("milk, eggs").await?;
+    println!("Storing a note is estimated to cost {estimate}");
+
+    println!("Creating the notes...");
+    let groceries = alice_notes.create("groceries", "milk, eggs").await?;
+    let _ = alice_notes.create("todo", "water the plants").await?;
+
+    let listed = alice_notes.list().await?;
+    assert_eq!(listed.keys().collect::<Vec<_>>(), vec!["groceries", "todo"]);
+    assert_eq!(listed.get("groceries"), Some(&groceries));
+    assert_eq!(alice_notes.get("todo").await?, "water the plants");
+
+    // the index is readable again from scratch, e.g. by another session of the same user
+    let mut reopened = Notes::open(alice.clone(), alice_dir.path().to_path_buf()).await?;
+    assert_eq!(reopened.list().await?, listed);
+
+    println!("Sharing a note with Bob...");
+    let mut bob_events = subscribe_to_shared_notes(&bob)?;
+    // let the subscription propagate through gossip
+    sleep(Duration::from_secs(5)).await;
+    let shared_address = alice_notes.share("groceries", &bob.signer_pk()).await?;
+
+    let shared = timeout(
+        Duration::from_secs(60),
+        next_shared_note(&bob, &mut bob_events),
+    )
+    .await
+    .map_err(|_| eyre!("Bob did not receive the shared note in time"))??;
+    assert_eq!(shared.title, "groceries");
+    assert_eq!(shared.address, shared_address);
+    assert_eq!(fetch_note(&bob, shared.address).await?, "milk, eggs");
+
+    // the note stored for Alice is not readable by Bob
+    assert!(fetch_note(&bob, groceries).await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::{get_client_from_builder, get_gossip_client_and_wallet};
+use assert_fs::TempDir;
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use rand::{thread_rng, Rng};
+use sn_client::{ClientBuilder, FilesApi, FilesUpload};
+use sn_logging::LogBuilder;
+use sn_protocol::storage::{Chunk, ChunkAddress};
+use std::time::Duration;
+
+const CHUNKS_COUNT: usize = 300;
+const MAX_CONCURRENT_OPS: usize = 4;
+// well above the limit, for the operations of the batches to queue on it
+const BATCH_SIZE: usize = 64;
+// generous, the operations are only expected to complete, rather than quickly
+const STRESS_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+#[tokio::test(flavor = "multi_thread")]
+async fn many_operations_complete_under_a_small_limit() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("ops_limiter");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (funding_client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let client = get_client_from_builder(
+        ClientBuilder::default()
+            .enable_gossip(true)
+            .max_concurrent_ops(MAX_CONCURRENT_OPS),
+    )
+    .await;
+
+    let mut chunks = Vec::with_capacity(CHUNKS_COUNT);
+    for _ in 0..CHUNKS_COUNT {
+        let content: [u8; 32] = thread_rng().gen();
+        let chunk = Chunk::new(Bytes::copy_from_slice(&content));
+        let path = chunks_dir.path().join(hex::encode(chunk.name()));
+        std::fs::write(&path, content)?;
+        chunks.push((*chunk.name(), path));
+    }
+    let addresses: Vec<_> = chunks
+        .iter()
+        .map(|(name, _)| ChunkAddress::new(*name))
+        .collect();
+
+    let files_api = FilesApi::new(client.clone(), paying_wallet_dir.to_path_buf());
+    let mut upload = FilesUpload::new(files_api).set_batch_size(BATCH_SIZE);
+    tokio::time::timeout(STRESS_TIMEOUT, upload.upload_chunks(chunks))
+        .await
+        .map_err(|_| eyre!("Uploading {CHUNKS_COUNT} chunks stalled"))??;
+    println!("Uploaded {CHUNKS_COUNT} chunks, {MAX_CONCURRENT_OPS} operations at a time");
+
+    let fetched = tokio::time::timeout(STRESS_TIMEOUT, client.get_chunks(&addresses, BATCH_SIZE))
+        .await
+        .map_err(|_| eyre!("Fetching {CHUNKS_COUNT} chunks stalled"))??;
+    assert_eq!(fetched.len(), CHUNKS_COUNT);
+    for (chunk, address) in fetched.iter().zip(&addresses) {
+        assert_eq!(chunk.address(), address);
+    }
+
+    client.shutdown().await?;
+    funding_client.shutdown().await?;
+    Ok(())
+}
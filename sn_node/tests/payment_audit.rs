@@ -0,0 +1,107 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use eyre::{eyre, Result};
+use sn_client::{
+    FilesUpload, PaymentAuditReport, PaymentAuditStatus, WalletClient, DEFAULT_QUOTE_VALIDITY,
+};
+use sn_logging::LogBuilder;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use xor_name::XorName;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn paid_but_never_uploaded_chunks_are_found_and_repaired() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("payment_audit");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    assert!(chunks.len() >= 3, "Not enough chunks to audit");
+
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    wallet_client.store_local_wallet()?;
+
+    // only the first chunk gets uploaded
+    let (stored, _) = chunks[0].clone();
+    let (missing, _) = chunks[1].clone();
+    let (expired, _) = chunks[2].clone();
+    FilesUpload::new(files_api.clone())
+        .upload_chunks(vec![chunks[0].clone()])
+        .await?;
+
+    // and the quote of the third one is made to have expired
+    let mut expired_payment = wallet_client
+        .mut_wallet()
+        .get_cached_payment_for_xorname(&expired)
+        .cloned()
+        .ok_or_else(|| eyre!("No payment cached for {expired:?}"))?;
+    expired_payment.quote.timestamp -= DEFAULT_QUOTE_VALIDITY * 2;
+    wallet_client
+        .mut_wallet()
+        .insert_payment_transaction(expired, expired_payment);
+
+    let report = wallet_client.audit_payments().await?;
+    assert_eq!(
+        status_of(&report, &stored),
+        Some(PaymentAuditStatus::Stored)
+    );
+    assert_eq!(
+        status_of(&report, &missing),
+        Some(PaymentAuditStatus::MissingButQuoteValid)
+    );
+    assert_eq!(
+        status_of(&report, &expired),
+        Some(PaymentAuditStatus::MissingAndExpired)
+    );
+    let (lost, _) = report.totals(PaymentAuditStatus::MissingAndExpired);
+    assert_eq!(lost, 1);
+
+    // only the chunks still paid for are uploaded again
+    let repairable: Vec<_> = report.repairable().collect();
+    let to_repair: Vec<_> = chunks
+        .iter()
+        .filter(|(name, _)| repairable.contains(&name))
+        .cloned()
+        .collect();
+    let repaired = files_api.repair_chunks(&to_repair, true).await?;
+    assert!(repaired.contains(&missing), "{missing:?} was not repaired");
+    assert!(!repaired.contains(&expired), "{expired:?} was repaired");
+
+    let report = wallet_client.audit_payments().await?;
+    assert_eq!(
+        status_of(&report, &missing),
+        Some(PaymentAuditStatus::Stored)
+    );
+    assert_eq!(
+        status_of(&report, &expired),
+        Some(PaymentAuditStatus::MissingAndExpired)
+    );
+    Ok(())
+}
+
+fn status_of(report: &PaymentAuditReport, name: &XorName) -> Option<PaymentAuditStatus> {
+    report
+        .payments
+        .iter()
+        .find(|payment| &payment.name == name)
+        .map(|payment| payment.status)
+}
@@ -0,0 +1,82 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_all_rpc_addresses, get_all_peer_ids};
+use color_eyre::Result;
+use libp2p::PeerId;
+use sn_logging::LogBuilder;
+use sn_node::MAX_PEER_SCORE;
+use sn_protocol::safenode_proto::{
+    safe_node_client::SafeNodeClient, PeerScoresRequest, RecordAddressesRequest,
+    ReplicateCorruptedRecordRequest,
+};
+use std::time::Duration;
+use tonic::Request;
+
+/// Time given to the peers to fetch and verify the corrupted records.
+const REPLICATION_WAIT: Duration = Duration::from_secs(10);
+
+// The nodes of the testnet are to be built with the `test-utils` feature.
+#[tokio::test(flavor = "multi_thread")]
+async fn peer_replicating_corrupted_records_is_penalised() -> Result<()> {
+    let _log_appender_guard =
+        LogBuilder::init_multi_threaded_tokio_test("peer_replicating_corrupted_records");
+
+    let node_rpc_addresses = get_all_rpc_addresses()?;
+    let all_peers = get_all_peer_ids(&node_rpc_addresses).await?;
+    let bad_peer = all_peers[0];
+
+    let mut bad_node =
+        SafeNodeClient::connect(format!("https://{}", node_rpc_addresses[0])).await?;
+    for peer in all_peers.iter().skip(1) {
+        bad_node
+            .replicate_corrupted_record(Request::new(ReplicateCorruptedRecordRequest {
+                peer: peer.to_bytes(),
+            }))
+            .await?;
+    }
+    println!("Replicated corrupted records from {bad_peer:?}, waiting {REPLICATION_WAIT:?}");
+    tokio::time::sleep(REPLICATION_WAIT).await;
+
+    let mut penalising_nodes = 0;
+    for (node_index, rpc_address) in node_rpc_addresses.iter().enumerate().skip(1) {
+        let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_address}")).await?;
+
+        let response = rpc_client
+            .peer_scores(Request::new(PeerScoresRequest {}))
+            .await?;
+        let Some(score) = response
+            .get_ref()
+            .scores
+            .iter()
+            .find(|score| PeerId::from_bytes(&score.peer).ok() == Some(bad_peer))
+        else {
+            println!("Node #{node_index} did not penalise {bad_peer:?}");
+            continue;
+        };
+        assert!(score.score < MAX_PEER_SCORE);
+        assert!(score.invalid_records >= 1);
+        penalising_nodes += 1;
+
+        // the corrupted record is keyed at the address of the node it was sent to
+        let corrupted_key = all_peers[node_index].to_bytes();
+        let records = rpc_client
+            .record_addresses(Request::new(RecordAddressesRequest {}))
+            .await?;
+        assert!(
+            !records.get_ref().addresses.contains(&corrupted_key),
+            "Node #{node_index} stored the corrupted record"
+        );
+    }
+
+    println!("{penalising_nodes} nodes penalised {bad_peer:?}");
+    assert!(penalising_nodes > 0, "No node penalised {bad_peer:?}");
+    Ok(())
+}
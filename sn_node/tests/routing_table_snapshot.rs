@@ -0,0 +1,139 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::get_all_rpc_addresses;
+use eyre::{eyre, Result};
+use sn_logging::LogBuilder;
+use sn_peers_acquisition::RoutingTableSnapshot;
+use sn_protocol::safenode_proto::{
+    safe_node_client::SafeNodeClient, NetworkInfoRequest, NodeInfoRequest, StopRequest,
+};
+use std::{
+    fs::File,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use tonic::Request;
+
+// Long enough for the node to have taken the snapshot of its routing table since startup.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(120);
+// Long enough for the restarted node to start and rejoin the network.
+const REJOIN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The safenode binary the testnet was started with, `SAFENODE_PATH` if set.
+fn safenode_path() -> PathBuf {
+    std::env::var_os("SAFENODE_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join("target")
+                .join("release")
+                .join(format!("safenode{}", std::env::consts::EXE_SUFFIX))
+        })
+}
+
+async fn connected_peers(rpc_addr: SocketAddr) -> Result<usize> {
+    let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_addr}")).await?;
+    let response = rpc_client
+        .network_info(Request::new(NetworkInfoRequest {}))
+        .await?;
+    Ok(response.get_ref().connected_peers.len())
+}
+
+// NB: this test stops a node of the network and starts it again, so has to run on its own.
+// The nodes of the testnet are to be built with the `local-discovery` feature, for the restarted
+// node not to fetch the network contacts.
+#[tokio::test(flavor = "multi_thread")]
+async fn restarted_node_rejoins_from_its_routing_table_snapshot() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("routing_table_snapshot");
+
+    // the genesis node is left alone
+    let rpc_addr = *get_all_rpc_addresses()?
+        .last()
+        .ok_or_else(|| eyre!("No node in the testnet"))?;
+    let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_addr}")).await?;
+    let node_info = rpc_client
+        .node_info(Request::new(NodeInfoRequest {}))
+        .await?;
+    let log_dir = PathBuf::from(&node_info.get_ref().log_dir);
+    let root_dir = log_dir
+        .parent()
+        .ok_or_else(|| eyre!("could not obtain parent from logging directory"))?
+        .to_path_buf();
+
+    let snapshot = RoutingTableSnapshot::new(&root_dir);
+    let snapshot_peers = tokio::time::timeout(SNAPSHOT_TIMEOUT, async {
+        loop {
+            match snapshot.load() {
+                Ok(peers) if !peers.is_empty() => return peers,
+                _ => tokio::time::sleep(Duration::from_secs(5)).await,
+            }
+        }
+    })
+    .await
+    .map_err(|_| eyre!("No routing table snapshot at {:?}", snapshot.path()))?;
+    println!(
+        "Node at {rpc_addr} has {} peers in its routing table snapshot",
+        snapshot_peers.len()
+    );
+
+    let _response = rpc_client
+        .stop(Request::new(StopRequest { delay_millis: 0 }))
+        .await?;
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    // no peers given, nor any in the environment: only the snapshot is there to bootstrap from
+    let stdout_path = log_dir.join("restarted_node_stdout.log");
+    let mut node = Command::new(safenode_path())
+        .arg("--root-dir")
+        .arg(&root_dir)
+        .arg("--log-output-dest")
+        .arg(&log_dir)
+        .arg("--rpc")
+        .arg(rpc_addr.to_string())
+        .arg("--local")
+        .env_remove("SAFE_PEERS")
+        .stdout(File::create(&stdout_path)?)
+        .spawn()?;
+    // the node is left running as part of the testnet
+    let _handle = std::thread::spawn(move || node.wait());
+
+    let bootstrap_line = tokio::time::timeout(REJOIN_TIMEOUT, async {
+        loop {
+            let stdout = std::fs::read_to_string(&stdout_path).unwrap_or_default();
+            if let Some(line) = stdout
+                .lines()
+                .find(|line| line.contains("peers from the routing table snapshot"))
+            {
+                return line.to_string();
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    })
+    .await
+    .map_err(|_| eyre!("The restarted node didn't bootstrap from its snapshot"))?;
+    println!("Restarted node: {bootstrap_line}");
+
+    tokio::time::timeout(REJOIN_TIMEOUT, async {
+        loop {
+            match connected_peers(rpc_addr).await {
+                Ok(peers) if peers > 0 => return,
+                _ => tokio::time::sleep(Duration::from_secs(1)).await,
+            }
+        }
+    })
+    .await
+    .map_err(|_| eyre!("The restarted node didn't rejoin the network"))?;
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_gossip_client_and_wallet, get_wallet},
+    random_content,
+};
+use assert_fs::TempDir;
+use eyre::{eyre, Result};
+use sn_client::{send, split_wallet_balance, AuditProgress, RoyaltyTracker, WalletClient};
+use sn_logging::LogBuilder;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use sn_transfers::{
+    bls_secret_from_hex, create_offline_transfer, rng, DerivationIndex, Hash, LocalWallet,
+    MainSecretKey, NanoTokens, SignedRoyaltiesClaim, SpendAddress, UnsignedRoyaltiesClaim,
+    GENESIS_CASHNOTE, GENESIS_CASHNOTE_SK, NETWORK_ROYALTIES_PK,
+};
+
+const ROYALTIES: usize = 3;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn royalties_are_claimed_with_the_key_only_used_offline() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("royalties_claim");
+
+    // some storage payments, paying royalties
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (_files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let _cost = WalletClient::new(client.clone(), paying_wallet)
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+
+    // step 1, online: find the royalties and export the unsigned claim
+    let auditor_dir = TempDir::new()?;
+    let recipient_dir = TempDir::new()?;
+    let recipient_wallet = LocalWallet::load_from(recipient_dir.path())?;
+    let mut tracker = RoyaltyTracker::new();
+    let genesis_addr = SpendAddress::from_unique_pubkey(&GENESIS_CASHNOTE.unique_pubkey());
+    let _audit = client
+        .follow_spend(
+            genesis_addr,
+            false,
+            Some(&mut tracker),
+            None,
+            auditor_dir.path(),
+            None,
+        )
+        .await?;
+    let redemptions = tracker.redemptions();
+    assert!(!redemptions.is_empty(), "No royalties found");
+    let claim = client
+        .create_royalties_claim(redemptions.clone(), recipient_wallet.address())
+        .await?;
+    let total = claim.total()?;
+    println!("Claiming {} royalties for {total}", claim.spends.len());
+
+    // step 2, offline: sign the claim
+    let signed_claim_hex = sign_offline(&claim.to_hex()?)?;
+
+    // step 3, online: broadcast the claim and deposit the royalties
+    let signed_claim = SignedRoyaltiesClaim::from_hex(&signed_claim_hex)?;
+    let cash_note = client.broadcast_royalties_claim(&signed_claim).await?;
+    let mut recipient_wallet = LocalWallet::load_from(recipient_dir.path())?;
+    recipient_wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+    assert_eq!(recipient_wallet.balance(), total);
+
+    for spend in &signed_claim.spends {
+        let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+        let _spend = client.get_spend_from_network(address).await?;
+    }
+
+    // the claimed royalties can't be claimed again
+    assert!(client
+        .create_royalties_claim(redemptions, recipient_wallet.address())
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn royalties_paid_by_several_spends_are_deposited_at_once() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("royalties_claim");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    // our note, split in two halves by a single Tx
+    let payer_dir = TempDir::new()?;
+    let mut payer = get_wallet(payer_dir.path());
+    let note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        payer.address(),
+        &client,
+        true,
+    )
+    .await?;
+    payer.deposit_and_store_to_disk(&vec![note.clone()])?;
+    let mut payer = split_wallet_balance(&client, payer, 2, 2).await?;
+
+    // both halves spent paying royalties worth more than either, each spend listing them all
+    let royalty = NanoTokens::from(200);
+    let mut rng = rng::thread_rng();
+    let royalties = (0..ROYALTIES)
+        .map(|_| {
+            (
+                royalty,
+                *NETWORK_ROYALTIES_PK,
+                DerivationIndex::random(&mut rng),
+            )
+        })
+        .collect();
+    let (halves, _exclusive_access) = payer.available_cash_notes()?;
+    assert_eq!(halves.len(), 2);
+    let transfer = create_offline_transfer(halves, royalties, payer.address(), Hash::default())?;
+    assert_eq!(transfer.all_spend_requests.len(), 2);
+    client
+        .send_spends(transfer.all_spend_requests.iter(), true)
+        .await?;
+
+    // the auditor holds the royalties key
+    let auditor_dir = TempDir::new()?;
+    let royalties_sk = MainSecretKey::new(bls_secret_from_hex(GENESIS_CASHNOTE_SK)?);
+    let _ = LocalWallet::load_from_main_key(auditor_dir.path(), royalties_sk)?;
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    let spend_addr = SpendAddress::from_unique_pubkey(&note.unique_pubkey());
+    let _audit = client
+        .follow_spend(
+            spend_addr,
+            true,
+            None,
+            None,
+            auditor_dir.path(),
+            Some(&mut progress),
+        )
+        .await?;
+
+    let total = NanoTokens::from(ROYALTIES as u64 * royalty.as_nano());
+    let deposits: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            AuditProgress::RoyaltyDeposited {
+                old_balance,
+                new_balance,
+            } => Some((*old_balance, *new_balance)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        deposits,
+        vec![(NanoTokens::zero(), total)],
+        "unexpected events: {events:#?}"
+    );
+    let identified = events
+        .iter()
+        .filter(|event| matches!(event, AuditProgress::RoyaltyIdentified(_)))
+        .count();
+    assert_eq!(identified, ROYALTIES);
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, AuditProgress::RoyaltiesFound(ROYALTIES))));
+    assert_eq!(LocalWallet::load_from(auditor_dir.path())?.balance(), total);
+
+    Ok(())
+}
+
+/// The only place the royalties key is used, without any access to the network.
+fn sign_offline(claim_hex: &str) -> Result<String> {
+    let claim = UnsignedRoyaltiesClaim::from_hex(claim_hex)?;
+    let royalties_sk = MainSecretKey::new(bls_secret_from_hex(GENESIS_CASHNOTE_SK)?);
+    let signed = claim
+        .sign(&royalties_sk)
+        .map_err(|err| eyre!("Failed to sign the claim offline: {err}"))?;
+    Ok(signed.to_hex()?)
+}
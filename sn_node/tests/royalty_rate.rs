@@ -0,0 +1,108 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{
+    client::{get_all_rpc_addresses, get_gossip_client_and_wallet},
+    random_content,
+};
+use assert_fs::TempDir;
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use sn_client::Error as ClientError;
+use sn_logging::LogBuilder;
+use sn_protocol::{
+    safenode_proto::{safe_node_client::SafeNodeClient, SetRoyaltyRateRequest},
+    storage::{Chunk, ChunkAddress},
+};
+use sn_transfers::{RoyaltyRate, ROYALTY_RATE};
+use std::time::Duration;
+use tonic::Request;
+
+async fn set_royalty_rate_of_all_nodes(royalty_rate: RoyaltyRate) -> Result<()> {
+    for rpc_address in get_all_rpc_addresses()? {
+        let mut rpc_client = SafeNodeClient::connect(format!("https://{rpc_address}")).await?;
+        rpc_client
+            .set_royalty_rate(Request::new(SetRoyaltyRateRequest {
+                version: royalty_rate.version,
+                basis_points: royalty_rate.basis_points,
+            }))
+            .await?;
+    }
+    // the nodes pick up the rate in their event loop
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
+// The nodes of the testnet are to be built with the `test-utils` feature.
+#[tokio::test(flavor = "multi_thread")]
+async fn payments_at_another_royalty_rate_are_refused() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("royalty_rate");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _head_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let (paid_chunks, unpaid_chunks) = chunks.split_at(chunks.len() / 2);
+
+    // paid for at our rate, before the network moves to another one
+    let (_cost, (payees, _skipped)) = files_api
+        .pay_for_chunks(paid_chunks.iter().map(|(name, _)| *name).collect())
+        .await?;
+
+    let theirs = RoyaltyRate {
+        version: ROYALTY_RATE.version + 1,
+        basis_points: ROYALTY_RATE.basis_points + 500,
+    };
+    set_royalty_rate_of_all_nodes(theirs).await?;
+
+    // the nodes reject the payments made at our rate
+    let (name, path) = &paid_chunks[0];
+    let payee = payees
+        .iter()
+        .find_map(|(paid_name, payee)| (paid_name == name).then_some(*payee))
+        .ok_or_else(|| eyre!("No payee for {name:?}"))?;
+    let chunk = Chunk::new(Bytes::from(std::fs::read(path)?));
+    let upload = files_api
+        .get_local_payment_and_upload_chunk(chunk, payee, true)
+        .await;
+    let stored = client.get_chunk(ChunkAddress::new(*name), false).await;
+
+    // and the client refuses to pay at their rate
+    let payment = files_api
+        .pay_for_chunks(unpaid_chunks.iter().map(|(name, _)| *name).collect())
+        .await;
+
+    set_royalty_rate_of_all_nodes(ROYALTY_RATE).await?;
+
+    // the nodes tell why they refused the payment, when asked for the proof of the chunk
+    match upload {
+        Err(ClientError::RoyaltyRateMismatch { ours, theirs: got }) => {
+            assert_eq!(ours, ROYALTY_RATE);
+            assert_eq!(got, theirs);
+        }
+        other => panic!("Expected the nodes to refuse the payment at our rate, got {other:?}"),
+    }
+    assert!(
+        stored.is_err(),
+        "Expected the chunk paid at another rate not to be stored, got {stored:?}"
+    );
+    match payment {
+        Err(ClientError::RoyaltyRateMismatch { ours, theirs: got }) => {
+            assert_eq!(ours, ROYALTY_RATE);
+            assert_eq!(got, theirs);
+        }
+        other => panic!("Expected a royalty rate mismatch, got {other:?}"),
+    }
+
+    client.shutdown().await?;
+    Ok(())
+}
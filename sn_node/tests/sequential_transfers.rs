@@ -11,9 +11,17 @@ mod common;
 use assert_fs::TempDir;
 use common::client::{get_gossip_client_and_wallet, get_wallet};
 use eyre::Result;
-use sn_client::send;
+use futures::future::join_all;
+use sn_client::{send, split_wallet_balance};
 use sn_logging::LogBuilder;
-use sn_transfers::{create_offline_transfer, rng, DerivationIndex, Hash, NanoTokens};
+use sn_transfers::{
+    create_first_cash_note_from_key, create_offline_transfer, rng, DerivationIndex, Hash,
+    MainSecretKey, NanoTokens, SpendAddress, Transfer, WalletError,
+};
+use std::{collections::BTreeSet, time::Instant};
+
+const SPLIT_NOTES: usize = 20;
+const PAYOUTS: usize = 8;
 
 #[tokio::test]
 async fn cash_note_transfer_multiple_sequential_succeed() -> Result<()> {
@@ -52,6 +60,78 @@ async fn cash_note_transfer_multiple_sequential_succeed() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn transfer_sent_as_hex_is_received_by_its_recipient_only() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("sequential_transfer");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let amount = NanoTokens::from(300_000_000);
+    let cash_note = send(first_wallet, amount, second_wallet.address(), &client, true).await?;
+
+    // handed over out-of-band, as a wallet app would
+    let transfer_hex = Transfer::transfer_from_cash_note(&cash_note)?.to_hex()?;
+
+    // no one else can redeem it
+    let third_wallet_dir = TempDir::new()?;
+    let third_wallet = get_wallet(third_wallet_dir.path());
+    assert!(client
+        .receive_from_hex(&transfer_hex, &third_wallet)
+        .await
+        .is_err());
+    assert!(client
+        .receive_from_hex("not a transfer", &second_wallet)
+        .await
+        .is_err());
+
+    let cash_notes = client
+        .receive_from_hex(&transfer_hex, &second_wallet)
+        .await?;
+    assert_eq!(cash_notes.len(), 1);
+    assert_eq!(cash_notes[0].unique_pubkey(), cash_note.unique_pubkey());
+    second_wallet.deposit_and_store_to_disk(&cash_notes)?;
+    assert_eq!(second_wallet.balance(), amount);
+
+    // the same as receiving the decoded transfer
+    let transfer = Transfer::from_hex(&transfer_hex)?;
+    assert_eq!(client.receive(&transfer, &second_wallet).await?, cash_notes);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfer_to_a_retired_address_is_received() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("sequential_transfer");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    // the recipient rotates its key after handing its address over
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let retired_address = second_wallet.address();
+    let new_address = second_wallet.rotate_key()?;
+    assert_ne!(retired_address, new_address);
+
+    let amount = NanoTokens::from(300_000_000);
+    let cash_note = send(first_wallet, amount, retired_address, &client, true).await?;
+    let transfer = Transfer::transfer_from_cash_note(&cash_note)?;
+
+    let cash_notes = client.receive(&transfer, &second_wallet).await?;
+    assert_eq!(cash_notes.len(), 1);
+    assert_eq!(cash_notes[0].unique_pubkey(), cash_note.unique_pubkey());
+    assert_eq!(cash_notes[0].main_pubkey(), &retired_address);
+    second_wallet.deposit_and_store_to_disk(&cash_notes)?;
+    assert_eq!(second_wallet.balance(), amount);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn cash_note_transfer_double_spend_fail() -> Result<()> {
     let _log_guards = LogBuilder::init_single_threaded_tokio_test("sequential_transfer");
@@ -116,3 +196,188 @@ async fn cash_note_transfer_double_spend_fail() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn double_spend_conflicts_are_served_by_the_close_group() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("sequential_transfer");
+
+    let first_wallet_balance = 1_000_000_000;
+    let first_wallet_dir = TempDir::new()?;
+    let (client, mut first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), first_wallet_balance).await?;
+    let second_wallet_dir = TempDir::new()?;
+    let second_wallet = get_wallet(second_wallet_dir.path());
+
+    // spend the same cash notes twice, to two different recipients
+    let amount = NanoTokens::from(first_wallet_balance / 3);
+    let change_to = first_wallet.address();
+    let (some_cash_notes, _exclusive_access) = first_wallet.available_cash_notes()?;
+    let same_cash_notes = some_cash_notes.clone();
+    let mut rng = rng::thread_rng();
+    let to_first = (amount, change_to, DerivationIndex::random(&mut rng));
+    let to_second = (
+        amount,
+        second_wallet.address(),
+        DerivationIndex::random(&mut rng),
+    );
+    let first_transfer =
+        create_offline_transfer(some_cash_notes, vec![to_first], change_to, Hash::default())?;
+    let second_transfer =
+        create_offline_transfer(same_cash_notes, vec![to_second], change_to, Hash::default())?;
+
+    println!("Sending both transfers to the network...");
+    let _ = client
+        .send_spends(first_transfer.all_spend_requests.iter(), false)
+        .await;
+    let _ = client
+        .send_spends(second_transfer.all_spend_requests.iter(), false)
+        .await;
+    // let the close group aggregate the conflicting spends
+    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+
+    let first_spend = &first_transfer.all_spend_requests[0];
+    let address = SpendAddress::from_unique_pubkey(first_spend.unique_pubkey());
+    let second_spend = second_transfer
+        .all_spend_requests
+        .iter()
+        .find(|spend| spend.unique_pubkey() == first_spend.unique_pubkey())
+        .ok_or_else(|| eyre::eyre!("Both transfers shall spend the same cash note"))?;
+
+    let conflicts = client.get_spend_conflicts(address).await?;
+    println!("Got {} conflicting spends at {address:?}", conflicts.len());
+    assert!(conflicts.contains(first_spend));
+    assert!(conflicts.contains(second_spend));
+    for spend in conflicts {
+        spend.verify(spend.spent_tx_hash())?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spend_with_fabricated_parent_is_refused() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("sequential_transfer");
+
+    let wallet_dir = TempDir::new()?;
+    let (client, wallet) = get_gossip_client_and_wallet(wallet_dir.path(), 1_000_000).await?;
+
+    // a cash_note whose parent spend has never been stored on the network
+    let fabricated_key = MainSecretKey::random();
+    let fabricated_cash_note = create_first_cash_note_from_key(&fabricated_key)?;
+    let derived_key = fabricated_cash_note.derived_key(&fabricated_key)?;
+    let parent_address = SpendAddress::from_unique_pubkey(&derived_key.unique_pubkey());
+
+    let mut rng = rng::thread_rng();
+    let recipient = (
+        NanoTokens::from(1_000),
+        wallet.address(),
+        DerivationIndex::random(&mut rng),
+    );
+    let transfer = create_offline_transfer(
+        vec![(fabricated_cash_note, derived_key)],
+        vec![recipient],
+        fabricated_key.main_pubkey(),
+        Hash::default(),
+    )?;
+
+    let res = client
+        .send_spends(transfer.all_spend_requests.iter(), false)
+        .await;
+    println!("Sending the spend with a fabricated parent: {res:?}");
+    match res {
+        Err(WalletError::CouldNotSendMoney(reason)) => {
+            assert!(reason.contains(&format!("{parent_address:?}")));
+        }
+        other => eyre::bail!("Expected the spend to be refused, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn split_wallet_pays_out_concurrently() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("sequential_transfer");
+
+    let wallet_balance = 1_000_000_000;
+    let wallet_dir = TempDir::new()?;
+    let (client, wallet) = get_gossip_client_and_wallet(wallet_dir.path(), wallet_balance).await?;
+
+    let wallet = split_wallet_balance(&client, wallet, SPLIT_NOTES, SPLIT_NOTES / 2).await?;
+    assert_eq!(wallet.balance().as_nano(), wallet_balance);
+
+    // the split is done, it's not redone
+    let wallet = split_wallet_balance(&client, wallet, SPLIT_NOTES, SPLIT_NOTES / 2).await?;
+    assert_eq!(wallet.balance().as_nano(), wallet_balance);
+
+    let payout = NanoTokens::from(100);
+
+    for _ in 0..PAYOUTS {
+        let recipient = MainSecretKey::random().main_pubkey();
+        let _ = send(get_wallet(&wallet_dir), payout, recipient, &client, true).await?;
+    }
+
+    // each payout records when its spends start being sent to the network, and when they're in
+    let payouts = (0..PAYOUTS).map(|_| {
+        let (wallet_dir, client) = (&wallet_dir, &client);
+        let recipient = MainSecretKey::random().main_pubkey();
+        async move {
+            // the spends of the payouts made before are pending in the wallet too
+            let mut wallet = get_wallet(wallet_dir);
+            let pending = wallet.unconfirmed_spend_requests().clone();
+            let created_cash_notes = wallet.local_send(vec![(payout, recipient)], None)?;
+            let spends: Vec<_> = wallet
+                .unconfirmed_spend_requests()
+                .difference(&pending)
+                .cloned()
+                .collect();
+
+            let start = Instant::now();
+            client.send_spends(spends.iter(), true).await?;
+            let finish = Instant::now();
+
+            Ok::<_, eyre::Report>((created_cash_notes, start, finish))
+        }
+    });
+    let mut spent_inputs = BTreeSet::new();
+    let mut intervals = vec![];
+    for result in join_all(payouts).await {
+        let (created_cash_notes, start, finish) = result?;
+        for cash_note in &created_cash_notes {
+            for input in &cash_note.src_tx.inputs {
+                // no two payouts spent the same note
+                assert!(spent_inputs.insert(input.unique_pubkey));
+            }
+        }
+        intervals.push((start, finish));
+    }
+
+    // all the payouts were being sent at once, none waiting on another
+    let last_start = intervals
+        .iter()
+        .map(|(start, _)| *start)
+        .max()
+        .ok_or_else(|| eyre::eyre!("No payout was made"))?;
+    let first_finish = intervals
+        .iter()
+        .map(|(_, finish)| *finish)
+        .min()
+        .ok_or_else(|| eyre::eyre!("No payout was made"))?;
+    println!(
+        "{PAYOUTS} concurrent payouts, the last one started {:?} before the first one finished",
+        first_finish.saturating_duration_since(last_start)
+    );
+    assert!(
+        last_start < first_finish,
+        "the payouts were sent one after the other"
+    );
+
+    // all the spends of the payouts are in
+    let mut wallet = get_wallet(&wallet_dir);
+    wallet.clear_confirmed_spend_requests();
+    assert_eq!(
+        wallet.balance().as_nano(),
+        wallet_balance - 2 * PAYOUTS as u64 * payout.as_nano()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,580 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use assert_fs::TempDir;
+use common::client::{get_gossip_client_and_wallet, get_wallet};
+use eyre::Result;
+use sn_client::{
+    send, split_wallet_balance, AuditFrontier, AuditProgress, Client, SpendVerificationReport,
+    DEFAULT_AUDIT_CONCURRENCY,
+};
+use sn_logging::LogBuilder;
+use sn_transfers::{
+    create_offline_transfer, rng, CashNote, DerivationIndex, Hash, MainSecretKey, NanoTokens,
+    SpendAddress, NETWORK_ROYALTIES_PK,
+};
+use std::{collections::BTreeSet, time::Duration};
+
+const FAN_OUT: usize = 30;
+
+/// Spend a short chain of our own: first -> second -> third -> someone, returning the cash notes
+/// received by the second and third wallets, the latter spent.
+async fn spend_short_chain() -> Result<(Client, CashNote, CashNote)> {
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let amount = NanoTokens::from(1_000);
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(first_wallet, amount, second_wallet.address(), &client, true).await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(second_wallet, amount, third_wallet.address(), &client, true).await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note.clone()])?;
+
+    let someone = MainSecretKey::random().main_pubkey();
+    let _ = send(third_wallet, amount, someone, &client, true).await?;
+    Ok((client, second_note, third_note))
+}
+
+#[tokio::test]
+async fn verifying_a_spend_reports_the_chain_of_its_ancestors() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("spend_verification");
+
+    let (client, second_note, third_note) = spend_short_chain().await?;
+    let spend_addr = SpendAddress::from_unique_pubkey(&third_note.unique_pubkey());
+
+    let report = client.verify_spend(spend_addr, false, None, None).await?;
+    assert_eq!(report.address, spend_addr);
+    assert_eq!(report.depth, 0);
+    assert!(report.verified_txs.is_empty());
+    assert!(!report.reached_genesis);
+
+    let mut progress_reports: Vec<SpendVerificationReport> = vec![];
+    let mut progress = |event: AuditProgress| {
+        if let AuditProgress::GenerationVerified(report) = event {
+            progress_reports.push(report);
+        }
+    };
+    let report = client
+        .verify_spend_with_progress(spend_addr, true, None, None, Some(&mut progress))
+        .await?;
+    println!("Verified {spend_addr:?}: {report:?}");
+
+    assert_eq!(report.address, spend_addr);
+    assert!(report.reached_genesis);
+    // the two transactions of our chain, then at least the one funding the first wallet
+    assert!(report.depth >= 3, "depth {} too shallow", report.depth);
+    assert_eq!(report.verified_txs[0], third_note.src_tx.hash());
+    assert_eq!(report.verified_txs[1], second_note.src_tx.hash());
+    assert!(report.txs_verified() >= report.depth);
+    assert!(report.elapsed > Duration::ZERO);
+    assert!(!report.is_partial());
+
+    // once per generation, the last one having verified all there was
+    let depths: Vec<_> = progress_reports.iter().map(|report| report.depth).collect();
+    assert_eq!(depths, (1..=report.depth).collect::<Vec<_>>());
+    assert_eq!(
+        progress_reports.last().map(|last| &last.verified_txs),
+        Some(&report.verified_txs)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_verification_split_in_bounded_runs_verifies_as_much_as_one() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("spend_verification");
+
+    let (client, second_note, third_note) = spend_short_chain().await?;
+    let spend_addr = SpendAddress::from_unique_pubkey(&third_note.unique_pubkey());
+    let whole = client.verify_spend(spend_addr, true, None, None).await?;
+
+    // the parent tx of the spend only, leaving its own parent to verify
+    let first_run = client.verify_spend(spend_addr, true, Some(1), None).await?;
+    assert!(first_run.is_partial());
+    assert!(!first_run.reached_genesis);
+    assert_eq!(first_run.depth, 1);
+    assert_eq!(first_run.verified_txs, vec![third_note.src_tx.hash()]);
+    assert_eq!(first_run.frontier_hashes(), vec![second_note.src_tx.hash()]);
+
+    // out of budget once the single input of that parent tx was fetched
+    let budget_run = client.verify_spend(spend_addr, true, None, Some(1)).await?;
+    assert_eq!(budget_run.spends_fetched, 1);
+    assert_eq!(budget_run.verified_txs, first_run.verified_txs);
+    assert_eq!(budget_run.frontier_hashes(), first_run.frontier_hashes());
+    assert_eq!(
+        budget_run.frontier_depths.get(&second_note.src_tx.hash()),
+        Some(&2)
+    );
+    let resumed_budget_run = client
+        .resume_spend_verification(budget_run, None, None, None)
+        .await?;
+    assert_eq!(resumed_budget_run.depth, whole.depth);
+
+    let resumed = client
+        .resume_spend_verification(first_run, None, None, None)
+        .await?;
+    assert!(!resumed.is_partial());
+    assert!(resumed.reached_genesis);
+    assert_eq!(resumed.depth, whole.depth);
+    assert_eq!(resumed.verified_txs, whole.verified_txs);
+
+    // resuming a complete verification leaves it as it is
+    let again = client
+        .resume_spend_verification(resumed.clone(), Some(1), Some(1), None)
+        .await?;
+    assert_eq!(again.verified_txs, resumed.verified_txs);
+    assert_eq!(again.depth, resumed.depth);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn following_a_spend_reports_its_progress() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("spend_verification");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    // a tiny DAG: the spend of our note, to someone and back to us as change, both unspent
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        second_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+    let someone = MainSecretKey::random().main_pubkey();
+    let _ = send(second_wallet, NanoTokens::from(400), someone, &client, true).await?;
+    let spend_addr = SpendAddress::from_unique_pubkey(&second_note.unique_pubkey());
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    let auditor_dir = TempDir::new()?;
+    let audit = client
+        .follow_spend(
+            spend_addr,
+            false,
+            None,
+            None,
+            auditor_dir.path(),
+            Some(&mut progress),
+        )
+        .await?;
+    for event in &events {
+        println!("{event}");
+    }
+
+    assert_eq!(audit.utxos.len(), 2);
+    assert!(audit.double_spends.is_empty());
+    assert_eq!(audit.stats.generations, 1);
+    assert!(
+        matches!(
+            events.as_slice(),
+            [
+                AuditProgress::FirstSpendFound(first),
+                AuditProgress::GenerationStarted { generation: 1, txs: 1 },
+                AuditProgress::TxsFetched { generation: 1, txs: 1, concurrent: 1 },
+                AuditProgress::SpendsFetched { generation: 1, spends: 0, .. },
+                AuditProgress::GenerationFollowed { generation: 1, utxos: 2, spends: 0, .. },
+                AuditProgress::AuditFinished { generations: 1, utxos: 2, txs_verified: 1, rejected, double_spent, .. },
+            ] if *first == spend_addr && rejected.is_empty() && double_spent.is_empty()
+        ),
+        "unexpected events: {events:#?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn following_spends_reports_double_spends_and_carries_on() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("spend_verification");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    // the spend of our note, to a third wallet and back to us as change
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(
+        first_wallet,
+        NanoTokens::from(1_000),
+        second_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(
+        second_wallet,
+        NanoTokens::from(400),
+        third_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note.clone()])?;
+
+    // the third wallet forks its spend, paying two recipients with the same note
+    let (some_cash_notes, _exclusive_access) = third_wallet.available_cash_notes()?;
+    let same_cash_notes = some_cash_notes.clone();
+    let mut rng = rng::thread_rng();
+    let amount = NanoTokens::from(100);
+    let change_to = third_wallet.address();
+    let to_someone = (
+        amount,
+        MainSecretKey::random().main_pubkey(),
+        DerivationIndex::random(&mut rng),
+    );
+    let to_someone_else = (
+        amount,
+        MainSecretKey::random().main_pubkey(),
+        DerivationIndex::random(&mut rng),
+    );
+    let first_transfer = create_offline_transfer(
+        some_cash_notes,
+        vec![to_someone],
+        change_to,
+        Hash::default(),
+    )?;
+    let second_transfer = create_offline_transfer(
+        same_cash_notes,
+        vec![to_someone_else],
+        change_to,
+        Hash::default(),
+    )?;
+    let _ = client
+        .send_spends(first_transfer.all_spend_requests.iter(), false)
+        .await;
+    let _ = client
+        .send_spends(second_transfer.all_spend_requests.iter(), false)
+        .await;
+    // let the close group aggregate the conflicting spends
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let spend_addr = SpendAddress::from_unique_pubkey(&second_note.unique_pubkey());
+    let forked_addr = SpendAddress::from_unique_pubkey(&third_note.unique_pubkey());
+    let auditor_dir = TempDir::new()?;
+    let audit = client
+        .follow_spend(spend_addr, false, None, None, auditor_dir.path(), None)
+        .await?;
+    println!("Audited from {spend_addr:?}: {audit:?}");
+
+    // the fork is reported with both its spends, and not followed
+    assert_eq!(audit.double_spent_addresses(), [forked_addr].into());
+    let forked_spends = &audit.double_spends[0].spends;
+    assert!(forked_spends.contains(&first_transfer.all_spend_requests[0]));
+    assert!(forked_spends.contains(&second_transfer.all_spend_requests[0]));
+    // while its sibling, the change of the second wallet, is found unspent
+    assert_eq!(audit.utxos.len(), 1);
+    assert!(!audit.utxos.contains(&forked_addr));
+    assert_eq!(audit.stats.generations, 1);
+    assert_eq!(audit.stats.txs_verified, 1);
+    assert!(audit.stats.rejected.is_empty());
+
+    // carrying on from a frontier before the fork reports it just the same
+    let frontier = AuditFrontier::new(BTreeSet::from([spend_addr]));
+    let (frontier, resumed) = client
+        .follow_spend_from(frontier, false, None, None, auditor_dir.path(), None)
+        .await?;
+    assert_eq!(resumed.double_spent_addresses(), [forked_addr].into());
+    assert_eq!(resumed.utxos, audit.utxos);
+    assert!(!frontier.utxos.contains(&forked_addr));
+
+    Ok(())
+}
+
+/// Spend a wide generation of our own: our note is split into FAN_OUT notes by a single Tx,
+/// each of them paid to a collector by a Tx of its own, the collector then paying them all at
+/// once to a last wallet, which spends them. Returns our note and the one of the last wallet.
+async fn spend_wide_generation() -> Result<(Client, CashNote, CashNote)> {
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    let wallet_dir = TempDir::new()?;
+    let mut wallet = get_wallet(wallet_dir.path());
+    let note = send(
+        first_wallet,
+        NanoTokens::from(FAN_OUT as u64 * 1_000),
+        wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    wallet.deposit_and_store_to_disk(&vec![note.clone()])?;
+    let mut wallet = split_wallet_balance(&client, wallet, FAN_OUT, FAN_OUT).await?;
+    let (split_notes, _exclusive_access) = wallet.available_cash_notes()?;
+    assert_eq!(split_notes.len(), FAN_OUT);
+
+    // one payout after the other, each spending a note of the split
+    let collector_dir = TempDir::new()?;
+    let mut collector = get_wallet(collector_dir.path());
+    let mut rng = rng::thread_rng();
+    for split_note in split_notes {
+        let payout = create_offline_transfer(
+            vec![split_note],
+            vec![(
+                NanoTokens::from(100),
+                collector.address(),
+                DerivationIndex::random(&mut rng),
+            )],
+            wallet.address(),
+            Hash::default(),
+        )?;
+        client
+            .send_spends(payout.all_spend_requests.iter(), true)
+            .await?;
+        collector.deposit_and_store_to_disk(&payout.created_cash_notes)?;
+    }
+
+    let last_wallet_dir = TempDir::new()?;
+    let mut last_wallet = get_wallet(last_wallet_dir.path());
+    let last_note = send(
+        collector,
+        NanoTokens::from(FAN_OUT as u64 * 100),
+        last_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    assert_eq!(last_note.src_tx.inputs.len(), FAN_OUT);
+    last_wallet.deposit_and_store_to_disk(&vec![last_note.clone()])?;
+    let someone = MainSecretKey::random().main_pubkey();
+    let _ = send(last_wallet, NanoTokens::from(100), someone, &client, true).await?;
+
+    Ok((client, note, last_note))
+}
+
+/// The number of transactions of each generation, and the most of them fetched at once.
+fn txs_fetched(events: &[AuditProgress]) -> Vec<(usize, usize)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            AuditProgress::TxsFetched {
+                txs, concurrent, ..
+            } => Some((*txs, *concurrent)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wide_generations_are_followed_concurrently() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("spend_verification");
+
+    let (mut client, note, _) = spend_wide_generation().await?;
+    let spend_addr = SpendAddress::from_unique_pubkey(&note.unique_pubkey());
+    let auditor_dir = TempDir::new()?;
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    client.set_audit_concurrency(1);
+    let one_at_a_time = client
+        .follow_spend(
+            spend_addr,
+            false,
+            None,
+            None,
+            auditor_dir.path(),
+            Some(&mut progress),
+        )
+        .await?;
+    let sequential_fetches = txs_fetched(&events);
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    client.set_audit_concurrency(DEFAULT_AUDIT_CONCURRENCY);
+    let concurrent = client
+        .follow_spend(
+            spend_addr,
+            false,
+            None,
+            None,
+            auditor_dir.path(),
+            Some(&mut progress),
+        )
+        .await?;
+    let concurrent_fetches = txs_fetched(&events);
+    println!("Fetched one at a time: {sequential_fetches:?}, concurrently: {concurrent_fetches:?}");
+
+    // the same DAG is found either way
+    assert!(one_at_a_time.stats.txs_verified > FAN_OUT);
+    assert_eq!(concurrent.utxos, one_at_a_time.utxos);
+    assert_eq!(concurrent.total_utxo_value, one_at_a_time.total_utxo_value);
+    assert_eq!(
+        concurrent.stats.generations,
+        one_at_a_time.stats.generations
+    );
+    assert_eq!(
+        concurrent.stats.txs_verified,
+        one_at_a_time.stats.txs_verified
+    );
+
+    // with as many txs fetched at once as allowed, FAN_OUT of them in the wide generation
+    assert!(sequential_fetches
+        .iter()
+        .all(|(txs, concurrent)| *concurrent == 1.min(*txs)));
+    assert_eq!(
+        sequential_fetches
+            .iter()
+            .map(|(txs, _)| txs)
+            .collect::<Vec<_>>(),
+        concurrent_fetches
+            .iter()
+            .map(|(txs, _)| txs)
+            .collect::<Vec<_>>()
+    );
+    assert!(concurrent_fetches.iter().any(|(txs, _)| *txs >= FAN_OUT));
+    for (txs, concurrent) in concurrent_fetches {
+        assert_eq!(concurrent, txs.min(DEFAULT_AUDIT_CONCURRENCY));
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wide_generations_of_ancestors_are_verified_concurrently() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("spend_verification");
+
+    let (mut client, _, last_note) = spend_wide_generation().await?;
+    let spend_addr = SpendAddress::from_unique_pubkey(&last_note.unique_pubkey());
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    client.set_audit_concurrency(1);
+    let one_at_a_time = client
+        .verify_spend_with_progress(spend_addr, true, None, None, Some(&mut progress))
+        .await?;
+    let sequential_fetches = txs_fetched(&events);
+
+    let mut events = vec![];
+    let mut progress = |event: AuditProgress| events.push(event);
+    client.set_audit_concurrency(DEFAULT_AUDIT_CONCURRENCY);
+    let concurrent = client
+        .verify_spend_with_progress(spend_addr, true, None, None, Some(&mut progress))
+        .await?;
+    let concurrent_fetches = txs_fetched(&events);
+    println!("Fetched one at a time: {sequential_fetches:?}, concurrently: {concurrent_fetches:?}");
+
+    // the same ancestors are verified either way, the payouts to the collector in generation 2
+    assert!(one_at_a_time.reached_genesis);
+    assert!(concurrent.reached_genesis);
+    assert_eq!(concurrent.depth, one_at_a_time.depth);
+    assert_eq!(
+        concurrent.verified_txs.iter().collect::<BTreeSet<_>>(),
+        one_at_a_time.verified_txs.iter().collect::<BTreeSet<_>>()
+    );
+    assert_eq!(concurrent.spends_fetched, one_at_a_time.spends_fetched);
+    assert_eq!(
+        sequential_fetches.get(1).map(|(txs, _)| *txs),
+        Some(FAN_OUT)
+    );
+
+    assert!(sequential_fetches
+        .iter()
+        .all(|(txs, concurrent)| *concurrent == 1.min(*txs)));
+    assert_eq!(
+        sequential_fetches
+            .iter()
+            .map(|(txs, _)| txs)
+            .collect::<Vec<_>>(),
+        concurrent_fetches
+            .iter()
+            .map(|(txs, _)| txs)
+            .collect::<Vec<_>>()
+    );
+    for (txs, concurrent) in concurrent_fetches {
+        assert_eq!(concurrent, txs.min(DEFAULT_AUDIT_CONCURRENCY));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_supply_of_a_small_economy_adds_up() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("spend_verification");
+
+    let first_wallet_dir = TempDir::new()?;
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), 1_000_000_000).await?;
+
+    // our note of 1000, of which 400 to a third wallet
+    let supply = NanoTokens::from(1_000);
+    let second_wallet_dir = TempDir::new()?;
+    let mut second_wallet = get_wallet(second_wallet_dir.path());
+    let second_note = send(first_wallet, supply, second_wallet.address(), &client, true).await?;
+    second_wallet.deposit_and_store_to_disk(&vec![second_note.clone()])?;
+    let third_wallet_dir = TempDir::new()?;
+    let mut third_wallet = get_wallet(third_wallet_dir.path());
+    let third_note = send(
+        second_wallet,
+        NanoTokens::from(400),
+        third_wallet.address(),
+        &client,
+        true,
+    )
+    .await?;
+    third_wallet.deposit_and_store_to_disk(&vec![third_note])?;
+
+    // which pays someone 100, and 2 royalties of 50
+    let mut rng = rng::thread_rng();
+    let mut recipients = vec![(
+        NanoTokens::from(100),
+        MainSecretKey::random().main_pubkey(),
+        DerivationIndex::random(&mut rng),
+    )];
+    for _ in 0..2 {
+        recipients.push((
+            NanoTokens::from(50),
+            *NETWORK_ROYALTIES_PK,
+            DerivationIndex::random(&mut rng),
+        ));
+    }
+    let (cash_notes, _exclusive_access) = third_wallet.available_cash_notes()?;
+    let transfer = create_offline_transfer(
+        cash_notes,
+        recipients,
+        third_wallet.address(),
+        Hash::default(),
+    )?;
+    client
+        .send_spends(transfer.all_spend_requests.iter(), true)
+        .await?;
+
+    let spend_addr = SpendAddress::from_unique_pubkey(&second_note.unique_pubkey());
+    let auditor_dir = TempDir::new()?;
+    let audit = client
+        .follow_spend(spend_addr, false, None, None, auditor_dir.path(), None)
+        .await?;
+    println!("Audited from {spend_addr:?}: {audit:?}");
+
+    // the change of both wallets, someone's 100 and the royalties
+    assert_eq!(audit.utxos.len(), 5);
+    assert_eq!(audit.total_utxo_value, supply);
+    assert_eq!(audit.total_royalties, NanoTokens::from(100));
+    assert_eq!(audit.total_unfollowed_value, NanoTokens::zero());
+    audit.supply_check(supply)?;
+    assert!(audit.supply_check(NanoTokens::from(999)).is_err());
+
+    Ok(())
+}
@@ -8,21 +8,37 @@
 
 mod common;
 
-use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use crate::common::{
+    client::{get_all_rpc_addresses, get_gossip_client_and_wallet, get_gossip_client_with_key},
+    get_all_peer_ids, random_content,
+};
 use assert_fs::TempDir;
 use eyre::{eyre, Result};
+use futures::StreamExt;
+use libp2p::kad::{KBucketKey, Quorum};
 use rand::Rng;
-use sn_client::{Error as ClientError, FilesDownload, FilesUpload, WalletClient};
+use sn_client::{
+    ClientEvent, DirPaymentStore, Error as ClientError, FilesApi, FilesDownload, FilesUpload,
+    ReadCfg, RegisterReadOptions, SessionCosts, WalletClient, MIN_TARGET_CHUNK_SIZE,
+};
 use sn_logging::LogBuilder;
-use sn_networking::{Error as NetworkError, GetRecordError};
+use sn_networking::{
+    sort_peers_by_key, Error as NetworkError, GetRecordError, CLOSE_GROUP_SIZE, REPLICATE_RANGE,
+};
 use sn_protocol::{
     error::Error as ProtocolError,
+    messages::REGISTER_CLAIM_GRACE_WINDOW,
     storage::{ChunkAddress, RegisterAddress},
     NetworkAddress,
 };
-use sn_transfers::{MainPubkey, NanoTokens, PaymentQuote};
-use std::collections::BTreeMap;
-use tokio::time::{sleep, Duration};
+use sn_registers::{EntryHash, Error as RegisterError, Permissions};
+use sn_transfers::{LocalWallet, MainPubkey, NanoTokens, PaymentQuote, SpendAddress, Transfer};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    num::NonZeroUsize,
+    sync::Arc,
+};
+use tokio::time::{sleep, timeout, Duration};
 use xor_name::XorName;
 
 #[tokio::test]
@@ -65,6 +81,53 @@ async fn storage_payment_succeeds() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn storage_payment_shared_through_payment_store() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_000_001;
+    let first_wallet_dir = TempDir::new()?;
+    let second_wallet_dir = TempDir::new()?;
+    let payment_store_dir = TempDir::new()?;
+
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), paying_wallet_balance).await?;
+    let (_, second_wallet) =
+        get_gossip_client_and_wallet(second_wallet_dir.path(), paying_wallet_balance).await?;
+
+    let mut rng = rand::thread_rng();
+    let random_content_addrs = (0..rng.gen_range(10..20))
+        .map(|_| {
+            sn_protocol::NetworkAddress::ChunkAddress(ChunkAddress::new(XorName::random(&mut rng)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut first_client = WalletClient::new(client.clone(), first_wallet);
+    first_client.set_payment_store(Arc::new(DirPaymentStore::new(payment_store_dir.path())?));
+    let ((storage_cost, _), _) = first_client
+        .pay_for_storage(random_content_addrs.clone().into_iter())
+        .await?;
+    assert!(storage_cost > NanoTokens::zero());
+
+    // a second wallet sharing the same store shall not pay for those addresses again
+    let mut second_client = WalletClient::new(client.clone(), second_wallet);
+    second_client.set_payment_store(Arc::new(DirPaymentStore::new(payment_store_dir.path())?));
+    let balance_before = second_client.balance();
+    let ((storage_cost, royalties_fees), (payee_map, _)) = second_client
+        .pay_for_storage(random_content_addrs.clone().into_iter())
+        .await?;
+
+    assert_eq!(storage_cost, NanoTokens::zero());
+    assert_eq!(royalties_fees, NanoTokens::zero());
+    assert_eq!(second_client.balance(), balance_before);
+    assert_eq!(payee_map.len(), random_content_addrs.len());
+    for addr in &random_content_addrs {
+        assert!(second_client.get_payment_for_addr(addr).is_ok());
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn storage_payment_fails_with_insufficient_money() -> Result<()> {
     let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
@@ -208,6 +271,616 @@ async fn storage_payment_chunk_upload_succeeds() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn storage_payment_chunk_upload_broadcasts_its_progress() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut events = client.events_channel();
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let mut expected_chunks: BTreeSet<_> = chunks.iter().map(|(name, _)| *name).collect();
+
+    // a single batch for the whole upload
+    let mut files_upload = FilesUpload::new(files_api).set_batch_size(chunks.len());
+    files_upload.upload_chunks(chunks).await?;
+
+    let mut paid = false;
+    let mut batch_completed = false;
+    while !expected_chunks.is_empty() || !batch_completed {
+        let event = timeout(Duration::from_secs(10), events.recv())
+            .await
+            .map_err(|_| eyre!("Missing events, chunks not reported {expected_chunks:?}"))??;
+        match event {
+            ClientEvent::PaymentMade { total, .. } => {
+                assert!(!paid, "Paid more than once for a single batch");
+                assert!(total > NanoTokens::zero());
+                paid = true;
+            }
+            ClientEvent::ChunkStored { addr, cost } => {
+                assert!(paid, "Chunk {addr:?} stored before being paid for");
+                assert!(cost > NanoTokens::zero());
+                assert!(expected_chunks.remove(addr.xorname()));
+            }
+            ClientEvent::UploadBatchCompleted {
+                batch,
+                total_batches,
+            } => {
+                assert!(paid, "Batch completed before being paid for");
+                assert_eq!((batch, total_batches), (1, 1));
+                batch_completed = true;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunk_upload_breaks_down_payments_by_payee() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let balance_before = paying_wallet.balance();
+    drop(paying_wallet);
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let chunks_len = chunks.len();
+    assert!(chunks_len > 1);
+
+    let mut files_upload = FilesUpload::new(files_api);
+    files_upload.upload_chunks(chunks).await?;
+
+    let payees = files_upload.get_upload_payee_breakdown();
+    assert!(!payees.is_empty());
+    assert_eq!(
+        payees.iter().map(|payee| payee.chunks).sum::<usize>(),
+        chunks_len
+    );
+    let storage_cost: u64 = payees
+        .iter()
+        .map(|payee| payee.storage_cost.as_nano())
+        .sum();
+    let royalties: u64 = payees.iter().map(|payee| payee.royalties.as_nano()).sum();
+    assert_eq!(
+        storage_cost,
+        files_upload.get_upload_storage_cost().as_nano()
+    );
+    assert_eq!(royalties, files_upload.get_upload_royalty_fees().as_nano());
+
+    let balance_after = files_upload.get_upload_final_balance();
+    assert_eq!(
+        storage_cost + royalties,
+        balance_before.as_nano() - balance_after.as_nano()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_session_costs_match_the_wallet_spendings() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let balance_before = paying_wallet.balance();
+    drop(paying_wallet);
+    // funding the wallet is no fee
+    assert_eq!(client.session_costs().total(), NanoTokens::zero());
+    let mut events = client.events_channel();
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let names: Vec<_> = chunks.iter().map(|(name, _)| *name).collect();
+    assert!(names.len() > 1);
+    let (first_half, second_half) = names.split_at(names.len() / 2);
+
+    let _ = files_api.pay_for_chunks(first_half.to_vec()).await?;
+    let ((_, _, balance_after), _) = files_api.pay_for_chunks(second_half.to_vec()).await?;
+
+    let costs = client.session_costs();
+    assert_eq!(costs.payments, 2);
+    assert!(costs.storage_cost > NanoTokens::zero());
+    assert!(costs.royalties > NanoTokens::zero());
+    assert_eq!(
+        costs.total().as_nano(),
+        balance_before.as_nano() - balance_after.as_nano()
+    );
+
+    // the events carry the running totals
+    let mut last_session: Option<SessionCosts> = None;
+    while last_session.map(|session| session.payments) != Some(2) {
+        let event = timeout(Duration::from_secs(10), events.recv())
+            .await
+            .map_err(|_| eyre!("Missing payment events"))??;
+        if let ClientEvent::PaymentMade { session, .. } = event {
+            last_session = Some(session);
+        }
+    }
+    assert_eq!(last_session, Some(costs));
+
+    assert_eq!(client.reset_session_costs(), costs);
+    assert_eq!(client.session_costs().total(), NanoTokens::zero());
+    assert_eq!(client.session_costs().payments, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunk_download_stream_yields_the_file_in_order() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let (files_api, content_bytes, file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    assert!(chunks.len() > 2);
+
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+
+    // leave out one of the data chunks, for the stream to fail midway
+    let (missing_chunk, _) = chunks
+        .iter()
+        .find(|(name, _)| name != file_addr.xorname())
+        .cloned()
+        .ok_or_else(|| eyre!("No data chunk"))?;
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload
+        .upload_chunks(
+            chunks
+                .iter()
+                .filter(|(name, _)| *name != missing_chunk)
+                .cloned()
+                .collect(),
+        )
+        .await?;
+
+    // a single chunk in flight at a time
+    let mut files_download = FilesDownload::new(files_api.clone()).set_batch_size(1);
+    let mut stream = files_download.download_stream(file_addr).await?;
+    let mut failed = false;
+    while let Some(block) = stream.next().await {
+        assert!(!failed, "The stream went on after an error");
+        failed = block.is_err();
+    }
+    assert!(failed, "The stream did not fail on the missing chunk");
+
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload.upload_chunks(chunks).await?;
+
+    let mut files_download = FilesDownload::new(files_api).set_batch_size(2);
+    let mut stream = files_download.download_stream(file_addr).await?;
+    let mut streamed = Vec::new();
+    let mut blocks = 0;
+    while let Some(block) = stream.next().await {
+        streamed.extend_from_slice(&block?);
+        blocks += 1;
+    }
+    assert!(blocks > 1);
+    assert_eq!(streamed, content_bytes.to_vec());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunk_upload_with_target_chunk_size_succeeds() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let mut files_api = FilesApi::new(client.clone(), paying_wallet_dir.to_path_buf());
+
+    // sizes beyond the bounds are rejected
+    assert!(files_api
+        .set_target_chunk_size(MIN_TARGET_CHUNK_SIZE - 1)
+        .is_err());
+    assert!(files_api
+        .set_target_chunk_size(FilesApi::max_chunk_size() + 1)
+        .is_err());
+    assert_eq!(files_api.target_chunk_size(), None);
+
+    let chunk_size = 16 * 1024;
+    files_api.set_target_chunk_size(chunk_size)?;
+
+    let mut content = vec![0; 10 * chunk_size + 100];
+    rand::thread_rng().fill(&mut content[..]);
+    let file_path = chunks_dir.path().join("content");
+    std::fs::write(&file_path, &content)?;
+    let (file_addr, _data_map, _file_size, chunks) =
+        files_api.chunk_file_with_target_size(&file_path, chunks_dir.path(), true)?;
+
+    // all the chunks but the data map are about the target size, as random data doesn't compress
+    for (name, path) in &chunks {
+        if name != file_addr.xorname() {
+            let size = std::fs::metadata(path)?.len() as usize;
+            assert!(size <= chunk_size + 1024, "chunk of {size} bytes");
+        }
+    }
+
+    println!("Paying for {} random addresses...", chunks.len());
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload.upload_chunks(chunks).await?;
+
+    let mut files_download = FilesDownload::new(files_api.clone());
+    let downloaded = files_download.download_file(file_addr, None).await?;
+    assert_eq!(downloaded, content);
+
+    // a range spanning the first two segments, of three chunks each
+    let (position, length) = (3 * chunk_size - 100, 200);
+    let mut files_download = FilesDownload::new(files_api);
+    let range = files_download
+        .download_from(file_addr, position, length)
+        .await?;
+    assert_eq!(range, content[position..position + length]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunks_fetched_in_batch() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let mut files_api = FilesApi::new(client.clone(), paying_wallet_dir.to_path_buf());
+
+    // small chunks, so the file is split into at least 50 of them
+    let chunk_size = MIN_TARGET_CHUNK_SIZE;
+    files_api.set_target_chunk_size(chunk_size)?;
+    let mut content = vec![0; 50 * chunk_size];
+    rand::thread_rng().fill(&mut content[..]);
+    let file_path = chunks_dir.path().join("content");
+    std::fs::write(&file_path, &content)?;
+    let (_file_addr, _data_map, _file_size, chunks) =
+        files_api.chunk_file_with_target_size(&file_path, chunks_dir.path(), true)?;
+    assert!(chunks.len() >= 50, "only {} chunks", chunks.len());
+    let addrs: Vec<_> = chunks
+        .iter()
+        .map(|(name, _)| ChunkAddress::new(*name))
+        .take(50)
+        .collect();
+
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    let mut files_upload = FilesUpload::new(files_api);
+    files_upload.upload_chunks(chunks).await?;
+
+    let fetched = client.get_chunks(&addrs, 8).await?;
+    assert_eq!(fetched.len(), addrs.len());
+    for (chunk, address) in fetched.iter().zip(&addrs) {
+        assert_eq!(chunk.address(), address);
+    }
+
+    // a chunk that was never stored is reported, the others are still handed back
+    let missing = ChunkAddress::new(XorName::random(&mut rand::thread_rng()));
+    let mut with_missing = addrs.clone();
+    with_missing.insert(10, missing);
+    match client.get_chunks(&with_missing, 8).await {
+        Err(ClientError::ChunksNotFetched { failed, fetched }) => {
+            assert_eq!(failed, vec![missing]);
+            assert_eq!(fetched.len(), with_missing.len());
+            assert!(fetched[10].is_none());
+            assert_eq!(fetched.iter().flatten().count(), addrs.len());
+        }
+        other => {
+            return Err(eyre!(
+                "Expected the missing chunk to be reported, got {other:?}"
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunk_provenance_is_within_close_group() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let all_peers = get_all_peer_ids(&get_all_rpc_addresses()?).await?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload.upload_chunks(chunks.clone()).await?;
+
+    for (name, _) in chunks {
+        let address = ChunkAddress::new(name);
+        let (chunk, holders) = client.get_chunk_with_provenance(address).await?;
+        assert_eq!(chunk.address(), &address);
+        assert!(!holders.is_empty());
+
+        let record_key = NetworkAddress::from_chunk_address(address).to_record_key();
+        let close_group = sort_peers_by_key(
+            &all_peers,
+            &KBucketKey::from(record_key.to_vec()),
+            CLOSE_GROUP_SIZE,
+        )?;
+        for holder in holders {
+            assert!(
+                close_group.contains(&&holder),
+                "Chunk {address:?} was served by {holder:?}, which is not in its close group"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_chunk_read_beyond_its_replicas_fails_cleanly() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+
+    let mut files_upload = FilesUpload::new(files_api);
+    files_upload.upload_chunks(chunks.clone()).await?;
+
+    // the nodes replicate a chunk as soon as they store it, so how many of them hold it right
+    // after the upload is a race. Asking for more copies than the nodes replicate it to is
+    // what a `Quorum::All` read of a chunk with missing replicas comes down to.
+    let address = ChunkAddress::new(chunks[0].0);
+    let unreachable_quorum =
+        NonZeroUsize::new(REPLICATE_RANGE + 1).ok_or_else(|| eyre!("Quorum must not be zero"))?;
+    let read_all = ReadCfg {
+        quorum: Quorum::N(unreachable_quorum),
+        re_attempt: false,
+        timeout: None,
+    };
+    let result = timeout(
+        Duration::from_secs(120),
+        client.get_chunk_with_cfg(address, false, read_all),
+    )
+    .await?;
+    match result {
+        Err(ClientError::Network(NetworkError::GetRecordError(
+            GetRecordError::NotEnoughCopies { expected, got, .. },
+        ))) => {
+            println!(
+                "Reading {address:?} from all its holders failed, got {got}/{expected} copies"
+            );
+            assert_eq!(expected, REPLICATE_RANGE + 1);
+            assert!(
+                got >= 1,
+                "the holders of {address:?} should have returned it"
+            );
+            assert!(got < expected);
+        }
+        other => {
+            return Err(eyre!(
+                "Reading {address:?} from more holders than it has should fail with \
+                 NotEnoughCopies, got {other:?}"
+            ))
+        }
+    }
+
+    // the default quorum still reads it
+    let chunk = client.get_chunk(address, false).await?;
+    assert_eq!(chunk.address(), &address);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_reads_of_missing_records_time_out_within_budget() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let client = get_gossip_client_with_key(bls::SecretKey::random()).await;
+    let mut rng = rand::thread_rng();
+    // shorter than the retries of a missing record, so the reads can't fail any other way
+    let budget = Duration::from_secs(2);
+    // leeway for the timer to fire and the error to be mapped
+    let max_elapsed = budget + Duration::from_secs(1);
+
+    let chunk_addr = ChunkAddress::new(XorName::random(&mut rng));
+    let read_cfg = ReadCfg::new(Quorum::One).with_timeout(budget);
+    let start = std::time::Instant::now();
+    match client.get_chunk_with_cfg(chunk_addr, false, read_cfg).await {
+        Err(ClientError::OperationTimedOut { addr, elapsed }) => {
+            assert_eq!(addr, NetworkAddress::from_chunk_address(chunk_addr));
+            assert!(elapsed >= budget);
+        }
+        other => return Err(eyre!("Unexpected result reading {chunk_addr:?}: {other:?}")),
+    }
+    assert!(start.elapsed() < max_elapsed);
+
+    let register_addr = RegisterAddress::new(XorName::random(&mut rng), client.signer_pk());
+    let options = RegisterReadOptions::default().with_timeout(budget);
+    let start = std::time::Instant::now();
+    match client.get_register(register_addr, options).await {
+        Err(ClientError::OperationTimedOut { addr, .. }) => {
+            assert_eq!(addr, NetworkAddress::from_register_address(register_addr));
+        }
+        Err(err) => return Err(eyre!("Unexpected error reading {register_addr:?}: {err:?}")),
+        Ok(_) => return Err(eyre!("Read a Register never created at {register_addr:?}")),
+    }
+    assert!(start.elapsed() < max_elapsed);
+
+    let spend_addr = SpendAddress::new(XorName::random(&mut rng));
+    let read_cfg = ReadCfg::new(Quorum::Majority).with_timeout(budget);
+    let start = std::time::Instant::now();
+    match client
+        .get_spend_from_network_with_cfg(spend_addr, read_cfg)
+        .await
+    {
+        Err(ClientError::OperationTimedOut { addr, .. }) => {
+            assert_eq!(addr, NetworkAddress::from_spend_address(spend_addr));
+        }
+        other => return Err(eyre!("Unexpected result reading {spend_addr:?}: {other:?}")),
+    }
+    assert!(start.elapsed() < max_elapsed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_not_made_for_already_stored_chunks() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+
+    let mut files_upload = FilesUpload::new(files_api.clone()).set_verify_store(true);
+    files_upload.upload_chunks(chunks.clone()).await?;
+
+    // delete the local payment records, so paying again can't rely on them, only on the nodes
+    // reporting the chunks as already stored
+    let mut paying_wallet = LocalWallet::load_from(paying_wallet_dir.path())?;
+    let paid: Vec<XorName> = paying_wallet
+        .cached_payments()
+        .map(|(name, _)| *name)
+        .collect();
+    assert!(!paid.is_empty());
+    for name in &paid {
+        let _ = paying_wallet.remove_payment_transaction(name);
+    }
+    paying_wallet.deposit_and_store_to_disk(&vec![])?;
+    let balance_before = paying_wallet.balance();
+
+    // quote every chunk again rather than probing for them first, to go through the payment
+    let mut files_upload = FilesUpload::new(files_api.clone())
+        .set_verify_store(true)
+        .set_skip_existing(false);
+    files_upload.upload_chunks(chunks.clone()).await?;
+
+    assert_eq!(files_upload.get_upload_storage_cost(), NanoTokens::zero());
+    assert_eq!(files_upload.get_upload_royalty_fees(), NanoTokens::zero());
+    assert_eq!(files_upload.get_already_stored_chunks().len(), chunks.len());
+    assert!(files_upload.get_failed_chunks().is_empty());
+    let paying_wallet = LocalWallet::load_from(paying_wallet_dir.path())?;
+    assert_eq!(paying_wallet.balance(), balance_before);
+    assert_eq!(paying_wallet.cached_payments().count(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_not_made_when_uploading_a_file_again() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 50_000_000_000_002;
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+
+    let (files_api, _content_bytes, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload.upload_chunks(chunks.clone()).await?;
+    assert!(files_upload.get_already_stored_chunks().is_empty());
+    let balance_after_first_upload = files_upload.get_upload_final_balance();
+
+    // wait for the chunks to be replicated to their whole close group
+    sleep(Duration::from_secs(10)).await;
+
+    let mut files_upload = FilesUpload::new(files_api.clone());
+    files_upload.upload_chunks(chunks.clone()).await?;
+
+    assert_eq!(files_upload.get_upload_storage_cost(), NanoTokens::zero());
+    assert_eq!(files_upload.get_upload_royalty_fees(), NanoTokens::zero());
+    assert!(files_upload.get_upload_payee_breakdown().is_empty());
+    assert_eq!(
+        files_upload.get_already_stored_chunks(),
+        chunks.iter().map(|(name, _)| *name).collect::<HashSet<_>>()
+    );
+    assert_eq!(files_api.wallet()?.balance(), balance_after_first_upload);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn storage_payment_chunk_upload_fails_if_no_tokens_sent() -> Result<()> {
     let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
@@ -246,12 +919,14 @@ async fn storage_payment_chunk_upload_fails_if_no_tokens_sent() -> Result<()> {
 
     println!("Reading {content_addr:?} expected to fail");
     let mut files_download = FilesDownload::new(files_api);
+    // the read is retried, the last attempt telling why it failed
     assert!(
         matches!(
             files_download.download_file(content_addr, None).await,
-            Err(ClientError::Network(NetworkError::GetRecordError(
-                GetRecordError::RecordNotFound
-            )))
+            Err(ClientError::Network(err)) if matches!(
+                err.last_cause(),
+                NetworkError::GetRecordError(GetRecordError::RecordNotFound)
+            )
         ),
         "read bytes should fail as we didn't store them"
     );
@@ -259,6 +934,63 @@ async fn storage_payment_chunk_upload_fails_if_no_tokens_sent() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn storage_payment_register_batch_creation_shares_payment() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let paying_wallet_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let mut rng = rand::thread_rng();
+    let metas: Vec<_> = (0..10).map(|_| XorName::random(&mut rng)).collect();
+
+    println!("Creating a batch of {} Registers ...", metas.len());
+    let (results, storage_cost, royalties_fees) = client
+        .create_registers_batch(&metas, &mut wallet_client, true)
+        .await?;
+    println!("Paid {storage_cost} and {royalties_fees} royalties for the batch");
+    assert_eq!(results.len(), metas.len());
+
+    // every payment of the batch shall come from the same spend(s)
+    let mut parent_spends = BTreeSet::new();
+    for meta in &metas {
+        let address = RegisterAddress::new(*meta, client.signer_pk());
+        let xorname = NetworkAddress::from_register_address(address)
+            .as_xorname()
+            .ok_or_else(|| eyre!("Register address has no xorname"))?;
+        let payment = wallet_client
+            .mut_wallet()
+            .get_cached_payment_for_xorname(&xorname)
+            .ok_or_else(|| eyre!("No payment cached for Register {address:?}"))?;
+        if let Transfer::NetworkRoyalties(redemptions) = &payment.royalties.0 {
+            parent_spends.extend(redemptions.iter().map(|r| r.parent_spend));
+        }
+    }
+    println!(
+        "Payments of the batch came from {} spend(s)",
+        parent_spends.len()
+    );
+    assert!(!parent_spends.is_empty());
+    assert!(parent_spends.len() <= 2);
+
+    for (meta, result) in metas.iter().zip(results) {
+        let register = result?;
+        let address = RegisterAddress::new(*meta, client.signer_pk());
+        assert_eq!(register.address(), &address);
+
+        let retrieved_reg = client
+            .get_register(address, RegisterReadOptions::default())
+            .await?;
+        assert_eq!(register.read(), retrieved_reg.read());
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn storage_payment_register_creation_succeeds() -> Result<()> {
     let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
@@ -284,7 +1016,9 @@ async fn storage_payment_register_creation_succeeds() -> Result<()> {
         .create_and_pay_for_register(xor_name, &mut wallet_client, true)
         .await?;
 
-    let retrieved_reg = client.get_register(address).await?;
+    let retrieved_reg = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
 
     assert_eq!(register.read(), retrieved_reg.read());
 
@@ -293,13 +1027,326 @@ async fn storage_payment_register_creation_succeeds() -> Result<()> {
     register.write(&random_entry)?;
     register.sync(&mut wallet_client, true).await?;
 
-    let retrieved_reg = client.get_register(address).await?;
+    let retrieved_reg = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
 
     assert_eq!(retrieved_reg.read().iter().next().unwrap().1, random_entry);
 
     Ok(())
 }
 
+#[tokio::test]
+async fn storage_payment_register_ownership_transfer() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let paying_wallet_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let old_owner = client.signer_pk();
+    let new_owner_sk = bls::SecretKey::random();
+    let new_owner = new_owner_sk.public_key();
+    let new_owner_client = get_gossip_client_with_key(new_owner_sk).await;
+
+    let mut rng = rand::thread_rng();
+    let xor_name = XorName::random(&mut rng);
+    let address = RegisterAddress::new(xor_name, old_owner);
+    let (mut register, _cost, _royalties_fees) = client
+        .create_and_pay_for_register(xor_name, &mut wallet_client, true)
+        .await?;
+    let old_entry = rng.gen::<[u8; 32]>().to_vec();
+    register.write_online(&old_entry, true).await?;
+    // a replica of the old owner, which won't learn of the transfer
+    let mut stale_register = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
+
+    println!("Handing the Register {address:?} over to {new_owner:?} ...");
+    register.transfer_ownership(new_owner, true).await?;
+    assert_eq!(register.owner(), new_owner);
+
+    // the Register is still read at the same address, with the entries of the old owner
+    let retrieved_reg = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
+    assert_eq!(retrieved_reg.owner(), new_owner);
+    assert_eq!(retrieved_reg.read(), register.read());
+
+    // the old owner can't write anymore, whether it knows of the transfer or not
+    let stale_entry = rng.gen::<[u8; 32]>().to_vec();
+    assert!(matches!(
+        register.write(&stale_entry),
+        Err(ClientError::Register(RegisterError::AccessDenied(key))) if key == old_owner
+    ));
+    assert!(matches!(
+        stale_register.write_online(&stale_entry, true).await,
+        Err(ClientError::Register(RegisterError::AccessDenied(key))) if key == old_owner
+    ));
+
+    // while the new owner writes atop the entries of the old one
+    let mut new_owner_register = new_owner_client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
+    let new_entry = rng.gen::<[u8; 32]>().to_vec();
+    new_owner_register.write_online(&new_entry, true).await?;
+
+    let retrieved_reg = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
+    let entries: Vec<_> = retrieved_reg
+        .read()
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect();
+    assert_eq!(entries, vec![new_entry]);
+    assert_eq!(retrieved_reg.size(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_register_creation_race_spends_one_payment() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let first_wallet_dir = TempDir::new()?;
+    let second_wallet_dir = TempDir::new()?;
+
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), paying_wallet_balance).await?;
+    let (_, second_wallet) =
+        get_gossip_client_and_wallet(second_wallet_dir.path(), paying_wallet_balance).await?;
+    // the same user creating the same Register from two clients
+    let other_client = get_gossip_client_with_key(client.signer().clone()).await;
+    let mut first_wallet_client = WalletClient::new(client.clone(), first_wallet);
+    let mut second_wallet_client = WalletClient::new(other_client.clone(), second_wallet);
+    let first_balance_before = first_wallet_client.balance();
+    let second_balance_before = second_wallet_client.balance();
+
+    let xor_name = XorName::random(&mut rand::thread_rng());
+    println!("Racing two clients to create the Register {xor_name:?} ...");
+    let (first_result, second_result) = tokio::join!(
+        client.create_and_pay_for_register(xor_name, &mut first_wallet_client, false),
+        other_client.create_and_pay_for_register(xor_name, &mut second_wallet_client, false)
+    );
+
+    // only the outcomes matter here, the created Register is checked below
+    let first_result = first_result.map(|_| ());
+    let second_result = second_result.map(|_| ());
+
+    let (winner, winner_spent, loser_spent, loser_result) = match (&first_result, &second_result) {
+        (Ok(_), Err(_)) => (
+            &client,
+            first_wallet_client.balance() < first_balance_before,
+            second_wallet_client.balance() < second_balance_before,
+            second_result,
+        ),
+        (Err(_), Ok(_)) => (
+            &other_client,
+            second_wallet_client.balance() < second_balance_before,
+            first_wallet_client.balance() < first_balance_before,
+            first_result,
+        ),
+        _ => {
+            return Err(eyre!(
+                "Exactly one client shall create the Register, got {first_result:?} and {second_result:?}"
+            ))
+        }
+    };
+    assert!(winner_spent, "the winner shall have paid for the Register");
+    assert!(
+        !loser_spent,
+        "the loser shall not have paid for the Register"
+    );
+
+    match loser_result {
+        Err(ClientError::RegisterClaimed {
+            claimant,
+            nonce,
+            remaining,
+        }) => {
+            assert_eq!(claimant, winner.signer_pk());
+            assert_eq!(nonce, winner.register_claim_nonce());
+            assert!(remaining <= REGISTER_CLAIM_GRACE_WINDOW);
+        }
+        other => return Err(eyre!("Unexpected result for the loser: {other:?}")),
+    }
+
+    let address = RegisterAddress::new(xor_name, client.signer_pk());
+    let _ = client
+        .get_register(address, RegisterReadOptions::default())
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_register_get_or_create_race_pays_once() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let first_wallet_dir = TempDir::new()?;
+    let second_wallet_dir = TempDir::new()?;
+
+    let (client, first_wallet) =
+        get_gossip_client_and_wallet(first_wallet_dir.path(), paying_wallet_balance).await?;
+    let (_, second_wallet) =
+        get_gossip_client_and_wallet(second_wallet_dir.path(), paying_wallet_balance).await?;
+    // the same user opening the same Register from two clients
+    let other_client = get_gossip_client_with_key(client.signer().clone()).await;
+    let mut first_wallet_client = WalletClient::new(client.clone(), first_wallet);
+    let mut second_wallet_client = WalletClient::new(other_client.clone(), second_wallet);
+    let first_balance_before = first_wallet_client.balance();
+    let second_balance_before = second_wallet_client.balance();
+
+    let xor_name = XorName::random(&mut rand::thread_rng());
+    println!("Racing two clients to get or create the Register {xor_name:?} ...");
+    let (first_result, second_result) = tokio::join!(
+        client.get_or_create_register(
+            xor_name,
+            &mut first_wallet_client,
+            Permissions::new_owner_only(),
+            false
+        ),
+        other_client.get_or_create_register(
+            xor_name,
+            &mut second_wallet_client,
+            Permissions::new_owner_only(),
+            false
+        )
+    );
+    let (first_reg, first_created, first_cost) = first_result?;
+    let (second_reg, second_created, second_cost) = second_result?;
+
+    let address = RegisterAddress::new(xor_name, client.signer_pk());
+    assert_eq!(first_reg.address(), &address);
+    assert_eq!(second_reg.address(), &address);
+    assert!(
+        first_created != second_created,
+        "Exactly one client shall create the Register"
+    );
+
+    let first_paid = first_wallet_client.balance() < first_balance_before;
+    let second_paid = second_wallet_client.balance() < second_balance_before;
+    assert!(
+        first_paid != second_paid,
+        "Exactly one client shall pay for the Register"
+    );
+    assert_eq!(first_paid, first_created);
+    assert_eq!(first_cost == NanoTokens::zero(), !first_paid);
+    assert_eq!(second_cost == NanoTokens::zero(), !second_paid);
+
+    // once created, the Register is only retrieved
+    let (reg, created, cost) = client
+        .get_or_create_register(
+            xor_name,
+            &mut first_wallet_client,
+            Permissions::new_owner_only(),
+            false,
+        )
+        .await?;
+    assert_eq!(reg.address(), &address);
+    assert!(!created);
+    assert_eq!(cost, NanoTokens::zero());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_register_write_verified_with_its_entry() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let paying_wallet_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let mut rng = rand::thread_rng();
+    let xor_name = XorName::random(&mut rng);
+    let address = RegisterAddress::new(xor_name, client.signer_pk());
+    let (mut register, _cost, _royalties_fees) = client
+        .create_and_pay_for_register(xor_name, &mut wallet_client, true)
+        .await?;
+
+    register.write_online(&rng.gen::<[u8; 32]>(), true).await?;
+    let written: BTreeSet<_> = register.read().into_iter().map(|(hash, _)| hash).collect();
+
+    let _ = client
+        .verify_register_stored_with(address, &written, Quorum::Majority)
+        .await?;
+
+    let unknown = BTreeSet::from([EntryHash(rng.gen())]);
+    match client
+        .verify_register_stored_with(address, &unknown, Quorum::Majority)
+        .await
+    {
+        Err(ClientError::RegisterEntriesMissing {
+            address: missing_from,
+            missing,
+        }) => {
+            assert_eq!(*missing_from, address);
+            assert_eq!(missing, unknown);
+        }
+        other => panic!("Expected the unknown entry to be missing, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_payment_register_concurrent_writes_are_all_read_merging_all() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_payments");
+
+    let paying_wallet_balance = 65_000_000_000;
+    let paying_wallet_dir = TempDir::new()?;
+
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), paying_wallet_balance).await?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+
+    let xor_name = XorName::random(&mut rand::thread_rng());
+    let address = RegisterAddress::new(xor_name, client.signer_pk());
+    let _ = client
+        .create_and_pay_for_register(xor_name, &mut wallet_client, true)
+        .await?;
+
+    // the owner writing to the Register from several clients at once
+    let writers_count = 4;
+    let mut writes = Vec::new();
+    for writer in 0..writers_count {
+        let writer_client = get_gossip_client_with_key(client.signer().clone()).await;
+        let mut register = writer_client
+            .get_register(address, RegisterReadOptions::default())
+            .await?;
+        let entry = format!("entry from writer #{writer}").into_bytes();
+        writes.push(async move { register.write_online(&entry, false).await.map(|_| entry) });
+    }
+    println!("Writing to the Register {address:?} from {writers_count} clients ...");
+    let mut written = BTreeSet::new();
+    for result in futures::future::join_all(writes).await {
+        let _ = written.insert(result?);
+    }
+
+    // let the writes settle on the close group
+    sleep(Duration::from_secs(5)).await;
+
+    let merged = client
+        .get_register(address, RegisterReadOptions::merge_all())
+        .await?;
+    let read: BTreeSet<_> = merged.read().into_iter().map(|(_, entry)| entry).collect();
+    assert!(
+        written.is_subset(&read),
+        "the merged read shall hold every entry written, got {read:?}"
+    );
+
+    Ok(())
+}
+
 #[tokio::test]
 #[ignore = "Test currently invalid as we always try to pay and upload registers if none found... need to check if this test is valid"]
 async fn storage_payment_register_creation_and_mutation_fails() -> Result<()> {
@@ -340,7 +1387,7 @@ async fn storage_payment_register_creation_and_mutation_fails() -> Result<()> {
 
     sleep(Duration::from_secs(5)).await;
     assert!(matches!(
-        client.get_register(address).await,
+        client.get_register(address, RegisterReadOptions::default()).await,
         Err(ClientError::Protocol(ProtocolError::RegisterNotFound(addr))) if *addr == address
     ));
 
@@ -0,0 +1,147 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use assert_fs::TempDir;
+use common::{
+    client::{get_gossip_client, get_gossip_client_and_wallet},
+    random_content,
+};
+use eyre::Result;
+use sn_client::{FilesUpload, WalletClient};
+use sn_logging::LogBuilder;
+use sn_protocol::{storage::ChunkAddress, NetworkAddress};
+use sn_transfers::{LocalWallet, NanoTokens, StorageVoucherGrant, WalletError};
+use std::time::{Duration, SystemTime};
+use xor_name::XorName;
+
+#[tokio::test]
+async fn uploads_paid_with_a_voucher_are_accounted_for_by_the_funder() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_vouchers");
+
+    let funder_dir = TempDir::new()?;
+    let (funder_client, funder_wallet) =
+        get_gossip_client_and_wallet(funder_dir.path(), 50_000_000_000).await?;
+    let mut funder_wallet_client = WalletClient::new(funder_client.clone(), funder_wallet);
+
+    let max_amount = NanoTokens::from(10_000_000_000);
+    let grant = funder_wallet_client
+        .issue_storage_voucher(
+            max_amount,
+            SystemTime::now() + Duration::from_secs(3600),
+            None,
+            true,
+        )
+        .await?;
+
+    // the uploader only ever gets the grant, as handed over in hex
+    let grant = StorageVoucherGrant::from_hex(&grant.to_hex()?)?;
+    let uploader_client = get_gossip_client().await;
+    let uploader_dir = TempDir::new()?;
+    let voucher_wallet = uploader_client
+        .redeem_storage_voucher(&grant, uploader_dir.path())
+        .await?;
+    assert_eq!(voucher_wallet.balance(), max_amount);
+    assert_eq!(
+        voucher_wallet.storage_voucher()?,
+        Some(grant.voucher.clone())
+    );
+
+    drop(voucher_wallet);
+
+    // the chunks are paid for with the voucher wallet, being the uploader's wallet
+    let chunks_dir = TempDir::new()?;
+    let (files_api, _content, _head_addr, chunks) = random_content(
+        &uploader_client,
+        uploader_dir.to_path_buf(),
+        chunks_dir.path(),
+    )?;
+    println!("Uploading {} chunks paid with the voucher...", chunks.len());
+    FilesUpload::new(files_api)
+        .upload_chunks(chunks.clone())
+        .await?;
+
+    let mut stored = 0;
+    for (name, _) in &chunks {
+        if uploader_client
+            .get_chunk(ChunkAddress::new(*name), false)
+            .await
+            .is_ok()
+        {
+            stored += 1;
+        }
+    }
+    assert_eq!(stored, chunks.len(), "all the chunks should be stored");
+
+    let usage = funder_client.storage_voucher_usage(&grant.voucher).await?;
+    println!("Voucher usage: {usage:?}");
+    assert_eq!(usage.chunks_paid, stored);
+    assert_eq!(usage.spent_otherwise, NanoTokens::zero());
+    assert_eq!(
+        usage.total_paid().as_nano() + usage.unspent.as_nano(),
+        max_amount.as_nano()
+    );
+    assert_eq!(
+        usage.unspent,
+        LocalWallet::try_load_from(uploader_dir.path())?.balance()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn vouchers_only_pay_for_addresses_with_their_prefix() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("storage_vouchers");
+
+    let funder_dir = TempDir::new()?;
+    let (funder_client, funder_wallet) =
+        get_gossip_client_and_wallet(funder_dir.path(), 50_000_000_000).await?;
+    let mut funder_wallet_client = WalletClient::new(funder_client.clone(), funder_wallet);
+
+    let prefix = 0xab;
+    let grant = funder_wallet_client
+        .issue_storage_voucher(
+            NanoTokens::from(10_000_000_000),
+            SystemTime::now() + Duration::from_secs(3600),
+            Some(vec![prefix]),
+            true,
+        )
+        .await?;
+
+    let uploader_client = get_gossip_client().await;
+    let uploader_dir = TempDir::new()?;
+    let voucher_wallet = uploader_client
+        .redeem_storage_voucher(&grant, uploader_dir.path())
+        .await?;
+    let mut uploader_wallet_client = WalletClient::new(uploader_client, voucher_wallet);
+
+    let mut name = XorName::random(&mut rand::thread_rng());
+    name.0[0] = !prefix;
+    let result = uploader_wallet_client
+        .pay_for_storage(std::iter::once(NetworkAddress::ChunkAddress(
+            ChunkAddress::new(name),
+        )))
+        .await;
+    assert!(
+        matches!(result, Err(WalletError::StorageVoucherAddressNotAllowed(address)) if address == name),
+        "the payment should have been refused, got {result:?}"
+    );
+
+    name.0[0] = prefix;
+    let _cost = uploader_wallet_client
+        .pay_for_storage(std::iter::once(NetworkAddress::ChunkAddress(
+            ChunkAddress::new(name),
+        )))
+        .await?;
+
+    let usage = funder_client.storage_voucher_usage(&grant.voucher).await?;
+    assert_eq!(usage.chunks_paid, 1);
+
+    Ok(())
+}
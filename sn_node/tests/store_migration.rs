@@ -0,0 +1,72 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use assert_fs::TempDir;
+use bytes::Bytes;
+use eyre::Result;
+use libp2p::identity::{ed25519, Keypair};
+use sn_logging::LogBuilder;
+use sn_node::{export_store, import_store, NodeBuilder, RunningNode};
+use sn_protocol::storage::{try_serialize_record, Chunk, RecordKind};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::Path,
+};
+
+/// Start a lone local node with the secret key and records found in its root dir.
+fn start_node(root_dir: &Path) -> Result<RunningNode> {
+    let secret_key = std::fs::read(root_dir.join("secret-key"))?;
+    let keypair = Keypair::ed25519_from_bytes(secret_key)?;
+    let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    let mut node_builder = NodeBuilder::new(keypair, addr, vec![], true, root_dir.to_path_buf());
+    node_builder.restore_records(true);
+    Ok(node_builder.build_and_run()?)
+}
+
+#[tokio::test]
+async fn migrated_node_keeps_its_identity_and_records() -> Result<()> {
+    let _log_guards = LogBuilder::init_single_threaded_tokio_test("store_migration");
+
+    let old_root_dir = TempDir::new()?;
+    let new_root_dir = TempDir::new()?;
+    let archive_dir = TempDir::new()?;
+    let archive = archive_dir.path().join("store.tar.zst");
+
+    // the records held by the node on the old machine
+    let record_store = old_root_dir.path().join("record_store");
+    std::fs::create_dir_all(&record_store)?;
+    for _ in 0..20 {
+        let chunk = Chunk::new(Bytes::from(rand::random::<[u8; 32]>().to_vec()));
+        let value = try_serialize_record(&chunk, RecordKind::Chunk)?;
+        let filename = hex::encode(chunk.network_address().to_record_key());
+        std::fs::write(record_store.join(filename), value)?;
+    }
+    let secret_key = ed25519::SecretKey::generate();
+    std::fs::write(old_root_dir.path().join("secret-key"), secret_key.as_ref())?;
+
+    let old_node = start_node(old_root_dir.path())?;
+    let old_records = old_node.get_all_record_addresses().await?;
+    assert_eq!(old_records.len(), 20);
+    let old_peer_id = old_node.peer_id();
+    drop(old_node);
+
+    let exported = export_store(old_root_dir.path(), &archive, true)?;
+    assert_eq!(exported.records, 20);
+
+    let imported = import_store(&archive, new_root_dir.path(), true)?;
+    assert_eq!(imported.imported, 20);
+    assert!(imported.skipped.is_empty());
+    assert!(imported.keypair_imported);
+
+    let new_node = start_node(new_root_dir.path())?;
+    assert_eq!(new_node.peer_id(), old_peer_id);
+    let new_records = new_node.get_all_record_addresses().await?;
+    assert_eq!(new_records, old_records);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::client::get_gossip_client;
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use sn_client::Client;
+use sn_logging::LogBuilder;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn subscriptions_receive_only_the_messages_of_their_topic() -> Result<()> {
+    let _log_guards =
+        LogBuilder::init_single_threaded_tokio_test("subscriptions_receive_only_their_topic");
+
+    let subscriber = get_gossip_client().await;
+    let publisher = get_gossip_client().await;
+
+    let first_topic = format!("TestTopic-{}", rand::random::<u64>());
+    let second_topic = format!("TestTopic-{}", rand::random::<u64>());
+    let mut first = subscriber.subscribe(first_topic.clone())?;
+    let mut second = subscriber.subscribe(second_topic.clone())?;
+    // a second subscription to the same topic gets all of its messages too
+    let mut first_again = subscriber.subscribe(first_topic.clone())?;
+
+    // give the subscriptions time to be gossiped over the mesh
+    sleep(Duration::from_secs(5)).await;
+
+    publisher.publish_on_topic(first_topic.clone(), Bytes::from_static(b"first msg"))?;
+    publisher.publish_on_topic(second_topic.clone(), Bytes::from_static(b"second msg"))?;
+
+    let wait = Duration::from_secs(30);
+    let received = timeout(wait, first.recv())
+        .await?
+        .ok_or_else(|| eyre!("No message on {first_topic}"))?;
+    assert_eq!(received, Bytes::from_static(b"first msg"));
+    let received = timeout(wait, first_again.recv())
+        .await?
+        .ok_or_else(|| eyre!("No message on {first_topic}"))?;
+    assert_eq!(received, Bytes::from_static(b"first msg"));
+    let received = timeout(wait, second.recv())
+        .await?
+        .ok_or_else(|| eyre!("No message on {second_topic}"))?;
+    assert_eq!(received, Bytes::from_static(b"second msg"));
+
+    // nothing else arrived on either topic
+    sleep(Duration::from_secs(2)).await;
+    assert!(first.try_recv().is_none());
+    assert!(first_again.try_recv().is_none());
+    assert!(second.try_recv().is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn signed_msgs_are_verified_by_their_subscribers() -> Result<()> {
+    let _log_guards =
+        LogBuilder::init_single_threaded_tokio_test("signed_msgs_are_verified_by_subscribers");
+
+    let subscriber = get_gossip_client().await;
+    let publisher = get_gossip_client().await;
+
+    let topic = format!("TestTopic-{}", rand::random::<u64>());
+    let mut subscription = subscriber.subscribe(topic.clone())?;
+    sleep(Duration::from_secs(5)).await;
+
+    publisher.publish_signed(topic.clone(), Bytes::from_static(b"signed msg"))?;
+
+    let msg = timeout(Duration::from_secs(30), subscription.recv())
+        .await?
+        .ok_or_else(|| eyre!("No message on {topic}"))?;
+    let (signer, payload) = Client::verify_gossip_msg(&topic, &msg)?;
+    assert_eq!(signer, publisher.signer_pk());
+    assert_eq!(payload, Bytes::from_static(b"signed msg"));
+    // the message can't be replayed on another topic
+    assert!(Client::verify_gossip_msg(&format!("{topic}-replayed"), &msg).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use sn_client::UploadManifest;
+use sn_logging::LogBuilder;
+use sn_protocol::storage::Chunk;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reconciling_an_interrupted_upload_completes_it() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("upload_reconcile");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let other_chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _head_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let (_, _, _, other_chunks) = random_content(
+        &client,
+        paying_wallet_dir.to_path_buf(),
+        other_chunks_dir.path(),
+    )?;
+    assert!(chunks.len() > 3);
+
+    // the upload got interrupted: the first chunk was stored, the second was paid for only,
+    // the third was neither, and the fourth was lost from the disk as well
+    let (stored, paid, unpaid, lost) = (
+        chunks[0].clone(),
+        chunks[1].clone(),
+        chunks[2].clone(),
+        chunks[3].clone(),
+    );
+    // a payment for a chunk of another upload
+    let unmatched = other_chunks[0].0;
+    let (_cost, (payees, _skipped)) = files_api
+        .pay_for_chunks(vec![stored.0, paid.0, unmatched])
+        .await?;
+    let payee_of = |name| {
+        payees
+            .iter()
+            .find_map(|(paid_name, payee)| (*paid_name == name).then_some(*payee))
+            .ok_or_else(|| eyre!("No payee for {name:?}"))
+    };
+    let chunk = Chunk::new(Bytes::from(std::fs::read(&stored.1)?));
+    files_api
+        .get_local_payment_and_upload_chunk(chunk, payee_of(stored.0)?, true)
+        .await?;
+    std::fs::remove_file(&lost.1)?;
+
+    let manifest_path = chunks_dir.path().join("upload.json");
+    UploadManifest::new(&[stored.clone(), paid.clone(), unpaid.clone(), lost.clone()])
+        .write(&manifest_path)?;
+    let manifest = UploadManifest::read(&manifest_path)?;
+
+    let plan = files_api.reconcile_upload(&manifest).await?;
+    assert_eq!(plan.complete, vec![stored.clone()]);
+    assert_eq!(
+        plan.to_push,
+        vec![(paid.0, paid.1.clone(), payee_of(paid.0)?)]
+    );
+    assert_eq!(plan.to_repay, vec![unpaid.clone()]);
+    assert_eq!(plan.lost, vec![lost.clone()]);
+    assert_eq!(plan.unmatched_payments, vec![unmatched]);
+    assert!(!plan.is_converged());
+
+    let payments_before = client.session_costs().payments;
+    let plan = files_api.apply_reconcile_plan(&manifest, &plan).await?;
+    assert!(plan.is_converged(), "Not converged: {plan:?}");
+    assert_eq!(plan.complete, vec![stored, paid, unpaid]);
+    assert_eq!(plan.lost, vec![lost]);
+    assert_eq!(plan.unmatched_payments, vec![unmatched]);
+    // only the chunk with no payment was paid for
+    assert_eq!(client.session_costs().payments, payments_before + 1);
+
+    client.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,160 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+mod common;
+
+use crate::common::{client::get_gossip_client_and_wallet, random_content};
+use assert_fs::TempDir;
+use bytes::Bytes;
+use eyre::{eyre, Result};
+use futures::StreamExt;
+use sn_client::{Error as ClientError, FilesUpload, VerificationStatus, WalletClient};
+use sn_logging::LogBuilder;
+use sn_protocol::{
+    storage::{Chunk, ChunkAddress},
+    NetworkAddress,
+};
+use std::{path::Path, time::Instant};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verify_chunk_stored_fails_for_chunks_not_on_the_network() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("verify_chunk_stored");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    let mut wallet_client = WalletClient::new(client.clone(), paying_wallet);
+    let _cost = wallet_client
+        .pay_for_storage(
+            chunks
+                .iter()
+                .map(|(name, _)| NetworkAddress::ChunkAddress(ChunkAddress::new(*name))),
+        )
+        .await?;
+    wallet_client.store_local_wallet()?;
+
+    // only the first chunk gets uploaded
+    let (stored, missing) = (chunks[0].clone(), chunks[1].clone());
+    FilesUpload::new(files_api.clone())
+        .upload_chunks(vec![stored.clone()])
+        .await?;
+
+    client.verify_chunk_stored(&read_chunk(&stored.1)?).await?;
+    let missing_chunk = read_chunk(&missing.1)?;
+    match client.verify_chunk_stored(&missing_chunk).await {
+        Err(ClientError::ChunkVerificationFailed { addr, source }) => {
+            assert_eq!(addr, missing_chunk.network_address());
+            let report = source
+                .chunk_replication_report()
+                .ok_or_else(|| eyre!("No replication report in {source:?}"))?;
+            assert!(!report.quorum_met);
+            assert!(report.holders_ok.is_empty(), "{report:?}");
+        }
+        other => panic!("Expected the verification of {missing:?} to fail, got {other:?}"),
+    }
+
+    let to_verify = vec![stored, missing.clone()];
+    let failed = client.verify_uploaded_chunks(&to_verify, 8).await?;
+    assert_eq!(failed, vec![missing.clone()]);
+
+    // uploading the failed chunks again is enough for all of them to verify
+    FilesUpload::new(files_api).upload_chunks(failed).await?;
+    let failed = client.verify_uploaded_chunks(&to_verify, 8).await?;
+    assert!(failed.is_empty(), "{failed:?} still fail to verify");
+    client.verify_chunk_stored(&missing_chunk).await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn verification_results_are_streamed_as_they_complete() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("verify_chunk_stored");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+    assert!(chunks.len() > 3);
+
+    // all but the last chunk get uploaded
+    let (missing, stored) = chunks.split_last().ok_or_else(|| eyre!("No chunks"))?;
+    FilesUpload::new(files_api)
+        .upload_chunks(stored.to_vec())
+        .await?;
+
+    // one chunk at a time, for the results to be spread over the whole verification
+    let start = Instant::now();
+    let mut results = client.verify_uploaded_chunks_stream(chunks.clone(), 1);
+    let mut arrivals = vec![];
+    let mut failed = vec![];
+    while let Some((name, path, status)) = results.next().await {
+        arrivals.push(start.elapsed());
+        match status {
+            VerificationStatus::Verified(_) => {}
+            VerificationStatus::Failed(ClientError::ChunkVerificationFailed { .. }) => {
+                failed.push((name, path));
+            }
+            other => panic!("Unexpected failure for {name:?}: {other:?}"),
+        }
+    }
+    let total = start.elapsed();
+
+    assert_eq!(arrivals.len(), chunks.len());
+    assert_eq!(failed, vec![missing.clone()]);
+    // rather than all at once at the end
+    assert!(
+        arrivals[0] < total / 2,
+        "The first result arrived after {:?}, out of {total:?}",
+        arrivals[0]
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunks_are_verified_from_memory_and_missing_files_are_told_apart() -> Result<()> {
+    let _log_guards = LogBuilder::init_multi_threaded_tokio_test("verify_chunk_stored");
+
+    let paying_wallet_dir = TempDir::new()?;
+    let chunks_dir = TempDir::new()?;
+    let (client, _paying_wallet) =
+        get_gossip_client_and_wallet(paying_wallet_dir.path(), 50_000_000_000_002).await?;
+    let (files_api, _content, _file_addr, chunks) =
+        random_content(&client, paying_wallet_dir.to_path_buf(), chunks_dir.path())?;
+
+    // only the first chunk gets uploaded
+    let (stored, missing) = (chunks[0].clone(), chunks[1].clone());
+    FilesUpload::new(files_api)
+        .upload_chunks(vec![stored.clone()])
+        .await?;
+
+    // the chunks are held in memory only from now on
+    let in_memory = vec![read_chunk(&stored.1)?, read_chunk(&missing.1)?];
+    std::fs::remove_file(&stored.1)?;
+    std::fs::remove_file(&missing.1)?;
+
+    let failed = client.verify_chunks(&in_memory, 8).await;
+    assert_eq!(failed, vec![missing.0]);
+
+    // verifying from the deleted files tells them apart from the chunks not stored
+    let mut results = client.verify_uploaded_chunks_stream(vec![stored, missing], 8);
+    while let Some((name, _path, status)) = results.next().await {
+        assert!(
+            matches!(status, VerificationStatus::FileMissing),
+            "Expected the file of {name:?} to be missing, got {status:?}"
+        );
+    }
+    Ok(())
+}
+
+fn read_chunk(path: &Path) -> Result<Chunk> {
+    Ok(Chunk::new(Bytes::from(std::fs::read(path)?)))
+}
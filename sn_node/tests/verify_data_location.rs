@@ -17,6 +17,7 @@ use crate::common::{
     get_all_peer_ids, node_restart,
 };
 use assert_fs::TempDir;
+use bytes::Bytes;
 use eyre::{eyre, Result};
 use libp2p::{
     kad::{KBucketKey, RecordKey},
@@ -28,7 +29,7 @@ use sn_logging::LogBuilder;
 use sn_networking::{sort_peers_by_key, CLOSE_GROUP_SIZE};
 use sn_protocol::{
     safenode_proto::{safe_node_client::SafeNodeClient, NodeInfoRequest, RecordAddressesRequest},
-    storage::ChunkAddress,
+    storage::{Chunk, ChunkAddress},
     NetworkAddress, PrettyPrintRecordKey,
 };
 use std::{
@@ -102,11 +103,15 @@ async fn verify_data_location() -> Result<()> {
         get_gossip_client_and_wallet(paying_wallet_dir.path(), PAYING_WALLET_INITIAL_BALANCE)
             .await?;
 
-    store_chunks(client, chunk_count, paying_wallet_dir.to_path_buf()).await?;
+    let stored_chunks =
+        store_chunks(client.clone(), chunk_count, paying_wallet_dir.to_path_buf()).await?;
 
     // Verify data location initially
     verify_location(&all_peers, &node_rpc_address).await?;
 
+    // Once replicated, the whole close group proves to hold each chunk
+    verify_replication_reports(&client, &stored_chunks).await?;
+
     // Churn nodes and verify the location of the data after VERIFICATION_DELAY
     let mut current_churn_count = 0;
 
@@ -306,13 +311,44 @@ async fn verify_location(all_peers: &Vec<PeerId>, node_rpc_addresses: &[SocketAd
     }
 }
 
-// Generate random Chunks and store them to the Network
-async fn store_chunks(client: Client, chunk_count: usize, wallet_dir: PathBuf) -> Result<()> {
+// Checks the replication report of each chunk lists all of its close group as holders
+async fn verify_replication_reports(client: &Client, chunks: &[Chunk]) -> Result<()> {
+    for chunk in chunks {
+        let report = client.verify_chunk_stored(chunk).await?;
+        println!(
+            "Chunk {:?} is held by {:?}, missing from {:?}",
+            chunk.name(),
+            report.holders_ok,
+            report.holders_missing
+        );
+        if !report.quorum_met || report.holders_ok.len() != CLOSE_GROUP_SIZE {
+            return Err(eyre!(
+                "Chunk {:?} is held by {} nodes of its close group, missing from {:?}",
+                chunk.name(),
+                report.holders_ok.len(),
+                report.holders_missing
+            ));
+        }
+    }
+    println!(
+        "All the {} chunks are held by their close group",
+        chunks.len()
+    );
+    Ok(())
+}
+
+// Generate random Chunks and store them to the Network, returning the stored Chunks
+async fn store_chunks(
+    client: Client,
+    chunk_count: usize,
+    wallet_dir: PathBuf,
+) -> Result<Vec<Chunk>> {
     let start = Instant::now();
     let mut rng = OsRng;
     let files_api = FilesApi::new(client, wallet_dir);
 
     let mut uploaded_chunks_count = 0;
+    let mut stored_chunks = vec![];
     loop {
         if uploaded_chunks_count >= chunk_count {
             break;
@@ -343,6 +379,9 @@ async fn store_chunks(client: Client, chunk_count: usize, wallet_dir: PathBuf) -
         let mut file_upload = FilesUpload::new(files_api.clone())
             .set_show_holders(true)
             .set_verify_store(false);
+        for (_, path) in chunks.iter() {
+            stored_chunks.push(Chunk::new(Bytes::from(std::fs::read(path)?)));
+        }
         file_upload.upload_chunks(chunks).await?;
         uploaded_chunks_count += 1;
 
@@ -357,5 +396,5 @@ async fn store_chunks(client: Client, chunk_count: usize, wallet_dir: PathBuf) -
     // to make sure the last chunk was stored
     tokio::time::sleep(Duration::from_secs(10)).await;
 
-    Ok(())
+    Ok(stored_chunks)
 }
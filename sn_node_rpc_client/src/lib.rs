@@ -8,7 +8,7 @@ use libp2p::{Multiaddr, PeerId};
 use sn_protocol::safenode_proto::{
     safe_node_client::SafeNodeClient, GossipsubPublishRequest, GossipsubSubscribeRequest,
     GossipsubUnsubscribeRequest, NetworkInfoRequest, NodeInfoRequest, RecordAddressesRequest,
-    RestartRequest, StopRequest, UpdateRequest,
+    RestartRequest, StopRequest, StoreStatsRequest, UpdateRequest,
 };
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -36,11 +36,19 @@ pub struct RecordAddress {
     pub key: RecordKey,
 }
 
+#[derive(Debug, Clone)]
+pub struct StoreStats {
+    pub chunks_bytes: u64,
+    pub spends_bytes: u64,
+    pub registers_bytes: u64,
+}
+
 #[async_trait]
 pub trait RpcActions {
     async fn node_info(&self) -> Result<NodeInfo>;
     async fn network_info(&self) -> Result<NetworkInfo>;
     async fn record_addresses(&self) -> Result<Vec<RecordAddress>>;
+    async fn store_stats(&self) -> Result<StoreStats>;
     async fn gossipsub_subscribe(&self, topic: &str) -> Result<()>;
     async fn gossipsub_unsubscribe(&self, topic: &str) -> Result<()>;
     async fn gossipsub_publish(&self, topic: &str, message: &str) -> Result<()>;
@@ -117,6 +125,19 @@ impl RpcActions for RpcClient {
         Ok(record_addresses)
     }
 
+    async fn store_stats(&self) -> Result<StoreStats> {
+        let mut client = SafeNodeClient::connect(self.endpoint.clone()).await?;
+        let response = client
+            .store_stats(Request::new(StoreStatsRequest {}))
+            .await?;
+        let store_stats = response.get_ref();
+        Ok(StoreStats {
+            chunks_bytes: store_stats.chunks_bytes,
+            spends_bytes: store_stats.spends_bytes,
+            registers_bytes: store_stats.registers_bytes,
+        })
+    }
+
     async fn gossipsub_subscribe(&self, topic: &str) -> Result<()> {
         let mut client = SafeNodeClient::connect(self.endpoint.clone()).await?;
         let _response = client
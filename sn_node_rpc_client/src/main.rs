@@ -47,6 +47,9 @@ enum Cmd {
     /// Retrieve information about the node's connections to the network
     #[clap(name = "netinfo")]
     Netinfo,
+    /// Retrieve the bytes stored by the node for each kind of record
+    #[clap(name = "store-stats")]
+    StoreStats,
     /// Start listening for node events.
     /// Note this blocks the app and it will print events as they are broadcasted by the node
     #[clap(name = "events")]
@@ -128,6 +131,7 @@ async fn main() -> Result<()> {
     match opt.cmd {
         Cmd::Info => node_info(addr).await,
         Cmd::Netinfo => network_info(addr).await,
+        Cmd::StoreStats => store_stats(addr).await,
         Cmd::Events => node_events(addr).await,
         Cmd::TransfersEvents {
             sk,
@@ -191,6 +195,19 @@ pub async fn network_info(addr: SocketAddr) -> Result<()> {
     Ok(())
 }
 
+pub async fn store_stats(addr: SocketAddr) -> Result<()> {
+    let endpoint = format!("https://{addr}");
+    let client = RpcClient::new(&endpoint);
+    let store_stats = client.store_stats().await?;
+
+    println!("Bytes stored by the node:");
+    println!("Chunks: {}", store_stats.chunks_bytes);
+    println!("Spends: {}", store_stats.spends_bytes);
+    println!("Registers: {}", store_stats.registers_bytes);
+
+    Ok(())
+}
+
 pub async fn node_events(addr: SocketAddr) -> Result<()> {
     let endpoint = format!("https://{addr}");
     let mut client = SafeNodeClient::connect(endpoint).await?;
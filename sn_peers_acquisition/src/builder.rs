@@ -0,0 +1,254 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+#[cfg(feature = "network-contacts")]
+use crate::NetworkContactsRetry;
+use crate::{error::Result, get_peers_from_args, PeersArgs, RoutingTableSnapshot};
+use libp2p::Multiaddr;
+use std::path::PathBuf;
+#[cfg(feature = "network-contacts")]
+use std::time::Duration;
+#[cfg(feature = "network-contacts")]
+use url::Url;
+
+/// Builds the [`PeersArgs`] to obtain the bootstrap peers with, for applications not using clap.
+///
+/// Unset options keep the defaults of the command line, apart from the environment variables
+/// backing some of the options, which are not read here.
+///
+/// ```no_run
+/// # async fn example() -> sn_peers_acquisition::error::Result<()> {
+/// use sn_peers_acquisition::{parse_peer_addr, PeersArgsBuilder};
+///
+/// let peers = PeersArgsBuilder::default()
+///     .peer(parse_peer_addr("1.2.3.4:12000")?)
+///     .ignore_env_peers(true)
+///     .get_peers()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PeersArgsBuilder {
+    args: PeersArgs,
+}
+
+impl PeersArgsBuilder {
+    /// Set to indicate this is the first node in a new network, which has no peers to use.
+    pub fn first(mut self, first: bool) -> Self {
+        self.args.first = first;
+        self
+    }
+
+    /// Add a peer to use for bootstrap.
+    pub fn peer(mut self, peer: Multiaddr) -> Self {
+        self.args.peers.push(peer);
+        self
+    }
+
+    /// Add peers to use for bootstrap.
+    pub fn peers(mut self, peers: impl IntoIterator<Item = Multiaddr>) -> Self {
+        self.args.peers.extend(peers);
+        self
+    }
+
+    /// Read more peers from a local file, one per line.
+    pub fn peers_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.args.peers_file = Some(path.into());
+        self
+    }
+
+    /// Drop the peers not accepting a connection.
+    pub fn verify_peers(mut self, verify_peers: bool) -> Self {
+        self.args.verify_peers = verify_peers;
+        self
+    }
+
+    /// Use at most this number of peers, zero meaning no limit.
+    pub fn max_bootstrap_peers(mut self, max_bootstrap_peers: usize) -> Self {
+        self.args.max_bootstrap_peers = max_bootstrap_peers;
+        self
+    }
+
+    /// Don't read peers from the `SAFE_PEERS` environment variable.
+    pub fn ignore_env_peers(mut self, ignore_env_peers: bool) -> Self {
+        self.args.ignore_env_peers = ignore_env_peers;
+        self
+    }
+
+    /// Bootstrap from the peers of the given routing table snapshot, unless peers are given.
+    pub fn routing_table_snapshot(mut self, snapshot: RoutingTableSnapshot) -> Self {
+        self.args.set_routing_table_snapshot(snapshot);
+        self
+    }
+
+    /// Add a URL to fetch the network contacts from, tried in the order they were added.
+    #[cfg(feature = "network-contacts")]
+    pub fn network_contacts_url(mut self, url: Url) -> Self {
+        self.args.network_contacts_url.push(url);
+        self
+    }
+
+    /// Always fetch the network contacts, instead of using the peers cached from a previous run.
+    #[cfg(feature = "network-contacts")]
+    pub fn ignore_peer_cache(mut self, ignore_peer_cache: bool) -> Self {
+        self.args.ignore_peer_cache = ignore_peer_cache;
+        self
+    }
+
+    /// How long the cached peers are used for before fetching the network contacts again.
+    #[cfg(feature = "network-contacts")]
+    pub fn peer_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.args.peer_cache_ttl = ttl.as_secs();
+        self
+    }
+
+    /// How fetching the network contacts is retried. The jitter is always added.
+    #[cfg(feature = "network-contacts")]
+    pub fn network_contacts_retry(mut self, retry: NetworkContactsRetry) -> Self {
+        self.args.network_contacts_retries = retry.retries;
+        self.args.network_contacts_backoff_ms = retry.initial_backoff.as_millis() as u64;
+        self.args.network_contacts_max_backoff_ms = retry.max_backoff.as_millis() as u64;
+        self.args.network_contacts_timeout_ms = retry.timeout.as_millis() as u64;
+        self
+    }
+
+    /// Have the host names of the peers resolved through the DNS-over-HTTPS server at `url`,
+    /// instead of the system resolver, by the transport dialing them.
+    #[cfg(feature = "network-contacts")]
+    pub fn doh_server(mut self, url: Url) -> Self {
+        self.args.doh_server = Some(url);
+        self
+    }
+
+    /// The built arguments.
+    pub fn build(self) -> PeersArgs {
+        self.args
+    }
+
+    /// Get the peers, the same way as from the command line arguments.
+    pub async fn get_peers(self) -> Result<Vec<Multiaddr>> {
+        get_peers_from_args(self.build()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_peer_addr;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        peers: PeersArgs,
+    }
+
+    #[test]
+    fn defaults_match_the_command_line() {
+        let parsed = Cli::parse_from(["test"]).peers;
+        assert_eq!(PeersArgsBuilder::default().build(), parsed);
+    }
+
+    #[test]
+    fn built_args_match_the_command_line() -> Result<()> {
+        let parsed = Cli::parse_from([
+            "test",
+            "--peer",
+            "1.2.3.4:12000",
+            "--peer",
+            "1.2.3.5:12000",
+            "--peers-file",
+            "/tmp/peers",
+            "--verify-peers",
+            "--max-bootstrap-peers",
+            "10",
+        ])
+        .peers;
+
+        let built = PeersArgs::builder()
+            .peer(parse_peer_addr("1.2.3.4:12000")?)
+            .peers([parse_peer_addr("1.2.3.5:12000")?])
+            .peers_file("/tmp/peers")
+            .verify_peers(true)
+            .max_bootstrap_peers(10)
+            .build();
+        assert_eq!(built, parsed);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn first_node_gets_no_peers() -> Result<()> {
+        let peers = PeersArgsBuilder::default()
+            .first(true)
+            .peer(parse_peer_addr("1.2.3.4:12000")?)
+            .get_peers()
+            .await?;
+        assert!(peers.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn built_peers_are_returned() -> Result<()> {
+        let peer = parse_peer_addr("1.2.3.4:12000")?;
+        let peers = PeersArgsBuilder::default()
+            .peer(peer.clone())
+            .ignore_env_peers(true)
+            .get_peers()
+            .await?;
+        assert_eq!(peers, vec![peer]);
+        Ok(())
+    }
+
+    #[cfg(not(any(feature = "local-discovery", feature = "network-contacts")))]
+    #[tokio::test]
+    async fn no_peers_errors() {
+        let result = PeersArgsBuilder::default()
+            .ignore_env_peers(true)
+            .get_peers()
+            .await;
+        assert!(matches!(result, Err(crate::error::Error::PeersNotObtained)));
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[test]
+    fn network_contacts_options_match_the_command_line() -> Result<()> {
+        let parsed = Cli::parse_from([
+            "test",
+            "--network-contacts-url",
+            "http://127.0.0.1:1/network-contacts",
+            "--ignore-peer-cache",
+            "--peer-cache-ttl",
+            "60",
+            "--network-contacts-retries",
+            "5",
+            "--network-contacts-backoff-ms",
+            "200",
+            "--network-contacts-max-backoff-ms",
+            "2000",
+            "--network-contacts-timeout-ms",
+            "5000",
+        ])
+        .peers;
+
+        let built = PeersArgsBuilder::default()
+            .network_contacts_url(Url::parse("http://127.0.0.1:1/network-contacts")?)
+            .ignore_peer_cache(true)
+            .peer_cache_ttl(Duration::from_secs(60))
+            .network_contacts_retry(NetworkContactsRetry {
+                retries: 5,
+                initial_backoff: Duration::from_millis(200),
+                max_backoff: Duration::from_millis(2000),
+                jitter: true,
+                timeout: Duration::from_millis(5000),
+            })
+            .build();
+        assert_eq!(built, parsed);
+        Ok(())
+    }
+}
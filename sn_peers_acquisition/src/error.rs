@@ -1,3 +1,4 @@
+use std::{fmt, path::PathBuf, time::Duration};
 use thiserror::Error;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -7,16 +8,82 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     #[error("Could not parse the supplied multiaddr or socket address")]
     InvalidPeerAddr,
-    #[error("Could not obtain network contacts from {0} after {1} retries")]
-    NetworkContactsUnretrievable(String, usize),
+    #[error("Invalid port in the peer address {0}")]
+    InvalidPeerPort(String),
+    #[error("IPv6 address {0} has a scope id, which can't be used as a peer address")]
+    ScopedIpv6PeerAddr(String),
+    #[error(
+        "Could not obtain network contacts from {url} after {} attempts in {elapsed:?}: {}",
+        attempts.len(),
+        list_attempts(attempts)
+    )]
+    NetworkContactsUrlUnretrievable {
+        url: String,
+        elapsed: Duration,
+        /// Why each attempt failed.
+        attempts: Vec<FetchFailure>,
+    },
+    #[error("Could not obtain network contacts from any of the URLs: {}", .0.join(", "))]
+    NetworkContactsUnretrievable(Vec<String>),
     #[error("No valid multaddr was present in the contacts file at {0}")]
     NoMultiAddrObtainedFromNetworkContacts(String),
+    #[error("The JSON network contacts at {0} hold no valid peer")]
+    NoValidPeerInJsonNetworkContacts(String),
+    #[error("Could not parse the JSON network contacts at {0}: {1}")]
+    InvalidJsonNetworkContacts(String, String),
+    #[error("Could not read the peers file at {0:?}: {1}")]
+    PeersFileUnreadable(PathBuf, String),
+    #[error("No valid peer was present in the peers file at {0:?}")]
+    NoValidPeerInPeersFile(PathBuf),
     #[error("Could not obtain peers through any available options")]
     PeersNotObtained,
+    #[error("Could not read or write the peer cache: {0}")]
+    PeerCacheIo(#[from] std::io::Error),
+    #[error("Could not serialise or deserialise the peer cache: {0}")]
+    PeerCacheSerialisation(#[from] serde_json::Error),
+    #[error("Could not read or write the routing table snapshot at {0:?}: {1}")]
+    RoutingTableSnapshot(PathBuf, String),
     #[cfg(feature = "network-contacts")]
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
     #[cfg(feature = "network-contacts")]
     #[error(transparent)]
     UrlParseError(#[from] url::ParseError),
+    #[cfg(feature = "network-contacts")]
+    #[error("Invalid URL {value:?} in the {var} environment variable: {source}")]
+    InvalidUrlInEnv {
+        var: &'static str,
+        value: String,
+        source: url::ParseError,
+    },
+}
+
+/// Why an attempt to fetch the network contacts failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FetchFailure {
+    /// The attempt didn't complete within the given timeout.
+    Timeout(Duration),
+    /// The server responded with this non-success HTTP status.
+    Status(u16),
+    /// Any other failure, e.g. the connection was refused.
+    Other(String),
+}
+
+impl fmt::Display for FetchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout(timeout) => write!(f, "timed out after {timeout:?}"),
+            Self::Status(status) => write!(f, "status {status}"),
+            Self::Other(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+fn list_attempts(attempts: &[FetchFailure]) -> String {
+    attempts
+        .iter()
+        .enumerate()
+        .map(|(index, failure)| format!("attempt {}: {failure}", index + 1))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
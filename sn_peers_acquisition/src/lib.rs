@@ -6,12 +6,40 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod builder;
 pub mod error;
+#[cfg(feature = "network-contacts")]
+mod network_contacts;
+mod peer_cache;
+mod peers_file;
+#[cfg(feature = "network-contacts")]
+mod retry;
+mod routing_table_snapshot;
+mod verify;
 
+pub use crate::builder::PeersArgsBuilder;
+pub use crate::peer_cache::{PeerCache, DEFAULT_PEER_CACHE_TTL};
+#[cfg(feature = "network-contacts")]
+pub use crate::retry::{
+    NetworkContactsRetry, DEFAULT_NETWORK_CONTACTS_BACKOFF, DEFAULT_NETWORK_CONTACTS_MAX_BACKOFF,
+    DEFAULT_NETWORK_CONTACTS_RETRIES, DEFAULT_NETWORK_CONTACTS_TIMEOUT,
+};
+pub use crate::routing_table_snapshot::{
+    RoutingTableSnapshot, MAX_ROUTING_TABLE_SNAPSHOT_PEERS, ROUTING_TABLE_SNAPSHOT_DIAL_TIMEOUT,
+};
+pub use crate::verify::{is_connection_checked, responsive_peers, PEER_VERIFICATION_TIMEOUT};
+
+#[cfg(feature = "network-contacts")]
+use crate::error::FetchFailure;
 use crate::error::{Error, Result};
 use clap::Args;
-use libp2p::{multiaddr::Protocol, Multiaddr};
-use rand::{seq::SliceRandom, thread_rng};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
 use tracing::*;
 #[cfg(feature = "network-contacts")]
 use url::Url;
@@ -20,14 +48,51 @@ use url::Url;
 // URL containing the multi-addresses of the bootstrap nodes.
 const NETWORK_CONTACTS_URL: &str = "https://sn-testnet.s3.eu-west-2.amazonaws.com/network-contacts";
 
-#[cfg(feature = "network-contacts")]
-// The maximum number of retries to be performed while trying to fetch the network contacts file.
-const MAX_NETWORK_CONTACTS_GET_RETRIES: usize = 3;
-
 /// The name of the environment variable that can be used to pass peers to the node.
 pub const SAFE_PEERS_ENV: &str = "SAFE_PEERS";
 
-#[derive(Args, Debug)]
+/// The default maximum number of bootstrap peers returned, see `--max-bootstrap-peers`.
+pub const DEFAULT_MAX_BOOTSTRAP_PEERS: usize = 25;
+
+/// The name of the environment variable that can be used to set the URL(s) to fetch the network
+/// contacts from, as a comma-separated list.
+pub const SAFE_NETWORK_CONTACTS_URL_ENV: &str = "SAFE_NETWORK_CONTACTS_URL";
+
+/// Where a bootstrap peer was obtained from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerSource {
+    /// The `--peer` argument.
+    Arg,
+    /// The `--peers-file` argument.
+    PeersFile,
+    /// The `SAFE_PEERS` environment variable.
+    Env,
+    /// The network contacts, fetched from their URL.
+    NetworkContacts,
+    /// The peers cached from the network contacts by a previous run.
+    PeerCache,
+    /// The routing table of the node, as it was before it restarted.
+    RoutingTableSnapshot,
+}
+
+impl fmt::Display for PeerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Arg => write!(f, "--peer"),
+            Self::PeersFile => write!(f, "--peers-file"),
+            Self::Env => write!(f, "{SAFE_PEERS_ENV}"),
+            Self::NetworkContacts => write!(f, "network contacts"),
+            Self::PeerCache => write!(f, "peer cache"),
+            Self::RoutingTableSnapshot => write!(f, "routing table snapshot"),
+        }
+    }
+}
+
+/// The arguments peers are obtained with.
+///
+/// These are parsed from the command line, or built with a [`PeersArgsBuilder`] when not using
+/// clap.
+#[derive(Args, Debug, Clone, PartialEq, Eq)]
 pub struct PeersArgs {
     /// Set to indicate this is the first node in a new network
     ///
@@ -41,6 +106,9 @@ pub struct PeersArgs {
     /// '/ip4/1.2.3.4/tcp/1200/tcp/p2p/12D3KooWRi6wF7yxWLuPSNskXc6kQ5cJ6eaymeMbCRdTnMesPgFx' where
     /// `1.2.3.4` is the IP, `1200` is the port and the (optional) last part is the peer ID.
     ///
+    /// A socket address such as '1.2.3.4:1200', or a host name and port such as
+    /// 'mynode.example.com:1200', is also accepted.
+    ///
     /// This argument can be provided multiple times to connect to multiple peers.
     ///
     /// Alternatively, the `SAFE_PEERS` environment variable can provide a comma-separated peer
@@ -51,35 +119,320 @@ pub struct PeersArgs {
     #[clap(long = "peer", value_name = "multiaddr", value_delimiter = ',', value_parser = parse_peer_addr, conflicts_with = "first")]
     pub peers: Vec<Multiaddr>,
 
+    /// A local file listing the peer(s) to use for bootstrap, one per line.
+    ///
+    /// The peers are in the same format as for the `--peer` argument. Blank lines and lines
+    /// starting with '#' are ignored.
+    ///
+    /// If both the `--peer` and `--peers-file` arguments are used, the specified peers will be
+    /// combined.
+    #[clap(long, value_name = "PATH", conflicts_with = "first")]
+    pub peers_file: Option<PathBuf>,
+
+    /// Check that the bootstrap peers accept a connection before using them, dropping the
+    /// unresponsive ones.
+    ///
+    /// Each peer is given 2 seconds to respond. If none of them does, they are all used anyway.
+    #[clap(long, conflicts_with = "first")]
+    pub verify_peers: bool,
+
+    /// The maximum number of bootstrap peers to use, picked at random. Zero means no limit.
+    ///
+    /// The peers given with `--peer` are always picked before the others, e.g. the ones from the
+    /// network contacts.
+    #[clap(
+        long,
+        value_name = "N",
+        default_value_t = DEFAULT_MAX_BOOTSTRAP_PEERS,
+        conflicts_with = "first"
+    )]
+    pub max_bootstrap_peers: usize,
+
+    /// Don't read peers from the `SAFE_PEERS` environment variable.
+    ///
+    /// Only settable through the `PeersArgsBuilder`, e.g. for an application embedding the
+    /// client that doesn't want its environment to interfere.
+    #[clap(skip)]
+    pub(crate) ignore_env_peers: bool,
+
+    /// The snapshot of the node's routing table to bootstrap from, if any.
+    ///
+    /// Set by the node, which knows its root dir, see `PeersArgs::set_routing_table_snapshot`.
+    #[clap(skip)]
+    pub(crate) routing_table_snapshot: Option<RoutingTableSnapshot>,
+
     /// Specify the URL to fetch the network contacts from.
     ///
+    /// This argument can be provided multiple times, e.g. to list mirrors of the contacts file.
+    /// The URLs are tried in order, until one of them provides the contacts.
+    ///
+    /// Alternatively, the `SAFE_NETWORK_CONTACTS_URL` environment variable can provide a
+    /// comma-separated URL list, which is only used if this argument is not.
+    ///
     /// This argument will be overridden if the "peers" argument is set or if the `local-discovery`
     /// feature flag is enabled.
     #[cfg(feature = "network-contacts")]
+    #[clap(
+        long = "network-contacts-url",
+        value_name = "URL",
+        conflicts_with = "first"
+    )]
+    pub network_contacts_url: Vec<Url>,
+
+    /// Always fetch the network contacts, instead of using the peers cached from a previous run.
+    #[cfg(feature = "network-contacts")]
     #[clap(long, conflicts_with = "first")]
-    pub network_contacts_url: Option<Url>,
+    pub ignore_peer_cache: bool,
+
+    /// How long, in seconds, the cached peers are used for before fetching the network contacts
+    /// again.
+    #[cfg(feature = "network-contacts")]
+    #[clap(long, value_name = "SECONDS", default_value_t = DEFAULT_PEER_CACHE_TTL.as_secs())]
+    pub peer_cache_ttl: u64,
+
+    /// The number of times fetching the network contacts from a URL is retried, after a first
+    /// failed attempt.
+    #[cfg(feature = "network-contacts")]
+    #[clap(
+        long,
+        value_name = "COUNT",
+        env = "SAFE_NETWORK_CONTACTS_RETRIES",
+        default_value_t = DEFAULT_NETWORK_CONTACTS_RETRIES
+    )]
+    pub network_contacts_retries: usize,
+
+    /// The delay, in milliseconds, before retrying to fetch the network contacts for the first
+    /// time. The delay doubles after each retry, and a random jitter of up to half of it is added.
+    #[cfg(feature = "network-contacts")]
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "SAFE_NETWORK_CONTACTS_BACKOFF_MS",
+        default_value_t = DEFAULT_NETWORK_CONTACTS_BACKOFF.as_millis() as u64
+    )]
+    pub network_contacts_backoff_ms: u64,
+
+    /// The maximum delay, in milliseconds, between two retries to fetch the network contacts,
+    /// jitter excluded.
+    #[cfg(feature = "network-contacts")]
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "SAFE_NETWORK_CONTACTS_MAX_BACKOFF_MS",
+        default_value_t = DEFAULT_NETWORK_CONTACTS_MAX_BACKOFF.as_millis() as u64
+    )]
+    pub network_contacts_max_backoff_ms: u64,
+
+    /// The time, in milliseconds, an attempt to fetch the network contacts is given before it
+    /// is counted as failed, and retried.
+    #[cfg(feature = "network-contacts")]
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "SAFE_NETWORK_CONTACTS_TIMEOUT_MS",
+        default_value_t = DEFAULT_NETWORK_CONTACTS_TIMEOUT.as_millis() as u64
+    )]
+    pub network_contacts_timeout_ms: u64,
+
+    /// Resolve the host names of the `dns`, `dns4` and `dns6` addresses dialed, the ones of the
+    /// peers included, through this DNS-over-HTTPS server, instead of the system resolver, e.g.
+    /// where DNS is filtered.
+    ///
+    /// The server is queried in the JSON format, e.g. 'https://cloudflare-dns.com/dns-query'.
+    /// The system resolver is used for the host names the server fails to resolve.
+    #[cfg(feature = "network-contacts")]
+    #[clap(long, value_name = "URL")]
+    pub doh_server: Option<Url>,
+}
+
+/// The same defaults as when parsed from an empty command line.
+impl Default for PeersArgs {
+    fn default() -> Self {
+        Self {
+            first: false,
+            peers: vec![],
+            peers_file: None,
+            verify_peers: false,
+            max_bootstrap_peers: DEFAULT_MAX_BOOTSTRAP_PEERS,
+            ignore_env_peers: false,
+            routing_table_snapshot: None,
+            #[cfg(feature = "network-contacts")]
+            network_contacts_url: vec![],
+            #[cfg(feature = "network-contacts")]
+            ignore_peer_cache: false,
+            #[cfg(feature = "network-contacts")]
+            peer_cache_ttl: DEFAULT_PEER_CACHE_TTL.as_secs(),
+            #[cfg(feature = "network-contacts")]
+            network_contacts_retries: DEFAULT_NETWORK_CONTACTS_RETRIES,
+            #[cfg(feature = "network-contacts")]
+            network_contacts_backoff_ms: DEFAULT_NETWORK_CONTACTS_BACKOFF.as_millis() as u64,
+            #[cfg(feature = "network-contacts")]
+            network_contacts_max_backoff_ms: DEFAULT_NETWORK_CONTACTS_MAX_BACKOFF.as_millis()
+                as u64,
+            #[cfg(feature = "network-contacts")]
+            network_contacts_timeout_ms: DEFAULT_NETWORK_CONTACTS_TIMEOUT.as_millis() as u64,
+            #[cfg(feature = "network-contacts")]
+            doh_server: None,
+        }
+    }
+}
+
+impl PeersArgs {
+    /// Build the arguments without clap, starting from the defaults.
+    pub fn builder() -> PeersArgsBuilder {
+        PeersArgsBuilder::default()
+    }
+
+    /// Bootstrap from the peers of the given routing table snapshot, unless peers are given
+    /// explicitly.
+    pub fn set_routing_table_snapshot(&mut self, snapshot: RoutingTableSnapshot) {
+        self.routing_table_snapshot = Some(snapshot);
+    }
+
+    /// The cache the peers obtained from the network contacts should be saved to, once they
+    /// are known to be reachable.
+    ///
+    /// Returns `None` if the peers don't come from the network contacts, e.g. they were given
+    /// through `--peer`, `--peers-file` or `SAFE_PEERS`, or if the cache is to be ignored.
+    #[cfg(feature = "network-contacts")]
+    pub fn peer_cache(&self) -> Option<PeerCache> {
+        if self.first
+            || self.ignore_peer_cache
+            || !self.peers.is_empty()
+            || self.peers_file.is_some()
+            || cfg!(feature = "local-discovery")
+            || (!self.ignore_env_peers && std::env::var(SAFE_PEERS_ENV).is_ok())
+        {
+            return None;
+        }
+        let contacts_url = match self.network_contacts_urls() {
+            Ok(urls) => urls
+                .iter()
+                .map(|url| url.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            Err(err) => {
+                warn!("Not caching the peers, as the network contacts URL is invalid: {err}");
+                return None;
+            }
+        };
+        let Some(path) = PeerCache::default_path() else {
+            warn!("Not caching the peers, as there is no data dir to cache them in");
+            return None;
+        };
+        let mut cache = PeerCache::new(path, contacts_url);
+        cache.set_ttl(std::time::Duration::from_secs(self.peer_cache_ttl));
+        Some(cache)
+    }
+
+    /// The peers never come from the network contacts without the `network-contacts` feature.
+    #[cfg(not(feature = "network-contacts"))]
+    pub fn peer_cache(&self) -> Option<PeerCache> {
+        None
+    }
+
+    /// The same arguments, fetching the network contacts rather than using the cached peers,
+    /// e.g. once the cached peers turned out to be unreachable.
+    #[cfg(feature = "network-contacts")]
+    pub fn ignoring_peer_cache(mut self) -> Self {
+        self.ignore_peer_cache = true;
+        self
+    }
+
+    /// There is no peer cache to ignore without the `network-contacts` feature.
+    #[cfg(not(feature = "network-contacts"))]
+    pub fn ignoring_peer_cache(self) -> Self {
+        self
+    }
+
+    /// The URLs to fetch the network contacts from, in order of precedence:
+    /// * The `--network-contacts-url` arguments.
+    /// * The `SAFE_NETWORK_CONTACTS_URL` environment variable.
+    /// * The default network contacts URL.
+    #[cfg(feature = "network-contacts")]
+    pub fn network_contacts_urls(&self) -> Result<Vec<Url>> {
+        if !self.network_contacts_url.is_empty() {
+            return Ok(self.network_contacts_url.clone());
+        }
+        if let Ok(value) = std::env::var(SAFE_NETWORK_CONTACTS_URL_ENV) {
+            let urls = parse_network_contacts_urls_env(&value)?;
+            if !urls.is_empty() {
+                info!("Using the network contacts URL(s) from {SAFE_NETWORK_CONTACTS_URL_ENV}");
+                return Ok(urls);
+            }
+        }
+        Ok(vec![Url::parse(NETWORK_CONTACTS_URL)?])
+    }
+
+    /// How fetching the network contacts is retried.
+    #[cfg(feature = "network-contacts")]
+    pub fn network_contacts_retry(&self) -> NetworkContactsRetry {
+        NetworkContactsRetry {
+            retries: self.network_contacts_retries,
+            initial_backoff: std::time::Duration::from_millis(self.network_contacts_backoff_ms),
+            max_backoff: std::time::Duration::from_millis(self.network_contacts_max_backoff_ms),
+            jitter: true,
+            timeout: std::time::Duration::from_millis(self.network_contacts_timeout_ms),
+        }
+    }
 }
 
-/// Gets the peers based on the arguments provided.
+/// Gets the peers based on the arguments provided, parsed from the command line or built with
+/// a [`PeersArgsBuilder`].
 ///
 /// If the `--first` flag is used, no peers will be provided.
 ///
 /// Otherwise, peers are obtained in the following order of precedence:
-/// * The `--peer` argument.
+/// * The `--peer` and `--peers-file` arguments, combined.
+/// * The peers of the routing table snapshot, if set and some of them accept a connection
+///   within `ROUTING_TABLE_SNAPSHOT_DIAL_TIMEOUT`. The others are pruned.
 /// * The `SAFE_PEERS` environment variable.
 /// * Using the `local-discovery` feature, which will return an empty peer list.
-/// * Using the `network-contacts` feature, which will download the peer list from a file on S3.
+/// * Using the `network-contacts` feature, which will use the peers cached by a previous run if
+///   fresh enough (see `PeersArgs::peer_cache`), or else download the peer list from a file on S3.
+///
+/// The peers are shuffled, with the `--peer` ones first, and at most `--max-bootstrap-peers` of
+/// them are kept. With `--verify-peers`, the peers not accepting a connection are then dropped.
+/// Their host names are left to the transport dialing them, which resolves them through
+/// DNS-over-HTTPS with `--doh-server`.
 ///
 /// Note: the current behaviour is that `--peer` and `SAFE_PEERS` will be combined. Some tests
 /// currently rely on this. We will change it soon.
 pub async fn get_peers_from_args(args: PeersArgs) -> Result<Vec<Multiaddr>> {
+    let peers = get_peers_with_sources(args).await?;
+    Ok(peers.into_iter().map(|(peer, _source)| peer).collect())
+}
+
+/// Gets the peers the same way as `get_peers_from_args`, along with where each of them was
+/// obtained from.
+///
+/// A peer obtained from several sources is attributed to the first one it was seen in, in the
+/// order of precedence of `get_peers_from_args`.
+pub async fn get_peers_with_sources(args: PeersArgs) -> Result<Vec<(Multiaddr, PeerSource)>> {
     if args.first {
         return Ok(vec![]);
     }
 
-    let mut peers = if !args.peers.is_empty() {
-        info!("Using peers supplied with the --peer argument(s)");
-        args.peers
+    let mut sources = HashMap::new();
+    let mut peers = if !args.peers.is_empty() || args.peers_file.is_some() {
+        info!("Using peers supplied with the --peer and --peers-file argument(s)");
+        let peers = combine_with_peers_file(args.peers.clone(), args.peers_file.as_deref())?;
+        for peer in &peers {
+            let source = if args.peers.contains(peer) {
+                PeerSource::Arg
+            } else {
+                PeerSource::PeersFile
+            };
+            let _ = sources.entry(peer.clone()).or_insert(source);
+        }
+        peers
+    } else if let Some(peers) = get_routing_table_snapshot_peers(&args).await {
+        for peer in &peers {
+            let _ = sources
+                .entry(peer.clone())
+                .or_insert(PeerSource::RoutingTableSnapshot);
+        }
+        peers
     } else if cfg!(feature = "local-discovery") {
         info!("No peers given");
         info!(
@@ -87,20 +440,23 @@ pub async fn get_peers_from_args(args: PeersArgs) -> Result<Vec<Multiaddr>> {
         );
         return Ok(vec![]);
     } else if cfg!(feature = "network-contacts") {
-        get_network_contacts(&args).await?
+        let (peers, source) = get_network_contacts(&args).await?;
+        for peer in &peers {
+            let _ = sources.entry(peer.clone()).or_insert(source);
+        }
+        peers
     } else {
         vec![]
     };
 
-    if let Ok(safe_peers_str) = std::env::var(SAFE_PEERS_ENV) {
-        let peers_str = safe_peers_str.split(',');
-        for peer_str in peers_str {
-            match parse_peer_addr(peer_str) {
-                Ok(safe_peer) => peers.push(safe_peer),
-                Err(_) => println!("Failed to parse safe_peer from {peer_str:?}"),
-            }
+    if !args.ignore_env_peers {
+        let env_peers = get_peers_from_env();
+        for peer in &env_peers {
+            let _ = sources.entry(peer.clone()).or_insert(PeerSource::Env);
         }
+        peers.extend(env_peers);
     }
+    let mut peers = dedup_peers(peers);
 
     if peers.is_empty() {
         error!("Peers not obtained through any available options");
@@ -108,44 +464,255 @@ pub async fn get_peers_from_args(args: PeersArgs) -> Result<Vec<Multiaddr>> {
     };
 
     // Randomly sort peers before we return them to avoid overly hitting any one peer
-    let mut rng = thread_rng();
-    peers.shuffle(&mut rng);
+    let mut peers = prioritise_and_limit_peers(
+        peers,
+        &args.peers,
+        args.max_bootstrap_peers,
+        &mut thread_rng(),
+    );
 
-    Ok(peers)
+    if args.verify_peers {
+        info!("Verifying that the {} peers are responsive", peers.len());
+        peers = verify::retain_responsive_peers(peers, PEER_VERIFICATION_TIMEOUT).await;
+    }
+
+    Ok(peers
+        .into_iter()
+        .filter_map(|peer| sources.get(&peer).map(|source| (peer.clone(), *source)))
+        .collect())
+}
+
+/// The peers of the routing table snapshot accepting a connection, if there are any.
+async fn get_routing_table_snapshot_peers(args: &PeersArgs) -> Option<Vec<Multiaddr>> {
+    let snapshot = args.routing_table_snapshot.as_ref()?;
+    let peers = match snapshot.load() {
+        Ok(peers) if !peers.is_empty() => peers,
+        Ok(_) => {
+            debug!(
+                "No peers in the routing table snapshot at {:?}",
+                snapshot.path()
+            );
+            return None;
+        }
+        Err(err) => {
+            warn!("Failed to load the routing table snapshot: {err}");
+            return None;
+        }
+    };
+
+    let responsive = verify::responsive_peers(&peers, ROUTING_TABLE_SNAPSHOT_DIAL_TIMEOUT).await;
+    info!(
+        "{} of the {} peers of the routing table snapshot at {:?} accepted a connection",
+        responsive.len(),
+        peers.len(),
+        snapshot.path()
+    );
+    if responsive.is_empty() {
+        None
+    } else {
+        Some(responsive)
+    }
 }
 
 // should not be reachable, but needed for the compiler to be happy.
 #[allow(clippy::unused_async)]
 #[cfg(not(feature = "network-contacts"))]
-async fn get_network_contacts(_args: &PeersArgs) -> Result<Vec<Multiaddr>> {
-    Ok(vec![])
+async fn get_network_contacts(_args: &PeersArgs) -> Result<(Vec<Multiaddr>, PeerSource)> {
+    Ok((vec![], PeerSource::NetworkContacts))
 }
 
 #[cfg(feature = "network-contacts")]
-async fn get_network_contacts(args: &PeersArgs) -> Result<Vec<Multiaddr>> {
-    info!("Trying to fetch the bootstrap peers from {NETWORK_CONTACTS_URL}");
-    println!("Trying to fetch the bootstrap peers from {NETWORK_CONTACTS_URL}");
+async fn get_network_contacts(args: &PeersArgs) -> Result<(Vec<Multiaddr>, PeerSource)> {
+    if let Some(cache) = args.peer_cache() {
+        match cache.load() {
+            Ok(Some(peers)) => {
+                info!(
+                    "Using {} peers from the peer cache at {:?}",
+                    peers.len(),
+                    cache.path()
+                );
+                return Ok((peers, PeerSource::PeerCache));
+            }
+            Ok(None) => debug!("No fresh peers in the peer cache at {:?}", cache.path()),
+            Err(err) => warn!("Failed to load the peer cache at {:?}: {err}", cache.path()),
+        }
+    }
+
+    let urls = args.network_contacts_urls()?;
+    let peers = get_bootstrap_peers_from_urls(urls, &args.network_contacts_retry()).await?;
+    Ok((peers, PeerSource::NetworkContacts))
+}
+
+#[cfg(feature = "network-contacts")]
+/// Parse the comma-separated URLs of the `SAFE_NETWORK_CONTACTS_URL` environment variable,
+/// skipping the blank entries.
+fn parse_network_contacts_urls_env(value: &str) -> Result<Vec<Url>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| {
+            Url::parse(url).map_err(|source| Error::InvalidUrlInEnv {
+                var: SAFE_NETWORK_CONTACTS_URL_ENV,
+                value: url.to_string(),
+                source,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "network-contacts")]
+/// Get bootstrap peers from the first of the given URLs that provides them.
+///
+/// Each URL is retried on its own, the error lists why each of them failed.
+async fn get_bootstrap_peers_from_urls(
+    urls: Vec<Url>,
+    retry: &NetworkContactsRetry,
+) -> Result<Vec<Multiaddr>> {
+    let mut errors = vec![];
+    for url in urls {
+        info!("Trying to fetch the bootstrap peers from {url}");
+        println!("Trying to fetch the bootstrap peers from {url}");
+
+        match get_bootstrap_peers_from_url(url.clone(), retry).await {
+            Ok(peers) => {
+                info!("Got {} bootstrap peers from {url}", peers.len());
+                println!("Got {} bootstrap peers from {url}", peers.len());
+                return Ok(peers);
+            }
+            Err(err) => {
+                warn!("Failed to get the bootstrap peers from {url}: {err}");
+                errors.push(format!("{url}: {err}"));
+            }
+        }
+    }
+    Err(Error::NetworkContactsUnretrievable(errors))
+}
+
+/// Remove the duplicate peers, keeping the first seen address of each, in order.
+///
+/// Peers are duplicates if their multiaddrs are the same, or if they hold the same peer id:
+/// the same node listed with different addresses is only dialed once.
+fn dedup_peers(peers: Vec<Multiaddr>) -> Vec<Multiaddr> {
+    let mut seen_addrs = HashSet::new();
+    let mut seen_peer_ids = HashSet::new();
+    let mut deduped = vec![];
+    for peer in peers {
+        if !seen_addrs.insert(peer.clone()) {
+            debug!("Skipping the duplicate peer {peer}");
+            continue;
+        }
+        if let Some(peer_id) = peer_id_of(&peer) {
+            if !seen_peer_ids.insert(peer_id) {
+                debug!("Skipping {peer}, another address of the peer {peer_id} is already used");
+                continue;
+            }
+        }
+        deduped.push(peer);
+    }
+    deduped
+}
+
+/// Shuffle the peers, keeping the explicitly given ones ahead of the others, then keep at most
+/// `max_peers` of them, or all of them if it is zero.
+fn prioritise_and_limit_peers(
+    peers: Vec<Multiaddr>,
+    explicit_peers: &[Multiaddr],
+    max_peers: usize,
+    rng: &mut impl Rng,
+) -> Vec<Multiaddr> {
+    let explicit_peers: HashSet<_> = explicit_peers.iter().collect();
+    let (mut prioritised, mut others): (Vec<_>, Vec<_>) = peers
+        .into_iter()
+        .partition(|peer| explicit_peers.contains(peer));
+    prioritised.shuffle(rng);
+    others.shuffle(rng);
+    prioritised.append(&mut others);
 
-    let url = args
-        .network_contacts_url
-        .clone()
-        .unwrap_or(Url::parse(NETWORK_CONTACTS_URL)?);
-    get_bootstrap_peers_from_url(url).await
+    if max_peers > 0 && prioritised.len() > max_peers {
+        info!(
+            "Using {max_peers} of the {} bootstrap peers obtained",
+            prioritised.len()
+        );
+        prioritised.truncate(max_peers);
+    }
+    prioritised
+}
+
+/// The peer id at the end of a multiaddr, if any.
+fn peer_id_of(peer: &Multiaddr) -> Option<PeerId> {
+    peer.iter().find_map(|protocol| match protocol {
+        Protocol::P2p(peer_id) => Some(peer_id),
+        _ => None,
+    })
 }
 
-/// Parse strings like `1.2.3.4:1234` and `/ip4/1.2.3.4/tcp/1234` into a (TCP) multiaddr.
+/// Add the peers read from the peers file, if any, to the given ones, skipping duplicates.
+fn combine_with_peers_file(
+    mut peers: Vec<Multiaddr>,
+    peers_file_path: Option<&Path>,
+) -> Result<Vec<Multiaddr>> {
+    if let Some(path) = peers_file_path {
+        for peer in peers_file::read_peers_file(path)? {
+            if !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+    }
+    Ok(peers)
+}
+
+/// Get the peers provided through the `SAFE_PEERS` environment variable, as a comma-separated list.
+/// Entries that can't be parsed are skipped.
+fn get_peers_from_env() -> Vec<Multiaddr> {
+    let mut peers = vec![];
+    if let Ok(safe_peers_str) = std::env::var(SAFE_PEERS_ENV) {
+        let peers_str = safe_peers_str.split(',');
+        for peer_str in peers_str {
+            match parse_peer_addr(peer_str) {
+                Ok(safe_peer) => peers.push(safe_peer),
+                Err(err) => println!("Failed to parse safe_peer from {peer_str:?}: {err}"),
+            }
+        }
+    }
+    peers
+}
+
+/// Parse strings like `1.2.3.4:1234`, `[2001:db8::1]:1234`, `mynode.example.com:1234` and
+/// `/ip4/1.2.3.4/tcp/1234` into a (TCP) multiaddr.
+///
+/// Host names are resolved when dialing, as `/dns4/<host>` multiaddrs. `/dnsaddr/...` multiaddrs
+/// are taken as they are.
 pub fn parse_peer_addr(addr: &str) -> Result<Multiaddr> {
     // Parse valid IPv4 socket address, e.g. `1.2.3.4:1234`.
     if let Ok(addr) = addr.parse::<std::net::SocketAddrV4>() {
-        #[cfg(not(feature = "quic"))]
-        // Turn the address into a `/ip4/<ip>/tcp/<port>` multiaddr.
-        let multiaddr = Multiaddr::from(*addr.ip()).with(Protocol::Tcp(addr.port()));
-        #[cfg(feature = "quic")]
-        // Turn the address into a `/ip4/<ip>/udp/<port>/quic-v1` multiaddr.
-        let multiaddr = Multiaddr::from(*addr.ip())
-            .with(Protocol::Udp(addr.port()))
-            .with(Protocol::QuicV1);
-        return Ok(multiaddr);
+        return Ok(socket_addr_to_multiaddr(
+            Multiaddr::from(*addr.ip()),
+            addr.port(),
+        ));
+    }
+
+    // Parse valid IPv6 socket address, e.g. `[2001:db8::1]:1234`.
+    if let Ok(v6_addr) = addr.parse::<std::net::SocketAddrV6>() {
+        // Multiaddrs carry no scope id, a scoped (e.g. link-local) address can't be dialed from it.
+        if v6_addr.scope_id() != 0 {
+            return Err(Error::ScopedIpv6PeerAddr(addr.to_string()));
+        }
+        return Ok(socket_addr_to_multiaddr(
+            Multiaddr::from(*v6_addr.ip()),
+            v6_addr.port(),
+        ));
+    }
+
+    // Parse a host name and port, e.g. `mynode.example.com:1234`.
+    if let Some((host, port)) = addr.rsplit_once(':') {
+        if let Some(host) = parse_host_name(host) {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| Error::InvalidPeerPort(addr.to_string()))?;
+            let dns = Multiaddr::empty().with(Protocol::Dns4(host.to_string().into()));
+            return Ok(socket_addr_to_multiaddr(dns, port));
+        }
     }
 
     // Parse any valid multiaddr string, e.g. `/ip4/1.2.3.4/tcp/1234/p2p/<peer_id>`.
@@ -156,61 +723,744 @@ pub fn parse_peer_addr(addr: &str) -> Result<Multiaddr> {
     Err(Error::InvalidPeerAddr)
 }
 
+/// Return the given host name without its trailing dot, if it is a valid DNS name.
+///
+/// Names made only of numeric labels are rejected, as they are malformed IPv4 addresses.
+fn parse_host_name(host: &str) -> Option<&str> {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    if host.is_empty() || host.len() > 253 {
+        return None;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_labels = labels.iter().all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    let all_numeric = labels
+        .iter()
+        .all(|label| label.chars().all(|c| c.is_ascii_digit()));
+
+    (valid_labels && !all_numeric).then_some(host)
+}
+
+/// Turn an `/ip4/<ip>` or `/ip6/<ip>` multiaddr into a `/<ip>/tcp/<port>` multiaddr.
+#[cfg(not(feature = "quic"))]
+fn socket_addr_to_multiaddr(ip: Multiaddr, port: u16) -> Multiaddr {
+    ip.with(Protocol::Tcp(port))
+}
+
+/// Turn an `/ip4/<ip>` or `/ip6/<ip>` multiaddr into a `/<ip>/udp/<port>/quic-v1` multiaddr.
+#[cfg(feature = "quic")]
+fn socket_addr_to_multiaddr(ip: Multiaddr, port: u16) -> Multiaddr {
+    ip.with(Protocol::Udp(port)).with(Protocol::QuicV1)
+}
+
 #[cfg(feature = "network-contacts")]
 /// Get bootstrap peers from the Network contacts file stored in the given URL.
 ///
-/// If URL is not provided, the addresses are fetched from the default NETWORK_CONTACTS_URL
-async fn get_bootstrap_peers_from_url(url: Url) -> Result<Vec<Multiaddr>> {
-    let mut retries = 0;
+/// Failing requests, including the ones timing out, are retried as set by `retry`, the error
+/// lists why each attempt failed.
+async fn get_bootstrap_peers_from_url(
+    url: Url,
+    retry: &NetworkContactsRetry,
+) -> Result<Vec<Multiaddr>> {
+    let start = std::time::Instant::now();
+    let client = reqwest::Client::builder().timeout(retry.timeout).build()?;
+    let mut failed_attempts = vec![];
 
     loop {
-        let response = reqwest::get(url.clone()).await;
-
-        match response {
-            Ok(response) => {
-                let mut multi_addresses = Vec::new();
-                if response.status().is_success() {
-                    let text = response.text().await?;
-                    trace!("Got bootstrap peers from {url}: {text}");
-                    // example of contacts file exists in resources/network-contacts-examples
-                    for addr in text.split('\n') {
-                        // ignore empty/last lines
-                        if addr.is_empty() {
-                            continue;
-                        }
-
-                        debug!("Attempting to parse {addr}");
-                        multi_addresses.push(parse_peer_addr(addr)?);
-                    }
-                    if !multi_addresses.is_empty() {
-                        trace!("Successfully got bootstrap peers from URL {multi_addresses:?}");
-                        return Ok(multi_addresses);
-                    } else {
-                        return Err(Error::NoMultiAddrObtainedFromNetworkContacts(
-                            NETWORK_CONTACTS_URL.to_string(),
-                        ));
-                    }
-                } else {
-                    retries += 1;
-                    if retries >= MAX_NETWORK_CONTACTS_GET_RETRIES {
-                        return Err(Error::NetworkContactsUnretrievable(
-                            NETWORK_CONTACTS_URL.to_string(),
-                            MAX_NETWORK_CONTACTS_GET_RETRIES,
-                        ));
-                    }
+        let failure = match fetch_network_contacts(&client, &url, retry.timeout).await {
+            Ok((content_type, text)) => {
+                trace!("Got bootstrap peers from {url}: {text}");
+
+                let multi_addresses = network_contacts::parse_network_contacts(
+                    url.as_str(),
+                    content_type.as_deref(),
+                    &text,
+                )?;
+                trace!("Successfully got bootstrap peers from URL {multi_addresses:?}");
+                return Ok(multi_addresses);
+            }
+            Err(failure) => failure,
+        };
+
+        let attempt = failed_attempts.len() + 1;
+        trace!("Attempt {attempt} to get bootstrap peers from URL failed: {failure}");
+        failed_attempts.push(failure);
+        if attempt > retry.retries {
+            return Err(Error::NetworkContactsUrlUnretrievable {
+                url: url.to_string(),
+                elapsed: start.elapsed(),
+                attempts: failed_attempts,
+            });
+        }
+
+        let delay = retry.delay(attempt);
+        trace!(
+            "Retrying to get bootstrap peers from URL {attempt}/{} in {delay:?}",
+            retry.retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(feature = "network-contacts")]
+/// A single attempt to fetch the network contacts, returning their content type and text.
+async fn fetch_network_contacts(
+    client: &reqwest::Client,
+    url: &Url,
+    timeout: std::time::Duration,
+) -> std::result::Result<(Option<String>, String), FetchFailure> {
+    let to_failure = |err: reqwest::Error| {
+        if err.is_timeout() {
+            FetchFailure::Timeout(timeout)
+        } else {
+            FetchFailure::Other(err.to_string())
+        }
+    };
+
+    let response = client.get(url.clone()).send().await.map_err(to_failure)?;
+    if !response.status().is_success() {
+        return Err(FetchFailure::Status(response.status().as_u16()));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_string());
+    let text = response.text().await.map_err(to_failure)?;
+    Ok((content_type, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serialises the tests setting the `SAFE_PEERS` and `SAFE_NETWORK_CONTACTS_URL` environment
+    /// variables, as the peers acquisition reads both.
+    static SAFE_PEERS_ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// The environment variables set by a test, holding `SAFE_PEERS_ENV_LOCK` until dropped,
+    /// when they are restored to their values from before the test.
+    struct EnvVars {
+        _lock: std::sync::MutexGuard<'static, ()>,
+        saved: Vec<(&'static str, Option<String>)>,
+    }
+
+    impl EnvVars {
+        fn lock() -> Self {
+            Self {
+                _lock: SAFE_PEERS_ENV_LOCK
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                saved: vec![],
+            }
+        }
+
+        /// Set the variable to the value, or remove it if `None`.
+        fn set(&mut self, var: &'static str, value: Option<&str>) {
+            if !self.saved.iter().any(|(saved, _)| *saved == var) {
+                self.saved.push((var, std::env::var(var).ok()));
+            }
+            match value {
+                Some(value) => std::env::set_var(var, value),
+                None => std::env::remove_var(var),
+            }
+        }
+    }
+
+    impl Drop for EnvVars {
+        fn drop(&mut self) {
+            for (var, value) in self.saved.drain(..) {
+                match value {
+                    Some(value) => std::env::set_var(var, value),
+                    None => std::env::remove_var(var),
                 }
             }
-            Err(_) => {
-                retries += 1;
-                if retries >= MAX_NETWORK_CONTACTS_GET_RETRIES {
-                    return Err(Error::NetworkContactsUnretrievable(
-                        NETWORK_CONTACTS_URL.to_string(),
-                        MAX_NETWORK_CONTACTS_GET_RETRIES,
-                    ));
+        }
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn all_network_contacts_urls_are_tried() -> Result<()> {
+        // nothing listens on port 1, so both fail straight away
+        let urls = vec![
+            Url::parse("http://127.0.0.1:1/network-contacts")?,
+            Url::parse("http://127.0.0.1:1/network-contacts-mirror")?,
+        ];
+
+        let retry = NetworkContactsRetry {
+            retries: 0,
+            ..Default::default()
+        };
+        match get_bootstrap_peers_from_urls(urls, &retry).await {
+            Err(Error::NetworkContactsUnretrievable(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors[0].starts_with("http://127.0.0.1:1/network-contacts:"));
+                assert!(errors[1].starts_with("http://127.0.0.1:1/network-contacts-mirror:"));
+            }
+            other => panic!("Expected NetworkContactsUnretrievable, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    /// Serve the given HTTP status codes in turn on a local port, the last one repeatedly,
+    /// recording when each request came in. Successful responses hold a single peer.
+    /// A status of 0 holds the connection open without ever responding.
+    #[cfg(feature = "network-contacts")]
+    async fn mock_contacts_server(
+        statuses: Vec<u16>,
+    ) -> Result<(
+        Url,
+        std::sync::Arc<std::sync::Mutex<Vec<std::time::Instant>>>,
+    )> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = Url::parse(&format!(
+            "http://{}/network-contacts",
+            listener.local_addr()?
+        ))?;
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let requests_seen = requests.clone();
+
+        let _handle = tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await;
+                if let Ok(mut requests) = requests_seen.lock() {
+                    requests.push(std::time::Instant::now());
                 }
+
+                let status = statuses
+                    .get(served)
+                    .or(statuses.last())
+                    .copied()
+                    .unwrap_or(500);
+                served += 1;
+                if status == 0 {
+                    let _hang = tokio::spawn(async move {
+                        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                        drop(stream);
+                    });
+                    continue;
+                }
+                let body = if status == 200 { "1.2.3.4:12000\n" } else { "" };
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        Ok((url, requests))
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn network_contacts_fetch_backs_off_exponentially() -> Result<()> {
+        let (url, requests) = mock_contacts_server(vec![503, 503, 503, 200]).await?;
+        let retry = NetworkContactsRetry {
+            retries: 3,
+            initial_backoff: std::time::Duration::from_millis(100),
+            max_backoff: std::time::Duration::from_secs(1),
+            jitter: false,
+            ..Default::default()
+        };
+
+        let peers = get_bootstrap_peers_from_url(url, &retry).await?;
+        assert_eq!(peers, vec![parse_peer_addr("1.2.3.4:12000")?]);
+
+        let requests = requests
+            .lock()
+            .map_err(|_| Error::PeersNotObtained)?
+            .clone();
+        assert_eq!(requests.len(), 4);
+        // the gaps between the attempts are at least the 100ms, 200ms, 400ms backoff schedule,
+        // however slow the machine running the test: a sleep may overrun, never fall short
+        let gaps: Vec<_> = requests.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        for (retry_nb, gap) in gaps.iter().enumerate() {
+            let backoff = retry.backoff(retry_nb + 1);
+            assert!(
+                *gap >= backoff,
+                "retry {retry_nb} came after {gap:?}, expected {backoff:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn network_contacts_fetch_error_lists_every_attempt() -> Result<()> {
+        let (url, _requests) = mock_contacts_server(vec![503]).await?;
+        let retry = NetworkContactsRetry {
+            retries: 2,
+            initial_backoff: std::time::Duration::from_millis(10),
+            max_backoff: std::time::Duration::from_millis(10),
+            jitter: false,
+            ..Default::default()
+        };
+
+        match get_bootstrap_peers_from_url(url.clone(), &retry).await {
+            Err(Error::NetworkContactsUrlUnretrievable {
+                url: failed_url,
+                elapsed,
+                attempts,
+            }) => {
+                assert_eq!(failed_url, url.to_string());
+                assert!(elapsed >= std::time::Duration::from_millis(20));
+                assert_eq!(attempts, vec![FetchFailure::Status(503); 3]);
+            }
+            other => panic!("Expected NetworkContactsUrlUnretrievable, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn network_contacts_fetch_times_out_and_retries() -> Result<()> {
+        let (url, requests) = mock_contacts_server(vec![0, 200]).await?;
+        let retry = NetworkContactsRetry {
+            retries: 1,
+            initial_backoff: std::time::Duration::from_millis(10),
+            max_backoff: std::time::Duration::from_millis(10),
+            jitter: false,
+            timeout: std::time::Duration::from_millis(300),
+        };
+
+        let start = std::time::Instant::now();
+        let peers = get_bootstrap_peers_from_url(url, &retry).await?;
+        assert_eq!(peers, vec![parse_peer_addr("1.2.3.4:12000")?]);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(
+            requests.lock().map_err(|_| Error::PeersNotObtained)?.len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn network_contacts_fetch_error_tells_timeouts_from_statuses() -> Result<()> {
+        let (url, _requests) = mock_contacts_server(vec![503, 0]).await?;
+        let timeout = std::time::Duration::from_millis(300);
+        let retry = NetworkContactsRetry {
+            retries: 2,
+            initial_backoff: std::time::Duration::from_millis(10),
+            max_backoff: std::time::Duration::from_millis(10),
+            jitter: false,
+            timeout,
+        };
+
+        let start = std::time::Instant::now();
+        match get_bootstrap_peers_from_url(url, &retry).await {
+            Err(Error::NetworkContactsUrlUnretrievable { attempts, .. }) => {
+                assert_eq!(
+                    attempts,
+                    vec![
+                        FetchFailure::Status(503),
+                        FetchFailure::Timeout(timeout),
+                        FetchFailure::Timeout(timeout)
+                    ]
+                );
+            }
+            other => panic!("Expected NetworkContactsUrlUnretrievable, got {other:?}"),
+        }
+        // the blackholed attempts didn't hang for long
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        Ok(())
+    }
+
+    fn transport(port: u16) -> Multiaddr {
+        socket_addr_to_multiaddr(Multiaddr::empty(), port)
+    }
+
+    #[test]
+    fn parse_ipv4_socket_addr() -> Result<()> {
+        let multiaddr = parse_peer_addr("1.2.3.4:12000")?;
+        let expected = format!("/ip4/1.2.3.4{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_bracketed_ipv6_socket_addr() -> Result<()> {
+        let multiaddr = parse_peer_addr("[2001:db8::1]:12000")?;
+        let expected = format!("/ip6/2001:db8::1{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+
+        let multiaddr = parse_peer_addr("[::1]:12000")?;
+        let expected = format!("/ip6/::1{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_ipv6_multiaddr() -> Result<()> {
+        let multiaddr = parse_peer_addr("/ip6/2001:db8::1/udp/12000/quic-v1")?;
+        assert_eq!(multiaddr.to_string(), "/ip6/2001:db8::1/udp/12000/quic-v1");
+        Ok(())
+    }
+
+    #[test]
+    fn parse_scoped_ipv6_socket_addr_errors() {
+        assert!(matches!(
+            parse_peer_addr("[fe80::1%3]:12000"),
+            Err(Error::ScopedIpv6PeerAddr(_))
+        ));
+    }
+
+    #[test]
+    fn parse_host_name_and_port() -> Result<()> {
+        let multiaddr = parse_peer_addr("mynode.example.com:12000")?;
+        let expected = format!("/dns4/mynode.example.com{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+
+        let multiaddr = parse_peer_addr("my-node-1.safe-network.example:12000")?;
+        let expected = format!("/dns4/my-node-1.safe-network.example{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+
+        let multiaddr = parse_peer_addr("localhost:12000")?;
+        let expected = format!("/dns4/localhost{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_host_name_with_trailing_dot() -> Result<()> {
+        let multiaddr = parse_peer_addr("mynode.example.com.:12000")?;
+        let expected = format!("/dns4/mynode.example.com{}", transport(12000));
+        assert_eq!(multiaddr.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_host_name_with_invalid_port_errors() {
+        for addr in [
+            "mynode.example.com:99999",
+            "mynode.example.com:",
+            "mynode.example.com:port",
+            "mynode.example.com:-1",
+        ] {
+            assert!(
+                matches!(parse_peer_addr(addr), Err(Error::InvalidPeerPort(_))),
+                "{addr} should have an invalid port"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_host_name_errors() {
+        for addr in [
+            "-mynode.example.com:12000",
+            "mynode-.example.com:12000",
+            "my_node.example.com:12000",
+            "mynode..example.com:12000",
+            "1.2.3.256:12000",
+            ":12000",
+        ] {
+            assert!(
+                matches!(parse_peer_addr(addr), Err(Error::InvalidPeerAddr)),
+                "{addr} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_dnsaddr_multiaddr() -> Result<()> {
+        let addr = format!("/dnsaddr/bootstrap.example.com/p2p/{PEER_ID}");
+        let multiaddr = parse_peer_addr(&addr)?;
+        assert_eq!(multiaddr.to_string(), addr);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_unbracketed_ipv6_socket_addr_errors() {
+        assert!(matches!(
+            parse_peer_addr("2001:db8::1:12000"),
+            Err(Error::InvalidPeerAddr)
+        ));
+    }
+
+    #[test]
+    fn peers_file_is_combined_with_peer_args() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("peers");
+        std::fs::write(&path, "# testnet\n1.2.3.4:12000\n\n1.2.3.5:12000\n")?;
+
+        let peers = combine_with_peers_file(
+            vec![
+                parse_peer_addr("1.2.3.5:12000")?,
+                parse_peer_addr("1.2.3.6:12000")?,
+            ],
+            Some(path.as_path()),
+        )?;
+        let peers: Vec<_> = peers.iter().map(|peer| peer.to_string()).collect();
+        assert_eq!(
+            peers,
+            vec![
+                format!("/ip4/1.2.3.5{}", transport(12000)),
+                format!("/ip4/1.2.3.6{}", transport(12000)),
+                format!("/ip4/1.2.3.4{}", transport(12000)),
+            ]
+        );
+        Ok(())
+    }
+
+    const PEER_ID: &str = "12D3KooWRi6wF7yxWLuPSNskXc6kQ5cJ6eaymeMbCRdTnMesPgFx";
+
+    #[test]
+    fn duplicate_peers_from_args_and_env_are_dialed_once() -> Result<()> {
+        let args_peers = vec![
+            parse_peer_addr("1.2.3.4:12000")?,
+            parse_peer_addr(&format!("/ip4/1.2.3.5/tcp/12000/p2p/{PEER_ID}"))?,
+        ];
+        // as parsed from `SAFE_PEERS`, without racing the other tests setting it
+        let env_peers = vec![
+            parse_peer_addr("1.2.3.6:12000")?,
+            parse_peer_addr("1.2.3.4:12000")?,
+            parse_peer_addr(&format!("/ip4/1.2.3.7/tcp/12000/p2p/{PEER_ID}"))?,
+        ];
+
+        let peers: Vec<_> = dedup_peers(args_peers.into_iter().chain(env_peers).collect())
+            .iter()
+            .map(|peer| peer.to_string())
+            .collect();
+        assert_eq!(
+            peers,
+            vec![
+                format!("/ip4/1.2.3.4{}", transport(12000)),
+                format!("/ip4/1.2.3.5/tcp/12000/p2p/{PEER_ID}"),
+                format!("/ip4/1.2.3.6{}", transport(12000)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn duplicate_peers_from_contacts_and_env_are_dialed_once() -> Result<()> {
+        // the mock contacts hold the single peer 1.2.3.4:12000
+        let (url, _requests) = mock_contacts_server(vec![200]).await?;
+        let contacts_peers =
+            get_bootstrap_peers_from_url(url, &NetworkContactsRetry::default()).await?;
+        let env_peers = vec![
+            parse_peer_addr("1.2.3.4:12000")?,
+            parse_peer_addr("1.2.3.5:12000")?,
+        ];
+
+        let peers = dedup_peers(contacts_peers.into_iter().chain(env_peers).collect());
+        assert_eq!(
+            peers,
+            vec![
+                parse_peer_addr("1.2.3.4:12000")?,
+                parse_peer_addr("1.2.3.5:12000")?,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn peers_are_deduped_by_peer_id_keeping_the_first_address() -> Result<()> {
+        let first = parse_peer_addr(&format!("/ip4/1.2.3.4/tcp/12000/p2p/{PEER_ID}"))?;
+        let second = parse_peer_addr(&format!("/ip4/1.2.3.5/tcp/12000/p2p/{PEER_ID}"))?;
+        let without_id = parse_peer_addr("/ip4/1.2.3.5/tcp/12000")?;
+
+        assert_eq!(
+            dedup_peers(vec![
+                first.clone(),
+                without_id.clone(),
+                second,
+                first.clone()
+            ]),
+            vec![first, without_id]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[test]
+    fn network_contacts_urls_precedence() -> Result<()> {
+        let default = vec![Url::parse(NETWORK_CONTACTS_URL)?];
+        let from_env = vec![
+            Url::parse("http://127.0.0.1:1/env-contacts")?,
+            Url::parse("http://127.0.0.1:1/env-contacts-mirror")?,
+        ];
+        let from_args = vec![Url::parse("http://127.0.0.1:1/args-contacts")?];
+        let with_args = PeersArgs {
+            network_contacts_url: from_args.clone(),
+            ..Default::default()
+        };
+
+        let mut env_vars = EnvVars::lock();
+        env_vars.set(SAFE_NETWORK_CONTACTS_URL_ENV, None);
+        let without_env = PeersArgs::default().network_contacts_urls();
+        env_vars.set(
+            SAFE_NETWORK_CONTACTS_URL_ENV,
+            Some("http://127.0.0.1:1/env-contacts, http://127.0.0.1:1/env-contacts-mirror"),
+        );
+        let with_env = PeersArgs::default().network_contacts_urls();
+        let with_env_and_args = with_args.network_contacts_urls();
+        env_vars.set(SAFE_NETWORK_CONTACTS_URL_ENV, Some("not a url"));
+        let with_invalid_env = PeersArgs::default().network_contacts_urls();
+        drop(env_vars);
+
+        assert_eq!(without_env?, default);
+        assert_eq!(with_env?, from_env);
+        assert_eq!(with_env_and_args?, from_args);
+        match with_invalid_env {
+            Err(err @ Error::InvalidUrlInEnv { .. }) => {
+                assert!(err.to_string().contains(SAFE_NETWORK_CONTACTS_URL_ENV));
+                assert!(err.to_string().contains("not a url"));
             }
+            other => panic!("Expected InvalidUrlInEnv, got {other:?}"),
         }
-        trace!("Failed to get bootstrap peers from URL, retrying {retries}/{MAX_NETWORK_CONTACTS_GET_RETRIES}");
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        Ok(())
+    }
+
+    fn synthetic_peers(count: u8, third_octet: u8) -> Result<Vec<Multiaddr>> {
+        (0..count)
+            .map(|i| parse_peer_addr(&format!("10.0.{third_octet}.{i}:12000")))
+            .collect()
+    }
+
+    #[test]
+    fn peers_are_limited_to_the_maximum() -> Result<()> {
+        let peers = synthetic_peers(100, 0)?;
+        let mut rng = rand::thread_rng();
+
+        let limited = prioritise_and_limit_peers(peers.clone(), &[], 25, &mut rng);
+        assert_eq!(limited.len(), 25);
+        assert!(limited.iter().all(|peer| peers.contains(peer)));
+        assert_eq!(limited.iter().collect::<HashSet<_>>().len(), 25);
+
+        // zero means no limit
+        let unlimited = prioritise_and_limit_peers(peers.clone(), &[], 0, &mut rng);
+        assert_eq!(
+            unlimited.iter().collect::<HashSet<_>>(),
+            peers.iter().collect::<HashSet<_>>()
+        );
+
+        // fewer peers than the limit are all kept
+        let few = prioritise_and_limit_peers(peers[..10].to_vec(), &[], 25, &mut rng);
+        assert_eq!(few.len(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn explicit_peers_are_kept_ahead_of_the_others() -> Result<()> {
+        let explicit = synthetic_peers(5, 1)?;
+        let contacts = synthetic_peers(100, 0)?;
+        let peers: Vec<_> = contacts.iter().chain(&explicit).cloned().collect();
+        let mut rng = rand::thread_rng();
+
+        let limited = prioritise_and_limit_peers(peers.clone(), &explicit, 8, &mut rng);
+        assert_eq!(limited.len(), 8);
+        assert_eq!(
+            limited[..5].iter().collect::<HashSet<_>>(),
+            explicit.iter().collect::<HashSet<_>>()
+        );
+        assert!(limited[5..].iter().all(|peer| contacts.contains(peer)));
+
+        // with a limit below their count, only explicit peers are kept
+        let limited = prioritise_and_limit_peers(peers, &explicit, 3, &mut rng);
+        assert!(limited.iter().all(|peer| explicit.contains(peer)));
+        assert_eq!(limited.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_ipv4_and_ipv6_peers_from_env() {
+        let mut env_vars = EnvVars::lock();
+        env_vars.set(
+            SAFE_PEERS_ENV,
+            Some("1.2.3.4:12000,[2001:db8::1]:12001,[fe80::1%3]:12002,/ip6/::1/tcp/12003"),
+        );
+        let peers = get_peers_from_env();
+        drop(env_vars);
+
+        let peers: Vec<_> = peers.iter().map(|peer| peer.to_string()).collect();
+        assert_eq!(
+            peers,
+            vec![
+                format!("/ip4/1.2.3.4{}", transport(12000)),
+                format!("/ip6/2001:db8::1{}", transport(12001)),
+                "/ip6/::1/tcp/12003".to_string(),
+            ]
+        );
+    }
+
+    #[cfg(feature = "network-contacts")]
+    #[tokio::test]
+    async fn restarting_node_bootstraps_from_its_routing_table_snapshot() -> Result<()> {
+        let tcp_peer =
+            |port| Multiaddr::from(std::net::Ipv4Addr::LOCALHOST).with(Protocol::Tcp(port));
+        let dir = tempfile::tempdir()?;
+        let snapshot = RoutingTableSnapshot::new(dir.path());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let live_addr = tcp_peer(listener.local_addr()?.port());
+        let live_peer = PeerId::random();
+        snapshot.save(&[
+            (live_peer, vec![live_addr.clone()]),
+            // nothing listens on port 1
+            (PeerId::random(), vec![tcp_peer(1)]),
+        ])?;
+
+        // nothing listens on port 1 either, the network contacts are unreachable
+        let mut args = PeersArgs::builder()
+            .network_contacts_url(Url::parse("http://127.0.0.1:1/network-contacts")?)
+            .ignore_peer_cache(true)
+            .ignore_env_peers(true)
+            .build();
+        args.set_routing_table_snapshot(snapshot);
+
+        let peers = get_peers_with_sources(args).await?;
+        assert_eq!(
+            peers,
+            vec![(
+                live_addr.with(Protocol::P2p(live_peer)),
+                PeerSource::RoutingTableSnapshot
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn peer_sources_are_attributed_when_combined() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("peers");
+        std::fs::write(&path, "1.2.3.4:12000\n1.2.3.5:12000\n")?;
+        let args = PeersArgs::builder()
+            .peer(parse_peer_addr("1.2.3.4:12000")?)
+            .peers_file(&path)
+            .max_bootstrap_peers(0)
+            .build();
+
+        let peers = {
+            let mut env_vars = EnvVars::lock();
+            env_vars.set(SAFE_PEERS_ENV, Some("1.2.3.5:12000,1.2.3.6:12000"));
+            tokio::runtime::Builder::new_current_thread()
+                .build()?
+                .block_on(get_peers_with_sources(args))?
+        };
+
+        // each peer is attributed to the first source it was seen in
+        let sources: HashMap<_, _> = peers.into_iter().collect();
+        assert_eq!(
+            sources,
+            HashMap::from([
+                (parse_peer_addr("1.2.3.4:12000")?, PeerSource::Arg),
+                (parse_peer_addr("1.2.3.5:12000")?, PeerSource::PeersFile),
+                (parse_peer_addr("1.2.3.6:12000")?, PeerSource::Env),
+            ])
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,173 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    parse_peer_addr,
+};
+use libp2p::Multiaddr;
+use serde::Deserialize;
+use tracing::*;
+
+/// The latest version of the JSON network contacts format.
+const NETWORK_CONTACTS_VERSION: u32 = 1;
+
+/// The JSON network contacts format.
+/// An example exists in resources/network-contacts-example.json
+#[derive(Deserialize)]
+struct NetworkContacts {
+    version: u32,
+    peers: Vec<NetworkContact>,
+}
+
+#[derive(Deserialize)]
+struct NetworkContact {
+    addr: String,
+    #[serde(default)]
+    region: Option<String>,
+}
+
+/// Parse the network contacts fetched from `url`.
+///
+/// They're either a JSON document, detected by its content type or its content, or the legacy
+/// format holding one multiaddr per line.
+pub(crate) fn parse_network_contacts(
+    url: &str,
+    content_type: Option<&str>,
+    text: &str,
+) -> Result<Vec<Multiaddr>> {
+    let is_json = content_type.is_some_and(|content_type| content_type.contains("json"))
+        || text.trim_start().starts_with('{');
+    if is_json {
+        parse_json_network_contacts(url, text)
+    } else {
+        parse_plain_text_network_contacts(url, text)
+    }
+}
+
+/// Entries that aren't valid peers are skipped.
+fn parse_json_network_contacts(url: &str, text: &str) -> Result<Vec<Multiaddr>> {
+    let contacts: NetworkContacts = serde_json::from_str(text)
+        .map_err(|err| Error::InvalidJsonNetworkContacts(url.to_string(), err.to_string()))?;
+    if contacts.version != NETWORK_CONTACTS_VERSION {
+        warn!(
+            "The network contacts at {url} are of version {}, expected {NETWORK_CONTACTS_VERSION}",
+            contacts.version
+        );
+    }
+
+    let mut multi_addresses = Vec::new();
+    for contact in contacts.peers {
+        match parse_peer_addr(&contact.addr) {
+            Ok(addr) => {
+                debug!("Got peer {addr} in region {:?} from {url}", contact.region);
+                multi_addresses.push(addr);
+            }
+            Err(err) => warn!("Skipping invalid peer {:?} from {url}: {err}", contact.addr),
+        }
+    }
+
+    if multi_addresses.is_empty() {
+        return Err(Error::NoValidPeerInJsonNetworkContacts(url.to_string()));
+    }
+    Ok(multi_addresses)
+}
+
+/// Example exists in resources/network-contacts-example
+fn parse_plain_text_network_contacts(url: &str, text: &str) -> Result<Vec<Multiaddr>> {
+    let mut multi_addresses = Vec::new();
+    for addr in text.split('\n') {
+        // ignore empty/last lines
+        if addr.is_empty() {
+            continue;
+        }
+
+        debug!("Attempting to parse {addr}");
+        multi_addresses.push(parse_peer_addr(addr)?);
+    }
+
+    if multi_addresses.is_empty() {
+        return Err(Error::NoMultiAddrObtainedFromNetworkContacts(
+            url.to_string(),
+        ));
+    }
+    Ok(multi_addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/network-contacts";
+    const PEER: &str =
+        "/ip4/142.93.232.219/tcp/38095/p2p/12D3KooWLAX6Z1m5gNxPGZQRV6VEzCtipNGcP1YhrAYNYT3yq5mv";
+
+    #[test]
+    fn plain_text_contacts_are_parsed() -> Result<()> {
+        let peers = parse_network_contacts(URL, Some("text/plain"), &format!("{PEER}\n"))?;
+        assert_eq!(peers, vec![parse_peer_addr(PEER)?]);
+        Ok(())
+    }
+
+    #[test]
+    fn json_contacts_are_detected_by_content_type_or_content() -> Result<()> {
+        let json =
+            format!(r#"{{"version": 1, "peers": [{{"addr": "{PEER}", "region": "lon1"}}]}}"#);
+
+        let peers = parse_network_contacts(URL, Some("application/json"), &json)?;
+        assert_eq!(peers, vec![parse_peer_addr(PEER)?]);
+
+        // S3 may serve it as an octet stream
+        let peers = parse_network_contacts(URL, Some("binary/octet-stream"), &json)?;
+        assert_eq!(peers, vec![parse_peer_addr(PEER)?]);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_json_entries_are_skipped() -> Result<()> {
+        let json = format!(
+            r#"{{"version": 1, "peers": [{{"addr": "not a peer"}}, {{"addr": "{PEER}"}}]}}"#
+        );
+        let peers = parse_network_contacts(URL, None, &json)?;
+        assert_eq!(peers, vec![parse_peer_addr(PEER)?]);
+        Ok(())
+    }
+
+    #[test]
+    fn json_without_valid_peers_errors() {
+        let json = r#"{"version": 1, "peers": [{"addr": "not a peer"}]}"#;
+        assert!(matches!(
+            parse_network_contacts(URL, None, json),
+            Err(Error::NoValidPeerInJsonNetworkContacts(_))
+        ));
+
+        let json = r#"{"version": 1, "peers": []}"#;
+        assert!(matches!(
+            parse_network_contacts(URL, None, json),
+            Err(Error::NoValidPeerInJsonNetworkContacts(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_json_errors() {
+        assert!(matches!(
+            parse_network_contacts(URL, Some("application/json"), r#"{"peers": "#),
+            Err(Error::InvalidJsonNetworkContacts(..))
+        ));
+    }
+
+    #[test]
+    fn example_contacts_files_are_parsed() -> Result<()> {
+        let plain_text = include_str!("../../resources/network-contacts-example");
+        assert!(!parse_network_contacts(URL, None, plain_text)?.is_empty());
+
+        let json = include_str!("../../resources/network-contacts-example.json");
+        assert_eq!(parse_network_contacts(URL, None, json)?.len(), 3);
+        Ok(())
+    }
+}
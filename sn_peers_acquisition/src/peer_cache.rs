@@ -0,0 +1,283 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{error::Result, parse_peer_addr};
+use libp2p::Multiaddr;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::*;
+
+/// How long the cached peers are used for, before fetching the network contacts again.
+pub const DEFAULT_PEER_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Bump this whenever the layout of `PeerCacheFile` changes, older caches are then ignored.
+const PEER_CACHE_VERSION: u32 = 1;
+
+const PEER_CACHE_FILE_NAME: &str = "peer_cache.json";
+
+#[derive(Serialize, Deserialize)]
+struct PeerCacheFile {
+    version: u32,
+    /// Seconds since the unix epoch.
+    saved_at: u64,
+    /// The network contacts the peers were originally obtained from.
+    contacts_url: String,
+    peers: Vec<String>,
+}
+
+/// A local cache of the bootstrap peers that were successfully contacted.
+///
+/// It avoids fetching the network contacts on every start. The cache is tied to the network
+/// contacts URL it was obtained from, and is only used while it is fresher than its TTL.
+#[derive(Clone, Debug)]
+pub struct PeerCache {
+    path: PathBuf,
+    contacts_url: String,
+    ttl: Duration,
+}
+
+impl PeerCache {
+    /// Create a cache at the given path, for the peers obtained from `contacts_url`.
+    pub fn new(path: PathBuf, contacts_url: String) -> Self {
+        Self {
+            path,
+            contacts_url,
+            ttl: DEFAULT_PEER_CACHE_TTL,
+        }
+    }
+
+    /// The default location of the cache, under the user data dir.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs_next::data_dir().map(|dir| dir.join("safe").join(PEER_CACHE_FILE_NAME))
+    }
+
+    /// Set how long the cached peers are used for.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+
+    /// The path of the cache file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the cached peers.
+    ///
+    /// Returns `None` if there is no cache, or if it is stale, of another version, for other
+    /// network contacts, or holds no usable peer.
+    pub fn load(&self) -> Result<Option<Vec<Multiaddr>>> {
+        let bytes = match std::fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let cache: PeerCacheFile = serde_json::from_slice(&bytes)?;
+
+        if cache.version != PEER_CACHE_VERSION {
+            debug!(
+                "Ignoring peer cache of version {}, expected {PEER_CACHE_VERSION}",
+                cache.version
+            );
+            return Ok(None);
+        }
+        if cache.contacts_url != self.contacts_url {
+            debug!(
+                "Ignoring peer cache obtained from {}, expected {}",
+                cache.contacts_url, self.contacts_url
+            );
+            return Ok(None);
+        }
+        let age = unix_now().saturating_sub(cache.saved_at);
+        if age > self.ttl.as_secs() {
+            debug!(
+                "Ignoring peer cache saved {age}s ago, its TTL is {:?}",
+                self.ttl
+            );
+            return Ok(None);
+        }
+
+        let peers: Vec<_> = cache
+            .peers
+            .iter()
+            .filter_map(|peer| match parse_peer_addr(peer) {
+                Ok(peer) => Some(peer),
+                Err(err) => {
+                    warn!("Ignoring cached peer {peer:?}: {err}");
+                    None
+                }
+            })
+            .collect();
+        if peers.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(peers))
+    }
+
+    /// Save the given peers, replacing the current cache.
+    ///
+    /// The cache is first written to a temporary file which is then renamed over it, so a
+    /// reader never sees a partially written cache. Nothing is saved if there are no peers.
+    pub fn save(&self, peers: &[Multiaddr]) -> Result<()> {
+        if peers.is_empty() {
+            return Ok(());
+        }
+        let cache = PeerCacheFile {
+            version: PEER_CACHE_VERSION,
+            saved_at: unix_now(),
+            contacts_url: self.contacts_url.clone(),
+            peers: peers.iter().map(|peer| peer.to_string()).collect(),
+        };
+        let bytes = serde_json::to_vec_pretty(&cache)?;
+        write_atomically(&self.path, &bytes)?;
+
+        info!(
+            "Saved {} peers to the peer cache at {:?}",
+            peers.len(),
+            self.path
+        );
+        Ok(())
+    }
+
+    /// Remove the cache, so the network contacts are fetched on the next start.
+    pub fn clear(&self) -> Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Write the file through a temporary file renamed over it, so a reader never sees it partially
+/// written.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut tmp_path = path.to_path_buf().into_os_string();
+    tmp_path.push(format!(".{}.tmp", std::process::id()));
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTACTS_URL: &str = "https://example.com/network-contacts";
+
+    fn peers() -> Vec<Multiaddr> {
+        vec![
+            "/ip4/1.2.3.4/tcp/12000/p2p/12D3KooWRi6wF7yxWLuPSNskXc6kQ5cJ6eaymeMbCRdTnMesPgFx"
+                .parse()
+                .expect("valid multiaddr"),
+            "/ip6/2001:db8::1/tcp/12001"
+                .parse()
+                .expect("valid multiaddr"),
+        ]
+    }
+
+    fn write_cache_file(path: &Path, cache: &PeerCacheFile) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec(cache)?)?;
+        Ok(())
+    }
+
+    #[test]
+    fn saved_peers_are_loaded_back() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = PeerCache::new(dir.path().join("peers.json"), CONTACTS_URL.to_string());
+
+        assert_eq!(cache.load()?, None);
+        cache.save(&peers())?;
+        assert_eq!(cache.load()?, Some(peers()));
+
+        cache.clear()?;
+        assert_eq!(cache.load()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn stale_cache_is_ignored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut cache = PeerCache::new(dir.path().join("peers.json"), CONTACTS_URL.to_string());
+        cache.set_ttl(Duration::from_secs(60));
+
+        write_cache_file(
+            cache.path(),
+            &PeerCacheFile {
+                version: PEER_CACHE_VERSION,
+                saved_at: unix_now() - 120,
+                contacts_url: CONTACTS_URL.to_string(),
+                peers: peers().iter().map(|peer| peer.to_string()).collect(),
+            },
+        )?;
+        assert_eq!(cache.load()?, None);
+
+        cache.set_ttl(Duration::from_secs(180));
+        assert_eq!(cache.load()?, Some(peers()));
+        Ok(())
+    }
+
+    #[test]
+    fn cache_of_other_version_or_contacts_is_ignored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = PeerCache::new(dir.path().join("peers.json"), CONTACTS_URL.to_string());
+        let saved_peers: Vec<_> = peers().iter().map(|peer| peer.to_string()).collect();
+
+        write_cache_file(
+            cache.path(),
+            &PeerCacheFile {
+                version: PEER_CACHE_VERSION + 1,
+                saved_at: unix_now(),
+                contacts_url: CONTACTS_URL.to_string(),
+                peers: saved_peers.clone(),
+            },
+        )?;
+        assert_eq!(cache.load()?, None);
+
+        write_cache_file(
+            cache.path(),
+            &PeerCacheFile {
+                version: PEER_CACHE_VERSION,
+                saved_at: unix_now(),
+                contacts_url: "https://example.com/other-network-contacts".to_string(),
+                peers: saved_peers,
+            },
+        )?;
+        assert_eq!(cache.load()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn cache_without_usable_peers_is_ignored() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = PeerCache::new(dir.path().join("peers.json"), CONTACTS_URL.to_string());
+
+        write_cache_file(
+            cache.path(),
+            &PeerCacheFile {
+                version: PEER_CACHE_VERSION,
+                saved_at: unix_now(),
+                contacts_url: CONTACTS_URL.to_string(),
+                peers: vec!["not a peer".to_string()],
+            },
+        )?;
+        assert_eq!(cache.load()?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    parse_peer_addr,
+};
+use libp2p::Multiaddr;
+use std::path::Path;
+use tracing::*;
+
+/// Read the peers listed in a local file, one per line, in the same format as the plain text
+/// network contacts. Blank lines and lines starting with `#` are ignored.
+///
+/// Lines that aren't valid peers are skipped, but the file must hold at least one valid peer.
+/// Example exists in resources/peers-file-example
+pub(crate) fn read_peers_file(path: &Path) -> Result<Vec<Multiaddr>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| Error::PeersFileUnreadable(path.to_path_buf(), err.to_string()))?;
+
+    let mut peers = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_peer_addr(line) {
+            Ok(peer) => peers.push(peer),
+            Err(err) => warn!(
+                "Skipping invalid peer {line:?} at line {} of {path:?}: {err}",
+                line_number + 1
+            ),
+        }
+    }
+
+    if peers.is_empty() {
+        return Err(Error::NoValidPeerInPeersFile(path.to_path_buf()));
+    }
+    info!("Read {} peers from {path:?}", peers.len());
+    Ok(peers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn example_peers_file() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources/peers-file-example")
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() -> Result<()> {
+        let peers = read_peers_file(&example_peers_file())?;
+        let expected = [
+            "/ip4/142.93.232.219/tcp/38095/p2p/12D3KooWLAX6Z1m5gNxPGZQRV6VEzCtipNGcP1YhrAYNYT3yq5mv",
+            "/ip4/64.227.158.176/tcp/34893/p2p/12D3KooWG3cHz8aM9Zf2Gyar7NBb2BZ2wfcBf1zY7PHuUtn3EkvL",
+            "/ip4/147.182.237.224/tcp/46429/p2p/12D3KooWFCbRRe6GoyTvgbVqDK7VnD3TwEsM2876pBzuB1G3KHKj",
+            "139.59.125.187:33641",
+        ]
+        .iter()
+        .map(|peer| parse_peer_addr(peer))
+        .collect::<Result<Vec<_>>>()?;
+        assert_eq!(peers, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("peers");
+        std::fs::write(&path, "# testnet\nnot a peer\n\n1.2.3.4:12000\n")?;
+
+        assert_eq!(
+            read_peers_file(&path)?,
+            vec![parse_peer_addr("1.2.3.4:12000")?]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn file_without_valid_peers_errors() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("peers");
+        std::fs::write(&path, "# no peers yet\n\nnot a peer\n")?;
+
+        assert!(matches!(
+            read_peers_file(&path),
+            Err(Error::NoValidPeerInPeersFile(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn missing_file_errors() {
+        assert!(matches!(
+            read_peers_file(Path::new("/this/peers/file/does/not/exist")),
+            Err(Error::PeersFileUnreadable(..))
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use rand::Rng;
+use std::time::Duration;
+
+/// The default number of retries after a failed attempt to fetch the network contacts.
+pub const DEFAULT_NETWORK_CONTACTS_RETRIES: usize = 2;
+/// The default delay before the first retry to fetch the network contacts.
+pub const DEFAULT_NETWORK_CONTACTS_BACKOFF: Duration = Duration::from_secs(1);
+/// The default maximum delay between two retries to fetch the network contacts.
+pub const DEFAULT_NETWORK_CONTACTS_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// The default time an attempt to fetch the network contacts is given to complete.
+pub const DEFAULT_NETWORK_CONTACTS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How fetching the network contacts from a URL is retried.
+///
+/// The delay before each retry doubles, up to `max_backoff`. With `jitter` set, a random delay
+/// of up to half the backoff is added, so that clients failing together don't retry together.
+///
+/// An attempt not completed within `timeout` counts as a failed one, so that an endpoint
+/// blackholing the connection doesn't stall us.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkContactsRetry {
+    /// The number of retries after a first failed attempt.
+    pub retries: usize,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay before a retry, jitter excluded.
+    pub max_backoff: Duration,
+    pub jitter: bool,
+    /// The time each attempt is given to complete, response body included.
+    pub timeout: Duration,
+}
+
+impl Default for NetworkContactsRetry {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_NETWORK_CONTACTS_RETRIES,
+            initial_backoff: DEFAULT_NETWORK_CONTACTS_BACKOFF,
+            max_backoff: DEFAULT_NETWORK_CONTACTS_MAX_BACKOFF,
+            jitter: true,
+            timeout: DEFAULT_NETWORK_CONTACTS_TIMEOUT,
+        }
+    }
+}
+
+impl NetworkContactsRetry {
+    /// The backoff before the given retry, counting from 1, jitter excluded.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let factor = 2u32.checked_pow(exponent).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// The delay to wait for before the given retry, counting from 1.
+    pub(crate) fn delay(&self, retry: usize) -> Duration {
+        let backoff = self.backoff(retry);
+        if !self.jitter {
+            return backoff;
+        }
+        let max_jitter = backoff.as_millis() as u64 / 2;
+        backoff + Duration::from_millis(rand::thread_rng().gen_range(0..=max_jitter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_max() {
+        let retry = NetworkContactsRetry {
+            retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: false,
+            ..Default::default()
+        };
+        let schedule: Vec<_> = (1..=5).map(|retry_nb| retry.backoff(retry_nb)).collect();
+        assert_eq!(
+            schedule,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+        assert_eq!(retry.backoff(1_000), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_adds_up_to_half_the_backoff() {
+        let retry = NetworkContactsRetry {
+            retries: 1,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            jitter: true,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let delay = retry.delay(1);
+            assert!(delay >= Duration::from_millis(100));
+            assert!(delay <= Duration::from_millis(150));
+        }
+    }
+}
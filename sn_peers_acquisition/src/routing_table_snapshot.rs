@@ -0,0 +1,215 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    peer_cache::{unix_now, write_atomically},
+    peer_id_of,
+};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::*;
+
+/// The maximum number of peers kept in a routing table snapshot.
+pub const MAX_ROUTING_TABLE_SNAPSHOT_PEERS: usize = 300;
+
+/// How long a snapshot peer is given to accept a connection before being pruned at startup.
+pub const ROUTING_TABLE_SNAPSHOT_DIAL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The maximum number of addresses kept for each peer of a snapshot.
+const MAX_ADDRS_PER_PEER: usize = 3;
+
+/// Bump this whenever the layout of `SnapshotFile` changes, older snapshots are then ignored.
+const ROUTING_TABLE_SNAPSHOT_VERSION: u32 = 1;
+
+const ROUTING_TABLE_SNAPSHOT_FILE_NAME: &str = "routing_table_snapshot.json";
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    version: u32,
+    /// Seconds since the unix epoch.
+    saved_at: u64,
+    peers: Vec<SnapshotPeer>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotPeer {
+    peer_id: String,
+    addrs: Vec<String>,
+}
+
+/// A snapshot of the peers of a node's routing table, kept under its root dir.
+///
+/// A restarting node bootstraps from the peers it knew before, rather than from the network
+/// contacts only.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutingTableSnapshot {
+    path: PathBuf,
+}
+
+impl RoutingTableSnapshot {
+    /// The snapshot of the node with the given root dir.
+    pub fn new(root_dir: &Path) -> Self {
+        Self {
+            path: root_dir.join(ROUTING_TABLE_SNAPSHOT_FILE_NAME),
+        }
+    }
+
+    /// The path of the snapshot file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the peers of the snapshot, each address ending with the id of its peer.
+    ///
+    /// Returns no peer if there is no snapshot, or if it is of another version.
+    pub fn load(&self) -> Result<Vec<Multiaddr>> {
+        let bytes = match std::fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(self.error(err)),
+        };
+        let snapshot: SnapshotFile =
+            serde_json::from_slice(&bytes).map_err(|err| self.error(err))?;
+
+        if snapshot.version != ROUTING_TABLE_SNAPSHOT_VERSION {
+            debug!(
+                "Ignoring routing table snapshot of version {}, expected {ROUTING_TABLE_SNAPSHOT_VERSION}",
+                snapshot.version
+            );
+            return Ok(vec![]);
+        }
+        debug!(
+            "Loading the routing table snapshot saved {}s ago",
+            unix_now().saturating_sub(snapshot.saved_at)
+        );
+
+        let mut peers = vec![];
+        for entry in snapshot.peers {
+            let Ok(peer_id) = entry.peer_id.parse::<PeerId>() else {
+                warn!("Ignoring snapshot peer with invalid id {:?}", entry.peer_id);
+                continue;
+            };
+            for addr in entry.addrs {
+                match addr.parse::<Multiaddr>() {
+                    Ok(addr) => peers.push(with_peer_id(addr, peer_id)),
+                    Err(err) => warn!("Ignoring snapshot address {addr:?} of {peer_id}: {err}"),
+                }
+            }
+        }
+        Ok(peers)
+    }
+
+    /// Save the given routing table peers, replacing the current snapshot.
+    ///
+    /// At most `MAX_ROUTING_TABLE_SNAPSHOT_PEERS` peers are kept, the first ones given.
+    /// Nothing is saved if there are no peers with an address.
+    pub fn save(&self, peers: &[(PeerId, Vec<Multiaddr>)]) -> Result<()> {
+        let peers: Vec<_> = peers
+            .iter()
+            .filter(|(_, addrs)| !addrs.is_empty())
+            .take(MAX_ROUTING_TABLE_SNAPSHOT_PEERS)
+            .map(|(peer_id, addrs)| SnapshotPeer {
+                peer_id: peer_id.to_string(),
+                addrs: addrs
+                    .iter()
+                    .take(MAX_ADDRS_PER_PEER)
+                    .map(|addr| addr.to_string())
+                    .collect(),
+            })
+            .collect();
+        if peers.is_empty() {
+            return Ok(());
+        }
+        let count = peers.len();
+        let snapshot = SnapshotFile {
+            version: ROUTING_TABLE_SNAPSHOT_VERSION,
+            saved_at: unix_now(),
+            peers,
+        };
+        let bytes = serde_json::to_vec(&snapshot).map_err(|err| self.error(err))?;
+        write_atomically(&self.path, &bytes).map_err(|err| self.error(err))?;
+
+        debug!(
+            "Saved {count} peers to the routing table snapshot at {:?}",
+            self.path
+        );
+        Ok(())
+    }
+
+    fn error(&self, err: impl std::fmt::Display) -> Error {
+        Error::RoutingTableSnapshot(self.path.clone(), err.to_string())
+    }
+}
+
+/// The address, ending with the given peer id if it doesn't hold one already.
+fn with_peer_id(addr: Multiaddr, peer_id: PeerId) -> Multiaddr {
+    if peer_id_of(&addr).is_some() {
+        addr
+    } else {
+        addr.with(Protocol::P2p(peer_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> Multiaddr {
+        Multiaddr::from(std::net::Ipv4Addr::LOCALHOST).with(Protocol::Tcp(port))
+    }
+
+    #[test]
+    fn saved_peers_are_loaded_back_with_their_id() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let snapshot = RoutingTableSnapshot::new(dir.path());
+        assert_eq!(snapshot.load()?, vec![]);
+
+        let first = PeerId::random();
+        let second = PeerId::random();
+        snapshot.save(&[
+            (first, vec![addr(12000), addr(12001)]),
+            (PeerId::random(), vec![]),
+            (second, vec![addr(12002).with(Protocol::P2p(second))]),
+        ])?;
+
+        assert_eq!(
+            snapshot.load()?,
+            vec![
+                addr(12000).with(Protocol::P2p(first)),
+                addr(12001).with(Protocol::P2p(first)),
+                addr(12002).with(Protocol::P2p(second)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_is_bounded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let snapshot = RoutingTableSnapshot::new(dir.path());
+
+        let peers: Vec<_> = (0..MAX_ROUTING_TABLE_SNAPSHOT_PEERS + 50)
+            .map(|i| {
+                let addrs = (0..5).map(|j| addr((i * 5 + j) as u16)).collect();
+                (PeerId::random(), addrs)
+            })
+            .collect();
+        snapshot.save(&peers)?;
+
+        assert_eq!(
+            snapshot.load()?.len(),
+            MAX_ROUTING_TABLE_SNAPSHOT_PEERS * MAX_ADDRS_PER_PEER
+        );
+        Ok(())
+    }
+}
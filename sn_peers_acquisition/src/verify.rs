@@ -0,0 +1,295 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use futures::{stream, StreamExt};
+use libp2p::{multiaddr::Protocol, Multiaddr};
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
+};
+use tracing::*;
+
+/// How long a peer is given to accept a connection before being deemed unresponsive.
+pub const PEER_VERIFICATION_TIMEOUT: Duration = Duration::from_secs(2);
+/// The maximum number of peers being verified at once.
+const MAX_CONCURRENT_PEER_VERIFICATIONS: usize = 16;
+
+/// Keep only the peers responding within `timeout`, in the order they were given, see
+/// `responsive_peers`.
+///
+/// If no peer responds, all of them are returned anyway: the network may only be unreachable
+/// from here for now, and dialing them later is still the best bet.
+pub(crate) async fn retain_responsive_peers(
+    peers: Vec<Multiaddr>,
+    timeout: Duration,
+) -> Vec<Multiaddr> {
+    let responsive = responsive_peers(&peers, timeout).await;
+    if responsive.is_empty() {
+        warn!(
+            "None of the {} peers responded within {timeout:?}, using them all anyway",
+            peers.len()
+        );
+        return peers;
+    }
+    info!(
+        "{} of the {} peers responded within {timeout:?}",
+        responsive.len(),
+        peers.len()
+    );
+    responsive
+}
+
+/// The peers responding within `timeout`, in the order they were given, which may be none of
+/// them.
+///
+/// TCP peers have to accept a connection. UDP peers, e.g. over QUIC, have to not refuse a
+/// datagram: a closed port or an unreachable host is reported back, while a QUIC peer ignores a
+/// datagram too short to be a QUIC packet. The host names of `/dns`, `/dns4` and `/dns6`
+/// addresses have to resolve first. Peers with other addresses, e.g. `/dnsaddr`, are kept
+/// unchecked.
+pub async fn responsive_peers(peers: &[Multiaddr], timeout: Duration) -> Vec<Multiaddr> {
+    let checks: Vec<bool> = stream::iter(peers.iter())
+        .map(|peer| is_responsive(peer, timeout))
+        .buffered(MAX_CONCURRENT_PEER_VERIFICATIONS)
+        .collect()
+        .await;
+
+    peers
+        .iter()
+        .zip(checks)
+        .filter_map(|(peer, responsive)| responsive.then(|| peer.clone()))
+        .collect()
+}
+
+/// Whether `responsive_peers` checks that the peer accepts a connection, as it does for TCP peers.
+///
+/// UDP peers, e.g. over QUIC, are only checked not to refuse a datagram, which an unresponsive
+/// peer behind a firewall dropping it doesn't either, and peers with other addresses, e.g.
+/// `/dnsaddr`, aren't checked at all.
+pub fn is_connection_checked(peer: &Multiaddr) -> bool {
+    PeerEndpoint::of(peer).map_or(false, |endpoint| !endpoint.udp)
+}
+
+/// Whether the peer responds within `timeout`, see `responsive_peers`.
+async fn is_responsive(peer: &Multiaddr, timeout: Duration) -> bool {
+    let Some(endpoint) = PeerEndpoint::of(peer) else {
+        trace!("Can't verify the peer {peer}, assuming it is responsive");
+        return true;
+    };
+    let check = async {
+        let addr = endpoint.resolve().await?;
+        if endpoint.udp {
+            udp_not_refused(addr, timeout).await
+        } else {
+            tokio::net::TcpStream::connect(addr).await.map(|_| ())
+        }
+    };
+    match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => true,
+        Ok(Err(err)) => {
+            debug!("Peer {peer} is unresponsive: {err}");
+            false
+        }
+        Err(_) => {
+            debug!("Peer {peer} did not respond within {timeout:?}");
+            false
+        }
+    }
+}
+
+/// Send a datagram to the address, failing if it's refused within `timeout`. No answer is
+/// expected.
+async fn udp_not_refused(addr: SocketAddr, timeout: Duration) -> io::Result<()> {
+    let local = match addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = tokio::net::UdpSocket::bind(local).await?;
+    socket.connect(addr).await?;
+    let _ = socket.send(&[0]).await?;
+
+    // the refusal comes back as an error on the next receive, before the check times out
+    let mut buf = [0; 1];
+    match tokio::time::timeout(timeout / 2, socket.recv(&mut buf)).await {
+        Ok(Err(err)) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Where a peer is reached at: its host, port, and whether over UDP rather than TCP.
+#[derive(Debug, PartialEq, Eq)]
+struct PeerEndpoint {
+    host: PeerHost,
+    port: u16,
+    udp: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PeerHost {
+    Ip(IpAddr),
+    /// A host name, resolving to IPv4 addresses only, to IPv6 ones only, or to either.
+    Name {
+        name: String,
+        ipv4: Option<bool>,
+    },
+}
+
+impl PeerEndpoint {
+    /// The endpoint of an address starting with `/ip4`, `/ip6`, `/dns`, `/dns4` or `/dns6`,
+    /// followed by `/tcp` or `/udp`.
+    fn of(peer: &Multiaddr) -> Option<Self> {
+        let mut protocols = peer.iter();
+        let host = match protocols.next()? {
+            Protocol::Ip4(ip) => PeerHost::Ip(IpAddr::V4(ip)),
+            Protocol::Ip6(ip) => PeerHost::Ip(IpAddr::V6(ip)),
+            Protocol::Dns(name) => PeerHost::Name {
+                name: name.to_string(),
+                ipv4: None,
+            },
+            Protocol::Dns4(name) => PeerHost::Name {
+                name: name.to_string(),
+                ipv4: Some(true),
+            },
+            Protocol::Dns6(name) => PeerHost::Name {
+                name: name.to_string(),
+                ipv4: Some(false),
+            },
+            _ => return None,
+        };
+        let (port, udp) = match protocols.next()? {
+            Protocol::Tcp(port) => (port, false),
+            Protocol::Udp(port) => (port, true),
+            _ => return None,
+        };
+        Some(Self { host, port, udp })
+    }
+
+    /// The socket address of the endpoint, resolving its host name if it has one.
+    async fn resolve(&self) -> io::Result<SocketAddr> {
+        let (name, ipv4) = match &self.host {
+            PeerHost::Ip(ip) => return Ok(SocketAddr::new(*ip, self.port)),
+            PeerHost::Name { name, ipv4 } => (name, ipv4),
+        };
+        tokio::net::lookup_host((name.as_str(), self.port))
+            .await?
+            .find(|addr| ipv4.map_or(true, |ipv4| addr.is_ipv4() == ipv4))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{name} has no address of the expected family"),
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Result;
+
+    fn tcp_peer(addr: SocketAddr) -> Multiaddr {
+        Multiaddr::from(addr.ip()).with(Protocol::Tcp(addr.port()))
+    }
+
+    fn quic_peer(addr: SocketAddr) -> Multiaddr {
+        Multiaddr::from(addr.ip())
+            .with(Protocol::Udp(addr.port()))
+            .with(Protocol::QuicV1)
+    }
+
+    fn multiaddr(addr: &str) -> Result<Multiaddr> {
+        addr.parse()
+            .map_err(|_| crate::error::Error::InvalidPeerAddr)
+    }
+
+    #[test]
+    fn endpoints_are_read_off_ip_and_dns_multiaddrs() -> Result<()> {
+        let tcp = multiaddr(
+            "/ip4/1.2.3.4/tcp/12000/p2p/12D3KooWRi6wF7yxWLuPSNskXc6kQ5cJ6eaymeMbCRdTnMesPgFx",
+        )?;
+        assert_eq!(
+            PeerEndpoint::of(&tcp),
+            Some(PeerEndpoint {
+                host: PeerHost::Ip(IpAddr::from([1, 2, 3, 4])),
+                port: 12000,
+                udp: false,
+            })
+        );
+        let quic = multiaddr("/dns6/node.example/udp/12000/quic-v1")?;
+        assert_eq!(
+            PeerEndpoint::of(&quic),
+            Some(PeerEndpoint {
+                host: PeerHost::Name {
+                    name: "node.example".to_string(),
+                    ipv4: Some(false),
+                },
+                port: 12000,
+                udp: true,
+            })
+        );
+        assert_eq!(PeerEndpoint::of(&multiaddr("/dnsaddr/node.example")?), None);
+        Ok(())
+    }
+
+    #[test]
+    fn only_tcp_peers_are_checked_to_accept_a_connection() -> Result<()> {
+        assert!(is_connection_checked(&multiaddr("/ip4/1.2.3.4/tcp/12000")?));
+        assert!(is_connection_checked(&multiaddr(
+            "/dns4/node.example/tcp/12000"
+        )?));
+        assert!(!is_connection_checked(&multiaddr(
+            "/ip4/1.2.3.4/udp/12000/quic-v1"
+        )?));
+        assert!(!is_connection_checked(&multiaddr("/dnsaddr/node.example")?));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unresponsive_peers_are_dropped_keeping_the_order() -> Result<()> {
+        let first = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let second = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let udp = tokio::net::UdpSocket::bind("127.0.0.1:0").await?;
+        // nothing listens on port 1
+        let closed = SocketAddr::from(([127, 0, 0, 1], 1));
+        let peers = vec![
+            tcp_peer(second.local_addr()?),
+            tcp_peer(closed),
+            multiaddr(&format!(
+                "/dns4/localhost/tcp/{}",
+                first.local_addr()?.port()
+            ))?,
+            quic_peer(udp.local_addr()?),
+            quic_peer(closed),
+            multiaddr("/dns4/unresolvable.invalid/tcp/12000")?,
+            multiaddr("/dnsaddr/unresolvable.invalid")?,
+        ];
+
+        let responsive = retain_responsive_peers(peers.clone(), PEER_VERIFICATION_TIMEOUT).await;
+        assert_eq!(
+            responsive,
+            vec![
+                peers[0].clone(),
+                peers[2].clone(),
+                peers[3].clone(),
+                peers[6].clone()
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn all_peers_are_kept_if_none_responds() {
+        let peers = vec![
+            tcp_peer(SocketAddr::from(([127, 0, 0, 1], 1))),
+            tcp_peer(SocketAddr::from(([127, 0, 0, 1], 2))),
+        ];
+        let responsive = retain_responsive_peers(peers.clone(), PEER_VERIFICATION_TIMEOUT).await;
+        assert_eq!(responsive, peers);
+    }
+}
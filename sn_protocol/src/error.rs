@@ -8,6 +8,7 @@
 
 use crate::{storage::RegisterAddress, NetworkAddress, PrettyPrintRecordKey};
 use serde::{Deserialize, Serialize};
+use sn_transfers::RoyaltyRate;
 use thiserror::Error;
 
 /// A specialised `Result` type for protocol crate.
@@ -20,18 +21,37 @@ pub enum Error {
     // ---------- Chunk Proof errors
     #[error("Chunk does not exist {0:?}")]
     ChunkDoesNotExist(NetworkAddress),
+    #[error("Could not parse a ChunkProof: {0}")]
+    ChunkProofParsingFailed(String),
 
     // ---------- Register Errors
     #[error("Register not found: {0}")]
     RegisterNotFound(Box<RegisterAddress>),
     #[error("The Register was already created by another owner: {0:?}")]
     RegisterAlreadyClaimed(bls::PublicKey),
+    #[error("The claim on Register {0:?} isn't signed by its owner")]
+    RegisterClaimInvalid(Box<RegisterAddress>),
 
     // ---------- payment errors
     #[error("There was an error getting the storecost from kademlia store")]
     GetStoreCostFailed,
     #[error("There was an error generating the payment quote")]
     QuoteGenerationFailed,
+    #[error("The network royalties were paid at {theirs:?}, while we expect them at {ours:?}")]
+    RoyaltyRateMismatch {
+        /// The rate the node expects
+        ours: RoyaltyRate,
+        /// The rate the payment was made at
+        theirs: RoyaltyRate,
+    },
+
+    // ---------- backpressure errors
+    /// The node has too much inbound work queued to take on more for now.
+    #[error("The node is busy, retry after {retry_after_ms}ms")]
+    Busy {
+        /// How long the node asks to be left alone for, in milliseconds
+        retry_after_ms: u64,
+    },
 
     // ---------- replication errors
     /// Replication not found.
@@ -53,4 +73,7 @@ pub enum Error {
     // The record already exists at this node
     #[error("The record already exists, so do not charge for it: {0:?}")]
     RecordExists(PrettyPrintRecordKey<'static>),
+    // The record read off disk isn't a valid record stored under its key
+    #[error("{0}")]
+    InvalidStoredRecord(String),
 }
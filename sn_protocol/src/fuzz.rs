@@ -0,0 +1,249 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Fuzz harnesses for the decoding of the untrusted bytes nodes receive: records, and the
+//! messages of the protocol.
+//!
+//! Each harness decodes arbitrary bytes, as well as valid encodings with some of their bytes
+//! overwritten or cut off, and then handles what was decoded the way nodes do, logging it
+//! included. Decoding is free to fail, but never to panic.
+//!
+//! They run with the unit tests. Set `PROPTEST_CASES` to fuzz for longer, as CI does:
+//! `PROPTEST_CASES=100000 cargo test --release -p sn_protocol fuzz_`
+
+use crate::{
+    messages::{Cmd, Nonce, Query, RegisterClaim, Request, Response, SignedRegisterClaim},
+    storage::{
+        try_deserialize_record, try_serialize_record, Chunk, RecordHeader, RecordKind, RecordType,
+    },
+    NetworkAddress,
+};
+use bytes::Bytes;
+use libp2p::kad::{Record, RecordKey};
+use proptest::{prelude::*, sample::Index};
+use sn_registers::{Register, RegisterAddress, SignedRegister};
+use sn_transfers::{Payment, SignedSpend, SpendAddress, Transaction, GENESIS_CASHNOTE};
+use xor_name::XorName;
+
+/// The largest arbitrary input, well above the size of the valid encodings mutated.
+const MAX_INPUT_LEN: usize = 4096;
+
+fn arbitrary_bytes() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..MAX_INPUT_LEN)
+}
+
+/// The `seed` encoding with a few of its bytes overwritten, and maybe cut off at some point.
+fn mutated(seed: Vec<u8>) -> impl Strategy<Value = Vec<u8>> {
+    (
+        prop::collection::vec((any::<Index>(), any::<u8>()), 1..8),
+        prop::option::of(any::<Index>()),
+    )
+        .prop_map(move |(writes, cut)| {
+            let mut bytes = seed.clone();
+            for (at, byte) in writes {
+                let at = at.index(bytes.len());
+                bytes[at] = byte;
+            }
+            if let Some(cut) = cut {
+                bytes.truncate(cut.index(seed.len() + 1));
+            }
+            bytes
+        })
+}
+
+fn record_seeds() -> Vec<Vec<u8>> {
+    let chunk = Chunk::new(Bytes::from_static(b"fuzzed chunk content"));
+    let spends: Vec<SignedSpend> = GENESIS_CASHNOTE.signed_spends.iter().cloned().collect();
+    let owner = bls::SecretKey::random();
+    let register = Register::new_owned(owner.public_key(), XorName::from_content(b"fuzz"))
+        .into_signed(&owner)
+        .expect("register to be signed");
+
+    [
+        try_serialize_record(&chunk, RecordKind::Chunk),
+        try_serialize_record(&spends, RecordKind::Spend),
+        try_serialize_record(&register, RecordKind::Register),
+    ]
+    .into_iter()
+    .map(|record| record.expect("seed record to serialise").to_vec())
+    .collect()
+}
+
+fn request_seeds() -> Vec<Vec<u8>> {
+    let key = NetworkAddress::from_record_key(&RecordKey::new(&XorName::from_content(b"fuzz")));
+    let owner = bls::SecretKey::random().public_key();
+    let requests = [
+        Request::Query(Query::GetStoreCost(key.clone())),
+        Request::Query(Query::GetReplicatedRecord {
+            requester: key.clone(),
+            key: key.clone(),
+        }),
+        Request::Query(Query::GetChunkExistenceProof {
+            key: key.clone(),
+            nonce: Nonce::MAX,
+        }),
+        Request::Query(Query::ClaimRegister {
+            address: RegisterAddress::new(XorName::from_content(b"fuzz"), owner),
+            claim: SignedRegisterClaim {
+                claim: RegisterClaim {
+                    claimant: owner,
+                    claimed_at: 0,
+                    nonce: 0,
+                },
+                signature: bls::SecretKey::random().sign(b"fuzz"),
+            },
+        }),
+        Request::Query(Query::GetSpendConflicts {
+            address: SpendAddress::from_unique_pubkey(&GENESIS_CASHNOTE.unique_pubkey()),
+        }),
+        Request::Cmd(Cmd::Replicate {
+            holder: key.clone(),
+            keys: vec![(key, RecordType::Chunk)],
+        }),
+    ];
+    requests
+        .iter()
+        .map(|request| rmp_serde::to_vec(request).expect("seed request to serialise"))
+        .collect()
+}
+
+/// An address of any kind, or made of any bytes, as a peer may send.
+fn network_address() -> impl Strategy<Value = NetworkAddress> {
+    prop_oneof![
+        prop::collection::vec(any::<u8>(), 0..64)
+            .prop_map(|bytes| NetworkAddress::RecordKey(Bytes::from(bytes))),
+        prop::collection::vec(any::<u8>(), 0..64)
+            .prop_map(|bytes| NetworkAddress::PeerId(Bytes::from(bytes))),
+        any::<[u8; 32]>().prop_map(
+            |bytes| NetworkAddress::from_spend_address(SpendAddress::new(XorName(bytes)))
+        ),
+    ]
+}
+
+/// Decode a record the way nodes do, whatever its header says it holds.
+fn handle_record(value: Vec<u8>) {
+    let record = Record::new(RecordKey::new(&XorName::from_content(&value)), value);
+    if let Ok(header) = RecordHeader::from_record(&record) {
+        let _ = header.kind.to_string();
+    }
+    let _ = RecordHeader::is_record_of_type_chunk(&record);
+
+    if let Ok(chunk) = try_deserialize_record::<Chunk>(&record) {
+        let _ = format!("{chunk:?} {:?}", chunk.network_address());
+    }
+    if let Ok((payment, chunk)) = try_deserialize_record::<(Payment, Chunk)>(&record) {
+        let _ = format!("{payment:?} {chunk:?}");
+    }
+    if let Ok(spends) = try_deserialize_record::<Vec<SignedSpend>>(&record) {
+        for spend in spends {
+            let _ = format!("{spend:?}");
+            let _ = spend.verify(spend.spent_tx_hash());
+            let _ = spend.spent_tx().hash();
+        }
+    }
+    if let Ok(register) = try_deserialize_record::<SignedRegister>(&record) {
+        let _ = format!("{:?}", register.address());
+        let _ = register.verify();
+    }
+    if let Ok((payment, register, claim)) =
+        try_deserialize_record::<(Payment, SignedRegister, SignedRegisterClaim)>(&record)
+    {
+        let _ = format!("{payment:?} {:?} {claim:?}", register.address());
+        let _ = register.verify();
+        let _ = claim.verify(register.address());
+    }
+    if let Ok((payment, register)) = try_deserialize_record::<(Payment, SignedRegister)>(&record) {
+        let _ = format!("{payment:?} {:?}", register.address());
+        let _ = register.verify();
+    }
+}
+
+/// Decode a request or a response the way nodes and clients do, logging it included.
+fn handle_message(bytes: &[u8]) {
+    if let Ok(request) = rmp_serde::from_slice::<Request>(bytes) {
+        let _ = format!("{request:?} {:?}", request.dst());
+        match request {
+            Request::Cmd(cmd) => {
+                let _ = format!("{cmd}");
+            }
+            Request::Query(query) => {
+                let _ = format!("{query}");
+            }
+        }
+    }
+    if let Ok(response) = rmp_serde::from_slice::<Response>(bytes) {
+        let _ = format!("{response}");
+    }
+}
+
+proptest! {
+    #[test]
+    fn fuzz_records_from_arbitrary_bytes(value in arbitrary_bytes()) {
+        handle_record(value);
+    }
+
+    #[test]
+    fn fuzz_records_from_mutated_records(
+        value in prop::sample::select(record_seeds()).prop_flat_map(mutated)
+    ) {
+        handle_record(value);
+    }
+
+    #[test]
+    fn fuzz_records_behind_a_valid_header(
+        kind in prop::sample::select(vec![
+            RecordKind::Chunk,
+            RecordKind::ChunkWithPayment,
+            RecordKind::Spend,
+            RecordKind::Register,
+            RecordKind::RegisterWithPayment,
+        ]),
+        body in arbitrary_bytes(),
+    ) {
+        let mut value = RecordHeader { kind }
+            .try_serialize()
+            .expect("header to serialise")
+            .to_vec();
+        value.extend(body);
+        handle_record(value);
+    }
+
+    #[test]
+    fn fuzz_transactions(
+        bytes in prop_oneof![
+            arbitrary_bytes(),
+            mutated(rmp_serde::to_vec(&GENESIS_CASHNOTE.src_tx).expect("tx to serialise")),
+        ]
+    ) {
+        if let Ok(tx) = rmp_serde::from_slice::<Transaction>(&bytes) {
+            let _ = format!("{tx:?} {:?}", tx.hash());
+        }
+    }
+
+    #[test]
+    fn fuzz_messages_from_arbitrary_bytes(bytes in arbitrary_bytes()) {
+        handle_message(&bytes);
+    }
+
+    #[test]
+    fn fuzz_messages_from_mutated_requests(
+        bytes in prop::sample::select(request_seeds()).prop_flat_map(mutated)
+    ) {
+        handle_message(&bytes);
+    }
+
+    #[test]
+    fn fuzz_messages_about_any_address(
+        key in network_address(),
+        requester in network_address(),
+    ) {
+        let request = Request::Query(Query::GetReplicatedRecord { requester, key });
+        let bytes = rmp_serde::to_vec(&request).expect("request to serialise");
+        handle_message(&bytes);
+    }
+}
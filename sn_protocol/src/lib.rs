@@ -11,6 +11,8 @@ extern crate tracing;
 
 /// Errors.
 pub mod error;
+#[cfg(test)]
+mod fuzz;
 /// Messages types
 pub mod messages;
 /// RPC commands to node
@@ -199,10 +201,14 @@ impl Debug for NetworkAddress {
                 "NetworkAddress::RegisterAddress({} - ",
                 &register_address.to_hex()[0..6]
             ),
-            NetworkAddress::RecordKey(bytes) => format!(
-                "NetworkAddress::RecordKey({} - ",
-                &PrettyPrintRecordKey::from(&RecordKey::new(bytes)).no_kbucket_log()[0..6]
-            ),
+            NetworkAddress::RecordKey(bytes) => {
+                // record keys come from peers, they may be shorter than the 6 chars logged
+                let key = PrettyPrintRecordKey::from(&RecordKey::new(bytes)).no_kbucket_log();
+                format!(
+                    "NetworkAddress::RecordKey({} - ",
+                    key.get(0..6).unwrap_or(&key)
+                )
+            }
         };
         write!(
             f,
@@ -421,4 +427,13 @@ mod tests {
 
         assert!(net_addr_fmt.contains(spend_addr_hex));
     }
+
+    #[test]
+    fn short_record_keys_are_logged_whole() {
+        let net_addr = NetworkAddress::RecordKey(Bytes::from_static(&[0xab, 0xcd]));
+        assert!(format!("{net_addr:?}").starts_with("NetworkAddress::RecordKey(abcd - "));
+
+        let net_addr = NetworkAddress::RecordKey(Bytes::new());
+        assert!(format!("{net_addr:?}").starts_with("NetworkAddress::RecordKey( - "));
+    }
 }
@@ -6,17 +6,43 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-/// The nonce provided by the verifier
+/// The nonce provided by the verifier.
+///
+/// It is appended to the record value as 8 big-endian bytes before hashing.
 pub type Nonce = u64;
 
-/// The hash(record_value + nonce) that is used to prove the existence of a chunk
+/// The length of a `ChunkProof`, in bytes.
+const CHUNK_PROOF_LEN: usize = 32;
+
+/// The sha3_256(record_value + nonce) that is used to prove the existence of a chunk.
+///
+/// A `ChunkProof` is stored with the same serialisation it has on the wire, so it can be
+/// re-verified later:
+///
+/// ```
+/// use sn_protocol::messages::ChunkProof;
+///
+/// let stored_bytes = b"the record value as stored by the node";
+/// let nonce = 42;
+///
+/// // the proof handed over by the node being audited
+/// let proof = ChunkProof::new(stored_bytes, nonce);
+/// let serialised = rmp_serde::to_vec(&proof).expect("proof to serialise");
+///
+/// // later on, by the auditor holding a copy of the data
+/// let proof: ChunkProof = rmp_serde::from_slice(&serialised).expect("proof to deserialise");
+/// assert!(proof.verify(stored_bytes, nonce));
+/// assert!(!proof.verify(stored_bytes, nonce + 1));
+/// ```
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq)]
-pub struct ChunkProof([u8; 32]);
+pub struct ChunkProof([u8; CHUNK_PROOF_LEN]);
 
 impl ChunkProof {
+    /// Compute the proof for the record value, as stored by a node, and the nonce.
     pub fn new(record_value: &[u8], nonce: Nonce) -> Self {
         let nonce_bytes = nonce.to_be_bytes();
         let combined = [record_value, &nonce_bytes].concat();
@@ -24,21 +50,39 @@ impl ChunkProof {
         ChunkProof(hash)
     }
 
-    pub fn verify(&self, other_proof: &ChunkProof) -> bool {
-        self.0 == other_proof.0
+    /// Check this proof was computed over the stored bytes with the nonce.
+    pub fn verify(&self, stored_bytes: &[u8], nonce: Nonce) -> bool {
+        self == &Self::new(stored_bytes, nonce)
     }
 
     /// Serialize this `ChunkProof` instance to a hex string.
-    fn to_hex(&self) -> String {
+    pub fn to_hex(&self) -> String {
         hex::encode(self.0)
     }
+
+    /// Deserialize a hex string into a `ChunkProof`.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes =
+            hex::decode(hex).map_err(|err| Error::ChunkProofParsingFailed(err.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let proof = bytes.try_into().map_err(|_| {
+            Error::ChunkProofParsingFailed(format!(
+                "expected {CHUNK_PROOF_LEN} bytes, got {}",
+                bytes.len()
+            ))
+        })?;
+        Ok(ChunkProof(proof))
+    }
 }
 
-fn sha3_256(input: &[u8]) -> [u8; 32] {
+fn sha3_256(input: &[u8]) -> [u8; CHUNK_PROOF_LEN] {
     use tiny_keccak::{Hasher, Sha3};
 
     let mut sha3 = Sha3::v256();
-    let mut output = [0; 32];
+    let mut output = [0; CHUNK_PROOF_LEN];
     sha3.update(input);
     sha3.finalize(&mut output);
     output
@@ -49,3 +93,77 @@ impl fmt::Debug for ChunkProof {
         f.debug_tuple("ChunkProof").field(&self.to_hex()).finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pinned vectors, any change here breaks the proofs stored by auditors.
+    const RECORD_VALUE: &[u8] = b"safe network chunk";
+    const NONCE: Nonce = 42;
+    const PROOF_HEX: &str = "173ac3ab0c2799c5b47f7df208299f40a4153debfa3ff456367ffd4e7a83c2c2";
+    const EMPTY_RECORD_ZERO_NONCE_PROOF_HEX: &str =
+        "48dda5bbe9171a6656206ec56c595c5834b6cf38c5fe71bcb44fe43833aee9df";
+
+    #[test]
+    fn proof_matches_pinned_vectors() {
+        assert_eq!(ChunkProof::new(RECORD_VALUE, NONCE).to_hex(), PROOF_HEX);
+        assert_eq!(
+            ChunkProof::new(&[], 0).to_hex(),
+            EMPTY_RECORD_ZERO_NONCE_PROOF_HEX
+        );
+    }
+
+    #[test]
+    fn proof_verifies_only_with_same_bytes_and_nonce() -> Result<()> {
+        let proof = ChunkProof::from_hex(PROOF_HEX)?;
+        assert!(proof.verify(RECORD_VALUE, NONCE));
+        assert!(!proof.verify(RECORD_VALUE, NONCE + 1));
+        assert!(!proof.verify(b"safe network chunk!", NONCE));
+        Ok(())
+    }
+
+    #[test]
+    fn proof_serialises_to_pinned_msgpack() -> color_eyre::Result<()> {
+        let proof = ChunkProof::new(RECORD_VALUE, NONCE);
+        let serialised = rmp_serde::to_vec(&proof)?;
+
+        // msgpack array 16 header, followed by the 32 bytes of the proof as integers
+        let mut expected = vec![0xdc, 0x00, 0x20];
+        for byte in hex::decode(PROOF_HEX)? {
+            if byte >= 0x80 {
+                expected.push(0xcc);
+            }
+            expected.push(byte);
+        }
+        assert_eq!(serialised, expected);
+
+        let deserialised: ChunkProof = rmp_serde::from_slice(&serialised)?;
+        assert_eq!(deserialised, proof);
+        Ok(())
+    }
+
+    #[test]
+    fn proof_of_wrong_length_fails_to_parse() {
+        assert!(matches!(
+            ChunkProof::from_hex("173ac3ab"),
+            Err(Error::ChunkProofParsingFailed(_))
+        ));
+    }
+
+    #[test]
+    fn proof_from_a_sequence_of_wrong_length_fails_to_parse() -> color_eyre::Result<()> {
+        for len in [
+            0,
+            CHUNK_PROOF_LEN - 1,
+            CHUNK_PROOF_LEN + 1,
+            10 * CHUNK_PROOF_LEN,
+        ] {
+            let serialised = rmp_serde::to_vec(&vec![0u8; len])?;
+            assert!(rmp_serde::from_slice::<ChunkProof>(&serialised).is_err());
+        }
+        let serialised = rmp_serde::to_vec(&vec![0u8; CHUNK_PROOF_LEN])?;
+        assert!(rmp_serde::from_slice::<ChunkProof>(&serialised).is_ok());
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@ mod cmd;
 mod node_id;
 mod query;
 mod register;
+mod register_claim;
 mod response;
 
 pub use self::{
@@ -20,6 +21,7 @@ pub use self::{
     node_id::NodeId,
     query::Query,
     register::RegisterCmd,
+    register_claim::{RegisterClaim, SignedRegisterClaim, REGISTER_CLAIM_GRACE_WINDOW},
     response::{CmdOk, CmdResponse, QueryResponse},
 };
 
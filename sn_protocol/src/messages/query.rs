@@ -6,7 +6,11 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::{messages::Nonce, NetworkAddress};
+use crate::{
+    messages::{Nonce, SignedRegisterClaim},
+    storage::{RegisterAddress, SpendAddress},
+    NetworkAddress,
+};
 use serde::{Deserialize, Serialize};
 
 /// Data queries - retrieving data and inspecting their structure.
@@ -38,6 +42,26 @@ pub enum Query {
         /// The random nonce that the node uses to produce the Proof (i.e., hash(record+nonce))
         nonce: Nonce,
     },
+    /// Have the node hold a claim on a Register which is about to be paid for and uploaded.
+    ///
+    /// This should eventually lead to a [`ClaimRegister`] response.
+    ///
+    /// [`ClaimRegister`]: super::QueryResponse::ClaimRegister
+    ClaimRegister {
+        /// The address of the Register being created.
+        address: RegisterAddress,
+        /// The claim to hold, signed by the owner of the Register.
+        claim: SignedRegisterClaim,
+    },
+    /// Retrieve the conflicting spends a node holds for the given address, if any.
+    ///
+    /// This should eventually lead to a [`GetSpendConflicts`] response.
+    ///
+    /// [`GetSpendConflicts`]: super::QueryResponse::GetSpendConflicts
+    GetSpendConflicts {
+        /// The address of the spend.
+        address: SpendAddress,
+    },
 }
 
 impl Query {
@@ -49,6 +73,8 @@ impl Query {
             // and the destination shall be decided by the requester already.
             Query::GetReplicatedRecord { key, .. } => key.clone(),
             Query::GetChunkExistenceProof { key, .. } => key.clone(),
+            Query::ClaimRegister { address, .. } => NetworkAddress::from_register_address(*address),
+            Query::GetSpendConflicts { address } => NetworkAddress::from_spend_address(*address),
         }
     }
 }
@@ -65,6 +91,12 @@ impl std::fmt::Display for Query {
             Query::GetChunkExistenceProof { key, nonce } => {
                 write!(f, "Query::GetChunkExistenceProof({key:?} {nonce:?})")
             }
+            Query::ClaimRegister { address, claim } => {
+                write!(f, "Query::ClaimRegister({address:?} {claim:?})")
+            }
+            Query::GetSpendConflicts { address } => {
+                write!(f, "Query::GetSpendConflicts({address:?})")
+            }
         }
     }
 }
@@ -6,7 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use sn_registers::{Register, RegisterAddress, RegisterOp};
+use sn_registers::{OwnershipTransfer, Register, RegisterAddress, RegisterOp};
 
 use serde::{Deserialize, Serialize};
 
@@ -23,6 +23,8 @@ pub enum RegisterCmd {
     },
     /// Edit the register
     Edit(RegisterOp),
+    /// Hand the register over to a new owner
+    TransferOwnership(OwnershipTransfer),
 }
 
 /// Custom debug implementation to avoid printing the whole register
@@ -34,6 +36,13 @@ impl std::fmt::Debug for RegisterCmd {
                 write!(f, "RegisterCmd::Create({:?})", register.address())
             }
             RegisterCmd::Edit(op) => write!(f, "RegisterCmd::Edit({:?})", op.address()),
+            RegisterCmd::TransferOwnership(transfer) => {
+                write!(
+                    f,
+                    "RegisterCmd::TransferOwnership({:?})",
+                    transfer.address()
+                )
+            }
         }
     }
 }
@@ -44,6 +53,7 @@ impl RegisterCmd {
         match self {
             Self::Create { register, .. } => *register.address(),
             Self::Edit(op) => op.address(),
+            Self::TransferOwnership(transfer) => transfer.address(),
         }
     }
 }
@@ -0,0 +1,178 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    error::{Error, Result},
+    storage::RegisterAddress,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// For how long nodes hold a claim on a Register being created, rejecting competing creations.
+/// This gives the claimant the time to pay for and upload the Register.
+pub const REGISTER_CLAIM_GRACE_WINDOW: Duration = Duration::from_secs(60);
+
+/// A claim on the creation of a Register, marking it as still being uploaded.
+///
+/// The claimant first has the close group of the Register hold its claim, and only then pays
+/// for the Register and puts it along with the same claim. Any other creation attempt of the
+/// Register is rejected until the claim expires, so no one pays for a Register they can't create.
+///
+/// Claims travel as a [`SignedRegisterClaim`]. Nodes time claims by their own clock, the
+/// `claimed_at` of the claims they receive being ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RegisterClaim {
+    /// The public key of the client creating the Register.
+    pub claimant: bls::PublicKey,
+    /// When the claim was made, in milliseconds since the UNIX epoch.
+    pub claimed_at: u64,
+    /// Tells apart the claims of two clients sharing the same key.
+    /// A client renews its claim by claiming again under the same nonce.
+    pub nonce: u64,
+}
+
+impl RegisterClaim {
+    /// A claim made now by the given claimant.
+    pub fn new(claimant: bls::PublicKey, nonce: u64) -> Self {
+        Self {
+            claimant,
+            claimed_at: now_millis(),
+            nonce,
+        }
+    }
+
+    /// The time left before the claim expires, zero if it already has.
+    pub fn remaining(&self) -> Duration {
+        let expires_at = self
+            .claimed_at
+            .saturating_add(REGISTER_CLAIM_GRACE_WINDOW.as_millis() as u64);
+        Duration::from_millis(expires_at.saturating_sub(now_millis()))
+    }
+
+    /// Whether both claims were made by the same client, one renewing the other.
+    pub fn is_same_claimer(&self, other: &RegisterClaim) -> bool {
+        self.claimant == other.claimant && self.nonce == other.nonce
+    }
+
+    /// Whether the claim is past its grace window.
+    pub fn has_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// The bytes the owner of the Register signs to make the claim: the address of the
+    /// Register, the claimant and the nonce. The time of the claim is left to the nodes.
+    pub fn bytes_for_signing(&self, address: &RegisterAddress) -> Vec<u8> {
+        let mut bytes = address.xorname().0.to_vec();
+        bytes.extend(address.owner().to_bytes());
+        bytes.extend(self.claimant.to_bytes());
+        bytes.extend(self.nonce.to_le_bytes());
+        bytes
+    }
+}
+
+/// A claim on the creation of a Register, signed by the owner of the Register.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedRegisterClaim {
+    /// The claim made.
+    pub claim: RegisterClaim,
+    /// The signature of the owner of the Register over `RegisterClaim::bytes_for_signing`.
+    pub signature: bls::Signature,
+}
+
+impl SignedRegisterClaim {
+    /// Sign the claim on the Register at the given address with the secret key of its owner.
+    pub fn new(address: &RegisterAddress, claim: RegisterClaim, owner: &bls::SecretKey) -> Self {
+        let signature = owner.sign(claim.bytes_for_signing(address));
+        Self { claim, signature }
+    }
+
+    /// Check the claim is made by the owner of the Register at the given address.
+    pub fn verify(&self, address: &RegisterAddress) -> Result<()> {
+        if self.claim.claimant == address.owner()
+            && self
+                .claim
+                .claimant
+                .verify(&self.signature, self.claim.bytes_for_signing(address))
+        {
+            Ok(())
+        } else {
+            Err(Error::RegisterClaimInvalid(Box::new(*address)))
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_expire_after_the_grace_window() {
+        let claimant = bls::SecretKey::random().public_key();
+
+        let claim = RegisterClaim::new(claimant, 0);
+        assert!(!claim.has_expired());
+        assert!(claim.remaining() <= REGISTER_CLAIM_GRACE_WINDOW);
+
+        let stale_claim = RegisterClaim {
+            claimed_at: claim.claimed_at - REGISTER_CLAIM_GRACE_WINDOW.as_millis() as u64,
+            ..claim
+        };
+        assert!(stale_claim.has_expired());
+        assert_eq!(stale_claim.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn claims_must_be_signed_by_the_register_owner() {
+        let owner = bls::SecretKey::random();
+        let address =
+            RegisterAddress::new(xor_name::XorName::from_content(b"reg"), owner.public_key());
+        let claim = RegisterClaim::new(owner.public_key(), 0);
+
+        let signed = SignedRegisterClaim::new(&address, claim, &owner);
+        assert_eq!(signed.verify(&address), Ok(()));
+
+        // the time of the claim isn't signed, nodes using their own
+        let later = SignedRegisterClaim {
+            claim: RegisterClaim {
+                claimed_at: claim.claimed_at + 1,
+                ..claim
+            },
+            ..signed.clone()
+        };
+        assert_eq!(later.verify(&address), Ok(()));
+
+        let other_nonce = SignedRegisterClaim {
+            claim: RegisterClaim { nonce: 1, ..claim },
+            ..signed.clone()
+        };
+        let other_register = RegisterAddress::new(
+            xor_name::XorName::from_content(b"other"),
+            owner.public_key(),
+        );
+        let not_the_owner = bls::SecretKey::random();
+        let by_someone_else = SignedRegisterClaim::new(
+            &address,
+            RegisterClaim::new(not_the_owner.public_key(), 0),
+            &not_the_owner,
+        );
+        for invalid in [
+            other_nonce.verify(&address),
+            signed.verify(&other_register),
+            by_someone_else.verify(&address),
+        ] {
+            assert!(matches!(invalid, Err(Error::RegisterClaimInvalid(_))));
+        }
+    }
+}
@@ -8,11 +8,11 @@
 
 use crate::{error::Result, NetworkAddress};
 
-use super::ChunkProof;
+use super::{ChunkProof, RegisterClaim};
 use bytes::Bytes;
 use core::fmt;
 use serde::{Deserialize, Serialize};
-use sn_transfers::{MainPubkey, PaymentQuote};
+use sn_transfers::{MainPubkey, PaymentQuote, SignedSpend};
 use std::fmt::Debug;
 
 /// The response to a query, containing the query result.
@@ -44,6 +44,28 @@ pub enum QueryResponse {
     ///
     /// [`GetChunkExistenceProof`]: crate::messages::Query::GetChunkExistenceProof
     GetChunkExistenceProof(Result<ChunkProof>),
+    // ===== ClaimRegister =====
+    //
+    /// Response to [`ClaimRegister`]
+    ///
+    /// [`ClaimRegister`]: crate::messages::Query::ClaimRegister
+    ClaimRegister {
+        /// Ok if the node now holds the claim, else `RegisterAlreadyClaimed` with the claimant
+        /// of the competing claim, `RegisterClaimInvalid` if the claim isn't signed by the owner
+        /// of the Register, or `Busy` if the node holds too many claims already.
+        result: Result<()>,
+        /// The claim the node holds on the Register, timed by the node. Ours if it holds none.
+        held_claim: RegisterClaim,
+    },
+    // ===== GetSpendConflicts =====
+    //
+    /// Response to [`GetSpendConflicts`]
+    ///
+    /// Holds all the spends the node stores for the address if they conflict, or none if the
+    /// node holds a single spend or none at all.
+    ///
+    /// [`GetSpendConflicts`]: crate::messages::Query::GetSpendConflicts
+    GetSpendConflicts(Result<Vec<SignedSpend>>),
 }
 
 // Debug implementation for QueryResponse, to avoid printing Vec<u8>
@@ -76,6 +98,13 @@ impl Debug for QueryResponse {
             QueryResponse::GetChunkExistenceProof(proof) => {
                 write!(f, "GetChunkExistenceProof(proof: {proof:?})")
             }
+            QueryResponse::ClaimRegister { result, held_claim } => {
+                write!(f, "ClaimRegister({result:?}, held_claim: {held_claim:?})")
+            }
+            QueryResponse::GetSpendConflicts(result) => match result {
+                Ok(spends) => write!(f, "GetSpendConflicts(Ok({} spends))", spends.len()),
+                Err(err) => write!(f, "GetSpendConflicts(Err({err:?}))"),
+            },
         }
     }
 }
@@ -9,9 +9,11 @@
 mod address;
 mod chunks;
 mod header;
+mod validation;
 
 pub use self::{
     address::{ChunkAddress, RegisterAddress, SpendAddress},
     chunks::Chunk,
     header::{try_deserialize_record, try_serialize_record, RecordHeader, RecordKind, RecordType},
+    validation::validate_stored_record,
 };
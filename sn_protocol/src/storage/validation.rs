@@ -0,0 +1,70 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{try_deserialize_record, Chunk, RecordHeader, RecordKind, SpendAddress};
+use crate::{error::Error, NetworkAddress};
+use libp2p::kad::Record;
+use sn_registers::SignedRegister;
+use sn_transfers::SignedSpend;
+
+/// Check a record read off disk, or from anywhere it wasn't validated on the way in, is a valid
+/// record stored under its key: its content deserializes, the spends and registers it holds are
+/// correctly signed, and the key is the one of its content.
+///
+/// Returns the kind of the record.
+pub fn validate_stored_record(record: &Record) -> Result<RecordKind, Error> {
+    let header = RecordHeader::from_record(record)
+        .map_err(|err| Error::InvalidStoredRecord(format!("invalid header: {err}")))?;
+    let keys = match header.kind {
+        RecordKind::Chunk => {
+            let chunk = try_deserialize_record::<Chunk>(record)
+                .map_err(|err| Error::InvalidStoredRecord(format!("invalid chunk: {err}")))?;
+            vec![chunk.network_address().to_record_key()]
+        }
+        RecordKind::Spend => {
+            let spends = try_deserialize_record::<Vec<SignedSpend>>(record)
+                .map_err(|err| Error::InvalidStoredRecord(format!("invalid spends: {err}")))?;
+            if spends.is_empty() {
+                return Err(Error::InvalidStoredRecord(
+                    "no spend in the record".to_string(),
+                ));
+            }
+            spends
+                .iter()
+                .map(|spend| {
+                    spend.verify(spend.spent_tx_hash()).map_err(|err| {
+                        Error::InvalidStoredRecord(format!("invalid spend: {err}"))
+                    })?;
+                    let address = SpendAddress::from_unique_pubkey(spend.unique_pubkey());
+                    Ok(NetworkAddress::SpendAddress(address).to_record_key())
+                })
+                .collect::<Result<_, Error>>()?
+        }
+        RecordKind::Register => {
+            let register = try_deserialize_record::<SignedRegister>(record)
+                .map_err(|err| Error::InvalidStoredRecord(format!("invalid register: {err}")))?;
+            register
+                .verify()
+                .map_err(|err| Error::InvalidStoredRecord(format!("invalid register: {err}")))?;
+            vec![NetworkAddress::from_register_address(*register.address()).to_record_key()]
+        }
+        RecordKind::ChunkWithPayment | RecordKind::RegisterWithPayment => {
+            return Err(Error::InvalidStoredRecord(format!(
+                "{:?} records are never stored",
+                header.kind
+            )));
+        }
+    };
+
+    if keys.iter().any(|key| key != &record.key) {
+        return Err(Error::InvalidStoredRecord(
+            "the content does not match the record key".to_string(),
+        ));
+    }
+    Ok(header.kind)
+}
@@ -69,11 +69,15 @@ impl RegisterAddress {
     /// Deserialize a hex-encoded representation of a `RegisterAddress` to a `RegisterAddress` instance.
     pub fn from_hex(hex: &str) -> Result<Self> {
         let bytes = hex::decode(hex).map_err(|_| Error::HexDeserializeFailed)?;
-        let meta_bytes: [u8; XOR_NAME_LEN] = bytes[..XOR_NAME_LEN]
+        if bytes.len() != XOR_NAME_LEN + PK_SIZE {
+            return Err(Error::HexDeserializeFailed);
+        }
+        let (meta_bytes, owner_bytes) = bytes.split_at(XOR_NAME_LEN);
+        let meta_bytes: [u8; XOR_NAME_LEN] = meta_bytes
             .try_into()
             .map_err(|_| Error::HexDeserializeFailed)?;
         let meta = XorName(meta_bytes);
-        let owner_bytes: [u8; PK_SIZE] = bytes[XOR_NAME_LEN..]
+        let owner_bytes: [u8; PK_SIZE] = owner_bytes
             .try_into()
             .map_err(|_| Error::HexDeserializeFailed)?;
         let owner = PublicKey::from_bytes(owner_bytes).map_err(|_| Error::HexDeserializeFailed)?;
@@ -112,4 +116,19 @@ mod tests {
         let err = RegisterAddress::from_hex(&bad_hex);
         assert_eq!(err, Err(Error::HexDeserializeFailed));
     }
+
+    #[test]
+    fn short_register_hex_is_an_error() {
+        for hex in [
+            "",
+            "00",
+            &"ab".repeat(XOR_NAME_LEN - 1),
+            &"ab".repeat(XOR_NAME_LEN),
+        ] {
+            assert_eq!(
+                RegisterAddress::from_hex(hex),
+                Err(Error::HexDeserializeFailed)
+            );
+        }
+    }
 }
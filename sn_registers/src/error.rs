@@ -63,6 +63,12 @@ pub enum Error {
         requested: Box<RegisterAddress>,
         got: Box<RegisterAddress>,
     },
+    /// The ownership transfer isn't signed by the current owner of the Register
+    #[error("Ownership transfer signed by {signer:?} while the Register is owned by {owner:?}")]
+    OwnershipTransferNotFromOwner { signer: PublicKey, owner: PublicKey },
+    /// Two copies of the Register were handed over to different owners
+    #[error("The copies of the Register were handed over to different owners")]
+    ConflictingOwnershipTransfers,
     /// The provided String can't be deserialized as a RegisterAddress
     #[error("Failed to deserialize hex RegisterAddress")]
     HexDeserializeFailed,
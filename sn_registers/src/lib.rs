@@ -9,15 +9,19 @@
 mod address;
 pub(crate) mod error;
 mod metadata;
+mod ownership;
 mod permissions;
 pub(crate) mod reg_crdt;
 pub(crate) mod register;
 mod register_op;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 pub use self::{
     address::RegisterAddress,
     error::Error,
     metadata::{Entry, EntryHash},
+    ownership::OwnershipTransfer,
     permissions::Permissions,
     register::{Register, SignedRegister},
     register_op::RegisterOp,
@@ -0,0 +1,90 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{error::Result, EntryHash, Error, RegisterAddress};
+
+use bls::{PublicKey, SecretKey, Signature};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// The transfer of the ownership of a Register to a new key, signed by its current owner.
+///
+/// The Register keeps its address, the owner in it remaining the original one. The entries
+/// written by the previous owner up to the transfer are the ones the `root_hashes` descend from,
+/// any later write of the previous owner is rejected.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OwnershipTransfer {
+    /// Address of the Register handed over.
+    pub(crate) address: RegisterAddress,
+    /// The owner handing the Register over, who signed the transfer.
+    pub(crate) previous_owner: PublicKey,
+    /// The owner the Register is handed over to.
+    pub(crate) new_owner: PublicKey,
+    /// The latest entries of the Register at the time of the transfer.
+    pub(crate) root_hashes: BTreeSet<EntryHash>,
+    /// The signature of the previous owner on (address, new_owner, root_hashes).
+    pub(crate) signature: Signature,
+}
+
+impl OwnershipTransfer {
+    /// Create a new OwnershipTransfer, signed by the given current owner.
+    pub(crate) fn new(
+        address: RegisterAddress,
+        new_owner: PublicKey,
+        root_hashes: BTreeSet<EntryHash>,
+        signer: &SecretKey,
+    ) -> Result<Self> {
+        let bytes = Self::bytes_for_signing(&address, &new_owner, &root_hashes)?;
+        Ok(Self {
+            address,
+            previous_owner: signer.public_key(),
+            new_owner,
+            root_hashes,
+            signature: signer.sign(bytes),
+        })
+    }
+
+    /// Address of the Register handed over.
+    pub fn address(&self) -> RegisterAddress {
+        self.address
+    }
+
+    /// The owner handing the Register over.
+    pub fn previous_owner(&self) -> PublicKey {
+        self.previous_owner
+    }
+
+    /// The owner the Register is handed over to.
+    pub fn new_owner(&self) -> PublicKey {
+        self.new_owner
+    }
+
+    /// The latest entries of the Register at the time of the transfer.
+    pub fn root_hashes(&self) -> &BTreeSet<EntryHash> {
+        &self.root_hashes
+    }
+
+    /// Check the transfer is signed by the previous owner.
+    pub fn verify_signature(&self) -> Result<()> {
+        let bytes = Self::bytes_for_signing(&self.address, &self.new_owner, &self.root_hashes)?;
+        if !self.previous_owner.verify(&self.signature, bytes) {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(())
+    }
+
+    /// Returns a bytes version of the transfer used for signing
+    fn bytes_for_signing(
+        address: &RegisterAddress,
+        new_owner: &PublicKey,
+        root_hashes: &BTreeSet<EntryHash>,
+    ) -> Result<Vec<u8>> {
+        rmp_serde::to_vec(&(address, new_owner, root_hashes))
+            .map_err(|_| Error::SerialisationFailed)
+    }
+}
@@ -104,6 +104,25 @@ impl RegisterCrdt {
         self.data.node(hash.0).map(|node| &node.value)
     }
 
+    /// Whether the entry with the given `hash` is one of the `roots`, or one they descend from.
+    /// Only the entries of this replica are followed.
+    pub(crate) fn is_in_history_of(&self, hash: EntryHash, roots: &BTreeSet<EntryHash>) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut to_visit: Vec<_> = roots.iter().map(|root| root.0).collect();
+        while let Some(current) = to_visit.pop() {
+            if current == hash.0 {
+                return true;
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(node) = self.data.node(current) {
+                to_visit.extend(node.children.iter().copied());
+            }
+        }
+        false
+    }
+
     /// Read current entries (multiple entries occur on concurrent writes).
     pub(crate) fn read(&self) -> BTreeSet<(EntryHash, Entry)> {
         self.data
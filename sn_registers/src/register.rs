@@ -7,14 +7,14 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    error::Result, reg_crdt::RegisterCrdt, Entry, EntryHash, Error, Permissions, RegisterAddress,
-    RegisterOp,
+    error::Result, reg_crdt::RegisterCrdt, Entry, EntryHash, Error, OwnershipTransfer, Permissions,
+    RegisterAddress, RegisterOp,
 };
 
 use bls::{PublicKey, SecretKey, Signature};
 use self_encryption::MIN_ENCRYPTABLE_BYTES;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
+use std::{borrow::Cow, collections::BTreeSet};
 use xor_name::XorName;
 
 /// Arbitrary maximum size of a register entry.
@@ -32,6 +32,10 @@ pub struct Register {
     /// Depending on the permissions, the owner can allow other users to write to the register
     /// Everyone can always read the Register because all data is public
     permissions: Permissions,
+    /// Transfers of the ownership of the Register, oldest first
+    /// Left out when empty, so that Registers never handed over serialize as they always did
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ownership_transfers: Vec<OwnershipTransfer>,
 }
 
 /// A Signed Register on the SAFE Network
@@ -45,6 +49,10 @@ pub struct SignedRegister {
     /// operations to apply on this register,
     /// they contain a signature of the writer
     ops: BTreeSet<RegisterOp>,
+    /// transfers of the ownership of the register, oldest first,
+    /// they contain a signature of the previous owner
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ownership_transfers: Vec<OwnershipTransfer>,
 }
 
 impl SignedRegister {
@@ -54,10 +62,11 @@ impl SignedRegister {
             base_register,
             signature,
             ops: BTreeSet::new(),
+            ownership_transfers: vec![],
         }
     }
 
-    /// Verfies a SignedRegister
+    /// Verfies a SignedRegister, along with the chain of its ownership transfers
     pub fn verify(&self) -> Result<()> {
         let bytes = self.base_register.bytes()?;
         if !self
@@ -68,8 +77,9 @@ impl SignedRegister {
             return Err(Error::InvalidSignature);
         }
 
+        let checking_register = self.checking_register()?;
         for op in &self.ops {
-            self.base_register.check_register_op(op)?;
+            checking_register.check_register_op(op)?;
         }
         Ok(())
     }
@@ -84,22 +94,27 @@ impl SignedRegister {
         self.verify()
     }
 
-    /// Return the Register after applying all the operations
+    /// Return the Register after applying all the ownership transfers and operations
     pub fn register(self) -> Result<Register> {
-        let mut register = self.base_register;
+        let checking_register = self.checking_register()?.into_owned();
+        let mut register = self.owned_register()?;
         for op in self.ops {
-            register.apply_op(op)?;
+            checking_register.check_register_op(&op)?;
+            register.check_entry_and_reg_sizes(&op.crdt_op.value)?;
+            register.crdt.apply_op(op)?;
         }
         Ok(register)
     }
 
     /// Merge two SignedRegisters
+    /// The ops of previous owners made after they handed the Register over are dropped
     pub fn merge(&mut self, other: SignedRegister) -> Result<()> {
         if self.base_register != other.base_register {
             return Err(Error::DifferentBaseRegister);
         }
+        self.merge_ownership_transfers(other.ownership_transfers)?;
         self.ops.extend(other.ops);
-        Ok(())
+        self.prune_stale_ops()
     }
 
     /// Merge two SignedRegisters but verify the incoming content
@@ -109,8 +124,7 @@ impl SignedRegister {
             return Err(Error::DifferentBaseRegister);
         }
         other.verify()?;
-        self.ops.extend(other.ops);
-        Ok(())
+        self.merge(other)
     }
 
     /// Return the address.
@@ -118,17 +132,82 @@ impl SignedRegister {
         self.base_register.address()
     }
 
-    /// Return the owner of the data.
+    /// Return the current owner of the data.
     pub fn owner(&self) -> PublicKey {
-        self.base_register.owner()
+        self.ownership_transfers
+            .last()
+            .map_or_else(|| self.base_register.owner(), |transfer| transfer.new_owner)
+    }
+
+    /// Return the transfers of the ownership of the Register, oldest first.
+    pub fn ownership_transfers(&self) -> &[OwnershipTransfer] {
+        &self.ownership_transfers
     }
 
     /// Check and add an Op to the SignedRegister
     pub fn add_op(&mut self, op: RegisterOp) -> Result<()> {
-        self.base_register.check_register_op(&op)?;
+        self.checking_register()?.check_register_op(&op)?;
         self.ops.insert(op);
         Ok(())
     }
+
+    /// Check and add an ownership transfer to the SignedRegister
+    /// The ops of the previous owner made after the transfer are dropped
+    pub fn add_ownership_transfer(&mut self, transfer: OwnershipTransfer) -> Result<()> {
+        let mut register = self.owned_register()?;
+        register.apply_ownership_transfer(transfer)?;
+        self.ownership_transfers = register.ownership_transfers;
+        self.prune_stale_ops()
+    }
+
+    /// The base Register, handed over as per the ownership transfers
+    fn owned_register(&self) -> Result<Register> {
+        let mut register = self.base_register.clone();
+        for transfer in &self.ownership_transfers {
+            register.apply_ownership_transfer(transfer.clone())?;
+        }
+        Ok(register)
+    }
+
+    /// The Register the ops are checked against
+    /// Once handed over, the entries of all the ops are merged in, so that the ops of the previous
+    /// owners can be followed from the roots of their transfers
+    fn checking_register(&self) -> Result<Cow<'_, Register>> {
+        if self.ownership_transfers.is_empty() {
+            return Ok(Cow::Borrowed(&self.base_register));
+        }
+        let mut register = self.owned_register()?;
+        for op in &self.ops {
+            register.crdt.apply_op(op.clone())?;
+        }
+        Ok(Cow::Owned(register))
+    }
+
+    /// Adopt the longest of the two chains of ownership transfers, if one follows the other
+    fn merge_ownership_transfers(&mut self, other: Vec<OwnershipTransfer>) -> Result<()> {
+        if other.starts_with(&self.ownership_transfers) {
+            self.ownership_transfers = other;
+            Ok(())
+        } else if self.ownership_transfers.starts_with(&other) {
+            Ok(())
+        } else {
+            Err(Error::ConflictingOwnershipTransfers)
+        }
+    }
+
+    /// Drop the ops of previous owners made after they handed the Register over
+    /// Replicas may have stored some before learning of the transfer
+    fn prune_stale_ops(&mut self) -> Result<()> {
+        if self.ownership_transfers.is_empty() {
+            return Ok(());
+        }
+        let checking_register = self.checking_register()?.into_owned();
+        self.ops.retain(|op| {
+            !checking_register.is_previous_owner(&op.source)
+                || checking_register.check_register_op(op).is_ok()
+        });
+        Ok(())
+    }
 }
 
 impl Register {
@@ -139,6 +218,7 @@ impl Register {
         Self {
             crdt: RegisterCrdt::new(address),
             permissions,
+            ownership_transfers: vec![],
         }
     }
 
@@ -175,9 +255,17 @@ impl Register {
         self.crdt.address()
     }
 
-    /// Return the owner of the data.
+    /// Return the current owner of the data.
+    /// This is the owner in the address, unless the Register was handed over to another one.
     pub fn owner(&self) -> PublicKey {
-        self.address().owner()
+        self.ownership_transfers
+            .last()
+            .map_or_else(|| self.address().owner(), |transfer| transfer.new_owner)
+    }
+
+    /// Return the transfers of the ownership of the Register, oldest first.
+    pub fn ownership_transfers(&self) -> &[OwnershipTransfer] {
+        &self.ownership_transfers
     }
 
     /// Return the number of items held in the register
@@ -195,6 +283,11 @@ impl Register {
         self.crdt.get(hash).cloned().ok_or(Error::NoSuchEntry(hash))
     }
 
+    /// Whether the entry with the given `hash` is one of the `roots`, or one they descend from.
+    pub(crate) fn is_in_history_of(&self, hash: EntryHash, roots: &BTreeSet<EntryHash>) -> bool {
+        self.crdt.is_in_history_of(hash, roots)
+    }
+
     /// Read the last entry, or entries when there are branches, if the register is not empty.
     pub fn read(&self) -> BTreeSet<(EntryHash, Entry)> {
         self.crdt.read()
@@ -227,14 +320,64 @@ impl Register {
         self.crdt.apply_op(op)
     }
 
+    /// Hand the Register over to the `new_owner`, returning the signed transfer
+    /// so the caller can broadcast it to other replicas.
+    /// The signer must be the current owner, which can't write to the Register anymore afterwards.
+    pub fn transfer_ownership(
+        &mut self,
+        new_owner: PublicKey,
+        signer: &SecretKey,
+    ) -> Result<OwnershipTransfer> {
+        if self.owner() != signer.public_key() {
+            return Err(Error::InvalidSecretKey);
+        }
+        let root_hashes = self.read().into_iter().map(|(hash, _)| hash).collect();
+        let transfer = OwnershipTransfer::new(*self.address(), new_owner, root_hashes, signer)?;
+        self.ownership_transfers.push(transfer.clone());
+        Ok(transfer)
+    }
+
+    /// Apply a signed ownership transfer, which must be signed by the current owner.
+    /// Applying an already applied transfer is a no-op.
+    pub fn apply_ownership_transfer(&mut self, transfer: OwnershipTransfer) -> Result<()> {
+        if self.ownership_transfers.contains(&transfer) {
+            return Ok(());
+        }
+        if transfer.address != *self.address() {
+            return Err(Error::RegisterAddrMismatch {
+                dst_addr: Box::new(transfer.address),
+                reg_addr: Box::new(*self.address()),
+            });
+        }
+        if transfer.previous_owner != self.owner() {
+            return Err(Error::OwnershipTransferNotFromOwner {
+                signer: transfer.previous_owner,
+                owner: self.owner(),
+            });
+        }
+        transfer.verify_signature()?;
+        self.ownership_transfers.push(transfer);
+        Ok(())
+    }
+
     /// Merge another Register into this one.
+    /// Its ownership transfers are adopted if they follow ours.
     pub fn merge(&mut self, other: Self) {
+        if other
+            .ownership_transfers
+            .starts_with(&self.ownership_transfers)
+        {
+            self.ownership_transfers = other.ownership_transfers;
+        }
         self.crdt.merge(other.crdt);
     }
 
     /// Check if a register op is valid for our current register
+    /// The ops of previous owners are only valid if made before they handed the Register over
     pub fn check_register_op(&self, op: &RegisterOp) -> Result<()> {
-        self.check_user_permissions(op.source)?;
+        if !self.is_written_before_handover(op) {
+            self.check_user_permissions(op.source)?;
+        }
         if self.permissions.anyone_can_write() {
             return Ok(()); // anyone can write, so no need to check the signature
         }
@@ -243,18 +386,44 @@ impl Register {
     }
 
     /// Helper to check user write permissions for the given requester's public key.
+    /// Previous owners lose their write permissions, unless anyone can write.
     ///
     /// Returns:
     /// `Ok(())` if the user can write to this register
     /// `Err::AccessDenied` if the user cannot write to this register
     pub fn check_user_permissions(&self, requester: PublicKey) -> Result<()> {
-        if requester == self.owner() || self.permissions.can_write(&requester) {
+        if requester == self.owner()
+            || self.permissions.anyone_can_write()
+            || (self.permissions.can_write(&requester) && !self.is_previous_owner(&requester))
+        {
             Ok(())
         } else {
             Err(Error::AccessDenied(requester))
         }
     }
 
+    // Private helper telling if the given key owned the Register before the current owner.
+    fn is_previous_owner(&self, key: &PublicKey) -> bool {
+        *key != self.owner()
+            && self
+                .ownership_transfers
+                .iter()
+                .any(|transfer| transfer.previous_owner == *key)
+    }
+
+    // Private helper telling if the op was made by a previous owner before it last handed the
+    // Register over, i.e. if its entry is one of those the transfer roots descend from.
+    fn is_written_before_handover(&self, op: &RegisterOp) -> bool {
+        self.ownership_transfers
+            .iter()
+            .rev()
+            .find(|transfer| transfer.previous_owner == op.source)
+            .is_some_and(|transfer| {
+                self.crdt
+                    .is_in_history_of(EntryHash(op.crdt_op.hash()), &transfer.root_hashes)
+            })
+    }
+
     // Private helper to check the given Entry's size is within define limit,
     // as well as check the Register hasn't already reached the maximum number of entries.
     fn check_entry_and_reg_sizes(&self, entry: &Entry) -> Result<()> {
@@ -460,6 +629,113 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn register_ownership_transfer() -> eyre::Result<()> {
+        let old_owner_sk = SecretKey::random();
+        let new_owner_sk = SecretKey::random();
+        let new_owner = new_owner_sk.public_key();
+
+        let mut register = Register::new_owned(old_owner_sk.public_key(), xor_name::rand::random());
+        let mut signed_register = register.clone().into_signed(&old_owner_sk)?;
+        let address = *register.address();
+
+        let (old_hash, op) =
+            register.write(random_register_entry(), &BTreeSet::new(), &old_owner_sk)?;
+        signed_register.add_op(op)?;
+
+        // only the owner can hand the Register over
+        assert_eq!(
+            register
+                .clone()
+                .transfer_ownership(new_owner, &new_owner_sk),
+            Err(Error::InvalidSecretKey)
+        );
+        let transfer = register.transfer_ownership(new_owner, &old_owner_sk)?;
+        assert_eq!(transfer.root_hashes(), &BTreeSet::from([old_hash]));
+        signed_register.add_ownership_transfer(transfer.clone())?;
+        assert_eq!(register.owner(), new_owner);
+        assert_eq!(signed_register.owner(), new_owner);
+
+        // the address is kept, and the new owner writes atop the entries of the old one
+        let children = [old_hash].into_iter().collect();
+        let (new_hash, op) = register.write(random_register_entry(), &children, &new_owner_sk)?;
+        signed_register.add_op(op)?;
+        signed_register.verify_with_address(address)?;
+
+        let read_register = signed_register.register()?;
+        assert_eq!(read_register.owner(), new_owner);
+        assert_eq!(read_register.size(), 2);
+        assert_eq!(
+            read_register
+                .read()
+                .into_iter()
+                .map(|(hash, _)| hash)
+                .collect::<Vec<_>>(),
+            vec![new_hash]
+        );
+
+        // applying the transfer again is a no-op, a transfer from the old owner isn't followed
+        let mut replica = read_register.clone();
+        replica.apply_ownership_transfer(transfer)?;
+        assert_eq!(
+            replica.ownership_transfers(),
+            read_register.ownership_transfers()
+        );
+        let forged = Register::new_owned(old_owner_sk.public_key(), address.meta)
+            .transfer_ownership(SecretKey::random().public_key(), &old_owner_sk)?;
+        assert_eq!(
+            replica.apply_ownership_transfer(forged),
+            Err(Error::OwnershipTransferNotFromOwner {
+                signer: old_owner_sk.public_key(),
+                owner: new_owner,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_stale_owner_write_is_rejected() -> eyre::Result<()> {
+        let old_owner_sk = SecretKey::random();
+        let old_owner = old_owner_sk.public_key();
+        let new_owner_sk = SecretKey::random();
+
+        let mut register = Register::new_owned(old_owner, xor_name::rand::random());
+        let mut signed_register = register.clone().into_signed(&old_owner_sk)?;
+        let (hash, op) =
+            register.write(random_register_entry(), &BTreeSet::new(), &old_owner_sk)?;
+        signed_register.add_op(op)?;
+
+        // a stale replica of the old owner keeps writing, unaware of the transfer
+        let mut stale_register = register.clone();
+        let mut stale_signed_register = signed_register.clone();
+        let children = [hash].into_iter().collect();
+        let (_, stale_op) =
+            stale_register.write(random_register_entry(), &children, &old_owner_sk)?;
+        stale_signed_register.add_op(stale_op.clone())?;
+
+        let transfer = register.transfer_ownership(new_owner_sk.public_key(), &old_owner_sk)?;
+        signed_register.add_ownership_transfer(transfer)?;
+
+        assert_eq!(
+            register.check_user_permissions(old_owner),
+            Err(Error::AccessDenied(old_owner))
+        );
+        assert_eq!(
+            signed_register.add_op(stale_op),
+            Err(Error::AccessDenied(old_owner))
+        );
+
+        // merging the stale copy drops the write made after the transfer, the older one is kept
+        signed_register.merge(stale_signed_register)?;
+        signed_register.verify()?;
+        let read_register = signed_register.register()?;
+        assert_eq!(read_register.size(), 1);
+        assert_eq!(read_register.get(hash)?, register.get(hash)?);
+
+        Ok(())
+    }
+
     #[test]
     fn exceeding_max_reg_entries_errors() -> eyre::Result<()> {
         let meta = xor_name::rand::random();
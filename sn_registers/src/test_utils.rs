@@ -0,0 +1,557 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Multi-writer scenarios over registers, to test merge semantics without a network.
+//!
+//! A [`RegisterScenario`] holds a replica of the same register per writer. The ops written by a
+//! writer are in flight to the others until delivered, in a scripted or seeded order, and
+//! partitions withhold them until healed. Given the same steps, a scenario always ends up with
+//! the same entries. See the README for an example.
+
+use crate::{error::Result, Entry, EntryHash, Permissions, Register, RegisterOp, SignedRegister};
+use bls::SecretKey;
+use std::collections::BTreeSet;
+use xor_name::XorName;
+
+/// A step of a scripted scenario, see `RegisterScenario::run`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// The writer writes the entry on top of the entries it has seen.
+    Write { writer: usize, entry: Entry },
+    /// The ops in flight from a writer to another are delivered, oldest first.
+    Deliver { from: usize, to: usize },
+    /// All the ops in flight, and not withheld, are delivered, oldest first.
+    DeliverAll,
+    /// All the ops in flight, and not withheld, are delivered in the order drawn from the seed.
+    DeliverShuffled(u64),
+    /// The writers are split into these groups, see `RegisterScenario::partition`.
+    Partition(Vec<Vec<usize>>),
+    /// The partition is healed.
+    Heal,
+}
+
+/// An entry written during a scenario, with the entries it was written on top of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrittenEntry {
+    pub writer: usize,
+    pub hash: EntryHash,
+    pub entry: Entry,
+    pub parents: BTreeSet<EntryHash>,
+}
+
+/// An op delivered during a scenario, from the writer of its entry to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeliveredOp {
+    pub from: usize,
+    pub to: usize,
+    pub hash: EntryHash,
+}
+
+/// The replica of a writer, both as a `Register` applying the ops and as the `SignedRegister`
+/// collecting them, as stored by the network.
+#[derive(Clone, Debug)]
+struct Replica {
+    register: Register,
+    signed: SignedRegister,
+}
+
+#[derive(Clone, Debug)]
+struct InFlightOp {
+    from: usize,
+    to: usize,
+    op: RegisterOp,
+}
+
+/// Replicas of the same register, one per writer, exchanging the ops they write.
+///
+/// Writers are numbered from 0, the first one owning the register. Referring to a writer out
+/// of range panics.
+#[derive(Clone, Debug)]
+pub struct RegisterScenario {
+    keys: Vec<SecretKey>,
+    replicas: Vec<Replica>,
+    in_flight: Vec<InFlightOp>,
+    /// The group of each writer while partitioned.
+    groups: Option<Vec<usize>>,
+    written: Vec<WrittenEntry>,
+    delivered: Vec<DeliveredOp>,
+}
+
+impl RegisterScenario {
+    /// A register owned by the first of `writers` writers, all allowed to write to it.
+    pub fn new(writers: usize) -> Result<Self> {
+        let writers = writers.max(1);
+        let keys: Vec<_> = (0..writers).map(|_| SecretKey::random()).collect();
+        let permissions = Permissions::new_with(keys.iter().map(|key| key.public_key()));
+        let register = Register::new(
+            keys[0].public_key(),
+            XorName::from_content(b"register scenario"),
+            permissions,
+        );
+        let signed = register.clone().into_signed(&keys[0])?;
+        let replicas = (0..writers)
+            .map(|_| Replica {
+                register: register.clone(),
+                signed: signed.clone(),
+            })
+            .collect();
+
+        Ok(Self {
+            keys,
+            replicas,
+            in_flight: vec![],
+            groups: None,
+            written: vec![],
+            delivered: vec![],
+        })
+    }
+
+    /// The number of writers.
+    pub fn writers(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// The secret key a writer signs its ops with.
+    pub fn writer_key(&self, writer: usize) -> &SecretKey {
+        &self.keys[writer]
+    }
+
+    /// The replica of the register of a writer.
+    pub fn replica(&self, writer: usize) -> &Register {
+        &self.replicas[writer].register
+    }
+
+    /// The replica of a writer as a `SignedRegister`, as it would be stored by the network.
+    pub fn signed_replica(&self, writer: usize) -> &SignedRegister {
+        &self.replicas[writer].signed
+    }
+
+    /// The entries written so far, in the order they were written.
+    pub fn written(&self) -> &[WrittenEntry] {
+        &self.written
+    }
+
+    /// The ops delivered so far, in the order they were delivered.
+    pub fn delivered(&self) -> &[DeliveredOp] {
+        &self.delivered
+    }
+
+    /// The number of ops in flight, withheld ones included.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// The writer writes the entry on top of the entries it has seen, the op being in flight
+    /// to all the other writers until delivered.
+    pub fn write(&mut self, writer: usize, entry: Entry) -> Result<EntryHash> {
+        let replica = &mut self.replicas[writer];
+        let parents: BTreeSet<_> = replica
+            .register
+            .read()
+            .into_iter()
+            .map(|(hash, _)| hash)
+            .collect();
+        let (hash, op) = replica
+            .register
+            .write(entry.clone(), &parents, &self.keys[writer])?;
+        replica.signed.add_op(op.clone())?;
+
+        for to in (0..self.replicas.len()).filter(|to| *to != writer) {
+            self.in_flight.push(InFlightOp {
+                from: writer,
+                to,
+                op: op.clone(),
+            });
+        }
+        self.written.push(WrittenEntry {
+            writer,
+            hash,
+            entry,
+            parents,
+        });
+        Ok(hash)
+    }
+
+    /// Split the writers into groups, the ops in flight from a group to another being withheld
+    /// until the partition is healed. The writers left out of the groups are each on their own.
+    pub fn partition(&mut self, groups: &[&[usize]]) {
+        let mut group_of: Vec<_> = (0..self.replicas.len())
+            .map(|writer| groups.len() + writer)
+            .collect();
+        for (group, writers) in groups.iter().enumerate() {
+            for writer in writers.iter() {
+                group_of[*writer] = group;
+            }
+        }
+        self.groups = Some(group_of);
+    }
+
+    /// Heal the partition, so that the ops withheld can be delivered.
+    pub fn heal(&mut self) {
+        self.groups = None;
+    }
+
+    /// Deliver the ops in flight from a writer to another, oldest first, unless the partition
+    /// withholds them.
+    ///
+    /// Returns the number of ops delivered.
+    pub fn deliver(&mut self, from: usize, to: usize) -> Result<usize> {
+        self.deliver_where(|op| op.from == from && op.to == to)
+    }
+
+    /// Deliver all the ops in flight, oldest first, apart from the ones the partition withholds.
+    ///
+    /// Returns the number of ops delivered.
+    pub fn deliver_all(&mut self) -> Result<usize> {
+        self.deliver_where(|_| true)
+    }
+
+    /// Deliver all the ops in flight apart from the ones the partition withholds, in an order
+    /// drawn from the seed. The same seed always gives the same order.
+    ///
+    /// Returns the number of ops delivered.
+    pub fn deliver_shuffled(&mut self, seed: u64) -> Result<usize> {
+        let mut rng = SplitMix64(seed);
+        let mut delivered = 0;
+        loop {
+            let deliverable: Vec<_> = (0..self.in_flight.len())
+                .filter(|index| !self.is_withheld(&self.in_flight[*index]))
+                .collect();
+            if deliverable.is_empty() {
+                return Ok(delivered);
+            }
+            let index = deliverable[(rng.next() % deliverable.len() as u64) as usize];
+            let op = self.in_flight.remove(index);
+            self.apply(op)?;
+            delivered += 1;
+        }
+    }
+
+    /// Run the steps in turn.
+    pub fn run(&mut self, steps: &[Step]) -> Result<()> {
+        for step in steps {
+            match step {
+                Step::Write { writer, entry } => {
+                    let _ = self.write(*writer, entry.clone())?;
+                }
+                Step::Deliver { from, to } => {
+                    let _ = self.deliver(*from, *to)?;
+                }
+                Step::DeliverAll => {
+                    let _ = self.deliver_all()?;
+                }
+                Step::DeliverShuffled(seed) => {
+                    let _ = self.deliver_shuffled(*seed)?;
+                }
+                Step::Partition(groups) => {
+                    let groups: Vec<&[usize]> = groups.iter().map(|group| &group[..]).collect();
+                    self.partition(&groups);
+                }
+                Step::Heal => self.heal(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether all the replicas hold the same entries, both as `Register`s and as
+    /// `SignedRegister`s.
+    pub fn is_converged(&self) -> bool {
+        self.convergence_failure().is_none()
+    }
+
+    /// Panics unless all the replicas hold the same entries, all of the ones written, both as
+    /// `Register`s and as `SignedRegister`s.
+    pub fn assert_converged(&self) {
+        if let Some(failure) = self.convergence_failure() {
+            panic!("The replicas did not converge: {failure}");
+        }
+        for written in &self.written {
+            for (writer, replica) in self.replicas.iter().enumerate() {
+                assert!(
+                    replica.register.get(written.hash).is_ok(),
+                    "Writer {writer} misses the entry {} written by writer {}",
+                    written.hash,
+                    written.writer
+                );
+            }
+        }
+    }
+
+    /// Panics if all the replicas hold the same entries.
+    pub fn assert_diverged(&self) {
+        assert!(
+            !self.is_converged(),
+            "The replicas converged, with {} ops in flight",
+            self.in_flight.len()
+        );
+    }
+
+    /// Panics unless `ancestor` is in the history of `descendant`, as followed through the
+    /// entries of the first writer to hold `descendant`.
+    pub fn assert_descends_from(&self, descendant: EntryHash, ancestor: EntryHash) {
+        let replica = self
+            .replicas
+            .iter()
+            .find(|replica| replica.register.get(descendant).is_ok())
+            .unwrap_or_else(|| panic!("No writer holds the entry {descendant}"));
+        assert!(
+            replica
+                .register
+                .is_in_history_of(ancestor, &BTreeSet::from([descendant])),
+            "The entry {descendant} does not descend from {ancestor}"
+        );
+    }
+
+    /// Panics unless each entry written descends from the ones its writer had seen, in the
+    /// replicas holding it.
+    pub fn assert_ancestry(&self) {
+        for written in &self.written {
+            for (writer, replica) in self.replicas.iter().enumerate() {
+                if replica.register.get(written.hash).is_err() {
+                    continue;
+                }
+                for parent in &written.parents {
+                    assert!(
+                        replica
+                            .register
+                            .is_in_history_of(*parent, &BTreeSet::from([written.hash])),
+                        "In the replica of writer {writer}, the entry {} does not descend from {parent}",
+                        written.hash
+                    );
+                }
+            }
+        }
+    }
+
+    fn is_withheld(&self, op: &InFlightOp) -> bool {
+        self.groups
+            .as_ref()
+            .is_some_and(|groups| groups[op.from] != groups[op.to])
+    }
+
+    fn deliver_where(&mut self, filter: impl Fn(&InFlightOp) -> bool) -> Result<usize> {
+        let (to_deliver, in_flight): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|op| filter(op) && !self.is_withheld(op));
+        self.in_flight = in_flight;
+        let delivered = to_deliver.len();
+        for op in to_deliver {
+            self.apply(op)?;
+        }
+        Ok(delivered)
+    }
+
+    fn apply(&mut self, op: InFlightOp) -> Result<()> {
+        let replica = &mut self.replicas[op.to];
+        replica.register.apply_op(op.op.clone())?;
+        self.delivered.push(DeliveredOp {
+            from: op.from,
+            to: op.to,
+            hash: op.op.entry_hash(),
+        });
+        replica.signed.add_op(op.op)
+    }
+
+    /// Why the replicas differ, if they do.
+    fn convergence_failure(&self) -> Option<String> {
+        let first = &self.replicas[0];
+        let first_entries = first.register.read();
+        for (writer, replica) in self.replicas.iter().enumerate() {
+            if replica.register != first.register {
+                return Some(format!(
+                    "writer {writer} reads {:?} out of {} entries, writer 0 reads {:?} out of {}",
+                    entry_hashes(&replica.register),
+                    replica.register.size(),
+                    entry_hashes(&first.register),
+                    first.register.size()
+                ));
+            }
+            match replica.signed.clone().register() {
+                Ok(register) if register.read() == first_entries => {}
+                Ok(register) => {
+                    return Some(format!(
+                        "the signed register of writer {writer} reads {:?}, writer 0 reads {:?}",
+                        entry_hashes(&register),
+                        entry_hashes(&first.register)
+                    ))
+                }
+                Err(err) => {
+                    return Some(format!(
+                        "the signed register of writer {writer} is invalid: {err}"
+                    ))
+                }
+            }
+        }
+        None
+    }
+}
+
+fn entry_hashes(register: &Register) -> Vec<EntryHash> {
+    register.read().into_iter().map(|(hash, _)| hash).collect()
+}
+
+/// A small deterministic generator, for the delivery order to only depend on the seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn partitioned_writers_branch_and_merge_once_healed() -> eyre::Result<()> {
+        let mut scenario = RegisterScenario::new(4)?;
+        let root = scenario.write(0, b"root".to_vec())?;
+        assert_eq!(scenario.deliver_all()?, 3);
+        scenario.assert_converged();
+
+        scenario.partition(&[&[0, 1], &[2, 3]]);
+        let left = scenario.write(1, b"left".to_vec())?;
+        let right = scenario.write(3, b"right".to_vec())?;
+        assert_eq!(scenario.deliver_all()?, 2);
+        assert_eq!(scenario.in_flight(), 4);
+        scenario.assert_diverged();
+        assert_eq!(entry_hashes(scenario.replica(0)), vec![left]);
+        assert_eq!(entry_hashes(scenario.replica(2)), vec![right]);
+
+        scenario.heal();
+        assert_eq!(scenario.deliver_shuffled(7)?, 4);
+        scenario.assert_converged();
+        assert_eq!(
+            scenario.replica(0).read().len(),
+            2,
+            "the concurrent writes are both read"
+        );
+
+        // writing on top of both branches merges them
+        let merge = scenario.write(2, b"merge".to_vec())?;
+        let _ = scenario.deliver_all()?;
+        scenario.assert_converged();
+        assert_eq!(entry_hashes(scenario.replica(1)), vec![merge]);
+        scenario.assert_descends_from(merge, left);
+        scenario.assert_descends_from(merge, right);
+        scenario.assert_descends_from(merge, root);
+        scenario.assert_ancestry();
+        Ok(())
+    }
+
+    #[test]
+    fn ops_are_only_delivered_between_the_given_writers() -> eyre::Result<()> {
+        let mut scenario = RegisterScenario::new(3)?;
+        scenario.run(&[
+            Step::Write {
+                writer: 0,
+                entry: b"a".to_vec(),
+            },
+            Step::Deliver { from: 0, to: 1 },
+        ])?;
+        assert_eq!(scenario.replica(1), scenario.replica(0));
+        assert_ne!(scenario.replica(2), scenario.replica(0));
+        assert_eq!(scenario.in_flight(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn the_same_seed_delivers_in_the_same_order() -> eyre::Result<()> {
+        // each op delivered, as its sender, recipient and the index of the entry written
+        let deliveries = |seed| -> eyre::Result<Vec<(usize, usize, usize)>> {
+            let mut scenario = RegisterScenario::new(3)?;
+            for (writer, entry) in [(0, "a"), (1, "b"), (2, "c")] {
+                let _ = scenario.write(writer, entry.as_bytes().to_vec())?;
+            }
+            assert_eq!(scenario.deliver_shuffled(seed)?, 6);
+            scenario.assert_converged();
+            scenario
+                .delivered()
+                .iter()
+                .map(|op| {
+                    let index = scenario
+                        .written()
+                        .iter()
+                        .position(|written| written.hash == op.hash)
+                        .ok_or_else(|| eyre::eyre!("{op:?} delivers an entry never written"))?;
+                    Ok((op.from, op.to, index))
+                })
+                .collect()
+        };
+
+        let order = deliveries(3)?;
+        assert_eq!(order.len(), 6);
+        assert_eq!(order, deliveries(3)?);
+        // while other seeds shuffle the ops otherwise
+        let orders: BTreeSet<_> = (0..8).map(deliveries).collect::<eyre::Result<_>>()?;
+        assert!(orders.len() > 1);
+        Ok(())
+    }
+
+    fn generate_step(writers: usize) -> impl Strategy<Value = Step> {
+        prop_oneof![
+            4 => (0..writers, "\\PC{0,16}").prop_map(|(writer, entry)| Step::Write {
+                writer,
+                entry: entry.into_bytes(),
+            }),
+            2 => (0..writers, 0..writers).prop_map(|(from, to)| Step::Deliver { from, to }),
+            1 => any::<u64>().prop_map(Step::DeliverShuffled),
+            1 => prop::collection::vec(0..writers, 0..writers)
+                .prop_map(|group| Step::Partition(vec![group])),
+            1 => Just(Step::Heal),
+        ]
+    }
+
+    fn generate_script() -> impl Strategy<Value = (usize, Vec<Step>)> {
+        (1..6usize).prop_flat_map(|writers| {
+            (
+                Just(writers),
+                prop::collection::vec(generate_step(writers), 1..40),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_concurrent_writes_converge_once_all_ops_are_delivered(
+            (writers, steps) in generate_script(),
+            seed in any::<u64>(),
+        ) {
+            let mut scenario = RegisterScenario::new(writers)?;
+            scenario.run(&steps)?;
+            scenario.assert_ancestry();
+
+            scenario.heal();
+            let _ = scenario.deliver_shuffled(seed)?;
+            prop_assert_eq!(scenario.in_flight(), 0);
+            scenario.assert_converged();
+            scenario.assert_ancestry();
+        }
+
+        #[test]
+        fn proptest_delivery_order_does_not_change_the_outcome(
+            (writers, steps) in generate_script(),
+            seeds in (any::<u64>(), any::<u64>()),
+        ) {
+            let mut scenario = RegisterScenario::new(writers)?;
+            scenario.run(&steps)?;
+            scenario.heal();
+
+            let mut shuffled = scenario.clone();
+            let _ = scenario.deliver_shuffled(seeds.0)?;
+            let _ = shuffled.deliver_shuffled(seeds.1)?;
+            prop_assert_eq!(scenario.replica(0), shuffled.replica(0));
+        }
+    }
+}
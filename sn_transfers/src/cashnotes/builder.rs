@@ -9,7 +9,7 @@
 use super::{
     transaction::{Output, Transaction},
     CashNote, DerivationIndex, DerivedSecretKey, Hash, Input, MainPubkey, NanoTokens, SignedSpend,
-    Spend, UniquePubkey,
+    Spend, UniquePubkey, MAX_SPEND_SIZE,
 };
 
 use crate::{Error, Result};
@@ -83,10 +83,22 @@ impl TransactionBuilder {
     }
 
     /// Build the Transaction by signing the inputs. Return a CashNoteBuilder.
+    ///
+    /// Fails with `Error::TransactionTooLarge` if any of the spends would not fit in a record.
     pub fn build(
         self,
         reason: Hash,
         network_royalties: Vec<DerivationIndex>,
+    ) -> Result<CashNoteBuilder> {
+        self.build_within(reason, network_royalties, MAX_SPEND_SIZE)
+    }
+
+    /// Like `build`, with the spends limited to `max_spend_size` bytes.
+    pub(crate) fn build_within(
+        self,
+        reason: Hash,
+        network_royalties: Vec<DerivationIndex>,
+        max_spend_size: usize,
     ) -> Result<CashNoteBuilder> {
         let spent_tx = Transaction {
             inputs: self.inputs,
@@ -112,6 +124,20 @@ impl TransactionBuilder {
             }
         }
 
+        // each spend carries the whole tx, and the tx its input was created in
+        let mut size = 0;
+        for signed_spend in &signed_spends {
+            size = size.max(signed_spend.serialised_size()?);
+        }
+        if size > max_spend_size {
+            return Err(Error::TransactionTooLarge {
+                size,
+                max: max_spend_size,
+                inputs: spent_tx.inputs.len(),
+                outputs: spent_tx.outputs.len(),
+            });
+        }
+
         Ok(CashNoteBuilder::new(
             spent_tx,
             self.output_details,
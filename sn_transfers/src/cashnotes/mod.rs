@@ -15,14 +15,14 @@ mod signed_spend;
 mod transaction;
 mod unique_keys;
 
-pub(crate) use builder::TransactionBuilder;
-pub(crate) use transaction::Input;
+pub(crate) use builder::{CashNoteBuilder, TransactionBuilder};
+pub(crate) use transaction::{Input, Output};
 
 pub use address::SpendAddress;
 pub use cashnote::CashNote;
 pub use nano::NanoTokens;
 pub use reason_hash::Hash;
-pub use signed_spend::{SignedSpend, Spend};
+pub use signed_spend::{SignedSpend, Spend, MAX_SPEND_SIZE};
 pub use transaction::Transaction;
 pub use unique_keys::{DerivationIndex, DerivedSecretKey, MainPubkey, MainSecretKey, UniquePubkey};
 
@@ -30,7 +30,6 @@ pub use unique_keys::{DerivationIndex, DerivedSecretKey, MainPubkey, MainSecretK
 pub(crate) mod tests {
     use super::*;
     use crate::Error;
-    use transaction::Output;
 
     #[test]
     fn from_hex_should_deserialize_a_hex_encoded_string_to_a_cashnote() -> Result<(), Error> {
@@ -13,6 +13,10 @@ use custom_debug::Debug;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
+/// The maximum size of a serialised spend. Each spend is stored in a record of its own, which
+/// the network caps at 5MB, so this leaves room for the record header and the packet framing.
+pub const MAX_SPEND_SIZE: usize = 5 * 1024 * 1024 - 64 * 1024;
+
 /// SignedSpend's are constructed when a CashNote is logged to the spentbook.
 #[derive(Debug, Clone, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SignedSpend {
@@ -62,6 +66,13 @@ impl SignedSpend {
         bytes
     }
 
+    /// The size of this SignedSpend once serialised, as it is stored on the network.
+    pub fn serialised_size(&self) -> Result<usize> {
+        rmp_serde::to_vec(self)
+            .map(|bytes| bytes.len())
+            .map_err(|err| Error::SerializationFailed(err.to_string()))
+    }
+
     /// Verify this SignedSpend
     ///
     /// Checks that
@@ -103,6 +103,22 @@ impl Transaction {
         }
     }
 
+    /// A transaction spending the given keys and amounts into the given ones, unchecked, to build
+    /// the transactions of test fixtures in other crates.
+    #[cfg(feature = "test-utils")]
+    pub fn from_keys(inputs: &[(UniquePubkey, u64)], outputs: &[(UniquePubkey, u64)]) -> Self {
+        Self {
+            inputs: inputs
+                .iter()
+                .map(|(key, amount)| Input::new(*key, *amount))
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|(key, amount)| Output::new(*key, *amount))
+                .collect(),
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut v: Vec<u8> = Default::default();
         v.extend("inputs".as_bytes());
@@ -125,6 +141,13 @@ impl Transaction {
         crate::Hash::from(hash)
     }
 
+    /// The size of this transaction once serialised, as carried by the spends of its inputs.
+    pub fn serialised_size(&self) -> Result<usize> {
+        rmp_serde::to_vec(self)
+            .map(|bytes| bytes.len())
+            .map_err(|err| Error::SerializationFailed(err.to_string()))
+    }
+
     /// Quickly check is a transaction is balanced
     fn verify_balanced(&self) -> Result<()> {
         // Check that the input and output tokens are equal.
@@ -65,6 +65,17 @@ pub enum Error {
     SpendsDoNotMatchInputs,
     #[error("Overflow occurred while adding values")]
     NumericOverflow,
+    #[error(
+        "The transaction of {inputs} inputs and {outputs} outputs is too large: its spends take up \
+        to {size} bytes, more than the {max} bytes a record can hold. Consolidate the cash notes \
+        of the wallet, or send to fewer recipients at once."
+    )]
+    TransactionTooLarge {
+        size: usize,
+        max: usize,
+        inputs: usize,
+        outputs: usize,
+    },
 
     /// Not enough balance to perform a transaction
     #[error("Not enough balance, {0} available, {1} required")]
@@ -85,6 +96,14 @@ pub enum Error {
     TransferSerializationFailed,
     #[error("Transfer deserialisation failed")]
     TransferDeserializationFailed,
+    #[error("CashNote {0:?} was not paid to the network royalties key")]
+    NotARoyaltyCashNote(UniquePubkey),
+    #[error("Royalties claim serialisation failed: {0}")]
+    RoyaltiesClaimSerializationFailed(String),
+    #[error("Royalties claim deserialisation failed: {0}")]
+    RoyaltiesClaimDeserializationFailed(String),
+    #[error("Serialisation failed: {0}")]
+    SerializationFailed(String),
 
     #[error("Bls error: {0}")]
     Blsttc(#[from] bls::error::Error),
@@ -15,6 +15,7 @@ use crate::{
 
 use bls::SecretKey;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, path::PathBuf};
 use thiserror::Error;
 
@@ -24,12 +25,56 @@ use thiserror::Error;
 /// thus creating a total of 1,288,490,189,000,000,000 available units.
 pub(super) const GENESIS_CASHNOTE_AMOUNT: u64 = (0.3 * TOTAL_SUPPLY as f64) as u64;
 
-/// Based on the given store cost, it calculates what's the expected amount to be paid as network royalties.
+/// The network royalties rate in use, see `RoyaltyRate`.
+pub const ROYALTY_RATE: RoyaltyRate = RoyaltyRate {
+    version: 1,
+    basis_points: 1500,
+};
+
+/// The share of a storage payment going to the network royalties.
+///
+/// Quotes and payments carry the rate they were made with, and the version is bumped whenever
+/// the rate changes, for nodes and clients to tell when they disagree on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct RoyaltyRate {
+    pub version: u32,
+    /// The share of the whole payment, store cost and royalties, in hundredths of a percent.
+    pub basis_points: u32,
+}
+
+impl Default for RoyaltyRate {
+    fn default() -> Self {
+        ROYALTY_RATE
+    }
+}
+
+impl RoyaltyRate {
+    /// The royalties to pay along with the given store cost, rounded down.
+    ///
+    /// This is computed in floating point, as it always was, for payments made under version 1
+    /// of the rate to keep being accepted to the nano.
+    pub fn fee_for(&self, store_cost: NanoTokens) -> NanoTokens {
+        let basis_points = self.basis_points.min(9_999);
+        let share = f64::from(basis_points) / 10_000.0;
+        let rest = f64::from(10_000 - basis_points) / 10_000.0;
+        let fees_amount = (store_cost.as_nano() as f64 * share) / rest;
+        // we round down the calculated amount
+        NanoTokens::from(fees_amount as u64)
+    }
+
+    /// The bytes of the rate, for it to be signed as part of a quote.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.version.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.basis_points.to_le_bytes());
+        bytes
+    }
+}
+
+/// Based on the given store cost, it calculates what's the expected amount to be paid as network royalties,
+/// at the current `ROYALTY_RATE`.
 /// Network royalties fee is expected to be 15% of the payment amount, i.e. 85% of store cost + 15% royalties fees.
 pub fn calculate_royalties_fee(store_cost: NanoTokens) -> NanoTokens {
-    let fees_amount = (store_cost.as_nano() as f64 * 0.15) / 0.85;
-    // we round down the calculated amount
-    NanoTokens::from(fees_amount as u64)
+    ROYALTY_RATE.fee_for(store_cost)
 }
 
 /// A specialised `Result` type for genesis crate.
@@ -198,3 +243,30 @@ fn get_faucet_dir() -> PathBuf {
         .expect("Faucet test path to be successfully created.");
     data_dirs
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn royalties_fee_is_rounded_as_before_the_rate_was_versioned() {
+        for store_cost in [
+            0,
+            1,
+            7,
+            17,
+            85,
+            1_000,
+            123_456_789,
+            10_000_000_000,
+            u64::MAX / 2,
+        ] {
+            let fees_amount = (store_cost as f64 * 0.15) / 0.85;
+            assert_eq!(
+                calculate_royalties_fee(NanoTokens::from(store_cost)),
+                NanoTokens::from(fees_amount as u64),
+                "store cost {store_cost}"
+            );
+        }
+    }
+}
@@ -15,27 +15,31 @@ mod genesis;
 mod transfers;
 mod wallet;
 
-pub(crate) use cashnotes::{Input, TransactionBuilder};
+pub(crate) use cashnotes::{CashNoteBuilder, Input, Output, TransactionBuilder};
 
 /// Types used in the public API
 pub use cashnotes::{
     CashNote, DerivationIndex, DerivedSecretKey, Hash, MainPubkey, MainSecretKey, NanoTokens,
-    SignedSpend, Spend, SpendAddress, Transaction, UniquePubkey,
+    SignedSpend, Spend, SpendAddress, Transaction, UniquePubkey, MAX_SPEND_SIZE,
 };
 pub use error::{Error, Result};
-pub use transfers::{CashNoteRedemption, OfflineTransfer, Transfer};
+pub use transfers::{
+    CashNoteRedemption, OfflineTransfer, SignedRoyaltiesClaim, Transfer, UnsignedRoyaltiesClaim,
+};
 
 /// Utilities exposed
 pub use genesis::{
     calculate_royalties_fee, create_faucet_wallet, create_first_cash_note_from_key,
-    is_genesis_parent_tx, load_genesis_wallet, Error as GenesisError, GENESIS_CASHNOTE,
-    GENESIS_CASHNOTE_SK, NETWORK_ROYALTIES_PK,
+    is_genesis_parent_tx, load_genesis_wallet, Error as GenesisError, RoyaltyRate,
+    GENESIS_CASHNOTE, GENESIS_CASHNOTE_SK, NETWORK_ROYALTIES_PK, ROYALTY_RATE,
 };
-pub use transfers::create_offline_transfer;
+pub use transfers::{create_offline_transfer, create_offline_transfer_with_auto_split};
 pub use wallet::bls_secret_from_hex;
 pub use wallet::{
-    Error as WalletError, LocalWallet, Payment, PaymentQuote, Result as WalletResult,
-    WatchOnlyWallet,
+    AutoSplitPolicy, Error as WalletError, HistoryEntry, HistoryFormat, HistoryKind, LocalWallet,
+    Payment, PaymentDetails, PaymentQuote, Result as WalletResult, StorageVoucher,
+    StorageVoucherGrant, WalletDirCheck, WalletLockState, WatchOnlyWallet,
+    DEFAULT_AUTO_SPLIT_MAX_NOTES, HISTORY_CSV_HEADER,
 };
 
 // re-export crates used in our public API
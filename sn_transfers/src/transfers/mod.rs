@@ -28,7 +28,12 @@
 //! A cash_note transaction is the lower layer concept where the blinded inputs and outputs are specified.
 
 mod offline_transfer;
+mod royalties_claim;
 mod transfer;
 
-pub use offline_transfer::{create_offline_transfer, OfflineTransfer};
+pub(crate) use offline_transfer::create_offline_transfers;
+pub use offline_transfer::{
+    create_offline_transfer, create_offline_transfer_with_auto_split, OfflineTransfer,
+};
+pub use royalties_claim::{SignedRoyaltiesClaim, UnsignedRoyaltiesClaim};
 pub use transfer::{CashNoteRedemption, Transfer};
@@ -7,13 +7,14 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::{
-    rng, CashNote, DerivationIndex, DerivedSecretKey, Hash, Input, MainPubkey, NanoTokens,
-    SignedSpend, Transaction, TransactionBuilder, NETWORK_ROYALTIES_PK,
+    rng, AutoSplitPolicy, CashNote, DerivationIndex, DerivedSecretKey, Hash, Input, MainPubkey,
+    MainSecretKey, NanoTokens, SignedSpend, Transaction, TransactionBuilder, MAX_SPEND_SIZE,
+    NETWORK_ROYALTIES_PK,
 };
 use crate::{Error, Result};
 
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Offline Transfer
 /// This struct contains all the necessary information to carry out the transfer.
@@ -32,6 +33,11 @@ pub struct OfflineTransfer {
     /// spending the necessary input cash_notes.
     #[debug(skip)]
     pub change_cash_note: Option<CashNote>,
+    /// The further cash_notes holding surplus tokens, when the change
+    /// was split following an `AutoSplitPolicy`.
+    #[debug(skip)]
+    #[serde(default)]
+    pub split_change_cash_notes: Vec<CashNote>,
     /// The parameters necessary to send all spend requests to the network.
     pub all_spend_requests: Vec<SignedSpend>,
 }
@@ -45,8 +51,9 @@ struct TranferInputs {
     pub cash_notes_to_spend: Vec<(CashNote, DerivedSecretKey)>,
     /// The amounts and cash_note ids for the cash_notes that will be created to hold the transferred tokens.
     pub recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
-    /// Any surplus amount after spending the necessary input cash_notes.
-    pub change: (NanoTokens, MainPubkey),
+    /// Any surplus amount after spending the necessary input cash_notes,
+    /// split into one or more amounts.
+    pub change: (Vec<NanoTokens>, MainPubkey),
 }
 
 /// A function for creating an offline transfer of tokens.
@@ -64,6 +71,151 @@ pub fn create_offline_transfer(
     recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
     change_to: MainPubkey,
     reason_hash: Hash,
+) -> Result<OfflineTransfer> {
+    create_offline_transfer_splitting_change(
+        available_cash_notes,
+        recipients,
+        change_to,
+        reason_hash,
+        None,
+        MAX_SPEND_SIZE,
+    )
+}
+
+/// Like `create_offline_transfer`, but the change is split following the given policy,
+/// into several cash_notes in the same transaction.
+pub fn create_offline_transfer_with_auto_split(
+    available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+    recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+    change_to: MainPubkey,
+    reason_hash: Hash,
+    policy: &AutoSplitPolicy,
+) -> Result<OfflineTransfer> {
+    create_offline_transfer_splitting_change(
+        available_cash_notes,
+        recipients,
+        change_to,
+        reason_hash,
+        Some(policy),
+        MAX_SPEND_SIZE,
+    )
+}
+
+/// Like `create_offline_transfer`, but the recipients are split across as many transactions
+/// as needed for their spends to fit in a record, the change being split following the policy
+/// if one is given.
+///
+/// Each transaction spends the change of the previous one, so the change has to be paid to
+/// `change_key`, for it to be spent. The spends of a transaction have to be sent to the
+/// network after those of the transactions before it.
+pub(crate) fn create_offline_transfers(
+    available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+    recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+    change_key: &MainSecretKey,
+    reason_hash: Hash,
+    policy: Option<&AutoSplitPolicy>,
+) -> Result<Vec<OfflineTransfer>> {
+    create_offline_transfers_within(
+        available_cash_notes,
+        recipients,
+        change_key,
+        reason_hash,
+        policy,
+        MAX_SPEND_SIZE,
+    )
+}
+
+fn create_offline_transfers_within(
+    mut available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+    recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+    change_key: &MainSecretKey,
+    reason_hash: Hash,
+    policy: Option<&AutoSplitPolicy>,
+    max_spend_size: usize,
+) -> Result<Vec<OfflineTransfer>> {
+    let mut transfers = vec![];
+    let mut pending = recipients;
+    let mut batch_len = pending.len();
+    loop {
+        let batch = pending[..batch_len].to_vec();
+        let is_last = batch_len == pending.len();
+        let result = create_offline_transfer_splitting_change(
+            available_cash_notes.clone(),
+            batch,
+            change_key.main_pubkey(),
+            reason_hash,
+            policy,
+            max_spend_size,
+        )
+        .and_then(|transfer| {
+            // a transaction followed by another is carried by its spends too, as their parent,
+            // so it can only take up half of them
+            let size = transfer.tx.serialised_size()?;
+            if !is_last && size > max_spend_size / 2 {
+                return Err(Error::TransactionTooLarge {
+                    size,
+                    max: max_spend_size / 2,
+                    inputs: transfer.tx.inputs.len(),
+                    outputs: transfer.tx.outputs.len(),
+                });
+            }
+            Ok(transfer)
+        });
+        let transfer = match result {
+            Ok(transfer) => transfer,
+            Err(Error::TransactionTooLarge { .. }) if batch_len > 1 => {
+                // storage payments pay a node and the royalties for each chunk, in pairs which
+                // are kept in the same transaction, for the spends to tell which chunk each is for
+                batch_len = match batch_len / 2 {
+                    half if half > 1 => half - half % 2,
+                    half => half,
+                };
+                debug!("Transaction too large, trying again with {batch_len} recipients");
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        // the spent cash_notes make way for the change, spent by the next transaction
+        let spent: BTreeSet<_> = transfer
+            .tx
+            .inputs
+            .iter()
+            .map(|input| *input.unique_pubkey())
+            .collect();
+        available_cash_notes.retain(|(cash_note, _)| !spent.contains(&cash_note.unique_pubkey()));
+        for change in transfer
+            .change_cash_note
+            .iter()
+            .chain(&transfer.split_change_cash_notes)
+        {
+            available_cash_notes.insert(0, (change.clone(), change.derived_key(change_key)?));
+        }
+        transfers.push(transfer);
+
+        let _ = pending.drain(..batch_len);
+        if pending.is_empty() {
+            break;
+        }
+        batch_len = batch_len.min(pending.len());
+    }
+
+    if transfers.len() > 1 {
+        info!(
+            "Split the transfer into {} transactions to stay under {max_spend_size} bytes per spend",
+            transfers.len()
+        );
+    }
+    Ok(transfers)
+}
+
+fn create_offline_transfer_splitting_change(
+    available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+    recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+    change_to: MainPubkey,
+    reason_hash: Hash,
+    policy: Option<&AutoSplitPolicy>,
+    max_spend_size: usize,
 ) -> Result<OfflineTransfer> {
     let total_output_amount = recipients
         .iter()
@@ -77,16 +229,25 @@ pub fn create_offline_transfer(
         })?;
 
     // We need to select the necessary number of cash_notes from those that we were passed.
+    let available_count = available_cash_notes.len();
     let (cash_notes_to_spend, change_amount) =
         select_inputs(available_cash_notes, total_output_amount)?;
 
+    let change_amounts = match policy {
+        Some(policy) => {
+            let untouched_notes = available_count.saturating_sub(cash_notes_to_spend.len());
+            policy.split_change(change_amount, untouched_notes)
+        }
+        None => vec![change_amount],
+    };
+
     let selected_inputs = TranferInputs {
         cash_notes_to_spend,
         recipients,
-        change: (change_amount, change_to),
+        change: (change_amounts, change_to),
     };
 
-    create_offline_transfer_with(selected_inputs, reason_hash)
+    create_offline_transfer_with(selected_inputs, reason_hash, max_spend_size)
 }
 
 /// Select the necessary number of cash_notes from those that we were passed.
@@ -159,9 +320,10 @@ fn select_inputs(
 fn create_offline_transfer_with(
     selected_inputs: TranferInputs,
     reason_hash: Hash,
+    max_spend_size: usize,
 ) -> Result<OfflineTransfer> {
     let TranferInputs {
-        change: (change, change_to),
+        change: (change_amounts, change_to),
         ..
     } = selected_inputs;
 
@@ -196,14 +358,19 @@ fn create_offline_transfer_with(
         .add_inputs(inputs)
         .add_outputs(selected_inputs.recipients);
     let mut rng = rng::thread_rng();
-    let derivation_index = DerivationIndex::random(&mut rng);
-    let change_id = change_to.new_unique_pubkey(&derivation_index);
-    if !change.is_zero() {
+    let mut change_ids = vec![];
+    for change in change_amounts
+        .into_iter()
+        .filter(|change| !change.is_zero())
+    {
+        let derivation_index = DerivationIndex::random(&mut rng);
+        change_ids.push(change_to.new_unique_pubkey(&derivation_index));
         tx_builder = tx_builder.add_output(change, change_to, derivation_index);
     }
 
     // Finalize the tx builder to get the cash_note builder.
-    let cash_note_builder = tx_builder.build(reason_hash, network_royalties)?;
+    let cash_note_builder =
+        tx_builder.build_within(reason_hash, network_royalties, max_spend_size)?;
 
     let tx = cash_note_builder.spent_tx.clone();
 
@@ -237,20 +404,205 @@ fn create_offline_transfer_with(
         .map(|(cash_note, _)| cash_note)
         .collect();
 
-    let mut change_cash_note = None;
+    let mut change_cash_notes = BTreeMap::new();
     created_cash_notes.retain(|created| {
-        if created.unique_pubkey() == change_id {
-            change_cash_note = Some(created.clone());
+        if change_ids.contains(&created.unique_pubkey()) {
+            let _ = change_cash_notes.insert(created.unique_pubkey(), created.clone());
             false
         } else {
             true
         }
     });
+    // the first change amount is the change cash_note, any others come from splitting it
+    let mut change_cash_notes = change_ids
+        .iter()
+        .filter_map(|change_id| change_cash_notes.remove(change_id));
+    let change_cash_note = change_cash_notes.next();
+    let split_change_cash_notes = change_cash_notes.collect();
 
     Ok(OfflineTransfer {
         tx,
         created_cash_notes,
         change_cash_note,
+        split_change_cash_notes,
         all_spend_requests,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genesis::create_first_cash_note_from_key;
+    use eyre::Result;
+
+    type Recipients = Vec<(NanoTokens, MainPubkey, DerivationIndex)>;
+
+    /// A key, a cash_note it can spend, and `count` recipients to pay 1 nano each.
+    fn key_cash_note_and_recipients(
+        count: usize,
+    ) -> Result<(MainSecretKey, Vec<(CashNote, DerivedSecretKey)>, Recipients)> {
+        let key = MainSecretKey::random();
+        let cash_note = create_first_cash_note_from_key(&key)?;
+        let derived_key = cash_note.derived_key(&key)?;
+        let mut rng = rng::thread_rng();
+        let recipient = MainSecretKey::random().main_pubkey();
+        let recipients = (0..count)
+            .map(|_| {
+                let derivation_index = DerivationIndex::random(&mut rng);
+                (NanoTokens::from(1), recipient, derivation_index)
+            })
+            .collect();
+        Ok((key, vec![(cash_note, derived_key)], recipients))
+    }
+
+    fn largest_spend_size(transfer: &OfflineTransfer) -> Result<usize> {
+        let mut size = 0;
+        for spend in &transfer.all_spend_requests {
+            size = size.max(spend.serialised_size()?);
+        }
+        Ok(size)
+    }
+
+    #[test]
+    fn an_oversized_transfer_fails_before_being_sent() -> Result<()> {
+        let (key, available, recipients) = key_cash_note_and_recipients(60)?;
+        let whole = create_offline_transfer(
+            available.clone(),
+            recipients.clone(),
+            key.main_pubkey(),
+            Hash::default(),
+        )?;
+        let max = largest_spend_size(&whole)? / 2;
+
+        let result = create_offline_transfer_splitting_change(
+            available,
+            recipients,
+            key.main_pubkey(),
+            Hash::default(),
+            None,
+            max,
+        );
+        match result {
+            Err(Error::TransactionTooLarge {
+                size,
+                max: reported_max,
+                inputs,
+                outputs,
+            }) => {
+                assert!(size > max);
+                assert_eq!(reported_max, max);
+                assert_eq!(inputs, 1);
+                // the recipients and the change
+                assert_eq!(outputs, 61);
+            }
+            other => panic!("Expected the transfer to be too large, got {other:?}"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn an_oversized_transfer_is_split_across_transactions() -> Result<()> {
+        let (key, available, recipients) = key_cash_note_and_recipients(60)?;
+        let whole = create_offline_transfer(
+            available.clone(),
+            recipients.clone(),
+            key.main_pubkey(),
+            Hash::default(),
+        )?;
+        let max = largest_spend_size(&whole)? / 2;
+
+        let transfers = create_offline_transfers_within(
+            available,
+            recipients.clone(),
+            &key,
+            Hash::default(),
+            None,
+            max,
+        )?;
+        assert!(transfers.len() > 1);
+
+        let mut paid: Vec<_> = transfers
+            .iter()
+            .flat_map(|transfer| &transfer.created_cash_notes)
+            .map(|cash_note| cash_note.unique_pubkey())
+            .collect();
+        let mut expected: Vec<_> = recipients
+            .iter()
+            .map(|(_, main_pubkey, derivation_index)| {
+                main_pubkey.new_unique_pubkey(derivation_index)
+            })
+            .collect();
+        paid.sort();
+        expected.sort();
+        assert_eq!(paid, expected);
+
+        for transfer in &transfers {
+            assert!(largest_spend_size(transfer)? <= max);
+        }
+        // each transaction spends the change of the one before it
+        for pair in transfers.windows(2) {
+            let change = pair[0]
+                .change_cash_note
+                .as_ref()
+                .expect("There to be change left.")
+                .unique_pubkey();
+            assert!(pair[1]
+                .tx
+                .inputs
+                .iter()
+                .any(|input| *input.unique_pubkey() == change));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pairs_of_recipients_are_kept_in_the_same_transaction() -> Result<()> {
+        // halving 62 recipients would leave an odd 31 of them
+        let (key, available, recipients) = key_cash_note_and_recipients(62)?;
+        let whole = create_offline_transfer(
+            available.clone(),
+            recipients.clone(),
+            key.main_pubkey(),
+            Hash::default(),
+        )?;
+        let max = largest_spend_size(&whole)? / 2;
+
+        let transfers = create_offline_transfers_within(
+            available,
+            recipients.clone(),
+            &key,
+            Hash::default(),
+            None,
+            max,
+        )?;
+        assert!(transfers.len() > 1);
+
+        // the recipients are paid in order, so each pair is paid by the same transaction
+        let mut pairs = recipients.chunks(2);
+        for transfer in &transfers {
+            assert_eq!(transfer.created_cash_notes.len() % 2, 0);
+            for _ in 0..transfer.created_cash_notes.len() / 2 {
+                let pair = pairs.next().expect("There to be a pair left.");
+                for (_, main_pubkey, derivation_index) in pair {
+                    let unique_pubkey = main_pubkey.new_unique_pubkey(derivation_index);
+                    assert!(transfer
+                        .created_cash_notes
+                        .iter()
+                        .any(|cash_note| cash_note.unique_pubkey() == unique_pubkey));
+                }
+            }
+        }
+        assert!(pairs.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn a_transfer_within_the_limit_is_not_split() -> Result<()> {
+        let (key, available, recipients) = key_cash_note_and_recipients(3)?;
+        let transfers =
+            create_offline_transfers(available, recipients, &key, Hash::default(), None)?;
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].created_cash_notes.len(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::{
+    rng, CashNote, CashNoteRedemption, DerivationIndex, Hash, Input, MainPubkey, MainSecretKey,
+    NanoTokens, Output, SignedSpend, Spend, Transaction, NETWORK_ROYALTIES_PK,
+};
+use crate::{CashNoteBuilder, Error, Result};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A claim of the royalties paid to `NETWORK_ROYALTIES_PK`, prepared online without the royalties key.
+///
+/// The claim spends all the royalty CashNotes into a single CashNote to the recipient.
+/// It holds everything needed to sign the spends offline, where the royalties key lives,
+/// and nothing that would allow anyone to spend the royalties in any other way.
+#[derive(custom_debug::Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnsignedRoyaltiesClaim {
+    /// The redemptions of the royalties being claimed, as found when auditing.
+    #[debug(skip)]
+    pub redemptions: Vec<CashNoteRedemption>,
+    /// The spends of the royalty CashNotes, with the derivation index of the key to sign them with.
+    #[debug(skip)]
+    pub spends: Vec<(Spend, DerivationIndex)>,
+    /// The recipient of the claimed royalties.
+    pub recipient: MainPubkey,
+    /// The derivation index of the CashNote created for the recipient.
+    pub derivation_index: DerivationIndex,
+}
+
+/// A royalties claim signed with the royalties key, ready to be broadcast.
+#[derive(custom_debug::Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignedRoyaltiesClaim {
+    /// The spends of the royalty CashNotes, to be sent to the network.
+    pub spends: Vec<SignedSpend>,
+    /// The CashNote created for the recipient, only valid once the spends are on the network.
+    #[debug(skip)]
+    pub cash_note: CashNote,
+}
+
+impl UnsignedRoyaltiesClaim {
+    /// Prepare the claim of the given royalty CashNotes, rebuilt from their redemptions.
+    /// Returns an error if any of them wasn't paid to `NETWORK_ROYALTIES_PK`.
+    pub fn new(
+        royalties: Vec<(CashNoteRedemption, CashNote)>,
+        recipient: MainPubkey,
+        reason: Hash,
+    ) -> Result<Self> {
+        if royalties.is_empty() {
+            return Err(Error::MissingTxInputs);
+        }
+
+        let mut inputs = vec![];
+        let mut total = NanoTokens::zero();
+        for (_, cash_note) in &royalties {
+            if *cash_note.main_pubkey() != *NETWORK_ROYALTIES_PK {
+                return Err(Error::NotARoyaltyCashNote(cash_note.unique_pubkey()));
+            }
+            let amount = cash_note.value()?;
+            total = total.checked_add(amount).ok_or(Error::NumericOverflow)?;
+            inputs.push(Input::new(cash_note.unique_pubkey(), amount.as_nano()));
+        }
+
+        let derivation_index = DerivationIndex::random(&mut rng::thread_rng());
+        let output = Output::new(
+            recipient.new_unique_pubkey(&derivation_index),
+            total.as_nano(),
+        );
+        let spent_tx = Transaction {
+            inputs,
+            outputs: vec![output],
+        };
+
+        let (redemptions, spends) = royalties
+            .into_iter()
+            .map(|(redemption, cash_note)| {
+                let spend = Spend {
+                    unique_pubkey: cash_note.unique_pubkey(),
+                    spent_tx: spent_tx.clone(),
+                    reason,
+                    token: cash_note.value()?,
+                    parent_tx: cash_note.src_tx,
+                    network_royalties: vec![],
+                };
+                Ok((redemption, (spend, cash_note.derivation_index)))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        Ok(Self {
+            redemptions,
+            spends,
+            recipient,
+            derivation_index,
+        })
+    }
+
+    /// The total amount of royalties claimed.
+    pub fn total(&self) -> Result<NanoTokens> {
+        self.spends
+            .iter()
+            .try_fold(NanoTokens::zero(), |total, (spend, _)| {
+                total.checked_add(spend.token).ok_or(Error::NumericOverflow)
+            })
+    }
+
+    /// Sign the spends with the royalties key, and build the CashNote of the recipient.
+    /// This is meant to be done offline, the claim being checked before anything gets signed.
+    pub fn sign(self, royalties_sk: &MainSecretKey) -> Result<SignedRoyaltiesClaim> {
+        if royalties_sk.main_pubkey() != *NETWORK_ROYALTIES_PK {
+            return Err(Error::MainSecretKeyDoesNotMatchMainPubkey);
+        }
+        let spent_tx = match self.spends.first() {
+            Some((spend, _)) => spend.spent_tx.clone(),
+            None => return Err(Error::MissingTxInputs),
+        };
+        let recipient_key = self.recipient.new_unique_pubkey(&self.derivation_index);
+        if spent_tx.outputs.len() != 1 || spent_tx.outputs[0].unique_pubkey != recipient_key {
+            return Err(Error::UniquePubkeyNotFound);
+        }
+
+        let mut signed_spends = BTreeSet::new();
+        for (spend, derivation_index) in self.spends {
+            let derived_key = royalties_sk.derive_key(&derivation_index);
+            if derived_key.unique_pubkey() != spend.unique_pubkey {
+                return Err(Error::NotARoyaltyCashNote(spend.unique_pubkey));
+            }
+            if spend.spent_tx != spent_tx {
+                return Err(Error::TransactionHashMismatch(
+                    spent_tx.hash(),
+                    spend.spent_tx.hash(),
+                ));
+            }
+            let derived_key_sig = derived_key.sign(&spend.to_bytes());
+            let _ = signed_spends.insert(SignedSpend {
+                spend,
+                derived_key_sig,
+            });
+        }
+
+        let output_details =
+            BTreeMap::from([(recipient_key, (self.recipient, self.derivation_index))]);
+        let spends = signed_spends.iter().cloned().collect();
+        let cash_note = CashNoteBuilder::new(spent_tx, output_details, signed_spends)
+            .build()?
+            .into_iter()
+            .map(|(cash_note, _)| cash_note)
+            .next()
+            .ok_or(Error::OutputNotFound)?;
+
+        Ok(SignedRoyaltiesClaim { spends, cash_note })
+    }
+
+    /// Deserializes an `UnsignedRoyaltiesClaim` represented as a hex string.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        from_hex(hex)
+    }
+
+    /// Serialize this `UnsignedRoyaltiesClaim` to a hex string, to be carried to the offline signer.
+    pub fn to_hex(&self) -> Result<String> {
+        to_hex(self)
+    }
+}
+
+impl SignedRoyaltiesClaim {
+    /// Deserializes a `SignedRoyaltiesClaim` represented as a hex string.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        from_hex(hex)
+    }
+
+    /// Serialize this `SignedRoyaltiesClaim` to a hex string, to be carried back online.
+    pub fn to_hex(&self) -> Result<String> {
+        to_hex(self)
+    }
+}
+
+fn from_hex<T: for<'de> Deserialize<'de>>(hex: &str) -> Result<T> {
+    let mut bytes = hex::decode(hex.trim())
+        .map_err(|e| Error::RoyaltiesClaimDeserializationFailed(e.to_string()))?;
+    bytes.reverse();
+    rmp_serde::from_slice(&bytes)
+        .map_err(|e| Error::RoyaltiesClaimDeserializationFailed(e.to_string()))
+}
+
+fn to_hex<T: Serialize>(claim: &T) -> Result<String> {
+    let mut serialized = rmp_serde::to_vec(claim)
+        .map_err(|e| Error::RoyaltiesClaimSerializationFailed(e.to_string()))?;
+    serialized.reverse();
+    Ok(hex::encode(serialized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SpendAddress, GENESIS_CASHNOTE_SK};
+
+    fn royalties_sk() -> MainSecretKey {
+        MainSecretKey::new(
+            bls::SecretKey::from_hex(GENESIS_CASHNOTE_SK).expect("genesis key to be valid"),
+        )
+    }
+
+    fn royalty(main_pubkey: MainPubkey, amount: u64) -> (CashNoteRedemption, CashNote) {
+        let derivation_index = DerivationIndex::random(&mut rng::thread_rng());
+        let id = main_pubkey.new_unique_pubkey(&derivation_index);
+        let src_tx = Transaction {
+            inputs: vec![],
+            outputs: vec![Output::new(id, amount)],
+        };
+        let parent_spend = SpendAddress::from_unique_pubkey(&id);
+        let cash_note = CashNote {
+            id,
+            src_tx,
+            signed_spends: BTreeSet::new(),
+            main_pubkey,
+            derivation_index,
+        };
+        (
+            CashNoteRedemption::new(derivation_index, parent_spend),
+            cash_note,
+        )
+    }
+
+    #[test]
+    fn claim_is_signed_offline_into_a_cash_note_to_the_recipient() -> Result<()> {
+        let recipient = MainSecretKey::random();
+        let royalties = vec![
+            royalty(*NETWORK_ROYALTIES_PK, 10),
+            royalty(*NETWORK_ROYALTIES_PK, 32),
+        ];
+        let claim =
+            UnsignedRoyaltiesClaim::new(royalties, recipient.main_pubkey(), Hash::default())?;
+        assert_eq!(claim.total()?, NanoTokens::from(42));
+
+        // the claim travels to the offline signer and back as hex
+        let claim = UnsignedRoyaltiesClaim::from_hex(&claim.to_hex()?)?;
+        let signed = claim.sign(&royalties_sk())?;
+        let signed = SignedRoyaltiesClaim::from_hex(&signed.to_hex()?)?;
+
+        assert_eq!(signed.spends.len(), 2);
+        assert_eq!(signed.cash_note.value()?, NanoTokens::from(42));
+        assert_eq!(*signed.cash_note.main_pubkey(), recipient.main_pubkey());
+        signed.cash_note.verify(&recipient)?;
+        Ok(())
+    }
+
+    #[test]
+    fn only_royalties_can_be_claimed_with_the_royalties_key() -> Result<()> {
+        let recipient = MainSecretKey::random().main_pubkey();
+        let not_a_royalty = royalty(MainSecretKey::random().main_pubkey(), 10);
+        assert!(matches!(
+            UnsignedRoyaltiesClaim::new(vec![not_a_royalty], recipient, Hash::default()),
+            Err(Error::NotARoyaltyCashNote(_))
+        ));
+
+        let claim = UnsignedRoyaltiesClaim::new(
+            vec![royalty(*NETWORK_ROYALTIES_PK, 10)],
+            recipient,
+            Hash::default(),
+        )?;
+        assert!(matches!(
+            claim.sign(&MainSecretKey::random()),
+            Err(Error::MainSecretKeyDoesNotMatchMainPubkey)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::NanoTokens;
+use serde::{Deserialize, Serialize};
+
+/// The default maximum number of available cash_notes the auto split aims to keep the wallet at.
+pub const DEFAULT_AUTO_SPLIT_MAX_NOTES: usize = 64;
+
+/// A wallet policy splitting large change into several cash_notes, as extra outputs
+/// to ourselves in the same transaction.
+///
+/// Spending one large cash_note over and over chains every payment onto the change of the
+/// previous one. Holding several cash_notes lets payments be made concurrently instead.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoSplitPolicy {
+    /// Change above this amount is split.
+    pub threshold: NanoTokens,
+    /// The amounts the change is split into, largest first. The change is split into
+    /// cash_notes of `threshold` when none are set.
+    pub denominations: Vec<NanoTokens>,
+    /// The change is not split beyond this number of available cash_notes in the wallet.
+    pub max_notes: usize,
+}
+
+impl AutoSplitPolicy {
+    /// A policy splitting change above `threshold` into cash_notes of `threshold`.
+    pub fn new(threshold: NanoTokens) -> Self {
+        Self {
+            threshold,
+            denominations: vec![],
+            max_notes: DEFAULT_AUTO_SPLIT_MAX_NOTES,
+        }
+    }
+
+    /// Split the change amount, given the number of cash_notes the wallet holds
+    /// besides the ones being spent.
+    ///
+    /// The returned amounts add up to the change. Unless the change is below the threshold,
+    /// or the wallet already holds enough cash_notes, the change is split greedily into the
+    /// largest denominations first, the last amount holding what remains.
+    pub fn split_change(&self, change: NanoTokens, untouched_notes: usize) -> Vec<NanoTokens> {
+        let room = self.max_notes.saturating_sub(untouched_notes);
+        if change <= self.threshold || room < 2 {
+            return vec![change];
+        }
+
+        let mut denominations: Vec<_> = if self.denominations.is_empty() {
+            vec![self.threshold.as_nano()]
+        } else {
+            self.denominations.iter().map(|d| d.as_nano()).collect()
+        };
+        denominations.retain(|denomination| *denomination > 0);
+        denominations.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut remaining = change.as_nano();
+        let mut amounts = vec![];
+        for denomination in denominations {
+            while remaining > denomination && amounts.len() + 1 < room {
+                amounts.push(NanoTokens::from(denomination));
+                remaining -= denomination;
+            }
+        }
+        amounts.push(NanoTokens::from(remaining));
+        amounts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nanos(amounts: &[NanoTokens]) -> Vec<u64> {
+        amounts.iter().map(|amount| amount.as_nano()).collect()
+    }
+
+    #[test]
+    fn change_below_threshold_is_not_split() {
+        let policy = AutoSplitPolicy::new(NanoTokens::from(100));
+        assert_eq!(
+            nanos(&policy.split_change(NanoTokens::from(100), 0)),
+            vec![100]
+        );
+    }
+
+    #[test]
+    fn change_is_split_into_denominations_largest_first() {
+        let policy = AutoSplitPolicy {
+            threshold: NanoTokens::from(100),
+            denominations: vec![NanoTokens::from(10), NanoTokens::from(100)],
+            max_notes: 10,
+        };
+        let amounts = policy.split_change(NanoTokens::from(325), 0);
+        assert_eq!(nanos(&amounts), vec![100, 100, 100, 10, 10, 5]);
+    }
+
+    #[test]
+    fn split_keeps_within_max_notes() {
+        let policy = AutoSplitPolicy {
+            threshold: NanoTokens::from(10),
+            denominations: vec![],
+            max_notes: 5,
+        };
+        // 2 notes are already held, so the change is split into 3 at most
+        let amounts = policy.split_change(NanoTokens::from(1_000), 2);
+        assert_eq!(nanos(&amounts), vec![10, 10, 980]);
+
+        // no room left to split at all
+        let amounts = policy.split_change(NanoTokens::from(1_000), 4);
+        assert_eq!(nanos(&amounts), vec![1_000]);
+    }
+}
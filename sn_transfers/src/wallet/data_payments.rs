@@ -11,7 +11,10 @@ use std::{collections::BTreeMap, time::SystemTime};
 use serde::{Deserialize, Serialize};
 use xor_name::XorName;
 
-use crate::{MainPubkey, NanoTokens, Transfer};
+use super::StorageVoucher;
+use crate::{
+    DerivationIndex, MainPubkey, NanoTokens, RoyaltyRate, Transfer, UniquePubkey, ROYALTY_RATE,
+};
 
 #[derive(Clone, Serialize, Deserialize, Eq, PartialEq, custom_debug::Debug)]
 pub struct Payment {
@@ -20,6 +23,16 @@ pub struct Payment {
     pub transfers: Vec<Transfer>,
     /// The Quote we're paying for
     pub quote: PaymentQuote,
+    /// The rate the royalties were paid at
+    #[serde(default)]
+    pub royalty_rate: RoyaltyRate,
+    /// The voucher the payment was made with, on behalf of its funder
+    #[serde(default)]
+    pub voucher: Option<StorageVoucher>,
+    /// The derivation indexes of the inputs spent by a payment made with a voucher, bar its
+    /// reserve, for nodes to check they were all derived from the voucher key
+    #[serde(default)]
+    pub voucher_inputs: BTreeMap<UniquePubkey, DerivationIndex>,
 }
 
 /// Information relating to a data payment for one address
@@ -33,6 +46,15 @@ pub struct PaymentDetails {
     pub royalties: (Transfer, NanoTokens),
     /// The original quote
     pub quote: PaymentQuote,
+    /// The rate the royalties were paid at
+    #[serde(default)]
+    pub royalty_rate: RoyaltyRate,
+    /// The voucher the payment was made with, if any
+    #[serde(default)]
+    pub voucher: Option<StorageVoucher>,
+    /// The derivation indexes of the inputs spent with the voucher, bar its reserve
+    #[serde(default)]
+    pub voucher_inputs: BTreeMap<UniquePubkey, DerivationIndex>,
 }
 
 impl PaymentDetails {
@@ -41,6 +63,9 @@ impl PaymentDetails {
         Payment {
             transfers: vec![self.transfer.0.clone(), self.royalties.0.clone()],
             quote: self.quote.clone(),
+            royalty_rate: self.royalty_rate,
+            voucher: self.voucher.clone(),
+            voucher_inputs: self.voucher_inputs.clone(),
         }
     }
 }
@@ -64,9 +89,12 @@ pub struct PaymentQuote {
     pub cost: NanoTokens,
     /// the local node time when the quote was created
     pub timestamp: SystemTime,
-    /// the node's signature of the 3 fields above
+    /// the node's signature of the other fields
     #[debug(skip)]
     pub signature: QuoteSignature,
+    /// the rate the node expects the network royalties to be paid at
+    #[serde(default)]
+    pub royalty_rate: RoyaltyRate,
 }
 
 impl PaymentQuote {
@@ -77,11 +105,17 @@ impl PaymentQuote {
             cost: NanoTokens::zero(),
             timestamp: SystemTime::now(),
             signature: vec![],
+            royalty_rate: ROYALTY_RATE,
         }
     }
 
     /// returns the bytes to be signed
-    pub fn bytes_for_signing(xorname: XorName, cost: NanoTokens, timestamp: SystemTime) -> Vec<u8> {
+    pub fn bytes_for_signing(
+        xorname: XorName,
+        cost: NanoTokens,
+        timestamp: SystemTime,
+        royalty_rate: RoyaltyRate,
+    ) -> Vec<u8> {
         let mut bytes = xorname.to_vec();
         bytes.extend_from_slice(&cost.to_bytes());
         bytes.extend_from_slice(
@@ -91,6 +125,7 @@ impl PaymentQuote {
                 .as_secs()
                 .to_le_bytes(),
         );
+        bytes.extend_from_slice(&royalty_rate.to_bytes());
         bytes
     }
 
@@ -101,6 +136,7 @@ impl PaymentQuote {
             cost,
             timestamp: SystemTime::now(),
             signature: vec![],
+            royalty_rate: ROYALTY_RATE,
         }
     }
 }
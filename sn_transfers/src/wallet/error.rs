@@ -9,7 +9,7 @@
 use std::collections::BTreeSet;
 use thiserror::Error;
 
-use crate::UniquePubkey;
+use crate::{RoyaltyRate, SignedSpend, SpendAddress, UniquePubkey};
 
 /// Specialisation of `std::Result`.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -20,6 +20,12 @@ pub enum Error {
     /// The cashnotes that were attempted to be spent have already been spent to another address
     #[error("Double spend attempted with cashnotes: {0:?}")]
     DoubleSpendAttemptedForCashNotes(BTreeSet<UniquePubkey>),
+    /// Conflicting spends were found on the network at a single address
+    #[error("Found {} conflicting spends at {address:?}", spends.len())]
+    DoubleSpendDetected {
+        address: SpendAddress,
+        spends: Vec<SignedSpend>,
+    },
 
     /// Address provided is of the wrong type
     #[error("Invalid address type")]
@@ -27,6 +33,12 @@ pub enum Error {
     /// CashNote add would overflow
     #[error("Total price exceed possible token amount")]
     TotalPriceTooHigh,
+    /// A node quoted for royalties at another rate than ours
+    #[error("The royalty rate {theirs:?} of the node differs from ours {ours:?}")]
+    RoyaltyRateMismatch {
+        ours: RoyaltyRate,
+        theirs: RoyaltyRate,
+    },
     /// A general error when a transfer fails
     #[error("Failed to send tokens due to {0}")]
     CouldNotSendMoney(String),
@@ -60,6 +72,42 @@ pub enum Error {
     /// No cached payment found for address
     #[error("No ongoing payment found for address")]
     NoPaymentForAddress,
+    /// Another process held the lock of the wallet for too long
+    #[error("Timed out after {timeout:?} waiting for the wallet lock {path:?}")]
+    WalletLockTimeout {
+        path: std::path::PathBuf,
+        timeout: std::time::Duration,
+    },
+    /// Failed to export the wallet history
+    #[error("Failed to export the wallet history: {0}")]
+    HistoryExport(String),
+    /// The storage voucher wasn't signed by its funder
+    #[error("The storage voucher's signature is invalid")]
+    InvalidStorageVoucherSignature,
+    /// The storage voucher expired
+    #[error("The storage voucher expired at {0:?}")]
+    StorageVoucherExpired(std::time::SystemTime),
+    /// The expiry of the storage voucher can't be encoded, being before the UNIX epoch
+    #[error("The storage voucher's expiry {0:?} is before the UNIX epoch")]
+    InvalidStorageVoucherExpiry(std::time::SystemTime),
+    /// The storage voucher doesn't pay for data at the address
+    #[error("The storage voucher doesn't pay for data at {0:?}")]
+    StorageVoucherAddressNotAllowed(xor_name::XorName),
+    /// The storage voucher grant is invalid
+    #[error("Invalid storage voucher grant: {0}")]
+    InvalidStorageVoucherGrant(String),
+    /// Failed to read or write the payment store
+    #[error("Payment store I/O error at {path:?}: {source}")]
+    PaymentStoreIo {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    /// A payment of the payment store could not be encoded or decoded
+    #[error("Could not serialise the payment stored at {path:?}: {reason}")]
+    PaymentStoreSerialisation {
+        path: std::path::PathBuf,
+        reason: String,
+    },
 
     /// Transfer error
     #[error("Transfer error: {0}")]
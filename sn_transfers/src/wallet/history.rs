@@ -0,0 +1,232 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{Error, Result};
+use crate::{Hash, MainPubkey, NanoTokens, SpendAddress};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::Write,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The header of the CSV history export. The column set is stable, new columns are only ever
+/// appended:
+///  - `timestamp`: seconds since the UNIX epoch at which the entry was recorded
+///  - `kind`: `received`, `sent` or `storage_payment`
+///  - `amount`: the tokens received or sent, always positive
+///  - `balance`: the wallet balance right after the entry
+///  - `counterparty`: the hex encoded main pubkey of the recipient of a send, empty otherwise
+///  - `reason`: the reason of the transfer, when it is known to the wallet
+///  - `spend_addresses`: hex encoded, `;` separated. The addresses the received cash notes will be
+///    spent at, or for payments, the addresses of the spends we made.
+pub const HISTORY_CSV_HEADER: &str =
+    "timestamp,kind,amount,balance,counterparty,reason,spend_addresses";
+
+/// The format of a wallet history export.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryFormat {
+    /// One row per entry, under the `HISTORY_CSV_HEADER`.
+    Csv,
+    /// One JSON object per line, with the same fields as the CSV columns.
+    JsonLines,
+}
+
+impl FromStr for HistoryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "json-lines" => Ok(Self::JsonLines),
+            other => Err(format!(
+                "Unknown history format {other:?}, expected 'csv' or 'jsonl'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HistoryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Csv => write!(f, "csv"),
+            Self::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+/// What moved the tokens of a history entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryKind {
+    /// Cash notes deposited onto the wallet.
+    Received,
+    /// Tokens sent to another wallet.
+    Sent,
+    /// Payment for storing data, including the network royalties.
+    StoragePayment,
+}
+
+impl fmt::Display for HistoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Received => write!(f, "received"),
+            Self::Sent => write!(f, "sent"),
+            Self::StoragePayment => write!(f, "storage_payment"),
+        }
+    }
+}
+
+/// A movement of tokens into or out of the wallet, as recorded by the wallet when it happened.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the UNIX epoch at which the entry was recorded.
+    pub timestamp: u64,
+    pub kind: HistoryKind,
+    pub amount: NanoTokens,
+    /// The wallet balance right after the entry.
+    pub balance: NanoTokens,
+    /// The recipient of a send, unknown for the other kinds.
+    pub counterparty: Option<MainPubkey>,
+    pub reason_hash: Hash,
+    pub spend_addresses: Vec<SpendAddress>,
+}
+
+impl HistoryEntry {
+    pub(super) fn new(
+        kind: HistoryKind,
+        amount: NanoTokens,
+        balance: NanoTokens,
+        counterparty: Option<MainPubkey>,
+        reason_hash: Hash,
+        spend_addresses: Vec<SpendAddress>,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            kind,
+            amount,
+            balance,
+            counterparty,
+            reason_hash,
+            spend_addresses,
+        }
+    }
+
+    /// The text of the reason hash, if it is one the wallet knows of.
+    /// Only the hash of a reason is kept on the spends, so any other reason can't be resolved.
+    pub fn reason(&self) -> Option<&'static str> {
+        if self.reason_hash == Hash::hash(b"GENESIS") {
+            Some("GENESIS")
+        } else {
+            None
+        }
+    }
+}
+
+/// An entry as exported, every amount rendered with the `NanoTokens` decimal formatter.
+#[derive(Serialize)]
+struct HistoryRow {
+    timestamp: u64,
+    kind: String,
+    amount: String,
+    balance: String,
+    counterparty: String,
+    reason: String,
+    spend_addresses: Vec<String>,
+}
+
+impl From<&HistoryEntry> for HistoryRow {
+    fn from(entry: &HistoryEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            kind: entry.kind.to_string(),
+            amount: entry.amount.to_string(),
+            balance: entry.balance.to_string(),
+            counterparty: entry
+                .counterparty
+                .map(|pubkey| pubkey.to_hex())
+                .unwrap_or_default(),
+            reason: entry.reason().unwrap_or_default().to_string(),
+            spend_addresses: entry
+                .spend_addresses
+                .iter()
+                .map(|address| address.to_hex())
+                .collect(),
+        }
+    }
+}
+
+/// Write the given history entries in the given format.
+pub(super) fn write_history<W: Write>(
+    entries: &[HistoryEntry],
+    format: HistoryFormat,
+    mut writer: W,
+) -> Result<()> {
+    match format {
+        HistoryFormat::Csv => {
+            writeln!(writer, "{HISTORY_CSV_HEADER}")?;
+            for row in entries.iter().map(HistoryRow::from) {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    row.timestamp,
+                    row.kind,
+                    row.amount,
+                    row.balance,
+                    row.counterparty,
+                    csv_field(&row.reason),
+                    row.spend_addresses.join(";")
+                )?;
+            }
+        }
+        HistoryFormat::JsonLines => {
+            for row in entries.iter().map(HistoryRow::from) {
+                let line = serde_json::to_string(&row)
+                    .map_err(|err| Error::HistoryExport(err.to_string()))?;
+                writeln!(writer, "{line}")?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Quote a CSV field if it holds a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("GENESIS"), "GENESIS");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn history_format_from_str() {
+        assert_eq!(HistoryFormat::from_str("CSV"), Ok(HistoryFormat::Csv));
+        assert_eq!(
+            HistoryFormat::from_str("jsonl"),
+            Ok(HistoryFormat::JsonLines)
+        );
+        assert!(HistoryFormat::from_str("ofx").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    keys::{
+        get_main_key, get_main_pubkey, get_retired_keys, get_retired_pubkeys, MAIN_PUBKEY_FILENAME,
+        MAIN_SECRET_KEY_FILENAME, RETIRED_PUBKEYS_FILENAME, RETIRED_SECRET_KEYS_FILENAME,
+    },
+    lock::is_contended,
+    wallet_file::{
+        cash_notes_dir, get_auto_split_policy, get_history, get_issued_storage_vouchers,
+        get_storage_voucher, get_unconfirmed_spend_requests, get_wallet, wallet_lockfile_name,
+        AUTO_SPLIT_POLICY_FILE_NAME, HISTORY_FILE_NAME, ISSUED_STORAGE_VOUCHERS_FILE_NAME,
+        STORAGE_VOUCHER_FILE_NAME, UNCONFRIMED_TX_NAME, WALLET_FILE_NAME,
+    },
+};
+use crate::CashNote;
+use fs2::FileExt;
+use std::{
+    fs::{self, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// The state of the lock of a wallet dir, as seen by another process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletLockState {
+    /// The wallet was never locked.
+    NoLockFile,
+    /// Nobody holds the lock.
+    Free,
+    /// Another process holds the lock, taken this long ago if it can be told.
+    Held { since: Option<Duration> },
+}
+
+/// What was found checking a wallet dir, see `LocalWallet::check_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletDirCheck {
+    /// The dir that was checked.
+    pub wallet_dir: PathBuf,
+    /// Whether the dir exists at all.
+    pub exists: bool,
+    /// The state of the wallet lock.
+    pub lock: WalletLockState,
+    /// The files of the wallet that could not be read or deserialised, with the reason why.
+    pub unreadable_files: Vec<(PathBuf, String)>,
+    /// The number of valid cash notes found in the dir.
+    pub cash_notes: usize,
+}
+
+/// Check the files of the wallet in `wallet_dir`, without creating, locking for longer than
+/// a probe, or writing any of them.
+pub(super) fn check_wallet_dir(wallet_dir: &Path) -> WalletDirCheck {
+    let mut check = WalletDirCheck {
+        wallet_dir: wallet_dir.to_path_buf(),
+        exists: wallet_dir.is_dir(),
+        lock: WalletLockState::NoLockFile,
+        unreadable_files: vec![],
+        cash_notes: 0,
+    };
+    if !check.exists {
+        return check;
+    }
+
+    let lock_path = wallet_lockfile_name(wallet_dir);
+    match probe_lock(&lock_path) {
+        Ok(lock) => check.lock = lock,
+        Err(err) => check.unreadable_files.push((lock_path, err.to_string())),
+    }
+
+    let mut unreadable = |file: &str, err: String| {
+        check.unreadable_files.push((wallet_dir.join(file), err));
+    };
+    if let Err(err) = get_wallet(wallet_dir) {
+        unreadable(WALLET_FILE_NAME, err.to_string());
+    }
+    if let Err(err) = get_main_key(wallet_dir) {
+        unreadable(MAIN_SECRET_KEY_FILENAME, err.to_string());
+    }
+    if let Err(err) = get_main_pubkey(wallet_dir) {
+        unreadable(MAIN_PUBKEY_FILENAME, err.to_string());
+    }
+    if let Err(err) = get_retired_keys(wallet_dir) {
+        unreadable(RETIRED_SECRET_KEYS_FILENAME, err.to_string());
+    }
+    if let Err(err) = get_retired_pubkeys(wallet_dir) {
+        unreadable(RETIRED_PUBKEYS_FILENAME, err.to_string());
+    }
+    if let Err(err) = get_unconfirmed_spend_requests(wallet_dir) {
+        unreadable(UNCONFRIMED_TX_NAME, err.to_string());
+    }
+    if let Err(err) = get_auto_split_policy(wallet_dir) {
+        unreadable(AUTO_SPLIT_POLICY_FILE_NAME, err.to_string());
+    }
+    if let Err(err) = get_history(wallet_dir) {
+        unreadable(HISTORY_FILE_NAME, err.to_string());
+    }
+    if let Err(err) = get_storage_voucher(wallet_dir) {
+        unreadable(STORAGE_VOUCHER_FILE_NAME, err.to_string());
+    }
+    if let Err(err) = get_issued_storage_vouchers(wallet_dir) {
+        unreadable(ISSUED_STORAGE_VOUCHERS_FILE_NAME, err.to_string());
+    }
+
+    let cash_notes_dir = cash_notes_dir(wallet_dir);
+    if !cash_notes_dir.is_dir() {
+        return check;
+    }
+    for entry in walkdir::WalkDir::new(cash_notes_dir).min_depth(1) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                let path = err.path().map(Path::to_path_buf).unwrap_or_default();
+                check.unreadable_files.push((path, err.to_string()));
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let read = fs::read_to_string(entry.path())
+            .map_err(|err| err.to_string())
+            .and_then(|hex| CashNote::from_hex(hex.trim()).map_err(|err| err.to_string()));
+        match read {
+            Ok(_) => check.cash_notes += 1,
+            Err(err) => check.unreadable_files.push((entry.into_path(), err)),
+        }
+    }
+
+    check
+}
+
+/// Tell whether another process holds the lock of the wallet, by trying to take it and
+/// releasing it straight away. The lock file is neither created nor truncated.
+fn probe_lock(lock_path: &Path) -> io::Result<WalletLockState> {
+    let file = match OpenOptions::new().read(true).open(lock_path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(WalletLockState::NoLockFile)
+        }
+        Err(err) => return Err(err),
+    };
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            file.unlock()?;
+            Ok(WalletLockState::Free)
+        }
+        Err(err) if is_contended(&err) => {
+            // the lock file is truncated whenever the lock is taken
+            let since = file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            Ok(WalletLockState::Held { since })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genesis::create_first_cash_note_from_key, LocalWallet, MainSecretKey};
+    use assert_fs::TempDir;
+    use eyre::Result;
+
+    #[test]
+    fn a_missing_wallet_dir_is_not_created() {
+        let dir = TempDir::new().expect("Should be able to create a temp dir.");
+        let wallet_dir = dir.path().join("wallet");
+
+        let check = check_wallet_dir(&wallet_dir);
+        assert!(!check.exists);
+        assert!(check.unreadable_files.is_empty());
+        assert!(!wallet_dir.exists());
+    }
+
+    #[test]
+    fn a_sound_wallet_has_no_unreadable_files() -> Result<()> {
+        let dir = TempDir::new()?;
+        let key = MainSecretKey::random();
+        let mut wallet = LocalWallet::load_from_main_key(dir.path(), key.clone())?;
+        wallet.deposit_and_store_to_disk(&vec![create_first_cash_note_from_key(&key)?])?;
+        let wallet_dir = wallet.wallet_dir().to_path_buf();
+
+        let check = check_wallet_dir(&wallet_dir);
+        assert!(check.exists);
+        assert!(check.unreadable_files.is_empty(), "{check:?}");
+        assert_eq!(check.cash_notes, 1);
+        assert!(!matches!(check.lock, WalletLockState::Held { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_files_are_reported() -> Result<()> {
+        let dir = TempDir::new()?;
+        let wallet = LocalWallet::load_from_main_key(dir.path(), MainSecretKey::random())?;
+        let wallet_dir = wallet.wallet_dir().to_path_buf();
+        fs::write(wallet_dir.join(MAIN_PUBKEY_FILENAME), "not hex")?;
+        fs::create_dir_all(cash_notes_dir(&wallet_dir))?;
+        let corrupt_note = cash_notes_dir(&wallet_dir).join("corrupt.cash_note");
+        fs::write(&corrupt_note, "not a cash note")?;
+
+        let check = check_wallet_dir(&wallet_dir);
+        let unreadable: Vec<_> = check
+            .unreadable_files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect();
+        assert!(unreadable.contains(&wallet_dir.join(MAIN_PUBKEY_FILENAME)));
+        assert!(unreadable.contains(&corrupt_note));
+        assert_eq!(check.cash_notes, 0);
+        // nothing was repaired
+        assert_eq!(fs::read_to_string(&corrupt_note)?, "not a cash note");
+        Ok(())
+    }
+
+    #[test]
+    fn a_held_lock_is_reported() -> Result<()> {
+        let dir = TempDir::new()?;
+        let wallet = LocalWallet::load_from_main_key(dir.path(), MainSecretKey::random())?;
+        let wallet_dir = wallet.wallet_dir().to_path_buf();
+
+        let exclusive_access = wallet.lock()?;
+        let check = check_wallet_dir(&wallet_dir);
+        assert!(
+            matches!(check.lock, WalletLockState::Held { .. }),
+            "{check:?}"
+        );
+
+        drop(exclusive_access);
+        let check = check_wallet_dir(&wallet_dir);
+        assert_eq!(check.lock, WalletLockState::Free);
+        Ok(())
+    }
+}
@@ -14,9 +14,15 @@ use hex::{decode, encode};
 use std::path::Path;
 
 /// Filename for storing the node's reward (BLS hex-encoded) main secret key.
-const MAIN_SECRET_KEY_FILENAME: &str = "main_secret_key";
+pub(super) const MAIN_SECRET_KEY_FILENAME: &str = "main_secret_key";
 /// Filename for storing the node's reward (BLS hex-encoded) public key.
-const MAIN_PUBKEY_FILENAME: &str = "main_pubkey";
+pub(super) const MAIN_PUBKEY_FILENAME: &str = "main_pubkey";
+/// Filename for storing the (BLS hex-encoded) main secret keys retired by key rotations,
+/// one per line, oldest first.
+pub(super) const RETIRED_SECRET_KEYS_FILENAME: &str = "retired_secret_keys";
+/// Filename for storing the (BLS hex-encoded) public keys retired by key rotations,
+/// one per line, oldest first.
+pub(super) const RETIRED_PUBKEYS_FILENAME: &str = "retired_pubkeys";
 
 /// Writes the public address and main key (hex-encoded) to different locations at disk.
 pub(crate) fn store_new_keypair(wallet_dir: &Path, main_key: &MainSecretKey) -> Result<()> {
@@ -62,6 +68,56 @@ pub(super) fn get_main_pubkey(wallet_dir: &Path) -> Result<Option<MainPubkey>> {
     Ok(Some(main_pk))
 }
 
+/// Writes the main keys retired by key rotations, and their public addresses (hex-encoded),
+/// to different locations at disk.
+pub(super) fn store_retired_keys(wallet_dir: &Path, retired_keys: &[MainSecretKey]) -> Result<()> {
+    let secret_keys: Vec<_> = retired_keys
+        .iter()
+        .map(|key| encode(key.to_bytes()))
+        .collect();
+    let pubkeys: Vec<_> = retired_keys
+        .iter()
+        .map(|key| encode(key.main_pubkey().to_bytes()))
+        .collect();
+    std::fs::write(
+        wallet_dir.join(RETIRED_SECRET_KEYS_FILENAME),
+        secret_keys.join("\n"),
+    )?;
+    std::fs::write(
+        wallet_dir.join(RETIRED_PUBKEYS_FILENAME),
+        pubkeys.join("\n"),
+    )?;
+    Ok(())
+}
+
+/// Returns the main keys retired by key rotations, oldest first, none if the file doesn't exist.
+pub(super) fn get_retired_keys(wallet_dir: &Path) -> Result<Vec<MainSecretKey>> {
+    let path = wallet_dir.join(RETIRED_SECRET_KEYS_FILENAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    std::fs::read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(MainSecretKey::new(bls_secret_from_hex(line.trim())?)))
+        .collect()
+}
+
+/// Returns the public keys retired by key rotations, oldest first, none if the file doesn't exist.
+pub(super) fn get_retired_pubkeys(wallet_dir: &Path) -> Result<Vec<MainPubkey>> {
+    let path = wallet_dir.join(RETIRED_PUBKEYS_FILENAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    std::fs::read_to_string(&path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(MainPubkey::from_hex(line.trim())?))
+        .collect()
+}
+
 /// Construct a BLS secret key from a hex-encoded string.
 pub fn bls_secret_from_hex<T: AsRef<[u8]>>(hex: T) -> Result<bls::SecretKey> {
     let bytes = decode(hex).map_err(|_| Error::FailedToDecodeHexToKey)?;
@@ -75,7 +131,10 @@ pub fn bls_secret_from_hex<T: AsRef<[u8]>>(hex: T) -> Result<bls::SecretKey> {
 
 #[cfg(test)]
 mod test {
-    use super::{get_main_key, store_new_keypair, MainSecretKey};
+    use super::{
+        get_main_key, get_retired_keys, get_retired_pubkeys, store_new_keypair, store_retired_keys,
+        MainSecretKey,
+    };
     use assert_fs::TempDir;
     use eyre::Result;
 
@@ -90,6 +149,24 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn retired_keys_to_and_from_file() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        assert!(get_retired_keys(&root_dir)?.is_empty());
+
+        let retired_keys = vec![MainSecretKey::random(), MainSecretKey::random()];
+        store_retired_keys(&root_dir, &retired_keys)?;
+        let secret_result: Vec<_> = get_retired_keys(&root_dir)?
+            .iter()
+            .map(|key| key.main_pubkey())
+            .collect();
+        let expected: Vec<_> = retired_keys.iter().map(|key| key.main_pubkey()).collect();
+        assert_eq!(secret_result, expected);
+        assert_eq!(get_retired_pubkeys(&root_dir)?, expected);
+        Ok(())
+    }
+
     fn create_temp_dir() -> TempDir {
         TempDir::new().expect("Should be able to create a temp dir.")
     }
@@ -7,40 +7,53 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 use super::{
     data_payments::{PaymentDetails, PaymentQuote},
-    keys::{get_main_key, store_new_keypair},
+    history::{write_history, HistoryEntry, HistoryFormat, HistoryKind},
+    integrity::{check_wallet_dir, WalletDirCheck},
+    keys::{
+        get_main_key, get_retired_keys, store_new_keypair, store_retired_keys,
+        MAIN_SECRET_KEY_FILENAME,
+    },
+    lock::{lock_shared, WalletExclusiveAccess, WalletSharedAccess, WALLET_LOCK_TIMEOUT},
     wallet_file::{
-        get_unconfirmed_spend_requests, load_cash_notes_from_disk, load_created_cash_note,
-        remove_cash_notes, store_created_cash_notes, store_unconfirmed_spend_requests,
+        append_to_history, append_to_issued_storage_vouchers, get_auto_split_policy, get_history,
+        get_issued_storage_vouchers, get_storage_voucher, get_unconfirmed_spend_requests,
+        load_cash_notes_from_disk, load_created_cash_note, remove_cash_notes,
+        store_auto_split_policy, store_created_cash_notes, store_storage_voucher,
+        store_unconfirmed_spend_requests,
     },
     watch_only::WatchOnlyWallet,
-    Error, Result,
+    AutoSplitPolicy, Error, Result, StorageVoucher, StorageVoucherGrant,
 };
 
 use crate::{
     calculate_royalties_fee,
-    transfers::{create_offline_transfer, OfflineTransfer},
+    transfers::{
+        create_offline_transfer, create_offline_transfer_with_auto_split, create_offline_transfers,
+        OfflineTransfer,
+    },
     CashNote, CashNoteRedemption, DerivationIndex, DerivedSecretKey, Hash, MainPubkey,
-    MainSecretKey, NanoTokens, SignedSpend, Transfer, UniquePubkey, WalletError,
-    NETWORK_ROYALTIES_PK,
+    MainSecretKey, NanoTokens, SignedSpend, SpendAddress, Transfer, UniquePubkey, WalletError,
+    NETWORK_ROYALTIES_PK, ROYALTY_RATE,
 };
 use xor_name::XorName;
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashSet},
-    fs::File,
+    io::Write,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 const WALLET_DIR_NAME: &str = "wallet";
 
-/// A locked file handle, that when dropped releases the lock.
-pub type WalletExclusiveAccess = File;
-
 /// A wallet that can only receive tokens.
 pub struct LocalWallet {
     /// The secret key with which we can access
     /// all the tokens in the available_cash_notes.
     key: MainSecretKey,
+    /// The keys retired by key rotations, oldest first, with which we can still access
+    /// the tokens received by them.
+    retired_keys: Vec<MainSecretKey>,
     /// The wallet containing all data.
     watchonly_wallet: WatchOnlyWallet,
     /// These have not yet been successfully sent to the network
@@ -73,6 +86,12 @@ impl LocalWallet {
         self.watchonly_wallet.lock()
     }
 
+    /// Locks the wallet for reading and returns shared access to the wallet
+    /// Any number of processes can hold it at once, while `lock` waits for them all to release it
+    pub fn lock_shared(&self) -> Result<WalletSharedAccess> {
+        self.watchonly_wallet.lock_shared()
+    }
+
     /// Stores the given cash_notes to the `created cash_notes dir` in the wallet dir.
     /// These can then be sent to the recipients out of band, over any channel preferred.
     pub fn store_cash_notes_to_disk<'a, T>(&self, cash_notes: T) -> Result<()>
@@ -134,10 +153,12 @@ impl LocalWallet {
             Some(unconfirmed_spend_requests) => unconfirmed_spend_requests,
             None => Default::default(),
         };
+        let retired_keys = get_retired_keys(&wallet_dir)?;
         let watchonly_wallet = WatchOnlyWallet::load_from(&wallet_dir, key.main_pubkey())?;
 
         Ok(Self {
             key,
+            retired_keys,
             watchonly_wallet,
             unconfirmed_spend_requests,
         })
@@ -149,12 +170,38 @@ impl LocalWallet {
         Self::load_from_path(&wallet_dir, None)
     }
 
+    /// Whether a wallet, i.e. its main key, is stored in the root dir.
+    pub fn exists_at(root_dir: &Path) -> bool {
+        root_dir
+            .join(WALLET_DIR_NAME)
+            .join(MAIN_SECRET_KEY_FILENAME)
+            .is_file()
+    }
+
     /// Tries to loads a serialized wallet from a path, bailing out if it doesn't exist.
     pub fn try_load_from(root_dir: &Path) -> Result<Self> {
         let wallet_dir = root_dir.join(WALLET_DIR_NAME);
         Self::load_from_path_and_key(&wallet_dir, None)
     }
 
+    /// Loads a serialized wallet from a path for reading, e.g. its balance or history.
+    /// The wallet is read under its shared lock, so that it's never read halfway through a
+    /// write, while other readers go on concurrently.
+    pub fn load_shared_from(root_dir: &Path) -> Result<Self> {
+        let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+        std::fs::create_dir_all(&wallet_dir)?;
+        let _shared_access = lock_shared(&wallet_dir, WALLET_LOCK_TIMEOUT)?;
+        Self::load_from_path_and_key(&wallet_dir, None)
+    }
+
+    /// Tries to load a serialized wallet from a path for reading, bailing out if it doesn't
+    /// exist. See `load_shared_from`.
+    pub fn try_load_shared_from(root_dir: &Path) -> Result<Self> {
+        let wallet_dir = root_dir.join(WALLET_DIR_NAME);
+        let _shared_access = lock_shared(&wallet_dir, WALLET_LOCK_TIMEOUT)?;
+        Self::load_from_path_and_key(&wallet_dir, None)
+    }
+
     /// Loads a serialized wallet from a given path, no additional element will
     /// be added to the provided path and strictly taken as the wallet files location.
     pub fn load_from_path(wallet_dir: &Path, main_key: Option<MainSecretKey>) -> Result<Self> {
@@ -162,14 +209,212 @@ impl LocalWallet {
         Self::load_from_path_and_key(wallet_dir, main_key)
     }
 
+    /// Checks the wallet stored in the root dir for unreadable files and a held lock, without
+    /// creating, repairing or otherwise modifying anything.
+    pub fn check_dir(root_dir: &Path) -> WalletDirCheck {
+        check_wallet_dir(&root_dir.join(WALLET_DIR_NAME))
+    }
+
+    /// The directory the wallet is stored in.
+    pub fn wallet_dir(&self) -> &Path {
+        self.watchonly_wallet.wallet_dir()
+    }
+
     pub fn address(&self) -> MainPubkey {
         self.key.main_pubkey()
     }
 
+    /// The addresses retired by key rotations, oldest first, which still receive tokens.
+    pub fn retired_addresses(&self) -> Vec<MainPubkey> {
+        self.retired_keys
+            .iter()
+            .map(|key| key.main_pubkey())
+            .collect()
+    }
+
+    /// The address an available cash note was received by, the current one or a retired one.
+    pub fn cash_note_key(&self, unique_pubkey: &UniquePubkey) -> Option<MainPubkey> {
+        self.watchonly_wallet.cash_note_key(unique_pubkey)
+    }
+
+    /// Replace the key of the wallet with a new random one, returning the new address.
+    ///
+    /// The current key is retired rather than dropped: the tokens held, and the ones sent to
+    /// its address later on, can still be deposited and spent. Only the new address is handed
+    /// out and receives the change of our payments from now on.
+    pub fn rotate_key(&mut self) -> Result<MainPubkey> {
+        let exclusive_access = self.lock()?;
+        self.reload()?;
+
+        let new_key = MainSecretKey::random();
+        let new_address = new_key.main_pubkey();
+        let retired_key = std::mem::replace(&mut self.key, new_key);
+        info!(
+            "Rotating the wallet key from {:?} to {new_address:?}",
+            retired_key.main_pubkey()
+        );
+
+        // the retired key is stored first, so that it's never lost midway
+        self.retired_keys.push(retired_key);
+        let wallet_dir = self.watchonly_wallet.wallet_dir().to_path_buf();
+        store_retired_keys(&wallet_dir, &self.retired_keys)?;
+        store_new_keypair(&wallet_dir, &self.key)?;
+
+        self.watchonly_wallet.rotate_main_pubkey(new_address);
+        self.store(exclusive_access)?;
+        Ok(new_address)
+    }
+
     pub fn unconfirmed_spend_requests(&self) -> &BTreeSet<SignedSpend> {
         &self.unconfirmed_spend_requests
     }
 
+    /// The policy splitting the change of our payments, if one is set.
+    pub fn auto_split_policy(&self) -> Result<Option<AutoSplitPolicy>> {
+        get_auto_split_policy(self.watchonly_wallet.wallet_dir())
+    }
+
+    /// Set, or remove with `None`, the policy splitting the change of our payments.
+    /// The policy is persisted in the wallet dir.
+    pub fn set_auto_split_policy(&self, policy: Option<&AutoSplitPolicy>) -> Result<()> {
+        store_auto_split_policy(self.watchonly_wallet.wallet_dir(), policy)
+    }
+
+    /// The voucher the storage payments of this wallet are made with, if it was created from a
+    /// voucher grant.
+    pub fn storage_voucher(&self) -> Result<Option<StorageVoucher>> {
+        get_storage_voucher(self.watchonly_wallet.wallet_dir())
+    }
+
+    /// Make the storage payments of this wallet with the given voucher, whose key is this
+    /// wallet's. The voucher is persisted in the wallet dir.
+    pub fn set_storage_voucher(&self, voucher: &StorageVoucher) -> Result<()> {
+        if voucher.voucher_key != self.address() {
+            return Err(Error::InvalidStorageVoucherGrant(
+                "the voucher key is not the wallet's".to_string(),
+            ));
+        }
+        store_storage_voucher(self.watchonly_wallet.wallet_dir(), voucher)
+    }
+
+    /// The storage vouchers issued by this wallet, oldest first.
+    pub fn issued_storage_vouchers(&self) -> Result<Vec<StorageVoucher>> {
+        get_issued_storage_vouchers(self.watchonly_wallet.wallet_dir())
+    }
+
+    /// Reserve `max_amount` of our tokens for someone else to pay for storage with, sending them
+    /// to a fresh voucher key, and sign a voucher over them.
+    ///
+    /// The returned grant, holding the voucher, its key and the reserve, is to be handed over to
+    /// the uploader once the spends of the reserve are sent to the network. The voucher is
+    /// recorded as issued by this wallet, so that its usage can be accounted for.
+    pub fn issue_storage_voucher(
+        &mut self,
+        max_amount: NanoTokens,
+        expiry: SystemTime,
+        address_prefix: Option<Vec<u8>>,
+    ) -> Result<StorageVoucherGrant> {
+        // no tokens are to be reserved for a voucher which can't be signed
+        if expiry < SystemTime::UNIX_EPOCH {
+            return Err(Error::InvalidStorageVoucherExpiry(expiry));
+        }
+        let voucher_key = MainSecretKey::random();
+        let created_cash_notes =
+            self.local_send(vec![(max_amount, voucher_key.main_pubkey())], None)?;
+        let reserve = match &created_cash_notes[..] {
+            [reserve] => reserve.clone(),
+            _ => {
+                return Err(Error::CouldNotSendMoney(
+                    "Exactly one CashNote was expected to be reserved for the voucher".to_string(),
+                ))
+            }
+        };
+
+        let voucher = StorageVoucher::new(
+            &self.key,
+            voucher_key.main_pubkey(),
+            reserve.unique_pubkey(),
+            max_amount,
+            expiry,
+            address_prefix,
+        )?;
+        append_to_issued_storage_vouchers(self.watchonly_wallet.wallet_dir(), &voucher)?;
+        info!(
+            "Issued storage voucher {:?} for {max_amount}",
+            voucher.hash()
+        );
+
+        Ok(StorageVoucherGrant::new(voucher, &voucher_key, reserve))
+    }
+
+    /// The tokens received and sent by this wallet, oldest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>> {
+        get_history(self.watchonly_wallet.wallet_dir())
+    }
+
+    /// Write the history of this wallet to the given writer, in the given format.
+    pub fn export_history<W: Write>(&self, format: HistoryFormat, writer: W) -> Result<()> {
+        write_history(&self.history()?, format, writer)
+    }
+
+    /// Append the given entries to the history stored in the wallet dir.
+    ///
+    /// This is done once the wallet has been written, so failing to record the history is
+    /// only logged: the wallet itself is already up to date.
+    fn record_history(&self, entries: Vec<HistoryEntry>) {
+        if let Err(err) = append_to_history(self.watchonly_wallet.wallet_dir(), &entries) {
+            warn!(
+                "Failed to record {} entries in the wallet history: {err}",
+                entries.len()
+            );
+        }
+    }
+
+    /// Create a transfer from the available cash_notes, with the change split
+    /// following the auto split policy, if one is set.
+    fn create_transfer(
+        &self,
+        available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+        reason_hash: Hash,
+    ) -> Result<OfflineTransfer> {
+        let transfer = match self.auto_split_policy()? {
+            Some(policy) => create_offline_transfer_with_auto_split(
+                available_cash_notes,
+                recipients,
+                self.address(),
+                reason_hash,
+                &policy,
+            )?,
+            None => create_offline_transfer(
+                available_cash_notes,
+                recipients,
+                self.address(),
+                reason_hash,
+            )?,
+        };
+        Ok(transfer)
+    }
+
+    /// Create the transfers to the recipients from the available cash_notes, split across as many
+    /// transactions as needed for their spends to fit in a record, with the change split
+    /// following the auto split policy, if one is set.
+    fn create_transfers(
+        &self,
+        available_cash_notes: Vec<(CashNote, DerivedSecretKey)>,
+        recipients: Vec<(NanoTokens, MainPubkey, DerivationIndex)>,
+        reason_hash: Hash,
+    ) -> Result<Vec<OfflineTransfer>> {
+        let transfers = create_offline_transfers(
+            available_cash_notes,
+            recipients,
+            &self.key,
+            reason_hash,
+            self.auto_split_policy()?.as_ref(),
+        )?;
+        Ok(transfers)
+    }
+
     /// Moves all files for the current wallet, including keys and cashnotes
     /// to directory root_dir/wallet_<short_address>
     pub fn clear(root_dir: &Path) -> Result<PathBuf> {
@@ -230,7 +475,10 @@ impl LocalWallet {
         for (id, _token) in self.watchonly_wallet.available_cash_notes().iter() {
             let held_cash_note = load_created_cash_note(id, &wallet_dir);
             if let Some(cash_note) = held_cash_note {
-                if let Ok(derived_key) = cash_note.derived_key(&self.key) {
+                if let Some(derived_key) = self
+                    .main_keys()
+                    .find_map(|key| cash_note.derived_key(key).ok())
+                {
                     available_cash_notes.push((cash_note.clone(), derived_key));
                 } else {
                     warn!(
@@ -251,6 +499,23 @@ impl LocalWallet {
         self.watchonly_wallet.get_payment_transaction(name)
     }
 
+    /// All the cached payments, by the content they were made for.
+    pub fn cached_payments(&self) -> impl Iterator<Item = (&XorName, &PaymentDetails)> {
+        self.watchonly_wallet.payment_transactions()
+    }
+
+    /// Cache a payment made for the given xorname, e.g. one made by another wallet.
+    pub fn insert_payment_transaction(&mut self, name: XorName, payment: PaymentDetails) {
+        self.watchonly_wallet
+            .insert_payment_transaction(name, payment);
+    }
+
+    /// Forget the payment cached for the given xorname, returning it if there was one.
+    /// The content will have to be paid for again, unless the network already holds it.
+    pub fn remove_payment_transaction(&mut self, name: &XorName) -> Option<PaymentDetails> {
+        self.watchonly_wallet.remove_payment_transaction(name)
+    }
+
     /// Make a transfer and return all created cash_notes
     pub fn local_send(
         &mut self,
@@ -272,24 +537,96 @@ impl LocalWallet {
 
         let reason_hash = reason_hash.unwrap_or_default();
 
+        let transfers =
+            self.create_transfers(available_cash_notes, to_unique_keys.clone(), reason_hash)?;
+
+        let created_cash_notes: Vec<_> = transfers
+            .iter()
+            .flat_map(|transfer| transfer.created_cash_notes.clone())
+            .collect();
+        let spend_addresses = spend_addresses_of(&transfers);
+
+        self.update_local_wallet(transfers, exclusive_access)?;
+
+        // one entry per recipient, the balance running down to the one we're left with
+        let mut balance = self.balance().as_nano()
+            + to_unique_keys
+                .iter()
+                .map(|(amount, _, _)| amount.as_nano())
+                .sum::<u64>();
+        let entries = to_unique_keys
+            .into_iter()
+            .map(|(amount, recipient, _)| {
+                balance -= amount.as_nano();
+                HistoryEntry::new(
+                    HistoryKind::Sent,
+                    amount,
+                    NanoTokens::from(balance),
+                    Some(recipient),
+                    reason_hash,
+                    spend_addresses.clone(),
+                )
+            })
+            .collect();
+        self.record_history(entries);
+
+        trace!("Releasing wallet lock"); // by dropping _exclusive_access
+        Ok(created_cash_notes)
+    }
+
+    /// Create the transfer `local_send` would make, without applying it to the wallet,
+    /// e.g. to preview how the change would be split.
+    pub fn preview_send(&mut self, to: Vec<(NanoTokens, MainPubkey)>) -> Result<OfflineTransfer> {
+        let mut rng = &mut rand::rngs::OsRng;
+        let to_unique_keys: Vec<_> = to
+            .into_iter()
+            .map(|(amount, address)| (amount, address, DerivationIndex::random(&mut rng)))
+            .collect();
+
+        let (available_cash_notes, _exclusive_access) = self.available_cash_notes()?;
+        self.create_transfer(available_cash_notes, to_unique_keys, Hash::default())
+    }
+
+    /// Split `count` cash notes of `amount` each off the balance, paid to ourselves.
+    ///
+    /// Our largest cash notes are spent first, so that notes split off by earlier calls are
+    /// left untouched. The created cash notes are deposited, and returned.
+    pub fn local_split(&mut self, amount: NanoTokens, count: usize) -> Result<Vec<CashNote>> {
+        let mut rng = &mut rand::rngs::OsRng;
+        let address = self.address();
+        let to_unique_keys: Vec<_> = (0..count)
+            .map(|_| (amount, address, DerivationIndex::random(&mut rng)))
+            .collect();
+
+        let (mut available_cash_notes, exclusive_access) = self.available_cash_notes()?;
+        available_cash_notes.sort_by_key(|(cash_note, _)| {
+            std::cmp::Reverse(cash_note.value().map(|value| value.as_nano()).unwrap_or(0))
+        });
+
         let transfer = create_offline_transfer(
             available_cash_notes,
             to_unique_keys,
-            self.address(),
-            reason_hash,
+            address,
+            Hash::default(),
         )?;
 
         let created_cash_notes = transfer.created_cash_notes.clone();
 
-        self.update_local_wallet(transfer, exclusive_access)?;
+        self.update_local_wallet(vec![transfer], exclusive_access)?;
+        // paid to ourselves, so not recorded in the history
+        self.watchonly_wallet
+            .deposit_and_store_to_disk(&created_cash_notes)?;
 
-        trace!("Releasing wallet lock"); // by dropping _exclusive_access
+        trace!("Split {count} cash notes of {amount} off the wallet");
         Ok(created_cash_notes)
     }
 
     /// Performs a payment for each content address.
     /// Includes payment of network royalties.
     /// Returns the amount paid for storage, including the network royalties fee paid.
+    ///
+    /// If the wallet has a storage voucher, the payments are made with it: it must allow each
+    /// content address, and the spends have its hash as their reason.
     pub fn local_send_storage_payment(
         &mut self,
         price_map: &BTreeMap<XorName, (MainPubkey, PaymentQuote)>,
@@ -298,9 +635,24 @@ impl LocalWallet {
         let mut storage_cost = NanoTokens::zero();
         let mut royalties_fees = NanoTokens::zero();
 
+        let voucher = self.storage_voucher()?;
+        if let Some(voucher) = &voucher {
+            let now = SystemTime::now();
+            for xorname in price_map.keys() {
+                voucher.check_payment_for(xorname, now)?;
+            }
+        }
+
         // create random derivation indexes for recipients
         let mut recipients_by_xor = BTreeMap::new();
         for (xorname, (main_pubkey, quote)) in price_map.iter() {
+            // a payment at another rate would be rejected by the node
+            if quote.royalty_rate != ROYALTY_RATE {
+                return Err(WalletError::RoyaltyRateMismatch {
+                    ours: ROYALTY_RATE,
+                    theirs: quote.royalty_rate,
+                });
+            }
             let storage_payee = (quote.cost, *main_pubkey, DerivationIndex::random(&mut rng));
             let royalties_fee = calculate_royalties_fee(quote.cost);
             let royalties_payee = (
@@ -327,19 +679,33 @@ impl LocalWallet {
             .collect();
         let (available_cash_notes, exclusive_access) = self.available_cash_notes()?;
         debug!("Available CashNotes: {:#?}", available_cash_notes);
-        let reason_hash = Default::default();
-        let offline_transfer = create_offline_transfer(
-            available_cash_notes,
-            recipients,
-            self.address(),
-            reason_hash,
-        )?;
+        let reason_hash = voucher
+            .as_ref()
+            .map(|voucher| voucher.hash())
+            .unwrap_or_default();
+        // the nodes check the inputs of payments made with a voucher were derived from its key
+        let mut derivation_indexes: BTreeMap<_, _> = available_cash_notes
+            .iter()
+            .map(|(cash_note, _)| (cash_note.unique_pubkey(), cash_note.derivation_index))
+            .collect();
+        let offline_transfers =
+            self.create_transfers(available_cash_notes, recipients, reason_hash)?;
+        derivation_indexes.extend(
+            offline_transfers
+                .iter()
+                .flat_map(|transfer| {
+                    transfer
+                        .change_cash_note
+                        .iter()
+                        .chain(&transfer.split_change_cash_notes)
+                })
+                .map(|change| (change.unique_pubkey(), change.derivation_index)),
+        );
 
         // cache transfer payments in the wallet
-        let mut cashnotes_to_use: HashSet<CashNote> = offline_transfer
-            .created_cash_notes
+        let mut cashnotes_to_use: HashSet<CashNote> = offline_transfers
             .iter()
-            .cloned()
+            .flat_map(|transfer| transfer.created_cash_notes.iter().cloned())
             .collect();
         for (xorname, recipients_info) in recipients_by_xor {
             let (storage_payee, royalties_payee) = recipients_info;
@@ -376,6 +742,22 @@ impl LocalWallet {
             let royalties_amount = cash_note_for_royalties.value()?;
             trace!("Created network royalties cnr regarding {xorname:?} paying {royalties_amount:?} to {royalties_key:?}.");
 
+            let voucher_inputs = match &voucher {
+                Some(voucher) => cash_note_for_node
+                    .src_tx
+                    .inputs
+                    .iter()
+                    .map(|input| *input.unique_pubkey())
+                    .filter(|unique_pubkey| *unique_pubkey != voucher.reserve)
+                    .filter_map(|unique_pubkey| {
+                        derivation_indexes
+                            .get(&unique_pubkey)
+                            .map(|index| (unique_pubkey, *index))
+                    })
+                    .collect(),
+                None => BTreeMap::new(),
+            };
+
             let quote = price_map
                 .get(xorname)
                 .ok_or(Error::CouldNotSendMoney(format!(
@@ -388,6 +770,9 @@ impl LocalWallet {
                 transfer: (transfer_for_node, transfer_amount),
                 royalties: (royalties, royalties_amount),
                 quote,
+                royalty_rate: ROYALTY_RATE,
+                voucher: voucher.clone(),
+                voucher_inputs,
             };
 
             self.watchonly_wallet
@@ -395,36 +780,59 @@ impl LocalWallet {
         }
 
         // write all changes to local wallet
-        self.update_local_wallet(offline_transfer, exclusive_access)?;
+        let spend_addresses = spend_addresses_of(&offline_transfers);
+        self.update_local_wallet(offline_transfers, exclusive_access)?;
+
+        let total_cost = storage_cost
+            .checked_add(royalties_fees)
+            .ok_or(WalletError::TotalPriceTooHigh)?;
+        self.record_history(vec![HistoryEntry::new(
+            HistoryKind::StoragePayment,
+            total_cost,
+            self.balance(),
+            None,
+            reason_hash,
+            spend_addresses,
+        )]);
+
         Ok((storage_cost, royalties_fees))
     }
 
+    /// Apply the transfers to the wallet, in order, as each may spend the change of the ones
+    /// before it.
     fn update_local_wallet(
         &mut self,
-        transfer: OfflineTransfer,
+        transfers: Vec<OfflineTransfer>,
         exclusive_access: WalletExclusiveAccess,
     ) -> Result<()> {
-        // First of all, update client local state.
-        let spent_unique_pubkeys: BTreeSet<_> = transfer
-            .tx
-            .inputs
-            .iter()
-            .map(|input| input.unique_pubkey())
-            .collect();
-
-        self.watchonly_wallet
-            .mark_notes_as_spent(spent_unique_pubkeys.clone());
+        for transfer in transfers {
+            // First of all, update client local state.
+            let spent_unique_pubkeys: BTreeSet<_> = transfer
+                .tx
+                .inputs
+                .iter()
+                .map(|input| input.unique_pubkey())
+                .collect();
 
-        if let Some(cash_note) = transfer.change_cash_note {
-            self.watchonly_wallet.deposit(&[cash_note.clone()])?;
-            self.store_cash_notes_to_disk(&[cash_note])?;
-        }
+            self.watchonly_wallet
+                .mark_notes_as_spent(spent_unique_pubkeys.clone());
+
+            let change_cash_notes: Vec<_> = transfer
+                .change_cash_note
+                .into_iter()
+                .chain(transfer.split_change_cash_notes)
+                .collect();
+            if !change_cash_notes.is_empty() {
+                self.watchonly_wallet.deposit(&change_cash_notes)?;
+                self.store_cash_notes_to_disk(&change_cash_notes)?;
+            }
 
-        // Store created CashNotes in a batch, improving IO performance
-        self.store_cash_notes_to_disk(&transfer.created_cash_notes)?;
+            // Store created CashNotes in a batch, improving IO performance
+            self.store_cash_notes_to_disk(&transfer.created_cash_notes)?;
 
-        for request in transfer.all_spend_requests {
-            self.unconfirmed_spend_requests.insert(request);
+            for request in transfer.all_spend_requests {
+                self.unconfirmed_spend_requests.insert(request);
+            }
         }
 
         // store wallet to disk
@@ -440,21 +848,74 @@ impl LocalWallet {
     /// Store the given cash_notes to the `cash_notes` dir in the wallet dir.
     /// Update and store the updated wallet to disk
     /// This function locks the wallet to prevent concurrent processes from writing to it
+    /// The deposit is recorded in the wallet history.
     pub fn deposit_and_store_to_disk(&mut self, received_cash_notes: &Vec<CashNote>) -> Result<()> {
+        // only the cash notes we didn't hold yet make for a history entry
+        let addresses: Vec<_> = self.main_keys().map(|key| key.main_pubkey()).collect();
+        let new_cash_notes: Vec<_> = received_cash_notes
+            .iter()
+            .filter(|cash_note| {
+                addresses
+                    .iter()
+                    .any(|address| cash_note.derived_pubkey(address).is_ok())
+                    && !self
+                        .watchonly_wallet
+                        .available_cash_notes()
+                        .contains_key(&cash_note.unique_pubkey())
+            })
+            .collect();
+
         self.watchonly_wallet
-            .deposit_and_store_to_disk(received_cash_notes)
+            .deposit_and_store_to_disk(received_cash_notes)?;
+
+        if let Some(first) = new_cash_notes.first() {
+            let mut amount = 0;
+            for cash_note in new_cash_notes.iter() {
+                amount += cash_note.value()?.as_nano();
+            }
+            let spend_addresses = new_cash_notes
+                .iter()
+                .map(|cash_note| SpendAddress::from_unique_pubkey(&cash_note.unique_pubkey()))
+                .collect();
+            self.record_history(vec![HistoryEntry::new(
+                HistoryKind::Received,
+                NanoTokens::from(amount),
+                self.balance(),
+                None,
+                first.reason(),
+                spend_addresses,
+            )]);
+        }
+        Ok(())
     }
 
-    pub fn unwrap_transfer(&self, transfer: &Transfer) -> Result<Vec<CashNoteRedemption>> {
-        transfer
-            .cashnote_redemptions(&self.key)
-            .map_err(|_| Error::FailedToDecypherTransfer)
+    /// Decrypt a transfer sent to our address, or to one of our retired addresses.
+    ///
+    /// Returns the address the transfer was sent to along with its redemptions, as they are to
+    /// be verified against it.
+    pub fn unwrap_transfer(
+        &self,
+        transfer: &Transfer,
+    ) -> Result<(MainPubkey, Vec<CashNoteRedemption>)> {
+        self.main_keys()
+            .find_map(|key| {
+                transfer
+                    .cashnote_redemptions(key)
+                    .ok()
+                    .map(|redemptions| (key.main_pubkey(), redemptions))
+            })
+            .ok_or(Error::FailedToDecypherTransfer)
     }
 
     pub fn derive_key(&self, derivation_index: &DerivationIndex) -> DerivedSecretKey {
         self.key.derive_key(derivation_index)
     }
 
+    /// The current key, then the retired ones.
+    fn main_keys(&self) -> impl Iterator<Item = &MainSecretKey> {
+        std::iter::once(&self.key).chain(self.retired_keys.iter())
+    }
+
     /// Loads a serialized wallet from a path.
     fn load_from_path_and_key(wallet_dir: &Path, main_key: Option<MainSecretKey>) -> Result<Self> {
         let key = match get_main_key(wallet_dir)? {
@@ -470,19 +931,34 @@ impl LocalWallet {
             Some(unconfirmed_spend_requests) => unconfirmed_spend_requests,
             None => Default::default(),
         };
+        let retired_keys = get_retired_keys(wallet_dir)?;
         let watchonly_wallet = WatchOnlyWallet::load_from(wallet_dir, key.main_pubkey())?;
 
         Ok(Self {
             key,
+            retired_keys,
             watchonly_wallet,
             unconfirmed_spend_requests,
         })
     }
 }
 
+/// The addresses of the spends made by the given transfer.
+fn spend_addresses_of(transfers: &[OfflineTransfer]) -> Vec<SpendAddress> {
+    transfers
+        .iter()
+        .flat_map(|transfer| transfer.tx.inputs.iter())
+        .map(|input| SpendAddress::from_unique_pubkey(input.unique_pubkey()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::BTreeMap;
+    use std::{
+        collections::BTreeMap,
+        io::Write,
+        time::{Duration, SystemTime},
+    };
 
     use super::LocalWallet;
     use crate::{
@@ -490,11 +966,11 @@ mod tests {
         wallet::{
             data_payments::PaymentQuote,
             local_store::WALLET_DIR_NAME,
-            wallet_file::{get_wallet, store_wallet},
+            wallet_file::{get_wallet, store_wallet, HISTORY_FILE_NAME},
             watch_only::WatchOnlyWallet,
-            KeyLessWallet,
+            AutoSplitPolicy, HistoryFormat, KeyLessWallet,
         },
-        MainSecretKey, NanoTokens, SpendAddress,
+        MainSecretKey, NanoTokens, RoyaltyRate, SpendAddress, WalletError, ROYALTY_RATE,
     };
     use assert_fs::TempDir;
     use eyre::Result;
@@ -531,6 +1007,7 @@ mod tests {
 
         let deposit_only = LocalWallet {
             key,
+            retired_keys: vec![],
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
         };
@@ -558,6 +1035,7 @@ mod tests {
 
         let mut deposit_only = LocalWallet {
             key,
+            retired_keys: vec![],
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
         };
@@ -583,6 +1061,7 @@ mod tests {
 
         let mut deposit_only = LocalWallet {
             key,
+            retired_keys: vec![],
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
         };
@@ -604,6 +1083,7 @@ mod tests {
 
         let mut local_wallet = LocalWallet {
             key,
+            retired_keys: vec![],
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
         };
@@ -627,6 +1107,7 @@ mod tests {
 
         let mut deposit_only = LocalWallet {
             key,
+            retired_keys: vec![],
             watchonly_wallet: WatchOnlyWallet::new(main_pubkey, &dir, KeyLessWallet::default()),
             unconfirmed_spend_requests: Default::default(),
         };
@@ -719,6 +1200,148 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn rotated_key_still_receives_and_spends() -> Result<()> {
+        let sender_dir = create_temp_dir();
+        let mut sender = LocalWallet::load_from(sender_dir.path())?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut wallet = LocalWallet::load_from(&root_dir)?;
+        let old_address = wallet.address();
+        let new_address = wallet.rotate_key()?;
+        assert_ne!(old_address, new_address);
+        assert_eq!(new_address, wallet.address());
+        assert_eq!(vec![old_address], wallet.retired_addresses());
+
+        // deposits to both addresses, made after the rotation
+        let to = vec![
+            (NanoTokens::from(1_000), old_address),
+            (NanoTokens::from(2_000), new_address),
+        ];
+        let created_cash_notes = sender.local_send(to, None)?;
+        wallet.deposit_and_store_to_disk(&created_cash_notes)?;
+        assert_eq!(3_000, wallet.balance().as_nano());
+
+        let mut wallet = LocalWallet::load_from(&root_dir)?;
+        assert_eq!(new_address, wallet.address());
+        assert_eq!(vec![old_address], wallet.retired_addresses());
+        for cash_note in &created_cash_notes {
+            assert_eq!(
+                Some(*cash_note.main_pubkey()),
+                wallet.cash_note_key(&cash_note.unique_pubkey())
+            );
+        }
+
+        // spending both, the change going to the new address
+        let to = vec![(
+            NanoTokens::from(2_500),
+            MainSecretKey::random().main_pubkey(),
+        )];
+        let _created_cash_notes = wallet.local_send(to, None)?;
+        assert_eq!(500, wallet.balance().as_nano());
+        let change = wallet
+            .watchonly_wallet
+            .available_cash_notes()
+            .keys()
+            .next()
+            .expect("There to be the change.");
+        assert_eq!(Some(new_address), wallet.cash_note_key(change));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn splitting_conserves_balance_and_keeps_split_notes() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+
+        let mut wallet = LocalWallet::load_from(&root_dir)?;
+        let cash_note =
+            create_first_cash_note_from_key(&wallet.key).expect("Genesis creation to succeed.");
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+
+        let amount = NanoTokens::from(1_000);
+        let first_split = wallet.local_split(amount, 10)?;
+        let second_split = wallet.local_split(amount, 10)?;
+
+        assert_eq!(GENESIS_CASHNOTE_AMOUNT, wallet.balance().as_nano());
+        // the 20 split notes and the change
+        assert_eq!(21, wallet.watchonly_wallet.available_cash_notes().len());
+        for cash_note in first_split.iter().chain(second_split.iter()) {
+            assert_eq!(amount, cash_note.value()?);
+            assert!(wallet
+                .watchonly_wallet
+                .available_cash_notes()
+                .contains_key(&cash_note.unique_pubkey()));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_split_policy_splits_large_change() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+
+        let mut wallet = LocalWallet::load_from(&root_dir)?;
+        let cash_note =
+            create_first_cash_note_from_key(&wallet.key).expect("Genesis creation to succeed.");
+        wallet.deposit_and_store_to_disk(&vec![cash_note])?;
+
+        let threshold = NanoTokens::from(1_000_000);
+        let policy = AutoSplitPolicy {
+            threshold,
+            denominations: vec![threshold, NanoTokens::from(10_000)],
+            max_notes: 8,
+        };
+        wallet.set_auto_split_policy(Some(&policy))?;
+        assert_eq!(
+            LocalWallet::load_from(&root_dir)?.auto_split_policy()?,
+            Some(policy)
+        );
+
+        let send_amount = NanoTokens::from(100);
+        let to = vec![(send_amount, MainSecretKey::random().main_pubkey())];
+        let preview = wallet.preview_send(to.clone())?;
+        assert_eq!(7, preview.split_change_cash_notes.len());
+        // previewing leaves the wallet untouched
+        assert_eq!(1, wallet.watchonly_wallet.available_cash_notes().len());
+
+        let _created_cash_notes = wallet.local_send(to, None)?;
+
+        // the change is split into the 8 notes allowed: 7 of the largest denomination,
+        // then the remainder
+        let mut notes: Vec<_> = wallet
+            .watchonly_wallet
+            .available_cash_notes()
+            .values()
+            .map(|amount| amount.as_nano())
+            .collect();
+        notes.sort_unstable();
+        let remainder = GENESIS_CASHNOTE_AMOUNT - send_amount.as_nano() - 7 * threshold.as_nano();
+        let mut expected = vec![threshold.as_nano(); 7];
+        expected.insert(0, remainder);
+        expected.sort_unstable();
+        assert_eq!(notes, expected);
+        assert_eq!(
+            GENESIS_CASHNOTE_AMOUNT - send_amount.as_nano(),
+            wallet.balance().as_nano()
+        );
+
+        // without the policy, the change goes to a single note again
+        wallet.set_auto_split_policy(None)?;
+        assert_eq!(wallet.auto_split_policy()?, None);
+        let to = vec![(send_amount, MainSecretKey::random().main_pubkey())];
+        let preview = wallet.preview_send(to)?;
+        assert!(preview.split_change_cash_notes.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn send_wallet_to_and_from_file() -> Result<()> {
         let dir = create_temp_dir();
@@ -865,6 +1488,243 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn storage_payment_at_another_royalty_rate_is_refused() -> Result<()> {
+        let dir = create_temp_dir();
+        let mut sender = LocalWallet::load_from(dir.path())?;
+        let sender_cash_note =
+            create_first_cash_note_from_key(&sender.key).expect("Genesis creation to succeed.");
+        sender.deposit_and_store_to_disk(&vec![sender_cash_note])?;
+        let balance = sender.balance();
+
+        let xorname = XorName::random(&mut bls::rand::thread_rng());
+        let mut quote = PaymentQuote::test_dummy(xorname, 100.into());
+        let theirs = RoyaltyRate {
+            version: ROYALTY_RATE.version + 1,
+            basis_points: 2000,
+        };
+        quote.royalty_rate = theirs;
+        let map = BTreeMap::from([(xorname, (MainSecretKey::random().main_pubkey(), quote))]);
+
+        match sender.local_send_storage_payment(&map) {
+            Err(WalletError::RoyaltyRateMismatch { ours, theirs: got }) => {
+                assert_eq!(ours, ROYALTY_RATE);
+                assert_eq!(got, theirs);
+            }
+            other => panic!("Expected a royalty rate mismatch, got {other:?}"),
+        }
+        // nothing was spent
+        assert_eq!(sender.balance(), balance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_payments_with_a_voucher_are_made_on_its_behalf() -> Result<()> {
+        let funder_dir = create_temp_dir();
+        let mut funder = LocalWallet::load_from(funder_dir.path())?;
+        let funder_cash_note =
+            create_first_cash_note_from_key(&funder.key).expect("Genesis creation to succeed.");
+        funder.deposit_and_store_to_disk(&vec![funder_cash_note])?;
+
+        let allowed = XorName([0xab; 32]);
+        let other = XorName([0xcd; 32]);
+        let max_amount = NanoTokens::from(10_000);
+        let expiry = SystemTime::now() + Duration::from_secs(3600);
+        let grant = funder.issue_storage_voucher(max_amount, expiry, Some(vec![0xab]))?;
+        grant.verify()?;
+        assert_eq!(
+            funder.issued_storage_vouchers()?,
+            vec![grant.voucher.clone()]
+        );
+
+        // the uploader only ever holds the voucher key
+        let uploader_dir = create_temp_dir();
+        let mut uploader = LocalWallet::create_from_key(uploader_dir.path(), grant.voucher_key()?)?;
+        uploader.set_storage_voucher(&grant.voucher)?;
+        uploader.deposit_and_store_to_disk(&vec![grant.reserve.clone()])?;
+        assert_eq!(uploader.balance(), max_amount);
+
+        let payee = MainSecretKey::random().main_pubkey();
+        let map = BTreeMap::from([(other, (payee, PaymentQuote::test_dummy(other, 100.into())))]);
+        assert!(matches!(
+            uploader.local_send_storage_payment(&map),
+            Err(WalletError::StorageVoucherAddressNotAllowed(address)) if address == other
+        ));
+
+        let map = BTreeMap::from([(
+            allowed,
+            (payee, PaymentQuote::test_dummy(allowed, 100.into())),
+        )]);
+        let _ = uploader.local_send_storage_payment(&map)?;
+        let voucher_hash = grant.voucher.hash();
+        assert!(uploader
+            .unconfirmed_spend_requests()
+            .iter()
+            .all(|spend| spend.reason() == voucher_hash));
+        let payment = uploader
+            .get_cached_payment_for_xorname(&allowed)
+            .expect("There to be a payment.")
+            .to_payment();
+        assert_eq!(payment.voucher, Some(grant.voucher.clone()));
+        // the reserve is the voucher's own
+        assert!(payment.voucher_inputs.is_empty());
+
+        // the change of the first payment, derived from the voucher key, pays for the next one
+        let mut next = [0; 32];
+        next[0] = 0xab;
+        let next = XorName(next);
+        let map = BTreeMap::from([(next, (payee, PaymentQuote::test_dummy(next, 100.into())))]);
+        let _ = uploader.local_send_storage_payment(&map)?;
+        let payment = uploader
+            .get_cached_payment_for_xorname(&next)
+            .expect("There to be a payment.")
+            .to_payment();
+        assert!(!payment.voucher_inputs.is_empty());
+        for (input, index) in &payment.voucher_inputs {
+            assert_eq!(grant.voucher.voucher_key.new_unique_pubkey(index), *input);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn history_export_of_sends_and_receives() -> Result<()> {
+        let funder_dir = create_temp_dir();
+        let mut funder = LocalWallet::load_from(funder_dir.path())?;
+        let genesis =
+            create_first_cash_note_from_key(&funder.key).expect("Genesis creation to succeed.");
+        funder.deposit_and_store_to_disk(&vec![genesis])?;
+
+        let wallet_dir = create_temp_dir();
+        let mut wallet = LocalWallet::load_from(wallet_dir.path())?;
+        let recipient = MainSecretKey::random().main_pubkey();
+
+        // receive 1 token, send a quarter of it on, then receive 500 nanos
+        let first_deposit = funder.local_send(
+            vec![(NanoTokens::from(1_000_000_000), wallet.address())],
+            None,
+        )?;
+        wallet.deposit_and_store_to_disk(&first_deposit)?;
+        // depositing the same cash notes again is not recorded twice
+        wallet.deposit_and_store_to_disk(&first_deposit)?;
+        let _sent = wallet.local_send(vec![(NanoTokens::from(250_000_000), recipient)], None)?;
+        let second_deposit =
+            funder.local_send(vec![(NanoTokens::from(500), wallet.address())], None)?;
+        wallet.deposit_and_store_to_disk(&second_deposit)?;
+
+        let history = wallet.history()?;
+        assert_eq!(3, history.len());
+        let first_address = SpendAddress::from_unique_pubkey(&first_deposit[0].unique_pubkey());
+        let second_address = SpendAddress::from_unique_pubkey(&second_deposit[0].unique_pubkey());
+
+        let mut csv = vec![];
+        wallet.export_history(HistoryFormat::Csv, &mut csv)?;
+        let expected = format!(
+            "timestamp,kind,amount,balance,counterparty,reason,spend_addresses\n\
+            {},received,1.000000000,1.000000000,,,{}\n\
+            {},sent,0.250000000,0.750000000,{},,{}\n\
+            {},received,0.000000500,0.750000500,,,{}\n",
+            history[0].timestamp,
+            first_address.to_hex(),
+            history[1].timestamp,
+            recipient.to_hex(),
+            first_address.to_hex(),
+            history[2].timestamp,
+            second_address.to_hex(),
+        );
+        assert_eq!(String::from_utf8(csv)?, expected);
+
+        let mut json_lines = vec![];
+        wallet.export_history(HistoryFormat::JsonLines, &mut json_lines)?;
+        let rows: Vec<serde_json::Value> = String::from_utf8(json_lines)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(3, rows.len());
+        assert_eq!(rows[1]["kind"], "sent");
+        assert_eq!(rows[1]["amount"], "0.250000000");
+        assert_eq!(rows[2]["balance"], "0.750000500");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn a_corrupt_trailing_history_entry_is_skipped() -> Result<()> {
+        let funder_dir = create_temp_dir();
+        let mut funder = LocalWallet::load_from(funder_dir.path())?;
+        let genesis =
+            create_first_cash_note_from_key(&funder.key).expect("Genesis creation to succeed.");
+        funder.deposit_and_store_to_disk(&vec![genesis])?;
+
+        let wallet_dir = create_temp_dir();
+        let mut wallet = LocalWallet::load_from(wallet_dir.path())?;
+        let deposit = funder.local_send(
+            vec![(NanoTokens::from(1_000_000_000), wallet.address())],
+            None,
+        )?;
+        wallet.deposit_and_store_to_disk(&deposit)?;
+        assert_eq!(1, wallet.history()?.len());
+
+        // an entry only partially written, e.g. the process being killed while appending it
+        let entry = rmp_serde::to_vec(&wallet.history()?[0])?;
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(wallet.watchonly_wallet.wallet_dir().join(HISTORY_FILE_NAME))?;
+        file.write_all(&entry[..entry.len() / 2])?;
+
+        let history = wallet.history()?;
+        assert_eq!(1, history.len());
+        assert_eq!(history[0].amount, NanoTokens::from(1_000_000_000));
+
+        Ok(())
+    }
+
+    #[test]
+    fn readers_share_the_wallet_and_see_the_writes_once_done() -> Result<()> {
+        let dir = create_temp_dir();
+        let root_dir = dir.path().to_path_buf();
+        let mut writer = LocalWallet::load_from(&root_dir)?;
+        let cash_note =
+            create_first_cash_note_from_key(&writer.key).expect("Genesis creation to succeed.");
+
+        // readers don't wait on each other
+        let reader = LocalWallet::load_shared_from(&root_dir)?;
+        let shared_access = reader.lock_shared()?;
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let root_dir = root_dir.clone();
+                std::thread::spawn(move || {
+                    LocalWallet::try_load_shared_from(&root_dir).map(|wallet| wallet.balance())
+                })
+            })
+            .collect();
+        for reader in readers {
+            assert_eq!(
+                NanoTokens::zero(),
+                reader.join().expect("reader to not panic")?
+            );
+        }
+
+        // the writer waits for the readers to be done
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let deposit = std::thread::spawn(move || {
+            let result = writer.deposit_and_store_to_disk(&vec![cash_note]);
+            let _ = sender.send(());
+            result
+        });
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_millis(300))
+            .is_err());
+        drop(shared_access);
+        deposit.join().expect("writer to not panic")?;
+
+        let reader = LocalWallet::try_load_shared_from(&root_dir)?;
+        assert_eq!(GENESIS_CASHNOTE_AMOUNT, reader.balance().as_nano());
+
+        Ok(())
+    }
+
     fn create_temp_dir() -> TempDir {
         TempDir::new().expect("Should be able to create a temp dir.")
     }
@@ -0,0 +1,226 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    error::{Error, Result},
+    wallet_file::wallet_lockfile_name,
+};
+use fs2::FileExt;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::runtime::RuntimeFlavor;
+
+/// How long taking the lock of a wallet waits for other processes to release it, before giving up.
+pub const WALLET_LOCK_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often the lock is tried again while another process holds it.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A locked file handle, that when dropped releases the lock.
+pub type WalletExclusiveAccess = File;
+
+/// The shared lock of a wallet, held by its readers, that when dropped releases the lock.
+///
+/// Any number of processes can hold it at once, while the exclusive lock waits for all of them
+/// to release it.
+#[derive(Debug)]
+pub struct WalletSharedAccess {
+    file: File,
+    wallet_dir: PathBuf,
+}
+
+impl WalletSharedAccess {
+    /// Trade the shared lock for the exclusive one, to write to the wallet after reading it,
+    /// waiting for the other readers for at most `WALLET_LOCK_TIMEOUT`.
+    ///
+    /// The shared lock is released before the exclusive one is taken, so another writer may get
+    /// in between: the wallet is to be reloaded once upgraded.
+    pub fn upgrade(self) -> Result<WalletExclusiveAccess> {
+        let Self { file, wallet_dir } = self;
+        file.unlock()?;
+        drop(file);
+        lock_exclusive(&wallet_dir, WALLET_LOCK_TIMEOUT)
+    }
+}
+
+/// Take the exclusive lock of the wallet in `wallet_dir`, waiting for the readers and the other
+/// writer holding it for at most `timeout`.
+pub(super) fn lock_exclusive(
+    wallet_dir: &Path,
+    timeout: Duration,
+) -> Result<WalletExclusiveAccess> {
+    let lock = wallet_lockfile_name(wallet_dir);
+    // truncated so that its modification time tells when the lock was taken
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&lock)?;
+    acquire(&lock, timeout, || file.try_lock_exclusive())?;
+    Ok(file)
+}
+
+/// Take the shared lock of the wallet in `wallet_dir`, waiting for a writer holding the
+/// exclusive lock for at most `timeout`.
+pub(super) fn lock_shared(wallet_dir: &Path, timeout: Duration) -> Result<WalletSharedAccess> {
+    let lock = wallet_lockfile_name(wallet_dir);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock)?;
+    acquire(&lock, timeout, || file.try_lock_shared())?;
+    Ok(WalletSharedAccess {
+        file,
+        wallet_dir: wallet_dir.to_path_buf(),
+    })
+}
+
+/// Try to take a lock until it's taken, or `timeout` elapsed.
+fn acquire(lock: &Path, timeout: Duration, try_lock: impl Fn() -> io::Result<()>) -> Result<()> {
+    match try_lock() {
+        Ok(()) => return Ok(()),
+        Err(err) if is_contended(&err) => {
+            trace!("Wallet lock {lock:?} held by another process, waiting...");
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    wait_blocking(|| {
+        let start = Instant::now();
+        loop {
+            if start.elapsed() >= timeout {
+                warn!("Gave up on the wallet lock {lock:?} after {timeout:?}");
+                return Err(Error::WalletLockTimeout {
+                    path: lock.to_path_buf(),
+                    timeout,
+                });
+            }
+            std::thread::sleep(LOCK_RETRY_INTERVAL);
+            match try_lock() {
+                Ok(()) => return Ok(()),
+                Err(err) if is_contended(&err) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    })
+}
+
+/// Wait for a lock held by another process. The wallet being used from async code, when called
+/// from a worker of a multi-threaded tokio runtime the worker's other tasks are handed over to
+/// another thread for as long as it's blocked, rather than being stalled with it.
+fn wait_blocking<T>(wait: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) if runtime.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(wait)
+        }
+        _ => wait(),
+    }
+}
+
+pub(super) fn is_contended(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::WouldBlock
+        || err.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use std::{sync::mpsc, thread};
+
+    const SHORT_WAIT: Duration = Duration::from_millis(300);
+
+    #[test]
+    fn readers_do_not_block_each_other() -> eyre::Result<()> {
+        let dir = TempDir::new()?;
+        let _first = lock_shared(dir.path(), SHORT_WAIT)?;
+        let _second = lock_shared(dir.path(), SHORT_WAIT)?;
+
+        let wallet_dir = dir.path().to_path_buf();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let wallet_dir = wallet_dir.clone();
+                thread::spawn(move || lock_shared(&wallet_dir, SHORT_WAIT).map(|_| ()))
+            })
+            .collect();
+        for reader in readers {
+            reader.join().expect("reader to not panic")?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn the_writer_waits_for_the_readers() -> eyre::Result<()> {
+        let dir = TempDir::new()?;
+        let reader = lock_shared(dir.path(), SHORT_WAIT)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let wallet_dir = dir.path().to_path_buf();
+        let writer = thread::spawn(move || {
+            let result = lock_exclusive(&wallet_dir, WALLET_LOCK_TIMEOUT).map(|_| ());
+            let _ = sender.send(());
+            result
+        });
+
+        assert!(receiver.recv_timeout(SHORT_WAIT).is_err());
+        drop(reader);
+        receiver.recv_timeout(WALLET_LOCK_TIMEOUT)?;
+        writer.join().expect("writer to not panic")?;
+        Ok(())
+    }
+
+    #[test]
+    fn waiting_for_the_lock_times_out() -> eyre::Result<()> {
+        let dir = TempDir::new()?;
+        let _writer = lock_exclusive(dir.path(), SHORT_WAIT)?;
+
+        assert!(matches!(
+            lock_shared(dir.path(), SHORT_WAIT),
+            Err(Error::WalletLockTimeout { timeout, .. }) if timeout == SHORT_WAIT
+        ));
+        assert!(matches!(
+            lock_exclusive(dir.path(), SHORT_WAIT),
+            Err(Error::WalletLockTimeout { .. })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn waiting_for_the_lock_does_not_stall_the_runtime() -> eyre::Result<()> {
+        let dir = TempDir::new()?;
+        let reader = lock_shared(dir.path(), WALLET_LOCK_TIMEOUT)?;
+
+        // the only worker blocks on the lock, the task releasing it has to run elsewhere
+        let release = tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            drop(reader);
+        });
+        let _writer = lock_exclusive(dir.path(), WALLET_LOCK_TIMEOUT)?;
+        release.await?;
+        Ok(())
+    }
+
+    #[test]
+    fn shared_access_upgrades_once_the_other_readers_are_done() -> eyre::Result<()> {
+        let dir = TempDir::new()?;
+        let other_reader = lock_shared(dir.path(), SHORT_WAIT)?;
+        let reader = lock_shared(dir.path(), SHORT_WAIT)?;
+
+        let upgrade = thread::spawn(move || reader.upgrade().map(|_| ()));
+        thread::sleep(SHORT_WAIT);
+        assert!(!upgrade.is_finished());
+
+        drop(other_reader);
+        upgrade.join().expect("upgrade to not panic")?;
+        Ok(())
+    }
+}
@@ -52,10 +52,15 @@
 //! which eventually clears from the mempool and becomes spendable again.
 //!
 
+mod auto_split;
 mod data_payments;
 mod error;
+mod history;
+mod integrity;
 mod keys;
 mod local_store;
+mod lock;
+mod voucher;
 mod wallet_file;
 mod watch_only;
 
@@ -64,20 +69,29 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 pub use self::{
-    data_payments::{Payment, PaymentQuote},
+    auto_split::{AutoSplitPolicy, DEFAULT_AUTO_SPLIT_MAX_NOTES},
+    data_payments::{Payment, PaymentDetails, PaymentQuote},
     error::{Error, Result},
+    history::{HistoryEntry, HistoryFormat, HistoryKind, HISTORY_CSV_HEADER},
+    integrity::{WalletDirCheck, WalletLockState},
     keys::bls_secret_from_hex,
     local_store::LocalWallet,
+    lock::{WalletExclusiveAccess, WalletSharedAccess, WALLET_LOCK_TIMEOUT},
+    voucher::{StorageVoucher, StorageVoucherGrant},
     watch_only::WatchOnlyWallet,
 };
 pub(crate) use keys::store_new_keypair;
 
-use crate::{NanoTokens, UniquePubkey};
+use crate::{MainPubkey, NanoTokens, UniquePubkey};
 
 #[derive(Default, Serialize, Deserialize)]
 pub(super) struct KeyLessWallet {
     available_cash_notes: BTreeMap<UniquePubkey, NanoTokens>,
     payment_transactions: ContentPaymentsMap,
+    /// The main pubkey each available cash note was received by, once the wallet key was
+    /// rotated. Left out when empty, so that wallets never rotated serialize as they always did.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    cash_note_keys: BTreeMap<UniquePubkey, MainPubkey>,
 }
 
 impl KeyLessWallet {
@@ -0,0 +1,358 @@
+// Copyright 2023 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{keys::bls_secret_from_hex, Error, Result};
+use crate::{CashNote, Hash, MainPubkey, MainSecretKey, NanoTokens, Signature, UniquePubkey};
+
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+use xor_name::XorName;
+
+/// A funder's promise to pay for the storage of data uploaded by someone else.
+///
+/// The funder sends `max_amount` to a fresh voucher key, the reserve, and signs the voucher
+/// over it. Whoever holds the voucher key can pay for storage from the reserve, and the change
+/// of those payments, on the funder's behalf: the spends of those payments have the hash of the
+/// voucher as their reason, and the payments carry the voucher, so that nodes can check the
+/// funder's signature and the limits it set.
+///
+/// Nothing stops the holder of the voucher key from spending the reserve otherwise, but the
+/// funder never risks more than the reserve, and sees any such spend in its accounting.
+/// That accounting is done client side only, by the funder following the spends on the Network:
+/// nodes check each payment on its own, and keep no track of how much of a voucher was used.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize, custom_debug::Debug)]
+pub struct StorageVoucher {
+    /// The key of the funder's wallet, which signed the voucher
+    pub funder: MainPubkey,
+    /// The key the reserve was sent to, handed over to the uploader
+    pub voucher_key: MainPubkey,
+    /// The cash note holding the tokens reserved for the voucher
+    pub reserve: UniquePubkey,
+    /// The value of the reserve, which the payments made with the voucher can't exceed
+    pub max_amount: NanoTokens,
+    /// When the voucher stops being accepted by nodes
+    pub expiry: SystemTime,
+    /// The prefix the addresses of the data paid for must start with, if any
+    pub address_prefix: Option<Vec<u8>>,
+    /// The funder's signature of the other fields
+    #[debug(skip)]
+    pub signature: Signature,
+}
+
+impl StorageVoucher {
+    /// Create a voucher for the reserve sent to the voucher key, signed by the funder.
+    pub fn new(
+        funder: &MainSecretKey,
+        voucher_key: MainPubkey,
+        reserve: UniquePubkey,
+        max_amount: NanoTokens,
+        expiry: SystemTime,
+        address_prefix: Option<Vec<u8>>,
+    ) -> Result<Self> {
+        let bytes = Self::bytes_for_signing(
+            &funder.main_pubkey(),
+            &voucher_key,
+            &reserve,
+            max_amount,
+            expiry,
+            address_prefix.as_deref(),
+        )?;
+        Ok(Self {
+            funder: funder.main_pubkey(),
+            voucher_key,
+            reserve,
+            max_amount,
+            expiry,
+            address_prefix,
+            signature: funder.sign(&bytes),
+        })
+    }
+
+    /// Returns the bytes signed by the funder.
+    pub fn bytes_for_signing(
+        funder: &MainPubkey,
+        voucher_key: &MainPubkey,
+        reserve: &UniquePubkey,
+        max_amount: NanoTokens,
+        expiry: SystemTime,
+        address_prefix: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let expiry_secs = expiry
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|_| Error::InvalidStorageVoucherExpiry(expiry))?
+            .as_secs();
+
+        let mut bytes = funder.to_bytes().to_vec();
+        bytes.extend_from_slice(&voucher_key.to_bytes());
+        bytes.extend_from_slice(&reserve.to_bytes());
+        bytes.extend_from_slice(&max_amount.to_bytes());
+        bytes.extend_from_slice(&expiry_secs.to_le_bytes());
+        if let Some(prefix) = address_prefix {
+            bytes.push(1);
+            bytes.extend_from_slice(prefix);
+        } else {
+            bytes.push(0);
+        }
+        Ok(bytes)
+    }
+
+    /// The hash identifying the voucher, which the spends paying with it have as their reason.
+    ///
+    /// A voucher whose expiry can't be encoded never verifies, it's told apart by its signature.
+    pub fn hash(&self) -> Hash {
+        let mut bytes = Self::bytes_for_signing(
+            &self.funder,
+            &self.voucher_key,
+            &self.reserve,
+            self.max_amount,
+            self.expiry,
+            self.address_prefix.as_deref(),
+        )
+        .unwrap_or_default();
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        Hash::hash(&bytes)
+    }
+
+    /// Check the voucher was signed by its funder.
+    pub fn verify(&self) -> Result<()> {
+        let bytes = Self::bytes_for_signing(
+            &self.funder,
+            &self.voucher_key,
+            &self.reserve,
+            self.max_amount,
+            self.expiry,
+            self.address_prefix.as_deref(),
+        )?;
+        if !self.funder.verify(&self.signature, &bytes) {
+            return Err(Error::InvalidStorageVoucherSignature);
+        }
+        Ok(())
+    }
+
+    /// Whether the voucher expired at the given time.
+    pub fn is_expired_at(&self, time: SystemTime) -> bool {
+        time >= self.expiry
+    }
+
+    /// Whether the voucher pays for data at the given address.
+    pub fn allows(&self, address: &XorName) -> bool {
+        match &self.address_prefix {
+            Some(prefix) => address.0.starts_with(prefix),
+            None => true,
+        }
+    }
+
+    /// Check the voucher, signed by its funder, can pay at the given time for data at the given
+    /// address.
+    pub fn check_payment_for(&self, address: &XorName, time: SystemTime) -> Result<()> {
+        self.verify()?;
+        if self.is_expired_at(time) {
+            return Err(Error::StorageVoucherExpired(self.expiry));
+        }
+        if !self.allows(address) {
+            return Err(Error::StorageVoucherAddressNotAllowed(*address));
+        }
+        Ok(())
+    }
+}
+
+/// What a funder hands over to an uploader for them to pay with a voucher: the voucher, its
+/// key and the reserve.
+///
+/// The funder's own key is never part of it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StorageVoucherGrant {
+    /// The voucher, signed by the funder
+    pub voucher: StorageVoucher,
+    /// The hex encoded secret of the voucher key
+    voucher_secret: String,
+    /// The cash note holding the tokens reserved for the voucher
+    pub reserve: CashNote,
+}
+
+impl StorageVoucherGrant {
+    /// Bundle the voucher with its key and reserve.
+    pub fn new(voucher: StorageVoucher, voucher_key: &MainSecretKey, reserve: CashNote) -> Self {
+        Self {
+            voucher,
+            voucher_secret: hex::encode(voucher_key.to_bytes()),
+            reserve,
+        }
+    }
+
+    /// The key the reserve was sent to.
+    pub fn voucher_key(&self) -> Result<MainSecretKey> {
+        Ok(MainSecretKey::new(bls_secret_from_hex(
+            &self.voucher_secret,
+        )?))
+    }
+
+    /// Check the grant holds the key and reserve of its voucher, the voucher being signed by
+    /// its funder.
+    pub fn verify(&self) -> Result<()> {
+        self.voucher.verify()?;
+        if self.voucher_key()?.main_pubkey() != self.voucher.voucher_key {
+            return Err(Error::InvalidStorageVoucherGrant(
+                "the key is not the voucher's".to_string(),
+            ));
+        }
+        if self.reserve.unique_pubkey() != self.voucher.reserve
+            || self.reserve.main_pubkey() != &self.voucher.voucher_key
+        {
+            return Err(Error::InvalidStorageVoucherGrant(
+                "the reserve is not the voucher's".to_string(),
+            ));
+        }
+        if self.reserve.value()? != self.voucher.max_amount {
+            return Err(Error::InvalidStorageVoucherGrant(
+                "the reserve doesn't hold the voucher's max amount".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Serialize the grant to a hex string, to be handed over to the uploader.
+    pub fn to_hex(&self) -> Result<String> {
+        Ok(hex::encode(rmp_serde::to_vec(self)?))
+    }
+
+    /// Deserialize a grant represented as a hex string, as given by `to_hex`.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex.trim())
+            .map_err(|err| Error::InvalidStorageVoucherGrant(err.to_string()))?;
+        Ok(rmp_serde::from_slice(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_first_cash_note_from_key, DerivationIndex};
+    use std::time::Duration;
+
+    fn voucher(funder: &MainSecretKey, address_prefix: Option<Vec<u8>>) -> StorageVoucher {
+        let voucher_key = MainSecretKey::random().main_pubkey();
+        let reserve =
+            voucher_key.new_unique_pubkey(&DerivationIndex::random(&mut rand::thread_rng()));
+        StorageVoucher::new(
+            funder,
+            voucher_key,
+            reserve,
+            NanoTokens::from(1_000),
+            SystemTime::now() + Duration::from_secs(3600),
+            address_prefix,
+        )
+        .expect("voucher to be signed")
+    }
+
+    #[test]
+    fn vouchers_signed_by_their_funder_verify() -> Result<()> {
+        let funder = MainSecretKey::random();
+        let voucher = voucher(&funder, None);
+        voucher.verify()?;
+
+        let mut raised = voucher.clone();
+        raised.max_amount = NanoTokens::from(1_000_000);
+        assert!(matches!(
+            raised.verify(),
+            Err(Error::InvalidStorageVoucherSignature)
+        ));
+
+        let mut impersonated = voucher;
+        impersonated.funder = MainSecretKey::random().main_pubkey();
+        assert!(matches!(
+            impersonated.verify(),
+            Err(Error::InvalidStorageVoucherSignature)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn vouchers_pay_for_allowed_addresses_until_they_expire() -> Result<()> {
+        let funder = MainSecretKey::random();
+        let voucher = voucher(&funder, Some(vec![0xab]));
+        let allowed = XorName([0xab; 32]);
+        let other = XorName([0xcd; 32]);
+        let now = SystemTime::now();
+
+        voucher.check_payment_for(&allowed, now)?;
+        assert!(matches!(
+            voucher.check_payment_for(&other, now),
+            Err(Error::StorageVoucherAddressNotAllowed(address)) if address == other
+        ));
+        assert!(matches!(
+            voucher.check_payment_for(&allowed, voucher.expiry),
+            Err(Error::StorageVoucherExpired(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn the_prefix_is_part_of_the_signed_voucher() {
+        let funder = MainSecretKey::random();
+        let mut voucher = voucher(&funder, Some(vec![0xab]));
+        let hash = voucher.hash();
+
+        voucher.address_prefix = None;
+        assert!(voucher.verify().is_err());
+        assert_ne!(voucher.hash(), hash);
+    }
+
+    #[test]
+    fn vouchers_expiring_before_the_epoch_are_refused() {
+        let funder = MainSecretKey::random();
+        let voucher_key = MainSecretKey::random().main_pubkey();
+        let reserve =
+            voucher_key.new_unique_pubkey(&DerivationIndex::random(&mut rand::thread_rng()));
+        let expiry = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+
+        assert!(matches!(
+            StorageVoucher::new(&funder, voucher_key, reserve, NanoTokens::from(1), expiry, None),
+            Err(Error::InvalidStorageVoucherExpiry(time)) if time == expiry
+        ));
+
+        let mut voucher = voucher(&funder, None);
+        voucher.expiry = expiry;
+        assert!(matches!(
+            voucher.verify(),
+            Err(Error::InvalidStorageVoucherExpiry(_))
+        ));
+    }
+
+    #[test]
+    fn grants_round_trip_and_hold_their_voucher_key_and_reserve() -> Result<()> {
+        let funder = MainSecretKey::random();
+        let voucher_key = MainSecretKey::random();
+        let reserve = create_first_cash_note_from_key(&voucher_key).expect("reserve to be created");
+        let voucher = StorageVoucher::new(
+            &funder,
+            voucher_key.main_pubkey(),
+            reserve.unique_pubkey(),
+            reserve.value()?,
+            SystemTime::now() + Duration::from_secs(3600),
+            None,
+        )?;
+
+        let grant = StorageVoucherGrant::new(voucher.clone(), &voucher_key, reserve);
+        let grant = StorageVoucherGrant::from_hex(&grant.to_hex()?)?;
+        grant.verify()?;
+        assert_eq!(grant.voucher, voucher);
+        assert_eq!(
+            grant.voucher_key()?.main_pubkey(),
+            voucher_key.main_pubkey()
+        );
+
+        let other_key = MainSecretKey::random();
+        let wrong_key = StorageVoucherGrant::new(voucher, &other_key, grant.reserve.clone());
+        assert!(matches!(
+            wrong_key.verify(),
+            Err(Error::InvalidStorageVoucherGrant(_))
+        ));
+        Ok(())
+    }
+}
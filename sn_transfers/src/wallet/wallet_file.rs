@@ -8,7 +8,8 @@
 
 use super::{
     error::{Error, Result},
-    KeyLessWallet,
+    history::HistoryEntry,
+    AutoSplitPolicy, KeyLessWallet, StorageVoucher,
 };
 use crate::{CashNote, SignedSpend, SpendAddress, UniquePubkey};
 use serde::Serialize;
@@ -19,10 +20,14 @@ use std::{
 };
 
 // Filename for storing a wallet.
-const WALLET_FILE_NAME: &str = "wallet";
+pub(super) const WALLET_FILE_NAME: &str = "wallet";
 const WALLET_LOCK_FILE_NAME: &str = "wallet.lock";
 const CASHNOTES_DIR_NAME: &str = "cash_notes";
-const UNCONFRIMED_TX_NAME: &str = "unconfirmed_spend_requests";
+pub(super) const UNCONFRIMED_TX_NAME: &str = "unconfirmed_spend_requests";
+pub(super) const AUTO_SPLIT_POLICY_FILE_NAME: &str = "auto_split_policy";
+pub(super) const HISTORY_FILE_NAME: &str = "history";
+pub(super) const STORAGE_VOUCHER_FILE_NAME: &str = "storage_voucher";
+pub(super) const ISSUED_STORAGE_VOUCHERS_FILE_NAME: &str = "issued_storage_vouchers";
 
 /// Writes the `KeyLessWallet` to the specified path.
 pub(super) fn store_wallet(wallet_dir: &Path, wallet: &KeyLessWallet) -> Result<()> {
@@ -43,6 +48,11 @@ pub(super) fn wallet_lockfile_name(wallet_dir: &Path) -> PathBuf {
     wallet_dir.join(WALLET_LOCK_FILE_NAME)
 }
 
+/// Returns the dir the cash notes are stored in
+pub(super) fn cash_notes_dir(wallet_dir: &Path) -> PathBuf {
+    wallet_dir.join(CASHNOTES_DIR_NAME)
+}
+
 /// Returns `Some(KeyLessWallet)` or None if file doesn't exist.
 /// If the file is being written to, it will wait until the write is complete before reading.
 pub(super) fn get_wallet(wallet_dir: &Path) -> Result<Option<KeyLessWallet>> {
@@ -115,6 +125,137 @@ pub(super) fn get_unconfirmed_spend_requests(
     Ok(Some(unconfirmed_spend_requests))
 }
 
+/// Writes the `AutoSplitPolicy` to the wallet dir, or removes it if `None`.
+pub(super) fn store_auto_split_policy(
+    wallet_dir: &Path,
+    policy: Option<&AutoSplitPolicy>,
+) -> Result<()> {
+    let path = wallet_dir.join(AUTO_SPLIT_POLICY_FILE_NAME);
+    match policy {
+        Some(policy) => {
+            let mut file = fs::File::create(path)?;
+            let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+            policy.serialize(&mut serialiser)?;
+        }
+        None => {
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns `Some(AutoSplitPolicy)` or None if file doesn't exist.
+pub(super) fn get_auto_split_policy(wallet_dir: &Path) -> Result<Option<AutoSplitPolicy>> {
+    let path = wallet_dir.join(AUTO_SPLIT_POLICY_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let file = fs::File::open(&path)?;
+    let policy = rmp_serde::from_read(&file)?;
+
+    Ok(Some(policy))
+}
+
+/// Writes the `StorageVoucher` the wallet pays with to the wallet dir.
+pub(super) fn store_storage_voucher(wallet_dir: &Path, voucher: &StorageVoucher) -> Result<()> {
+    let path = wallet_dir.join(STORAGE_VOUCHER_FILE_NAME);
+    let mut file = fs::File::create(path)?;
+    let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+    voucher.serialize(&mut serialiser)?;
+    Ok(())
+}
+
+/// Returns `Some(StorageVoucher)` or None if file doesn't exist.
+pub(super) fn get_storage_voucher(wallet_dir: &Path) -> Result<Option<StorageVoucher>> {
+    let path = wallet_dir.join(STORAGE_VOUCHER_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let file = fs::File::open(&path)?;
+    let voucher = rmp_serde::from_read(&file)?;
+
+    Ok(Some(voucher))
+}
+
+/// Appends the given voucher to the vouchers issued by the wallet, in the wallet dir.
+pub(super) fn append_to_issued_storage_vouchers(
+    wallet_dir: &Path,
+    voucher: &StorageVoucher,
+) -> Result<()> {
+    let path = wallet_dir.join(ISSUED_STORAGE_VOUCHERS_FILE_NAME);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+    voucher.serialize(&mut serialiser)?;
+    Ok(())
+}
+
+/// Returns the vouchers issued by the wallet, oldest first.
+pub(super) fn get_issued_storage_vouchers(wallet_dir: &Path) -> Result<Vec<StorageVoucher>> {
+    let path = wallet_dir.join(ISSUED_STORAGE_VOUCHERS_FILE_NAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let data = fs::read(&path)?;
+    let mut cursor = std::io::Cursor::new(&data);
+    let mut vouchers = vec![];
+    while (cursor.position() as usize) < data.len() {
+        vouchers.push(rmp_serde::from_read(&mut cursor)?);
+    }
+
+    Ok(vouchers)
+}
+
+/// Appends the given entries to the wallet history in the wallet dir.
+/// Entries are appended one after the other, so the file is never rewritten.
+pub(super) fn append_to_history(wallet_dir: &Path, entries: &[HistoryEntry]) -> Result<()> {
+    let path = wallet_dir.join(HISTORY_FILE_NAME);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut serialiser = rmp_serde::encode::Serializer::new(&mut file);
+    for entry in entries {
+        entry.serialize(&mut serialiser)?;
+    }
+    Ok(())
+}
+
+/// Returns the wallet history, empty if the file doesn't exist.
+/// An entry that can't be read, e.g. one only partially appended, ends the history there.
+pub(super) fn get_history(wallet_dir: &Path) -> Result<Vec<HistoryEntry>> {
+    let path = wallet_dir.join(HISTORY_FILE_NAME);
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+
+    let data = fs::read(&path)?;
+    let mut cursor = std::io::Cursor::new(&data);
+    let mut history = vec![];
+    while (cursor.position() as usize) < data.len() {
+        let position = cursor.position();
+        match rmp_serde::from_read(&mut cursor) {
+            Ok(entry) => history.push(entry),
+            Err(err) => {
+                warn!(
+                    "Skipping the wallet history from byte {position} of {}, it can't be read: {err}",
+                    data.len()
+                );
+                break;
+            }
+        }
+    }
+
+    Ok(history)
+}
+
 /// Hex encode and write each `CashNote` to a separate file in respective
 /// recipient public address dir in the created cash_notes dir. Each file is named after the cash_note id.
 pub(super) fn store_created_cash_notes<'a, T>(
@@ -9,17 +9,17 @@
 use super::{
     data_payments::PaymentDetails,
     error::{Error, Result},
-    keys::{get_main_pubkey, store_new_pubkey},
-    local_store::WalletExclusiveAccess,
-    wallet_file::{get_wallet, store_created_cash_notes, store_wallet, wallet_lockfile_name},
+    keys::{get_main_pubkey, get_retired_pubkeys, store_new_pubkey},
+    lock::{
+        lock_exclusive, lock_shared, WalletExclusiveAccess, WalletSharedAccess, WALLET_LOCK_TIMEOUT,
+    },
+    wallet_file::{get_wallet, store_created_cash_notes, store_wallet},
     KeyLessWallet,
 };
 
 use crate::{CashNote, MainPubkey, NanoTokens, UniquePubkey};
-use fs2::FileExt;
 use std::{
     collections::BTreeMap,
-    fs::OpenOptions,
     path::{Path, PathBuf},
 };
 use xor_name::XorName;
@@ -29,6 +29,9 @@ use xor_name::XorName;
 pub struct WatchOnlyWallet {
     /// Main public key which owns the cash notes.
     main_pubkey: MainPubkey,
+    /// Main public keys retired by key rotations, oldest first, which still receive cash notes.
+    #[serde(default)]
+    retired_pubkeys: Vec<MainPubkey>,
     /// The dir of the wallet file, main key, public address, and new cash_notes.
     wallet_dir: PathBuf,
     /// The wallet containing all data, cash notes & transactions data that gets serialised and stored on disk.
@@ -45,6 +48,7 @@ impl WatchOnlyWallet {
     ) -> Self {
         Self {
             main_pubkey,
+            retired_pubkeys: vec![],
             wallet_dir: wallet_dir.to_path_buf(),
             keyless_wallet,
         }
@@ -79,8 +83,11 @@ impl WatchOnlyWallet {
             }
         };
 
+        let retired_pubkeys = get_retired_pubkeys(wallet_dir)?;
+
         Ok(Self {
             main_pubkey,
+            retired_pubkeys,
             wallet_dir: wallet_dir.to_path_buf(),
             keyless_wallet,
         })
@@ -90,6 +97,30 @@ impl WatchOnlyWallet {
         self.main_pubkey
     }
 
+    /// The addresses retired by key rotations, oldest first, which still receive cash notes.
+    pub fn retired_addresses(&self) -> &[MainPubkey] {
+        &self.retired_pubkeys
+    }
+
+    /// The address an available cash note was received by, the current one or a retired one.
+    pub fn cash_note_key(&self, unique_pubkey: &UniquePubkey) -> Option<MainPubkey> {
+        if !self
+            .keyless_wallet
+            .available_cash_notes
+            .contains_key(unique_pubkey)
+        {
+            return None;
+        }
+        // notes received before the first rotation are untagged
+        Some(
+            self.keyless_wallet
+                .cash_note_keys
+                .get(unique_pubkey)
+                .copied()
+                .unwrap_or(self.main_pubkey),
+        )
+    }
+
     pub fn balance(&self) -> NanoTokens {
         self.keyless_wallet.balance()
     }
@@ -106,13 +137,13 @@ impl WatchOnlyWallet {
         for cash_note in received_cash_notes {
             let id = cash_note.unique_pubkey();
 
-            if cash_note.derived_pubkey(&self.main_pubkey).is_err() {
+            let Some(key) = self.receiving_key(cash_note) else {
                 debug!("skipping: cash_note is not our key");
                 continue;
-            }
+            };
 
             let value = cash_note.value()?;
-            self.keyless_wallet.available_cash_notes.insert(id, value);
+            self.insert_cash_note(id, value, key);
         }
 
         Ok(())
@@ -136,13 +167,13 @@ impl WatchOnlyWallet {
         for cash_note in received_cash_notes {
             let id = cash_note.unique_pubkey();
 
-            if cash_note.derived_pubkey(&self.main_pubkey).is_err() {
+            let Some(key) = self.receiving_key(cash_note) else {
                 debug!("skipping: cash_note is not our key");
                 continue;
-            }
+            };
 
             let value = cash_note.value()?;
-            self.keyless_wallet.available_cash_notes.insert(id, value);
+            self.insert_cash_note(id, value, key);
 
             store_created_cash_notes([cash_note], &self.wallet_dir)?;
         }
@@ -177,7 +208,23 @@ impl WatchOnlyWallet {
     {
         for k in unique_pubkeys {
             self.keyless_wallet.available_cash_notes.remove(k);
+            self.keyless_wallet.cash_note_keys.remove(k);
+        }
+    }
+
+    /// Make `new_main_pubkey` the address of the wallet, keeping the current one to receive
+    /// cash notes still. The available cash notes are tagged with the address they were
+    /// received by.
+    pub(super) fn rotate_main_pubkey(&mut self, new_main_pubkey: MainPubkey) {
+        let retired = std::mem::replace(&mut self.main_pubkey, new_main_pubkey);
+        for id in self.keyless_wallet.available_cash_notes.keys() {
+            let _ = self
+                .keyless_wallet
+                .cash_note_keys
+                .entry(*id)
+                .or_insert(retired);
         }
+        self.retired_pubkeys.push(retired);
     }
 
     /// Return a payment transaction detail
@@ -185,6 +232,11 @@ impl WatchOnlyWallet {
         self.keyless_wallet.payment_transactions.get(name)
     }
 
+    /// All the payment transactions, by the content they were made for
+    pub fn payment_transactions(&self) -> impl Iterator<Item = (&XorName, &PaymentDetails)> {
+        self.keyless_wallet.payment_transactions.iter()
+    }
+
     /// Insert a payment transaction
     pub fn insert_payment_transaction(&mut self, name: XorName, payment: PaymentDetails) {
         self.keyless_wallet
@@ -192,8 +244,30 @@ impl WatchOnlyWallet {
             .insert(name, payment);
     }
 
+    /// Remove a payment transaction, returning it if there was one
+    pub fn remove_payment_transaction(&mut self, name: &XorName) -> Option<PaymentDetails> {
+        self.keyless_wallet.payment_transactions.remove(name)
+    }
+
     // Helpers
 
+    /// The key of ours the cash note was sent to, the current one or a retired one.
+    fn receiving_key(&self, cash_note: &CashNote) -> Option<MainPubkey> {
+        std::iter::once(&self.main_pubkey)
+            .chain(self.retired_pubkeys.iter())
+            .find(|key| cash_note.derived_pubkey(key).is_ok())
+            .copied()
+    }
+
+    /// Add a cash note to the available ones, tagged with the key it was received by once
+    /// the wallet key was rotated.
+    fn insert_cash_note(&mut self, id: UniquePubkey, value: NanoTokens, key: MainPubkey) {
+        self.keyless_wallet.available_cash_notes.insert(id, value);
+        if !self.retired_pubkeys.is_empty() {
+            self.keyless_wallet.cash_note_keys.insert(id, key);
+        }
+    }
+
     // Stores the wallet to disk.
     // This requires having exclusive access to the wallet to prevent concurrent processes from writing to it
     pub(super) fn store(&self, exclusive_access: WalletExclusiveAccess) -> Result<()> {
@@ -206,14 +280,13 @@ impl WatchOnlyWallet {
     // Locks the wallet and returns exclusive access to the wallet
     // This lock prevents any other process from locking the wallet dir, effectively acts as a mutex for the wallet
     pub(super) fn lock(&self) -> Result<WalletExclusiveAccess> {
-        let lock = wallet_lockfile_name(&self.wallet_dir);
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(lock)?;
-        file.lock_exclusive()?;
-        Ok(file)
+        lock_exclusive(&self.wallet_dir, WALLET_LOCK_TIMEOUT)
+    }
+
+    // Locks the wallet for reading and returns shared access to the wallet
+    // Other readers can hold it at the same time, writers wait for them all to release it
+    pub(super) fn lock_shared(&self) -> Result<WalletSharedAccess> {
+        lock_shared(&self.wallet_dir, WALLET_LOCK_TIMEOUT)
     }
 }
 